@@ -0,0 +1,12 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+fn main() {
+    // Node.js addons resolve the N-API symbols when they are loaded, which needs extra linker
+    // flags on some platforms.
+    napi_build::setup();
+}
@@ -0,0 +1,245 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Node.js bindings for tree-sitter-graph, which are built into the `tree-sitter-graph` package
+//! with the [napi-rs][] command-line tool (see `package.json`).
+//!
+//! ``` js
+//! const Python = require("tree-sitter-python");
+//! const { File } = require("tree-sitter-graph");
+//!
+//! const file = new File(Python, tsg);
+//! const graph = file.execute(source, { globals: { filename: "test.py" } });
+//! for (let node = 0; node < graph.nodeCount(); node++) {
+//!   console.log(node, graph.attributes(node), graph.successors(node));
+//! }
+//! console.log(JSON.stringify(graph));
+//! ```
+//!
+//! Graph nodes are identified by their index.  Attribute values are in the same JSON format as
+//! `--format json` on the command line.
+//!
+//! [napi-rs]: https://napi.rs/
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use napi::Env;
+use napi::Error;
+use napi::JsObject;
+use napi::JsUnknown;
+use napi::NapiRaw;
+use napi::Result;
+use napi_derive::napi;
+use serde_json::Value;
+use tree_sitter::Language;
+use tree_sitter::Parser;
+use tree_sitter::Tree;
+
+use tree_sitter_graph::ast;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph;
+use tree_sitter_graph::parse_error::ParseError;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::Identifier;
+use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::Variables;
+
+/// The path that errors in TSG files are reported at.
+const TSG_PATH: &str = "<tsg>";
+/// The path that errors in source files are reported at.
+const SOURCE_PATH: &str = "<source>";
+
+/// Returns the language of a grammar package, such as `require("tree-sitter-python")`, whose
+/// `language` property is an external value that wraps the language.
+fn language(env: &Env, grammar: &JsObject) -> Result<Language> {
+    let external: JsUnknown = grammar.get_named_property("language")?;
+    let mut pointer = std::ptr::null_mut();
+    let status =
+        unsafe { napi::sys::napi_get_value_external(env.raw(), external.raw(), &mut pointer) };
+    if status != napi::sys::Status::napi_ok || pointer.is_null() {
+        return Err(Error::from_reason("Invalid language"));
+    }
+    Ok(unsafe { Language::from_raw(pointer as *const tree_sitter::ffi::TSLanguage) })
+}
+
+fn parse_tsg(language: Language, tsg: &str) -> Result<ast::File> {
+    ast::File::from_str(language, tsg)
+        .map_err(|err| Error::from_reason(err.display_pretty(Path::new(TSG_PATH), tsg).to_string()))
+}
+
+fn parse_source(language: &Language, source: &str) -> Result<Tree> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(language)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+    parser
+        .parse(source, None)
+        .ok_or_else(|| Error::from_reason("Cannot parse source code"))
+}
+
+/// Parses source code, returning its syntax tree as an S-expression.  The syntax tree is returned
+/// even if the source code contains syntax errors.
+#[napi]
+pub fn parse(env: Env, grammar: JsObject, source: String) -> Result<String> {
+    let tree = parse_source(&language(&env, &grammar)?, &source)?;
+    Ok(tree.root_node().to_sexp())
+}
+
+/// Parses and checks a graph DSL file, throwing an error if it is invalid.
+#[napi]
+pub fn check(env: Env, grammar: JsObject, tsg: String) -> Result<()> {
+    parse_tsg(language(&env, &grammar)?, &tsg)?;
+    Ok(())
+}
+
+/// A parsed and checked graph DSL file.
+#[napi]
+pub struct File {
+    file: ast::File,
+    tsg: String,
+}
+
+/// Options for executing a graph DSL file.
+#[napi(object)]
+pub struct ExecuteOptions {
+    /// Whether to execute lazily
+    pub lazy: Option<bool>,
+    /// Whether to execute against source code that contains syntax errors
+    pub allow_parse_errors: Option<bool>,
+    /// The global variables, which are all strings
+    pub globals: Option<HashMap<String, String>>,
+}
+
+#[napi]
+impl File {
+    /// Parses and checks a graph DSL file for the language of a grammar package, throwing an
+    /// error if it is invalid.
+    #[napi(constructor)]
+    pub fn new(env: Env, grammar: JsObject, tsg: String) -> Result<File> {
+        let file = parse_tsg(language(&env, &grammar)?, &tsg)?;
+        Ok(File { file, tsg })
+    }
+
+    /// Executes this file against source code with the standard library of functions, returning
+    /// the graph.
+    #[napi]
+    pub fn execute(&self, source: String, options: Option<ExecuteOptions>) -> Result<Graph> {
+        let options = options.unwrap_or(ExecuteOptions {
+            lazy: None,
+            allow_parse_errors: None,
+            globals: None,
+        });
+        let tree = parse_source(&self.file.language, &source)?;
+        if !options.allow_parse_errors.unwrap_or(false) {
+            if let Some(err) = ParseError::first(&tree) {
+                return Err(Error::from_reason(
+                    err.display_pretty(Path::new(SOURCE_PATH), &source)
+                        .to_string(),
+                ));
+            }
+        }
+        let functions = Functions::stdlib();
+        let mut globals = Variables::new();
+        for (name, value) in options.globals.into_iter().flatten() {
+            globals
+                .add(Identifier::from(name.as_str()), value.into())
+                .map_err(|err| Error::from_reason(err.to_string()))?;
+        }
+        let config = ExecutionConfig::new(&functions, &globals).lazy(options.lazy.unwrap_or(false));
+        let graph = self
+            .file
+            .execute(&tree, &source, &config, &NoCancellation)
+            .map_err(|err| {
+                Error::from_reason(
+                    err.display_pretty(
+                        Path::new(SOURCE_PATH),
+                        &source,
+                        Path::new(TSG_PATH),
+                        &self.tsg,
+                    )
+                    .to_string(),
+                )
+            })?;
+        Graph::new(&graph)
+    }
+}
+
+/// The graph that executing a graph DSL file produces.  Unlike the graph of the Rust library, it
+/// does not refer to the syntax tree, so it can be kept after execution.
+#[napi]
+pub struct Graph {
+    /// The graph nodes, in the JSON format of `--format json`
+    nodes: Vec<Value>,
+}
+
+impl Graph {
+    fn new(graph: &graph::Graph) -> Result<Graph> {
+        let nodes = match serde_json::to_value(graph) {
+            Ok(Value::Array(nodes)) => nodes,
+            Ok(_) => unreachable!("graphs are serialized as arrays"),
+            Err(err) => return Err(Error::from_reason(err.to_string())),
+        };
+        Ok(Graph { nodes })
+    }
+
+    fn node(&self, node: u32) -> Result<&Value> {
+        self.nodes
+            .get(node as usize)
+            .ok_or_else(|| Error::from_reason(format!("No graph node {}", node)))
+    }
+}
+
+#[napi]
+impl Graph {
+    /// Returns the number of graph nodes.
+    #[napi]
+    pub fn node_count(&self) -> u32 {
+        self.nodes.len() as u32
+    }
+
+    /// Returns the number of edges.
+    #[napi]
+    pub fn edge_count(&self) -> u32 {
+        self.nodes
+            .iter()
+            .map(|node| node["edges"].as_array().map_or(0, Vec::len))
+            .sum::<usize>() as u32
+    }
+
+    /// Returns the attributes of a graph node.
+    #[napi]
+    pub fn attributes(&self, node: u32) -> Result<Value> {
+        Ok(self.node(node)?["attrs"].clone())
+    }
+
+    /// Returns the outgoing edges of a graph node, as objects with the `sink` node and the
+    /// `attrs` of the edge.
+    #[napi]
+    pub fn edges(&self, node: u32) -> Result<Value> {
+        Ok(self.node(node)?["edges"].clone())
+    }
+
+    /// Returns the sink nodes of the outgoing edges of a graph node.
+    #[napi]
+    pub fn successors(&self, node: u32) -> Result<Vec<u32>> {
+        Ok(self.node(node)?["edges"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|edge| edge["sink"].as_u64())
+            .map(|sink| sink as u32)
+            .collect())
+    }
+
+    /// Returns the graph in the JSON format of `--format json`, which lets `JSON.stringify`
+    /// serialize it.
+    #[napi(js_name = "toJSON")]
+    pub fn to_json(&self) -> Value {
+        Value::Array(self.nodes.clone())
+    }
+}
@@ -0,0 +1,571 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Python bindings for tree-sitter-graph, which are built into the `tree_sitter_graph` Python
+//! package with [maturin][] (see `pyproject.toml`).
+//!
+//! ``` python
+//! import tree_sitter_python
+//! from tree_sitter_graph import File, Functions, Variables
+//!
+//! functions = Functions.stdlib()
+//! functions.add("shout", lambda text: text.upper() + "!")
+//! globals = Variables()
+//! globals.add("filename", "test.py")
+//!
+//! file = File(tree_sitter_python.language(), tsg)
+//! graph = file.execute(source, functions=functions, globals=globals)
+//! for node in graph.nodes():
+//!     print(node, graph.attributes(node))
+//! ```
+//!
+//! Graph values are converted to and from Python values as follows: null is `None`, Booleans,
+//! integers, and strings are `bool`, `int`, and `str`, lists are `list` (or `tuple`, from Python),
+//! sets are `frozenset` (or `set`, from Python), and references to syntax nodes and graph nodes
+//! are [`SyntaxNode`][] and [`GraphNode`][] instances.
+//!
+//! [maturin]: https://www.maturin.rs/
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::os::raw::c_void;
+use std::path::Path;
+
+use pyo3::exceptions::PyIndexError;
+use pyo3::exceptions::PyTypeError;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBool;
+use pyo3::types::PyCapsule;
+use pyo3::types::PyDict;
+use pyo3::types::PyFrozenSet;
+use pyo3::types::PyList;
+use pyo3::types::PyLong;
+use pyo3::types::PySet;
+use pyo3::types::PyString;
+use pyo3::types::PyTuple;
+use tree_sitter::Language;
+use tree_sitter::Parser;
+
+use tree_sitter_graph::ast;
+use tree_sitter_graph::functions;
+use tree_sitter_graph::functions::Parameters;
+use tree_sitter_graph::graph;
+use tree_sitter_graph::graph::Attributes;
+use tree_sitter_graph::graph::GraphNodeRef;
+use tree_sitter_graph::graph::SyntaxNodeRef;
+use tree_sitter_graph::graph::Value;
+use tree_sitter_graph::parse_error::ParseError;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::ExecutionError;
+use tree_sitter_graph::Identifier;
+use tree_sitter_graph::NoCancellation;
+
+/// The path that errors in TSG files are reported at.
+const TSG_PATH: &str = "<tsg>";
+/// The path that errors in source files are reported at.
+const SOURCE_PATH: &str = "<source>";
+
+#[pymodule]
+#[pyo3(name = "tree_sitter_graph")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<File>()?;
+    m.add_class::<Functions>()?;
+    m.add_class::<Graph>()?;
+    m.add_class::<GraphNode>()?;
+    m.add_class::<SyntaxNode>()?;
+    m.add_class::<Variables>()?;
+    Ok(())
+}
+
+/// Returns a language from the Python package of its grammar, such as the result of
+/// `tree_sitter_python.language()`, which is either a capsule or an address.
+fn language(language: &Bound<'_, PyAny>) -> PyResult<Language> {
+    let pointer = match language.downcast::<PyCapsule>() {
+        Ok(capsule) => capsule.pointer(),
+        Err(_) => language.extract::<usize>()? as *mut c_void,
+    };
+    if pointer.is_null() {
+        return Err(PyValueError::new_err("Invalid language"));
+    }
+    Ok(unsafe { Language::from_raw(pointer as *const tree_sitter::ffi::TSLanguage) })
+}
+
+// ------------------------------------------------------------------------------------------------
+// Files
+
+/// A parsed and checked graph DSL file.
+#[pyclass(module = "tree_sitter_graph")]
+pub struct File {
+    file: ast::File,
+    tsg: String,
+}
+
+#[pymethods]
+impl File {
+    /// Parses and checks a graph DSL file for a language.  Raises `ValueError` if the file is
+    /// invalid.
+    #[new]
+    fn new(language: &Bound<'_, PyAny>, tsg: &str) -> PyResult<File> {
+        let file = ast::File::from_str(self::language(language)?, tsg).map_err(|err| {
+            PyValueError::new_err(err.display_pretty(Path::new(TSG_PATH), tsg).to_string())
+        })?;
+        Ok(File {
+            file,
+            tsg: tsg.to_string(),
+        })
+    }
+
+    /// Executes this file against source code, returning the graph.  The standard library of
+    /// functions is used, unless other `functions` are given.  Raises `ValueError` if the source
+    /// code cannot be parsed, unless `allow_parse_errors` is set, or if execution fails.
+    #[pyo3(signature = (source, functions=None, globals=None, lazy=false, allow_parse_errors=false))]
+    fn execute(
+        &self,
+        py: Python<'_>,
+        source: &str,
+        functions: Option<PyRef<'_, Functions>>,
+        globals: Option<PyRef<'_, Variables>>,
+        lazy: bool,
+        allow_parse_errors: bool,
+    ) -> PyResult<Graph> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&self.file.language)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        let tree = parser
+            .parse(source, None)
+            .ok_or_else(|| PyValueError::new_err("Cannot parse source code"))?;
+        if !allow_parse_errors {
+            if let Some(err) = ParseError::first(&tree) {
+                return Err(PyValueError::new_err(
+                    err.display_pretty(Path::new(SOURCE_PATH), source)
+                        .to_string(),
+                ));
+            }
+        }
+
+        let stdlib;
+        let functions = match &functions {
+            Some(functions) => &functions.functions,
+            None => {
+                stdlib = functions::Functions::stdlib();
+                &stdlib
+            }
+        };
+        let mut variables = tree_sitter_graph::Variables::new();
+        if let Some(globals) = &globals {
+            for (name, value) in &globals.values {
+                variables
+                    .add(name.clone(), value.clone())
+                    .map_err(|err| PyValueError::new_err(err.to_string()))?;
+            }
+        }
+        let config = ExecutionConfig::new(functions, &variables).lazy(lazy);
+        let graph = self
+            .file
+            .execute(&tree, source, &config, &NoCancellation)
+            .map_err(|err| {
+                PyValueError::new_err(
+                    err.display_pretty(
+                        Path::new(SOURCE_PATH),
+                        source,
+                        Path::new(TSG_PATH),
+                        &self.tsg,
+                    )
+                    .to_string(),
+                )
+            })?;
+        Graph::new(py, &graph, source)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Functions
+
+/// A library of functions that graph DSL files can call.
+#[pyclass(module = "tree_sitter_graph")]
+pub struct Functions {
+    functions: functions::Functions,
+}
+
+#[pymethods]
+impl Functions {
+    /// Creates an empty library of functions.
+    #[new]
+    fn new() -> Functions {
+        Functions {
+            functions: functions::Functions::new(),
+        }
+    }
+
+    /// Returns the standard library of functions.
+    #[staticmethod]
+    fn stdlib() -> Functions {
+        Functions {
+            functions: functions::Functions::stdlib(),
+        }
+    }
+
+    /// Adds a Python callable to this library.  It is called with the parameters of each call in
+    /// the graph DSL file, and returns the result.  Exceptions that it raises make execution fail.
+    fn add(&mut self, name: &str, function: PyObject) {
+        self.functions.add(
+            Identifier::from(name),
+            PythonFunction {
+                name: name.to_string(),
+                function,
+            },
+        );
+    }
+}
+
+/// A function of a graph DSL file that is implemented by a Python callable.
+struct PythonFunction {
+    name: String,
+    function: PyObject,
+}
+
+impl functions::Function for PythonFunction {
+    fn call(
+        &self,
+        graph: &mut graph::Graph,
+        source: &str,
+        parameters: &mut dyn Parameters,
+    ) -> Result<Value, ExecutionError> {
+        let mut values = Vec::new();
+        while let Ok(value) = parameters.param() {
+            values.push(value);
+        }
+        Python::with_gil(|py| {
+            let args = values
+                .iter()
+                .map(|value| to_python(py, graph, source, value))
+                .collect::<PyResult<Vec<_>>>()?;
+            let result = self.function.call1(py, PyTuple::new_bound(py, args))?;
+            from_python(result.bind(py))
+        })
+        .map_err(|err| ExecutionError::FunctionFailed(self.name.clone(), err.to_string()))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Variables
+
+/// A set of global variables for executing graph DSL files.
+#[pyclass(module = "tree_sitter_graph")]
+pub struct Variables {
+    values: HashMap<Identifier, Value>,
+}
+
+#[pymethods]
+impl Variables {
+    /// Creates an empty set of global variables.
+    #[new]
+    fn new() -> Variables {
+        Variables {
+            values: HashMap::new(),
+        }
+    }
+
+    /// Adds a global variable.  Raises `ValueError` if it is already defined.
+    fn add(&mut self, name: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let name = Identifier::from(name);
+        if self.values.contains_key(&name) {
+            return Err(PyValueError::new_err(format!(
+                "Variable {} already defined",
+                name
+            )));
+        }
+        self.values.insert(name, from_python(value)?);
+        Ok(())
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    fn __len__(&self) -> usize {
+        self.values.len()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Graphs
+
+/// The graph that executing a graph DSL file produces.  Unlike the graph of the Rust library, it
+/// does not refer to the syntax tree, so it can be kept after execution.
+#[pyclass(module = "tree_sitter_graph")]
+pub struct Graph {
+    /// Each graph node, with its attributes as a dictionary
+    nodes: Vec<(GraphNodeRef, PyObject)>,
+    /// The outgoing edges of each graph node, with the attributes of each edge as a dictionary
+    edges: Vec<Vec<(GraphNodeRef, PyObject)>>,
+    /// The graph in JSON format
+    json: String,
+}
+
+impl Graph {
+    fn new(py: Python<'_>, graph: &graph::Graph, source: &str) -> PyResult<Graph> {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for node in graph.iter_nodes() {
+            nodes.push((
+                node,
+                attributes(py, graph, source, &graph.node_attributes(node))?,
+            ));
+            edges.push(
+                graph[node]
+                    .iter_edges()
+                    .map(|(sink, edge)| {
+                        Ok((sink, attributes(py, graph, source, &edge.attributes)?))
+                    })
+                    .collect::<PyResult<Vec<_>>>()?,
+            );
+        }
+        let json = serde_json::to_string_pretty(graph)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(Graph { nodes, edges, json })
+    }
+
+    fn index(&self, node: &GraphNode) -> PyResult<usize> {
+        let index = node.node.index();
+        if index >= self.nodes.len() {
+            return Err(PyIndexError::new_err(format!("No graph node {}", index)));
+        }
+        Ok(index)
+    }
+}
+
+#[pymethods]
+impl Graph {
+    /// Returns the graph nodes.
+    fn nodes(&self) -> Vec<GraphNode> {
+        self.nodes
+            .iter()
+            .map(|(node, _)| GraphNode { node: *node })
+            .collect()
+    }
+
+    /// Returns the attributes of a graph node, as a dictionary.
+    fn attributes(&self, py: Python<'_>, node: PyRef<'_, GraphNode>) -> PyResult<PyObject> {
+        Ok(self.nodes[self.index(&node)?].1.clone_ref(py))
+    }
+
+    /// Returns the outgoing edges of a graph node, as pairs of the sink node and the attributes
+    /// of the edge.
+    fn edges(
+        &self,
+        py: Python<'_>,
+        node: PyRef<'_, GraphNode>,
+    ) -> PyResult<Vec<(GraphNode, PyObject)>> {
+        Ok(self.edges[self.index(&node)?]
+            .iter()
+            .map(|(sink, attributes)| (GraphNode { node: *sink }, attributes.clone_ref(py)))
+            .collect())
+    }
+
+    /// Returns the number of edges.
+    fn edge_count(&self) -> usize {
+        self.edges.iter().map(Vec::len).sum()
+    }
+
+    /// Returns the graph in the same JSON format as `--format json` on the command line.
+    fn to_json(&self) -> String {
+        self.json.clone()
+    }
+
+    fn __len__(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+fn attributes(
+    py: Python<'_>,
+    graph: &graph::Graph,
+    source: &str,
+    attributes: &Attributes,
+) -> PyResult<PyObject> {
+    let dict = PyDict::new_bound(py);
+    for (name, value) in attributes.iter() {
+        dict.set_item(name.as_str(), to_python(py, graph, source, value)?)?;
+    }
+    Ok(dict.into_py(py))
+}
+
+/// A reference to a graph node.
+#[pyclass(module = "tree_sitter_graph", frozen)]
+#[derive(Clone)]
+pub struct GraphNode {
+    node: GraphNodeRef,
+}
+
+#[pymethods]
+impl GraphNode {
+    #[getter]
+    fn index(&self) -> usize {
+        self.node.index()
+    }
+
+    fn __eq__(&self, other: &GraphNode) -> bool {
+        self.node == other.node
+    }
+
+    fn __hash__(&self) -> u64 {
+        hash(&self.node)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("GraphNode({})", self.node.index())
+    }
+}
+
+/// A reference to a syntax node, with the properties of the node.
+#[pyclass(module = "tree_sitter_graph", frozen)]
+pub struct SyntaxNode {
+    node: SyntaxNodeRef,
+    /// The kind of the node
+    #[pyo3(get)]
+    kind: String,
+    /// The zero-based row and column that the node starts at
+    #[pyo3(get)]
+    start: (usize, usize),
+    /// The zero-based row and column that the node ends at
+    #[pyo3(get)]
+    end: (usize, usize),
+    /// The source text of the node
+    #[pyo3(get)]
+    text: String,
+}
+
+impl SyntaxNode {
+    fn new(graph: &graph::Graph, source: &str, node: SyntaxNodeRef) -> SyntaxNode {
+        let syntax_node = graph[node];
+        SyntaxNode {
+            node,
+            kind: syntax_node.kind().to_string(),
+            start: (
+                syntax_node.start_position().row,
+                syntax_node.start_position().column,
+            ),
+            end: (
+                syntax_node.end_position().row,
+                syntax_node.end_position().column,
+            ),
+            text: source[syntax_node.byte_range()].to_string(),
+        }
+    }
+}
+
+#[pymethods]
+impl SyntaxNode {
+    fn __eq__(&self, other: &SyntaxNode) -> bool {
+        self.node == other.node
+    }
+
+    fn __hash__(&self) -> u64 {
+        hash(&self.node)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SyntaxNode({}, {}:{})",
+            self.kind,
+            self.start.0 + 1,
+            self.start.1 + 1
+        )
+    }
+}
+
+fn hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// ------------------------------------------------------------------------------------------------
+// Values
+
+fn to_python(
+    py: Python<'_>,
+    graph: &graph::Graph,
+    source: &str,
+    value: &Value,
+) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Boolean(value) => value.into_py(py),
+        Value::Integer(value) => value.into_py(py),
+        Value::String(value) => value.into_py(py),
+        Value::SourceSlice(value) => value.as_str().into_py(py),
+        Value::List(values) => {
+            let values = values
+                .iter()
+                .map(|value| to_python(py, graph, source, value))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new_bound(py, values).into_py(py)
+        }
+        Value::Set(values) => {
+            let values = values
+                .iter()
+                .map(|value| to_python(py, graph, source, value))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyFrozenSet::new_bound(py, &values)?.into_py(py)
+        }
+        Value::SyntaxNode(node) => SyntaxNode::new(graph, source, *node).into_py(py),
+        Value::GraphNode(node) => GraphNode { node: *node }.into_py(py),
+        Value::GraphEdge(edge) => (
+            GraphNode {
+                node: edge.source(),
+            },
+            GraphNode { node: edge.sink() },
+        )
+            .into_py(py),
+        _ => {
+            return Err(PyTypeError::new_err(format!(
+                "Cannot convert {} to Python",
+                value
+            )))
+        }
+    })
+}
+
+fn from_python(value: &Bound<'_, PyAny>) -> PyResult<Value> {
+    if value.is_none() {
+        Ok(Value::Null)
+    } else if let Ok(value) = value.downcast::<PyBool>() {
+        Ok(Value::Boolean(value.is_true()))
+    } else if value.is_instance_of::<PyLong>() {
+        Ok(Value::Integer(value.extract()?))
+    } else if value.is_instance_of::<PyString>() {
+        Ok(Value::String(value.extract()?))
+    } else if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+        let values = value
+            .iter()?
+            .map(|value| value.and_then(|value| from_python(&value)))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(Value::List(values))
+    } else if value.is_instance_of::<PySet>() || value.is_instance_of::<PyFrozenSet>() {
+        let values = value
+            .iter()?
+            .map(|value| value.and_then(|value| from_python(&value)))
+            .collect::<PyResult<BTreeSet<_>>>()?;
+        Ok(Value::Set(values))
+    } else if let Ok(node) = value.downcast::<SyntaxNode>() {
+        Ok(Value::SyntaxNode(node.get().node))
+    } else if let Ok(node) = value.downcast::<GraphNode>() {
+        Ok(Value::GraphNode(node.get().node))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "Cannot convert {} to a graph value",
+            value
+        )))
+    }
+}
@@ -0,0 +1,349 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! [Proptest][] strategies that generate well-formed graph DSL files and source files, for fuzzing
+//! the checker and the execution engine.  Enable the `proptest` feature to use them.
+//!
+//! The queries of a graph DSL file depend on the grammar of the language that it is written for,
+//! so these are functions that take a [`Language`][], rather than [`Arbitrary`][] impls.
+//!
+//! ``` ignore
+//! use proptest::prelude::*;
+//! use tree_sitter_graph::arbitrary::source_text;
+//! use tree_sitter_graph::arbitrary::tsg_file;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn executes_without_panicking(
+//!         file in tsg_file(tree_sitter_python::language()),
+//!         source in source_text(vec!["def f(x): return x\n".into()]),
+//!     ) {
+//!         // parse `source` and execute `file` against it
+//!     }
+//! }
+//! ```
+//!
+//! Generated files always parse and check, and every variable, capture, and attribute that they
+//! use is defined, so they are also expected to execute without errors against any source file.
+//!
+//! [Proptest]: https://docs.rs/proptest/
+//! [`Arbitrary`]: proptest::arbitrary::Arbitrary
+
+use std::fmt::Write as _;
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use tree_sitter::Language;
+
+use crate::ast::File;
+
+/// The most choices that are drawn to generate one file.  A file is finished early, and without
+/// further nesting, once they run out.
+const MAX_CHOICES: usize = 256;
+const MAX_STANZAS: usize = 4;
+const MAX_STATEMENTS: usize = 6;
+const MAX_DEPTH: usize = 2;
+
+/// Returns a strategy that generates the source of well-formed graph DSL files for `language`.
+/// Its queries only use the named node kinds of the language.
+pub fn tsg_source(language: &Language) -> impl Strategy<Value = String> {
+    let kinds = node_kinds(language);
+    vec(any::<u32>(), 0..MAX_CHOICES)
+        .prop_map(move |choices| Generator::new(&kinds, choices).file())
+}
+
+/// Returns a strategy that generates well-formed graph DSL files for `language`.
+pub fn tsg_file(language: Language) -> impl Strategy<Value = File> {
+    tsg_source(&language).prop_map(
+        move |source| match File::from_str(language.clone(), &source) {
+            Ok(file) => file,
+            Err(e) => panic!("generated an invalid file: {}\n{}", e, source),
+        },
+    )
+}
+
+/// Returns a strategy that generates source files by mutating `samples`: slices of each sample are
+/// deleted or duplicated, and individual bytes are replaced.  The results are usually not
+/// syntactically valid, which exercises execution against trees with errors.
+pub fn source_text(samples: Vec<String>) -> impl Strategy<Value = String> {
+    assert!(!samples.is_empty(), "at least one sample is required");
+    let count = samples.len();
+    (
+        0..count,
+        vec((any::<u8>(), any::<usize>(), any::<usize>()), 0..8),
+    )
+        .prop_map(move |(sample, mutations)| {
+            let mut text = samples[sample].clone().into_bytes();
+            for (kind, a, b) in mutations {
+                if text.is_empty() {
+                    break;
+                }
+                let (start, end) = {
+                    let (a, b) = (a % text.len(), b % text.len());
+                    (a.min(b), a.max(b) + 1)
+                };
+                match kind % 3 {
+                    0 => {
+                        text.drain(start..end);
+                    }
+                    1 => {
+                        let slice = text[start..end].to_vec();
+                        text.splice(end..end, slice);
+                    }
+                    _ => {
+                        let replacements = b" \n(){}[];,.:=\"'x0";
+                        text[start] = replacements[kind as usize % replacements.len()];
+                    }
+                }
+            }
+            String::from_utf8_lossy(&text).into_owned()
+        })
+}
+
+/// Returns the named node kinds of a language that can appear in queries.
+fn node_kinds(language: &Language) -> Vec<&'static str> {
+    let mut kinds = Vec::new();
+    for id in 0..language.node_kind_count() as u16 {
+        if !language.node_kind_is_named(id) || !language.node_kind_is_visible(id) {
+            continue;
+        }
+        if let Some(kind) = language.node_kind_for_id(id) {
+            if kind != "ERROR" && !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+    }
+    kinds
+}
+
+/// Renders a graph DSL file from a sequence of choices.  Every choice that runs out is taken to
+/// be zero, which is always the simplest option, so shrinking the choices shrinks the file.
+struct Generator<'a> {
+    kinds: &'a [&'static str],
+    choices: std::vec::IntoIter<u32>,
+    output: String,
+    /// The number of names generated so far, which makes every name unique
+    names: usize,
+}
+
+/// What the statements of a block can refer to.
+#[derive(Clone, Default)]
+struct Scope {
+    /// Expressions that evaluate to syntax nodes
+    syntax_nodes: Vec<String>,
+    /// Variables and captures whose values the checker considers local, which are the only ones
+    /// that can be used in conditions and iterated over
+    locals: Vec<String>,
+    /// Variables and captures of any kind
+    values: Vec<String>,
+    /// Variables that can be assigned with `set`
+    mutables: Vec<String>,
+    /// Variables that hold graph nodes
+    graph_nodes: Vec<String>,
+    /// Graph nodes that were created in the current block, and so can be given attributes
+    /// without ever setting the same attribute twice
+    local_graph_nodes: Vec<String>,
+}
+
+impl Scope {
+    /// Returns the scope of a block that can run more than once.
+    fn repeated(&self) -> Scope {
+        Scope {
+            local_graph_nodes: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+impl<'a> Generator<'a> {
+    fn new(kinds: &'a [&'static str], choices: Vec<u32>) -> Generator<'a> {
+        Generator {
+            kinds,
+            choices: choices.into_iter(),
+            output: String::new(),
+            names: 0,
+        }
+    }
+
+    /// Returns a choice between `n` options.
+    fn choose(&mut self, n: usize) -> usize {
+        self.choices.next().map_or(0, |choice| choice as usize % n)
+    }
+
+    fn exhausted(&self) -> bool {
+        self.choices.len() == 0
+    }
+
+    fn name(&mut self, prefix: &str) -> String {
+        self.names += 1;
+        format!("{}{}", prefix, self.names)
+    }
+
+    fn file(mut self) -> String {
+        let stanzas = 1 + self.choose(MAX_STANZAS);
+        for _ in 0..stanzas {
+            self.stanza();
+        }
+        self.output
+    }
+
+    fn stanza(&mut self) {
+        let kind = match self.kinds.len() {
+            0 => "_",
+            n => self.kinds[self.choose(n)],
+        };
+        // The capture starts with an underscore, so that stanzas that do not use it still check.
+        let _ = writeln!(self.output, "({}) @_node", kind);
+        self.output += "{\n";
+        let mut scope = Scope {
+            syntax_nodes: vec!["@_node".to_string()],
+            locals: vec!["@_node".to_string()],
+            values: vec!["@_node".to_string()],
+            ..Default::default()
+        };
+        self.block(&mut scope, 0);
+        self.output += "}\n\n";
+    }
+
+    fn block(&mut self, scope: &mut Scope, depth: usize) {
+        let statements = self.choose(MAX_STATEMENTS + 1);
+        for _ in 0..statements {
+            self.statement(scope, depth);
+        }
+    }
+
+    fn statement(&mut self, scope: &mut Scope, depth: usize) {
+        let indent = "  ".repeat(depth + 1);
+        let choice = if self.exhausted() { 0 } else { self.choose(9) };
+        match choice {
+            1 => {
+                let name = self.name("x");
+                let (value, is_local) = self.expression(scope, 0, false);
+                let _ = writeln!(self.output, "{}let {} = {}", indent, name, value);
+                if is_local {
+                    scope.locals.push(name.clone());
+                }
+                scope.values.push(name);
+            }
+            2 => {
+                let name = self.name("m");
+                let (value, _) = self.expression(scope, 0, false);
+                let _ = writeln!(self.output, "{}var {} = {}", indent, name, value);
+                scope.values.push(name.clone());
+                scope.mutables.push(name);
+            }
+            3 if !scope.mutables.is_empty() => {
+                let name = self.pick(&scope.mutables);
+                let (value, _) = self.expression(scope, 0, false);
+                let _ = writeln!(self.output, "{}set {} = {}", indent, name, value);
+            }
+            4 if !scope.graph_nodes.is_empty() => {
+                let source = self.pick(&scope.graph_nodes);
+                let sink = self.pick(&scope.graph_nodes);
+                let _ = writeln!(self.output, "{}edge {} -> {}", indent, source, sink);
+            }
+            5 if !scope.local_graph_nodes.is_empty() => {
+                let node = self.pick(&scope.local_graph_nodes);
+                let name = self.name("a");
+                let (value, _) = self.expression(scope, 0, false);
+                let _ = writeln!(
+                    self.output,
+                    "{}attr ({}) {} = {}",
+                    indent, node, name, value
+                );
+            }
+            // Scoped variables can only be defined once per syntax node, so never in a block that
+            // can run more than once.
+            6 if depth == 0 => {
+                let name = format!("@_node.{}", self.name("s"));
+                let (value, _) = self.expression(scope, 0, false);
+                let _ = writeln!(self.output, "{}let {} = {}", indent, name, value);
+                scope.values.push(name);
+            }
+            7 if depth < MAX_DEPTH => {
+                let row = self.syntax_function(scope, "start-row");
+                let other = self.choose(4);
+                let _ = writeln!(self.output, "{}if (eq {} {}) {{", indent, row, other);
+                self.block(&mut scope.clone(), depth + 1);
+                let _ = writeln!(self.output, "{}}} else {{", indent);
+                self.block(&mut scope.clone(), depth + 1);
+                let _ = writeln!(self.output, "{}}}", indent);
+            }
+            8 if depth < MAX_DEPTH => {
+                if self.choose(2) == 0 {
+                    let name = self.name("i");
+                    let (first, _) = self.expression(scope, 1, true);
+                    let (second, _) = self.expression(scope, 1, true);
+                    let _ = writeln!(
+                        self.output,
+                        "{}for {} in [{}, {}] {{",
+                        indent, name, first, second
+                    );
+                    let mut inner = scope.repeated();
+                    inner.locals.push(name.clone());
+                    inner.values.push(name);
+                    self.block(&mut inner, depth + 1);
+                } else {
+                    let text = self.syntax_function(scope, "source-text");
+                    let _ = writeln!(self.output, "{}scan {} {{", indent, text);
+                    for regex in ["[a-z]+", "[0-9]+"] {
+                        let _ = writeln!(self.output, "{}  \"{}\" {{", indent, regex);
+                        let mut inner = scope.repeated();
+                        inner.locals.push("$0".to_string());
+                        inner.values.push("$0".to_string());
+                        self.block(&mut inner, depth + 2);
+                        let _ = writeln!(self.output, "{}  }}", indent);
+                    }
+                }
+                let _ = writeln!(self.output, "{}}}", indent);
+            }
+            _ => {
+                let name = self.name("g");
+                let _ = writeln!(self.output, "{}node {}", indent, name);
+                scope.locals.push(name.clone());
+                scope.values.push(name.clone());
+                scope.graph_nodes.push(name.clone());
+                scope.local_graph_nodes.push(name);
+            }
+        }
+    }
+
+    fn pick(&mut self, names: &[String]) -> String {
+        names[self.choose(names.len())].clone()
+    }
+
+    /// Returns an expression, and whether the checker considers its value local.  Only local
+    /// expressions are returned if `local` is set.
+    fn expression(&mut self, scope: &Scope, depth: usize, local: bool) -> (String, bool) {
+        match self.choose(7) {
+            1 => (format!("{}", self.choose(1000)), true),
+            2 if local => (self.pick(&scope.locals), true),
+            2 => {
+                let name = self.pick(&scope.values);
+                let is_local = scope.locals.contains(&name);
+                (name, is_local)
+            }
+            3 => (self.syntax_function(scope, "source-text"), true),
+            4 => (self.syntax_function(scope, "node-type"), true),
+            5 => (self.syntax_function(scope, "start-row"), true),
+            6 if depth < MAX_DEPTH => {
+                let (first, first_is_local) = self.expression(scope, depth + 1, local);
+                let (second, second_is_local) = self.expression(scope, depth + 1, local);
+                (
+                    format!("[{}, {}]", first, second),
+                    first_is_local && second_is_local,
+                )
+            }
+            _ => (format!("\"{}\"", self.name("s")), true),
+        }
+    }
+
+    fn syntax_function(&mut self, scope: &Scope, function: &str) -> String {
+        let node = self.pick(&scope.syntax_nodes);
+        format!("({} {})", function, node)
+    }
+}
@@ -0,0 +1,149 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Compiled graph DSL files, which can be loaded without parsing and checking them again.
+//! Enabled with the `artifact` feature.
+//!
+//! An artifact starts with a header that identifies the version of this crate and the grammar
+//! that the file was checked against, followed by the AST in a compact binary encoding.  The
+//! resolved capture indices, shorthands, and stanza names are stored as they were checked.
+//! tree-sitter queries and regular expressions cannot be stored in compiled form, so they are
+//! compiled again when an artifact is loaded.
+//!
+//! Tools that cache artifacts on disk, so that large files are not parsed and checked on every
+//! run, can store them under the key returned by [`File::artifact_key`][].  The key changes when
+//! the file, the grammar, or this crate change, so that stale artifacts are never found.
+
+use bincode::Options;
+use thiserror::Error;
+use tree_sitter::Language;
+
+use crate::ast::File;
+
+/// The bytes that every artifact starts with.
+const MAGIC: &[u8] = b"TSGC";
+/// The version of this crate, which artifacts must have been compiled by.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// An error that occurs when loading a compiled graph DSL file
+#[derive(Debug, Error)]
+pub enum ArtifactError {
+    #[error("Not a compiled graph DSL file")]
+    NotAnArtifact,
+    #[error(
+        "Compiled by tree-sitter-graph {0}, but this is tree-sitter-graph {}",
+        VERSION
+    )]
+    VersionMismatch(String),
+    #[error("Compiled for a different language")]
+    LanguageMismatch,
+    #[error("Invalid compiled graph DSL file: {0}")]
+    Invalid(String),
+}
+
+impl File {
+    /// Compiles this file into an artifact that [`File::load`][] loads for the same language,
+    /// without parsing and checking it again.
+    pub fn compile(&self) -> Vec<u8> {
+        let version = VERSION.as_bytes();
+        let mut artifact = Vec::new();
+        artifact.extend_from_slice(MAGIC);
+        artifact.push(version.len() as u8);
+        artifact.extend_from_slice(version);
+        artifact.extend_from_slice(&fingerprint(&self.language).to_le_bytes());
+        options()
+            .serialize_into(&mut artifact, self)
+            .expect("files can always be serialized");
+        artifact
+    }
+
+    /// Returns a key that identifies the artifact of a file with the given source, compiled for a
+    /// language by this version of the crate.  The key is a hash of the source, the node kinds,
+    /// fields, and ABI version of the language, and the version of this crate, written as 16
+    /// hexadecimal digits so that it can be used as a file name.
+    pub fn artifact_key(language: &Language, source: &str) -> String {
+        let mut hasher = Fnv::new();
+        hasher.write(VERSION.as_bytes());
+        hasher.write(&[0]);
+        hasher.write(&fingerprint(language).to_le_bytes());
+        hasher.write(source.as_bytes());
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Loads an artifact that was created by [`File::compile`][], for the language that the file
+    /// was checked against.  Fails if the artifact was compiled by a different version of this
+    /// crate, or for a different grammar.
+    pub fn load(language: Language, artifact: &[u8]) -> Result<File, ArtifactError> {
+        let rest = artifact
+            .strip_prefix(MAGIC)
+            .ok_or(ArtifactError::NotAnArtifact)?;
+        let (&version_len, rest) = rest.split_first().ok_or(ArtifactError::NotAnArtifact)?;
+        if rest.len() < version_len as usize + 8 {
+            return Err(ArtifactError::NotAnArtifact);
+        }
+        let (version, rest) = rest.split_at(version_len as usize);
+        if version != VERSION.as_bytes() {
+            return Err(ArtifactError::VersionMismatch(
+                String::from_utf8_lossy(version).into_owned(),
+            ));
+        }
+        let (fingerprint_bytes, payload) = rest.split_at(8);
+        if fingerprint_bytes != fingerprint(&language).to_le_bytes() {
+            return Err(ArtifactError::LanguageMismatch);
+        }
+        let mut deserializer = bincode::Deserializer::from_slice(payload, options());
+        File::deserialize_with(language, &mut deserializer)
+            .map_err(|err| ArtifactError::Invalid(err.to_string()))
+    }
+}
+
+/// The encoding of the AST in artifacts.
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Returns a hash of the node kinds and fields of a language, which the queries of a file must
+/// compile against in the same way as when the file was checked.  The hash is stable across
+/// builds, unlike the hashers of `std`.
+fn fingerprint(language: &Language) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write(&(language.version() as u64).to_le_bytes());
+    for id in 0..language.node_kind_count() as u16 {
+        hasher.write(language.node_kind_for_id(id).unwrap_or_default().as_bytes());
+        hasher.write(&[language.node_kind_is_named(id) as u8, 0]);
+    }
+    for id in 1..=language.field_count() as u16 {
+        hasher.write(
+            language
+                .field_name_for_id(id)
+                .unwrap_or_default()
+                .as_bytes(),
+        );
+        hasher.write(&[0]);
+    }
+    hasher.finish()
+}
+
+/// The FNV-1a hash function
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -8,6 +8,12 @@
 //! Defines the AST structure of a graph DSL file
 
 use regex::Regex;
+use serde::de::Error as _;
+use serde::ser::SerializeStruct;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
@@ -19,7 +25,20 @@ use crate::parser::Range;
 use crate::Identifier;
 use crate::Location;
 
+pub mod visit;
+pub mod visit_mut;
+
+pub use visit::Visitor;
+pub use visit_mut::VisitorMut;
+
 /// A graph DSL file
+///
+/// Files are serialized without their language and compiled queries, and are deserialized with
+/// [`File::deserialize_with`][], which compiles the queries again.
+///
+/// A checked file, including its compiled queries, is `Send` and `Sync`, and executing it does not
+/// change it.  Servers can load their rules once, and share them with their worker threads in an
+/// `Arc<File>`.
 #[derive(Debug)]
 pub struct File {
     pub language: Language,
@@ -27,12 +46,24 @@ pub struct File {
     pub globals: Vec<Global>,
     /// The scoped variables that are inherited by child nodes
     pub inherited_variables: HashSet<Identifier>,
+    /// The function namespaces that this file declares that it uses
+    pub uses: Vec<Uses>,
     /// The combined query of all stanzas in the file
     pub query: Option<Query>,
+    /// The source of the combined query, with one pattern for each stanza
+    pub query_source: String,
+    /// The stanzas that are executed on the matches of each pattern of the combined query.  Each
+    /// stanza is executed on the matches of its own pattern, unless [`optimize`][Self::optimize]
+    /// merged it into the pattern of an earlier stanza.
+    pub pattern_stanzas: Vec<Vec<usize>>,
     /// The list of stanzas in the file
     pub stanzas: Vec<Stanza>,
     /// Attribute shorthands defined in the file
     pub shorthands: AttributeShorthands,
+    /// The comments in the file, attached to the items and statements that they document
+    pub comments: Comments,
+    /// The ranges of the items, statements, and expressions in the file
+    pub spans: Spans,
 }
 
 impl File {
@@ -41,30 +72,292 @@ impl File {
             language,
             globals: Vec::new(),
             inherited_variables: HashSet::new(),
+            uses: Vec::new(),
             query: None,
+            query_source: String::new(),
+            pattern_stanzas: Vec::new(),
             stanzas: Vec::new(),
             shorthands: AttributeShorthands::new(),
+            comments: Comments::default(),
+            spans: Spans::default(),
+        }
+    }
+
+    /// Returns the index of the stanza with the given name, if there is one.
+    pub fn stanza_index(&self, name: &str) -> Option<usize> {
+        self.stanzas
+            .iter()
+            .position(|stanza| stanza.name.as_ref().map(Identifier::as_str) == Some(name))
+    }
+
+    /// Disables a stanza, so that its query no longer matches any syntax nodes and its statements
+    /// are never executed.  The other stanzas keep their indices.
+    pub fn disable_stanza(&mut self, index: usize) {
+        // The pattern that the stanza is executed on can be shared with other stanzas, which must
+        // still be executed on its matches.
+        for (pattern_index, stanzas) in self.pattern_stanzas.iter_mut().enumerate() {
+            if let Some(position) = stanzas.iter().position(|stanza| *stanza == index) {
+                stanzas.remove(position);
+                if stanzas.is_empty() {
+                    if let Some(query) = &mut self.query {
+                        query.disable_pattern(pattern_index);
+                    }
+                }
+            }
+        }
+        self.stanzas[index].query.disable_pattern(0);
+    }
+
+    /// Returns the stanzas that are executed on the matches of each pattern of the combined query,
+    /// when every stanza has its own pattern.
+    pub(crate) fn own_pattern_stanzas(stanza_count: usize) -> Vec<Vec<usize>> {
+        (0..stanza_count).map(|index| vec![index]).collect()
+    }
+
+    /// Deserializes a file that was serialized after it was parsed and checked, compiling its
+    /// queries for `language`, which must be the language that the file was parsed for.  The
+    /// file does not need to be checked again.  Stanzas that were disabled are enabled again, and
+    /// stanzas that were merged by [`optimize`][Self::optimize] are separated again.
+    pub fn deserialize_with<'de, D: Deserializer<'de>>(
+        language: Language,
+        deserializer: D,
+    ) -> Result<File, D::Error> {
+        let data = SerializedFile::deserialize(deserializer)?;
+        let query = Query::new(&language, &data.query_source).map_err(D::Error::custom)?;
+        let stanza_count = data.stanzas.len();
+        if query.pattern_count() != stanza_count {
+            return Err(D::Error::custom(format!(
+                "expected {} stanzas, found {}",
+                query.pattern_count(),
+                stanza_count
+            )));
         }
+        // The query source has one pattern for each stanza, which is the query of the stanza.
+        let mut stanzas = Vec::with_capacity(stanza_count);
+        for (index, stanza) in data.stanzas.into_iter().enumerate() {
+            let start = query.start_byte_for_pattern(index);
+            let end = if index + 1 < stanza_count {
+                query.start_byte_for_pattern(index + 1)
+            } else {
+                data.query_source.len()
+            };
+            let stanza_query =
+                Query::new(&language, &data.query_source[start..end]).map_err(D::Error::custom)?;
+            stanzas.push(Stanza {
+                name: stanza.name,
+                query: stanza_query,
+                guard: stanza.guard,
+                statements: stanza.statements,
+                full_match_stanza_capture_index: stanza.full_match_stanza_capture_index,
+                full_match_file_capture_index: stanza.full_match_file_capture_index,
+                range: stanza.range,
+            });
+        }
+        Ok(File {
+            language,
+            globals: data.globals,
+            inherited_variables: data.inherited_variables,
+            uses: data.uses,
+            query: Some(query),
+            query_source: data.query_source,
+            pattern_stanzas: File::own_pattern_stanzas(stanza_count),
+            stanzas,
+            shorthands: data.shorthands,
+            comments: data.comments,
+            spans: data.spans,
+        })
+    }
+}
+
+impl Serialize for File {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("File", 8)?;
+        state.serialize_field("globals", &self.globals)?;
+        state.serialize_field("inherited_variables", &self.inherited_variables)?;
+        state.serialize_field("uses", &self.uses)?;
+        state.serialize_field("query_source", &self.query_source)?;
+        state.serialize_field("stanzas", &self.stanzas)?;
+        state.serialize_field("shorthands", &self.shorthands)?;
+        state.serialize_field("comments", &self.comments)?;
+        state.serialize_field("spans", &self.spans)?;
+        state.end()
+    }
+}
+
+/// The serialized form of a file, whose queries have not been compiled yet
+#[derive(Deserialize)]
+struct SerializedFile {
+    globals: Vec<Global>,
+    inherited_variables: HashSet<Identifier>,
+    #[serde(default)]
+    uses: Vec<Uses>,
+    query_source: String,
+    stanzas: Vec<SerializedStanza>,
+    shorthands: AttributeShorthands,
+    #[serde(default)]
+    comments: Comments,
+    #[serde(default)]
+    spans: Spans,
+}
+
+/// The comments of a graph DSL file.  Each comment is attached to a global declaration, attribute
+/// shorthand, inherit directive, uses declaration, stanza, or statement, which is identified by
+/// its location: the `location` of a global, a shorthand, a uses declaration, or a statement, the
+/// start of the range of a stanza, or the location of the `inherit` keyword of an inherit
+/// directive.  Comments inside query patterns are part of the query source instead.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Comments {
+    /// The comments of each item or statement, in the order of their locations
+    attached: Vec<(Location, Trivia)>,
+    /// The comments of a file without items
+    dangling: Vec<Comment>,
+}
+
+impl Comments {
+    /// Returns the comments of the item or statement at `location`, if it has any.
+    pub fn get(&self, location: Location) -> Option<&Trivia> {
+        self.attached
+            .binary_search_by_key(&location, |(location, _)| *location)
+            .ok()
+            .map(|index| &self.attached[index].1)
+    }
+
+    /// Returns the comments on the lines before the item or statement at `location`.
+    pub fn leading(&self, location: Location) -> &[Comment] {
+        self.get(location).map_or(&[], |trivia| &trivia.leading)
+    }
+
+    /// Returns the comments after the start of the item or statement at `location`, up to the
+    /// end of its last line.
+    pub fn trailing(&self, location: Location) -> &[Comment] {
+        self.get(location).map_or(&[], |trivia| &trivia.trailing)
     }
+
+    /// Returns the items and statements that have comments, with their comments, in the order
+    /// of their locations.
+    pub fn iter(&self) -> impl Iterator<Item = (Location, &Trivia)> + '_ {
+        self.attached
+            .iter()
+            .map(|(location, trivia)| (*location, trivia))
+    }
+
+    /// Returns the comments of a file that has no items to attach them to.
+    pub fn dangling(&self) -> &[Comment] {
+        &self.dangling
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attached.is_empty() && self.dangling.is_empty()
+    }
+
+    pub(crate) fn new(mut attached: Vec<(Location, Trivia)>, dangling: Vec<Comment>) -> Comments {
+        attached.sort_by_key(|(location, _)| *location);
+        Comments { attached, dangling }
+    }
+}
+
+/// The ranges of the constructs of a graph DSL file: global declarations, attribute shorthands,
+/// stanzas, statements, and expressions.  Each range is identified by the location that it starts
+/// at, which is the `location` of a global, a shorthand, a statement, or an expression that has
+/// one, or the start of the range of a stanza.  The range of a scoped variable starts at its name,
+/// since its scope has a range of its own.  A range ends after the last token of its construct.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Spans(Vec<Range>);
+
+impl Spans {
+    /// Returns the range of the construct that starts at `location`, if there is one.
+    pub fn get(&self, location: Location) -> Option<Range> {
+        self.0
+            .binary_search_by_key(&location, |range| range.start)
+            .ok()
+            .map(|index| self.0[index])
+    }
+
+    /// Returns the end of the construct that starts at `location`, if there is one.
+    pub fn end(&self, location: Location) -> Option<Location> {
+        self.get(location).map(|range| range.end)
+    }
+
+    /// Returns the range of the innermost construct that contains `location`, if there is one.
+    pub fn innermost(&self, location: Location) -> Option<Range> {
+        // Ranges are either nested or disjoint, so the innermost one starts last.
+        let count = self.0.partition_point(|range| range.start <= location);
+        self.0[..count]
+            .iter()
+            .rev()
+            .find(|range| location < range.end)
+            .copied()
+    }
+
+    /// Returns the ranges of all constructs, in the order of their starts.
+    pub fn iter(&self) -> impl Iterator<Item = Range> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub(crate) fn new(mut ranges: Vec<Range>) -> Spans {
+        ranges.sort_by_key(|range| range.start);
+        ranges.dedup_by_key(|range| range.start);
+        Spans(ranges)
+    }
+}
+
+/// The comments attached to an item or statement.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Trivia {
+    /// The comments on the lines before the item or statement, after the previous one
+    pub leading: Vec<Comment>,
+    /// The comments within the item or statement, up to the end of its last line, and for the
+    /// last item or statement of a file or block, the comments after it
+    pub trailing: Vec<Comment>,
+    /// The comments in the blocks of the item or statement that have no statements
+    pub inner: Vec<Comment>,
+}
+
+impl Trivia {
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_empty() && self.inner.is_empty()
+    }
+}
+
+/// A comment, which runs from a semicolon to the end of its line.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Comment {
+    /// The text of the comment, including the semicolon, without trailing whitespace
+    pub text: String,
+    pub location: Location,
 }
 
 /// A global variable
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Global {
     /// The name of the global variable
     pub name: Identifier,
     /// The quantifier of the global variable
+    #[serde(with = "serde_quantifier")]
     pub quantifier: CaptureQuantifier,
     /// Default value
     pub default: Option<String>,
     pub location: Location,
 }
 
+/// A uses declaration, which names the namespaces of the functions that a file calls, as in
+/// `uses "string", "math"`
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Uses {
+    pub namespaces: Vec<String>,
+    pub location: Location,
+}
+
 /// One stanza within a file
 #[derive(Debug)]
 pub struct Stanza {
+    /// The name of this stanza, if it was given one
+    pub name: Option<Identifier>,
     /// The tree-sitter query for this stanza
     pub query: Query,
+    /// The condition on global variables that must hold for this stanza to be executed, as in
+    /// `when INCLUDE_TESTS`
+    pub guard: Option<Expression>,
     /// The list of statements in the stanza
     pub statements: Vec<Statement>,
     /// Capture index of the full match in the stanza query
@@ -74,12 +367,45 @@ pub struct Stanza {
     pub range: Range,
 }
 
+/// Serializes a stanza without its query, which is part of the query source of the file.
+impl Serialize for Stanza {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Stanza", 6)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("guard", &self.guard)?;
+        state.serialize_field("statements", &self.statements)?;
+        state.serialize_field(
+            "full_match_stanza_capture_index",
+            &self.full_match_stanza_capture_index,
+        )?;
+        state.serialize_field(
+            "full_match_file_capture_index",
+            &self.full_match_file_capture_index,
+        )?;
+        state.serialize_field("range", &self.range)?;
+        state.end()
+    }
+}
+
+/// The serialized form of a stanza, whose query has not been compiled yet
+#[derive(Deserialize)]
+struct SerializedStanza {
+    name: Option<Identifier>,
+    #[serde(default)]
+    guard: Option<Expression>,
+    statements: Vec<Statement>,
+    full_match_stanza_capture_index: usize,
+    full_match_file_capture_index: usize,
+    range: Range,
+}
+
 /// A statement that can appear in a graph DSL stanza
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Statement {
     // Variables
     DeclareImmutable(DeclareImmutable),
     DeclareMutable(DeclareMutable),
+    DestructureList(DestructureList),
     Assign(Assign),
     // Graph nodes
     CreateGraphNode(CreateGraphNode),
@@ -93,8 +419,36 @@ pub enum Statement {
     Print(Print),
     // If
     If(If),
+    // Match
+    Match(Match),
     // ForIn
     ForIn(ForIn),
+    ForInZip(ForInZip),
+    // Extensions
+    Extension(Extension),
+}
+
+impl Statement {
+    /// Returns the location of this statement in the graph DSL file.
+    pub fn location(&self) -> Location {
+        match self {
+            Statement::DeclareImmutable(s) => s.location,
+            Statement::DeclareMutable(s) => s.location,
+            Statement::DestructureList(s) => s.location,
+            Statement::Assign(s) => s.location,
+            Statement::CreateGraphNode(s) => s.location,
+            Statement::AddGraphNodeAttribute(s) => s.location,
+            Statement::CreateEdge(s) => s.location,
+            Statement::AddEdgeAttribute(s) => s.location,
+            Statement::Scan(s) => s.location,
+            Statement::Print(s) => s.location,
+            Statement::If(s) => s.location,
+            Statement::Match(s) => s.location,
+            Statement::ForIn(s) => s.location,
+            Statement::ForInZip(s) => s.location,
+            Statement::Extension(s) => s.location,
+        }
+    }
 }
 
 impl std::fmt::Display for Statement {
@@ -102,6 +456,7 @@ impl std::fmt::Display for Statement {
         match self {
             Self::DeclareImmutable(stmt) => stmt.fmt(f),
             Self::DeclareMutable(stmt) => stmt.fmt(f),
+            Self::DestructureList(stmt) => stmt.fmt(f),
             Self::Assign(stmt) => stmt.fmt(f),
             Self::CreateGraphNode(stmt) => stmt.fmt(f),
             Self::AddGraphNodeAttribute(stmt) => stmt.fmt(f),
@@ -110,13 +465,16 @@ impl std::fmt::Display for Statement {
             Self::Scan(stmt) => stmt.fmt(f),
             Self::Print(stmt) => stmt.fmt(f),
             Self::If(stmt) => stmt.fmt(f),
+            Self::Match(stmt) => stmt.fmt(f),
             Self::ForIn(stmt) => stmt.fmt(f),
+            Self::ForInZip(stmt) => stmt.fmt(f),
+            Self::Extension(stmt) => stmt.fmt(f),
         }
     }
 }
 
 /// An `attr` statement that adds an attribute to an edge
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct AddEdgeAttribute {
     pub source: Expression,
     pub sink: Expression,
@@ -141,7 +499,7 @@ impl std::fmt::Display for AddEdgeAttribute {
 }
 
 /// An `attr` statement that adds an attribute to a graph node
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct AddGraphNodeAttribute {
     pub node: Expression,
     pub attributes: Vec<Attribute>,
@@ -165,7 +523,7 @@ impl std::fmt::Display for AddGraphNodeAttribute {
 }
 
 /// A `set` statement that updates the value of a mutable variable
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Assign {
     pub variable: Variable,
     pub value: Expression,
@@ -189,23 +547,43 @@ impl std::fmt::Display for Assign {
 }
 
 /// The name and value of an attribute
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Attribute {
     pub name: Identifier,
     pub value: Expression,
+    /// Whether the value is appended to a list-valued attribute (`+=`), instead of setting the
+    /// attribute (`=`)
+    #[serde(default)]
+    pub append: bool,
+    /// Whether the attribute is written as a flag, which is `name` for `name = #true` and
+    /// `!name` for `name = #false`
+    #[serde(default)]
+    pub flag: bool,
 }
 
 impl std::fmt::Display for Attribute {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{} = {}", self.name, self.value)
+        match (&self.value, self.flag, self.append) {
+            (Expression::TrueLiteral, true, _) => write!(f, "{}", self.name),
+            (Expression::FalseLiteral, true, _) => write!(f, "!{}", self.name),
+            (_, _, true) => write!(f, "{} += {}", self.name, self.value),
+            (_, _, false) => write!(f, "{} = {}", self.name, self.value),
+        }
     }
 }
 
 /// An `edge` statement that creates a new edge
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct CreateEdge {
+    /// The variable that the edge is bound to, as in `edge e = a -> b`
+    #[serde(default)]
+    pub variable: Option<Variable>,
     pub source: Expression,
     pub sink: Expression,
+    /// The kind of the edge, as in `edge a -"calls"-> b`, which is stored apart from its
+    /// attributes
+    #[serde(default)]
+    pub kind: Option<Identifier>,
     pub location: Location,
 }
 
@@ -217,18 +595,26 @@ impl From<CreateEdge> for Statement {
 
 impl std::fmt::Display for CreateEdge {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "edge {} -> {} at {}",
-            self.source, self.sink, self.location,
-        )
+        write!(f, "edge ")?;
+        if let Some(variable) = &self.variable {
+            write!(f, "{} = ", variable)?;
+        }
+        match &self.kind {
+            Some(kind) => write!(f, "{} -{:?}-> ", self.source, kind.as_str())?,
+            None => write!(f, "{} -> ", self.source)?,
+        }
+        write!(f, "{} at {}", self.sink, self.location)
     }
 }
 
 /// A `node` statement that creates a new graph node
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct CreateGraphNode {
     pub node: Variable,
+    /// The label of the graph node, as in `node n : "definition"`, which is stored apart from
+    /// its attributes
+    #[serde(default)]
+    pub label: Option<Expression>,
     pub location: Location,
 }
 
@@ -240,12 +626,15 @@ impl From<CreateGraphNode> for Statement {
 
 impl std::fmt::Display for CreateGraphNode {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "node {} at {}", self.node, self.location)
+        match &self.label {
+            Some(label) => write!(f, "node {} : {} at {}", self.node, label, self.location),
+            None => write!(f, "node {} at {}", self.node, self.location),
+        }
     }
 }
 
 /// A `let` statement that declares a new immutable variable
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct DeclareImmutable {
     pub variable: Variable,
     pub value: Expression,
@@ -268,8 +657,47 @@ impl std::fmt::Display for DeclareImmutable {
     }
 }
 
+/// A `let` statement that declares new immutable variables for the elements of a list, as in
+/// `let [first, second, ...rest] = value`
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DestructureList {
+    /// The variables for the elements at the start of the list
+    pub elements: Vec<Variable>,
+    /// The variable for the list of the remaining elements, if there is one
+    pub rest: Option<Variable>,
+    pub value: Expression,
+    pub location: Location,
+}
+
+impl From<DestructureList> for Statement {
+    fn from(statement: DestructureList) -> Statement {
+        Statement::DestructureList(statement)
+    }
+}
+
+impl std::fmt::Display for DestructureList {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "let [")?;
+        let mut first = true;
+        for element in &self.elements {
+            if !first {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", element)?;
+            first = false;
+        }
+        if let Some(rest) = &self.rest {
+            if !first {
+                write!(f, ", ")?;
+            }
+            write!(f, "...{}", rest)?;
+        }
+        write!(f, "] = {} at {}", self.value, self.location)
+    }
+}
+
 /// A `var` statement that declares a new mutable variable
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct DeclareMutable {
     pub variable: Variable,
     pub value: Expression,
@@ -293,12 +721,24 @@ impl std::fmt::Display for DeclareMutable {
 }
 
 /// A `print` statement that prints out some debugging information
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Print {
+    /// The level of the message, if one was given.  Messages without a level are at the
+    /// [`Info`][PrintLevel::Info] level.
+    pub level: Option<PrintLevel>,
+    /// The channel that the message is printed on, if any
+    pub channel: Option<Identifier>,
     pub values: Vec<Expression>,
     pub location: Location,
 }
 
+impl Print {
+    /// Returns the level of the message.
+    pub fn level(&self) -> PrintLevel {
+        self.level.unwrap_or(PrintLevel::Info)
+    }
+}
+
 impl From<Print> for Statement {
     fn from(statement: Print) -> Statement {
         Statement::Print(statement)
@@ -308,6 +748,12 @@ impl From<Print> for Statement {
 impl std::fmt::Display for Print {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "print")?;
+        if let Some(level) = self.level {
+            write!(f, " {}", level)?;
+        }
+        if let Some(channel) = &self.channel {
+            write!(f, " {}:", channel)?;
+        }
         for val in &self.values {
             write!(f, " {},", val)?;
         }
@@ -315,11 +761,58 @@ impl std::fmt::Display for Print {
     }
 }
 
+/// The level of a `print` statement, from the most to the least important
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum PrintLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl PrintLevel {
+    /// All of the levels, from the most to the least important.
+    pub const ALL: [PrintLevel; 5] = [
+        PrintLevel::Error,
+        PrintLevel::Warn,
+        PrintLevel::Info,
+        PrintLevel::Debug,
+        PrintLevel::Trace,
+    ];
+
+    /// Returns the level with the given name, as it is written in a `print` statement.
+    pub fn from_name(name: &str) -> Option<PrintLevel> {
+        Self::ALL.iter().copied().find(|level| level.name() == name)
+    }
+
+    /// Returns the name of the level, as it is written in a `print` statement.
+    pub fn name(self) -> &'static str {
+        match self {
+            PrintLevel::Error => "error",
+            PrintLevel::Warn => "warn",
+            PrintLevel::Info => "info",
+            PrintLevel::Debug => "debug",
+            PrintLevel::Trace => "trace",
+        }
+    }
+}
+
+impl std::fmt::Display for PrintLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 /// A `scan` statement that matches regular expressions against a string
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Scan {
     pub value: Expression,
     pub arms: Vec<ScanArm>,
+    /// The variable that is bound to each element in the arms, when the value is a list.  Set in
+    /// checker.
+    #[serde(default)]
+    pub element: Option<UnscopedVariable>,
     pub location: Location,
 }
 
@@ -336,8 +829,9 @@ impl std::fmt::Display for Scan {
 }
 
 /// One arm of a `scan` statement
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ScanArm {
+    #[serde(with = "serde_regex")]
     pub regex: Regex,
     pub statements: Vec<Statement>,
     pub location: Location,
@@ -357,8 +851,86 @@ impl std::fmt::Display for ScanArm {
     }
 }
 
+/// A `match` statement that selects the first arm with a pattern that matches a string
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Match {
+    pub value: Expression,
+    pub arms: Vec<MatchArm>,
+    pub location: Location,
+}
+
+impl From<Match> for Statement {
+    fn from(statement: Match) -> Statement {
+        Statement::Match(statement)
+    }
+}
+
+impl std::fmt::Display for Match {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "match {} {{ ... }} at {}", self.value, self.location)
+    }
+}
+
+/// One arm of a `match` statement
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MatchArm {
+    /// The patterns of the arm, any of which selects it.  The `_` arm, which matches any string,
+    /// has no patterns.
+    pub patterns: Vec<MatchPattern>,
+    pub statements: Vec<Statement>,
+    pub location: Location,
+}
+
+impl std::fmt::Display for MatchArm {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.patterns.is_empty() {
+            return write!(f, "_ => {{ ... }}");
+        }
+        let mut first = true;
+        for pattern in &self.patterns {
+            if first {
+                first = false;
+                write!(f, "{}", pattern)?;
+            } else {
+                write!(f, " | {}", pattern)?;
+            }
+        }
+        write!(f, " => {{ ... }}")
+    }
+}
+
+/// A pattern of a `match` arm
+#[derive(Debug, Deserialize, Serialize)]
+pub enum MatchPattern {
+    /// A string that must be equal to the matched string
+    String(String),
+    /// A regular expression that must match somewhere in the matched string
+    Regex(#[serde(with = "serde_regex")] Regex),
+}
+
+impl Eq for MatchPattern {}
+
+impl PartialEq for MatchPattern {
+    fn eq(&self, other: &MatchPattern) -> bool {
+        match (self, other) {
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for MatchPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::String(string) => write!(f, "{:?}", string),
+            Self::Regex(regex) => write!(f, "/{}/", regex.as_str().replace('/', "\\/")),
+        }
+    }
+}
+
 /// A `cond` conditional statement that selects the first branch with a matching condition
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct If {
     pub arms: Vec<IfArm>,
     pub location: Location,
@@ -390,7 +962,7 @@ impl std::fmt::Display for If {
 }
 
 /// One arm of a `cond` statement
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub struct IfArm {
     pub conditions: Vec<Condition>,
     pub statements: Vec<Statement>,
@@ -399,7 +971,7 @@ pub struct IfArm {
 
 struct DisplayConditions<'a>(&'a Vec<Condition>);
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub enum Condition {
     Some {
         value: Expression,
@@ -447,7 +1019,7 @@ impl std::fmt::Display for Condition {
 }
 
 /// A `for in` statement
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ForIn {
     pub variable: UnscopedVariable,
     pub value: Expression,
@@ -471,8 +1043,78 @@ impl std::fmt::Display for ForIn {
     }
 }
 
+/// A `for in zip` statement, which iterates over several lists in parallel, as in
+/// `for name, value in zip(@names, @values)`
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ForInZip {
+    /// The variables for the elements of each list, in the order of the lists
+    pub variables: Vec<UnscopedVariable>,
+    pub values: Vec<Expression>,
+    pub statements: Vec<Statement>,
+    pub location: Location,
+}
+
+impl From<ForInZip> for Statement {
+    fn from(statement: ForInZip) -> Statement {
+        Statement::ForInZip(statement)
+    }
+}
+
+impl std::fmt::Display for ForInZip {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::fmt::Result {
+        let variables = self
+            .variables
+            .iter()
+            .map(|variable| variable.to_string())
+            .collect::<Vec<_>>();
+        let values = self
+            .values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "for {} in zip({}) {{ ... }} at {}",
+            variables.join(", "),
+            values.join(", "),
+            self.location,
+        )
+    }
+}
+
+/// A statement that a host application adds to the graph DSL, which starts with the keyword of a
+/// [`StatementExtension`][crate::extensions::StatementExtension]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Extension {
+    pub keyword: Identifier,
+    pub arguments: Vec<Expression>,
+    pub location: Location,
+}
+
+impl From<Extension> for Statement {
+    fn from(statement: Extension) -> Statement {
+        Statement::Extension(statement)
+    }
+}
+
+impl std::fmt::Display for Extension {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.keyword)?;
+        let mut first = true;
+        for arg in &self.arguments {
+            if first {
+                first = false;
+                write!(f, " {}", arg)?;
+            } else {
+                write!(f, ", {}", arg)?;
+            }
+        }
+        write!(f, " at {}", self.location)
+    }
+}
+
 /// A reference to a variable
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Variable {
     Scoped(ScopedVariable),
     Unscoped(UnscopedVariable),
@@ -488,10 +1130,13 @@ impl std::fmt::Display for Variable {
 }
 
 /// A reference to a scoped variable
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ScopedVariable {
     pub scope: Box<Expression>,
     pub name: Identifier,
+    /// The value of the variable if it is not defined for the scope, as in `@node.var ?? default`
+    #[serde(default)]
+    pub default: Option<Box<Expression>>,
     pub location: Location,
 }
 
@@ -503,12 +1148,16 @@ impl From<ScopedVariable> for Variable {
 
 impl std::fmt::Display for ScopedVariable {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}.{}", self.scope, self.name)
+        write!(f, "{}.{}", self.scope, self.name)?;
+        if let Some(default) = &self.default {
+            write!(f, " ?? {}", default)?;
+        }
+        Ok(())
     }
 }
 
 /// A reference to a global or local variable
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct UnscopedVariable {
     pub name: Identifier,
     pub location: Location,
@@ -527,7 +1176,7 @@ impl std::fmt::Display for UnscopedVariable {
 }
 
 /// An expression that can appear in a graph DSL file
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Expression {
     // Literals
     FalseLiteral,
@@ -548,6 +1197,8 @@ pub enum Expression {
     Variable(Variable),
     // Functions
     Call(Call),
+    // Graph nodes
+    NewNode(NewNode),
     // Regular expression
     RegexCapture(RegexCapture),
 }
@@ -567,13 +1218,14 @@ impl std::fmt::Display for Expression {
             Expression::Capture(expr) => expr.fmt(f),
             Expression::Variable(expr) => expr.fmt(f),
             Expression::Call(expr) => expr.fmt(f),
+            Expression::NewNode(expr) => expr.fmt(f),
             Expression::RegexCapture(expr) => expr.fmt(f),
         }
     }
 }
 
 /// A function call
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Call {
     pub function: Identifier,
     pub parameters: Vec<Expression>,
@@ -596,11 +1248,12 @@ impl std::fmt::Display for Call {
 }
 
 /// A capture expression that references a syntax node
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Capture {
     /// The name of the capture
     pub name: Identifier,
     /// The suffix of the capture
+    #[serde(with = "serde_quantifier")]
     pub quantifier: CaptureQuantifier,
     /// Capture index in the merged file query
     pub file_capture_index: usize,
@@ -622,9 +1275,9 @@ impl std::fmt::Display for Capture {
 }
 
 /// An integer constant
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct IntegerConstant {
-    pub value: u32,
+    pub value: i64,
 }
 
 impl From<IntegerConstant> for Expression {
@@ -640,7 +1293,7 @@ impl std::fmt::Display for IntegerConstant {
 }
 
 /// An ordered list of values
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ListLiteral {
     pub elements: Vec<Expression>,
 }
@@ -668,11 +1321,14 @@ impl std::fmt::Display for ListLiteral {
 }
 
 /// An list comprehension
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ListComprehension {
     pub element: Box<Expression>,
     pub variable: UnscopedVariable,
     pub value: Box<Expression>,
+    /// The condition that values must satisfy to be included, as in `if (not (is-keyword x))`
+    #[serde(default)]
+    pub condition: Option<Box<Expression>>,
     pub location: Location,
 }
 
@@ -686,16 +1342,51 @@ impl std::fmt::Display for ListComprehension {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "[ {} for {} in {} ]",
+            "[ {} for {} in {}",
             self.element, self.variable, self.value
-        )
+        )?;
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+        write!(f, " ]")
     }
 }
 
-/// A reference to one of the regex captures in a `scan` statement
-#[derive(Debug, Eq, PartialEq)]
+/// An expression that creates a new graph node, as in `(new node)`
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NewNode {
+    pub location: Location,
+}
+
+impl From<NewNode> for Expression {
+    fn from(expr: NewNode) -> Expression {
+        Expression::NewNode(expr)
+    }
+}
+
+impl std::fmt::Display for NewNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "(new node)")
+    }
+}
+
+/// A reference to the regex match of the current arm of a `scan` or `match` statement
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct RegexCapture {
-    pub match_index: usize,
+    pub group: RegexGroup,
+}
+
+/// The part of a regex match that a regex capture refers to
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum RegexGroup {
+    /// A numbered capture group, as in `$1`.  The whole match is `$0`.
+    Index(usize),
+    /// A named capture group, as in `$name`
+    Name(Identifier),
+    /// The offset where the match starts in the scanned string, `$start`
+    Start,
+    /// The offset where the match ends in the scanned string, `$end`
+    End,
 }
 
 impl From<RegexCapture> for Expression {
@@ -706,12 +1397,17 @@ impl From<RegexCapture> for Expression {
 
 impl std::fmt::Display for RegexCapture {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "${}", self.match_index)
+        match &self.group {
+            RegexGroup::Index(index) => write!(f, "${}", index),
+            RegexGroup::Name(name) => write!(f, "${}", name),
+            RegexGroup::Start => write!(f, "$start"),
+            RegexGroup::End => write!(f, "$end"),
+        }
     }
 }
 
 /// An unordered set of values
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct SetLiteral {
     pub elements: Vec<Expression>,
 }
@@ -739,11 +1435,14 @@ impl std::fmt::Display for SetLiteral {
 }
 
 /// An set comprehension
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct SetComprehension {
     pub element: Box<Expression>,
     pub variable: UnscopedVariable,
     pub value: Box<Expression>,
+    /// The condition that values must satisfy to be included, as in `if (not (is-keyword x))`
+    #[serde(default)]
+    pub condition: Option<Box<Expression>>,
     pub location: Location,
 }
 
@@ -757,14 +1456,18 @@ impl std::fmt::Display for SetComprehension {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "{{ {} for {} in {} }}",
+            "{{ {} for {} in {}",
             self.element, self.variable, self.value
-        )
+        )?;
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+        write!(f, " }}")
     }
 }
 
 /// A string constant
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct StringConstant {
     pub value: String,
 }
@@ -800,7 +1503,7 @@ impl From<ScopedVariable> for Expression {
 }
 
 /// Attribute shorthands
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct AttributeShorthands(HashMap<Identifier, AttributeShorthand>);
 
 impl AttributeShorthands {
@@ -820,13 +1523,17 @@ impl AttributeShorthands {
         self.0.values()
     }
 
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut AttributeShorthand> {
+        self.0.values_mut()
+    }
+
     pub fn into_iter(self) -> impl Iterator<Item = AttributeShorthand> {
         self.0.into_values()
     }
 }
 
 /// An attribute shorthand
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct AttributeShorthand {
     pub name: Identifier,
     pub variable: UnscopedVariable,
@@ -843,3 +1550,59 @@ impl std::fmt::Display for AttributeShorthand {
         write!(f, " at {}", self.location)
     }
 }
+
+/// Serializes regular expressions as their source.
+mod serde_regex {
+    use regex::Regex;
+    use serde::de::Error as _;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(regex.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Regex::new(&source).map_err(D::Error::custom)
+    }
+}
+
+/// Serializes capture quantifiers as their names.
+mod serde_quantifier {
+    use serde::de::Error as _;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+    use tree_sitter::CaptureQuantifier;
+
+    pub fn serialize<S: Serializer>(
+        quantifier: &CaptureQuantifier,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match quantifier {
+            CaptureQuantifier::Zero => "Zero",
+            CaptureQuantifier::One => "One",
+            CaptureQuantifier::ZeroOrOne => "ZeroOrOne",
+            CaptureQuantifier::ZeroOrMore => "ZeroOrMore",
+            CaptureQuantifier::OneOrMore => "OneOrMore",
+        })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<CaptureQuantifier, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "Zero" => Ok(CaptureQuantifier::Zero),
+            "One" => Ok(CaptureQuantifier::One),
+            "ZeroOrOne" => Ok(CaptureQuantifier::ZeroOrOne),
+            "ZeroOrMore" => Ok(CaptureQuantifier::ZeroOrMore),
+            "OneOrMore" => Ok(CaptureQuantifier::OneOrMore),
+            other => Err(D::Error::unknown_variant(
+                other,
+                &["Zero", "One", "ZeroOrOne", "ZeroOrMore", "OneOrMore"],
+            )),
+        }
+    }
+}
@@ -0,0 +1,559 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Traverses the AST of a graph DSL file by reference.
+//!
+//! Each method of [`Visitor`][] visits the children of its node by calling the `walk_` function
+//! of the same name.  Implementations override the methods for the nodes they are interested in,
+//! and call the `walk_` function from their override to keep traversing the children.
+//!
+//! ```
+//! use tree_sitter_graph::ast::visit;
+//! use tree_sitter_graph::ast::Call;
+//! use tree_sitter_graph::ast::Visitor;
+//! use tree_sitter_graph::Identifier;
+//!
+//! /// Collects the names of the functions that a file calls.
+//! #[derive(Default)]
+//! struct Calls<'ast> {
+//!     functions: Vec<&'ast Identifier>,
+//! }
+//!
+//! impl<'ast> Visitor<'ast> for Calls<'ast> {
+//!     fn visit_call(&mut self, call: &'ast Call) {
+//!         self.functions.push(&call.function);
+//!         visit::walk_call(self, call);
+//!     }
+//! }
+//! ```
+//!
+//! Children are visited in the order in which they appear in the source of the file, except for
+//! attribute shorthands, which are visited in no particular order.
+
+use crate::ast::AddEdgeAttribute;
+use crate::ast::AddGraphNodeAttribute;
+use crate::ast::Assign;
+use crate::ast::Attribute;
+use crate::ast::AttributeShorthand;
+use crate::ast::Call;
+use crate::ast::Capture;
+use crate::ast::Condition;
+use crate::ast::CreateEdge;
+use crate::ast::CreateGraphNode;
+use crate::ast::DeclareImmutable;
+use crate::ast::DeclareMutable;
+use crate::ast::DestructureList;
+use crate::ast::Expression;
+use crate::ast::Extension;
+use crate::ast::File;
+use crate::ast::ForIn;
+use crate::ast::ForInZip;
+use crate::ast::Global;
+use crate::ast::If;
+use crate::ast::IfArm;
+use crate::ast::IntegerConstant;
+use crate::ast::ListComprehension;
+use crate::ast::ListLiteral;
+use crate::ast::Match;
+use crate::ast::MatchArm;
+use crate::ast::NewNode;
+use crate::ast::Print;
+use crate::ast::RegexCapture;
+use crate::ast::Scan;
+use crate::ast::ScanArm;
+use crate::ast::ScopedVariable;
+use crate::ast::SetComprehension;
+use crate::ast::SetLiteral;
+use crate::ast::Stanza;
+use crate::ast::Statement;
+use crate::ast::StringConstant;
+use crate::ast::UnscopedVariable;
+use crate::ast::Variable;
+
+/// Visits the nodes of the AST of a graph DSL file by reference.  The `'ast` lifetime lets
+/// visitors keep references to the nodes that they visit.
+pub trait Visitor<'ast> {
+    fn visit_file(&mut self, file: &'ast File) {
+        walk_file(self, file)
+    }
+
+    fn visit_global(&mut self, global: &'ast Global) {
+        walk_global(self, global)
+    }
+
+    fn visit_attribute_shorthand(&mut self, shorthand: &'ast AttributeShorthand) {
+        walk_attribute_shorthand(self, shorthand)
+    }
+
+    fn visit_stanza(&mut self, stanza: &'ast Stanza) {
+        walk_stanza(self, stanza)
+    }
+
+    fn visit_statement(&mut self, stmt: &'ast Statement) {
+        walk_statement(self, stmt)
+    }
+
+    fn visit_declare_immutable(&mut self, stmt: &'ast DeclareImmutable) {
+        walk_declare_immutable(self, stmt)
+    }
+
+    fn visit_declare_mutable(&mut self, stmt: &'ast DeclareMutable) {
+        walk_declare_mutable(self, stmt)
+    }
+
+    fn visit_destructure_list(&mut self, stmt: &'ast DestructureList) {
+        walk_destructure_list(self, stmt)
+    }
+
+    fn visit_assign(&mut self, stmt: &'ast Assign) {
+        walk_assign(self, stmt)
+    }
+
+    fn visit_create_graph_node(&mut self, stmt: &'ast CreateGraphNode) {
+        walk_create_graph_node(self, stmt)
+    }
+
+    fn visit_add_graph_node_attribute(&mut self, stmt: &'ast AddGraphNodeAttribute) {
+        walk_add_graph_node_attribute(self, stmt)
+    }
+
+    fn visit_create_edge(&mut self, stmt: &'ast CreateEdge) {
+        walk_create_edge(self, stmt)
+    }
+
+    fn visit_add_edge_attribute(&mut self, stmt: &'ast AddEdgeAttribute) {
+        walk_add_edge_attribute(self, stmt)
+    }
+
+    fn visit_scan(&mut self, stmt: &'ast Scan) {
+        walk_scan(self, stmt)
+    }
+
+    fn visit_scan_arm(&mut self, arm: &'ast ScanArm) {
+        walk_scan_arm(self, arm)
+    }
+
+    fn visit_print(&mut self, stmt: &'ast Print) {
+        walk_print(self, stmt)
+    }
+
+    fn visit_if(&mut self, stmt: &'ast If) {
+        walk_if(self, stmt)
+    }
+
+    fn visit_if_arm(&mut self, arm: &'ast IfArm) {
+        walk_if_arm(self, arm)
+    }
+
+    fn visit_condition(&mut self, condition: &'ast Condition) {
+        walk_condition(self, condition)
+    }
+
+    fn visit_match(&mut self, stmt: &'ast Match) {
+        walk_match(self, stmt)
+    }
+
+    fn visit_match_arm(&mut self, arm: &'ast MatchArm) {
+        walk_match_arm(self, arm)
+    }
+
+    fn visit_for_in(&mut self, stmt: &'ast ForIn) {
+        walk_for_in(self, stmt)
+    }
+
+    fn visit_for_in_zip(&mut self, stmt: &'ast ForInZip) {
+        walk_for_in_zip(self, stmt)
+    }
+
+    fn visit_extension(&mut self, stmt: &'ast Extension) {
+        walk_extension(self, stmt)
+    }
+
+    fn visit_attribute(&mut self, attribute: &'ast Attribute) {
+        walk_attribute(self, attribute)
+    }
+
+    fn visit_variable(&mut self, variable: &'ast Variable) {
+        walk_variable(self, variable)
+    }
+
+    fn visit_scoped_variable(&mut self, variable: &'ast ScopedVariable) {
+        walk_scoped_variable(self, variable)
+    }
+
+    fn visit_unscoped_variable(&mut self, variable: &'ast UnscopedVariable) {
+        walk_unscoped_variable(self, variable)
+    }
+
+    fn visit_expression(&mut self, expr: &'ast Expression) {
+        walk_expression(self, expr)
+    }
+
+    fn visit_integer_constant(&mut self, expr: &'ast IntegerConstant) {
+        walk_integer_constant(self, expr)
+    }
+
+    fn visit_string_constant(&mut self, expr: &'ast StringConstant) {
+        walk_string_constant(self, expr)
+    }
+
+    fn visit_list_literal(&mut self, expr: &'ast ListLiteral) {
+        walk_list_literal(self, expr)
+    }
+
+    fn visit_set_literal(&mut self, expr: &'ast SetLiteral) {
+        walk_set_literal(self, expr)
+    }
+
+    fn visit_list_comprehension(&mut self, expr: &'ast ListComprehension) {
+        walk_list_comprehension(self, expr)
+    }
+
+    fn visit_set_comprehension(&mut self, expr: &'ast SetComprehension) {
+        walk_set_comprehension(self, expr)
+    }
+
+    fn visit_capture(&mut self, expr: &'ast Capture) {
+        walk_capture(self, expr)
+    }
+
+    fn visit_call(&mut self, expr: &'ast Call) {
+        walk_call(self, expr)
+    }
+
+    fn visit_new_node(&mut self, expr: &'ast NewNode) {
+        walk_new_node(self, expr)
+    }
+
+    fn visit_regex_capture(&mut self, expr: &'ast RegexCapture) {
+        walk_regex_capture(self, expr)
+    }
+}
+
+pub fn walk_file<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, file: &'ast File) {
+    for global in &file.globals {
+        visitor.visit_global(global);
+    }
+    for shorthand in file.shorthands.iter() {
+        visitor.visit_attribute_shorthand(shorthand);
+    }
+    for stanza in &file.stanzas {
+        visitor.visit_stanza(stanza);
+    }
+}
+
+pub fn walk_global<'ast, V: Visitor<'ast> + ?Sized>(_visitor: &mut V, _global: &'ast Global) {}
+
+pub fn walk_attribute_shorthand<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    shorthand: &'ast AttributeShorthand,
+) {
+    visitor.visit_unscoped_variable(&shorthand.variable);
+    for attribute in &shorthand.attributes {
+        visitor.visit_attribute(attribute);
+    }
+}
+
+pub fn walk_stanza<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stanza: &'ast Stanza) {
+    if let Some(guard) = &stanza.guard {
+        visitor.visit_expression(guard);
+    }
+    for stmt in &stanza.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast Statement) {
+    match stmt {
+        Statement::DeclareImmutable(stmt) => visitor.visit_declare_immutable(stmt),
+        Statement::DeclareMutable(stmt) => visitor.visit_declare_mutable(stmt),
+        Statement::DestructureList(stmt) => visitor.visit_destructure_list(stmt),
+        Statement::Assign(stmt) => visitor.visit_assign(stmt),
+        Statement::CreateGraphNode(stmt) => visitor.visit_create_graph_node(stmt),
+        Statement::AddGraphNodeAttribute(stmt) => visitor.visit_add_graph_node_attribute(stmt),
+        Statement::CreateEdge(stmt) => visitor.visit_create_edge(stmt),
+        Statement::AddEdgeAttribute(stmt) => visitor.visit_add_edge_attribute(stmt),
+        Statement::Scan(stmt) => visitor.visit_scan(stmt),
+        Statement::Print(stmt) => visitor.visit_print(stmt),
+        Statement::If(stmt) => visitor.visit_if(stmt),
+        Statement::Match(stmt) => visitor.visit_match(stmt),
+        Statement::ForIn(stmt) => visitor.visit_for_in(stmt),
+        Statement::ForInZip(stmt) => visitor.visit_for_in_zip(stmt),
+        Statement::Extension(stmt) => visitor.visit_extension(stmt),
+    }
+}
+
+pub fn walk_declare_immutable<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast DeclareImmutable,
+) {
+    visitor.visit_variable(&stmt.variable);
+    visitor.visit_expression(&stmt.value);
+}
+
+pub fn walk_declare_mutable<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast DeclareMutable,
+) {
+    visitor.visit_variable(&stmt.variable);
+    visitor.visit_expression(&stmt.value);
+}
+
+pub fn walk_destructure_list<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast DestructureList,
+) {
+    for element in &stmt.elements {
+        visitor.visit_variable(element);
+    }
+    if let Some(rest) = &stmt.rest {
+        visitor.visit_variable(rest);
+    }
+    visitor.visit_expression(&stmt.value);
+}
+
+pub fn walk_assign<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast Assign) {
+    visitor.visit_variable(&stmt.variable);
+    visitor.visit_expression(&stmt.value);
+}
+
+pub fn walk_create_graph_node<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast CreateGraphNode,
+) {
+    visitor.visit_variable(&stmt.node);
+    if let Some(label) = &stmt.label {
+        visitor.visit_expression(label);
+    }
+}
+
+pub fn walk_add_graph_node_attribute<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast AddGraphNodeAttribute,
+) {
+    visitor.visit_expression(&stmt.node);
+    for attribute in &stmt.attributes {
+        visitor.visit_attribute(attribute);
+    }
+}
+
+pub fn walk_create_edge<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast CreateEdge) {
+    if let Some(variable) = &stmt.variable {
+        visitor.visit_variable(variable);
+    }
+    visitor.visit_expression(&stmt.source);
+    visitor.visit_expression(&stmt.sink);
+}
+
+pub fn walk_add_edge_attribute<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    stmt: &'ast AddEdgeAttribute,
+) {
+    visitor.visit_expression(&stmt.source);
+    visitor.visit_expression(&stmt.sink);
+    for attribute in &stmt.attributes {
+        visitor.visit_attribute(attribute);
+    }
+}
+
+pub fn walk_scan<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast Scan) {
+    visitor.visit_expression(&stmt.value);
+    for arm in &stmt.arms {
+        visitor.visit_scan_arm(arm);
+    }
+}
+
+pub fn walk_scan_arm<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, arm: &'ast ScanArm) {
+    for stmt in &arm.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_print<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast Print) {
+    for value in &stmt.values {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_if<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast If) {
+    for arm in &stmt.arms {
+        visitor.visit_if_arm(arm);
+    }
+}
+
+pub fn walk_if_arm<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, arm: &'ast IfArm) {
+    for condition in &arm.conditions {
+        visitor.visit_condition(condition);
+    }
+    for stmt in &arm.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_condition<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    condition: &'ast Condition,
+) {
+    match condition {
+        Condition::Some { value, .. }
+        | Condition::None { value, .. }
+        | Condition::Bool { value, .. } => visitor.visit_expression(value),
+    }
+}
+
+pub fn walk_match<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast Match) {
+    visitor.visit_expression(&stmt.value);
+    for arm in &stmt.arms {
+        visitor.visit_match_arm(arm);
+    }
+}
+
+pub fn walk_match_arm<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, arm: &'ast MatchArm) {
+    for stmt in &arm.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_for_in<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast ForIn) {
+    visitor.visit_unscoped_variable(&stmt.variable);
+    visitor.visit_expression(&stmt.value);
+    for stmt in &stmt.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_for_in_zip<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast ForInZip) {
+    for variable in &stmt.variables {
+        visitor.visit_unscoped_variable(variable);
+    }
+    for value in &stmt.values {
+        visitor.visit_expression(value);
+    }
+    for stmt in &stmt.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_extension<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast Extension) {
+    for argument in &stmt.arguments {
+        visitor.visit_expression(argument);
+    }
+}
+
+pub fn walk_attribute<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    attribute: &'ast Attribute,
+) {
+    visitor.visit_expression(&attribute.value);
+}
+
+pub fn walk_variable<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, variable: &'ast Variable) {
+    match variable {
+        Variable::Scoped(variable) => visitor.visit_scoped_variable(variable),
+        Variable::Unscoped(variable) => visitor.visit_unscoped_variable(variable),
+    }
+}
+
+pub fn walk_scoped_variable<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    variable: &'ast ScopedVariable,
+) {
+    visitor.visit_expression(&variable.scope);
+    if let Some(default) = &variable.default {
+        visitor.visit_expression(default);
+    }
+}
+
+pub fn walk_unscoped_variable<'ast, V: Visitor<'ast> + ?Sized>(
+    _visitor: &mut V,
+    _variable: &'ast UnscopedVariable,
+) {
+}
+
+pub fn walk_expression<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Expression) {
+    match expr {
+        Expression::FalseLiteral | Expression::NullLiteral | Expression::TrueLiteral => {}
+        Expression::IntegerConstant(expr) => visitor.visit_integer_constant(expr),
+        Expression::StringConstant(expr) => visitor.visit_string_constant(expr),
+        Expression::ListLiteral(expr) => visitor.visit_list_literal(expr),
+        Expression::SetLiteral(expr) => visitor.visit_set_literal(expr),
+        Expression::ListComprehension(expr) => visitor.visit_list_comprehension(expr),
+        Expression::SetComprehension(expr) => visitor.visit_set_comprehension(expr),
+        Expression::Capture(expr) => visitor.visit_capture(expr),
+        Expression::Variable(expr) => visitor.visit_variable(expr),
+        Expression::Call(expr) => visitor.visit_call(expr),
+        Expression::NewNode(expr) => visitor.visit_new_node(expr),
+        Expression::RegexCapture(expr) => visitor.visit_regex_capture(expr),
+    }
+}
+
+pub fn walk_integer_constant<'ast, V: Visitor<'ast> + ?Sized>(
+    _visitor: &mut V,
+    _expr: &'ast IntegerConstant,
+) {
+}
+
+pub fn walk_string_constant<'ast, V: Visitor<'ast> + ?Sized>(
+    _visitor: &mut V,
+    _expr: &'ast StringConstant,
+) {
+}
+
+pub fn walk_list_literal<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast ListLiteral,
+) {
+    for element in &expr.elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_set_literal<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast SetLiteral) {
+    for element in &expr.elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_list_comprehension<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast ListComprehension,
+) {
+    visitor.visit_expression(&expr.element);
+    visitor.visit_unscoped_variable(&expr.variable);
+    visitor.visit_expression(&expr.value);
+    if let Some(condition) = &expr.condition {
+        visitor.visit_expression(condition);
+    }
+}
+
+pub fn walk_set_comprehension<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast SetComprehension,
+) {
+    visitor.visit_expression(&expr.element);
+    visitor.visit_unscoped_variable(&expr.variable);
+    visitor.visit_expression(&expr.value);
+    if let Some(condition) = &expr.condition {
+        visitor.visit_expression(condition);
+    }
+}
+
+pub fn walk_capture<'ast, V: Visitor<'ast> + ?Sized>(_visitor: &mut V, _expr: &'ast Capture) {}
+
+pub fn walk_call<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Call) {
+    for parameter in &expr.parameters {
+        visitor.visit_expression(parameter);
+    }
+}
+
+pub fn walk_new_node<'ast, V: Visitor<'ast> + ?Sized>(_visitor: &mut V, _expr: &'ast NewNode) {}
+
+pub fn walk_regex_capture<'ast, V: Visitor<'ast> + ?Sized>(
+    _visitor: &mut V,
+    _expr: &'ast RegexCapture,
+) {
+}
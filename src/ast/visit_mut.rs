@@ -0,0 +1,537 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Traverses the AST of a graph DSL file by mutable reference, so that it can be rewritten in
+//! place.
+//!
+//! Each method of [`VisitorMut`][] visits the children of its node by calling the `walk_`
+//! function of the same name.  Implementations override the methods for the nodes they are
+//! interested in, and call the `walk_` function from their override to keep traversing the
+//! children.
+//!
+//! ```
+//! use tree_sitter_graph::ast::visit_mut;
+//! use tree_sitter_graph::ast::Call;
+//! use tree_sitter_graph::ast::VisitorMut;
+//! use tree_sitter_graph::Identifier;
+//!
+//! /// Renames a function in every call to it.
+//! struct Rename {
+//!     from: Identifier,
+//!     to: Identifier,
+//! }
+//!
+//! impl VisitorMut for Rename {
+//!     fn visit_call(&mut self, call: &mut Call) {
+//!         if call.function == self.from {
+//!             call.function = self.to.clone();
+//!         }
+//!         visit_mut::walk_call(self, call);
+//!     }
+//! }
+//! ```
+//!
+//! Rewrites must keep the file valid: the checker is not run again, so for example captures must
+//! keep the indices of their queries.  Children are visited in the same order as by
+//! [`Visitor`][crate::ast::Visitor].
+
+use crate::ast::AddEdgeAttribute;
+use crate::ast::AddGraphNodeAttribute;
+use crate::ast::Assign;
+use crate::ast::Attribute;
+use crate::ast::AttributeShorthand;
+use crate::ast::Call;
+use crate::ast::Capture;
+use crate::ast::Condition;
+use crate::ast::CreateEdge;
+use crate::ast::CreateGraphNode;
+use crate::ast::DeclareImmutable;
+use crate::ast::DeclareMutable;
+use crate::ast::DestructureList;
+use crate::ast::Expression;
+use crate::ast::Extension;
+use crate::ast::File;
+use crate::ast::ForIn;
+use crate::ast::ForInZip;
+use crate::ast::Global;
+use crate::ast::If;
+use crate::ast::IfArm;
+use crate::ast::IntegerConstant;
+use crate::ast::ListComprehension;
+use crate::ast::ListLiteral;
+use crate::ast::Match;
+use crate::ast::MatchArm;
+use crate::ast::NewNode;
+use crate::ast::Print;
+use crate::ast::RegexCapture;
+use crate::ast::Scan;
+use crate::ast::ScanArm;
+use crate::ast::ScopedVariable;
+use crate::ast::SetComprehension;
+use crate::ast::SetLiteral;
+use crate::ast::Stanza;
+use crate::ast::Statement;
+use crate::ast::StringConstant;
+use crate::ast::UnscopedVariable;
+use crate::ast::Variable;
+
+/// Visits the nodes of the AST of a graph DSL file by mutable reference.
+pub trait VisitorMut {
+    fn visit_file(&mut self, file: &mut File) {
+        walk_file(self, file)
+    }
+
+    fn visit_global(&mut self, global: &mut Global) {
+        walk_global(self, global)
+    }
+
+    fn visit_attribute_shorthand(&mut self, shorthand: &mut AttributeShorthand) {
+        walk_attribute_shorthand(self, shorthand)
+    }
+
+    fn visit_stanza(&mut self, stanza: &mut Stanza) {
+        walk_stanza(self, stanza)
+    }
+
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        walk_statement(self, stmt)
+    }
+
+    fn visit_declare_immutable(&mut self, stmt: &mut DeclareImmutable) {
+        walk_declare_immutable(self, stmt)
+    }
+
+    fn visit_declare_mutable(&mut self, stmt: &mut DeclareMutable) {
+        walk_declare_mutable(self, stmt)
+    }
+
+    fn visit_destructure_list(&mut self, stmt: &mut DestructureList) {
+        walk_destructure_list(self, stmt)
+    }
+
+    fn visit_assign(&mut self, stmt: &mut Assign) {
+        walk_assign(self, stmt)
+    }
+
+    fn visit_create_graph_node(&mut self, stmt: &mut CreateGraphNode) {
+        walk_create_graph_node(self, stmt)
+    }
+
+    fn visit_add_graph_node_attribute(&mut self, stmt: &mut AddGraphNodeAttribute) {
+        walk_add_graph_node_attribute(self, stmt)
+    }
+
+    fn visit_create_edge(&mut self, stmt: &mut CreateEdge) {
+        walk_create_edge(self, stmt)
+    }
+
+    fn visit_add_edge_attribute(&mut self, stmt: &mut AddEdgeAttribute) {
+        walk_add_edge_attribute(self, stmt)
+    }
+
+    fn visit_scan(&mut self, stmt: &mut Scan) {
+        walk_scan(self, stmt)
+    }
+
+    fn visit_scan_arm(&mut self, arm: &mut ScanArm) {
+        walk_scan_arm(self, arm)
+    }
+
+    fn visit_print(&mut self, stmt: &mut Print) {
+        walk_print(self, stmt)
+    }
+
+    fn visit_if(&mut self, stmt: &mut If) {
+        walk_if(self, stmt)
+    }
+
+    fn visit_if_arm(&mut self, arm: &mut IfArm) {
+        walk_if_arm(self, arm)
+    }
+
+    fn visit_condition(&mut self, condition: &mut Condition) {
+        walk_condition(self, condition)
+    }
+
+    fn visit_match(&mut self, stmt: &mut Match) {
+        walk_match(self, stmt)
+    }
+
+    fn visit_match_arm(&mut self, arm: &mut MatchArm) {
+        walk_match_arm(self, arm)
+    }
+
+    fn visit_for_in(&mut self, stmt: &mut ForIn) {
+        walk_for_in(self, stmt)
+    }
+
+    fn visit_for_in_zip(&mut self, stmt: &mut ForInZip) {
+        walk_for_in_zip(self, stmt)
+    }
+
+    fn visit_extension(&mut self, stmt: &mut Extension) {
+        walk_extension(self, stmt)
+    }
+
+    fn visit_attribute(&mut self, attribute: &mut Attribute) {
+        walk_attribute(self, attribute)
+    }
+
+    fn visit_variable(&mut self, variable: &mut Variable) {
+        walk_variable(self, variable)
+    }
+
+    fn visit_scoped_variable(&mut self, variable: &mut ScopedVariable) {
+        walk_scoped_variable(self, variable)
+    }
+
+    fn visit_unscoped_variable(&mut self, variable: &mut UnscopedVariable) {
+        walk_unscoped_variable(self, variable)
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        walk_expression(self, expr)
+    }
+
+    fn visit_integer_constant(&mut self, expr: &mut IntegerConstant) {
+        walk_integer_constant(self, expr)
+    }
+
+    fn visit_string_constant(&mut self, expr: &mut StringConstant) {
+        walk_string_constant(self, expr)
+    }
+
+    fn visit_list_literal(&mut self, expr: &mut ListLiteral) {
+        walk_list_literal(self, expr)
+    }
+
+    fn visit_set_literal(&mut self, expr: &mut SetLiteral) {
+        walk_set_literal(self, expr)
+    }
+
+    fn visit_list_comprehension(&mut self, expr: &mut ListComprehension) {
+        walk_list_comprehension(self, expr)
+    }
+
+    fn visit_set_comprehension(&mut self, expr: &mut SetComprehension) {
+        walk_set_comprehension(self, expr)
+    }
+
+    fn visit_capture(&mut self, expr: &mut Capture) {
+        walk_capture(self, expr)
+    }
+
+    fn visit_call(&mut self, expr: &mut Call) {
+        walk_call(self, expr)
+    }
+
+    fn visit_new_node(&mut self, expr: &mut NewNode) {
+        walk_new_node(self, expr)
+    }
+
+    fn visit_regex_capture(&mut self, expr: &mut RegexCapture) {
+        walk_regex_capture(self, expr)
+    }
+}
+
+pub fn walk_file<V: VisitorMut + ?Sized>(visitor: &mut V, file: &mut File) {
+    for global in &mut file.globals {
+        visitor.visit_global(global);
+    }
+    for shorthand in file.shorthands.iter_mut() {
+        visitor.visit_attribute_shorthand(shorthand);
+    }
+    for stanza in &mut file.stanzas {
+        visitor.visit_stanza(stanza);
+    }
+}
+
+pub fn walk_global<V: VisitorMut + ?Sized>(_visitor: &mut V, _global: &mut Global) {}
+
+pub fn walk_attribute_shorthand<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    shorthand: &mut AttributeShorthand,
+) {
+    visitor.visit_unscoped_variable(&mut shorthand.variable);
+    for attribute in &mut shorthand.attributes {
+        visitor.visit_attribute(attribute);
+    }
+}
+
+pub fn walk_stanza<V: VisitorMut + ?Sized>(visitor: &mut V, stanza: &mut Stanza) {
+    if let Some(guard) = &mut stanza.guard {
+        visitor.visit_expression(guard);
+    }
+    for stmt in &mut stanza.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Statement) {
+    match stmt {
+        Statement::DeclareImmutable(stmt) => visitor.visit_declare_immutable(stmt),
+        Statement::DeclareMutable(stmt) => visitor.visit_declare_mutable(stmt),
+        Statement::DestructureList(stmt) => visitor.visit_destructure_list(stmt),
+        Statement::Assign(stmt) => visitor.visit_assign(stmt),
+        Statement::CreateGraphNode(stmt) => visitor.visit_create_graph_node(stmt),
+        Statement::AddGraphNodeAttribute(stmt) => visitor.visit_add_graph_node_attribute(stmt),
+        Statement::CreateEdge(stmt) => visitor.visit_create_edge(stmt),
+        Statement::AddEdgeAttribute(stmt) => visitor.visit_add_edge_attribute(stmt),
+        Statement::Scan(stmt) => visitor.visit_scan(stmt),
+        Statement::Print(stmt) => visitor.visit_print(stmt),
+        Statement::If(stmt) => visitor.visit_if(stmt),
+        Statement::Match(stmt) => visitor.visit_match(stmt),
+        Statement::ForIn(stmt) => visitor.visit_for_in(stmt),
+        Statement::ForInZip(stmt) => visitor.visit_for_in_zip(stmt),
+        Statement::Extension(stmt) => visitor.visit_extension(stmt),
+    }
+}
+
+pub fn walk_declare_immutable<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    stmt: &mut DeclareImmutable,
+) {
+    visitor.visit_variable(&mut stmt.variable);
+    visitor.visit_expression(&mut stmt.value);
+}
+
+pub fn walk_declare_mutable<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut DeclareMutable) {
+    visitor.visit_variable(&mut stmt.variable);
+    visitor.visit_expression(&mut stmt.value);
+}
+
+pub fn walk_destructure_list<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut DestructureList) {
+    for element in &mut stmt.elements {
+        visitor.visit_variable(element);
+    }
+    if let Some(rest) = &mut stmt.rest {
+        visitor.visit_variable(rest);
+    }
+    visitor.visit_expression(&mut stmt.value);
+}
+
+pub fn walk_assign<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Assign) {
+    visitor.visit_variable(&mut stmt.variable);
+    visitor.visit_expression(&mut stmt.value);
+}
+
+pub fn walk_create_graph_node<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut CreateGraphNode) {
+    visitor.visit_variable(&mut stmt.node);
+    if let Some(label) = &mut stmt.label {
+        visitor.visit_expression(label);
+    }
+}
+
+pub fn walk_add_graph_node_attribute<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    stmt: &mut AddGraphNodeAttribute,
+) {
+    visitor.visit_expression(&mut stmt.node);
+    for attribute in &mut stmt.attributes {
+        visitor.visit_attribute(attribute);
+    }
+}
+
+pub fn walk_create_edge<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut CreateEdge) {
+    if let Some(variable) = &mut stmt.variable {
+        visitor.visit_variable(variable);
+    }
+    visitor.visit_expression(&mut stmt.source);
+    visitor.visit_expression(&mut stmt.sink);
+}
+
+pub fn walk_add_edge_attribute<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    stmt: &mut AddEdgeAttribute,
+) {
+    visitor.visit_expression(&mut stmt.source);
+    visitor.visit_expression(&mut stmt.sink);
+    for attribute in &mut stmt.attributes {
+        visitor.visit_attribute(attribute);
+    }
+}
+
+pub fn walk_scan<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Scan) {
+    visitor.visit_expression(&mut stmt.value);
+    for arm in &mut stmt.arms {
+        visitor.visit_scan_arm(arm);
+    }
+}
+
+pub fn walk_scan_arm<V: VisitorMut + ?Sized>(visitor: &mut V, arm: &mut ScanArm) {
+    for stmt in &mut arm.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_print<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Print) {
+    for value in &mut stmt.values {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_if<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut If) {
+    for arm in &mut stmt.arms {
+        visitor.visit_if_arm(arm);
+    }
+}
+
+pub fn walk_if_arm<V: VisitorMut + ?Sized>(visitor: &mut V, arm: &mut IfArm) {
+    for condition in &mut arm.conditions {
+        visitor.visit_condition(condition);
+    }
+    for stmt in &mut arm.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_condition<V: VisitorMut + ?Sized>(visitor: &mut V, condition: &mut Condition) {
+    match condition {
+        Condition::Some { value, .. }
+        | Condition::None { value, .. }
+        | Condition::Bool { value, .. } => visitor.visit_expression(value),
+    }
+}
+
+pub fn walk_match<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Match) {
+    visitor.visit_expression(&mut stmt.value);
+    for arm in &mut stmt.arms {
+        visitor.visit_match_arm(arm);
+    }
+}
+
+pub fn walk_match_arm<V: VisitorMut + ?Sized>(visitor: &mut V, arm: &mut MatchArm) {
+    for stmt in &mut arm.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_for_in<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut ForIn) {
+    visitor.visit_unscoped_variable(&mut stmt.variable);
+    visitor.visit_expression(&mut stmt.value);
+    for stmt in &mut stmt.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_for_in_zip<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut ForInZip) {
+    for variable in &mut stmt.variables {
+        visitor.visit_unscoped_variable(variable);
+    }
+    for value in &mut stmt.values {
+        visitor.visit_expression(value);
+    }
+    for stmt in &mut stmt.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_extension<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Extension) {
+    for argument in &mut stmt.arguments {
+        visitor.visit_expression(argument);
+    }
+}
+
+pub fn walk_attribute<V: VisitorMut + ?Sized>(visitor: &mut V, attribute: &mut Attribute) {
+    visitor.visit_expression(&mut attribute.value);
+}
+
+pub fn walk_variable<V: VisitorMut + ?Sized>(visitor: &mut V, variable: &mut Variable) {
+    match variable {
+        Variable::Scoped(variable) => visitor.visit_scoped_variable(variable),
+        Variable::Unscoped(variable) => visitor.visit_unscoped_variable(variable),
+    }
+}
+
+pub fn walk_scoped_variable<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    variable: &mut ScopedVariable,
+) {
+    visitor.visit_expression(&mut variable.scope);
+    if let Some(default) = &mut variable.default {
+        visitor.visit_expression(default);
+    }
+}
+
+pub fn walk_unscoped_variable<V: VisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _variable: &mut UnscopedVariable,
+) {
+}
+
+pub fn walk_expression<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::FalseLiteral | Expression::NullLiteral | Expression::TrueLiteral => {}
+        Expression::IntegerConstant(expr) => visitor.visit_integer_constant(expr),
+        Expression::StringConstant(expr) => visitor.visit_string_constant(expr),
+        Expression::ListLiteral(expr) => visitor.visit_list_literal(expr),
+        Expression::SetLiteral(expr) => visitor.visit_set_literal(expr),
+        Expression::ListComprehension(expr) => visitor.visit_list_comprehension(expr),
+        Expression::SetComprehension(expr) => visitor.visit_set_comprehension(expr),
+        Expression::Capture(expr) => visitor.visit_capture(expr),
+        Expression::Variable(expr) => visitor.visit_variable(expr),
+        Expression::Call(expr) => visitor.visit_call(expr),
+        Expression::NewNode(expr) => visitor.visit_new_node(expr),
+        Expression::RegexCapture(expr) => visitor.visit_regex_capture(expr),
+    }
+}
+
+pub fn walk_integer_constant<V: VisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _expr: &mut IntegerConstant,
+) {
+}
+
+pub fn walk_string_constant<V: VisitorMut + ?Sized>(_visitor: &mut V, _expr: &mut StringConstant) {}
+
+pub fn walk_list_literal<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut ListLiteral) {
+    for element in &mut expr.elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_set_literal<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut SetLiteral) {
+    for element in &mut expr.elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_list_comprehension<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &mut ListComprehension,
+) {
+    visitor.visit_expression(&mut expr.element);
+    visitor.visit_unscoped_variable(&mut expr.variable);
+    visitor.visit_expression(&mut expr.value);
+    if let Some(condition) = &mut expr.condition {
+        visitor.visit_expression(condition);
+    }
+}
+
+pub fn walk_set_comprehension<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &mut SetComprehension,
+) {
+    visitor.visit_expression(&mut expr.element);
+    visitor.visit_unscoped_variable(&mut expr.variable);
+    visitor.visit_expression(&mut expr.value);
+    if let Some(condition) = &mut expr.condition {
+        visitor.visit_expression(condition);
+    }
+}
+
+pub fn walk_capture<V: VisitorMut + ?Sized>(_visitor: &mut V, _expr: &mut Capture) {}
+
+pub fn walk_call<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Call) {
+    for parameter in &mut expr.parameters {
+        visitor.visit_expression(parameter);
+    }
+}
+
+pub fn walk_new_node<V: VisitorMut + ?Sized>(_visitor: &mut V, _expr: &mut NewNode) {}
+
+pub fn walk_regex_capture<V: VisitorMut + ?Sized>(_visitor: &mut V, _expr: &mut RegexCapture) {}
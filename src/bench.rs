@@ -0,0 +1,191 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! A harness for benchmarking graph DSL files, which measures how long each stanza takes over
+//! repeated executions, so that the performance of rule changes can be compared.
+//!
+//! The harness can be used on its own, or from a [criterion][] benchmark, with `iter_custom`:
+//!
+//! ``` ignore
+//! c.bench_function("python rules", |b| {
+//!     b.iter_custom(|iterations| {
+//!         let config = ExecutionConfig::new(&functions, &globals);
+//!         let report = bench(&file, &sources, config, iterations as usize).unwrap();
+//!         report.total.sum()
+//!     })
+//! });
+//! ```
+//!
+//! [criterion]: https://docs.rs/criterion/
+
+use std::fmt;
+use std::time::Duration;
+use std::time::Instant;
+
+use tree_sitter::Tree;
+
+use crate::ast::File;
+use crate::ExecutionConfig;
+use crate::ExecutionError;
+use crate::ExecutionStatistics;
+use crate::Location;
+use crate::NoCancellation;
+
+/// The timings of a benchmark.
+#[derive(Clone, Debug)]
+pub struct BenchReport {
+    /// The time that each iteration took to execute the file against all of the sources
+    pub total: Distribution,
+    /// The timings of every stanza, in the order that they appear in the file
+    pub stanzas: Vec<StanzaBench>,
+}
+
+/// The timings of one stanza of a benchmark.
+#[derive(Clone, Debug)]
+pub struct StanzaBench {
+    /// The location of the stanza in the graph DSL file
+    pub location: Location,
+    /// The number of times that the stanza's query matched in each iteration
+    pub matches: usize,
+    /// The time that the stanza took in each iteration, over all of its matches
+    pub durations: Distribution,
+}
+
+/// The durations of the iterations of a benchmark, in increasing order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Distribution {
+    samples: Vec<Duration>,
+}
+
+impl Distribution {
+    fn new(mut samples: Vec<Duration>) -> Distribution {
+        samples.sort();
+        Distribution { samples }
+    }
+
+    /// Returns the durations, in increasing order.
+    pub fn samples(&self) -> &[Duration] {
+        &self.samples
+    }
+
+    pub fn min(&self) -> Duration {
+        self.samples.first().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.last().copied().unwrap_or_default()
+    }
+
+    pub fn sum(&self) -> Duration {
+        self.samples.iter().sum()
+    }
+
+    pub fn mean(&self) -> Duration {
+        match self.samples.len() {
+            0 => Duration::default(),
+            n => self.sum() / n as u32,
+        }
+    }
+
+    pub fn median(&self) -> Duration {
+        self.percentile(50.0)
+    }
+
+    /// Returns the smallest duration that at least `percentile` percent of the durations do not
+    /// exceed.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::default();
+        }
+        let rank = (percentile / 100.0 * self.samples.len() as f64).ceil() as usize;
+        self.samples[rank.clamp(1, self.samples.len()) - 1]
+    }
+}
+
+/// Executes `file` against each of `sources`, `iterations` times, and returns how long every
+/// iteration took, in total and for each stanza.  The file is executed with `config`, whose
+/// statistics are replaced by the benchmark's own.
+pub fn bench<'tree>(
+    file: &File,
+    sources: &[(&'tree Tree, &'tree str)],
+    config: ExecutionConfig,
+    iterations: usize,
+) -> Result<BenchReport, ExecutionError> {
+    let statistics = ExecutionStatistics::new();
+    let config = config.statistics(&statistics);
+    let mut totals = Vec::with_capacity(iterations);
+    // The statistics accumulate, so the durations of an iteration are the difference with the
+    // previous one.
+    let mut previous = Vec::new();
+    let mut durations = Vec::new();
+    let mut matches = Vec::new();
+    for _ in 0..iterations {
+        let start = Instant::now();
+        for (tree, source) in sources {
+            file.execute(tree, source, &config, &NoCancellation)?;
+        }
+        totals.push(start.elapsed());
+        let stanzas = statistics.stanzas();
+        durations.resize_with(stanzas.len(), Vec::new);
+        for (index, stanza) in stanzas.iter().enumerate() {
+            let previous = previous.get(index).copied().unwrap_or_default();
+            durations[index].push(stanza.duration - previous);
+        }
+        previous = stanzas.iter().map(|stanza| stanza.duration).collect();
+        if matches.is_empty() {
+            matches = stanzas.iter().map(|stanza| stanza.matches).collect();
+        }
+    }
+
+    let stanzas = statistics
+        .stanzas()
+        .into_iter()
+        .zip(durations)
+        .zip(matches)
+        .map(|((stanza, durations), matches)| StanzaBench {
+            location: stanza.location,
+            matches,
+            durations: Distribution::new(durations),
+        })
+        .collect();
+    Ok(BenchReport {
+        total: Distribution::new(totals),
+        stanzas,
+    })
+}
+
+impl fmt::Display for BenchReport {
+    /// Displays a table with the median, 90th percentile, and maximum duration of each stanza,
+    /// and of the whole execution.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<12} {:>8} {:>12} {:>12} {:>12}",
+            "stanza", "matches", "median", "p90", "max"
+        )?;
+        for stanza in &self.stanzas {
+            writeln!(
+                f,
+                "{:<12} {:>8} {:>12?} {:>12?} {:>12?}",
+                stanza.location.to_string(),
+                stanza.matches,
+                stanza.durations.median(),
+                stanza.durations.percentile(90.0),
+                stanza.durations.max(),
+            )?;
+        }
+        writeln!(
+            f,
+            "{:<12} {:>8} {:>12?} {:>12?} {:>12?}",
+            "total",
+            "",
+            self.total.median(),
+            self.total.percentile(90.0),
+            self.total.max(),
+        )
+    }
+}
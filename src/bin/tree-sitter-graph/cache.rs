@@ -0,0 +1,63 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! A cache of checked TSG files, in the directory given by `cache-dir` in `tsg.toml`.  Large rule
+//! files take a while to parse and check, which every invocation would otherwise do again.
+//!
+//! Each entry is a compiled artifact, named after the key that [`File::artifact_key`][] returns
+//! for the TSG files and the language.  The key changes when the TSG files, the grammar, or this
+//! version of tree-sitter-graph change, so stale entries are never used and can be deleted at any
+//! time.  tree-sitter queries have no compiled form that can be stored, so the queries of cached
+//! files are still compiled when they are loaded.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use tree_sitter::Language;
+use tree_sitter_graph::ast::File;
+
+/// The extension of cache entries.
+const EXTENSION: &str = "tsgc";
+
+/// Returns the path of the cache entry for TSG files with the given source.
+fn entry_path(dir: &Path, language: &Language, source: &str) -> PathBuf {
+    dir.join(File::artifact_key(language, source))
+        .with_extension(EXTENSION)
+}
+
+/// Loads the cached TSG files with the given source, if there are any.  Entries that cannot be
+/// loaded are removed, so that they are replaced.
+pub fn load(dir: &Path, language: &Language, source: &str) -> Option<File> {
+    let path = entry_path(dir, language, source);
+    let artifact = std::fs::read(&path).ok()?;
+    match File::load(language.clone(), &artifact) {
+        Ok(file) => {
+            tracing::debug!("Loaded cached TSG files from {}", path.display());
+            Some(file)
+        }
+        Err(e) => {
+            tracing::info!("Removing invalid cache entry {}: {}", path.display(), e);
+            let _ = std::fs::remove_file(&path);
+            None
+        }
+    }
+}
+
+/// Stores checked TSG files with the given source in the cache.  Failing to store them is not an
+/// error, since they are only parsed and checked again the next time.  Entries are written to a
+/// temporary file first, so that concurrent invocations never see a partial entry.
+pub fn store(dir: &Path, language: &Language, source: &str, file: &File) {
+    let path = entry_path(dir, language, source);
+    let temp_path = path.with_extension(format!("{}.{}", EXTENSION, std::process::id()));
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&temp_path, file.compile()))
+        .and_then(|_| std::fs::rename(&temp_path, &path));
+    if let Err(e) = result {
+        tracing::warn!("Cannot cache TSG files in {}: {}", dir.display(), e);
+        let _ = std::fs::remove_file(&temp_path);
+    }
+}
@@ -0,0 +1,75 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The `check` subcommand, which parses and checks TSG files without executing them, and can show
+//! what was parsed or compile it into an artifact.
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use tree_sitter_graph::functions::Functions;
+
+use crate::diagnostic::ErrorFormat;
+use crate::dump;
+use crate::find_tsg_files;
+use crate::language_args;
+use crate::load_languages;
+use crate::tsg_args;
+use crate::TsgSource;
+
+pub fn command() -> App<'static> {
+    App::new("check")
+        .about("Parses and checks TSG files against a language, without executing them")
+        .args(tsg_args())
+        .args(language_args())
+        .mut_arg("scope", |arg| arg.required(true))
+        .arg(
+            Arg::with_name("dump-ast")
+                .long("dump-ast")
+                .help("Print the parsed TSG files as JSON"),
+        )
+        .arg(
+            Arg::with_name("dump-query")
+                .long("dump-query")
+                .help("Print the combined query of the TSG files, with the index of each capture"),
+        )
+        .arg(
+            Arg::with_name("compile")
+                .long("compile")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("Write the checked TSG files to an artifact that the library loads without checking"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let loader = load_languages(matches)?;
+    let scope = matches.value_of("scope").unwrap();
+    let (language, _) = loader
+        .language_configuration_for_scope(scope)?
+        .ok_or_else(|| anyhow!("Unknown scope {}", scope))?;
+    let tsg = TsgSource::load(&find_tsg_files(matches)?)?;
+    let file = tsg.parse(&language, ErrorFormat::from_matches(matches))?;
+    if !file.uses.is_empty() {
+        file.validate_functions(&Functions::stdlib(), None)
+            .context("Cannot execute TSG file")?;
+    }
+    if matches.is_present("dump-ast") {
+        println!("{}", serde_json::to_string_pretty(&dump::ast(&file, &tsg))?);
+    }
+    if matches.is_present("dump-query") {
+        print!("{}", dump::query(&file, &tsg));
+    }
+    if let Some(path) = matches.value_of("compile") {
+        std::fs::write(path, file.compile())
+            .with_context(|| format!("Cannot write compiled artifact {}", path))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The `completions` subcommand, which prints a shell completion script.
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap_complete::Shell;
+
+pub fn command() -> App<'static> {
+    App::new("completions")
+        .about("Prints a completion script for a shell")
+        .long_about(
+            "Prints a completion script for a shell.  For example, for bash, add this to ~/.bashrc:\n\n    \
+             eval \"$(tree-sitter-graph completions bash)\"",
+        )
+        .arg(
+            Arg::with_name("shell")
+                .index(1)
+                .required(true)
+                .possible_values(["bash", "elvish", "fish", "powershell", "zsh"])
+                .help("Shell to print the completion script for"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let shell = matches.value_of("shell").unwrap();
+    let shell = Shell::from_str(shell).map_err(|e| anyhow!(e))?;
+    let mut command = crate::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+    Ok(())
+}
@@ -0,0 +1,223 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Writing of the coverage collected with `--coverage`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::Context as _;
+use anyhow::Result;
+use clap::ArgMatches;
+use serde_json::json;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::ExecutionCoverage;
+use tree_sitter_graph::Location;
+
+use crate::TsgSource;
+
+/// How the coverage is written.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CoverageFormat {
+    /// An LCOV tracefile, with stanzas as functions, arms as branches, and statements as lines
+    Lcov,
+    /// A JSON object with a record for each stanza, arm, and statement
+    Json,
+}
+
+impl CoverageFormat {
+    /// Returns the requested coverage format, or `None` if coverage is not enabled.
+    pub fn from_matches(matches: &ArgMatches) -> Option<CoverageFormat> {
+        matches.value_of("coverage")?;
+        match matches.value_of("coverage-format") {
+            Some("json") => Some(CoverageFormat::Json),
+            _ => Some(CoverageFormat::Lcov),
+        }
+    }
+}
+
+/// Writes the coverage of the TSG files to `path`.
+pub fn write(
+    coverage: &ExecutionCoverage,
+    file: &File,
+    tsg: &TsgSource,
+    format: CoverageFormat,
+    path: &Path,
+) -> Result<()> {
+    let output = match format {
+        CoverageFormat::Lcov => lcov(coverage, file, tsg),
+        CoverageFormat::Json => serde_json::to_string_pretty(&json(coverage, file, tsg))?,
+    };
+    std::fs::write(path, output)
+        .with_context(|| format!("Cannot write coverage to {}", path.display()))
+}
+
+/// Returns the name of the stanza at a location: its own name if it has one, and otherwise the
+/// line that it starts at.
+fn stanza_name(file: &File, tsg: &TsgSource, location: Location) -> String {
+    let stanza = file
+        .stanzas
+        .iter()
+        .find(|stanza| stanza.range.start == location);
+    match stanza.and_then(|stanza| stanza.name.as_ref()) {
+        Some(name) => name.to_string(),
+        None => format!("stanza@{}", tsg.locate(location).1.row + 1),
+    }
+}
+
+fn lcov(coverage: &ExecutionCoverage, file: &File, tsg: &TsgSource) -> String {
+    // The records of each TSG file, which LCOV reports separately
+    #[derive(Default)]
+    struct Records {
+        functions: Vec<(usize, String, usize)>,
+        branches: Vec<(usize, usize, usize, Option<usize>)>,
+        lines: BTreeMap<usize, usize>,
+    }
+    let mut files = BTreeMap::<&Path, Records>::new();
+    for stanza in coverage.stanzas() {
+        let (path, location) = tsg.locate(stanza.location);
+        let name = stanza_name(file, tsg, stanza.location);
+        let records = files.entry(path).or_default();
+        records
+            .functions
+            .push((location.row + 1, name, stanza.count));
+    }
+    let statements = coverage.statements();
+    for statement in &statements {
+        let (path, location) = tsg.locate(statement.location);
+        // A line is only covered if all of its statements ran.
+        let records = files.entry(path).or_default();
+        let count = records
+            .lines
+            .entry(location.row + 1)
+            .or_insert(statement.count);
+        *count = (*count).min(statement.count);
+    }
+    let mut blocks = BTreeMap::new();
+    for arm in coverage.arms() {
+        let (path, location) = tsg.locate(arm.statement);
+        let next_block = blocks.len();
+        let block = *blocks.entry(arm.statement).or_insert(next_block);
+        // Branches of statements that never ran are reported as not taken, rather than as
+        // taken zero times.
+        let ran = statements
+            .iter()
+            .any(|statement| statement.location == arm.statement && statement.count > 0);
+        let records = files.entry(path).or_default();
+        records.branches.push((
+            location.row + 1,
+            block,
+            arm.index,
+            Some(arm.count).filter(|_| ran),
+        ));
+    }
+
+    let mut output = String::new();
+    for (path, records) in files {
+        let _ = writeln!(output, "TN:");
+        let _ = writeln!(output, "SF:{}", path.display());
+        for (line, name, _) in &records.functions {
+            let _ = writeln!(output, "FN:{},{}", line, name);
+        }
+        for (_, name, count) in &records.functions {
+            let _ = writeln!(output, "FNDA:{},{}", count, name);
+        }
+        let _ = writeln!(output, "FNF:{}", records.functions.len());
+        let hit = records.functions.iter().filter(|f| f.2 > 0).count();
+        let _ = writeln!(output, "FNH:{}", hit);
+        for (line, block, branch, count) in &records.branches {
+            let taken = count.map_or("-".to_string(), |count| count.to_string());
+            let _ = writeln!(output, "BRDA:{},{},{},{}", line, block, branch, taken);
+        }
+        let _ = writeln!(output, "BRF:{}", records.branches.len());
+        let hit = records
+            .branches
+            .iter()
+            .filter(|b| b.3.unwrap_or(0) > 0)
+            .count();
+        let _ = writeln!(output, "BRH:{}", hit);
+        for (line, count) in &records.lines {
+            let _ = writeln!(output, "DA:{},{}", line, count);
+        }
+        let _ = writeln!(output, "LF:{}", records.lines.len());
+        let hit = records.lines.values().filter(|count| **count > 0).count();
+        let _ = writeln!(output, "LH:{}", hit);
+        let _ = writeln!(output, "end_of_record");
+    }
+    output
+}
+
+fn json(coverage: &ExecutionCoverage, file: &File, tsg: &TsgSource) -> serde_json::Value {
+    let position = |location: Location| {
+        let (path, location) = tsg.locate(location);
+        (
+            path.display().to_string(),
+            location.row + 1,
+            location.column + 1,
+        )
+    };
+    // The end of a construct, in the same file as its start
+    let end = |location: Location| {
+        file.spans.end(location).map(|end| {
+            let (_, start) = tsg.locate(location);
+            json!({
+                "line": end.row - (location.row - start.row) + 1,
+                "column": end.column + 1,
+            })
+        })
+    };
+    let stanzas = coverage
+        .stanzas()
+        .iter()
+        .map(|stanza| {
+            let (path, line, column) = position(stanza.location);
+            json!({
+                "path": path,
+                "line": line,
+                "column": column,
+                "end": end(stanza.location),
+                "name": stanza_name(file, tsg, stanza.location),
+                "matches": stanza.count,
+            })
+        })
+        .collect::<Vec<_>>();
+    let arms = coverage
+        .arms()
+        .iter()
+        .map(|arm| {
+            let (path, line, column) = position(arm.location);
+            json!({
+                "path": path,
+                "line": line,
+                "column": column,
+                "statement_line": position(arm.statement).1,
+                "index": arm.index,
+                "count": arm.count,
+            })
+        })
+        .collect::<Vec<_>>();
+    let statements = coverage
+        .statements()
+        .iter()
+        .map(|statement| {
+            let (path, line, column) = position(statement.location);
+            json!({
+                "path": path,
+                "line": line,
+                "column": column,
+                "end": end(statement.location),
+                "count": statement.count,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "stanzas": stanzas,
+        "arms": arms,
+        "statements": statements,
+    })
+}
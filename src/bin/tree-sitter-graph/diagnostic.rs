@@ -0,0 +1,190 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Reporting of errors in TSG and source files, either for people or as JSON records.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+use serde_json::json;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::diagnostic;
+use tree_sitter_graph::diagnostic::DiagnosticFile;
+use tree_sitter_graph::diagnostic::Severity;
+use tree_sitter_graph::parse_error;
+use tree_sitter_graph::ExecutionError;
+use tree_sitter_graph::Location;
+use tree_sitter_graph::ParseError;
+
+/// How errors in TSG and source files are reported.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Messages with excerpts of the files involved
+    Human,
+    /// One JSON record per error
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn from_matches(matches: &ArgMatches) -> ErrorFormat {
+        let error_format = matches
+            .value_of("error-format")
+            .or(crate::project::config().error_format.as_deref());
+        match error_format {
+            Some("json") => ErrorFormat::Json,
+            _ => ErrorFormat::Human,
+        }
+    }
+}
+
+/// A range of a file.  The end is not known for all errors.
+#[derive(Clone, Copy)]
+pub struct Span {
+    pub start: Location,
+    pub end: Option<Location>,
+}
+
+impl From<Location> for Span {
+    fn from(start: Location) -> Span {
+        Span { start, end: None }
+    }
+}
+
+/// An error in a TSG or source file, which is a library [`diagnostic::Diagnostic`][] whose spans
+/// have been resolved to the files they refer to.
+pub struct Diagnostic {
+    /// A short identifier of the kind of error
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub path: PathBuf,
+    pub span: Option<Span>,
+    /// Other locations that help explain the error, with a description of each
+    pub related: Vec<(String, PathBuf, Span)>,
+    pub help: Option<String>,
+    /// The error, along with excerpts of the files involved
+    pub pretty: String,
+}
+
+impl Diagnostic {
+    /// Resolves the spans of a library diagnostic to the TSG file and the source file.  Errors
+    /// without a location are reported against the source file.
+    fn new(
+        diagnostic: diagnostic::Diagnostic,
+        tsg_path: &Path,
+        source_path: &Path,
+        pretty: String,
+    ) -> Diagnostic {
+        let path = |span: &diagnostic::Span| match span.file {
+            DiagnosticFile::Tsg => tsg_path.to_path_buf(),
+            DiagnosticFile::Source => source_path.to_path_buf(),
+        };
+        let span = |span: &diagnostic::Span| Span {
+            start: span.start,
+            end: span.end,
+        };
+        let mut pretty = pretty;
+        if let Some(help) = &diagnostic.help {
+            pretty += &format!("\nhelp: {}", help);
+        }
+        Diagnostic {
+            code: diagnostic.code,
+            severity: diagnostic.severity,
+            message: diagnostic.message,
+            path: match &diagnostic.span {
+                Some(primary) => path(primary),
+                None => source_path.to_path_buf(),
+            },
+            span: diagnostic.span.as_ref().map(span),
+            related: diagnostic
+                .secondary
+                .iter()
+                .map(|label| (label.message.clone(), path(&label.span), span(&label.span)))
+                .collect(),
+            help: diagnostic.help,
+            pretty,
+        }
+    }
+
+    /// Creates a diagnostic for an error in a TSG file.
+    pub fn from_tsg_error(path: &Path, tsg: &str, error: &ParseError) -> Diagnostic {
+        let pretty = error.display_pretty(path, tsg).to_string();
+        Diagnostic::new(error.into(), path, path, pretty)
+    }
+
+    /// Creates a diagnostic for a syntax error in a source file.
+    pub fn from_source_error(
+        path: &Path,
+        source: &str,
+        error: &parse_error::ParseError,
+    ) -> Diagnostic {
+        let pretty = error.display_pretty(path, source).to_string();
+        Diagnostic::new(error.into(), path, path, pretty)
+    }
+
+    /// Creates a diagnostic for an error that occurred while executing a TSG file.  The error is
+    /// located at the statement that was executing, if it is known, and spans the whole
+    /// statement.
+    pub fn from_execution_error(
+        source_path: &Path,
+        source: &str,
+        tsg_path: &Path,
+        tsg: &str,
+        file: &File,
+        error: &ExecutionError,
+    ) -> Diagnostic {
+        let pretty = error
+            .display_pretty(source_path, source, tsg_path, tsg)
+            .to_string();
+        let diagnostic = diagnostic::Diagnostic::from(error).with_spans(&file.spans);
+        Diagnostic::new(diagnostic, tsg_path, source_path, pretty)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "code": self.code,
+            "severity": self.severity,
+            "message": self.message,
+            "path": self.path.display().to_string(),
+            "span": self.span.map(span_json),
+            "related": self
+                .related
+                .iter()
+                .map(|(message, path, span)| {
+                    json!({
+                        "message": message,
+                        "path": path.display().to_string(),
+                        "span": span_json(*span),
+                    })
+                })
+                .collect::<Vec<_>>(),
+            "help": self.help,
+        })
+    }
+
+    /// Prints the diagnostic to stderr.
+    pub fn report(&self, format: ErrorFormat) {
+        match format {
+            ErrorFormat::Human => eprintln!("{}", self.pretty),
+            ErrorFormat::Json => eprintln!("{}", self.to_json()),
+        }
+    }
+}
+
+fn span_json(span: Span) -> serde_json::Value {
+    let location = |location: Location| {
+        json!({
+            "line": location.row + 1,
+            "column": location.column + 1,
+        })
+    };
+    json!({
+        "start": location(span.start),
+        "end": span.end.map(location),
+    })
+}
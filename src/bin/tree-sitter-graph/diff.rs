@@ -0,0 +1,133 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The `diff` subcommand, which shows how the graph produced for a source file changes between
+//! two versions of a TSG file, or between two versions of the source file.
+
+use std::path::Path;
+
+use anyhow::Result;
+use clap::builder::ArgAction;
+use clap::App;
+use clap::Arg;
+use clap::ArgGroup;
+use clap::ArgMatches;
+use similar::TextDiff;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph::PrintOptions;
+use tree_sitter_graph::ExecutionLimits;
+
+use crate::diagnostic::ErrorFormat;
+use crate::expand_tsg_paths;
+use crate::find_tsg_files;
+use crate::globals_args;
+use crate::language_args;
+use crate::load_globals;
+use crate::load_languages;
+use crate::scope;
+use crate::tsg_args;
+use crate::Execution;
+use crate::Format;
+use crate::TsgSource;
+
+pub fn command() -> App<'static> {
+    App::new("diff")
+        .about("Shows the differences between the graphs produced by two TSG or source versions")
+        .args(tsg_args())
+        .arg(
+            Arg::with_name("source")
+                .required(true)
+                .help("Source file to execute the TSG files against"),
+        )
+        .arg(
+            Arg::with_name("base-tsg")
+                .long("base-tsg")
+                .takes_value(true)
+                .action(ArgAction::Append)
+                .help("TSG file, or directory of TSG files, to compare against"),
+        )
+        .arg(
+            Arg::with_name("base-source")
+                .long("base-source")
+                .takes_value(true)
+                .help("Source file to compare against"),
+        )
+        .group(
+            ArgGroup::new("base")
+                .args(&["base-tsg", "base-source"])
+                .multiple(true)
+                .required(true),
+        )
+        .args(language_args())
+        .args(globals_args())
+        .arg(
+            Arg::with_name("lazy")
+                .short('z')
+                .long("lazy")
+                .help("Use lazy evaluation (experimental)"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let source_path = Path::new(matches.value_of("source").unwrap());
+    let base_source_path = matches
+        .value_of("base-source")
+        .map_or(source_path, Path::new);
+    let current_dir = std::env::current_dir()?;
+    let mut loader = load_languages(matches)?;
+    let language =
+        loader.select_language(source_path, &current_dir, scope(matches, source_path))?;
+    let error_format = ErrorFormat::from_matches(matches);
+
+    let tsg = TsgSource::load(&find_tsg_files(matches)?)?;
+    let file = tsg.parse(&language, error_format)?;
+    let globals = load_globals(matches, &file)?;
+    let base_tsg = match matches.values_of("base-tsg") {
+        Some(paths) => TsgSource::load(&expand_tsg_paths(paths)?)?,
+        None => TsgSource::load(&find_tsg_files(matches)?)?,
+    };
+    let base_file = base_tsg.parse(&language, error_format)?;
+    let base_globals = load_globals(matches, &base_file)?;
+
+    let functions = Functions::stdlib();
+    let print_options = PrintOptions::default();
+    let execution = |file, tsg, globals| Execution {
+        file,
+        tsg,
+        language: language.clone(),
+        functions: &functions,
+        globals,
+        lazy: matches.is_present("lazy"),
+        allow_parse_errors: false,
+        format: Format::Debug,
+        print_options: &print_options,
+        error_format,
+        limits: ExecutionLimits::default(),
+        output: None,
+        statistics: None,
+        traces: None,
+        coverage: None,
+        fail_fast: false,
+    };
+    let base = execution(&base_file, &base_tsg, &base_globals).run(base_source_path)?;
+    let graph = execution(&file, &tsg, &globals).run(source_path)?;
+
+    if base == graph {
+        println!("No differences");
+        return Ok(());
+    }
+    let label =
+        |tsg: &TsgSource, source: &Path| format!("{} on {}", tsg.path.display(), source.display());
+    print!(
+        "{}",
+        TextDiff::from_lines(&base, &graph).unified_diff().header(
+            &label(&base_tsg, base_source_path),
+            &label(&tsg, source_path)
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,223 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Dumps of what the library constructed from the TSG files, for `check --dump-ast` and
+//! `check --dump-query`.
+
+use serde_json::json;
+use serde_json::Value;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::ast::Statement;
+use tree_sitter_graph::Location;
+
+use crate::TsgSource;
+
+/// Returns the parsed TSG files as JSON.  Statements are shown in TSG syntax, except for those
+/// that contain other statements, which are broken down further.
+pub fn ast(file: &File, tsg: &TsgSource) -> Value {
+    let mut inherited_variables = file
+        .inherited_variables
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    inherited_variables.sort();
+    let mut shorthands = file.shorthands.iter().collect::<Vec<_>>();
+    shorthands.sort_by_key(|shorthand| shorthand.location);
+    let query = file.query.as_ref().expect("missing file query");
+    json!({
+        "globals": file.globals.iter().map(|global| json!({
+            "name": global.name.to_string(),
+            "quantifier": format!("{:?}", global.quantifier),
+            "default": global.default,
+            "location": location(tsg, global.location),
+        })).collect::<Vec<_>>(),
+        "inherited_variables": inherited_variables,
+        "uses": file.uses.iter().map(|uses| json!({
+            "namespaces": uses.namespaces,
+            "location": location(tsg, uses.location),
+        })).collect::<Vec<_>>(),
+        "shorthands": shorthands.iter().map(|shorthand| json!({
+            "name": shorthand.name.to_string(),
+            "text": shorthand.to_string(),
+            "location": location(tsg, shorthand.location),
+        })).collect::<Vec<_>>(),
+        "stanzas": file.stanzas.iter().enumerate().map(|(index, stanza)| {
+            let quantifiers = stanza.query.capture_quantifiers(0);
+            let captures = stanza.query.capture_names().iter().enumerate().map(|(i, name)| {
+                json!({
+                    "name": name,
+                    "index": i,
+                    "file_index": query.capture_index_for_name(name),
+                    "quantifier": format!("{:?}", quantifiers[i]),
+                })
+            }).collect::<Vec<_>>();
+            json!({
+                "index": index,
+                "name": stanza.name.as_ref().map(|name| name.to_string()),
+                "location": location(tsg, stanza.range.start),
+                "query": pattern(file, index),
+                "guard": stanza.guard.as_ref().map(|guard| guard.to_string()),
+                "captures": captures,
+                "full_match_stanza_capture_index": stanza.full_match_stanza_capture_index,
+                "full_match_file_capture_index": stanza.full_match_file_capture_index,
+                "statements": statements(&stanza.statements, tsg),
+            })
+        }).collect::<Vec<_>>(),
+    })
+}
+
+fn statements(block: &[Statement], tsg: &TsgSource) -> Vec<Value> {
+    block
+        .iter()
+        .map(|statement| {
+            let mut result = json!({
+                "kind": kind(statement),
+                "location": location(tsg, statement.location()),
+            });
+            match statement {
+                Statement::If(statement) => {
+                    result["arms"] = statement
+                        .arms
+                        .iter()
+                        .map(|arm| {
+                            let conditions = arm
+                                .conditions
+                                .iter()
+                                .map(|condition| condition.to_string())
+                                .collect::<Vec<_>>();
+                            json!({
+                                "conditions": conditions,
+                                "statements": statements(&arm.statements, tsg),
+                            })
+                        })
+                        .collect();
+                }
+                Statement::ForIn(statement) => {
+                    result["variable"] = statement.variable.to_string().into();
+                    result["value"] = statement.value.to_string().into();
+                    result["statements"] = statements(&statement.statements, tsg).into();
+                }
+                Statement::ForInZip(statement) => {
+                    result["variables"] = statement
+                        .variables
+                        .iter()
+                        .map(|variable| variable.to_string())
+                        .collect();
+                    result["values"] = statement
+                        .values
+                        .iter()
+                        .map(|value| value.to_string())
+                        .collect();
+                    result["statements"] = statements(&statement.statements, tsg).into();
+                }
+                Statement::Scan(statement) => {
+                    result["value"] = statement.value.to_string().into();
+                    result["arms"] = statement
+                        .arms
+                        .iter()
+                        .map(|arm| {
+                            json!({
+                                "regex": arm.regex.as_str(),
+                                "statements": statements(&arm.statements, tsg),
+                            })
+                        })
+                        .collect();
+                }
+                Statement::Match(statement) => {
+                    result["value"] = statement.value.to_string().into();
+                    result["arms"] = statement
+                        .arms
+                        .iter()
+                        .map(|arm| {
+                            let patterns = arm
+                                .patterns
+                                .iter()
+                                .map(|pattern| pattern.to_string())
+                                .collect::<Vec<_>>();
+                            json!({
+                                "patterns": patterns,
+                                "statements": statements(&arm.statements, tsg),
+                            })
+                        })
+                        .collect();
+                }
+                _ => result["text"] = statement.to_string().into(),
+            }
+            result
+        })
+        .collect()
+}
+
+fn kind(statement: &Statement) -> &'static str {
+    match statement {
+        Statement::DeclareImmutable(_) => "DeclareImmutable",
+        Statement::DeclareMutable(_) => "DeclareMutable",
+        Statement::DestructureList(_) => "DestructureList",
+        Statement::Assign(_) => "Assign",
+        Statement::CreateGraphNode(_) => "CreateGraphNode",
+        Statement::AddGraphNodeAttribute(_) => "AddGraphNodeAttribute",
+        Statement::CreateEdge(_) => "CreateEdge",
+        Statement::AddEdgeAttribute(_) => "AddEdgeAttribute",
+        Statement::Scan(_) => "Scan",
+        Statement::Print(_) => "Print",
+        Statement::If(_) => "If",
+        Statement::Match(_) => "Match",
+        Statement::ForIn(_) => "ForIn",
+        Statement::ForInZip(_) => "ForInZip",
+        Statement::Extension(_) => "Extension",
+    }
+}
+
+/// Returns a location in the TSG files, with one-based lines and columns.
+fn location(tsg: &TsgSource, location: Location) -> Value {
+    let (path, location) = tsg.locate(location);
+    json!({
+        "path": path.display().to_string(),
+        "line": location.row + 1,
+        "column": location.column + 1,
+    })
+}
+
+/// Returns the source of one pattern of the combined file query.
+fn pattern(file: &File, index: usize) -> &str {
+    let query = file.query.as_ref().expect("missing file query");
+    let start = query.start_byte_for_pattern(index);
+    let end = if index + 1 < query.pattern_count() {
+        query.start_byte_for_pattern(index + 1)
+    } else {
+        file.query_source.len()
+    };
+    file.query_source[start..end].trim()
+}
+
+/// Returns the combined file query, annotated with the stanza that each pattern belongs to and
+/// the index of each capture.
+pub fn query(file: &File, tsg: &TsgSource) -> String {
+    let query = file.query.as_ref().expect("missing file query");
+    let mut result = String::new();
+    for (index, stanza) in file.stanzas.iter().enumerate() {
+        let (path, location) = tsg.locate(stanza.range.start);
+        result += &format!(
+            "; pattern {}: stanza{} at {}:{}:{}\n",
+            index,
+            stanza
+                .name
+                .as_ref()
+                .map_or(String::new(), |name| format!(" {}", name)),
+            path.display(),
+            location.row + 1,
+            location.column + 1,
+        );
+        result += pattern(file, index);
+        result += "\n\n";
+    }
+    result += "; captures\n";
+    for (index, name) in query.capture_names().iter().enumerate() {
+        result += &format!(";   {} @{}\n", index, name);
+    }
+    result
+}
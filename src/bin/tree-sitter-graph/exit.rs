@@ -0,0 +1,86 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The exit codes of the command, which let scripts tell the kinds of failure apart.
+
+use crate::diagnostic::Diagnostic;
+
+/// The exit code of errors that are not one of the kinds of [`Failure`][], such as files that
+/// cannot be read.  Invalid arguments exit with code 2.
+const OTHER: u8 = 1;
+
+/// The exit codes, as shown in `--help`.
+pub const HELP: &str = "\
+EXIT CODES:
+    0    Success
+    1    Other errors, such as files that cannot be read
+    2    Invalid arguments
+    3    A TSG file could not be parsed
+    4    A TSG file failed its checks, such as for undefined variables
+    5    A source file could not be parsed
+    6    Executing a TSG file failed
+    7    Executing a TSG file exceeded a limit, such as --timeout
+    8    Some of several source files failed, and the others succeeded";
+
+/// The kinds of failure that have their own exit codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    TsgParse = 3,
+    TsgCheck = 4,
+    SourceParse = 5,
+    Execution = 6,
+    LimitExceeded = 7,
+    PartialSuccess = 8,
+}
+
+impl Failure {
+    /// Returns the kind of failure of a diagnostic.
+    pub fn of_diagnostic(diagnostic: &Diagnostic) -> Failure {
+        match diagnostic.code {
+            "tsg-check" | "duplicate-global" => Failure::TsgCheck,
+            "tsg-parse" => Failure::TsgParse,
+            "source-parse" => Failure::SourceParse,
+            "limit-exceeded" => Failure::LimitExceeded,
+            _ => Failure::Execution,
+        }
+    }
+
+    /// Returns the kind of failure of an error, if it has one.
+    pub fn of(error: &anyhow::Error) -> Option<Failure> {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<FailureError>())
+            .map(|error| error.failure)
+    }
+
+    /// Creates an error of this kind.
+    pub fn error(self, message: String) -> anyhow::Error {
+        anyhow::Error::new(FailureError {
+            failure: self,
+            message,
+        })
+    }
+}
+
+/// Returns the exit code for an error.
+pub fn code(error: &anyhow::Error) -> u8 {
+    Failure::of(error).map_or(OTHER, |failure| failure as u8)
+}
+
+#[derive(Debug)]
+struct FailureError {
+    failure: Failure,
+    message: String,
+}
+
+impl std::fmt::Display for FailureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FailureError {}
@@ -0,0 +1,216 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The `explain` subcommand, which reports the syntax nodes that each stanza matches in a source
+//! file, without executing the stanzas, or why each stanza does not match one syntax node.
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use serde_json::json;
+use tree_sitter::Node;
+use tree_sitter::Parser;
+use tree_sitter::Point;
+use tree_sitter_graph::explain::StanzaExplanation;
+
+use crate::diagnostic::ErrorFormat;
+use crate::find_tsg_files;
+use crate::language_args;
+use crate::load_languages;
+use crate::scope;
+use crate::tsg_args;
+use crate::TsgSource;
+
+pub fn command() -> App<'static> {
+    App::new("explain")
+        .about("Shows which syntax nodes each stanza matches, without executing the stanzas")
+        .args(tsg_args())
+        .arg(
+            Arg::with_name("source")
+                .required(true)
+                .help("Source file to match against"),
+        )
+        .args(language_args())
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Output the matches as JSON"),
+        )
+        .arg(
+            Arg::with_name("node")
+                .long("node")
+                .value_name("LINE:COLUMN")
+                .takes_value(true)
+                .help("Explain why stanzas do not match the syntax node at a position"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let source_path = Path::new(matches.value_of("source").unwrap());
+    let current_dir = std::env::current_dir()?;
+    let mut loader = load_languages(matches)?;
+    let language =
+        loader.select_language(source_path, &current_dir, scope(matches, source_path))?;
+    let tsg = TsgSource::load(&find_tsg_files(matches)?)?;
+    let file = tsg.parse(&language, ErrorFormat::from_matches(matches))?;
+
+    let source = std::fs::read_to_string(source_path)
+        .with_context(|| format!("Cannot read source file {}", source_path.display()))?;
+    let mut parser = Parser::new();
+    parser.set_language(&language)?;
+    let tree = parser
+        .parse(&source, None)
+        .ok_or_else(|| anyhow!("Cannot parse {}", source_path.display()))?;
+
+    if let Some(position) = matches.value_of("node") {
+        let point = parse_position(position)?;
+        let node = tree
+            .root_node()
+            .named_descendant_for_point_range(point, point)
+            .ok_or_else(|| anyhow!("No syntax node at {}", position))?;
+        let explanations = file.explain(node, &source);
+        if matches.is_present("json") {
+            let json = json!({
+                "node": node_json(node, &source),
+                "stanzas": serde_json::to_value(&explanations)?,
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        } else {
+            print_explanations(node, &source, &explanations);
+        }
+        return Ok(());
+    }
+
+    // Matches are grouped by stanza, which are identified by the location of their query.
+    let mut stanzas = file
+        .stanzas
+        .iter()
+        .map(|stanza| (stanza.range.start, Vec::new()))
+        .collect::<Vec<_>>();
+    file.try_visit_matches(&tree, &source, false, |mat| {
+        let (_, matches) = stanzas
+            .iter_mut()
+            .find(|(location, _)| location == mat.query_location())
+            .expect("missing stanza for match");
+        let captures = mat
+            .named_captures()
+            .map(|(name, _, nodes)| (name, nodes.collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        matches.push((mat.full_capture(), captures));
+        Ok::<_, anyhow::Error>(())
+    })?;
+
+    if matches.is_present("json") {
+        let json = stanzas
+            .iter()
+            .map(|(location, matches)| {
+                json!({
+                    "line": location.row + 1,
+                    "column": location.column + 1,
+                    "matches": matches
+                        .iter()
+                        .map(|(node, captures)| {
+                            let captures = captures
+                                .iter()
+                                .map(|(name, nodes)| {
+                                    let nodes = nodes
+                                        .iter()
+                                        .map(|node| node_json(*node, &source))
+                                        .collect::<Vec<_>>();
+                                    (name.clone(), nodes.into())
+                                })
+                                .collect::<serde_json::Map<_, _>>();
+                            json!({
+                                "node": node_json(*node, &source),
+                                "captures": captures,
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    for (location, matches) in &stanzas {
+        match matches.len() {
+            0 => println!("stanza at {}: no matches", location),
+            1 => println!("stanza at {}: 1 match", location),
+            n => println!("stanza at {}: {} matches", location, n),
+        }
+        for (node, captures) in matches {
+            println!("  {}", describe_node(*node, &source));
+            for (name, nodes) in captures {
+                for node in nodes {
+                    println!("    @{} = {}", name, describe_node(*node, &source));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses a `LINE:COLUMN` position, whose line and column start at 1.
+fn parse_position(position: &str) -> Result<Point> {
+    let (row, column) = position
+        .split_once(':')
+        .and_then(|(line, column)| {
+            Some((line.parse::<usize>().ok()?, column.parse::<usize>().ok()?))
+        })
+        .filter(|(line, column)| *line > 0 && *column > 0)
+        .ok_or_else(|| anyhow!("Invalid position {}, expected LINE:COLUMN", position))?;
+    Ok(Point::new(row - 1, column - 1))
+}
+
+fn print_explanations(node: Node, source: &str, explanations: &[StanzaExplanation]) {
+    println!("{}", describe_node(node, source));
+    for explanation in explanations {
+        let stanza = match &explanation.name {
+            Some(name) => format!("stanza {} at {}", name, explanation.stanza),
+            None => format!("stanza at {}", explanation.stanza),
+        };
+        match &explanation.mismatch {
+            None => println!("  {}: matches", stanza),
+            Some(mismatch) => println!("  {}: {}", stanza, mismatch),
+        }
+    }
+}
+
+/// Describes a syntax node by its kind and span, including its text if it is on a single line.
+pub fn describe_node(node: Node, source: &str) -> String {
+    let start = node.start_position();
+    let end = node.end_position();
+    let mut description = format!(
+        "({}) {}:{}-{}:{}",
+        node.kind(),
+        start.row + 1,
+        start.column + 1,
+        end.row + 1,
+        end.column + 1,
+    );
+    if start.row == end.row {
+        description += &format!(" {:?}", &source[node.byte_range()]);
+    }
+    description
+}
+
+fn node_json(node: Node, source: &str) -> serde_json::Value {
+    let start = node.start_position();
+    let end = node.end_position();
+    json!({
+        "kind": node.kind(),
+        "start": { "line": start.row + 1, "column": start.column + 1 },
+        "end": { "line": end.row + 1, "column": end.column + 1 },
+        "text": &source[node.byte_range()],
+    })
+}
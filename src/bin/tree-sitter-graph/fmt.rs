@@ -0,0 +1,100 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The `fmt` subcommand, which formats TSG files in the canonical style.
+
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use tree_sitter_graph::ast::File;
+
+use crate::diagnostic::Diagnostic;
+use crate::diagnostic::ErrorFormat;
+use crate::expand_tsg_paths;
+use crate::STDIN;
+
+pub fn command() -> App<'static> {
+    App::new("fmt")
+        .about("Formats TSG files in the canonical style")
+        .arg(
+            Arg::with_name("tsg")
+                .index(1)
+                .required(true)
+                .multiple_values(true)
+                .help("TSG files, or directories of TSG files, to format (- for stdin)"),
+        )
+        .arg(
+            Arg::with_name("check")
+                .long("check")
+                .help("Report files that are not formatted, instead of rewriting them"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let check = matches.is_present("check");
+    let error_format = ErrorFormat::from_matches(matches);
+    let args = matches.values_of("tsg").unwrap().collect::<Vec<_>>();
+    if args.contains(&STDIN) {
+        if args.len() > 1 {
+            return Err(anyhow!(
+                "Cannot read from stdin when formatting multiple files"
+            ));
+        }
+        let mut source = String::new();
+        std::io::stdin()
+            .read_to_string(&mut source)
+            .context("Cannot read TSG file from stdin")?;
+        let formatted = format(Path::new("<stdin>"), &source, error_format)?;
+        if check {
+            if formatted != source {
+                return Err(anyhow!("<stdin> is not formatted"));
+            }
+        } else {
+            std::io::stdout().write_all(formatted.as_bytes())?;
+        }
+        return Ok(());
+    }
+
+    let mut unformatted = 0;
+    for path in expand_tsg_paths(args)? {
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Cannot read TSG file {}", path.display()))?;
+        let formatted = format(&path, &source, error_format)?;
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", path.display());
+            unformatted += 1;
+        } else {
+            std::fs::write(&path, formatted)
+                .with_context(|| format!("Cannot write {}", path.display()))?;
+        }
+    }
+    if unformatted > 0 {
+        return Err(anyhow!(
+            "{} TSG file{} not formatted",
+            unformatted,
+            if unformatted > 1 { "s are" } else { " is" },
+        ));
+    }
+    Ok(())
+}
+
+fn format(path: &Path, source: &str, error_format: ErrorFormat) -> Result<String> {
+    File::format(source).map_err(|err| {
+        Diagnostic::from_tsg_error(path, source, &err).report(error_format);
+        anyhow!("Cannot format TSG file {}", path.display())
+    })
+}
@@ -0,0 +1,65 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Finding the files that changed in a git repository, for `--changed-since`.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+
+/// Returns the canonical paths of the files that differ from revision `rev`, including changes in
+/// the working tree that are not committed, and untracked files that are not ignored.
+pub fn changed_files(rev: &str) -> Result<HashSet<PathBuf>> {
+    let root = git(&["rev-parse", "--show-toplevel"])?;
+    let root = PathBuf::from(root.trim_end_matches('\n'));
+    let mut changed = HashSet::new();
+    let diff = git(&["diff", "--name-only", "-z", rev, "--"])?;
+    let untracked = git(&[
+        "ls-files",
+        "--others",
+        "--exclude-standard",
+        "--full-name",
+        "-z",
+    ])?;
+    for path in diff.split('\0').chain(untracked.split('\0')) {
+        if path.is_empty() {
+            continue;
+        }
+        let path = root.join(path);
+        // Deleted files cannot be canonicalized, but are not sources to execute either.
+        if let Ok(path) = path.canonicalize() {
+            changed.insert(path);
+        }
+    }
+    Ok(changed)
+}
+
+/// Returns whether a file is one of the changed files.
+pub fn is_changed(changed: &HashSet<PathBuf>, path: &Path) -> bool {
+    path.canonicalize()
+        .is_ok_and(|path| changed.contains(&path))
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Cannot run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).context("git printed invalid UTF-8")
+}
@@ -5,7 +5,17 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::collections::HashSet;
+use std::io::Read;
 use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
 
 use anyhow::anyhow;
 use anyhow::Context as _;
@@ -13,36 +23,161 @@ use anyhow::Result;
 use clap::builder::ArgAction;
 use clap::App;
 use clap::Arg;
+use clap::ArgMatches;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::EnvFilter;
+use tree_sitter::Language;
 use tree_sitter::Parser;
 use tree_sitter_config::Config;
 use tree_sitter_graph::ast::File;
+use tree_sitter_graph::ast::PrintLevel;
+use tree_sitter_graph::diagnostic::Severity;
 use tree_sitter_graph::functions::Functions;
 use tree_sitter_graph::graph;
+use tree_sitter_graph::graph::Graph;
+use tree_sitter_graph::graph::NodeOrder;
+use tree_sitter_graph::graph::PrintOptions;
+use tree_sitter_graph::parse_error::Excerpt;
 use tree_sitter_graph::parse_error::ParseError;
 use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::ExecutionCoverage;
+use tree_sitter_graph::ExecutionLimits;
+use tree_sitter_graph::ExecutionStatistics;
+use tree_sitter_graph::ExecutionTrace;
 use tree_sitter_graph::Identifier;
+use tree_sitter_graph::Location;
 use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::StderrOutput;
 use tree_sitter_graph::Variables;
 use tree_sitter_loader::Loader;
 
+use crate::coverage::CoverageFormat;
+use crate::diagnostic::Diagnostic;
+use crate::diagnostic::ErrorFormat;
+use crate::diagnostic::Span;
+use crate::exit::Failure;
+use crate::profile::ProfileFormat;
+use crate::trace::Traces;
+
 const BUILD_VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 const MAX_PARSE_ERRORS: usize = 5;
 
-fn main() -> Result<()> {
-    init_log();
+/// The source path that means "read the source from stdin".
+const STDIN: &str = "-";
+
+/// The output formats of the graph.
+const FORMATS: [&str; 6] = ["debug", "json", "dot", "graphml", "mermaid", "canonical"];
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+mod cache;
+mod check;
+mod completions;
+mod coverage;
+mod diagnostic;
+mod diff;
+mod dump;
+mod exit;
+mod explain;
+mod fmt;
+mod git;
+mod man;
+mod output_dir;
+mod profile;
+mod project;
+mod repl;
+mod test;
+#[cfg(test)]
+mod tests;
+mod trace;
+
+fn main() -> ExitCode {
+    match try_main() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(exit::code(&e))
+        }
+    }
+}
 
-    let matches = App::new("tree-sitter-graph")
+fn try_main() -> Result<()> {
+    project::load()?;
+    let matches = command().get_matches();
+    init_log(&matches);
+
+    match matches.subcommand() {
+        Some(("check", matches)) => return check::run(matches),
+        Some(("completions", matches)) => return completions::run(matches),
+        Some(("diff", matches)) => return diff::run(matches),
+        Some(("explain", matches)) => return explain::run(matches),
+        Some(("fmt", matches)) => return fmt::run(matches),
+        Some(("man", matches)) => return man::run(matches),
+        Some(("repl", matches)) => return repl::run(matches),
+        Some(("test", matches)) => return test::run(matches),
+        _ => {}
+    }
+    if matches.is_present("watch") {
+        if matches
+            .values_of("source")
+            .unwrap()
+            .any(|source| source == STDIN)
+        {
+            return Err(anyhow!("Cannot watch stdin for changes"));
+        }
+        watch(&matches)
+    } else {
+        run(&matches)
+    }
+}
+
+/// The command-line interface, including all of the subcommands.
+fn command() -> App<'static> {
+    App::new("tree-sitter-graph")
         .version(BUILD_VERSION)
         .author("Douglas Creager <dcreager@dcreager.net>")
         .about("Generates graph structures from tree-sitter syntax trees")
-        .arg(Arg::with_name("tsg").index(1).required(true))
-        .arg(Arg::with_name("source").index(2).required(true))
+        .after_help(exit::HELP)
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .subcommand(check::command())
+        .subcommand(completions::command())
+        .subcommand(diff::command())
+        .subcommand(explain::command())
+        .subcommand(fmt::command())
+        .subcommand(man::command())
+        .subcommand(repl::command())
+        .subcommand(test::command())
+        .args(tsg_args())
+        .arg(
+            Arg::with_name("source")
+                .required(true)
+                .multiple_values(true)
+                .help("Source files, directories, or glob patterns to process (- for stdin)"),
+        )
+        .args(language_args())
         .arg(
             Arg::with_name("quiet")
                 .short('q')
                 .long("quiet")
-                .help("Suppress console output"),
+                .help("Suppress console output, and log only errors"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short('v')
+                .long("verbose")
+                .action(ArgAction::Count)
+                .global(true)
+                .help("Log more details; repeat for debug (-vv) and trace (-vvv) messages"),
+        )
+        .arg(
+            Arg::with_name("log-format")
+                .long("log-format")
+                .takes_value(true)
+                .global(true)
+                .possible_values(["text", "json"])
+                .help("Format of log messages on stderr [default: text]"),
         )
         .arg(
             Arg::with_name("lazy")
@@ -50,114 +185,1316 @@ fn main() -> Result<()> {
                 .long("lazy")
                 .help("Use lazy evaluation (experimental)"),
         )
-        .arg(Arg::with_name("scope").long("scope").takes_value(true))
-        .arg(Arg::with_name("json").long("json").takes_value(false))
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .takes_value(false)
+                .conflicts_with("format")
+                .help("Output the graph as JSON (same as --format json)"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(FORMATS)
+                .help("Output format of the graph [default: debug]"),
+        )
         .arg(
             Arg::with_name("output")
                 .short('o')
                 .long("output")
-                .requires("json")
-                .takes_value(true),
+                .takes_value(true)
+                .help("Write the output to this file instead of stdout"),
         )
         .arg(
             Arg::with_name("allow-parse-errors")
                 .long("allow-parse-errors")
                 .takes_value(false),
         )
+        .args(print_args())
+        .args(globals_args())
+        .arg(
+            Arg::with_name("jobs")
+                .short('j')
+                .long("jobs")
+                .takes_value(true)
+                .help("Number of source files to process in parallel"),
+        )
+        .arg(
+            Arg::with_name("fail-fast")
+                .long("fail-fast")
+                .help("Stop processing source files after the first one that fails"),
+        )
+        .arg(
+            Arg::with_name("per-file")
+                .long("per-file")
+                .conflicts_with("output")
+                .help("Write each graph next to its source file, instead of one merged output"),
+        )
+        .arg(
+            Arg::with_name("output-dir")
+                .long("output-dir")
+                .takes_value(true)
+                .conflicts_with_all(&["output", "per-file"])
+                .help("Write each graph to this directory, mirroring the source tree, along with an index.json manifest"),
+        )
+        .arg(
+            Arg::with_name("changed-since")
+                .long("changed-since")
+                .takes_value(true)
+                .requires("output-dir")
+                .help("Only execute the source files that changed since this git revision, updating the existing --output-dir"),
+        )
+        .arg(
+            Arg::with_name("output-extension")
+                .long("output-extension")
+                .takes_value(true)
+                .help("File extension of the graphs written by --per-file and --output-dir [default: depends on --format]"),
+        )
+        .arg(
+            Arg::with_name("error-format")
+                .long("error-format")
+                .takes_value(true)
+                .global(true)
+                .possible_values(["human", "json"])
+                .help("Format of the errors in TSG and source files [default: human]"),
+        )
+        .args(limits_args())
+        .arg(
+            Arg::with_name("only-stanza")
+                .long("only-stanza")
+                .takes_value(true)
+                .action(ArgAction::Append)
+                .help("Execute only this stanza, given by name or by zero-based index"),
+        )
+        .arg(
+            Arg::with_name("skip-stanza")
+                .long("skip-stanza")
+                .takes_value(true)
+                .action(ArgAction::Append)
+                .help("Do not execute this stanza, given by name or by zero-based index"),
+        )
+        .arg(
+            Arg::with_name("print-level")
+                .long("print-level")
+                .takes_value(true)
+                .possible_values(PrintLevel::ALL.map(PrintLevel::name))
+                .help("Only show the messages of print statements at this level or more important ones [default: trace]"),
+        )
+        .arg(
+            Arg::with_name("print-channel")
+                .long("print-channel")
+                .takes_value(true)
+                .action(ArgAction::Append)
+                .help("Only show the messages of print statements on this channel, or without a channel"),
+        )
+        .arg(Arg::with_name("profile").long("profile").help(
+            "Print the time, match count, and graph nodes and edges of each stanza to stderr",
+        ))
+        .arg(
+            Arg::with_name("profile-output")
+                .long("profile-output")
+                .takes_value(true)
+                .possible_values(["table", "json"])
+                .help("Format of the profile, which implies --profile [default: table]"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .takes_value(true)
+                .conflicts_with("lazy")
+                .help("Write every statement that runs, with the values it resolves, to this file"),
+        )
         .arg(
-            Arg::with_name("global")
-                .long("global")
+            Arg::with_name("trace-format")
+                .long("trace-format")
                 .takes_value(true)
-                .action(ArgAction::Append),
+                .requires("trace")
+                .possible_values(["text", "json"])
+                .help("Format of the trace [default: text]"),
         )
-        .get_matches();
+        .arg(
+            Arg::with_name("coverage")
+                .long("coverage")
+                .takes_value(true)
+                .help("Write how many times each stanza, arm, and statement ran to this file"),
+        )
+        .arg(
+            Arg::with_name("coverage-format")
+                .long("coverage-format")
+                .takes_value(true)
+                .requires("coverage")
+                .possible_values(["lcov", "json"])
+                .help("Format of the coverage [default: lcov]"),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .short('w')
+                .long("watch")
+                .help("Re-run whenever the TSG file or source file changes"),
+        )
+}
 
-    let tsg_path = Path::new(matches.value_of("tsg").unwrap());
-    let source_path = Path::new(matches.value_of("source").unwrap());
-    let current_dir = std::env::current_dir().unwrap();
-    let quiet = matches.is_present("quiet");
-    let lazy = matches.is_present("lazy");
-    let globals = matches.get_many::<String>("global").unwrap_or_default();
-    let mut globals_ = Variables::new();
-    for kv in globals {
-        let kv_ = kv
-            .split_once('=')
-            .with_context(|| format!("Expected key-value pair separated by '=', got {}.", kv))?;
-        globals_.add(
-            Identifier::from(kv_.0),
-            graph::Value::String(kv_.1.to_string()),
-        )?;
+/// The arguments that select the TSG files to use.  If the project configuration names TSG files,
+/// they are used unless others are given with `--main-tsg`, and the TSG file is no longer the first
+/// positional argument.
+fn tsg_args() -> [Arg<'static>; 2] {
+    let tsg = Arg::with_name("tsg");
+    let tsg = if project::config().tsg.is_empty() {
+        tsg.required(true)
+            .help("TSG file, or directory of TSG files, to execute")
+    } else {
+        tsg.long("main-tsg")
+            .takes_value(true)
+            .help("TSG file, or directory of TSG files, to execute instead of those in tsg.toml")
+    };
+    [
+        tsg,
+        Arg::with_name("more-tsg")
+            .long("tsg")
+            .takes_value(true)
+            .action(ArgAction::Append)
+            .help("Additional TSG file or directory, appended after the previous ones"),
+    ]
+}
+
+/// The arguments that control how languages are found.
+fn language_args() -> [Arg<'static>; 3] {
+    [
+        Arg::with_name("scope")
+            .long("scope")
+            .takes_value(true)
+            .help("Language scope of the source files, such as source.python"),
+        Arg::with_name("grammar")
+            .long("grammar")
+            .takes_value(true)
+            .action(ArgAction::Append)
+            .help("Directory of a locally built grammar to consider when selecting a language"),
+        Arg::with_name("config-path")
+            .long("config-path")
+            .takes_value(true)
+            .help("Path to an alternative tree-sitter config.json file"),
+    ]
+}
+
+/// The arguments that control what the debug output of the graph shows.
+fn print_args() -> [Arg<'static>; 4] {
+    [
+        Arg::with_name("only-nodes")
+            .long("only-nodes")
+            .takes_value(true)
+            .help("Only print the nodes that have this attribute, given as NAME or NAME=VALUE"),
+        Arg::with_name("sort-nodes")
+            .long("sort-nodes")
+            .takes_value(true)
+            .help("Print the nodes sorted by the position of their syntax nodes (span), or by the value of this attribute"),
+        Arg::with_name("max-value-length")
+            .long("max-value-length")
+            .takes_value(true)
+            .help("Elide attribute values that are longer than this many characters"),
+        Arg::with_name("incoming-edges")
+            .long("incoming-edges")
+            .help("Also print the incoming edges of each node"),
+    ]
+}
+
+/// Collects the options given with `--only-nodes`, `--sort-nodes`, `--max-value-length`, and
+/// `--incoming-edges`.
+fn load_print_options(matches: &ArgMatches) -> Result<PrintOptions> {
+    let mut options = PrintOptions::new().incoming_edges(matches.is_present("incoming-edges"));
+    if let Some(filter) = matches.value_of("only-nodes") {
+        let (name, value) = match filter.split_once('=') {
+            Some((name, value)) => (Identifier::from(name), Some(value.to_string())),
+            None => (Identifier::from(filter), None),
+        };
+        options = options.filter(move |attributes| match (attributes.get(&name), &value) {
+            (Some(actual), Some(value)) => actual.to_string() == *value,
+            (actual, None) => actual.is_some(),
+            (None, Some(_)) => false,
+        });
+    }
+    match matches.value_of("sort-nodes") {
+        Some("span") => options = options.order(NodeOrder::Span),
+        Some(name) => options = options.order(NodeOrder::Attribute(Identifier::from(name))),
+        None => {}
+    }
+    if let Some(length) = matches.value_of("max-value-length") {
+        let length = length
+            .parse()
+            .map_err(|_| anyhow!("Invalid value {} for --max-value-length", length))?;
+        options = options.max_value_length(length);
+    }
+    Ok(options)
+}
+
+/// The arguments that provide global variables.
+fn globals_args() -> [Arg<'static>; 2] {
+    [
+        Arg::with_name("global")
+            .long("global")
+            .takes_value(true)
+            .action(ArgAction::Append)
+            .help("Global variable, as NAME=VALUE or NAME:TYPE=VALUE, where TYPE is one of string, int, bool, or json"),
+        Arg::with_name("globals-file")
+            .long("globals-file")
+            .takes_value(true)
+            .help("JSON file containing an object of global variables"),
+    ]
+}
+
+/// The arguments that limit the resources used by each execution.
+fn limits_args() -> [Arg<'static>; 6] {
+    [
+        Arg::with_name("max-nodes")
+            .long("max-nodes")
+            .takes_value(true)
+            .help("Maximum number of graph nodes to create for each source file"),
+        Arg::with_name("max-edges")
+            .long("max-edges")
+            .takes_value(true)
+            .help("Maximum number of edges to create for each source file"),
+        Arg::with_name("timeout")
+            .long("timeout")
+            .takes_value(true)
+            .help("Maximum time in seconds to spend executing each source file"),
+        Arg::with_name("fuel")
+            .long("fuel")
+            .takes_value(true)
+            .help("Maximum number of statements to execute for each source file"),
+        Arg::with_name("max-value-size")
+            .long("max-value-size")
+            .takes_value(true)
+            .help("Maximum number of elements and string bytes in each value"),
+        Arg::with_name("max-value-depth")
+            .long("max-value-depth")
+            .takes_value(true)
+            .help("Maximum nesting depth of lists and sets in each value"),
+    ]
+}
+
+/// Collects the execution limits given with `--max-nodes`, `--max-edges`, `--timeout`, `--fuel`,
+/// `--max-value-size`, and `--max-value-depth`.
+fn load_limits(matches: &ArgMatches) -> Result<ExecutionLimits> {
+    fn parse<T: std::str::FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>> {
+        matches
+            .value_of(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid value {} for --{}", value, name))
+            })
+            .transpose()
+    }
+    Ok(ExecutionLimits {
+        max_nodes: parse(matches, "max-nodes")?,
+        max_edges: parse(matches, "max-edges")?,
+        timeout: parse::<f64>(matches, "timeout")?
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .context("Invalid value for --timeout")?,
+        fuel: parse(matches, "fuel")?,
+        max_value_size: parse(matches, "max-value-size")?,
+        max_value_depth: parse(matches, "max-value-depth")?,
+    })
+}
+
+/// Creates the output that selects the messages of print statements given with `--print-level`
+/// and `--print-channel`, if any.
+fn load_output(matches: &ArgMatches) -> Option<StderrOutput> {
+    if !matches.is_present("print-level") && !matches.is_present("print-channel") {
+        return None;
     }
+    let max_level = matches
+        .value_of("print-level")
+        .and_then(PrintLevel::from_name)
+        .unwrap_or(PrintLevel::Trace);
+    let mut output = StderrOutput::new(max_level);
+    for channel in matches.values_of("print-channel").unwrap_or_default() {
+        output = output.channel(channel);
+    }
+    Some(output)
+}
 
-    let config = Config::load()?;
+/// Creates a loader that knows about the languages in the tree-sitter configuration, as well as
+/// any grammars given with `--grammar`.
+fn load_languages(matches: &ArgMatches) -> Result<Loader> {
+    let config = Config::load(matches.value_of("config-path").map(PathBuf::from))?;
     let mut loader = Loader::new()?;
     let loader_config = config.get()?;
     loader.find_all_languages(&loader_config)?;
-    let language = loader.select_language(source_path, &current_dir, matches.value_of("scope"))?;
+    for grammar in matches.values_of("grammar").unwrap_or_default() {
+        loader
+            .find_language_configurations_at_path(Path::new(grammar), false)
+            .with_context(|| format!("Cannot load grammar from {}", grammar))?;
+    }
+    Ok(loader)
+}
+
+/// Runs once, and then again every time that one of the input files changes.  Errors are reported
+/// but do not stop the loop, since they are typically fixed by the next edit.
+fn watch(matches: &ArgMatches) -> Result<()> {
+    let source_args = matches.values_of("source").unwrap().collect::<Vec<_>>();
+    let scope = scope(matches, Path::new(source_args[0]));
+    // If the languages cannot be loaded, the run reports it, and the arguments are watched as they
+    // are.
+    let loader = load_languages(matches).ok();
+    let watched_paths = || -> Vec<PathBuf> {
+        // Directories and patterns are expanded every time, so that changes to the files in them,
+        // and added and removed files, are noticed.
+        let mut paths = find_tsg_files(matches).unwrap_or_default();
+        match loader
+            .as_ref()
+            .and_then(|loader| find_sources(loader, &source_args, scope).ok())
+        {
+            Some(sources) => paths.extend(sources),
+            None => paths.extend(source_args.iter().map(PathBuf::from)),
+        }
+        paths
+    };
+    let mut last_modified = modification_times(&watched_paths());
+    loop {
+        if let Err(e) = run(matches) {
+            eprintln!("Error: {:?}", e);
+        }
+        eprintln!("Watching for changes...");
+        loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            let modified = modification_times(&watched_paths());
+            if modified != last_modified {
+                last_modified = modified;
+                break;
+            }
+        }
+    }
+}
+
+fn modification_times(paths: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    paths
+        .iter()
+        .map(|path| {
+            let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+            (path.clone(), modified)
+        })
+        .collect()
+}
 
-    let tsg = std::fs::read(tsg_path)
-        .with_context(|| format!("Cannot read TSG file {}", tsg_path.display()))?;
-    let tsg = String::from_utf8(tsg)?;
-    let file = match File::from_str(language, &tsg) {
-        Ok(file) => file,
-        Err(err) => {
-            eprintln!("{}", err.display_pretty(tsg_path, &tsg));
-            return Err(anyhow!("Cannot parse TSG file {}", tsg_path.display()));
+fn run(matches: &ArgMatches) -> Result<()> {
+    let source_args = matches.values_of("source").unwrap().collect::<Vec<_>>();
+    let current_dir = std::env::current_dir().unwrap();
+    let quiet = matches.is_present("quiet");
+    let lazy = matches.is_present("lazy");
+    let error_format = ErrorFormat::from_matches(matches);
+    let format = if matches.is_present("json") {
+        Format::Json
+    } else {
+        match matches
+            .value_of("format")
+            .or(project::config().format.as_deref())
+        {
+            Some("json") => Format::Json,
+            Some("dot") => Format::Dot,
+            Some("graphml") => Format::Graphml,
+            Some("mermaid") => Format::Mermaid,
+            Some("canonical") => Format::Canonical,
+            _ => Format::Debug,
+        }
+    };
+    let mut loader = load_languages(matches)?;
+    let sources = find_sources(
+        &loader,
+        &source_args,
+        scope(matches, Path::new(source_args[0])),
+    )?;
+    let first_source = sources
+        .first()
+        .ok_or_else(|| anyhow!("No source files found"))?;
+    if sources.iter().any(|source| source == Path::new(STDIN)) {
+        if sources.len() > 1 {
+            return Err(anyhow!(
+                "Cannot read from stdin when processing multiple sources"
+            ));
+        }
+        if matches.value_of("scope").is_none() {
+            return Err(anyhow!(
+                "Reading from stdin requires --scope to select a language"
+            ));
         }
+    }
+    let language =
+        loader.select_language(first_source, &current_dir, scope(matches, first_source))?;
+
+    let tsg = TsgSource::load(&find_tsg_files(matches)?)?;
+    let mut file = tsg.parse(&language, error_format)?;
+    filter_stanzas(matches, &mut file)?;
+    let globals = load_globals(matches, &file)?;
+
+    let functions = Functions::stdlib();
+    let profile_format = ProfileFormat::from_matches(matches);
+    let statistics = Mutex::new(ExecutionStatistics::new());
+    let traces = Traces::from_matches(matches);
+    let coverage_format = CoverageFormat::from_matches(matches);
+    let coverage = Mutex::new(ExecutionCoverage::new());
+    let print_options = load_print_options(matches)?;
+    let output = load_output(matches);
+    let execution = Execution {
+        file: &file,
+        tsg: &tsg,
+        language,
+        functions: &functions,
+        globals: &globals,
+        lazy,
+        allow_parse_errors: matches.is_present("allow-parse-errors"),
+        format,
+        print_options: &print_options,
+        error_format,
+        limits: load_limits(matches)?,
+        output: output.as_ref(),
+        statistics: profile_format.map(|_| &statistics),
+        traces: traces.as_ref(),
+        coverage: coverage_format.map(|_| &coverage),
+        fail_fast: matches.is_present("fail-fast"),
     };
 
-    let source = std::fs::read(source_path)
-        .with_context(|| format!("Cannot read source file {}", source_path.display()))?;
-    let source = String::from_utf8(source)?;
-    let mut parser = Parser::new();
-    parser.set_language(language)?;
-    let tree = parser
-        .parse(&source, None)
-        .ok_or_else(|| anyhow!("Cannot parse {}", source_path.display()))?;
-    let allow_parse_errors = matches.is_present("allow-parse-errors");
-    if !allow_parse_errors {
-        let parse_errors = ParseError::all(&tree);
-        if !parse_errors.is_empty() {
-            for parse_error in parse_errors.iter().take(MAX_PARSE_ERRORS) {
-                eprintln!("{}", parse_error.display_pretty(source_path, &source));
+    let output_path = matches.value_of("output").map(|str| Path::new(str));
+    let output_dir = matches.value_of("output-dir").map(Path::new);
+    let extension = matches
+        .value_of("output-extension")
+        .map_or(format.extension(), |extension| {
+            extension.trim_start_matches('.')
+        });
+    if sources.len() == 1 && source_args.len() == 1 && output_dir.is_none() {
+        let output = execution.run(first_source);
+        if let Some(traces) = traces {
+            traces.write(&tsg)?;
+        }
+        if let Some(coverage_format) = coverage_format {
+            write_coverage(matches, coverage, &file, &tsg, coverage_format)?;
+        }
+        let output = output?;
+        if let Some(output_path) = output_path {
+            std::fs::write(output_path, output)
+                .with_context(|| format!("Cannot write {}", output_path.display()))?;
+        } else if format != Format::Debug || !quiet {
+            print!("{}", output);
+        }
+        if let Some(profile_format) = profile_format {
+            profile::report(&statistics.into_inner().unwrap(), &tsg, profile_format);
+        }
+        return Ok(());
+    }
+
+    let jobs = match matches.value_of("jobs") {
+        Some(jobs) => jobs
+            .parse::<usize>()
+            .with_context(|| format!("Invalid number of jobs {}", jobs))?,
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    if let Some(output_dir) = output_dir {
+        if sources[0] == Path::new(STDIN) {
+            return Err(anyhow!("Cannot use --output-dir when reading from stdin"));
+        }
+        let changed = match matches.value_of("changed-since") {
+            Some(rev) => {
+                let mut changed = git::changed_files(rev)?;
+                // Every graph can depend on the TSG files, so if they changed, nothing is current.
+                if tsg
+                    .files
+                    .iter()
+                    .any(|(path, _, _)| git::is_changed(&changed, path))
+                {
+                    tracing::info!("TSG files changed since {}", rev);
+                    changed.extend(
+                        sources
+                            .iter()
+                            .filter_map(|source| source.canonicalize().ok()),
+                    );
+                }
+                Some(changed)
+            }
+            None => None,
+        };
+        let (succeeded, failures) = output_dir::write(
+            &execution,
+            &sources,
+            changed.as_ref(),
+            jobs,
+            output_dir,
+            extension,
+        )?;
+        if let Some(profile_format) = profile_format {
+            profile::report(&statistics.into_inner().unwrap(), &tsg, profile_format);
+        }
+        if let Some(traces) = traces {
+            traces.write(&tsg)?;
+        }
+        if let Some(coverage_format) = coverage_format {
+            write_coverage(matches, coverage, &file, &tsg, coverage_format)?;
+        }
+        return check_failures(&failures, succeeded, sources.len());
+    }
+    let results = execution.run_all(&sources, jobs);
+
+    let per_file = matches.is_present("per-file");
+    let mut merged = String::new();
+    let mut merged_json = serde_json::Map::new();
+    let mut succeeded = 0;
+    let mut failures = Vec::new();
+    for (source_path, result) in sources.iter().zip(results) {
+        let output = match result {
+            Some(Ok(output)) => output,
+            Some(Err(e)) => {
+                eprintln!("{:?}", e);
+                failures.push(Failure::of(&e));
+                continue;
+            }
+            // Skipped by --fail-fast
+            None => continue,
+        };
+        succeeded += 1;
+        if per_file {
+            let mut output_file = source_path.clone().into_os_string();
+            output_file.push(".");
+            output_file.push(extension);
+            std::fs::write(&output_file, output)
+                .with_context(|| format!("Cannot write {}", Path::new(&output_file).display()))?;
+        } else if format == Format::Json {
+            merged_json.insert(
+                source_path.display().to_string(),
+                serde_json::from_str(&output)?,
+            );
+        } else if format == Format::Debug || format == Format::Canonical {
+            merged += &format!("==> {} <==\n{}", source_path.display(), output);
+        } else {
+            merged += &output;
+        }
+    }
+    if !per_file {
+        if format == Format::Json {
+            merged = serde_json::to_string_pretty(&merged_json)?;
+        }
+        if let Some(output_path) = output_path {
+            std::fs::write(output_path, merged)
+                .with_context(|| format!("Cannot write {}", output_path.display()))?;
+        } else if format != Format::Debug || !quiet {
+            print!("{}", merged);
+        }
+    }
+    if let Some(profile_format) = profile_format {
+        profile::report(&statistics.into_inner().unwrap(), &tsg, profile_format);
+    }
+    if let Some(traces) = traces {
+        traces.write(&tsg)?;
+    }
+    if let Some(coverage_format) = coverage_format {
+        write_coverage(matches, coverage, &file, &tsg, coverage_format)?;
+    }
+    check_failures(&failures, succeeded, sources.len())
+}
+
+/// Writes the coverage collected over all of the source files to the `--coverage` file.
+fn write_coverage(
+    matches: &ArgMatches,
+    coverage: Mutex<ExecutionCoverage>,
+    file: &File,
+    tsg: &TsgSource,
+    format: CoverageFormat,
+) -> Result<()> {
+    let path = Path::new(matches.value_of("coverage").expect("missing coverage path"));
+    coverage::write(&coverage.into_inner().unwrap(), file, tsg, format, path)
+}
+
+/// Fails if any of the source files failed to process, given the kind of each failure and the
+/// number of source files that succeeded.  The failures have already been reported.  If any of
+/// the source files succeeded, the kind of failure is [`Failure::PartialSuccess`][], and otherwise
+/// it is the kind of the first failure.  Source files skipped by `--fail-fast` did not succeed.
+fn check_failures(failures: &[Option<Failure>], succeeded: usize, total: usize) -> Result<()> {
+    let message = format!("{} of {} source files failed", failures.len(), total);
+    match failures.first() {
+        None => Ok(()),
+        Some(_) if succeeded > 0 => Err(Failure::PartialSuccess.error(message)),
+        Some(Some(failure)) => Err(failure.error(message)),
+        Some(None) => Err(anyhow!(message)),
+    }
+}
+
+/// The formats that graphs can be output in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Debug,
+    Json,
+    Dot,
+    Graphml,
+    Mermaid,
+    /// The line-oriented text of [`Graph::to_canonical_text`][], for golden files
+    Canonical,
+}
+
+impl Format {
+    fn render(self, graph: &Graph, print_options: &PrintOptions) -> Result<String> {
+        Ok(match self {
+            Format::Debug => graph.pretty_print_with(print_options).to_string(),
+            Format::Json => serde_json::to_string_pretty(graph)?,
+            Format::Dot => graph.display_dot().to_string(),
+            Format::Graphml => graph.display_graphml().to_string(),
+            Format::Mermaid => graph.display_mermaid().to_string(),
+            Format::Canonical => graph.to_canonical_text(),
+        })
+    }
+
+    /// The file extension used for per-file outputs in this format.
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Debug => "graph",
+            Format::Json => "graph.json",
+            Format::Dot => "dot",
+            Format::Graphml => "graphml",
+            Format::Mermaid => "mmd",
+            Format::Canonical => "graph.txt",
+        }
+    }
+}
+
+/// Everything needed to execute the TSG file against a source file.  The TSG file is parsed once
+/// and shared by all of the source files that are processed.
+struct Execution<'a> {
+    file: &'a File,
+    tsg: &'a TsgSource,
+    language: Language,
+    functions: &'a Functions,
+    globals: &'a [(String, graph::Value)],
+    lazy: bool,
+    allow_parse_errors: bool,
+    format: Format,
+    /// What the graph shows when rendered in the debug format
+    print_options: &'a PrintOptions,
+    error_format: ErrorFormat,
+    limits: ExecutionLimits,
+    /// Which messages of print statements to show, if not all of them
+    output: Option<&'a StderrOutput>,
+    /// Where to collect the statistics of each stanza, if profiling
+    statistics: Option<&'a Mutex<ExecutionStatistics>>,
+    /// Where to collect the trace of each source file, if tracing
+    traces: Option<&'a Traces>,
+    /// Where to collect the coverage of the TSG file, if requested
+    coverage: Option<&'a Mutex<ExecutionCoverage>>,
+    /// Whether to stop processing source files after the first failure
+    fail_fast: bool,
+}
+
+impl Execution<'_> {
+    /// Executes the TSG file against a source file, returning the rendered graph.
+    fn run(&self, source_path: &Path) -> Result<String> {
+        let _span = tracing::info_span!("source", path = %source_path.display()).entered();
+        let source = if source_path == Path::new(STDIN) {
+            let mut source = String::new();
+            std::io::stdin()
+                .read_to_string(&mut source)
+                .context("Cannot read source from stdin")?;
+            source
+        } else {
+            let source = std::fs::read(source_path)
+                .with_context(|| format!("Cannot read source file {}", source_path.display()))?;
+            String::from_utf8(source)?
+        };
+        let mut parser = Parser::new();
+        parser.set_language(&self.language)?;
+        let tree = parser
+            .parse(&source, None)
+            .ok_or_else(|| anyhow!("Cannot parse {}", source_path.display()))?;
+        if !self.allow_parse_errors {
+            let parse_errors = ParseError::all(&tree);
+            if !parse_errors.is_empty() {
+                for parse_error in parse_errors.iter().take(MAX_PARSE_ERRORS) {
+                    Diagnostic::from_source_error(source_path, &source, parse_error)
+                        .report(self.error_format);
+                }
+                if parse_errors.len() > MAX_PARSE_ERRORS {
+                    let more_errors = parse_errors.len() - MAX_PARSE_ERRORS;
+                    eprintln!(
+                        "{} more parse error{} omitted",
+                        more_errors,
+                        if more_errors > 1 { "s" } else { "" },
+                    );
+                }
+                return Err(
+                    Failure::SourceParse.error(format!("Cannot parse {}", source_path.display()))
+                );
             }
-            if parse_errors.len() > MAX_PARSE_ERRORS {
-                let more_errors = parse_errors.len() - MAX_PARSE_ERRORS;
-                eprintln!(
-                    "{} more parse error{} omitted",
-                    more_errors,
-                    if more_errors > 1 { "s" } else { "" },
+        }
+
+        let mut globals = Variables::new();
+        for (name, value) in self.globals {
+            globals.add(Identifier::from(name.as_str()), value.clone())?;
+        }
+        let statistics = ExecutionStatistics::new();
+        let mut config = ExecutionConfig::new(self.functions, &globals)
+            .lazy(self.lazy)
+            .limits(&self.limits);
+        if let Some(output) = self.output {
+            config = config.output(output);
+        }
+        if self.statistics.is_some() {
+            config = config.statistics(&statistics);
+        }
+        let trace = ExecutionTrace::new();
+        if self.traces.is_some() {
+            config = config.trace(&trace);
+        }
+        let coverage = ExecutionCoverage::new();
+        if self.coverage.is_some() {
+            config = config.coverage(&coverage);
+        }
+        let result = self
+            .file
+            .execute(&tree, &source, &mut config, &NoCancellation);
+        if let Some(traces) = self.traces {
+            // The trace is kept even when the execution fails, since that is when it is needed.
+            traces.add(source_path, trace);
+        }
+        if let Some(total) = self.coverage {
+            total.lock().unwrap().merge(&coverage);
+        }
+        let graph = match result {
+            Ok(graph) => graph,
+            Err(e) => {
+                let mut diagnostic = Diagnostic::from_execution_error(
+                    source_path,
+                    &source,
+                    &self.tsg.path,
+                    &self.tsg.text,
+                    self.file,
+                    &e,
                 );
+                self.tsg.relocate(&mut diagnostic);
+                diagnostic.report(self.error_format);
+                return Err(Failure::of_diagnostic(&diagnostic).error(format!(
+                    "Cannot execute TSG file {} on {}",
+                    self.tsg.path.display(),
+                    source_path.display()
+                )));
             }
-            return Err(anyhow!("Cannot parse {}", source_path.display()));
+        };
+        if let Some(total) = self.statistics {
+            total.lock().unwrap().merge(&statistics);
         }
+
+        self.format.render(&graph, self.print_options)
     }
 
-    let functions = Functions::stdlib();
-    let mut config = ExecutionConfig::new(&functions, &globals_).lazy(lazy);
-    let graph = match file.execute(&tree, &source, &mut config, &NoCancellation) {
-        Ok(graph) => graph,
-        Err(e) => {
-            eprintln!("{}", e.display_pretty(source_path, &source, tsg_path, &tsg));
-            return Err(anyhow!("Cannot execute TSG file {}", tsg_path.display()));
+    /// Executes the TSG file against several source files in parallel, using up to `jobs`
+    /// threads.  Results are returned in the same order as the source files.  There is no result
+    /// for source files that are skipped because an earlier one failed with `fail_fast` set.
+    fn run_all(&self, sources: &[PathBuf], jobs: usize) -> Vec<Option<Result<String>>> {
+        self.run_each(sources, jobs, |_, result| result)
+    }
+
+    /// Executes the TSG file against several source files in parallel, like `run_all`, but hands
+    /// each result to `finish` in the thread that produced it, so that it can be handled as soon as
+    /// it is ready.  Returns what `finish` returns for each source file, in the original order.
+    fn run_each<T, F>(&self, sources: &[PathBuf], jobs: usize, finish: F) -> Vec<Option<T>>
+    where
+        T: Send,
+        F: Fn(&Path, Result<String>) -> T + Sync,
+    {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let results = Mutex::new((0..sources.len()).map(|_| None).collect::<Vec<_>>());
+        std::thread::scope(|scope| {
+            for _ in 0..jobs.max(1).min(sources.len()) {
+                scope.spawn(|| loop {
+                    if self.fail_fast && failed.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= sources.len() {
+                        break;
+                    }
+                    let result = self.run(&sources[index]);
+                    tracing::info!(
+                        "{} {}",
+                        if result.is_ok() {
+                            "processed"
+                        } else {
+                            "failed"
+                        },
+                        sources[index].display()
+                    );
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    let result = finish(&sources[index], result);
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+        results.into_inner().unwrap()
+    }
+}
+
+/// Collects the global variables from `--globals-file` and `--global`, and checks that they
+/// include all of the globals that the TSG file requires.  Globals given with `--global` override
+/// those in the globals file.
+fn load_globals(matches: &ArgMatches, file: &File) -> Result<Vec<(String, graph::Value)>> {
+    let mut globals = Vec::new();
+    for (name, value) in &project::config().globals {
+        let value = json_to_value(value.clone())
+            .with_context(|| format!("Invalid value for global {} in tsg.toml", name))?;
+        globals.push((name.clone(), value));
+    }
+    if let Some(path) = matches.value_of("globals-file") {
+        let content =
+            std::fs::read(path).with_context(|| format!("Cannot read globals file {}", path))?;
+        let json: serde_json::Value = serde_json::from_slice(&content)
+            .with_context(|| format!("Cannot parse globals file {}", path))?;
+        let object = match json {
+            serde_json::Value::Object(object) => object,
+            _ => return Err(anyhow!("Globals file {} must contain a JSON object", path)),
+        };
+        for (name, value) in object {
+            let value = json_to_value(value)
+                .with_context(|| format!("Invalid value for global {} in {}", name, path))?;
+            globals.retain(|(existing, _)| *existing != name);
+            globals.push((name, value));
+        }
+    }
+    for kv in matches.values_of("global").unwrap_or_default() {
+        let (key, value) = kv
+            .split_once('=')
+            .with_context(|| format!("Expected key-value pair separated by '=', got {}.", kv))?;
+        let (name, value) = match key.split_once(':') {
+            None => (key, graph::Value::String(value.to_string())),
+            Some((name, "string")) => (name, graph::Value::String(value.to_string())),
+            Some((name, "int")) => (
+                name,
+                graph::Value::Integer(
+                    value
+                        .parse()
+                        .with_context(|| format!("Invalid integer for global {}", name))?,
+                ),
+            ),
+            Some((name, "bool")) => (
+                name,
+                graph::Value::Boolean(
+                    value
+                        .parse()
+                        .with_context(|| format!("Invalid boolean for global {}", name))?,
+                ),
+            ),
+            Some((name, "json")) => (
+                name,
+                serde_json::from_str(value)
+                    .map_err(anyhow::Error::from)
+                    .and_then(json_to_value)
+                    .with_context(|| format!("Invalid JSON for global {}", name))?,
+            ),
+            Some((_, ty)) => {
+                return Err(anyhow!(
+                    "Unknown global type {}, expected string, int, bool, or json",
+                    ty
+                ))
+            }
+        };
+        globals.retain(|(existing, _)| existing != name);
+        globals.push((name.to_string(), value));
+    }
+    let mut variables = Variables::new();
+    for (name, value) in &globals {
+        variables.add(Identifier::from(name.as_str()), value.clone())?;
+    }
+    file.validate_globals(&variables)
+        .context("Cannot execute TSG file")?;
+    Ok(globals)
+}
+
+/// Converts a JSON value into a graph value.  Objects, and numbers that are not `i64`s, have no
+/// graph equivalent.
+fn json_to_value(json: serde_json::Value) -> Result<graph::Value> {
+    Ok(match json {
+        serde_json::Value::Null => graph::Value::Null,
+        serde_json::Value::Bool(value) => graph::Value::Boolean(value),
+        serde_json::Value::Number(number) => number
+            .as_i64()
+            .map(graph::Value::Integer)
+            .ok_or_else(|| anyhow!("Unsupported number {}", number))?,
+        serde_json::Value::String(value) => graph::Value::String(value),
+        serde_json::Value::Array(values) => graph::Value::List(
+            values
+                .into_iter()
+                .map(json_to_value)
+                .collect::<Result<_>>()?,
+        ),
+        serde_json::Value::Object(_) => return Err(anyhow!("Objects are not supported")),
+    })
+}
+
+/// The TSG program to execute, which can be composed from several TSG files.  The files are
+/// concatenated in the order that they were given on the command line, with the files in a
+/// directory sorted by name.
+struct TsgSource {
+    /// The path used when reporting errors against the combined text.  For a single file, this is
+    /// the path of that file.
+    path: PathBuf,
+    /// The combined text of all of the files.
+    text: String,
+    /// Each file, along with its text and the line in the combined text that it starts at.
+    files: Vec<(PathBuf, String, usize)>,
+}
+
+impl TsgSource {
+    fn load(paths: &[PathBuf]) -> Result<TsgSource> {
+        let mut text = String::new();
+        let mut files = Vec::new();
+        for path in paths {
+            let content = std::fs::read(path)
+                .with_context(|| format!("Cannot read TSG file {}", path.display()))?;
+            let content = String::from_utf8(content)
+                .with_context(|| format!("TSG file {} is not valid UTF-8", path.display()))?;
+            files.push((path.clone(), content.clone(), text.lines().count()));
+            text += &content;
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+        }
+        let path = match files.as_slice() {
+            [(path, _, _)] => path.clone(),
+            _ => PathBuf::from(
+                files
+                    .iter()
+                    .map(|(path, _, _)| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("+"),
+            ),
+        };
+        Ok(TsgSource { path, text, files })
+    }
+
+    /// Parses and checks the TSG files, reporting any error.  If the project configuration has a
+    /// cache directory, the checked files are loaded from it, or stored in it.
+    fn parse(&self, language: &Language, error_format: ErrorFormat) -> Result<File> {
+        let cache_dir = project::config().cache_dir.as_deref();
+        let cache_source = self.cache_source();
+        if let Some(file) =
+            cache_dir.and_then(|cache_dir| cache::load(cache_dir, language, &cache_source))
+        {
+            return Ok(file);
+        }
+        let file = self.compile(language).map_err(|err| {
+            err.report(error_format);
+            Failure::of_diagnostic(&err)
+                .error(format!("Cannot parse TSG file {}", err.path.display()))
+        })?;
+        if let Some(cache_dir) = cache_dir {
+            cache::store(cache_dir, language, &cache_source, &file);
+        }
+        Ok(file)
+    }
+
+    /// Returns the text that identifies the TSG files in the cache.  This is the combined text,
+    /// along with where each file starts, since each file is parsed on its own as well.
+    fn cache_source(&self) -> String {
+        let mut source = String::new();
+        for (_, content, _) in &self.files {
+            source += &format!("{}\n{}", content.len(), content);
+        }
+        source
+    }
+
+    /// Parses and checks the TSG files.  Each file is first only parsed on its own, so that syntax
+    /// errors are reported against the file that contains them, and global variables that are
+    /// declared in more than one file are reported as conflicts.  The combined text is then
+    /// checked once, since a file can use the globals and stanzas of the others.
+    fn compile(&self, language: &Language) -> std::result::Result<File, Box<Diagnostic>> {
+        if self.files.len() == 1 {
+            return File::from_str(language.clone(), &self.text)
+                .map_err(|err| Box::new(Diagnostic::from_tsg_error(&self.path, &self.text, &err)));
         }
+        let mut declared = std::collections::HashMap::new();
+        for (path, content, _) in &self.files {
+            let (file, errors) = File::parse_with_recovery(language.clone(), content);
+            if let Some(err) = errors.iter().min_by_key(|err| err.location()) {
+                return Err(Box::new(Diagnostic::from_tsg_error(path, content, err)));
+            }
+            for global in &file.globals {
+                if let Some((previous_path, previous_location)) =
+                    declared.insert(global.name.clone(), (path, global.location))
+                {
+                    let message = format!(
+                        "Global variable {} is declared in both {} {} and {} {}",
+                        global.name,
+                        previous_path.display(),
+                        previous_location,
+                        path.display(),
+                        global.location,
+                    );
+                    let previous_content = &self
+                        .files
+                        .iter()
+                        .find(|(path, _, _)| path == previous_path)
+                        .expect("missing TSG file")
+                        .1;
+                    let pretty = format!(
+                        "Duplicate global variable {}\n{}previously declared\n{}",
+                        global.name,
+                        excerpt(path, content, global.location),
+                        excerpt(previous_path, previous_content, previous_location),
+                    );
+                    return Err(Box::new(Diagnostic {
+                        code: "duplicate-global",
+                        severity: Severity::Error,
+                        message,
+                        path: path.clone(),
+                        span: Some(global.location.into()),
+                        related: vec![(
+                            "previously declared".to_string(),
+                            previous_path.clone(),
+                            previous_location.into(),
+                        )],
+                        help: None,
+                        pretty,
+                    }));
+                }
+            }
+        }
+        File::from_str(language.clone(), &self.text).map_err(|err| {
+            let mut diagnostic = Diagnostic::from_tsg_error(&self.path, &self.text, &err);
+            self.relocate(&mut diagnostic);
+            Box::new(diagnostic)
+        })
+    }
+
+    /// Updates a diagnostic whose locations are in the combined text, so that they refer to the
+    /// files that contain them instead.
+    fn relocate(&self, diagnostic: &mut Diagnostic) {
+        if self.files.len() == 1 {
+            return;
+        }
+        let locate = |path: &mut PathBuf, span: &mut Span| {
+            if *path != self.path {
+                return;
+            }
+            let start = span.start.row;
+            let (file_path, location) = self.locate(span.start);
+            *path = file_path.to_path_buf();
+            span.start = location;
+            if let Some(end) = &mut span.end {
+                end.row = end.row.saturating_sub(start - location.row);
+            }
+        };
+        if let Some(span) = &mut diagnostic.span {
+            locate(&mut diagnostic.path, span);
+        }
+        for (_, path, span) in &mut diagnostic.related {
+            locate(path, span);
+        }
+        diagnostic.pretty = format!("{}\n{}", diagnostic.pretty, self.line_ranges());
+    }
+
+    /// Finds the file that contains a location in the combined text, and the location within
+    /// that file.
+    fn locate(&self, location: Location) -> (&Path, Location) {
+        let (path, _, start) = self
+            .files
+            .iter()
+            .rev()
+            .find(|(_, _, start)| *start <= location.row)
+            .unwrap_or(&self.files[0]);
+        let location = Location {
+            row: location.row - start,
+            column: location.column,
+        };
+        (path, location)
+    }
+
+    /// Describes which lines of the combined text come from which file.
+    fn line_ranges(&self) -> String {
+        let mut result = String::from("note: the combined TSG source consists of");
+        for (path, content, start) in &self.files {
+            result += &format!(
+                "\n  {} (lines {}-{})",
+                path.display(),
+                start + 1,
+                start + content.lines().count(),
+            );
+        }
+        result
+    }
+}
+
+/// Shows the line of a TSG file that contains `location`.
+fn excerpt<'a>(path: &'a Path, content: &'a str, location: Location) -> Excerpt<'a> {
+    Excerpt::from_source(
+        path,
+        content,
+        location.row,
+        location.column..location.column + 1,
+        0,
+    )
+}
+
+/// Finds the TSG files to execute.
+fn find_tsg_files(matches: &ArgMatches) -> Result<Vec<PathBuf>> {
+    expand_tsg_paths(
+        project::tsg_paths(matches.value_of("tsg"))
+            .into_iter()
+            .chain(matches.values_of("more-tsg").unwrap_or_default()),
+    )
+}
+
+/// Disables the stanzas excluded by `--only-stanza` or `--skip-stanza`.  Stanzas are given by
+/// name, or by their index in the TSG files, counting from zero.
+fn filter_stanzas(matches: &ArgMatches, file: &mut File) -> Result<()> {
+    let stanza_count = file.stanzas.len();
+    let find = |arg: &str| match arg.parse::<usize>() {
+        Ok(index) if index < stanza_count => Ok(index),
+        Ok(index) => Err(anyhow!(
+            "No stanza {}, the TSG files contain {} stanzas",
+            index,
+            stanza_count
+        )),
+        Err(_) => file
+            .stanza_index(arg)
+            .ok_or_else(|| anyhow!("No stanza named {}", arg)),
     };
+    let only = match matches.values_of("only-stanza") {
+        Some(args) => Some(args.map(find).collect::<Result<HashSet<_>>>()?),
+        None => None,
+    };
+    let skip = matches
+        .values_of("skip-stanza")
+        .unwrap_or_default()
+        .map(find)
+        .collect::<Result<HashSet<_>>>()?;
+    for index in 0..stanza_count {
+        if skip.contains(&index) || only.as_ref().is_some_and(|only| !only.contains(&index)) {
+            file.disable_stanza(index);
+        }
+    }
+    Ok(())
+}
 
-    let json = matches.is_present("json");
-    let output_path = matches.value_of("output").map(|str| Path::new(str));
-    if json {
-        graph.display_json(output_path).unwrap_or(());
-    } else if !quiet {
-        print!("{}", graph.pretty_print());
+/// Returns the language scope given with `--scope`, or else the one that the project configuration
+/// gives for the source file's extension.
+fn scope<'a>(matches: &'a ArgMatches, source_path: &Path) -> Option<&'a str> {
+    matches
+        .value_of("scope")
+        .or_else(|| project::config().scope_for(source_path))
+}
+
+/// Expands directories to the `.tsg` files they contain, in sorted order.
+fn expand_tsg_paths<'a>(args: impl IntoIterator<Item = &'a str>) -> Result<Vec<PathBuf>> {
+    let mut tsg_files = Vec::new();
+    for arg in args {
+        let path = Path::new(arg);
+        if path.is_dir() {
+            let mut files = Vec::new();
+            find_files(path, &mut files)?;
+            files.retain(|file| file.extension().is_some_and(|ext| ext == "tsg"));
+            if files.is_empty() {
+                return Err(anyhow!("No TSG files found in {}", path.display()));
+            }
+            tsg_files.extend(files);
+        } else {
+            tsg_files.push(path.to_path_buf());
+        }
     }
+    Ok(tsg_files)
+}
+
+/// Expands the source arguments into a list of source files.  Arguments can be files, directories,
+/// or glob patterns.  Directories are searched recursively.  Files found by searching directories
+/// or expanding patterns are only included if they have the same language as the first source file
+/// (or the language named by `scope`); files that are named explicitly are always included.
+fn find_sources(loader: &Loader, args: &[&str], scope: Option<&str>) -> Result<Vec<PathBuf>> {
+    let mut sources = Vec::new();
+    let mut candidates = Vec::new();
+    for arg in args {
+        let path = Path::new(arg);
+        if path.is_dir() {
+            find_files(path, &mut candidates)?;
+        } else if arg.contains(&['*', '?', '['][..]) {
+            for entry in glob::glob(arg).with_context(|| format!("Invalid pattern {}", arg))? {
+                let entry = entry?;
+                if entry.is_dir() {
+                    find_files(&entry, &mut candidates)?;
+                } else {
+                    candidates.push(entry);
+                }
+            }
+        } else {
+            sources.push(path.to_path_buf());
+            continue;
+        }
+        // Found files are only kept if they have the expected language.
+        let expected = match scope {
+            Some(scope) => loader.language_configuration_for_scope(scope)?,
+            None => match sources.first().or_else(|| candidates.first()) {
+                Some(first) => loader.language_configuration_for_file_name(first)?,
+                None => None,
+            },
+        }
+        .map(|(_, configuration)| configuration as *const _);
+        for candidate in candidates.drain(..) {
+            let configuration = loader
+                .language_configuration_for_file_name(&candidate)?
+                .map(|(_, configuration)| configuration as *const _);
+            if configuration.is_some() && configuration == expected {
+                sources.push(candidate);
+            }
+        }
+    }
+    Ok(sources)
+}
 
+/// Recursively collects the files in a directory, in sorted order, skipping hidden entries.
+fn find_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Cannot read directory {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if entry.is_dir() {
+            find_files(&entry, files)?;
+        } else {
+            files.push(entry);
+        }
+    }
     Ok(())
 }
 
-fn init_log() {
-    let _ = env_logger::builder()
-        .format_level(false)
-        .format_target(false)
-        .format_timestamp(None)
-        .init();
+/// Sets up logging to stderr.  Warnings are logged by default, and the level is adjusted by `-v` and
+/// `-q`.  Without either, `RUST_LOG` can select the level instead.  Messages are logged within the
+/// spans of the source file and stanza that they are about, which JSON records list as well.
+fn init_log(matches: &ArgMatches) {
+    let verbose = matches.get_count("verbose");
+    let quiet = matches.is_present("quiet");
+    let level = match verbose {
+        _ if quiet => LevelFilter::ERROR,
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) if verbose == 0 && !quiet => filter,
+        _ => EnvFilter::default().add_directive(level.into()),
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    if matches.value_of("log-format") == Some("json") {
+        builder.json().init();
+    } else {
+        builder
+            .without_time()
+            .with_level(false)
+            .with_target(false)
+            .init();
+    }
 }
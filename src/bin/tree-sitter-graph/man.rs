@@ -0,0 +1,164 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The `man` subcommand, which prints a man page for the command or one of its subcommands.  The
+//! page is generated from the same definitions as `--help`, so it is always up to date.
+
+use anyhow::anyhow;
+use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+
+pub fn command() -> App<'static> {
+    App::new("man")
+        .about("Prints a man page in roff format")
+        .long_about(
+            "Prints a man page in roff format.  For example, to read it:\n\n    \
+             tree-sitter-graph man | man -l -",
+        )
+        .arg(
+            Arg::with_name("subcommand")
+                .index(1)
+                .help("Subcommand to print the man page of, instead of the main command"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let mut command = crate::command();
+    command.build();
+    let page = match matches.value_of("subcommand") {
+        Some(name) => {
+            let parent = command.get_name().to_string();
+            let subcommand = command
+                .get_subcommands_mut()
+                .find(|subcommand| subcommand.get_name() == name)
+                .ok_or_else(|| anyhow!("Unknown subcommand {}", name))?;
+            render(subcommand, &format!("{}-{}", parent, name))
+        }
+        None => {
+            let name = command.get_name().to_string();
+            render(&mut command, &name)
+        }
+    };
+    print!("{}", page);
+    Ok(())
+}
+
+/// Renders the man page of a command.
+fn render(command: &mut App, title: &str) -> String {
+    let mut page = String::new();
+    page += &format!(
+        ".TH {} 1 \"\" \"{}\"\n",
+        escape(&title.to_uppercase()),
+        escape(&format!("tree-sitter-graph {}", crate::BUILD_VERSION))
+    );
+
+    page += ".SH NAME\n";
+    page += &escape(title);
+    if let Some(about) = command.get_about() {
+        page += &format!(" \\- {}", escape(about));
+    }
+    page += "\n";
+
+    page += ".SH SYNOPSIS\n";
+    let usage = command.render_usage();
+    for line in usage.lines().skip(1) {
+        page += &format!(".B {}\n.br\n", escape(line.trim()));
+    }
+
+    if let Some(about) = command.get_long_about().or_else(|| command.get_about()) {
+        page += ".SH DESCRIPTION\n";
+        page += &paragraphs(about);
+    }
+
+    let (positionals, options): (Vec<_>, Vec<_>) = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .partition(|arg| arg.is_positional());
+    if !positionals.is_empty() {
+        page += ".SH ARGUMENTS\n";
+        for arg in positionals {
+            page += &format!(".TP\n\\fI<{}>\\fR\n", escape(arg.get_id()));
+            page += &help(arg);
+        }
+    }
+    if !options.is_empty() {
+        page += ".SH OPTIONS\n";
+        for arg in options {
+            let mut names = Vec::new();
+            if let Some(short) = arg.get_short() {
+                names.push(format!("\\fB\\-{}\\fR", short));
+            }
+            if let Some(long) = arg.get_long() {
+                names.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+            }
+            page += &format!(".TP\n{}", names.join(", "));
+            if arg.is_takes_value_set() {
+                page += &format!(" \\fI<{}>\\fR", escape(arg.get_id()));
+            }
+            page += "\n";
+            page += &help(arg);
+        }
+    }
+
+    let subcommands = command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .collect::<Vec<_>>();
+    if !subcommands.is_empty() {
+        page += ".SH SUBCOMMANDS\n";
+        for subcommand in subcommands {
+            page += &format!(".TP\n\\fB{}\\fR\n", escape(subcommand.get_name()));
+            page += &format!("{}\n", escape(subcommand.get_about().unwrap_or_default()));
+        }
+    }
+    page
+}
+
+/// Renders the help of an argument, along with its possible values.
+fn help(arg: &Arg) -> String {
+    let mut help = escape(arg.get_long_help().or(arg.get_help()).unwrap_or_default());
+    if let Some(values) = arg.get_possible_values() {
+        let values = values
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name())
+            .collect::<Vec<_>>();
+        help += &format!(" [possible values: {}]", escape(&values.join(", ")));
+    }
+    help.push('\n');
+    help
+}
+
+/// Renders text as paragraphs.  Indented lines are shown as they are, for examples.
+fn paragraphs(text: &str) -> String {
+    let mut result = String::new();
+    for paragraph in text.split("\n\n") {
+        if paragraph.starts_with(' ') {
+            result += &format!(".PP\n.nf\n{}\n.fi\n", escape(paragraph.trim()));
+        } else {
+            result += &format!(".PP\n{}\n", escape(paragraph));
+        }
+    }
+    result
+}
+
+/// Escapes text so that it is not interpreted as roff requests or escapes.
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\\\").replace('-', "\\-");
+    text.lines()
+        .map(|line| {
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
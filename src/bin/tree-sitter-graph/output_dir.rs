@@ -0,0 +1,189 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Writing of the graphs of many source files to an output directory, with `--output-dir`.  Each
+//! graph is written as soon as it is ready, to a path that mirrors the source file's path below
+//! the directory that contains all of the source files.  Once every source file is processed, an
+//! `index.json` manifest lists the outputs.
+//!
+//! With `--changed-since`, the output directory is updated incrementally: only the source files
+//! that changed, or that have no output yet, are executed.  The manifest keeps the entries of the
+//! other source files, and outputs of source files that no longer exist are removed.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use anyhow::Result;
+use serde_json::json;
+
+use crate::exit::Failure;
+use crate::git;
+use crate::Execution;
+
+/// The name of the manifest in the output directory.
+const MANIFEST: &str = "index.json";
+
+/// Executes the TSG file against each source file, writing the graphs to `output_dir`.  If
+/// `changed` is given, only the changed source files are executed, and the results are merged
+/// into the existing output directory.  Returns the number of source files whose outputs are up to
+/// date, including those that were not executed because they did not change, and the kind of
+/// failure of each source file that failed, which have already been reported.
+pub fn write(
+    execution: &Execution,
+    sources: &[PathBuf],
+    changed: Option<&HashSet<PathBuf>>,
+    jobs: usize,
+    output_dir: &Path,
+    extension: &str,
+) -> Result<(usize, Vec<Option<Failure>>)> {
+    let base = common_base(sources);
+    let manifest_path = output_dir.join(MANIFEST);
+    let mut previous = match changed {
+        Some(_) => read_manifest(&manifest_path)?,
+        None => HashMap::new(),
+    };
+    let stale = sources
+        .iter()
+        .filter(|source| match changed {
+            Some(changed) => {
+                git::is_changed(changed, source)
+                    || !previous.contains_key(&source.display().to_string())
+            }
+            None => true,
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    tracing::info!(
+        "executing {} of {} source files",
+        stale.len(),
+        sources.len()
+    );
+
+    let results = execution.run_each(&stale, jobs, |source_path, result| {
+        let relative = output_path(source_path, &base, extension);
+        let written = result.and_then(|output| {
+            let path = output_dir.join(&relative);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Cannot create directory {}", parent.display()))?;
+            }
+            std::fs::write(&path, output)
+                .with_context(|| format!("Cannot write {}", path.display()))
+        });
+        match written {
+            Ok(()) => (
+                json!({
+                    "source": source_path.display().to_string(),
+                    "output": relative.display().to_string(),
+                    "status": "ok",
+                }),
+                None,
+            ),
+            Err(e) => {
+                eprintln!("{:?}", e);
+                // Do not leave the graph of a previous run behind.
+                let _ = std::fs::remove_file(output_dir.join(&relative));
+                let entry = json!({
+                    "source": source_path.display().to_string(),
+                    "status": "error",
+                    "error": e.to_string(),
+                });
+                (entry, Some(Failure::of(&e)))
+            }
+        }
+    });
+    // Source files skipped by --fail-fast keep the entries of the previous run, if any.
+    let mut succeeded = sources.len() - stale.len();
+    let mut failures = Vec::new();
+    for (source_path, result) in stale.iter().zip(results) {
+        if let Some((entry, failure)) = result {
+            match failure {
+                Some(failure) => failures.push(failure),
+                None => succeeded += 1,
+            }
+            previous.insert(source_path.display().to_string(), entry);
+        }
+    }
+
+    // Entries are listed in the order of the source files.  Any others are for source files that
+    // no longer exist, so their outputs are removed.
+    let mut entries = Vec::new();
+    for source_path in sources {
+        if let Some(entry) = previous.remove(&source_path.display().to_string()) {
+            entries.push(entry);
+        }
+    }
+    for entry in previous.values() {
+        if let Some(output) = entry["output"].as_str() {
+            let path = output_dir.join(output);
+            if path.is_file() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Cannot remove {}", path.display()))?;
+            }
+        }
+    }
+
+    std::fs::create_dir_all(output_dir)
+        .with_context(|| format!("Cannot create directory {}", output_dir.display()))?;
+    let manifest = json!({ "files": entries });
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Cannot write {}", manifest_path.display()))?;
+    Ok((succeeded, failures))
+}
+
+/// Reads the entries of an existing manifest, keyed by source file.  A missing manifest has no
+/// entries.
+fn read_manifest(path: &Path) -> Result<HashMap<String, serde_json::Value>> {
+    let mut entries = HashMap::new();
+    if !path.exists() {
+        return Ok(entries);
+    }
+    let content = std::fs::read(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let manifest: serde_json::Value = serde_json::from_slice(&content)
+        .with_context(|| format!("Cannot parse {}", path.display()))?;
+    for entry in manifest["files"].as_array().into_iter().flatten() {
+        if let Some(source) = entry["source"].as_str() {
+            entries.insert(source.to_string(), entry.clone());
+        }
+    }
+    Ok(entries)
+}
+
+/// Returns the deepest directory that contains all of the source files.
+fn common_base(sources: &[PathBuf]) -> PathBuf {
+    let mut base = match sources.first().and_then(|source| source.parent()) {
+        Some(parent) => parent.to_path_buf(),
+        None => return PathBuf::new(),
+    };
+    for source in &sources[1..] {
+        while !source.starts_with(&base) {
+            if !base.pop() {
+                return PathBuf::new();
+            }
+        }
+    }
+    base
+}
+
+/// Returns the path, relative to the output directory, that the graph of a source file is written
+/// to.  Only the normal components of the source path below the base are kept, so that the output
+/// cannot escape the output directory.
+fn output_path(source_path: &Path, base: &Path, extension: &str) -> PathBuf {
+    let relative = source_path.strip_prefix(base).unwrap_or(source_path);
+    let mut path = relative
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect::<PathBuf>()
+        .into_os_string();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
@@ -0,0 +1,149 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Reporting of the per-stanza statistics collected with `--profile`.
+
+use clap::ArgMatches;
+use serde_json::json;
+use tree_sitter_graph::ExecutionStatistics;
+use tree_sitter_graph::StanzaStatistics;
+
+use crate::TsgSource;
+
+/// The longest excerpt of a stanza's query that is shown in the profile table.
+const MAX_QUERY_LENGTH: usize = 40;
+
+/// How the profile is reported.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProfileFormat {
+    /// A table with the most expensive stanzas first
+    Table,
+    /// A JSON array with a record for each stanza, in the order of the TSG file
+    Json,
+}
+
+impl ProfileFormat {
+    /// Returns the requested profile format, or `None` if profiling is not enabled.
+    pub fn from_matches(matches: &ArgMatches) -> Option<ProfileFormat> {
+        if !matches.is_present("profile") && !matches.is_present("profile-output") {
+            return None;
+        }
+        match matches.value_of("profile-output") {
+            Some("json") => Some(ProfileFormat::Json),
+            _ => Some(ProfileFormat::Table),
+        }
+    }
+}
+
+/// Prints the statistics of each stanza to stderr.
+pub fn report(statistics: &ExecutionStatistics, tsg: &TsgSource, format: ProfileFormat) {
+    let stanzas = statistics.stanzas();
+    match format {
+        ProfileFormat::Table => eprint!("{}", table(&stanzas, tsg)),
+        ProfileFormat::Json => {
+            let records = stanzas
+                .iter()
+                .map(|stanza| {
+                    let (path, location) = tsg.locate(stanza.location);
+                    json!({
+                        "path": path.display().to_string(),
+                        "line": location.row + 1,
+                        "column": location.column + 1,
+                        "query": query(stanza, tsg),
+                        "matches": stanza.matches,
+                        "time_ms": stanza.duration.as_secs_f64() * 1000.0,
+                        "nodes": stanza.nodes,
+                        "edges": stanza.edges,
+                    })
+                })
+                .collect::<Vec<_>>();
+            eprintln!("{}", serde_json::Value::Array(records));
+        }
+    }
+}
+
+fn table(stanzas: &[StanzaStatistics], tsg: &TsgSource) -> String {
+    let mut stanzas = stanzas.iter().collect::<Vec<_>>();
+    stanzas.sort_by_key(|stanza| std::cmp::Reverse(stanza.duration));
+    let rows = stanzas
+        .iter()
+        .map(|stanza| {
+            let (path, location) = tsg.locate(stanza.location);
+            [
+                format!(
+                    "{}:{}:{}",
+                    path.display(),
+                    location.row + 1,
+                    location.column + 1
+                ),
+                query(stanza, tsg),
+                stanza.matches.to_string(),
+                format!("{:.3}", stanza.duration.as_secs_f64() * 1000.0),
+                stanza.nodes.to_string(),
+                stanza.edges.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = ["stanza", "query", "matches", "time (ms)", "nodes", "edges"].map(String::from);
+    let mut widths = header.clone().map(|column| column.chars().count());
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+    let mut result = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        // The first two columns are text, and the rest are numbers.
+        let line = format!(
+            "{:<w0$}  {:<w1$}  {:>w2$}  {:>w3$}  {:>w4$}  {:>w5$}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            row[5],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+            w5 = widths[5],
+        );
+        result += line.trim_end();
+        result.push('\n');
+    }
+    let total = stanzas
+        .iter()
+        .map(|stanza| stanza.duration)
+        .sum::<std::time::Duration>();
+    result += &format!(
+        "{} stanzas, {} matches, {:.3} ms\n",
+        stanzas.len(),
+        stanzas.iter().map(|stanza| stanza.matches).sum::<usize>(),
+        total.as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Returns the first line of a stanza's query, shortened if it is long.
+fn query(stanza: &StanzaStatistics, tsg: &TsgSource) -> String {
+    let line = tsg
+        .text
+        .lines()
+        .nth(stanza.location.row)
+        .unwrap_or_default()
+        .chars()
+        .skip(stanza.location.column)
+        .collect::<String>();
+    let line = line.trim_end();
+    if line.chars().count() > MAX_QUERY_LENGTH {
+        let prefix = line.chars().take(MAX_QUERY_LENGTH - 3).collect::<String>();
+        format!("{}...", prefix)
+    } else {
+        line.to_string()
+    }
+}
@@ -0,0 +1,171 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Project configuration, read from a `tsg.toml` file in the current directory or the nearest of
+//! its parents.  The configuration provides defaults for arguments that would otherwise have to be
+//! given on every invocation:
+//!
+//! ```toml
+//! # TSG files or directories, relative to this file
+//! tsg = ["rules/"]
+//! # Output format of the graph, and format of errors
+//! format = "json"
+//! error-format = "human"
+//! # Directory where checked TSG files are cached, relative to this file
+//! cache-dir = ".tsg-cache"
+//!
+//! # Language scopes of source files, by file extension
+//! [languages]
+//! pyi = "source.python"
+//!
+//! # Global variables, which --globals-file and --global override
+//! [globals]
+//! root = "src"
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+
+/// The name of the project configuration file.
+pub const CONFIG_FILE: &str = "tsg.toml";
+
+static CONFIG: OnceLock<ProjectConfig> = OnceLock::new();
+
+/// The settings in a project configuration file.
+#[derive(Default)]
+pub struct ProjectConfig {
+    /// TSG files or directories to use when none are given
+    pub tsg: Vec<String>,
+    /// The output format to use when none is given
+    pub format: Option<String>,
+    /// The error format to use when none is given
+    pub error_format: Option<String>,
+    /// The directory to cache checked TSG files in, if they are cached
+    pub cache_dir: Option<PathBuf>,
+    /// Language scopes, keyed by file extension
+    pub languages: HashMap<String, String>,
+    /// Global variables, as JSON values
+    pub globals: Vec<(String, serde_json::Value)>,
+}
+
+/// Returns the project configuration.  It is empty if there is no configuration file, or if
+/// [`load`][] has not been called.
+pub fn config() -> &'static ProjectConfig {
+    CONFIG.get_or_init(ProjectConfig::default)
+}
+
+/// Finds and reads the project configuration file, searching upward from the current directory.
+pub fn load() -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let config = match current_dir
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file())
+    {
+        Some(path) => {
+            ProjectConfig::read(&path).with_context(|| format!("Invalid {}", path.display()))?
+        }
+        None => ProjectConfig::default(),
+    };
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+impl ProjectConfig {
+    fn read(path: &Path) -> Result<ProjectConfig> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let table = match content.parse::<toml::Value>()? {
+            toml::Value::Table(table) => table,
+            _ => unreachable!("TOML documents are tables"),
+        };
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut config = ProjectConfig::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "tsg" => {
+                    let paths = match value {
+                        toml::Value::String(path) => vec![toml::Value::String(path)],
+                        toml::Value::Array(paths) => paths,
+                        _ => return Err(anyhow!("tsg must be a path or an array of paths")),
+                    };
+                    for path in paths {
+                        let path = path
+                            .as_str()
+                            .ok_or_else(|| anyhow!("tsg must be a path or an array of paths"))?;
+                        config.tsg.push(dir.join(path).display().to_string());
+                    }
+                }
+                "format" => {
+                    config.format = Some(choice(&key, value, &crate::FORMATS)?);
+                }
+                "error-format" => {
+                    config.error_format = Some(choice(&key, value, &["human", "json"])?);
+                }
+                "cache-dir" => {
+                    let path = value
+                        .as_str()
+                        .ok_or_else(|| anyhow!("cache-dir must be a path"))?;
+                    config.cache_dir = Some(dir.join(path));
+                }
+                "languages" => {
+                    for (extension, scope) in table_of(&key, value)? {
+                        let scope = scope.as_str().ok_or_else(|| {
+                            anyhow!("Scope of .{} files must be a string", extension)
+                        })?;
+                        config.languages.insert(extension, scope.to_string());
+                    }
+                }
+                "globals" => {
+                    for (name, value) in table_of(&key, value)? {
+                        let value = serde_json::to_value(value)
+                            .with_context(|| format!("Invalid value for global {}", name))?;
+                        config.globals.push((name, value));
+                    }
+                }
+                _ => return Err(anyhow!("Unknown setting {}", key)),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Returns the language scope for a source file, based on its extension.
+    pub fn scope_for(&self, source_path: &Path) -> Option<&str> {
+        let extension = source_path.extension()?.to_str()?;
+        self.languages.get(extension).map(String::as_str)
+    }
+}
+
+/// Checks that a setting is one of the allowed strings.
+fn choice(key: &str, value: toml::Value, allowed: &[&str]) -> Result<String> {
+    match value {
+        toml::Value::String(value) if allowed.contains(&value.as_str()) => Ok(value),
+        _ => Err(anyhow!("{} must be one of {}", key, allowed.join(", "))),
+    }
+}
+
+fn table_of(key: &str, value: toml::Value) -> Result<toml::value::Table> {
+    match value {
+        toml::Value::Table(table) => Ok(table),
+        _ => Err(anyhow!("{} must be a table", key)),
+    }
+}
+
+/// Returns the TSG files or directories to use: the ones given with the `tsg` argument, or else
+/// the ones in the project configuration.
+pub fn tsg_paths(tsg: Option<&str>) -> Vec<&str> {
+    match tsg {
+        Some(tsg) => vec![tsg],
+        None => config().tsg.iter().map(String::as_str).collect(),
+    }
+}
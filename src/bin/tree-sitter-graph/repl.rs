@@ -0,0 +1,263 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The `repl` subcommand, which parses a source file once and then runs query patterns and stanzas
+//! against it as they are typed.  A query pattern on its own shows the nodes that it matches.
+//! Anything else, such as a stanza or a global declaration, is added to the program, which is then
+//! executed again to show how the graph changed.
+
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use similar::ChangeTag;
+use similar::TextDiff;
+use tree_sitter::Language;
+use tree_sitter::Parser;
+use tree_sitter::Tree;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::Identifier;
+use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::ParseError;
+use tree_sitter_graph::Variables;
+
+use crate::diagnostic::Diagnostic;
+use crate::diagnostic::ErrorFormat;
+use crate::explain::describe_node;
+use crate::globals_args;
+use crate::language_args;
+use crate::load_globals;
+use crate::load_languages;
+use crate::scope;
+
+/// The path that errors in the typed program are reported against.
+const REPL_PATH: &str = "<repl>";
+
+const HELP: &str = "\
+Type a query pattern to see the nodes that it matches, or a stanza or other TSG item to add it
+to the program and see how the graph changes.  Input continues over several lines until all
+brackets are closed.  Commands:
+  :program  show the program
+  :graph    show the whole graph
+  :undo     remove the last item added to the program
+  :reset    remove every item from the program
+  :help     show this message
+  :quit     exit (as does end of input)";
+
+pub fn command() -> App<'static> {
+    App::new("repl")
+        .about("Interactively runs query patterns and stanzas against a source file")
+        .arg(
+            Arg::with_name("source")
+                .index(1)
+                .required(true)
+                .help("Source file to run against"),
+        )
+        .args(language_args())
+        .args(globals_args())
+        .arg(
+            Arg::with_name("lazy")
+                .short('z')
+                .long("lazy")
+                .help("Use lazy evaluation (experimental)"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let source_path = Path::new(matches.value_of("source").unwrap());
+    let current_dir = std::env::current_dir()?;
+    let mut loader = load_languages(matches)?;
+    let language =
+        loader.select_language(source_path, &current_dir, scope(matches, source_path))?;
+    let source = std::fs::read_to_string(source_path)
+        .with_context(|| format!("Cannot read source file {}", source_path.display()))?;
+    let mut parser = Parser::new();
+    parser.set_language(&language)?;
+    let tree = parser
+        .parse(&source, None)
+        .ok_or_else(|| anyhow!("Cannot parse {}", source_path.display()))?;
+
+    let mut repl = Repl {
+        matches,
+        language,
+        source_path,
+        source: &source,
+        tree: &tree,
+        functions: Functions::stdlib(),
+        error_format: ErrorFormat::from_matches(matches),
+        items: Vec::new(),
+        graph: String::new(),
+    };
+    println!("{}", HELP);
+    let stdin = std::io::stdin();
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { "tsg> " } else { "...> " });
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            match line.trim() {
+                ":program" => print!("{}", repl.program()),
+                ":graph" => print!("{}", repl.graph),
+                ":undo" => {
+                    repl.items.pop();
+                    match repl.execute(&repl.program()) {
+                        Ok(graph) => repl.graph = graph,
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                ":reset" => {
+                    repl.items.clear();
+                    repl.graph.clear();
+                }
+                ":help" => println!("{}", HELP),
+                ":quit" => return Ok(()),
+                command => println!("Unknown command {}, try :help", command),
+            }
+            continue;
+        }
+        input += &line;
+        if let Err(ParseError::UnexpectedEOF(_)) = File::format(&input) {
+            continue;
+        }
+        let item = std::mem::take(&mut input);
+        if !item.trim().is_empty() {
+            repl.evaluate(item);
+        }
+    }
+}
+
+/// The state of an interactive session.
+struct Repl<'a> {
+    matches: &'a ArgMatches,
+    language: Language,
+    source_path: &'a Path,
+    source: &'a str,
+    tree: &'a Tree,
+    functions: Functions,
+    error_format: ErrorFormat,
+    /// The items of the program, in the order that they were typed
+    items: Vec<String>,
+    /// The graph that the program produces
+    graph: String,
+}
+
+impl Repl<'_> {
+    fn program(&self) -> String {
+        self.items.concat()
+    }
+
+    /// Runs a query pattern, or adds any other item to the program.
+    fn evaluate(&mut self, item: String) {
+        if is_query(&item) {
+            self.show_matches(&item);
+            return;
+        }
+        let program = self.program() + &item;
+        let graph = match self.execute(&program) {
+            Ok(graph) => graph,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let mut changed = false;
+        for change in TextDiff::from_lines(&self.graph, &graph).iter_all_changes() {
+            let sign = match change.tag() {
+                ChangeTag::Delete => "-",
+                ChangeTag::Insert => "+",
+                ChangeTag::Equal => continue,
+            };
+            print!("{}{}", sign, change.value());
+            changed = true;
+        }
+        if !changed {
+            println!("(the graph did not change)");
+        }
+        self.items.push(item);
+        self.graph = graph;
+    }
+
+    /// Shows the nodes that a query pattern matches, along with their captures.
+    fn show_matches(&self, query: &str) {
+        let program = format!("{}\n{{\n}}\n", query.trim_end());
+        let file = match File::from_str(self.language.clone(), &program) {
+            Ok(file) => file,
+            Err(err) => {
+                Diagnostic::from_tsg_error(Path::new(REPL_PATH), &program, &err)
+                    .report(self.error_format);
+                return;
+            }
+        };
+        let mut count = 0;
+        let result = file.try_visit_matches(self.tree, self.source, false, |mat| {
+            count += 1;
+            println!("{}", describe_node(mat.full_capture(), self.source));
+            for (name, _, nodes) in mat.named_captures() {
+                for node in nodes {
+                    println!("  @{} = {}", name, describe_node(node, self.source));
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        });
+        match (result, count) {
+            (Err(e), _) => println!("{}", e),
+            (Ok(()), 1) => println!("1 match"),
+            (Ok(()), count) => println!("{} matches", count),
+        }
+    }
+
+    /// Executes a program against the source file, returning the graph that it produces.  Errors
+    /// are reported before they are returned.
+    fn execute(&self, program: &str) -> Result<String> {
+        let file = File::from_str(self.language.clone(), program).map_err(|err| {
+            Diagnostic::from_tsg_error(Path::new(REPL_PATH), program, &err)
+                .report(self.error_format);
+            anyhow!("The item was not added to the program")
+        })?;
+        let mut globals = Variables::new();
+        for (name, value) in load_globals(self.matches, &file)? {
+            globals.add(Identifier::from(name.as_str()), value)?;
+        }
+        let config =
+            ExecutionConfig::new(&self.functions, &globals).lazy(self.matches.is_present("lazy"));
+        let graph = file
+            .execute(self.tree, self.source, &config, &NoCancellation)
+            .map_err(|err| {
+                Diagnostic::from_execution_error(
+                    self.source_path,
+                    self.source,
+                    Path::new(REPL_PATH),
+                    program,
+                    &file,
+                    &err,
+                )
+                .report(self.error_format);
+                anyhow!("The item was not added to the program")
+            })?;
+        let result = graph.pretty_print().to_string();
+        Ok(result)
+    }
+}
+
+/// Returns whether an item is a query pattern on its own, rather than a stanza or other item.
+fn is_query(item: &str) -> bool {
+    let item = item.trim_start();
+    (item.starts_with('(') || item.starts_with('[')) && !item.contains('{')
+}
@@ -0,0 +1,152 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The `test` subcommand, which compares the graphs produced for a set of source files against
+//! golden snapshots.  The snapshot for a source file is stored next to it, with a `.graph`
+//! extension appended, in the same format that is printed by default.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use similar::TextDiff;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph::PrintOptions;
+use tree_sitter_graph::ExecutionLimits;
+
+use crate::diagnostic::ErrorFormat;
+use crate::find_sources;
+use crate::find_tsg_files;
+use crate::globals_args;
+use crate::language_args;
+use crate::load_globals;
+use crate::load_languages;
+use crate::scope;
+use crate::tsg_args;
+use crate::Execution;
+use crate::Format;
+use crate::TsgSource;
+
+pub fn command() -> App<'static> {
+    App::new("test")
+        .about("Checks the graphs produced for source files against their snapshots")
+        .args(tsg_args())
+        .arg(
+            Arg::with_name("tests")
+                .required(true)
+                .multiple_values(true)
+                .help("Source files, directories, or glob patterns of tests to run"),
+        )
+        .args(language_args())
+        .args(globals_args())
+        .arg(
+            Arg::with_name("lazy")
+                .short('z')
+                .long("lazy")
+                .help("Use lazy evaluation (experimental)"),
+        )
+        .arg(
+            Arg::with_name("update")
+                .long("update")
+                .help("Write the produced graphs to the snapshots, instead of comparing them"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let test_args = matches.values_of("tests").unwrap().collect::<Vec<_>>();
+    let mut loader = load_languages(matches)?;
+    let tests = find_sources(&loader, &test_args, scope(matches, Path::new(test_args[0])))?;
+    let first_test = tests.first().ok_or_else(|| anyhow!("No tests found"))?;
+    let language = loader.select_language(first_test, &current_dir, scope(matches, first_test))?;
+
+    let tsg = TsgSource::load(&find_tsg_files(matches)?)?;
+    let error_format = ErrorFormat::from_matches(matches);
+    let file = tsg.parse(&language, error_format)?;
+    let globals = load_globals(matches, &file)?;
+
+    let functions = Functions::stdlib();
+    let print_options = PrintOptions::default();
+    let execution = Execution {
+        file: &file,
+        tsg: &tsg,
+        language,
+        functions: &functions,
+        globals: &globals,
+        lazy: matches.is_present("lazy"),
+        allow_parse_errors: false,
+        format: Format::Debug,
+        print_options: &print_options,
+        error_format,
+        limits: ExecutionLimits::default(),
+        output: None,
+        statistics: None,
+        traces: None,
+        coverage: None,
+        fail_fast: false,
+    };
+    let jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let results = execution.run_all(&tests, jobs);
+
+    let update = matches.is_present("update");
+    let mut failures = 0;
+    for (test, result) in tests.iter().zip(results) {
+        let mut snapshot_path = test.clone().into_os_string();
+        snapshot_path.push(".graph");
+        let snapshot_path = PathBuf::from(snapshot_path);
+        let actual = match result.expect("tests are not skipped") {
+            Ok(actual) => actual,
+            Err(e) => {
+                println!("FAIL {}", test.display());
+                println!("{:?}", e);
+                failures += 1;
+                continue;
+            }
+        };
+        if update {
+            std::fs::write(&snapshot_path, &actual)
+                .with_context(|| format!("Cannot write {}", snapshot_path.display()))?;
+            println!("UPDATE {}", test.display());
+            continue;
+        }
+        let expected = match std::fs::read_to_string(&snapshot_path) {
+            Ok(expected) => expected,
+            Err(_) => {
+                println!("FAIL {}", test.display());
+                println!(
+                    "Missing snapshot {}, run with --update to create it",
+                    snapshot_path.display()
+                );
+                failures += 1;
+                continue;
+            }
+        };
+        if actual == expected {
+            println!("PASS {}", test.display());
+        } else {
+            println!("FAIL {}", test.display());
+            print!(
+                "{}",
+                TextDiff::from_lines(&expected, &actual)
+                    .unified_diff()
+                    .header(&snapshot_path.display().to_string(), "actual")
+            );
+            failures += 1;
+        }
+    }
+
+    println!("{} passed, {} failed", tests.len() - failures, failures);
+    if failures > 0 {
+        return Err(anyhow!("{} of {} tests failed", failures, tests.len()));
+    }
+    Ok(())
+}
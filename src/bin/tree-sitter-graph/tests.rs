@@ -0,0 +1,167 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph::PrintOptions;
+use tree_sitter_graph::ExecutionLimits;
+
+use crate::check_failures;
+use crate::diagnostic::ErrorFormat;
+use crate::exit;
+use crate::Execution;
+use crate::Format;
+use crate::TsgSource;
+
+/// A directory of files that is removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> TempDir {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "tree-sitter-graph-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&path).expect("Cannot create temporary directory");
+        TempDir(path)
+    }
+
+    fn write(&self, name: &str, content: &str) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::write(&path, content).expect("Cannot write temporary file");
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Executes `tsg` against each of the sources, one at a time, and returns the exit code that the
+/// command would have, along with which of the sources were processed.
+fn exit_code(tsg: &str, sources: &[&str], fail_fast: bool) -> (u8, Vec<bool>) {
+    let dir = TempDir::new();
+    let tsg = TsgSource::load(&[dir.write("test.tsg", tsg)]).expect("Cannot load TSG file");
+    let language = tree_sitter_python::language();
+    let file = tsg
+        .compile(&language)
+        .unwrap_or_else(|err| panic!("Cannot parse TSG file: {}", err.message));
+    let functions = Functions::stdlib();
+    let print_options = PrintOptions::default();
+    let execution = Execution {
+        file: &file,
+        tsg: &tsg,
+        language,
+        functions: &functions,
+        globals: &[],
+        lazy: false,
+        allow_parse_errors: false,
+        format: Format::Debug,
+        print_options: &print_options,
+        error_format: ErrorFormat::Human,
+        limits: ExecutionLimits::default(),
+        output: None,
+        statistics: None,
+        traces: None,
+        coverage: None,
+        fail_fast,
+    };
+    let sources = sources
+        .iter()
+        .enumerate()
+        .map(|(index, source)| dir.write(&format!("source{}.py", index), source))
+        .collect::<Vec<_>>();
+    let results = execution.run_all(&sources, 1);
+    let processed = results.iter().map(Option::is_some).collect();
+    let succeeded = results
+        .iter()
+        .filter(|result| matches!(result, Some(Ok(_))))
+        .count();
+    let failures = results
+        .iter()
+        .filter_map(|result| match result {
+            Some(Err(e)) => Some(exit::Failure::of(e)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let code = match check_failures(&failures, succeeded, sources.len()) {
+        Ok(()) => 0,
+        Err(e) => exit::code(&e),
+    };
+    (code, processed)
+}
+
+const TSG: &str = r#"
+    (module) @m {
+      node @m.node
+    }
+"#;
+
+#[test]
+fn fail_fast_reports_the_first_failure_if_nothing_succeeded() {
+    let (code, processed) = exit_code(TSG, &["pass +", "pass"], true);
+    assert_eq!(processed, vec![true, false]);
+    assert_eq!(code, exit::Failure::SourceParse as u8);
+}
+
+#[test]
+fn partial_success_requires_a_source_that_succeeded() {
+    let (code, processed) = exit_code(TSG, &["pass +", "pass"], false);
+    assert_eq!(processed, vec![true, true]);
+    assert_eq!(code, exit::Failure::PartialSuccess as u8);
+
+    let (code, processed) = exit_code(TSG, &["pass", "pass +"], true);
+    assert_eq!(processed, vec![true, true]);
+    assert_eq!(code, exit::Failure::PartialSuccess as u8);
+}
+
+/// Parses and checks several TSG files, returning the code of the diagnostic if they fail.
+fn compile(files: &[&str]) -> Result<(), &'static str> {
+    let dir = TempDir::new();
+    let paths = files
+        .iter()
+        .enumerate()
+        .map(|(index, content)| dir.write(&format!("file{}.tsg", index), content))
+        .collect::<Vec<_>>();
+    let tsg = TsgSource::load(&paths).expect("Cannot load TSG files");
+    tsg.compile(&tree_sitter_python::language())
+        .map(|_| ())
+        .map_err(|err| err.code)
+}
+
+#[test]
+fn can_use_globals_declared_in_another_file() {
+    let globals = "global ROOT\n";
+    let stanzas = r#"
+        (module) @m {
+          node @m.node
+          attr (@m.node) root = ROOT
+        }
+    "#;
+    assert_eq!(compile(&[globals, stanzas]), Ok(()));
+    assert_eq!(compile(&[stanzas, globals]), Ok(()));
+}
+
+#[test]
+fn cannot_declare_a_global_in_several_files() {
+    let globals = "global ROOT\n";
+    let stanzas = r#"
+        global ROOT
+        (module) @m {
+          node @m.node
+          attr (@m.node) root = ROOT
+        }
+    "#;
+    assert_eq!(compile(&[globals, stanzas]), Err("duplicate-global"));
+}
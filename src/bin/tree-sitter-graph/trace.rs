@@ -0,0 +1,170 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Writing of the statement traces recorded with `--trace`.
+
+use std::fmt::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Context as _;
+use anyhow::Result;
+use clap::ArgMatches;
+use serde_json::json;
+use tree_sitter_graph::ExecutionTrace;
+use tree_sitter_graph::Location;
+use tree_sitter_graph::TracedMatch;
+
+use crate::TsgSource;
+
+/// How the trace is written.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TraceFormat {
+    /// An indented log, with a paragraph for each match
+    Text,
+    /// A JSON object with an array of matches for each source file
+    Json,
+}
+
+/// The traces of the source files, collected as they are processed.
+pub struct Traces {
+    path: PathBuf,
+    format: TraceFormat,
+    traces: Mutex<Vec<(PathBuf, Vec<TracedMatch>)>>,
+}
+
+impl Traces {
+    /// Returns where to collect the traces, or `None` if tracing is not enabled.
+    pub fn from_matches(matches: &ArgMatches) -> Option<Traces> {
+        let path = matches.value_of("trace")?;
+        let format = match matches.value_of("trace-format") {
+            Some("json") => TraceFormat::Json,
+            _ => TraceFormat::Text,
+        };
+        Some(Traces {
+            path: PathBuf::from(path),
+            format,
+            traces: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Adds the trace of a source file.
+    pub fn add(&self, source_path: &Path, trace: ExecutionTrace) {
+        self.traces
+            .lock()
+            .unwrap()
+            .push((source_path.to_path_buf(), trace.matches()));
+    }
+
+    /// Writes the traces of all of the source files, in the order of their paths.  Locations are
+    /// given in the TSG file that contains them.
+    pub fn write(self, tsg: &TsgSource) -> Result<()> {
+        let Traces {
+            path,
+            format,
+            traces,
+        } = self;
+        let mut traces = traces.into_inner().unwrap();
+        traces.sort_by(|a, b| a.0.cmp(&b.0));
+        let output = match format {
+            TraceFormat::Text => text(&traces, tsg),
+            TraceFormat::Json => serde_json::to_string_pretty(&json(&traces, tsg))?,
+        };
+        std::fs::write(&path, output)
+            .with_context(|| format!("Cannot write trace to {}", path.display()))
+    }
+}
+
+fn text(traces: &[(PathBuf, Vec<TracedMatch>)], tsg: &TsgSource) -> String {
+    let mut output = String::new();
+    for (source_path, matches) in traces {
+        let _ = writeln!(output, "==> {} <==", source_path.display());
+        for mat in matches {
+            let _ = writeln!(
+                output,
+                "match of {} on ({}) at {}",
+                locate(tsg, mat.stanza),
+                mat.kind,
+                mat.position,
+            );
+            for capture in &mat.captures {
+                let _ = writeln!(output, "  @{} = {:?}", capture.name, capture.text);
+            }
+            for statement in &mat.statements {
+                let indent = "  ".repeat(statement.depth + 1);
+                let _ = writeln!(output, "{}{}", indent, statement.statement);
+                for value in &statement.values {
+                    let _ = writeln!(output, "{}  {} = {:?}", indent, value.label, value.value);
+                }
+                if !statement.nodes.is_empty() {
+                    let nodes = statement
+                        .nodes
+                        .iter()
+                        .map(|node| node.to_string())
+                        .collect::<Vec<_>>();
+                    let _ = writeln!(output, "{}  created {}", indent, nodes.join(", "));
+                }
+            }
+        }
+    }
+    output
+}
+
+fn json(traces: &[(PathBuf, Vec<TracedMatch>)], tsg: &TsgSource) -> serde_json::Value {
+    let mut sources = serde_json::Map::new();
+    for (source_path, matches) in traces {
+        let matches = matches
+            .iter()
+            .map(|mat| {
+                let statements = mat
+                    .statements
+                    .iter()
+                    .map(|statement| {
+                        let (path, location) = tsg.locate(statement.location);
+                        json!({
+                            "path": path.display().to_string(),
+                            "line": location.row + 1,
+                            "column": location.column + 1,
+                            "depth": statement.depth,
+                            "statement": statement.statement,
+                            "values": statement.values,
+                            "nodes": statement.nodes.iter().map(|node| node.index()).collect::<Vec<_>>(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let (path, location) = tsg.locate(mat.stanza);
+                json!({
+                    "path": path.display().to_string(),
+                    "line": location.row + 1,
+                    "column": location.column + 1,
+                    "kind": mat.kind,
+                    "source_line": mat.position.row + 1,
+                    "source_column": mat.position.column + 1,
+                    "captures": mat.captures,
+                    "statements": statements,
+                })
+            })
+            .collect();
+        sources.insert(
+            source_path.display().to_string(),
+            serde_json::Value::Array(matches),
+        );
+    }
+    serde_json::Value::Object(sources)
+}
+
+/// Describes the location of a stanza in the TSG file that contains it.
+fn locate(tsg: &TsgSource, location: Location) -> String {
+    let (path, location) = tsg.locate(location);
+    format!(
+        "stanza at {}:{}:{}",
+        path.display(),
+        location.row + 1,
+        location.column + 1
+    )
+}
@@ -0,0 +1,690 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! A debug adapter for TSG files, which communicates with an editor over stdin and stdout using
+//! the Debug Adapter Protocol.  It executes a TSG file on a source file, pausing at breakpoints
+//! and after steps, and shows the local variables, the captures of the current match, and the
+//! graph constructed so far.
+//!
+//! The `launch` request takes the following arguments:
+//!
+//! - `program`: the path of the TSG file
+//! - `source`: the path of the source file to execute it on
+//! - `scope`: the scope of the language of the source file, such as `source.python`, which is
+//!   otherwise selected by its file name
+//! - `grammar`: the directory of a locally built grammar to consider when selecting the language
+//! - `globals`: an object that gives the values of global variables, as strings
+//! - `stopOnEntry`: whether to pause before the first statement
+//!
+//! A breakpoint on a statement pauses before the statement.  A breakpoint on the query of a
+//! stanza pauses before the first statement of every match of the stanza.  Stepping over a
+//! statement with nested blocks runs the whole statement, and stepping out of the statements of a
+//! stanza continues to the next match.
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::TryRecvError;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use serde_json::json;
+use tree_sitter::Node;
+use tree_sitter::Parser;
+use tree_sitter_config::Config;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph::GraphNodeRef;
+use tree_sitter_graph::graph::Value;
+use tree_sitter_graph::CancellationError;
+use tree_sitter_graph::CancellationFlag;
+use tree_sitter_graph::DebugFrame;
+use tree_sitter_graph::Debugger;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::Identifier;
+use tree_sitter_graph::Variables;
+use tree_sitter_loader::Loader;
+
+use crate::protocol::Output;
+
+mod protocol;
+
+const BUILD_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The only thread of the debuggee.
+const THREAD_ID: u64 = 1;
+/// The frame of the current statement, in the TSG file.
+const STATEMENT_FRAME_ID: u64 = 1;
+/// The frame of the current match, in the source file.
+const MATCH_FRAME_ID: u64 = 2;
+
+fn command() -> App<'static> {
+    App::new("tsg-dap")
+        .version(BUILD_VERSION)
+        .about("Debug adapter for TSG files")
+        .arg(
+            Arg::with_name("config-path")
+                .long("config-path")
+                .takes_value(true)
+                .help("Path to an alternative tree-sitter config.json file"),
+        )
+}
+
+fn main() -> Result<()> {
+    let matches = command().get_matches();
+    let (sender, messages) = std::sync::mpsc::channel();
+    // Messages are read on their own thread, so that they can be handled while the TSG file is
+    // running, and not only while it is paused.
+    std::thread::spawn(move || {
+        let stdin = std::io::stdin();
+        let mut input = stdin.lock();
+        while let Ok(Some(message)) = protocol::read(&mut input) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    let stdout = std::io::stdout();
+    let session = Session {
+        messages,
+        output: RefCell::new(Output::new(stdout.lock())),
+        state: RefCell::new(State::default()),
+        terminated: Cell::new(false),
+    };
+    session.run(&matches)
+}
+
+/// Creates a loader that knows about the languages in the tree-sitter configuration, as well as
+/// the grammar given in the launch arguments.
+fn load_languages(matches: &ArgMatches, grammar: Option<&str>) -> Result<Loader> {
+    let config = Config::load(matches.value_of("config-path").map(PathBuf::from))?;
+    let mut loader = Loader::new()?;
+    let loader_config = config.get()?;
+    loader.find_all_languages(&loader_config)?;
+    if let Some(grammar) = grammar {
+        loader
+            .find_language_configurations_at_path(Path::new(grammar), false)
+            .with_context(|| format!("Cannot load grammar from {}", grammar))?;
+    }
+    Ok(loader)
+}
+
+struct Session<W> {
+    messages: Receiver<serde_json::Value>,
+    output: RefCell<Output<W>>,
+    state: RefCell<State>,
+    /// Whether the client has asked to stop the execution
+    terminated: Cell<bool>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The arguments of the launch request, once it has been received
+    launch: Option<serde_json::Value>,
+    /// Whether the client has finished configuring breakpoints
+    configured: bool,
+    program: PathBuf,
+    source_path: PathBuf,
+    /// The rows of the breakpoints in the TSG file
+    breakpoints: HashSet<usize>,
+    mode: Mode,
+    /// The number of matches that have been executed so far
+    matches: usize,
+    /// The stack frames of the paused execution
+    stack: Vec<serde_json::Value>,
+    /// The variables that the client can expand while the execution is paused, by their
+    /// variables reference minus one
+    references: Vec<Reference>,
+}
+
+/// When the execution pauses next.
+#[derive(Clone, Copy, Default)]
+enum Mode {
+    /// At the next breakpoint
+    #[default]
+    Continue,
+    /// At the next statement
+    Step(&'static str),
+    /// At the next statement that is not nested deeper than `depth` in the current match
+    StepOver { depth: usize, matches: usize },
+    /// At the next statement that is nested less deeply than `depth` in the current match
+    StepOut { depth: usize, matches: usize },
+}
+
+/// Something that the client can expand into a list of variables.
+enum Reference {
+    Locals,
+    Captures,
+    Graph,
+    /// The nodes of a capture, by the index of the capture in the frame
+    Capture(usize),
+    /// The scoped variables of a captured node, by the indices of the capture and the node
+    CapturedNode(usize, usize),
+    /// The elements of a list or set, the attributes and edges of a graph node, or the scoped
+    /// variables of a syntax node
+    Value(Value),
+    /// The attributes of an edge
+    Edge(GraphNodeRef, GraphNodeRef),
+}
+
+impl<W: Write> Session<W> {
+    /// Handles messages until the client disconnects.
+    fn run(&self, matches: &ArgMatches) -> Result<()> {
+        while !self.is_ready() {
+            match self.messages.recv() {
+                Ok(request) => {
+                    self.request(&request, None)?;
+                }
+                Err(_) => return Ok(()),
+            }
+            if self.terminated.get() {
+                return Ok(());
+            }
+        }
+        let exit_code = match self.launch(matches) {
+            Ok(()) => 0,
+            Err(e) => {
+                self.event(
+                    "output",
+                    json!({ "category": "stderr", "output": format!("{:#}\n", e) }),
+                )?;
+                1
+            }
+        };
+        self.event("exited", json!({ "exitCode": exit_code }))?;
+        self.event("terminated", json!({}))?;
+        while let Ok(request) = self.messages.recv() {
+            self.request(&request, None)?;
+            if request["command"] == "disconnect" {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the client has launched the debuggee and configured its breakpoints.
+    fn is_ready(&self) -> bool {
+        let state = self.state.borrow();
+        state.configured && state.launch.is_some()
+    }
+
+    /// Executes the TSG file on the source file.
+    fn launch(&self, matches: &ArgMatches) -> Result<()> {
+        let arguments = self.state.borrow().launch.clone().unwrap_or_default();
+        let argument = |name: &str| {
+            arguments[name]
+                .as_str()
+                .ok_or_else(|| anyhow!("Missing launch argument {}", name))
+        };
+        let program = PathBuf::from(argument("program")?);
+        let source_path = PathBuf::from(argument("source")?);
+        let loader = load_languages(matches, arguments["grammar"].as_str())?;
+        let language = match arguments["scope"].as_str() {
+            Some(scope) => loader
+                .language_configuration_for_scope(scope)?
+                .ok_or_else(|| anyhow!("Unknown scope {}", scope))?,
+            None => loader
+                .language_configuration_for_file_name(&source_path)?
+                .ok_or_else(|| anyhow!("No language found for {}", source_path.display()))?,
+        }
+        .0;
+
+        let tsg = std::fs::read_to_string(&program)
+            .with_context(|| format!("Cannot read TSG file {}", program.display()))?;
+        let file = File::from_str(language.clone(), &tsg)
+            .with_context(|| format!("Cannot parse TSG file {}", program.display()))?;
+        let source = std::fs::read_to_string(&source_path)
+            .with_context(|| format!("Cannot read source file {}", source_path.display()))?;
+        let mut parser = Parser::new();
+        parser.set_language(&language)?;
+        let tree = parser
+            .parse(&source, None)
+            .ok_or_else(|| anyhow!("Cannot parse {}", source_path.display()))?;
+
+        let functions = Functions::stdlib();
+        let mut globals = Variables::new();
+        if let Some(values) = arguments["globals"].as_object() {
+            for (name, value) in values {
+                let value = value
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Global variable {} is not a string", name))?;
+                globals.add(Identifier::from(name.as_str()), value.into())?;
+            }
+        }
+        {
+            let mut state = self.state.borrow_mut();
+            state.program = program.canonicalize().unwrap_or(program.clone());
+            state.source_path = source_path.canonicalize().unwrap_or(source_path.clone());
+            if arguments["stopOnEntry"].as_bool() == Some(true) {
+                state.mode = Mode::Step("entry");
+            }
+        }
+        let config = ExecutionConfig::new(&functions, &globals)
+            .lazy(false)
+            .debugger(self);
+        file.execute(&tree, &source, &config, self)
+            .with_context(|| {
+                format!(
+                    "Cannot execute TSG file {} on {}",
+                    program.display(),
+                    source_path.display()
+                )
+            })?;
+        Ok(())
+    }
+
+    /// Handles a request.  Returns the mode to resume the execution in, if the request resumes
+    /// it.  `frame` is the state of the execution, if it is paused.
+    fn request(
+        &self,
+        request: &serde_json::Value,
+        frame: Option<&DebugFrame>,
+    ) -> Result<Option<Mode>> {
+        let arguments = &request["arguments"];
+        let mut resume = None;
+        let body = match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                self.output.borrow_mut().response(
+                    request,
+                    Ok(json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsTerminateRequest": true,
+                    })),
+                )?;
+                return self.event("initialized", json!({})).map(|_| None);
+            }
+            "launch" => {
+                self.state.borrow_mut().launch = Some(arguments.clone());
+                Ok(json!({}))
+            }
+            "configurationDone" => {
+                self.state.borrow_mut().configured = true;
+                Ok(json!({}))
+            }
+            "setBreakpoints" => {
+                let lines = arguments["breakpoints"]
+                    .as_array()
+                    .map(|breakpoints| {
+                        breakpoints
+                            .iter()
+                            .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                // The TSG file is the only file with breakpoints.
+                let mut state = self.state.borrow_mut();
+                state.breakpoints = lines.iter().map(|line| *line as usize - 1).collect();
+                let breakpoints = lines
+                    .iter()
+                    .map(|line| json!({ "verified": true, "line": line }))
+                    .collect::<Vec<_>>();
+                Ok(json!({ "breakpoints": breakpoints }))
+            }
+            "setExceptionBreakpoints" => Ok(json!({})),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => {
+                let state = self.state.borrow();
+                Ok(json!({ "stackFrames": state.stack, "totalFrames": state.stack.len() }))
+            }
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Locals", "variablesReference": 1, "expensive": false },
+                    { "name": "Captures", "variablesReference": 2, "expensive": false },
+                    { "name": "Graph", "variablesReference": 3, "expensive": true },
+                ],
+            })),
+            "variables" => match frame {
+                Some(frame) => {
+                    let reference = arguments["variablesReference"].as_u64().unwrap_or(0);
+                    Ok(json!({ "variables": self.variables(frame, reference as usize) }))
+                }
+                None => Err("The execution is not paused".to_string()),
+            },
+            "continue" => {
+                resume = Some(Mode::Continue);
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" | "stepIn" | "stepOut" => {
+                let command = request["command"].as_str().unwrap_or_default();
+                let state = self.state.borrow();
+                let depth = frame.map_or(0, DebugFrame::depth);
+                let matches = state.matches;
+                resume = Some(match command {
+                    "next" => Mode::StepOver { depth, matches },
+                    "stepIn" => Mode::Step("step"),
+                    _ => Mode::StepOut { depth, matches },
+                });
+                Ok(json!({}))
+            }
+            "pause" => {
+                self.state.borrow_mut().mode = Mode::Step("pause");
+                Ok(json!({}))
+            }
+            "disconnect" | "terminate" => {
+                self.terminated.set(true);
+                resume = Some(Mode::Continue);
+                Ok(json!({}))
+            }
+            command => Err(format!("Unsupported request {}", command)),
+        };
+        self.output.borrow_mut().response(request, body)?;
+        Ok(resume)
+    }
+
+    fn event(&self, event: &str, body: serde_json::Value) -> Result<()> {
+        self.output.borrow_mut().event(event, body)
+    }
+
+    /// Handles the requests that arrived while the execution was running, and pauses it before
+    /// a statement if needed.
+    fn pause_at(&self, frame: &DebugFrame) -> Result<()> {
+        loop {
+            match self.messages.try_recv() {
+                Ok(request) => {
+                    if let Some(mode) = self.request(&request, None)? {
+                        self.state.borrow_mut().mode = mode;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.terminated.set(true);
+                    return Ok(());
+                }
+            }
+        }
+        if self.terminated.get() {
+            return Ok(());
+        }
+        let reason = match self.stop_reason(frame) {
+            Some(reason) => reason,
+            None => return Ok(()),
+        };
+        self.state.borrow_mut().stack = self.stack(frame);
+        self.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        )?;
+        loop {
+            let request = match self.messages.recv() {
+                Ok(request) => request,
+                Err(_) => {
+                    self.terminated.set(true);
+                    break;
+                }
+            };
+            if let Some(mode) = self.request(&request, Some(frame))? {
+                self.state.borrow_mut().mode = mode;
+                break;
+            }
+        }
+        let mut state = self.state.borrow_mut();
+        state.stack.clear();
+        state.references.clear();
+        Ok(())
+    }
+
+    /// Returns why the execution pauses before a statement, if it does.
+    fn stop_reason(&self, frame: &DebugFrame) -> Option<&'static str> {
+        let mut state = self.state.borrow_mut();
+        let statement = frame.statement();
+        let stanza = frame.stanza();
+        let first_statement = std::ptr::eq(statement, &stanza.statements[0]);
+        if first_statement {
+            state.matches += 1;
+        }
+        let row = frame.location().row;
+        let query_rows = stanza.range.start.row..row.max(stanza.range.start.row + 1);
+        if state.breakpoints.contains(&row)
+            || (first_statement && state.breakpoints.iter().any(|b| query_rows.contains(b)))
+        {
+            return Some("breakpoint");
+        }
+        match state.mode {
+            Mode::Continue => None,
+            Mode::Step(reason) => Some(reason),
+            Mode::StepOver { depth, matches } => {
+                (frame.depth() <= depth || state.matches != matches).then_some("step")
+            }
+            Mode::StepOut { depth, matches } => {
+                (frame.depth() < depth || state.matches != matches).then_some("step")
+            }
+        }
+    }
+
+    /// Returns the stack frames of a paused execution: the statement in the TSG file, and the
+    /// match in the source file.
+    fn stack(&self, frame: &DebugFrame) -> Vec<serde_json::Value> {
+        let state = self.state.borrow();
+        let location = frame.location();
+        let statement = frame.statement().to_string();
+        let stanza = frame.stanza();
+        let stanza_name = match &stanza.name {
+            Some(name) => format!("stanza {}", name),
+            None => format!("stanza at {}", stanza.range.start),
+        };
+        let match_position = frame.match_node().start_position();
+        vec![
+            json!({
+                "id": STATEMENT_FRAME_ID,
+                "name": statement.lines().next().unwrap_or_default(),
+                "source": { "path": state.program },
+                "line": location.row + 1,
+                "column": location.column + 1,
+            }),
+            json!({
+                "id": MATCH_FRAME_ID,
+                "name": format!("match of {}", stanza_name),
+                "source": { "path": state.source_path },
+                "line": match_position.row + 1,
+                "column": match_position.column + 1,
+            }),
+        ]
+    }
+
+    /// Returns the variables of a variables reference of a paused execution.
+    fn variables(&self, frame: &DebugFrame, reference: usize) -> Vec<serde_json::Value> {
+        let mut variables = Vec::new();
+        let graph = frame.graph();
+        let reference = match reference {
+            1 => Reference::Locals,
+            2 => Reference::Captures,
+            3 => Reference::Graph,
+            reference => {
+                let state = self.state.borrow();
+                match state.references.get(reference.wrapping_sub(4)) {
+                    Some(Reference::Capture(capture)) => Reference::Capture(*capture),
+                    Some(Reference::CapturedNode(capture, node)) => {
+                        Reference::CapturedNode(*capture, *node)
+                    }
+                    Some(Reference::Value(value)) => Reference::Value(value.clone()),
+                    Some(Reference::Edge(source, sink)) => Reference::Edge(*source, *sink),
+                    _ => return variables,
+                }
+            }
+        };
+        match reference {
+            Reference::Locals => {
+                let mut locals = frame.locals();
+                locals.sort_by(|a, b| a.0.cmp(b.0));
+                for (name, value) in locals {
+                    variables.push(self.value_variable(frame, name.to_string(), value));
+                }
+            }
+            Reference::Captures => {
+                for (index, (name, nodes)) in frame.captures().iter().enumerate() {
+                    let (value, reference) = match nodes.as_slice() {
+                        [node] => (
+                            describe(frame, *node),
+                            self.scoped_reference(frame, *node, index, 0),
+                        ),
+                        nodes => (
+                            format!("{} nodes", nodes.len()),
+                            self.reference(Reference::Capture(index)),
+                        ),
+                    };
+                    variables.push(variable(format!("@{}", name), value, reference));
+                }
+            }
+            Reference::Capture(capture) => {
+                let captures = frame.captures();
+                for (index, node) in captures[capture].1.iter().enumerate() {
+                    let reference = self.scoped_reference(frame, *node, capture, index);
+                    variables.push(variable(
+                        format!("[{}]", index),
+                        describe(frame, *node),
+                        reference,
+                    ));
+                }
+            }
+            Reference::CapturedNode(capture, index) => {
+                let node = frame.captures()[capture].1[index];
+                variables.extend(self.scoped_variables(frame, node));
+            }
+            Reference::Graph => {
+                for node in graph.iter_nodes() {
+                    variables.push(self.value_variable(
+                        frame,
+                        format!("[{}]", node.index()),
+                        &Value::GraphNode(node),
+                    ));
+                }
+            }
+            Reference::Value(Value::List(elements)) => {
+                for (index, element) in elements.iter().enumerate() {
+                    variables.push(self.value_variable(frame, format!("[{}]", index), element));
+                }
+            }
+            Reference::Value(Value::Set(elements)) => {
+                for element in &elements {
+                    variables.push(self.value_variable(frame, String::new(), element));
+                }
+            }
+            Reference::Value(Value::GraphNode(node)) => {
+                for (name, value) in graph.node_attributes(node).iter() {
+                    variables.push(self.value_variable(frame, name.to_string(), value));
+                }
+                for (sink, edge) in graph[node].iter_edges() {
+                    let reference = if edge.attributes.iter().next().is_some() {
+                        self.reference(Reference::Edge(node, sink))
+                    } else {
+                        0
+                    };
+                    variables.push(variable(
+                        format!("-> [{}]", sink.index()),
+                        edge.attributes.to_string(),
+                        reference,
+                    ));
+                }
+            }
+            Reference::Value(Value::SyntaxNode(node)) => {
+                variables.extend(self.scoped_variables(frame, graph[node]));
+            }
+            Reference::Value(_) => {}
+            Reference::Edge(source, sink) => {
+                if let Some(edge) = graph[source].get_edge(sink) {
+                    for (name, value) in edge.attributes.iter() {
+                        variables.push(self.value_variable(frame, name.to_string(), value));
+                    }
+                }
+            }
+        }
+        variables
+    }
+
+    /// Returns a variable for a value, which can be expanded if the value has elements,
+    /// attributes, edges, or scoped variables.
+    fn value_variable(&self, frame: &DebugFrame, name: String, value: &Value) -> serde_json::Value {
+        let graph = frame.graph();
+        let expandable = match value {
+            Value::List(elements) => !elements.is_empty(),
+            Value::Set(elements) => !elements.is_empty(),
+            Value::GraphNode(node) => {
+                graph.node_attributes(*node).iter().next().is_some()
+                    || graph[*node].edge_count() > 0
+            }
+            Value::SyntaxNode(node) => !frame.scoped_variables(graph[*node]).is_empty(),
+            _ => false,
+        };
+        let reference = if expandable {
+            self.reference(Reference::Value(value.clone()))
+        } else {
+            0
+        };
+        variable(name, format!("{:?}", value), reference)
+    }
+
+    /// Returns the scoped variables of a syntax node.
+    fn scoped_variables(&self, frame: &DebugFrame, node: Node) -> Vec<serde_json::Value> {
+        let mut scoped = frame.scoped_variables(node);
+        scoped.sort_by(|a, b| a.0.cmp(b.0));
+        scoped
+            .into_iter()
+            .map(|(name, value)| self.value_variable(frame, format!(".{}", name), value))
+            .collect()
+    }
+
+    /// Returns the variables reference of the scoped variables of a captured node, or 0 if it
+    /// has none.
+    fn scoped_reference(
+        &self,
+        frame: &DebugFrame,
+        node: Node,
+        capture: usize,
+        index: usize,
+    ) -> usize {
+        if frame.scoped_variables(node).is_empty() {
+            0
+        } else {
+            self.reference(Reference::CapturedNode(capture, index))
+        }
+    }
+
+    /// Returns a new variables reference.
+    fn reference(&self, reference: Reference) -> usize {
+        let mut state = self.state.borrow_mut();
+        state.references.push(reference);
+        state.references.len() + 3
+    }
+}
+
+impl<W: Write> Debugger for Session<W> {
+    fn statement(&self, frame: &DebugFrame) {
+        if self.pause_at(frame).is_err() {
+            // The client can no longer be reached.
+            self.terminated.set(true);
+        }
+    }
+}
+
+impl<W> CancellationFlag for Session<W> {
+    fn check(&self, at: &'static str) -> Result<(), CancellationError> {
+        if self.terminated.get() {
+            return Err(CancellationError(at));
+        }
+        Ok(())
+    }
+}
+
+/// Returns a DAP variable.
+fn variable(name: String, value: String, reference: usize) -> serde_json::Value {
+    json!({ "name": name, "value": value, "variablesReference": reference })
+}
+
+/// Describes a syntax node by its kind and text.
+fn describe(frame: &DebugFrame, node: Node) -> String {
+    let text = &frame.source()[node.byte_range()];
+    let text = text.lines().next().unwrap_or_default();
+    format!("({}) {:?}", node.kind(), text)
+}
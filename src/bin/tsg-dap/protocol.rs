@@ -0,0 +1,95 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The base protocol of the Debug Adapter Protocol: JSON messages, each preceded by a header that
+//! gives its length, and numbered in the order in which they are sent.
+
+use std::io::BufRead;
+use std::io::Write;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+use serde_json::json;
+use serde_json::Value;
+
+/// Reads the next message.  Returns `None` at the end of the input.
+pub fn read(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .context("Invalid Content-Length")?,
+                );
+            }
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("Missing Content-Length header"))?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    Ok(Some(
+        serde_json::from_slice(&content).context("Invalid message")?,
+    ))
+}
+
+/// Writes messages, numbering them as it goes.
+pub struct Output<W> {
+    output: W,
+    seq: u64,
+}
+
+impl<W: Write> Output<W> {
+    pub fn new(output: W) -> Output<W> {
+        Output { output, seq: 0 }
+    }
+
+    /// Writes the response to a request, which failed if `body` is an error message.
+    pub fn response(&mut self, request: &Value, body: Result<Value, String>) -> Result<()> {
+        let mut message = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": body.is_ok(),
+        });
+        match body {
+            Ok(body) => message["body"] = body,
+            Err(error) => message["message"] = Value::String(error),
+        }
+        self.write(message)
+    }
+
+    /// Writes an event.
+    pub fn event(&mut self, event: &str, body: Value) -> Result<()> {
+        self.write(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn write(&mut self, mut message: Value) -> Result<()> {
+        self.seq += 1;
+        message["seq"] = self.seq.into();
+        let content = serde_json::to_string(&message)?;
+        write!(
+            self.output,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )?;
+        self.output.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,410 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Open TSG documents, and the answers to questions about positions in them.
+//!
+//! Documents are usually invalid while they are being edited, so navigation uses the last version
+//! of each document that parsed.  Its locations are close enough to the current text for the
+//! stanza and declarations around a position to be found.
+
+use serde_json::json;
+use serde_json::Value;
+use tree_sitter::CaptureQuantifier;
+use tree_sitter::Language;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::ast::Stanza;
+use tree_sitter_graph::ast::Statement;
+use tree_sitter_graph::ast::Variable;
+use tree_sitter_graph::Identifier;
+use tree_sitter_graph::Location;
+use tree_sitter_graph::ParseError;
+
+/// The name of the capture that the library adds to every stanza query.
+const FULL_MATCH: &str = "__tsg__full_match";
+
+pub struct Document {
+    text: String,
+    /// The result of parsing the last version of the text that parsed
+    file: Option<File>,
+    /// The errors of the current text, if it does not parse
+    errors: Vec<ParseError>,
+}
+
+/// A token of the text, such as an identifier, a capture, or a scoped variable.
+struct Token<'a> {
+    text: &'a str,
+    start: Location,
+    /// The offset of the position in the token, in characters
+    offset: usize,
+}
+
+/// A variable declaration.
+struct Declaration<'a> {
+    name: &'a Identifier,
+    scoped: bool,
+    location: Location,
+}
+
+impl Document {
+    pub fn new(text: String, language: Option<&Language>) -> Document {
+        let mut document = Document {
+            text: String::new(),
+            file: None,
+            errors: Vec::new(),
+        };
+        document.update(text, language);
+        document
+    }
+
+    /// Replaces the text of the document and parses it again.
+    pub fn update(&mut self, text: String, language: Option<&Language>) {
+        self.text = text;
+        self.errors = Vec::new();
+        if let Some(language) = language {
+            match File::check_all(language.clone(), &self.text) {
+                Ok(file) => self.file = Some(file),
+                Err(errors) => self.errors = errors,
+            }
+        }
+    }
+
+    /// Returns the current text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the diagnostics of the current text.
+    pub fn diagnostics(&self) -> Value {
+        let diagnostics = self
+            .errors
+            .iter()
+            .map(|err| {
+                let start = err.location();
+                let end = Location {
+                    row: start.row,
+                    column: start.column + 1,
+                };
+                json!({
+                    "range": { "start": self.position(start), "end": self.position(end) },
+                    "severity": 1,
+                    "source": "tsg",
+                    "message": err.to_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+        Value::Array(diagnostics)
+    }
+
+    /// Returns the location of the declaration of the variable or capture at an LSP position.
+    pub fn definition(&self, position: &Value) -> Option<Value> {
+        let location = self.location(position)?;
+        let token = self.token_at(location)?;
+        let file = self.file.as_ref()?;
+        let stanza = stanza_at(file, location);
+        // Scoped variables are the part after the dot, as in `@node.name`.
+        let (name, scoped) = match token.text.split_once('.') {
+            Some((scope, name)) if token.offset > scope.chars().count() => (name, true),
+            Some((scope, _)) => (scope, false),
+            None => (token.text, false),
+        };
+        if name.starts_with('@') {
+            let start = self.find_capture(stanza?, name)?;
+            return Some(self.range(start, name));
+        }
+        let mut declarations = Vec::new();
+        if scoped {
+            // Scoped variables can be declared in any stanza.
+            for stanza in &file.stanzas {
+                collect_declarations(&stanza.statements, &mut declarations);
+            }
+        } else if let Some(stanza) = stanza {
+            collect_declarations(&stanza.statements, &mut declarations);
+        }
+        let declaration = declarations
+            .iter()
+            .filter(|declaration| declaration.scoped == scoped && declaration.name == name)
+            .min_by_key(|declaration| declaration.location)
+            .map(|declaration| declaration.location)
+            .or_else(|| {
+                file.globals
+                    .iter()
+                    .find(|global| !scoped && global.name == name)
+                    .map(|global| global.location)
+            })?;
+        Some(self.range(declaration, name))
+    }
+
+    /// Returns the hover text for a capture or global variable at an LSP position.
+    pub fn hover(&self, position: &Value) -> Option<Value> {
+        let location = self.location(position)?;
+        let token = self.token_at(location)?;
+        let file = self.file.as_ref()?;
+        let name = token.text.split('.').next().unwrap();
+        let text = if let Some(capture) = name.strip_prefix('@') {
+            let stanza = stanza_at(file, location)?;
+            let index = stanza
+                .query
+                .capture_names()
+                .iter()
+                .position(|name| *name == capture)?;
+            let quantifier = stanza.query.capture_quantifiers(0)[index];
+            format!("capture `@{}`: {}", capture, describe(quantifier, "node"))
+        } else {
+            let global = file.globals.iter().find(|global| global.name == name)?;
+            let mut text = format!(
+                "global `{}`: {}",
+                name,
+                describe(global.quantifier, "value")
+            );
+            if let Some(default) = &global.default {
+                text += &format!(", defaulting to {:?}", default);
+            }
+            text
+        };
+        Some(json!({
+            "contents": { "kind": "markdown", "value": text },
+            "range": self.range(token.start, token.text),
+        }))
+    }
+
+    /// Returns the completions at an LSP position: the captures of the stanza after an `@`, and
+    /// otherwise the functions and global variables.
+    pub fn completions<'a>(
+        &self,
+        position: &Value,
+        functions: impl Iterator<Item = &'a Identifier>,
+    ) -> Option<Value> {
+        let location = self.location(position)?;
+        let line = self.text.lines().nth(location.row).unwrap_or_default();
+        let prefix = line.chars().take(location.column).collect::<String>();
+        let word = prefix
+            .chars()
+            .rev()
+            .take_while(|c| is_token(*c) && *c != '.')
+            .count();
+        let items = if prefix.chars().rev().nth(word.saturating_sub(1)) == Some('@') {
+            let stanza = stanza_at(self.file.as_ref()?, location)?;
+            let start = Location {
+                row: location.row,
+                column: location.column - word,
+            };
+            let range = json!({ "start": self.position(start), "end": position });
+            stanza
+                .query
+                .capture_names()
+                .iter()
+                .filter(|name| **name != FULL_MATCH)
+                .map(|name| {
+                    json!({
+                        "label": format!("@{}", name),
+                        "kind": 6,
+                        "textEdit": { "range": range, "newText": format!("@{}", name) },
+                    })
+                })
+                .collect()
+        } else {
+            let mut functions = functions.collect::<Vec<_>>();
+            functions.sort();
+            let mut items = functions
+                .into_iter()
+                .map(|name| json!({ "label": name.as_str(), "kind": 3 }))
+                .collect::<Vec<_>>();
+            for global in self.file.iter().flat_map(|file| &file.globals) {
+                items.push(json!({ "label": global.name.as_str(), "kind": 6 }));
+            }
+            items
+        };
+        Some(Value::Array(items))
+    }
+
+    /// Returns the location of the first occurrence of a capture in the query of a stanza.
+    fn find_capture(&self, stanza: &Stanza, capture: &str) -> Option<Location> {
+        let mut location = stanza.range.start;
+        for line in self.text.lines().skip(location.row) {
+            let chars = line.chars().collect::<Vec<_>>();
+            while location.column < chars.len() {
+                let rest = chars[location.column..].iter().collect::<String>();
+                if rest.starts_with(capture)
+                    && !rest[capture.len()..].starts_with(|c| is_token(c) && c != '.')
+                {
+                    return Some(location);
+                }
+                location.column += 1;
+            }
+            location.row += 1;
+            location.column = 0;
+        }
+        None
+    }
+
+    /// Returns the token at a location.
+    fn token_at(&self, location: Location) -> Option<Token<'_>> {
+        let line = self.text.lines().nth(location.row)?;
+        let chars = line.char_indices().collect::<Vec<_>>();
+        let is_token_at = |column: usize| chars.get(column).is_some_and(|(_, c)| is_token(*c));
+        let mut start = location.column;
+        while start > 0 && is_token_at(start - 1) {
+            start -= 1;
+        }
+        let mut end = location.column;
+        while is_token_at(end) {
+            end += 1;
+        }
+        if start == end {
+            return None;
+        }
+        let byte_start = chars[start].0;
+        let byte_end = chars.get(end).map_or(line.len(), |(offset, _)| *offset);
+        Some(Token {
+            text: &line[byte_start..byte_end],
+            start: Location {
+                row: location.row,
+                column: start,
+            },
+            offset: location.column - start,
+        })
+    }
+
+    /// Converts an LSP position, whose column counts UTF-16 code units, to a location, whose
+    /// column counts characters.
+    fn location(&self, position: &Value) -> Option<Location> {
+        let row = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+        let line = self.text.lines().nth(row).unwrap_or_default();
+        let mut units = 0;
+        let mut column = 0;
+        for c in line.chars() {
+            if units >= character {
+                break;
+            }
+            units += c.len_utf16();
+            column += 1;
+        }
+        Some(Location { row, column })
+    }
+
+    /// Converts a location to an LSP position.
+    fn position(&self, location: Location) -> Value {
+        let line = self.text.lines().nth(location.row).unwrap_or_default();
+        let character = line
+            .chars()
+            .take(location.column)
+            .map(char::len_utf16)
+            .sum::<usize>();
+        json!({ "line": location.row, "character": character })
+    }
+
+    /// Returns the LSP range of some text at a location.
+    fn range(&self, start: Location, text: &str) -> Value {
+        let end = Location {
+            row: start.row,
+            column: start.column + text.chars().count(),
+        };
+        json!({ "start": self.position(start), "end": self.position(end) })
+    }
+}
+
+impl<'a> Declaration<'a> {
+    fn of(variable: &'a Variable) -> Declaration<'a> {
+        match variable {
+            Variable::Scoped(variable) => Declaration {
+                name: &variable.name,
+                scoped: true,
+                location: variable.location,
+            },
+            Variable::Unscoped(variable) => Declaration {
+                name: &variable.name,
+                scoped: false,
+                location: variable.location,
+            },
+        }
+    }
+}
+
+/// Returns whether a character can be part of a token.
+fn is_token(c: char) -> bool {
+    c == '_' || c == '-' || c == '@' || c == '.' || c == '?' || c.is_alphanumeric()
+}
+
+/// Returns the stanza that contains a location.
+fn stanza_at(file: &File, location: Location) -> Option<&Stanza> {
+    file.stanzas
+        .iter()
+        .rev()
+        .find(|stanza| stanza.range.start <= location)
+}
+
+fn collect_declarations<'a>(statements: &'a [Statement], declarations: &mut Vec<Declaration<'a>>) {
+    for statement in statements {
+        match statement {
+            Statement::DeclareImmutable(statement) => {
+                declarations.push(Declaration::of(&statement.variable))
+            }
+            Statement::DeclareMutable(statement) => {
+                declarations.push(Declaration::of(&statement.variable))
+            }
+            Statement::DestructureList(statement) => {
+                for variable in statement.elements.iter().chain(&statement.rest) {
+                    declarations.push(Declaration::of(variable))
+                }
+            }
+            Statement::CreateGraphNode(statement) => {
+                declarations.push(Declaration::of(&statement.node))
+            }
+            Statement::CreateEdge(statement) => {
+                if let Some(variable) = &statement.variable {
+                    declarations.push(Declaration::of(variable))
+                }
+            }
+            Statement::ForIn(statement) => {
+                declarations.push(Declaration {
+                    name: &statement.variable.name,
+                    scoped: false,
+                    location: statement.variable.location,
+                });
+                collect_declarations(&statement.statements, declarations);
+            }
+            Statement::ForInZip(statement) => {
+                for variable in &statement.variables {
+                    declarations.push(Declaration {
+                        name: &variable.name,
+                        scoped: false,
+                        location: variable.location,
+                    });
+                }
+                collect_declarations(&statement.statements, declarations);
+            }
+            Statement::If(statement) => {
+                for arm in &statement.arms {
+                    collect_declarations(&arm.statements, declarations);
+                }
+            }
+            Statement::Scan(statement) => {
+                for arm in &statement.arms {
+                    collect_declarations(&arm.statements, declarations);
+                }
+            }
+            Statement::Match(statement) => {
+                for arm in &statement.arms {
+                    collect_declarations(&arm.statements, declarations);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Describes what a capture or global variable with a quantifier contains.
+fn describe(quantifier: CaptureQuantifier, noun: &str) -> String {
+    match quantifier {
+        CaptureQuantifier::Zero => format!("no {}s", noun),
+        CaptureQuantifier::One => format!("exactly one {}", noun),
+        CaptureQuantifier::ZeroOrOne => format!("an optional {} (`?`)", noun),
+        CaptureQuantifier::ZeroOrMore => format!("a list of zero or more {}s (`*`)", noun),
+        CaptureQuantifier::OneOrMore => format!("a list of one or more {}s (`+`)", noun),
+    }
+}
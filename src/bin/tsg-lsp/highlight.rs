@@ -0,0 +1,139 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Semantic tokens for TSG documents, from the highlights query of the TSG grammar in
+//! `tree-sitter-tsg`.  The grammar is found by the loader like any other grammar, so highlighting
+//! is only offered when it is installed.
+
+use anyhow::Result;
+use serde_json::json;
+use serde_json::Value;
+use tree_sitter_highlight::HighlightConfiguration;
+use tree_sitter_highlight::HighlightEvent;
+use tree_sitter_highlight::Highlighter;
+
+/// The scope of the TSG grammar.
+pub const TSG_SCOPE: &str = "source.tsg";
+
+/// The highlight names of the highlights query, with the LSP token types they are reported as.
+/// Highlight names that are not listed fall back to their longest listed prefix.
+const HIGHLIGHTS: &[(&str, &str)] = &[
+    ("comment", "comment"),
+    ("constant.builtin", "enumMember"),
+    ("function", "function"),
+    ("function.builtin", "macro"),
+    ("keyword", "keyword"),
+    ("label", "parameter"),
+    ("number", "number"),
+    ("operator", "operator"),
+    ("property", "property"),
+    ("string", "string"),
+    ("string.regexp", "regexp"),
+    ("type", "type"),
+    ("variable", "variable"),
+];
+
+/// Returns the highlight names that the loader must be configured with.
+pub fn highlight_names() -> Vec<String> {
+    HIGHLIGHTS
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Returns the LSP token types, in the order of their indices in semantic tokens.
+fn token_types() -> Vec<&'static str> {
+    let mut token_types = Vec::new();
+    for (_, token_type) in HIGHLIGHTS {
+        if !token_types.contains(token_type) {
+            token_types.push(*token_type);
+        }
+    }
+    token_types
+}
+
+/// Returns the legend of the semantic tokens that this module produces.
+pub fn legend() -> Value {
+    json!({ "tokenTypes": token_types(), "tokenModifiers": [] })
+}
+
+/// Returns the semantic tokens of a document, in the relative encoding of the LSP.  Tokens are
+/// split at line breaks, since not all clients support multiline tokens.
+pub fn semantic_tokens(config: &HighlightConfiguration, text: &str) -> Result<Value> {
+    let token_types = token_types();
+    let mut highlighter = Highlighter::new();
+    let events = highlighter.highlight(config, text.as_bytes(), None, |_| None)?;
+    let mut data = Vec::new();
+    let mut stack = Vec::new();
+    // The position of the end of the last source event, and of the start of the last token
+    let mut position = Position::default();
+    let mut previous = Position::default();
+    for event in events {
+        match event? {
+            HighlightEvent::HighlightStart(highlight) => stack.push(highlight.0),
+            HighlightEvent::HighlightEnd => {
+                stack.pop();
+            }
+            HighlightEvent::Source { start, end } => {
+                position.advance(&text[position.offset..start]);
+                let token_type = match stack.last() {
+                    Some(highlight) => HIGHLIGHTS[*highlight].1,
+                    None => continue,
+                };
+                let token_type = token_types
+                    .iter()
+                    .position(|t| *t == token_type)
+                    .expect("all token types are in the legend");
+                for (i, segment) in text[start..end].split('\n').enumerate() {
+                    if i > 0 {
+                        position.advance("\n");
+                    }
+                    let length = utf16_len(segment);
+                    if length > 0 {
+                        let delta_line = position.line - previous.line;
+                        let delta_start = if delta_line == 0 {
+                            position.character - previous.character
+                        } else {
+                            position.character
+                        };
+                        data.extend([delta_line, delta_start, length, token_type, 0]);
+                        previous = position;
+                    }
+                    position.advance(segment);
+                }
+            }
+        }
+    }
+    Ok(json!({ "data": data }))
+}
+
+/// A position in a document, in bytes and in LSP coordinates.
+#[derive(Clone, Copy, Default)]
+struct Position {
+    offset: usize,
+    line: usize,
+    character: usize,
+}
+
+impl Position {
+    /// Moves the position past some text, which must follow it in the document.
+    fn advance(&mut self, text: &str) {
+        self.offset += text.len();
+        match text.rfind('\n') {
+            Some(i) => {
+                self.line += text.matches('\n').count();
+                self.character = utf16_len(&text[i + 1..]);
+            }
+            None => self.character += utf16_len(text),
+        }
+    }
+}
+
+/// Returns the length of a string in UTF-16 code units, which LSP positions count.
+fn utf16_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
@@ -0,0 +1,290 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! A language server for TSG files, which communicates with an editor over stdin and stdout.  It
+//! reports parse and check errors as you type, goes to the declarations of variables and
+//! captures, shows the quantifiers of captures on hover, and completes function names and the
+//! captures of the current stanza.  When the TSG grammar in `tree-sitter-tsg` is installed, it
+//! also provides semantic tokens for syntax highlighting.
+//!
+//! TSG files can only be checked against a language, which is given with `--scope`, or with the
+//! `scope` initialization option of the client.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use clap::ArgAction;
+use clap::ArgMatches;
+use serde_json::json;
+use serde_json::Value;
+use tree_sitter::Language;
+use tree_sitter_config::Config;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_highlight::HighlightConfiguration;
+use tree_sitter_loader::Loader;
+
+use crate::document::Document;
+
+mod document;
+mod highlight;
+mod protocol;
+
+const BUILD_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn command() -> App<'static> {
+    App::new("tsg-lsp")
+        .version(BUILD_VERSION)
+        .about("Language server for TSG files")
+        .arg(
+            Arg::with_name("scope")
+                .long("scope")
+                .takes_value(true)
+                .help("Language scope that the TSG files are written for, such as source.python"),
+        )
+        .arg(
+            Arg::with_name("grammar")
+                .long("grammar")
+                .takes_value(true)
+                .action(ArgAction::Append)
+                .help("Directory of a locally built grammar to consider when selecting a language"),
+        )
+        .arg(
+            Arg::with_name("config-path")
+                .long("config-path")
+                .takes_value(true)
+                .help("Path to an alternative tree-sitter config.json file"),
+        )
+}
+
+fn main() -> Result<()> {
+    let matches = command().get_matches();
+    let mut server = Server {
+        loader: load_languages(&matches)?,
+        scope: matches.value_of("scope").map(String::from),
+        language: None,
+        functions: Functions::stdlib(),
+        documents: HashMap::new(),
+        shutdown: false,
+    };
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let code = server.run(&mut stdin.lock(), &mut stdout.lock())?;
+    std::process::exit(code);
+}
+
+/// Creates a loader that knows about the languages in the tree-sitter configuration, as well as
+/// any grammars given with `--grammar`.
+fn load_languages(matches: &ArgMatches) -> Result<Loader> {
+    let config = Config::load(matches.value_of("config-path").map(PathBuf::from))?;
+    let mut loader = Loader::new()?;
+    loader.configure_highlights(&highlight::highlight_names());
+    let loader_config = config.get()?;
+    loader.find_all_languages(&loader_config)?;
+    for grammar in matches.values_of("grammar").unwrap_or_default() {
+        loader
+            .find_language_configurations_at_path(Path::new(grammar), false)
+            .with_context(|| format!("Cannot load grammar from {}", grammar))?;
+    }
+    Ok(loader)
+}
+
+struct Server {
+    loader: Loader,
+    scope: Option<String>,
+    /// The language of the TSG files, once it is loaded
+    language: Option<Language>,
+    /// The functions that are offered as completions
+    functions: Functions,
+    /// The open documents, by URI
+    documents: HashMap<String, Document>,
+    /// Whether the client has asked the server to shut down
+    shutdown: bool,
+}
+
+impl Server {
+    /// Handles messages until the client asks the server to exit, returning the exit code.
+    fn run(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> Result<i32> {
+        while let Some(message) = protocol::read(input)? {
+            let method = match message["method"].as_str() {
+                Some(method) => method,
+                // The server sends no requests, so there are no responses to handle.
+                None => continue,
+            };
+            let params = &message["params"];
+            match message.get("id") {
+                Some(id) => {
+                    let response = match self.request(method, params, output) {
+                        Ok(result) => protocol::response(id.clone(), result),
+                        Err((code, message)) => protocol::error_response(id.clone(), code, message),
+                    };
+                    protocol::write(output, &response)?;
+                }
+                None if method == "exit" => return Ok(if self.shutdown { 0 } else { 1 }),
+                None => self.notification(method, params, output)?,
+            }
+        }
+        Ok(1)
+    }
+
+    fn request(
+        &mut self,
+        method: &str,
+        params: &Value,
+        output: &mut impl Write,
+    ) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => {
+                if let Some(scope) = params["initializationOptions"]["scope"].as_str() {
+                    self.scope = Some(scope.to_string());
+                }
+                if let Err(e) = self.load_language() {
+                    let message = json!({ "type": 1, "message": format!("{:#}", e) });
+                    let notification = protocol::notification("window/showMessage", message);
+                    protocol::write(output, &notification)
+                        .map_err(|e| (protocol::INTERNAL_ERROR, e.to_string()))?;
+                }
+                let mut capabilities = json!({
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["@"] },
+                });
+                if let Ok(Some(_)) = self.highlight_config() {
+                    capabilities["semanticTokensProvider"] =
+                        json!({ "legend": highlight::legend(), "full": true });
+                }
+                Ok(json!({
+                    "capabilities": capabilities,
+                    "serverInfo": { "name": "tsg-lsp", "version": BUILD_VERSION },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => {
+                let (uri, document) = self.document(params)?;
+                let range = document.definition(&params["position"]);
+                Ok(range.map_or(Value::Null, |range| json!({ "uri": uri, "range": range })))
+            }
+            "textDocument/hover" => {
+                let (_, document) = self.document(params)?;
+                Ok(document.hover(&params["position"]).unwrap_or(Value::Null))
+            }
+            "textDocument/completion" => {
+                let (_, document) = self.document(params)?;
+                Ok(document
+                    .completions(&params["position"], self.functions.names())
+                    .unwrap_or(Value::Null))
+            }
+            "textDocument/semanticTokens/full" => {
+                let (_, document) = self.document(params)?;
+                let config = self
+                    .highlight_config()
+                    .map_err(|e| (protocol::INTERNAL_ERROR, format!("{:#}", e)))?
+                    .ok_or_else(|| {
+                        (
+                            protocol::INTERNAL_ERROR,
+                            "The TSG grammar is not installed".to_string(),
+                        )
+                    })?;
+                highlight::semantic_tokens(config, document.text())
+                    .map_err(|e| (protocol::INTERNAL_ERROR, format!("{:#}", e)))
+            }
+            _ => Err((
+                protocol::METHOD_NOT_FOUND,
+                format!("Unknown method {}", method),
+            )),
+        }
+    }
+
+    fn notification(
+        &mut self,
+        method: &str,
+        params: &Value,
+        output: &mut impl Write,
+    ) -> Result<()> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                let document = Document::new(text.to_string(), self.language.as_ref());
+                self.documents.insert(uri.to_string(), document);
+            }
+            "textDocument/didChange" => {
+                // The server asks for full synchronization, so the last change has the full text.
+                let changes = params["contentChanges"].as_array();
+                let text = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let (Some(document), Some(text)) = (self.documents.get_mut(uri), text) {
+                    document.update(text.to_string(), self.language.as_ref());
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                let params = json!({ "uri": uri, "diagnostics": [] });
+                let notification =
+                    protocol::notification("textDocument/publishDiagnostics", params);
+                return protocol::write(output, &notification);
+            }
+            _ => return Ok(()),
+        }
+        if let Some(document) = self.documents.get(uri) {
+            let params = json!({ "uri": uri, "diagnostics": document.diagnostics() });
+            let notification = protocol::notification("textDocument/publishDiagnostics", params);
+            protocol::write(output, &notification)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the document that a request is about.
+    fn document<'a>(&'a self, params: &'a Value) -> Result<(&'a str, &'a Document), (i64, String)> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| (protocol::INVALID_PARAMS, "Missing document".to_string()))?;
+        let document = self.documents.get(uri).ok_or_else(|| {
+            (
+                protocol::INVALID_PARAMS,
+                format!("Unknown document {}", uri),
+            )
+        })?;
+        Ok((uri, document))
+    }
+
+    fn load_language(&mut self) -> Result<()> {
+        let scope = self
+            .scope
+            .as_deref()
+            .ok_or_else(|| anyhow!("No language scope given, so TSG files cannot be checked"))?;
+        let (language, _) = self
+            .loader
+            .language_configuration_for_scope(scope)?
+            .ok_or_else(|| anyhow!("Unknown scope {}", scope))?;
+        self.language = Some(language);
+        Ok(())
+    }
+
+    /// Returns the highlighting configuration of the TSG grammar, if it is installed.
+    fn highlight_config(&self) -> Result<Option<&HighlightConfiguration>> {
+        match self
+            .loader
+            .language_configuration_for_scope(highlight::TSG_SCOPE)?
+        {
+            Some((language, configuration)) => configuration.highlight_config(language, None),
+            None => Ok(None),
+        }
+    }
+}
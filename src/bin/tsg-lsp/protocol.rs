@@ -0,0 +1,84 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! The base protocol of the Language Server Protocol: JSON-RPC messages, each preceded by a
+//! header that gives its length.
+
+use std::io::BufRead;
+use std::io::Write;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+use serde_json::json;
+use serde_json::Value;
+
+/// The error code of requests for methods that the server does not implement.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// The error code of requests with invalid parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+/// The error code of requests that failed because of the server.
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// Reads the next message.  Returns `None` at the end of the input.
+pub fn read(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .context("Invalid Content-Length")?,
+                );
+            }
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("Missing Content-Length header"))?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    Ok(Some(
+        serde_json::from_slice(&content).context("Invalid message")?,
+    ))
+}
+
+/// Writes a message.
+pub fn write(output: &mut impl Write, message: &Value) -> Result<()> {
+    let content = serde_json::to_string(message)?;
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Returns the response to a request that succeeded.
+pub fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+/// Returns the response to a request that failed.
+pub fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Returns a notification.
+pub fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
@@ -8,7 +8,10 @@
 use std::collections::HashSet;
 use std::path::Path;
 
+use regex::Regex;
 use thiserror::Error;
+use tracing::debug_span;
+use tracing::trace;
 use tree_sitter::CaptureQuantifier;
 use tree_sitter::CaptureQuantifier::One;
 use tree_sitter::CaptureQuantifier::OneOrMore;
@@ -17,6 +20,7 @@ use tree_sitter::CaptureQuantifier::ZeroOrOne;
 use tree_sitter::Query;
 
 use crate::ast;
+use crate::extensions::Extensions;
 use crate::parse_error::Excerpt;
 use crate::parser::FULL_MATCH;
 use crate::variables::MutVariables;
@@ -26,22 +30,42 @@ use crate::variables::Variables;
 use crate::Identifier;
 use crate::Location;
 
+/// The name of the variable that is bound to each element in the arms of a `scan` statement
+/// over a list.
+const SCAN_ELEMENT: &str = "element";
+
 #[derive(Debug, Error)]
 pub enum CheckError {
+    #[error("Cannot assign loop variable {0} at {1}")]
+    CannotAssignLoopVariable(String, Location),
+    #[error("Cannot assign variable with default value {0} at {1}")]
+    CannotAssignVariableWithDefault(String, Location),
     #[error("Cannot hide global variable {0} at {1}")]
     CannotHideGlobalVariable(String, Location),
     #[error("Cannot set global variable {0} at {1}")]
     CannotSetGlobalVariable(String, Location),
     #[error("Duplicate global variable {0} at {1}")]
     DuplicateGlobalVariable(String, Location),
+    #[error("Duplicate stanza name {0} at {1}")]
+    DuplicateStanzaName(String, Location),
+    #[error("Expected boolean value at {0}")]
+    ExpectedBooleanValue(Location),
     #[error("Expected list value at {0}")]
     ExpectedListValue(Location),
     #[error("Expected local value at {0}")]
     ExpectedLocalValue(Location),
     #[error("Expected optional value at {0}")]
     ExpectedOptionalValue(Location),
+    #[error("Invalid {0} statement: {1} at {2}")]
+    InvalidExtensionStatement(String, String, Location),
+    #[error("Invalid stanza guard {0} at {1}")]
+    InvalidStanzaGuard(String, Location),
+    #[error("Expected {0} lists to zip for {0} variables, but got {1} at {2}")]
+    MismatchedZip(usize, usize, Location),
     #[error("Nullable regular expression /{0}/ at {1}")]
     NullableRegex(String, Location),
+    #[error("Undefined regex capture {0} in arm at {1}")]
+    UndefinedRegexCapture(String, Location),
     #[error("Undefined syntax capture @{0} at {1}")]
     UndefinedSyntaxCapture(String, Location),
     #[error("Undefined variable {0} at {1}")]
@@ -53,6 +77,31 @@ pub enum CheckError {
 }
 
 impl CheckError {
+    /// Returns the location in the graph DSL file where this error occurred.
+    pub fn location(&self) -> Location {
+        match self {
+            CheckError::CannotAssignLoopVariable(_, location) => *location,
+            CheckError::CannotAssignVariableWithDefault(_, location) => *location,
+            CheckError::CannotHideGlobalVariable(_, location) => *location,
+            CheckError::CannotSetGlobalVariable(_, location) => *location,
+            CheckError::DuplicateGlobalVariable(_, location) => *location,
+            CheckError::DuplicateStanzaName(_, location) => *location,
+            CheckError::ExpectedBooleanValue(location) => *location,
+            CheckError::ExpectedListValue(location) => *location,
+            CheckError::ExpectedLocalValue(location) => *location,
+            CheckError::ExpectedOptionalValue(location) => *location,
+            CheckError::InvalidExtensionStatement(_, _, location) => *location,
+            CheckError::InvalidStanzaGuard(_, location) => *location,
+            CheckError::MismatchedZip(_, _, location) => *location,
+            CheckError::NullableRegex(_, location) => *location,
+            CheckError::UndefinedRegexCapture(_, location) => *location,
+            CheckError::UndefinedSyntaxCapture(_, location) => *location,
+            CheckError::UndefinedVariable(_, location) => *location,
+            CheckError::UnusedCaptures(_, location) => *location,
+            CheckError::Variable(_, _, location) => *location,
+        }
+    }
+
     pub fn display_pretty<'a>(
         &'a self,
         path: &'a Path,
@@ -74,19 +123,7 @@ struct DisplayCheckErrorPretty<'a> {
 
 impl std::fmt::Display for DisplayCheckErrorPretty<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let location = match self.error {
-            CheckError::CannotHideGlobalVariable(_, location) => *location,
-            CheckError::CannotSetGlobalVariable(_, location) => *location,
-            CheckError::DuplicateGlobalVariable(_, location) => *location,
-            CheckError::ExpectedListValue(location) => *location,
-            CheckError::ExpectedLocalValue(location) => *location,
-            CheckError::ExpectedOptionalValue(location) => *location,
-            CheckError::NullableRegex(_, location) => *location,
-            CheckError::UndefinedSyntaxCapture(_, location) => *location,
-            CheckError::UndefinedVariable(_, location) => *location,
-            CheckError::UnusedCaptures(_, location) => *location,
-            CheckError::Variable(_, _, location) => *location,
-        };
+        let location = self.error.location();
         writeln!(f, "{}", self.error)?;
         write!(
             f,
@@ -110,12 +147,59 @@ struct CheckContext<'a> {
     stanza_index: usize,
     stanza_query: &'a Query,
     locals: &'a mut dyn MutVariables<VariableResult>,
+    extensions: Option<&'a Extensions>,
+    /// The capture groups of the current `scan` or `match` arm, if they are known
+    regex_groups: Option<&'a RegexGroups>,
+}
+
+/// The capture groups that regex captures can refer to in an arm of a `scan` or `match`
+/// statement.
+#[derive(Clone, Debug)]
+struct RegexGroups {
+    /// The number of capture groups, including the whole match
+    count: usize,
+    names: HashSet<String>,
+    /// The location of the arm
+    location: Location,
+}
+
+impl RegexGroups {
+    fn new(regex: &Regex, location: Location) -> RegexGroups {
+        RegexGroups {
+            count: regex.captures_len(),
+            names: regex
+                .capture_names()
+                .flatten()
+                .map(str::to_string)
+                .collect(),
+            location,
+        }
+    }
+
+    /// Returns the capture groups that are in both this and another set of capture groups.
+    fn intersect(&self, other: &RegexGroups) -> RegexGroups {
+        RegexGroups {
+            count: self.count.min(other.count),
+            names: self.names.intersection(&other.names).cloned().collect(),
+            location: self.location,
+        }
+    }
+
+    fn contains(&self, group: &ast::RegexGroup) -> bool {
+        match group {
+            ast::RegexGroup::Index(index) => *index < self.count,
+            ast::RegexGroup::Name(name) => self.names.contains(name.as_str()),
+            ast::RegexGroup::Start | ast::RegexGroup::End => true,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 struct VariableResult {
     is_local: bool,
     quantifier: CaptureQuantifier,
+    /// Whether the variable is bound to each element of the list of a `for` loop
+    is_loop_variable: bool,
 }
 
 //-----------------------------------------------------------------------------
@@ -123,29 +207,72 @@ struct VariableResult {
 
 impl ast::File {
     pub fn check(&mut self) -> Result<(), CheckError> {
+        self.check_with_extensions(None)
+    }
+
+    /// Checks this file, including the extension statements that it contains, which are only
+    /// checked by their extensions if a library of extensions is given.
+    pub(crate) fn check_with_extensions(
+        &mut self,
+        extensions: Option<&Extensions>,
+    ) -> Result<(), CheckError> {
+        let errors = self.check_all_with_extensions(extensions, |_| false);
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks this file like `check_with_extensions`, but returns every error instead of the
+    /// first one: every duplicate global and stanza name, and the first error of each stanza.
+    /// The stanzas for which `skip` returns true are not checked.
+    pub(crate) fn check_all_with_extensions(
+        &mut self,
+        extensions: Option<&Extensions>,
+        skip: impl Fn(&ast::Stanza) -> bool,
+    ) -> Vec<CheckError> {
+        let _span = debug_span!("check", stanzas = self.stanzas.len()).entered();
+        let mut errors = Vec::new();
         let mut globals = VariableMap::new();
         for global in &self.globals {
-            globals
-                .add(
-                    global.name.clone(),
-                    VariableResult {
-                        quantifier: global.quantifier,
-                        is_local: true,
-                    },
-                    false,
-                )
-                .map_err(|_| {
-                    CheckError::DuplicateGlobalVariable(
-                        global.name.as_str().to_string(),
-                        global.location,
-                    )
-                })?;
+            let result = globals.add(
+                global.name.clone(),
+                VariableResult {
+                    quantifier: global.quantifier,
+                    is_local: true,
+                    is_loop_variable: false,
+                },
+                false,
+            );
+            if result.is_err() {
+                errors.push(CheckError::DuplicateGlobalVariable(
+                    global.name.as_str().to_string(),
+                    global.location,
+                ));
+            }
+        }
+        let mut stanza_names = HashSet::new();
+        for stanza in &self.stanzas {
+            if let Some(name) = &stanza.name {
+                if !stanza_names.insert(name) {
+                    errors.push(CheckError::DuplicateStanzaName(
+                        name.to_string(),
+                        stanza.range.start,
+                    ));
+                }
+            }
         }
         let file_query = self.query.as_ref().unwrap();
         for (index, stanza) in self.stanzas.iter_mut().enumerate() {
-            stanza.check(&globals, file_query, index)?;
+            if skip(stanza) {
+                continue;
+            }
+            trace!("check stanza at {}", stanza.range.start);
+            if let Err(err) = stanza.check(&globals, file_query, index, extensions) {
+                errors.push(err);
+            }
         }
-        Ok(())
+        errors
     }
 }
 
@@ -158,6 +285,7 @@ impl ast::Stanza {
         globals: &dyn Variables<VariableResult>,
         file_query: &Query,
         stanza_index: usize,
+        extensions: Option<&Extensions>,
     ) -> Result<(), CheckError> {
         let mut locals = VariableMap::new();
         let mut ctx = CheckContext {
@@ -166,12 +294,36 @@ impl ast::Stanza {
             stanza_index,
             stanza_query: &self.query,
             locals: &mut locals,
+            extensions,
+            regex_groups: None,
         };
         self.full_match_file_capture_index =
             ctx.file_query
                 .capture_index_for_name(FULL_MATCH)
                 .expect("missing capture index for full match") as usize;
 
+        if let Some(guard) = &mut self.guard {
+            // The guard is evaluated once per execution, before there are any matches, so it can
+            // only use the global variables.
+            let mut guard_locals = VariableMap::new();
+            let mut guard_ctx = CheckContext {
+                globals,
+                file_query,
+                stanza_index,
+                stanza_query: &self.query,
+                locals: &mut guard_locals,
+                extensions,
+                regex_groups: None,
+            };
+            guard.check(&mut guard_ctx)?;
+            if !guard.is_global() {
+                return Err(CheckError::InvalidStanzaGuard(
+                    guard.to_string(),
+                    self.range.start,
+                ));
+            }
+        }
+
         let mut used_captures = HashSet::new();
         for statement in &mut self.statements {
             let stmt_result = statement.check(&mut ctx)?;
@@ -206,6 +358,32 @@ impl ast::Stanza {
     }
 }
 
+impl ast::Expression {
+    /// Returns whether this expression can be evaluated from the global variables alone: it only
+    /// consists of literals, unscoped variables, and function calls.  Unscoped variables must be
+    /// global variables, which checking the expression without local variables makes sure of.
+    fn is_global(&self) -> bool {
+        match self {
+            Self::FalseLiteral | Self::NullLiteral | Self::TrueLiteral => true,
+            Self::IntegerConstant(_) | Self::StringConstant(_) => true,
+            Self::ListLiteral(expr) => expr.elements.iter().all(Self::is_global),
+            Self::SetLiteral(expr) => expr.elements.iter().all(Self::is_global),
+            Self::Variable(ast::Variable::Unscoped(_)) => true,
+            Self::Call(expr) => expr.parameters.iter().all(Self::is_global),
+            _ => false,
+        }
+    }
+
+    /// Returns whether this expression can evaluate to a boolean.  Only the values of variables
+    /// and function calls are not known before execution.
+    fn can_be_boolean(&self) -> bool {
+        matches!(
+            self,
+            Self::FalseLiteral | Self::TrueLiteral | Self::Variable(_) | Self::Call(_)
+        )
+    }
+}
+
 //-----------------------------------------------------------------------------
 // Statements
 
@@ -219,6 +397,7 @@ impl ast::Statement {
         match self {
             Self::DeclareImmutable(stmt) => stmt.check(ctx),
             Self::DeclareMutable(stmt) => stmt.check(ctx),
+            Self::DestructureList(stmt) => stmt.check(ctx),
             Self::Assign(stmt) => stmt.check(ctx),
             Self::CreateGraphNode(stmt) => stmt.check(ctx),
             Self::AddGraphNodeAttribute(stmt) => stmt.check(ctx),
@@ -227,7 +406,10 @@ impl ast::Statement {
             Self::Scan(stmt) => stmt.check(ctx),
             Self::Print(stmt) => stmt.check(ctx),
             Self::If(stmt) => stmt.check(ctx),
+            Self::Match(stmt) => stmt.check(ctx),
             Self::ForIn(stmt) => stmt.check(ctx),
+            Self::ForInZip(stmt) => stmt.check(ctx),
+            Self::Extension(stmt) => stmt.check(ctx),
         }
     }
 }
@@ -254,6 +436,39 @@ impl ast::DeclareMutable {
     }
 }
 
+impl ast::DestructureList {
+    fn check(&mut self, ctx: &mut CheckContext) -> Result<StatementResult, CheckError> {
+        let mut used_captures = HashSet::new();
+        let value = self.value.check(ctx)?;
+        if !value.is_local {
+            return Err(CheckError::ExpectedLocalValue(self.location));
+        }
+        if value.quantifier != ZeroOrMore && value.quantifier != OneOrMore {
+            return Err(CheckError::ExpectedListValue(self.location));
+        }
+        used_captures.extend(value.used_captures.iter().cloned());
+        for element in &mut self.elements {
+            let element_result = VariableResult {
+                is_local: true,
+                quantifier: One,
+                is_loop_variable: false,
+            };
+            let var_result = element.check_add(ctx, element_result, false)?;
+            used_captures.extend(var_result.used_captures);
+        }
+        if let Some(rest) = &mut self.rest {
+            let rest_result = VariableResult {
+                is_local: true,
+                quantifier: ZeroOrMore,
+                is_loop_variable: false,
+            };
+            let var_result = rest.check_add(ctx, rest_result, false)?;
+            used_captures.extend(var_result.used_captures);
+        }
+        Ok(StatementResult { used_captures })
+    }
+}
+
 impl ast::Assign {
     fn check(&mut self, ctx: &mut CheckContext) -> Result<StatementResult, CheckError> {
         let mut used_captures = HashSet::new();
@@ -267,17 +482,25 @@ impl ast::Assign {
 
 impl ast::CreateGraphNode {
     fn check(&mut self, ctx: &mut CheckContext) -> Result<StatementResult, CheckError> {
+        let mut used_captures = HashSet::new();
+        if let Some(label) = &mut self.label {
+            let label_result = label.check(ctx)?;
+            if !label_result.is_local {
+                return Err(CheckError::ExpectedLocalValue(self.location));
+            }
+            used_captures.extend(label_result.used_captures);
+        }
         let node_result = self.node.check_add(
             ctx,
             VariableResult {
                 is_local: true,
                 quantifier: One,
+                is_loop_variable: false,
             },
             false,
         )?;
-        Ok(StatementResult {
-            used_captures: node_result.used_captures,
-        })
+        used_captures.extend(node_result.used_captures);
+        Ok(StatementResult { used_captures })
     }
 }
 
@@ -301,6 +524,18 @@ impl ast::CreateEdge {
         used_captures.extend(source_result.used_captures);
         let sink_result = self.sink.check(ctx)?;
         used_captures.extend(sink_result.used_captures);
+        if let Some(variable) = &mut self.variable {
+            let variable_result = variable.check_add(
+                ctx,
+                VariableResult {
+                    is_local: source_result.is_local && sink_result.is_local,
+                    quantifier: One,
+                    is_loop_variable: false,
+                },
+                false,
+            )?;
+            used_captures.extend(variable_result.used_captures);
+        }
         Ok(StatementResult { used_captures })
     }
 }
@@ -330,6 +565,16 @@ impl ast::Scan {
         }
         used_captures.extend(value_result.used_captures);
 
+        // Lists are scanned element by element, and each element is bound to a variable in the
+        // arms.
+        self.element = match value_result.quantifier {
+            ZeroOrMore | OneOrMore => Some(ast::UnscopedVariable {
+                name: SCAN_ELEMENT.into(),
+                location: self.location,
+            }),
+            _ => None,
+        };
+
         for arm in &mut self.arms {
             // Be aware that this check is not complete, as it does not rule out
             // all regular expressions that admit empty matches. For example, th
@@ -343,6 +588,7 @@ impl ast::Scan {
                 ));
             }
 
+            let regex_groups = RegexGroups::new(&arm.regex, arm.location);
             let mut arm_locals = VariableMap::nested(ctx.locals);
             let mut arm_ctx = CheckContext {
                 globals: ctx.globals,
@@ -350,8 +596,19 @@ impl ast::Scan {
                 stanza_index: ctx.stanza_index,
                 stanza_query: ctx.stanza_query,
                 locals: &mut arm_locals,
+                extensions: ctx.extensions,
+                regex_groups: Some(&regex_groups),
             };
 
+            if let Some(element) = &mut self.element {
+                let element_result = VariableResult {
+                    is_local: true,
+                    quantifier: One,
+                    is_loop_variable: false,
+                };
+                element.check_add(&mut arm_ctx, element_result, false)?;
+            }
+
             for statement in &mut arm.statements {
                 let stmt_result = statement.check(&mut arm_ctx)?;
                 used_captures.extend(stmt_result.used_captures);
@@ -372,6 +629,26 @@ impl ast::Print {
     }
 }
 
+impl ast::Extension {
+    fn check(&mut self, ctx: &mut CheckContext) -> Result<StatementResult, CheckError> {
+        let mut used_captures = HashSet::new();
+        for argument in &mut self.arguments {
+            let argument_result = argument.check(ctx)?;
+            used_captures.extend(argument_result.used_captures);
+        }
+        if let Some(extensions) = ctx.extensions {
+            extensions.check(self).map_err(|message| {
+                CheckError::InvalidExtensionStatement(
+                    self.keyword.to_string(),
+                    message,
+                    self.location,
+                )
+            })?;
+        }
+        Ok(StatementResult { used_captures })
+    }
+}
+
 impl ast::If {
     fn check(&mut self, ctx: &mut CheckContext) -> Result<StatementResult, CheckError> {
         let mut used_captures = HashSet::new();
@@ -389,6 +666,8 @@ impl ast::If {
                 stanza_index: ctx.stanza_index,
                 stanza_query: ctx.stanza_query,
                 locals: &mut arm_locals,
+                extensions: ctx.extensions,
+                regex_groups: ctx.regex_groups,
             };
 
             for statement in &mut arm.statements {
@@ -400,6 +679,66 @@ impl ast::If {
     }
 }
 
+impl ast::Match {
+    fn check(&mut self, ctx: &mut CheckContext) -> Result<StatementResult, CheckError> {
+        let mut used_captures = HashSet::new();
+
+        let value_result = self.value.check(ctx)?;
+        if !value_result.is_local {
+            return Err(CheckError::ExpectedLocalValue(self.location));
+        }
+        used_captures.extend(value_result.used_captures);
+
+        for arm in &mut self.arms {
+            let regex_groups = arm.regex_groups(ctx.regex_groups);
+            let mut arm_locals = VariableMap::nested(ctx.locals);
+            let mut arm_ctx = CheckContext {
+                globals: ctx.globals,
+                file_query: ctx.file_query,
+                stanza_index: ctx.stanza_index,
+                stanza_query: ctx.stanza_query,
+                locals: &mut arm_locals,
+                extensions: ctx.extensions,
+                regex_groups: regex_groups.as_ref(),
+            };
+
+            for statement in &mut arm.statements {
+                let stmt_result = statement.check(&mut arm_ctx)?;
+                used_captures.extend(stmt_result.used_captures);
+            }
+        }
+        Ok(StatementResult { used_captures })
+    }
+}
+
+impl ast::MatchArm {
+    /// Returns the capture groups that regex captures can refer to in this arm, if they are
+    /// known.  Regex captures refer to the match of the pattern that matched, or to the match of
+    /// the enclosing arm if that pattern is a string or the wildcard, so only the groups that all
+    /// of these have can be referred to.
+    fn regex_groups(&self, outer: Option<&RegexGroups>) -> Option<RegexGroups> {
+        let mut pattern_groups = Vec::new();
+        if self.patterns.is_empty() {
+            pattern_groups.push(outer?.clone());
+        }
+        for pattern in &self.patterns {
+            pattern_groups.push(match pattern {
+                ast::MatchPattern::String(_) => outer?.clone(),
+                ast::MatchPattern::Regex(regex) => RegexGroups::new(regex, self.location),
+            });
+        }
+        let groups = pattern_groups[1..]
+            .iter()
+            .fold(pattern_groups[0].clone(), |groups, other| {
+                groups.intersect(other)
+            });
+        Some(RegexGroups {
+            location: self.location,
+            ..groups
+        })
+    }
+}
+
 impl ast::Condition {
     fn check(&mut self, ctx: &mut CheckContext) -> Result<StatementResult, CheckError> {
         let mut used_captures = HashSet::new();
@@ -409,7 +748,8 @@ impl ast::Condition {
                 if !value_result.is_local {
                     return Err(CheckError::ExpectedLocalValue(*location));
                 }
-                if value_result.quantifier != ZeroOrOne {
+                // Lists that can be empty are tested for being non-empty.
+                if value_result.quantifier != ZeroOrOne && value_result.quantifier != ZeroOrMore {
                     return Err(CheckError::ExpectedOptionalValue(*location));
                 }
                 used_captures.extend(value_result.used_captures);
@@ -419,6 +759,9 @@ impl ast::Condition {
                 if !value_result.is_local {
                     return Err(CheckError::ExpectedLocalValue(*location));
                 }
+                if !value.can_be_boolean() {
+                    return Err(CheckError::ExpectedBooleanValue(*location));
+                }
                 used_captures.extend(value_result.used_captures);
             }
         }
@@ -446,10 +789,12 @@ impl ast::ForIn {
             stanza_index: ctx.stanza_index,
             stanza_query: ctx.stanza_query,
             locals: &mut loop_locals,
+            extensions: ctx.extensions,
+            regex_groups: ctx.regex_groups,
         };
         let var_result = self
             .variable
-            .check_add(&mut loop_ctx, value_result.into(), false)?;
+            .check_add_loop_variable(&mut loop_ctx, value_result.into())?;
         used_captures.extend(var_result.used_captures);
 
         for statement in &mut self.statements {
@@ -461,6 +806,55 @@ impl ast::ForIn {
     }
 }
 
+impl ast::ForInZip {
+    fn check(&mut self, ctx: &mut CheckContext) -> Result<StatementResult, CheckError> {
+        let mut used_captures = HashSet::new();
+
+        if self.variables.len() != self.values.len() {
+            return Err(CheckError::MismatchedZip(
+                self.variables.len(),
+                self.values.len(),
+                self.location,
+            ));
+        }
+        let mut value_results = Vec::new();
+        for value in &mut self.values {
+            let value_result = value.check(ctx)?;
+            if !value_result.is_local {
+                return Err(CheckError::ExpectedLocalValue(self.location));
+            }
+            if value_result.quantifier != ZeroOrMore && value_result.quantifier != OneOrMore {
+                return Err(CheckError::ExpectedListValue(self.location));
+            }
+            used_captures.extend(value_result.used_captures.iter().cloned());
+            value_results.push(value_result);
+        }
+
+        let mut loop_locals = VariableMap::nested(ctx.locals);
+        let mut loop_ctx = CheckContext {
+            globals: ctx.globals,
+            file_query: ctx.file_query,
+            stanza_index: ctx.stanza_index,
+            stanza_query: ctx.stanza_query,
+            locals: &mut loop_locals,
+            extensions: ctx.extensions,
+            regex_groups: ctx.regex_groups,
+        };
+        for (variable, value_result) in self.variables.iter_mut().zip(value_results) {
+            let var_result =
+                variable.check_add_loop_variable(&mut loop_ctx, value_result.into())?;
+            used_captures.extend(var_result.used_captures);
+        }
+
+        for statement in &mut self.statements {
+            let stmt_result = statement.check(&mut loop_ctx)?;
+            used_captures.extend(stmt_result.used_captures);
+        }
+
+        Ok(StatementResult { used_captures })
+    }
+}
+
 //-----------------------------------------------------------------------------
 // Expressions
 
@@ -499,6 +893,7 @@ impl ast::Expression {
             Self::Capture(expr) => expr.check(ctx),
             Self::Variable(expr) => expr.check_get(ctx),
             Self::Call(expr) => expr.check(ctx),
+            Self::NewNode(expr) => expr.check(ctx),
             Self::RegexCapture(expr) => expr.check(ctx),
         }
     }
@@ -578,6 +973,8 @@ impl ast::ListComprehension {
             stanza_index: ctx.stanza_index,
             stanza_query: ctx.stanza_query,
             locals: &mut loop_locals,
+            extensions: ctx.extensions,
+            regex_groups: ctx.regex_groups,
         };
         let var_result = self
             .variable
@@ -587,6 +984,17 @@ impl ast::ListComprehension {
         let element_result = self.element.check(&mut loop_ctx)?;
         used_captures.extend(element_result.used_captures);
 
+        if let Some(condition) = &mut self.condition {
+            let condition_result = condition.check(&mut loop_ctx)?;
+            if !condition_result.is_local {
+                return Err(CheckError::ExpectedLocalValue(self.location));
+            }
+            if !condition.can_be_boolean() {
+                return Err(CheckError::ExpectedBooleanValue(self.location));
+            }
+            used_captures.extend(condition_result.used_captures);
+        }
+
         Ok(ExpressionResult {
             is_local: element_result.is_local,
             quantifier: ZeroOrMore,
@@ -615,6 +1023,8 @@ impl ast::SetComprehension {
             stanza_index: ctx.stanza_index,
             stanza_query: ctx.stanza_query,
             locals: &mut loop_locals,
+            extensions: ctx.extensions,
+            regex_groups: ctx.regex_groups,
         };
         let var_result = self
             .variable
@@ -624,6 +1034,17 @@ impl ast::SetComprehension {
         let element_result = self.element.check(&mut loop_ctx)?;
         used_captures.extend(element_result.used_captures);
 
+        if let Some(condition) = &mut self.condition {
+            let condition_result = condition.check(&mut loop_ctx)?;
+            if !condition_result.is_local {
+                return Err(CheckError::ExpectedLocalValue(self.location));
+            }
+            if !condition.can_be_boolean() {
+                return Err(CheckError::ExpectedBooleanValue(self.location));
+            }
+            used_captures.extend(condition_result.used_captures);
+        }
+
         Ok(ExpressionResult {
             is_local: element_result.is_local,
             quantifier: ZeroOrMore,
@@ -671,7 +1092,7 @@ impl ast::Call {
     }
 }
 
-impl ast::RegexCapture {
+impl ast::NewNode {
     fn check(&mut self, _ctx: &mut CheckContext) -> Result<ExpressionResult, CheckError> {
         Ok(ExpressionResult {
             is_local: true,
@@ -681,6 +1102,24 @@ impl ast::RegexCapture {
     }
 }
 
+impl ast::RegexCapture {
+    fn check(&mut self, ctx: &mut CheckContext) -> Result<ExpressionResult, CheckError> {
+        if let Some(groups) = ctx.regex_groups {
+            if !groups.contains(&self.group) {
+                return Err(CheckError::UndefinedRegexCapture(
+                    self.to_string(),
+                    groups.location,
+                ));
+            }
+        }
+        Ok(ExpressionResult {
+            is_local: true,
+            quantifier: One,
+            used_captures: HashSet::default(),
+        })
+    }
+}
+
 //-----------------------------------------------------------------------------
 // Variables
 
@@ -745,6 +1184,20 @@ impl ast::UnscopedVariable {
         })
     }
 
+    /// Adds the variable of a `for` loop, which is immutable, and cannot be assigned in the
+    /// body of the loop or in any scope nested in it.
+    fn check_add_loop_variable(
+        &mut self,
+        ctx: &mut CheckContext,
+        value: VariableResult,
+    ) -> Result<StatementResult, CheckError> {
+        let value = VariableResult {
+            is_loop_variable: true,
+            ..value
+        };
+        self.check_add(ctx, value, false)
+    }
+
     fn check_set(
         &mut self,
         ctx: &mut CheckContext,
@@ -756,6 +1209,18 @@ impl ast::UnscopedVariable {
                 self.location,
             ));
         }
+        // The innermost variable with this name is the one that is assigned, so a loop variable
+        // that is hidden by a mutable variable of a nested scope can be assigned through it.
+        if let Some(VariableResult {
+            is_loop_variable: true,
+            ..
+        }) = ctx.locals.get(&self.name)
+        {
+            return Err(CheckError::CannotAssignLoopVariable(
+                self.name.as_str().to_string(),
+                self.location,
+            ));
+        }
         let mut value = value;
         // Mutable variables are not considered local, because a non-local
         // assignment in a loop could invalidate an earlier local assignment.
@@ -788,6 +1253,7 @@ impl ast::ScopedVariable {
         _value: VariableResult,
         _mutable: bool,
     ) -> Result<StatementResult, CheckError> {
+        self.check_no_default()?;
         let scope_result = self.scope.check(ctx)?;
         Ok(scope_result.into())
     }
@@ -797,18 +1263,36 @@ impl ast::ScopedVariable {
         ctx: &mut CheckContext,
         _value: VariableResult,
     ) -> Result<StatementResult, CheckError> {
+        self.check_no_default()?;
         let scope_result = self.scope.check(ctx)?;
         Ok(scope_result.into())
     }
 
     fn check_get(&mut self, ctx: &mut CheckContext) -> Result<ExpressionResult, CheckError> {
         let scope_result = self.scope.check(ctx)?;
+        let mut used_captures = scope_result.used_captures;
+        if let Some(default) = &mut self.default {
+            let default_result = default.check(ctx)?;
+            used_captures.extend(default_result.used_captures);
+        }
         Ok(ExpressionResult {
             is_local: false,
             quantifier: One, // FIXME we don't really know
-            used_captures: scope_result.used_captures,
+            used_captures,
         })
     }
+
+    /// Default values are only used when reading a variable, so they cannot be given to the
+    /// variables that statements define or assign.
+    fn check_no_default(&self) -> Result<(), CheckError> {
+        match &self.default {
+            Some(_) => Err(CheckError::CannotAssignVariableWithDefault(
+                self.to_string(),
+                self.location,
+            )),
+            None => Ok(()),
+        }
+    }
 }
 
 //-----------------------------------------------------------------------------
@@ -854,6 +1338,7 @@ impl Into<VariableResult> for ExpressionResult {
         VariableResult {
             is_local: self.is_local,
             quantifier: self.quantifier,
+            is_loop_variable: false,
         }
     }
 }
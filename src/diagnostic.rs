@@ -0,0 +1,321 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! A common representation of the errors of the parser, the checker, and the executor.
+//!
+//! Every error type of the crate converts into a [`Diagnostic`][], which carries a stable code, a
+//! severity, the span of the graph DSL file or source file where the error occurred, the other
+//! spans that help explain it, and sometimes a hint on how to fix it.  Tools such as editors and
+//! CI integrations can handle this one type instead of each error type.
+
+use std::fmt;
+
+use serde::Serialize;
+use tree_sitter::Node;
+
+use crate::ast::Spans;
+use crate::checker::CheckError;
+#[cfg(feature = "execution")]
+use crate::execution::error::ExecutionError;
+use crate::parse_error;
+use crate::Location;
+use crate::ParseError;
+
+/// How serious a diagnostic is.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// The file that a span refers to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticFile {
+    /// The graph DSL file
+    Tsg,
+    /// The source file that the graph DSL file was executed against
+    Source,
+}
+
+/// A range of a graph DSL file or source file.  The end is not known for all errors.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub struct Span {
+    pub file: DiagnosticFile,
+    pub start: Location,
+    pub end: Option<Location>,
+}
+
+impl Span {
+    /// Returns the span of a location in the graph DSL file.
+    pub fn tsg(start: Location) -> Span {
+        Span {
+            file: DiagnosticFile::Tsg,
+            start,
+            end: None,
+        }
+    }
+
+    /// Returns the span of a syntax node in the source file.
+    pub fn source(node: Node) -> Span {
+        let start = node.start_position();
+        let end = node.end_position();
+        Span {
+            file: DiagnosticFile::Source,
+            start: Location {
+                row: start.row,
+                column: start.column,
+            },
+            end: Some(Location {
+                row: end.row,
+                column: end.column,
+            }),
+        }
+    }
+}
+
+/// A secondary span of a diagnostic, with a description of how it relates to the error.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Label {
+    pub message: String,
+    pub span: Span,
+}
+
+/// An error or warning, in a form that does not depend on which part of the crate produced it.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// A short identifier of the kind of diagnostic, such as `tsg-check`
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// Where the error occurred, if it is known
+    pub span: Option<Span>,
+    /// Other locations that help explain the error
+    pub secondary: Vec<Label>,
+    /// A hint on how to fix the error
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates an error diagnostic, without secondary spans or help.
+    pub fn error(code: &'static str, message: String, span: Option<Span>) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity: Severity::Error,
+            message,
+            span,
+            secondary: Vec::new(),
+            help: None,
+        }
+    }
+
+    /// Returns this diagnostic with the end of each span of the graph DSL file that starts at a
+    /// construct in `spans`, such as a statement or a variable, set to the end of that construct.
+    /// Diagnostics are converted from errors without access to the file, so their spans do not
+    /// have an end unless the error knows it.
+    pub fn with_spans(mut self, spans: &Spans) -> Diagnostic {
+        let resolve = |span: &mut Span| {
+            if span.file == DiagnosticFile::Tsg && span.end.is_none() {
+                span.end = spans.end(span.start);
+            }
+        };
+        if let Some(span) = &mut self.span {
+            resolve(span);
+        }
+        for label in &mut self.secondary {
+            resolve(&mut label.span);
+        }
+        self
+    }
+
+    fn with_help(self, help: Option<&str>) -> Diagnostic {
+        Diagnostic {
+            help: help.map(str::to_string),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+        if let Some(help) = &self.help {
+            write!(f, "\nhelp: {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Diagnostic {
+        if let ParseError::Check(error) = error {
+            return error.into();
+        }
+        let help = match error {
+            ParseError::InvalidRegexCapture(_) => {
+                Some("regex captures are referred to by their index or name, as in $1 or $name")
+            }
+            ParseError::UnexpectedQueryPatterns(_) => {
+                Some("each stanza must have a single query pattern")
+            }
+            ParseError::NestingTooDeep(_, _) => {
+                Some("the maximum nesting depth is set with ParseLimits")
+            }
+            _ => None,
+        };
+        Diagnostic::error(
+            "tsg-parse",
+            error.to_string(),
+            Some(Span::tsg(error.location())),
+        )
+        .with_help(help)
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Diagnostic {
+        Diagnostic::from(&error)
+    }
+}
+
+impl From<&CheckError> for Diagnostic {
+    fn from(error: &CheckError) -> Diagnostic {
+        let help = match error {
+            CheckError::CannotAssignLoopVariable(_, _) => Some(
+                "loop variables cannot be assigned; declare a mutable variable with var instead",
+            ),
+            CheckError::CannotSetGlobalVariable(_, _)
+            | CheckError::CannotHideGlobalVariable(_, _) => {
+                Some("global variables are provided by the caller and cannot be changed")
+            }
+            CheckError::DuplicateStanzaName(_, _) => Some("stanza names must be unique"),
+            CheckError::NullableRegex(_, _) => {
+                Some("regular expressions must not match the empty string")
+            }
+            CheckError::UndefinedRegexCapture(_, _) => {
+                Some("regex captures must refer to a capture group of the regular expression")
+            }
+            CheckError::UnusedCaptures(_, _) => {
+                Some("remove the captures, or prefix their names with _ to ignore them")
+            }
+            _ => None,
+        };
+        Diagnostic::error(
+            "tsg-check",
+            error.to_string(),
+            Some(Span::tsg(error.location())),
+        )
+        .with_help(help)
+    }
+}
+
+impl From<CheckError> for Diagnostic {
+    fn from(error: CheckError) -> Diagnostic {
+        Diagnostic::from(&error)
+    }
+}
+
+impl From<&parse_error::ParseError<'_>> for Diagnostic {
+    fn from(error: &parse_error::ParseError) -> Diagnostic {
+        let message = match error {
+            parse_error::ParseError::Missing(_) => "missing syntax",
+            parse_error::ParseError::Unexpected(_) => "unexpected syntax",
+        };
+        Diagnostic::error(
+            "source-parse",
+            message.to_string(),
+            Some(Span::source(*error.node())),
+        )
+    }
+}
+
+impl From<parse_error::ParseError<'_>> for Diagnostic {
+    fn from(error: parse_error::ParseError) -> Diagnostic {
+        Diagnostic::from(&error)
+    }
+}
+
+/// The diagnostic of an execution error is located at the statement that was executing, if it is
+/// known, and its secondary spans are the stanza, the syntax node that the stanza matched, and the
+/// statements that were executing around it.
+#[cfg(feature = "execution")]
+impl From<&ExecutionError> for Diagnostic {
+    fn from(error: &ExecutionError) -> Diagnostic {
+        let root_cause = error.root_cause();
+        let (code, help) = match root_cause {
+            ExecutionError::Cancelled(_) => ("cancelled", None),
+            ExecutionError::LimitExceeded(_) => (
+                "limit-exceeded",
+                Some("the limits of an execution are set with ExecutionLimits"),
+            ),
+            ExecutionError::MissingGlobalVariable(_) => (
+                "execution",
+                Some("global variables must be provided to the execution"),
+            ),
+            ExecutionError::MissingFunctionNamespaces(_) | ExecutionError::MissingFunctions(_) => (
+                "execution",
+                Some("the functions that a file uses must be registered in the Functions of the execution"),
+            ),
+            ExecutionError::UndefinedFunction(_) => (
+                "execution",
+                Some("functions must be registered in the Functions of the execution"),
+            ),
+            _ => ("execution", None),
+        };
+        let message = root_cause.to_string();
+        let contexts = error.statement_contexts();
+        let context = match contexts.first() {
+            Some(context) => context,
+            None => return Diagnostic::error(code, message, None).with_help(help),
+        };
+        let mut secondary = vec![
+            Label {
+                message: "in stanza".to_string(),
+                span: Span::tsg(context.stanza_location),
+            },
+            Label {
+                message: format!("matching ({}) node", context.node_kind),
+                span: Span {
+                    file: DiagnosticFile::Source,
+                    start: context.source_location,
+                    end: Some(context.source_end_location),
+                },
+            },
+        ];
+        for context in &contexts[1..] {
+            secondary.push(Label {
+                message: format!("and executing {}", context.statement),
+                span: Span::tsg(context.statement_location),
+            });
+        }
+        Diagnostic {
+            code,
+            severity: Severity::Error,
+            message,
+            span: Some(Span::tsg(context.statement_location)),
+            secondary,
+            help: help.map(str::to_string),
+        }
+    }
+}
+
+#[cfg(feature = "execution")]
+impl From<ExecutionError> for Diagnostic {
+    fn from(error: ExecutionError) -> Diagnostic {
+        Diagnostic::from(&error)
+    }
+}
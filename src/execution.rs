@@ -5,33 +5,96 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Instant;
+
+use regex::Captures;
+use regex::Regex;
 use thiserror::Error;
+use tracing::debug;
+use tracing::debug_span;
 use tree_sitter::CaptureQuantifier;
+use tree_sitter::InputEdit;
 use tree_sitter::Node;
 use tree_sitter::QueryMatch;
 use tree_sitter::Tree;
 
+use crate::ast::visit;
+use crate::ast::Call;
 use crate::ast::CreateEdge;
+use crate::ast::DestructureList;
+use crate::ast::Expression;
 use crate::ast::File;
+use crate::ast::ForInZip;
+use crate::ast::MatchArm;
+use crate::ast::MatchPattern;
+use crate::ast::NewNode;
+use crate::ast::RegexCapture;
+use crate::ast::RegexGroup;
+use crate::ast::Scan;
 use crate::ast::Stanza;
 use crate::ast::Variable;
+use crate::ast::Visitor;
+use crate::execution::coverage::ExecutionCoverage;
+use crate::execution::debugger::Debugger;
 use crate::execution::error::ExecutionError;
+use crate::execution::error::ResultWithExecutionError;
+use crate::execution::limits::ExecutionLimits;
+use crate::execution::outcome::ExecutionOutcome;
+use crate::execution::outcome::WarningCollector;
+use crate::execution::output::PrintOutput;
+use crate::execution::sink::GraphSink;
+use crate::execution::source_map::SourceMap;
+use crate::execution::statistics::ExecutionStatistics;
+use crate::execution::trace::ExecutionTrace;
+use crate::extensions::Extensions;
+use crate::functions::Environment;
 use crate::functions::Functions;
+use crate::graph::AttributeStorage;
 use crate::graph::Attributes;
 use crate::graph::Graph;
+use crate::graph::GraphNodeRef;
 use crate::graph::Value;
 use crate::variables::Globals;
 use crate::Identifier;
 use crate::Location;
+use crate::RuleSet;
 
+pub(crate) mod coverage;
+pub(crate) mod debugger;
 pub(crate) mod error;
 mod lazy;
+pub(crate) mod limits;
+pub(crate) mod outcome;
+pub(crate) mod output;
+mod regions;
+pub(crate) mod sink;
+pub(crate) mod source_map;
+pub(crate) mod statistics;
 mod strict;
+pub(crate) mod trace;
+
+// Files, functions, and global variables are shared by the threads that execute a file
+// concurrently, so none of them can have state that is not thread-safe.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<File>();
+    assert_send_sync::<Functions>();
+    assert_send_sync::<Globals<'static>>();
+    assert_send_sync::<Extensions>();
+    assert_send_sync::<ExecutionLimits>();
+    assert_send_sync::<RuleSet>();
+};
 
 impl File {
     /// Executes this graph DSL file against a source file.  You must provide the parsed syntax
     /// tree (`tree`) as well as the source text that it was parsed from (`source`).  You also
     /// provide the set of functions and global variables that are available during execution.
+    ///
+    /// Execution does not modify the file, its functions, or its global variables, and they are
+    /// all `Send` and `Sync`, so one checked file can be executed against many source files
+    /// concurrently, from as many threads as needed.
     pub fn execute<'a, 'tree>(
         &self,
         tree: &'tree Tree,
@@ -44,11 +107,95 @@ impl File {
         Ok(graph)
     }
 
+    /// Executes this graph DSL file against a source file, like [`execute`][Self::execute], and
+    /// returns the graph together with what the execution reported: the messages of its `print`
+    /// statements at the `warn` and `error` levels, statistics about each stanza, and the
+    /// statements that created each part of the graph.  The outcome collects its own statistics
+    /// and provenance, so `config` cannot have statistics or a source map, and fails with
+    /// [`ExecutionError::ConflictingConfig`][] if it does.  Messages are still sent to the output
+    /// of `config`, if it has one.
+    pub fn execute_with_outcome<'tree>(
+        &self,
+        tree: &'tree Tree,
+        source: &'tree str,
+        config: &ExecutionConfig,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<ExecutionOutcome<'tree>, ExecutionError> {
+        if config.statistics.is_some() || config.source_map.is_some() {
+            return Err(ExecutionError::ConflictingConfig(
+                "statistics and source maps are collected by the outcome".into(),
+            ));
+        }
+        let statistics = ExecutionStatistics::new();
+        let source_map = SourceMap::new();
+        let warnings = WarningCollector::new(config.output);
+        let config = ExecutionConfig {
+            functions: config.functions,
+            globals: config.globals,
+            lazy: config.lazy,
+            location_attr: config.location_attr.clone(),
+            variable_name_attr: config.variable_name_attr.clone(),
+            match_node_attr: config.match_node_attr.clone(),
+            environment: config.environment,
+            statistics: Some(&statistics),
+            limits: config.limits,
+            extensions: config.extensions,
+            sink: config.sink,
+            source_map: Some(&source_map),
+            debugger: config.debugger,
+            trace: config.trace,
+            coverage: config.coverage,
+            seed: config.seed,
+            output: Some(&warnings),
+            attribute_storage: config.attribute_storage,
+        };
+        let graph = self.execute(tree, source, &config, cancellation_flag)?;
+        Ok(ExecutionOutcome {
+            graph,
+            warnings: warnings.into_warnings(),
+            statistics,
+            provenance: source_map,
+        })
+    }
+
+    /// Executes this graph DSL file against a syntax tree that the caller maintains
+    /// incrementally, such as the tree of a document in an editor, or reuses the graph that was
+    /// built from the tree before.  `edits` are the edits that have been applied to the tree since
+    /// `previous` was built from it.  If there were none, the previous graph is reused for the
+    /// current tree, without executing the file again.  Otherwise, the whole file is executed
+    /// against the current tree.  Execution is not incremental: stanzas can share graph nodes
+    /// through scoped variables, so the stanzas whose matches overlap the edits cannot be executed
+    /// again on their own.
+    ///
+    /// The tree is never parsed again, but `source` must be its current text, which the graph DSL
+    /// file can refer to.
+    pub fn execute_or_reuse<'tree>(
+        &self,
+        tree: &'tree Tree,
+        source: &'tree str,
+        edits: &[InputEdit],
+        previous: Option<&Graph>,
+        config: &ExecutionConfig,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Graph<'tree>, ExecutionError> {
+        if edits.is_empty() {
+            if let Some(graph) = previous.and_then(|previous| previous.rebase(tree)) {
+                debug!("reused previous graph with {} nodes", graph.node_count());
+                return Ok(graph);
+            }
+        }
+        debug!("execute after {} edits", edits.len());
+        self.execute(tree, source, config, cancellation_flag)
+    }
+
     /// Executes this graph DSL file against a source file, saving the results into an existing
     /// `Graph` instance.  You must provide the parsed syntax tree (`tree`) as well as the source
     /// text that it was parsed from (`source`).  You also provide the set of functions and global
     /// variables that are available during execution. This variant is useful when you need to
     /// “pre-seed” the graph with some predefined nodes and/or edges before executing the DSL file.
+    /// The DSL file can look up the nodes that are added with
+    /// [`Graph::add_external_node`][crate::graph::Graph::add_external_node] by their keys, using
+    /// the [`external-node`][crate::reference::functions#external-node] function.
     pub fn execute_into<'a, 'tree>(
         &self,
         graph: &mut Graph<'tree>,
@@ -57,27 +204,45 @@ impl File {
         config: &ExecutionConfig,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), ExecutionError> {
-        if config.lazy {
+        let _span = debug_span!("execute", lazy = config.lazy).entered();
+        debug!(
+            "execute {} stanzas{}",
+            self.stanzas.len(),
+            if config.lazy { " lazily" } else { "" }
+        );
+        if !self.uses.is_empty() {
+            self.validate_functions(config.functions, config.environment)?;
+        }
+        let start = Instant::now();
+        graph.seed(config.seed);
+        if let Some(attribute_storage) = config.attribute_storage {
+            graph.set_attribute_storage(attribute_storage);
+        }
+        graph.share_source(source);
+        let result = if config.lazy {
             self.execute_lazy_into(graph, tree, source, config, cancellation_flag)
         } else {
             self.execute_strict_into(graph, tree, source, config, cancellation_flag)
-        }
+        };
+        graph.stop_sharing_source();
+        result?;
+        debug!(
+            "executed in {:?}, graph has {} nodes",
+            start.elapsed(),
+            graph.node_count()
+        );
+        Ok(())
     }
 
-    pub(self) fn check_globals(&self, globals: &mut Globals) -> Result<(), ExecutionError> {
+    /// Checks that `globals` provides a value for every global variable that this file declares
+    /// without a default, and that every list global has a list value.  The same check happens at
+    /// the start of every execution; calling this first lets you report a missing global once,
+    /// before processing any source files.
+    pub fn validate_globals(&self, globals: &Globals) -> Result<(), ExecutionError> {
         for global in &self.globals {
             match globals.get(&global.name) {
                 None => {
-                    if let Some(default) = &global.default {
-                        globals
-                            .add(global.name.clone(), default.to_string().into())
-                            .map_err(|_| {
-                                ExecutionError::DuplicateVariable(format!(
-                                    "global variable {} already defined",
-                                    global.name
-                                ))
-                            })?;
-                    } else {
+                    if global.default.is_none() {
                         return Err(ExecutionError::MissingGlobalVariable(
                             global.name.as_str().to_string(),
                         ));
@@ -100,6 +265,100 @@ impl File {
         Ok(())
     }
 
+    pub(self) fn check_globals(&self, globals: &mut Globals) -> Result<(), ExecutionError> {
+        self.validate_globals(globals)?;
+        for global in &self.globals {
+            if globals.get(&global.name).is_some() {
+                continue;
+            }
+            if let Some(default) = &global.default {
+                globals
+                    .add(global.name.clone(), default.to_string().into())
+                    .map_err(|_| {
+                        ExecutionError::DuplicateVariable(format!(
+                            "global variable {} already defined",
+                            global.name
+                        ))
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `functions`, together with the environment functions if they are enabled,
+    /// provide every namespace that this file declares in its `uses` declarations, and every
+    /// function that this file calls.  The error lists everything that is missing, rather than the
+    /// first call that would fail.  The same check happens at the start of every execution of a
+    /// file with `uses` declarations; calling this first lets you report missing functions once,
+    /// before processing any source files.
+    pub fn validate_functions(
+        &self,
+        functions: &Functions,
+        environment: Option<&Environment>,
+    ) -> Result<(), ExecutionError> {
+        let environment = environment.map(Environment::functions);
+        let mut missing_namespaces = self
+            .uses
+            .iter()
+            .flat_map(|uses| uses.namespaces.iter())
+            .filter(|namespace| {
+                !functions.has_namespace(namespace)
+                    && !environment.is_some_and(|env| env.has_namespace(namespace))
+            })
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        missing_namespaces.sort();
+        missing_namespaces.dedup();
+        if !missing_namespaces.is_empty() {
+            return Err(ExecutionError::MissingFunctionNamespaces(
+                missing_namespaces.join(", "),
+            ));
+        }
+
+        let mut calls = Calls::default();
+        calls.visit_file(self);
+        let mut missing_functions = calls
+            .functions
+            .into_iter()
+            .filter(|name| {
+                !functions.contains(name) && !environment.is_some_and(|env| env.contains(name))
+            })
+            .map(Identifier::as_str)
+            .collect::<Vec<_>>();
+        missing_functions.sort();
+        missing_functions.dedup();
+        if !missing_functions.is_empty() {
+            return Err(ExecutionError::MissingFunctions(
+                missing_functions.join(", "),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns, for each stanza, whether its guard holds, evaluating the guards against the global
+    /// variables of an execution.  Stanzas without a guard are always enabled.
+    pub(self) fn enabled_stanzas(
+        &self,
+        graph: &mut Graph,
+        source: &str,
+        config: &ExecutionConfig,
+    ) -> Result<Vec<bool>, ExecutionError> {
+        self.stanzas
+            .iter()
+            .map(|stanza| match &stanza.guard {
+                Some(guard) => guard
+                    .evaluate_guard(graph, source, config)
+                    .and_then(Value::into_boolean)
+                    .with_context(|| {
+                        format!("Evaluating guard of stanza at {}", stanza.range.start).into()
+                    }),
+                None => Ok(true),
+            })
+            .collect()
+    }
+
     pub fn try_visit_matches<'tree, E, F>(
         &self,
         tree: &'tree Tree,
@@ -110,9 +369,10 @@ impl File {
     where
         F: FnMut(Match<'_, 'tree>) -> Result<(), E>,
     {
+        let enabled = vec![true; self.stanzas.len()];
         if lazy {
             let file_query = self.query.as_ref().expect("missing file query");
-            self.try_visit_matches_lazy(tree, source, |stanza, mat| {
+            self.try_visit_matches_lazy(tree, source, &enabled, |stanza, mat| {
                 let named_captures = stanza
                     .query
                     .capture_names()
@@ -135,7 +395,7 @@ impl File {
                 })
             })
         } else {
-            self.try_visit_matches_strict(tree, source, |stanza, mat| {
+            self.try_visit_matches_strict(tree, source, &enabled, |stanza, mat| {
                 let named_captures = stanza
                     .query
                     .capture_names()
@@ -151,7 +411,7 @@ impl File {
                     .filter(|c| c.2 != stanza.full_match_stanza_capture_index as u32)
                     .collect();
                 visit(Match {
-                    mat,
+                    mat: &mat,
                     full_capture_index: stanza.full_match_stanza_capture_index as u32,
                     named_captures,
                     query_location: stanza.range.start,
@@ -171,7 +431,7 @@ impl Stanza {
     where
         F: FnMut(Match<'_, 'tree>) -> Result<(), E>,
     {
-        self.try_visit_matches_strict(tree, source, |mat| {
+        self.try_visit_matches_strict(tree, source, 0..usize::MAX, |mat| {
             let named_captures : Vec<(String,CaptureQuantifier,u32)> = self
                 .query
                 .capture_names()
@@ -187,7 +447,7 @@ impl Stanza {
                 .filter(|c| c.2 != self.full_match_stanza_capture_index as u32)
                 .collect();
             visit(Match {
-                mat,
+                mat: &mat,
                 full_capture_index: self.full_match_stanza_capture_index as u32,
                 named_captures,
                 query_location: self.range.start,
@@ -197,7 +457,7 @@ impl Stanza {
 }
 
 pub struct Match<'a, 'tree> {
-    mat: QueryMatch<'a, 'tree>,
+    mat: &'a QueryMatch<'a, 'tree>,
     full_capture_index: u32,
     named_captures: Vec<(String, CaptureQuantifier, u32)>,
     query_location: Location,
@@ -213,7 +473,7 @@ impl<'a, 'tree> Match<'a, 'tree> {
     }
 
     /// Return the matched nodes for a named capture.
-    pub fn named_captures<'s: 'a + 'tree>(
+    pub fn named_captures<'s>(
         &'s self,
     ) -> impl Iterator<
         Item = (
@@ -228,7 +488,7 @@ impl<'a, 'tree> Match<'a, 'tree> {
     }
 
     /// Return the matched nodes for a named capture.
-    pub fn named_capture<'s: 'a + 'tree>(
+    pub fn named_capture<'s>(
         &'s self,
         name: &str,
     ) -> Option<(CaptureQuantifier, impl Iterator<Item = Node<'tree>> + 's)> {
@@ -257,6 +517,18 @@ pub struct ExecutionConfig<'a, 'g> {
     pub(crate) location_attr: Option<Identifier>,
     pub(crate) variable_name_attr: Option<Identifier>,
     pub(crate) match_node_attr: Option<Identifier>,
+    pub(crate) environment: Option<&'a Environment>,
+    pub(crate) statistics: Option<&'a ExecutionStatistics>,
+    pub(crate) limits: Option<&'a ExecutionLimits>,
+    pub(crate) extensions: Option<&'a Extensions>,
+    pub(crate) sink: Option<&'a dyn GraphSink>,
+    pub(crate) source_map: Option<&'a SourceMap>,
+    pub(crate) debugger: Option<&'a dyn Debugger>,
+    pub(crate) trace: Option<&'a ExecutionTrace>,
+    pub(crate) coverage: Option<&'a ExecutionCoverage>,
+    pub(crate) seed: u64,
+    pub(crate) output: Option<&'a dyn PrintOutput>,
+    pub(crate) attribute_storage: Option<AttributeStorage>,
 }
 
 impl<'a, 'g> ExecutionConfig<'a, 'g> {
@@ -268,6 +540,18 @@ impl<'a, 'g> ExecutionConfig<'a, 'g> {
             location_attr: None,
             variable_name_attr: None,
             match_node_attr: None,
+            environment: None,
+            statistics: None,
+            limits: None,
+            extensions: None,
+            sink: None,
+            source_map: None,
+            debugger: None,
+            trace: None,
+            coverage: None,
+            seed: 0,
+            output: None,
+            attribute_storage: None,
         }
     }
 
@@ -284,6 +568,18 @@ impl<'a, 'g> ExecutionConfig<'a, 'g> {
             location_attr: location_attr.into(),
             variable_name_attr: variable_name_attr.into(),
             match_node_attr: match_node_attr.into(),
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
         }
     }
 
@@ -295,6 +591,317 @@ impl<'a, 'g> ExecutionConfig<'a, 'g> {
             location_attr: self.location_attr,
             variable_name_attr: self.variable_name_attr,
             match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Enables the functions that give access to the host environment, such as `env`,
+    /// `config-get`, `file-exists?`, and `now-iso8601`.  These are disabled by default.
+    pub fn environment(self, environment: &'a Environment) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: Some(environment),
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Collects statistics about each stanza into `statistics` during execution, such as how
+    /// often it matched and how long it took.
+    pub fn statistics(self, statistics: &'a ExecutionStatistics) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: Some(statistics),
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Limits the resources that the execution can use.
+    pub fn limits(self, limits: &'a ExecutionLimits) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: Some(limits),
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Executes the statements of a library of [extensions][crate::extensions], which must be
+    /// the library that the file was parsed with.
+    pub fn extensions(self, extensions: &'a Extensions) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: Some(extensions),
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Reports the graph nodes, edges, and attributes that the execution creates to `sink`, which
+    /// can reject or transform them before they are added to the graph.
+    pub fn sink(self, sink: &'a dyn GraphSink) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: Some(sink),
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Records the locations of the statements that create the graph nodes, edges, and attributes
+    /// into `source_map`.
+    pub fn source_map(self, source_map: &'a SourceMap) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: Some(source_map),
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Calls `debugger` before every statement of a strict execution.
+    pub fn debugger(self, debugger: &'a dyn Debugger) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: Some(debugger),
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Records every statement of a strict execution, with the values that it resolved, into
+    /// `trace`.
+    pub fn trace(self, trace: &'a ExecutionTrace) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: Some(trace),
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Counts how many times each stanza, arm, and statement runs into `coverage`.
+    pub fn coverage(self, coverage: &'a ExecutionCoverage) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: Some(coverage),
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Seeds the random number generator of the graph that the execution creates, which is the
+    /// only source of randomness available to functions.  Executions of the same file, against
+    /// the same source, with the same seed, produce identical graphs.  The seed is 0 by default.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed,
+            output: self.output,
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Sends the messages of `print` statements to `output`, which decides which levels and
+    /// channels it wants, instead of writing all of them to stderr.
+    pub fn output(self, output: &'a dyn PrintOutput) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: Some(output),
+            attribute_storage: self.attribute_storage,
+        }
+    }
+
+    /// Selects how the graph that the execution creates stores the attributes of its nodes.  If
+    /// it is not given, the graph keeps storing them as it does, which is in the nodes for new
+    /// graphs.
+    pub fn attribute_storage(self, attribute_storage: AttributeStorage) -> Self {
+        Self {
+            functions: self.functions,
+            globals: self.globals,
+            lazy: self.lazy,
+            location_attr: self.location_attr,
+            variable_name_attr: self.variable_name_attr,
+            match_node_attr: self.match_node_attr,
+            environment: self.environment,
+            statistics: self.statistics,
+            limits: self.limits,
+            extensions: self.extensions,
+            sink: self.sink,
+            source_map: self.source_map,
+            debugger: self.debugger,
+            trace: self.trace,
+            coverage: self.coverage,
+            seed: self.seed,
+            output: self.output,
+            attribute_storage: Some(attribute_storage),
         }
     }
 }
@@ -366,15 +973,216 @@ impl CreateEdge {
         Ok(())
     }
 }
+
+impl NewNode {
+    pub(crate) fn add_debug_attrs(
+        &self,
+        graph: &mut Graph,
+        node: GraphNodeRef,
+        config: &ExecutionConfig,
+    ) -> Result<(), ExecutionError> {
+        if let Some(location_attr) = &config.location_attr {
+            graph
+                .add_node_attribute(
+                    node,
+                    location_attr.clone(),
+                    format!(
+                        "line {} column {}",
+                        self.location.row + 1,
+                        self.location.column + 1
+                    ),
+                )
+                .map_err(|_| ExecutionError::DuplicateAttribute(location_attr.as_str().into()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Scan {
+    /// Returns the string that the arms are run on for one element of a list that is scanned:
+    /// the source text of a syntax node, or the value of a string.
+    pub(crate) fn element_text(
+        graph: &Graph,
+        source: &str,
+        element: &Value,
+    ) -> Result<String, ExecutionError> {
+        match element {
+            Value::SyntaxNode(node) => Ok(source[graph[*node].byte_range()].to_string()),
+            _ => element.as_str().map(str::to_string),
+        }
+    }
+}
+
+impl DestructureList {
+    /// Splits the elements of a list into the values of the element variables, and the list of
+    /// the remaining elements for the rest variable, if there is one.  Fails if the list is too
+    /// short, or if it is too long and there is no rest variable.
+    pub(crate) fn split(
+        &self,
+        mut values: Vec<Value>,
+    ) -> Result<(Vec<Value>, Option<Value>), ExecutionError> {
+        let count = self.elements.len();
+        if values.len() < count || (values.len() > count && self.rest.is_none()) {
+            let elements = match values.len() {
+                1 => "1 element".to_string(),
+                len => format!("{} elements", len),
+            };
+            return Err(ExecutionError::CannotDestructureList(format!(
+                "a list of {} in {}",
+                elements, self
+            )));
+        }
+        let rest = values.split_off(count);
+        Ok((values, self.rest.as_ref().map(|_| rest.into())))
+    }
+}
+
+/// The match of the regular expression of the current arm of a `scan` or `match` statement, which
+/// regex captures refer to.
+#[derive(Default)]
+pub(crate) struct RegexCaptures {
+    groups: Vec<String>,
+    names: HashMap<String, usize>,
+    range: Option<Range<usize>>,
+}
+
+impl RegexCaptures {
+    /// Creates the captures of a match of a regular expression in a substring that starts at
+    /// `offset` in the string that is scanned.
+    pub(crate) fn new(regex: &Regex, captures: &Captures, offset: usize) -> RegexCaptures {
+        let groups = captures
+            .iter()
+            .map(|c| c.map(|m| m.as_str()).unwrap_or("").to_string())
+            .collect();
+        let names = regex
+            .capture_names()
+            .enumerate()
+            .filter_map(|(index, name)| name.map(|name| (name.to_string(), index)))
+            .collect();
+        let range = captures.get(0).expect("missing regex capture").range();
+        RegexCaptures {
+            groups,
+            names,
+            range: Some(offset + range.start..offset + range.end),
+        }
+    }
+
+    /// Returns the value of a regex capture, or an error if the match does not have it.
+    pub(crate) fn get(&self, capture: &RegexCapture) -> Result<Value, ExecutionError> {
+        let value = match &capture.group {
+            RegexGroup::Index(index) => self.groups.get(*index).cloned().map(Value::from),
+            RegexGroup::Name(name) => self
+                .names
+                .get(name.as_str())
+                .map(|index| self.groups[*index].clone().into()),
+            RegexGroup::Start => self.range.as_ref().map(|r| Value::Integer(r.start as i64)),
+            RegexGroup::End => self.range.as_ref().map(|r| Value::Integer(r.end as i64)),
+        };
+        value.ok_or_else(|| ExecutionError::UndefinedRegexCapture(format!("{}", capture)))
+    }
+}
+
+impl crate::ast::Match {
+    /// Returns the first arm with a pattern that matches a string, if there is one.  If that
+    /// pattern is a regular expression, also returns its match, which is available to the
+    /// statements of the arm.
+    pub(crate) fn select_arm(&self, value: &str) -> Option<(&MatchArm, Option<RegexCaptures>)> {
+        for arm in &self.arms {
+            if arm.patterns.is_empty() {
+                return Some((arm, None));
+            }
+            for pattern in &arm.patterns {
+                match pattern {
+                    MatchPattern::String(string) if string == value => return Some((arm, None)),
+                    MatchPattern::String(_) => {}
+                    MatchPattern::Regex(regex) => {
+                        if let Some(captures) = regex.captures(value) {
+                            let captures = RegexCaptures::new(regex, &captures, 0);
+                            return Some((arm, Some(captures)));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Expression {
+    /// Evaluates a stanza guard, which the checker has made sure only uses literals, global
+    /// variables, and function calls.
+    fn evaluate_guard(
+        &self,
+        graph: &mut Graph,
+        source: &str,
+        config: &ExecutionConfig,
+    ) -> Result<Value, ExecutionError> {
+        match self {
+            Expression::FalseLiteral => Ok(Value::Boolean(false)),
+            Expression::NullLiteral => Ok(Value::Null),
+            Expression::TrueLiteral => Ok(Value::Boolean(true)),
+            Expression::IntegerConstant(expr) => Ok(Value::Integer(expr.value)),
+            Expression::StringConstant(expr) => Ok(Value::String(expr.value.clone())),
+            Expression::ListLiteral(expr) => {
+                let elements = expr
+                    .elements
+                    .iter()
+                    .map(|e| e.evaluate_guard(graph, source, config))
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::List(elements))
+            }
+            Expression::SetLiteral(expr) => {
+                let elements = expr
+                    .elements
+                    .iter()
+                    .map(|e| e.evaluate_guard(graph, source, config))
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::Set(elements))
+            }
+            Expression::Variable(Variable::Unscoped(variable)) => config
+                .globals
+                .get(&variable.name)
+                .cloned()
+                .ok_or_else(|| ExecutionError::UndefinedVariable(format!("{}", variable))),
+            Expression::Call(expr) => {
+                let parameters = expr
+                    .parameters
+                    .iter()
+                    .map(|p| p.evaluate_guard(graph, source, config))
+                    .collect::<Result<Vec<_>, _>>()?;
+                config.functions.call_with_environment(
+                    config.environment,
+                    None,
+                    &expr.function,
+                    graph,
+                    source,
+                    &mut parameters.into_iter(),
+                )
+            }
+            _ => unreachable!("invalid stanza guard {}", self),
+        }
+    }
+}
+
+impl ForInZip {
+    /// Returns the elements at each index of some lists, up to the length of the shortest list.
+    pub(crate) fn zip(lists: Vec<Vec<Value>>) -> impl Iterator<Item = Vec<Value>> {
+        let len = lists.iter().map(Vec::len).min().unwrap_or(0);
+        let mut lists = lists.into_iter().map(Vec::into_iter).collect::<Vec<_>>();
+        (0..len).map(move |_| lists.iter_mut().map(|list| list.next().unwrap()).collect())
+    }
+}
+
 impl Variable {
     pub(crate) fn add_debug_attrs(
         &self,
-        attributes: &mut Attributes,
+        graph: &mut Graph,
+        node: GraphNodeRef,
         config: &ExecutionConfig,
     ) -> Result<(), ExecutionError> {
         if let Some(variable_name_attr) = &config.variable_name_attr {
-            attributes
-                .add(variable_name_attr.clone(), format!("{}", self))
+            graph
+                .add_node_attribute(node, variable_name_attr.clone(), format!("{}", self))
                 .map_err(|_| {
                     ExecutionError::DuplicateAttribute(variable_name_attr.as_str().into())
                 })?;
@@ -384,8 +1192,9 @@ impl Variable {
                 Variable::Scoped(v) => v.location,
                 Variable::Unscoped(v) => v.location,
             };
-            attributes
-                .add(
+            graph
+                .add_node_attribute(
+                    node,
                     location_attr.clone(),
                     format!("line {} column {}", location.row + 1, location.column + 1),
                 )
@@ -394,3 +1203,16 @@ impl Variable {
         Ok(())
     }
 }
+
+/// Collects the names of the functions that a file calls.
+#[derive(Default)]
+struct Calls<'ast> {
+    functions: Vec<&'ast Identifier>,
+}
+
+impl<'ast> Visitor<'ast> for Calls<'ast> {
+    fn visit_call(&mut self, call: &'ast Call) {
+        self.functions.push(&call.function);
+        visit::walk_call(self, call);
+    }
+}
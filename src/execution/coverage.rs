@@ -0,0 +1,180 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines the coverage of a graph DSL file that can be collected while executing it.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::ast::Stanza;
+use crate::ast::Statement;
+use crate::Location;
+
+/// How many times each stanza, arm, and statement of a graph DSL file ran, collected while
+/// executing it.  Use [`ExecutionConfig::coverage`][crate::ExecutionConfig::coverage] to collect
+/// it.  The same instance can be used for several executions of a file, or instances can be
+/// merged, to find the parts of a file that a whole corpus of source files never runs.
+///
+/// Everything in the file is reported, including the stanzas, arms, and statements that never
+/// ran, which have a count of zero.
+#[derive(Debug, Default)]
+pub struct ExecutionCoverage {
+    stanzas: RefCell<BTreeMap<Location, usize>>,
+    arms: RefCell<BTreeMap<Location, ArmCoverage>>,
+    statements: RefCell<BTreeMap<Location, usize>>,
+}
+
+/// How many times a stanza or statement ran.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct CoverageCount {
+    /// The location of the stanza or statement in the graph DSL file
+    pub location: Location,
+    /// The number of times that it ran, which for a stanza is its number of matches
+    pub count: usize,
+}
+
+/// How many times an arm of an `if` or `scan` statement was taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ArmCoverage {
+    /// The location of the `if` or `scan` statement in the graph DSL file
+    pub statement: Location,
+    /// The index of the arm in its statement
+    pub index: usize,
+    /// The location of the arm in the graph DSL file
+    pub location: Location,
+    /// The number of times that the arm was taken
+    pub count: usize,
+}
+
+impl ExecutionCoverage {
+    pub fn new() -> ExecutionCoverage {
+        ExecutionCoverage::default()
+    }
+
+    /// Returns the coverage of every stanza, in the order that they appear in the file.
+    pub fn stanzas(&self) -> Vec<CoverageCount> {
+        Self::counts(&self.stanzas.borrow())
+    }
+
+    /// Returns the coverage of every arm of the `if` and `scan` statements, in the order that
+    /// they appear in the file.
+    pub fn arms(&self) -> Vec<ArmCoverage> {
+        self.arms.borrow().values().copied().collect()
+    }
+
+    /// Returns the coverage of every statement, including the statements of nested blocks, in the
+    /// order that they appear in the file.
+    pub fn statements(&self) -> Vec<CoverageCount> {
+        Self::counts(&self.statements.borrow())
+    }
+
+    /// Adds the coverage collected by `other` to this one.  Both must have been collected from
+    /// the same graph DSL file.
+    pub fn merge(&self, other: &ExecutionCoverage) {
+        Self::merge_counts(&mut self.stanzas.borrow_mut(), &other.stanzas.borrow());
+        Self::merge_counts(
+            &mut self.statements.borrow_mut(),
+            &other.statements.borrow(),
+        );
+        let mut arms = self.arms.borrow_mut();
+        for (location, other) in other.arms.borrow().iter() {
+            arms.entry(*location)
+                .or_insert(ArmCoverage { count: 0, ..*other })
+                .count += other.count;
+        }
+    }
+
+    fn counts(map: &BTreeMap<Location, usize>) -> Vec<CoverageCount> {
+        map.iter()
+            .map(|(location, count)| CoverageCount {
+                location: *location,
+                count: *count,
+            })
+            .collect()
+    }
+
+    fn merge_counts(map: &mut BTreeMap<Location, usize>, other: &BTreeMap<Location, usize>) {
+        for (location, count) in other {
+            *map.entry(*location).or_default() += count;
+        }
+    }
+
+    /// Makes sure that all of `stanzas`, and all of their arms and statements, are reported, even
+    /// if they never run.
+    pub(crate) fn add_stanzas<'a>(&self, stanzas: impl IntoIterator<Item = &'a Stanza>) {
+        for stanza in stanzas {
+            self.stanzas
+                .borrow_mut()
+                .entry(stanza.range.start)
+                .or_default();
+            self.add_statements(&stanza.statements);
+        }
+    }
+
+    fn add_statements(&self, statements: &[Statement]) {
+        for statement in statements {
+            self.statements
+                .borrow_mut()
+                .entry(statement.location())
+                .or_default();
+            match statement {
+                Statement::If(statement) => {
+                    for (index, arm) in statement.arms.iter().enumerate() {
+                        self.add_arm(statement.location, index, arm.location);
+                        self.add_statements(&arm.statements);
+                    }
+                }
+                Statement::Scan(statement) => {
+                    for (index, arm) in statement.arms.iter().enumerate() {
+                        self.add_arm(statement.location, index, arm.location);
+                        self.add_statements(&arm.statements);
+                    }
+                }
+                Statement::Match(statement) => {
+                    for (index, arm) in statement.arms.iter().enumerate() {
+                        self.add_arm(statement.location, index, arm.location);
+                        self.add_statements(&arm.statements);
+                    }
+                }
+                Statement::ForIn(statement) => self.add_statements(&statement.statements),
+                Statement::ForInZip(statement) => self.add_statements(&statement.statements),
+                _ => {}
+            }
+        }
+    }
+
+    fn add_arm(&self, statement: Location, index: usize, location: Location) {
+        self.arms
+            .borrow_mut()
+            .entry(location)
+            .or_insert(ArmCoverage {
+                statement,
+                index,
+                location,
+                count: 0,
+            });
+    }
+
+    /// Records one match of a stanza.
+    pub(crate) fn record_stanza(&self, stanza: Location) {
+        *self.stanzas.borrow_mut().entry(stanza).or_default() += 1;
+    }
+
+    /// Records that an arm was taken.  The arm must have been added with its stanza.
+    pub(crate) fn record_arm(&self, arm: Location) {
+        if let Some(arm) = self.arms.borrow_mut().get_mut(&arm) {
+            arm.count += 1;
+        }
+    }
+
+    /// Records that a statement ran.
+    pub(crate) fn record_statement(&self, statement: Location) {
+        *self.statements.borrow_mut().entry(statement).or_default() += 1;
+    }
+}
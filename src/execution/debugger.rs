@@ -0,0 +1,126 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines the hook that debuggers use to follow an execution statement by statement.
+
+use tree_sitter::Node;
+use tree_sitter::QueryMatch;
+
+use crate::ast::Stanza;
+use crate::ast::Statement;
+use crate::graph::Graph;
+use crate::graph::SyntaxNodeID;
+use crate::graph::Value;
+use crate::parser::FULL_MATCH;
+use crate::variables::MutVariables;
+use crate::Identifier;
+use crate::Location;
+
+/// Receives a callback before every statement of an execution, including the statements of
+/// nested blocks.  Use [`ExecutionConfig::debugger`][crate::ExecutionConfig::debugger] to install
+/// a debugger.
+///
+/// Execution waits for the callback to return, so a debugger can pause it at a breakpoint by not
+/// returning until the user resumes.  Use a [`CancellationFlag`][crate::CancellationFlag] to stop
+/// the execution early.
+///
+/// Only strict execution calls the debugger, since lazy execution does not execute statements in
+/// the order in which they appear.
+pub trait Debugger {
+    /// Called before a statement is executed.
+    fn statement(&self, frame: &DebugFrame);
+}
+
+/// The state of an execution before a statement, as seen by a [`Debugger`][].
+pub struct DebugFrame<'a, 'tree> {
+    pub(crate) statement: &'a Statement,
+    pub(crate) stanza: &'a Stanza,
+    pub(crate) depth: usize,
+    pub(crate) source: &'tree str,
+    pub(crate) graph: &'a Graph<'tree>,
+    pub(crate) mat: &'a QueryMatch<'a, 'tree>,
+    pub(crate) locals: &'a dyn MutVariables<Value>,
+    pub(crate) scoped: &'a dyn ScopedVariableLookup,
+}
+
+impl<'a, 'tree> DebugFrame<'a, 'tree> {
+    /// Returns the statement that is about to be executed.
+    pub fn statement(&self) -> &'a Statement {
+        self.statement
+    }
+
+    /// Returns the stanza that the statement belongs to.
+    pub fn stanza(&self) -> &'a Stanza {
+        self.stanza
+    }
+
+    /// Returns the location of the statement in the graph DSL file.
+    pub fn location(&self) -> Location {
+        self.statement.location()
+    }
+
+    /// Returns the number of blocks that the statement is nested in, which is 0 for the
+    /// statements of a stanza.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the source file that the graph DSL file is executed against.
+    pub fn source(&self) -> &'tree str {
+        self.source
+    }
+
+    /// Returns the graph, as it has been constructed up to the statement.
+    pub fn graph(&self) -> &'a Graph<'tree> {
+        self.graph
+    }
+
+    /// Returns the syntax node that the query of the stanza matched.
+    pub fn match_node(&self) -> Node<'tree> {
+        self.mat
+            .nodes_for_capture_index(self.stanza.full_match_stanza_capture_index as u32)
+            .next()
+            .expect("missing capture for full match")
+    }
+
+    /// Returns the captures of the stanza's query, with the syntax nodes that they captured in
+    /// the current match.
+    pub fn captures(&self) -> Vec<(&'a str, Vec<Node<'tree>>)> {
+        self.stanza
+            .query
+            .capture_names()
+            .iter()
+            .filter(|name| **name != FULL_MATCH)
+            .map(|name| (*name, self.captured_nodes(name).collect()))
+            .collect()
+    }
+
+    /// Returns the local variables that are defined before the statement, including those of
+    /// the enclosing blocks, in no particular order.
+    pub fn locals(&self) -> Vec<(&Identifier, &Value)> {
+        self.locals.variables()
+    }
+
+    /// Returns the scoped variables of a syntax node, in no particular order.
+    pub fn scoped_variables(&self, node: Node) -> Vec<(&Identifier, &Value)> {
+        self.scoped.scoped_variables(node.id() as SyntaxNodeID)
+    }
+
+    fn captured_nodes(&self, name: &str) -> impl Iterator<Item = Node<'tree>> + 'a {
+        let index = self
+            .stanza
+            .query
+            .capture_index_for_name(name)
+            .expect("missing capture");
+        self.mat.nodes_for_capture_index(index)
+    }
+}
+
+/// The scoped variables of an execution, by syntax node.
+pub(crate) trait ScopedVariableLookup {
+    fn scoped_variables(&self, node: SyntaxNodeID) -> Vec<(&Identifier, &Value)>;
+}
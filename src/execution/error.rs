@@ -25,6 +25,8 @@ pub enum ExecutionError {
     CannotAssignScopedVariable(String),
     #[error("Cannot define mutable scoped variable {0}")]
     CannotDefineMutableScopedVariable(String),
+    #[error("Cannot destructure {0}")]
+    CannotDestructureList(String),
     #[error("Duplicate attribute {0}")]
     DuplicateAttribute(String),
     #[error("Duplicate edge {0}")]
@@ -49,16 +51,24 @@ pub enum ExecutionError {
     InvalidVariableScope(String),
     #[error("Missing global variable {0}")]
     MissingGlobalVariable(String),
+    #[error("Missing function namespace(s) {0}")]
+    MissingFunctionNamespaces(String),
+    #[error("Missing function(s) {0}")]
+    MissingFunctions(String),
     #[error("Recursively defined scoped variable {0}")]
     RecursivelyDefinedScopedVariable(String),
     #[error("Recursively defined variable {0}")]
     RecursivelyDefinedVariable(String),
+    #[error("Rejected attribute {0}")]
+    RejectedAttribute(String),
     #[error("Undefined capture {0}")]
     UndefinedCapture(String),
     #[error("Undefined function {0}")]
     UndefinedFunction(String),
     #[error("Undefined regex capture {0}")]
     UndefinedRegexCapture(String),
+    #[error("Undefined statement {0}")]
+    UndefinedStatement(String),
     #[error("Undefined scoped variable {0}")]
     UndefinedScopedVariable(String),
     #[error("Empty regex capture {0}")]
@@ -71,6 +81,10 @@ pub enum ExecutionError {
     VariableScopesAlreadyForced(String),
     #[error("Function {0} failed: {1}")]
     FunctionFailed(String, String),
+    #[error("Exceeded the {0}")]
+    LimitExceeded(String),
+    #[error("Conflicting configuration {0}")]
+    ConflictingConfig(String),
     #[error("{0}. Caused by: {1}")]
     InContext(Context, Box<ExecutionError>),
 }
@@ -81,12 +95,16 @@ pub enum Context {
     Other(String),
 }
 
+/// The statement that was executing when an error occurred, along with the stanza that it belongs
+/// to and the syntax node that the stanza matched
 #[derive(Clone, Debug)]
 pub struct StatementContext {
     pub statement: String,
     pub statement_location: Location,
     pub stanza_location: Location,
     pub source_location: Location,
+    /// The end of the matched syntax node
+    pub source_end_location: Location,
     pub node_kind: String,
 }
 
@@ -97,6 +115,7 @@ impl StatementContext {
             statement_location: stmt.location(),
             stanza_location: stanza.range.start,
             source_location: Location::from(source_node.range().start_point),
+            source_end_location: Location::from(source_node.range().end_point),
             node_kind: source_node.kind().to_string(),
         }
     }
@@ -180,6 +199,27 @@ impl<R> ResultWithExecutionError<R> for Result<R, ExecutionError> {
 }
 
 impl ExecutionError {
+    /// Returns the innermost error, without the context that it occurred in.
+    pub fn root_cause(&self) -> &ExecutionError {
+        match self {
+            ExecutionError::InContext(_, cause) => cause.root_cause(),
+            other => other,
+        }
+    }
+
+    /// Returns the statements that were executing when the error occurred, outermost first.
+    pub fn statement_contexts(&self) -> Vec<&StatementContext> {
+        let mut result = Vec::new();
+        let mut error = self;
+        while let ExecutionError::InContext(context, cause) = error {
+            if let Context::Statement(stmts) = context {
+                result.extend(stmts);
+            }
+            error = cause;
+        }
+        result
+    }
+
     pub fn display_pretty<'a>(
         &'a self,
         source_path: &'a Path,
@@ -247,6 +287,17 @@ impl DisplayExecutionErrorPretty<'_> {
 }
 
 impl StatementContext {
+    /// The columns of the matched syntax node on its first line, which are underlined in the
+    /// source excerpt.
+    fn source_column_range(&self) -> std::ops::Range<usize> {
+        let start = self.source_location.column;
+        if self.source_end_location.row != self.source_location.row {
+            start..usize::MAX
+        } else {
+            start..self.source_end_location.column.max(start + 1)
+        }
+    }
+
     fn fmt_pretty(
         &self,
         f: &mut std::fmt::Formatter<'_>,
@@ -297,7 +348,7 @@ impl StatementContext {
                 source_path,
                 source,
                 self.source_location.row,
-                self.source_location.to_column_range(),
+                self.source_column_range(),
                 7
             )
         )?;
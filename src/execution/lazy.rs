@@ -9,11 +9,15 @@ mod statements;
 mod store;
 mod values;
 
-use log::{debug, trace};
+use tracing::{debug, debug_span, trace};
 
+use std::cell::OnceCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
 
+use tree_sitter::Query;
 use tree_sitter::QueryCursor;
 use tree_sitter::QueryMatch;
 use tree_sitter::Tree;
@@ -22,7 +26,18 @@ use crate::ast;
 use crate::execution::error::ExecutionError;
 use crate::execution::error::ResultWithExecutionError;
 use crate::execution::error::StatementContext;
+use crate::execution::limits::ExecutionLimits;
+use crate::execution::limits::ResourceUsage;
+use crate::execution::output;
+use crate::execution::output::PrintOutput;
+use crate::execution::regions::MatchRegions;
+use crate::execution::sink::SinkFilter;
+use crate::execution::source_map::SourceMap;
+use crate::execution::statistics::ExecutionStatistics;
 use crate::execution::ExecutionConfig;
+use crate::execution::RegexCaptures;
+use crate::functions::CallContext;
+use crate::functions::Environment;
 use crate::functions::Functions;
 use crate::graph;
 use crate::graph::Attributes;
@@ -61,6 +76,18 @@ impl ast::File {
             location_attr: config.location_attr.clone(),
             variable_name_attr: config.variable_name_attr.clone(),
             match_node_attr: config.match_node_attr.clone(),
+            environment: config.environment,
+            statistics: config.statistics,
+            limits: config.limits,
+            extensions: config.extensions,
+            sink: config.sink,
+            source_map: config.source_map,
+            debugger: config.debugger,
+            trace: config.trace,
+            coverage: config.coverage,
+            seed: config.seed,
+            output: config.output,
+            attribute_storage: config.attribute_storage,
         };
 
         let mut locals = VariableMap::new();
@@ -69,12 +96,29 @@ impl ast::File {
         let mut lazy_graph = LazyGraph::new();
         let mut function_parameters = Vec::new();
         let mut prev_element_debug_info = HashMap::new();
+        let no_limits = ExecutionLimits::default();
+        let usage = ResourceUsage::new(config.limits.unwrap_or(&no_limits), graph);
+        let sink_filter = SinkFilter::new(config.sink, graph);
 
-        self.try_visit_matches_lazy(tree, source, |stanza, mat| {
+        if let Some(statistics) = config.statistics {
+            statistics.add_stanzas(&self.stanzas);
+        }
+        if let Some(coverage) = config.coverage {
+            coverage.add_stanzas(&self.stanzas);
+        }
+        let enabled = self.enabled_stanzas(graph, source, &config)?;
+        let capture_names = self
+            .query
+            .as_ref()
+            .map_or(&[][..], |query| query.capture_names());
+        self.try_visit_matches_lazy(tree, source, &enabled, |stanza, mat| {
             cancellation_flag.check("processing matches")?;
+            let start = Instant::now();
+            let node_count = graph.node_count();
             stanza.execute_lazy(
                 source,
-                &mat,
+                mat,
+                capture_names,
                 graph,
                 &mut config,
                 &mut locals,
@@ -85,26 +129,47 @@ impl ast::File {
                 &mut prev_element_debug_info,
                 &self.inherited_variables,
                 &self.shorthands,
+                &usage,
+                &sink_filter,
                 cancellation_flag,
-            )
+            )?;
+            if let Some(statistics) = config.statistics {
+                statistics.record_match(
+                    stanza.range.start,
+                    start.elapsed(),
+                    graph.node_count() - node_count,
+                );
+            }
+            if let Some(coverage) = config.coverage {
+                coverage.record_stanza(stanza.range.start);
+            }
+            Ok::<_, ExecutionError>(())
         })?;
 
         let mut exec = EvaluationContext {
             source,
             graph,
             functions: config.functions,
+            environment: config.environment,
+            statistics: config.statistics,
+            source_map: config.source_map,
+            output: config.output,
             store: &store,
             scoped_store: &scoped_store,
             inherited_variables: &self.inherited_variables,
             function_parameters: &mut function_parameters,
             prev_element_debug_info: &mut prev_element_debug_info,
+            usage: &usage,
+            sink_filter: &sink_filter,
             cancellation_flag,
         };
+        let _span = debug_span!("evaluate").entered();
         lazy_graph.evaluate(&mut exec)?;
         // make sure any unforced values are now forced, to surface any problems
         // hidden by the fact that the values were unused
         store.evaluate_all(&mut exec)?;
         scoped_store.evaluate_all(&mut exec)?;
+        sink_filter.report_nodes(exec.graph);
 
         Ok(())
     }
@@ -113,20 +178,57 @@ impl ast::File {
         &self,
         tree: &'tree Tree,
         source: &'tree str,
+        enabled: &[bool],
         mut visit: F,
     ) -> Result<(), E>
     where
-        F: FnMut(&ast::Stanza, QueryMatch<'_, 'tree>) -> Result<(), E>,
+        F: FnMut(&ast::Stanza, &QueryMatch<'_, 'tree>) -> Result<(), E>,
     {
+        let region = match MatchRegions::new(self, tree).enabled_region(enabled) {
+            Some(region) => region,
+            None => return Ok(()),
+        };
         let mut cursor = QueryCursor::new();
-        let query = self.query.as_ref().unwrap();
+        cursor.set_byte_range(region);
+        let guarded_query = self.guarded_query(enabled);
+        let query = guarded_query
+            .as_ref()
+            .unwrap_or_else(|| self.query.as_ref().unwrap());
         let matches = cursor.matches(query, tree.root_node(), source.as_bytes());
         for mat in matches {
-            let stanza = &self.stanzas[mat.pattern_index];
-            visit(stanza, mat)?;
+            // Patterns can be shared by enabled and disabled stanzas, so we skip the disabled
+            // ones here.
+            for index in &self.pattern_stanzas[mat.pattern_index] {
+                if enabled[*index] {
+                    visit(&self.stanzas[*index], &mat)?;
+                }
+            }
         }
         Ok(())
     }
+
+    /// Returns a copy of the file query without the patterns whose stanzas are all disabled by
+    /// their guards, so that they are not matched at all, or `None` if the guards do not disable
+    /// any pattern.
+    fn guarded_query(&self, enabled: &[bool]) -> Option<Query> {
+        let disabled = |stanzas: &Vec<usize>| stanzas.iter().all(|index| !enabled[*index]);
+        if !self
+            .pattern_stanzas
+            .iter()
+            .any(|stanzas| !stanzas.is_empty() && disabled(stanzas))
+        {
+            return None;
+        }
+        // Patterns without stanzas are already disabled in the file query.
+        let mut query = Query::new(&self.language, &self.query_source).unwrap();
+        for (pattern_index, stanzas) in self.pattern_stanzas.iter().enumerate() {
+            if disabled(stanzas) {
+                debug!("skipping pattern {} of disabled stanzas", pattern_index);
+                query.disable_pattern(pattern_index);
+            }
+        }
+        Some(query)
+    }
 }
 
 /// Context for execution, which executes stanzas to build the lazy graph
@@ -135,8 +237,9 @@ struct ExecutionContext<'a, 'c, 'g, 'tree> {
     graph: &'a mut Graph<'tree>,
     config: &'a ExecutionConfig<'c, 'g>,
     locals: &'a mut dyn MutVariables<LazyValue>,
-    current_regex_captures: &'a Vec<String>,
+    current_regex_captures: &'a RegexCaptures,
     mat: &'a QueryMatch<'a, 'tree>,
+    call_site: &'a CallSite<'a>,
     full_match_file_capture_index: usize,
     store: &'a mut LazyStore,
     scoped_store: &'a mut LazyScopedVariables,
@@ -146,19 +249,56 @@ struct ExecutionContext<'a, 'c, 'g, 'tree> {
     error_context: StatementContext,
     inherited_variables: &'a HashSet<Identifier>,
     shorthands: &'a ast::AttributeShorthands,
+    usage: &'a ResourceUsage<'a>,
+    sink_filter: &'a SinkFilter<'a>,
     cancellation_flag: &'a dyn CancellationFlag,
 }
 
+/// The stanza that is executing on a match, and the context of the function calls that it makes
+/// on the match, which is only built if it calls any.  The calls are evaluated after all matches
+/// have been executed, so they hold on to the context.
+struct CallSite<'a> {
+    stanza: &'a ast::Stanza,
+    /// The capture names of the file query
+    capture_names: &'a [&'a str],
+    context: OnceCell<Arc<CallContext>>,
+}
+
+impl CallSite<'_> {
+    fn context<'tree>(
+        &self,
+        mat: &QueryMatch<'_, 'tree>,
+        graph: &mut Graph<'tree>,
+    ) -> Arc<CallContext> {
+        self.context
+            .get_or_init(|| {
+                Arc::new(CallContext::new(
+                    self.stanza,
+                    self.capture_names,
+                    mat,
+                    graph,
+                ))
+            })
+            .clone()
+    }
+}
+
 /// Context for evaluation, which evalautes the lazy graph to build the actual graph
 pub(self) struct EvaluationContext<'a, 'tree> {
     pub source: &'tree str,
     pub graph: &'a mut Graph<'tree>,
     pub functions: &'a Functions,
+    pub environment: Option<&'a Environment>,
+    pub statistics: Option<&'a ExecutionStatistics>,
+    pub source_map: Option<&'a SourceMap>,
+    pub output: Option<&'a dyn PrintOutput>,
     pub store: &'a LazyStore,
     pub scoped_store: &'a LazyScopedVariables,
     pub inherited_variables: &'a HashSet<Identifier>,
     pub function_parameters: &'a mut Vec<graph::Value>, // re-usable buffer to reduce memory allocations
     pub prev_element_debug_info: &'a mut HashMap<GraphElementKey, DebugInfo>,
+    pub usage: &'a ResourceUsage<'a>,
+    pub sink_filter: &'a SinkFilter<'a>,
     pub cancellation_flag: &'a dyn CancellationFlag,
 }
 
@@ -173,6 +313,7 @@ impl ast::Stanza {
         &self,
         source: &'tree str,
         mat: &QueryMatch<'_, 'tree>,
+        capture_names: &[&str],
         graph: &mut Graph<'tree>,
         config: &ExecutionConfig,
         locals: &mut VariableMap<'l, LazyValue>,
@@ -183,16 +324,29 @@ impl ast::Stanza {
         prev_element_debug_info: &mut HashMap<GraphElementKey, DebugInfo>,
         inherited_variables: &HashSet<Identifier>,
         shorthands: &ast::AttributeShorthands,
+        usage: &ResourceUsage,
+        sink_filter: &SinkFilter,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), ExecutionError> {
-        let current_regex_captures = vec![];
+        let current_regex_captures = RegexCaptures::default();
         locals.clear();
         let node = mat
             .nodes_for_capture_index(self.full_match_file_capture_index as u32)
             .next()
             .expect("missing capture for full match");
+        let _span = debug_span!(
+            "stanza",
+            at = %self.range.start,
+            name = self.name.as_ref().map(Identifier::as_str),
+        )
+        .entered();
         debug!("match {:?} at {}", node, self.range.start);
         trace!("{{");
+        let call_site = CallSite {
+            stanza: self,
+            capture_names,
+            context: OnceCell::new(),
+        };
         for statement in &self.statements {
             let error_context = { StatementContext::new(&statement, &self, &node) };
             let mut exec = ExecutionContext {
@@ -202,6 +356,7 @@ impl ast::Stanza {
                 locals,
                 current_regex_captures: &current_regex_captures,
                 mat,
+                call_site: &call_site,
                 full_match_file_capture_index: self.full_match_file_capture_index,
                 store,
                 scoped_store,
@@ -211,6 +366,8 @@ impl ast::Stanza {
                 error_context,
                 inherited_variables,
                 shorthands,
+                usage,
+                sink_filter,
                 cancellation_flag,
             };
             statement
@@ -225,9 +382,15 @@ impl ast::Stanza {
 impl ast::Statement {
     fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
         exec.cancellation_flag.check("executing statement")?;
+        exec.usage.execute_statement()?;
+        if let Some(coverage) = exec.config.coverage {
+            coverage.record_statement(self.location());
+        }
+        let node_count = exec.graph.node_count();
         match self {
             Self::DeclareImmutable(statement) => statement.execute_lazy(exec),
             Self::DeclareMutable(statement) => statement.execute_lazy(exec),
+            Self::DestructureList(statement) => statement.execute_lazy(exec),
             Self::Assign(statement) => statement.execute_lazy(exec),
             Self::CreateGraphNode(statement) => statement.execute_lazy(exec),
             Self::AddGraphNodeAttribute(statement) => statement.execute_lazy(exec),
@@ -235,9 +398,16 @@ impl ast::Statement {
             Self::AddEdgeAttribute(statement) => statement.execute_lazy(exec),
             Self::Scan(statement) => statement.execute_lazy(exec),
             Self::Print(statement) => statement.execute_lazy(exec),
+            Self::Extension(statement) => statement.execute_lazy(exec),
             Self::If(statement) => statement.execute_lazy(exec),
+            Self::Match(statement) => statement.execute_lazy(exec),
             Self::ForIn(statement) => statement.execute_lazy(exec),
+            Self::ForInZip(statement) => statement.execute_lazy(exec),
+        }?;
+        if let Some(source_map) = exec.config.source_map {
+            source_map.record_nodes(exec.graph.iter_nodes().skip(node_count), self.location());
         }
+        exec.usage.check_nodes(exec.graph)
     }
 }
 
@@ -255,6 +425,20 @@ impl ast::DeclareMutable {
     }
 }
 
+impl ast::DestructureList {
+    fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        let values = self.value.evaluate_eager(exec)?.into_list()?;
+        let (values, rest_value) = self.split(values)?;
+        for (element, value) in self.elements.iter().zip(values) {
+            element.add_lazy(exec, value.into(), false)?;
+        }
+        if let (Some(rest), Some(rest_value)) = (&self.rest, rest_value) {
+            rest.add_lazy(exec, rest_value.into(), false)?;
+        }
+        Ok(())
+    }
+}
+
 impl ast::Assign {
     fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
         let value = self.value.evaluate_lazy(exec)?;
@@ -264,37 +448,54 @@ impl ast::Assign {
 
 impl ast::CreateGraphNode {
     fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
-        let graph_node = exec.graph.add_graph_node();
+        let graph_node = match &self.label {
+            Some(label) => {
+                let label = label.evaluate_eager(exec)?.into_string()?;
+                exec.graph.add_labeled_graph_node(label.as_str().into())
+            }
+            None => exec.graph.add_graph_node(),
+        };
         self.node
-            .add_debug_attrs(&mut exec.graph[graph_node].attributes, exec.config)?;
-        if let Some(match_node_attr) = &exec.config.match_node_attr {
-            let match_node = exec
-                .mat
-                .nodes_for_capture_index(exec.full_match_file_capture_index as u32)
-                .next()
-                .expect("missing capture for full match");
-            let syn_node = exec.graph.add_syntax_node(match_node);
-            exec.graph[graph_node]
-                .attributes
-                .add(match_node_attr.clone(), syn_node)
-                .map_err(|_| {
-                    ExecutionError::DuplicateAttribute(format!(
-                        " {} on graph node ({}) in {}",
-                        match_node_attr, graph_node, self,
-                    ))
-                })?;
-        }
+            .add_debug_attrs(exec.graph, graph_node, exec.config)?;
+        add_match_node_attribute(exec, graph_node, self)?;
         self.node.add_lazy(exec, graph_node.into(), false)
     }
 }
 
+/// Adds the syntax node that the stanza matched to a new graph node, if the execution is
+/// configured with a match node attribute.  The graph node is created by `statement`, which is
+/// either a `node` statement or a `(new node)` expression.
+fn add_match_node_attribute(
+    exec: &mut ExecutionContext,
+    graph_node: graph::GraphNodeRef,
+    statement: &dyn std::fmt::Display,
+) -> Result<(), ExecutionError> {
+    if let Some(match_node_attr) = &exec.config.match_node_attr {
+        let match_node = exec
+            .mat
+            .nodes_for_capture_index(exec.full_match_file_capture_index as u32)
+            .next()
+            .expect("missing capture for full match");
+        let syn_node = exec.graph.add_syntax_node(match_node);
+        exec.graph
+            .add_node_attribute(graph_node, match_node_attr.clone(), syn_node)
+            .map_err(|_| {
+                ExecutionError::DuplicateAttribute(format!(
+                    " {} on graph node ({}) in {}",
+                    match_node_attr, graph_node, statement,
+                ))
+            })?;
+    }
+    Ok(())
+}
+
 impl ast::AddGraphNodeAttribute {
     fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
         let node = self.node.evaluate_lazy(exec)?;
         let mut attributes = Vec::new();
         let mut add_attribute = |a| attributes.push(a);
         for attribute in &self.attributes {
-            attribute.execute_lazy(exec, &mut add_attribute)?;
+            attribute.execute_lazy(exec, &mut add_attribute, false)?;
         }
         let stmt =
             LazyAddGraphNodeAttribute::new(node, attributes, exec.error_context.clone().into());
@@ -307,9 +508,19 @@ impl ast::CreateEdge {
     fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
         let source = self.source.evaluate_lazy(exec)?;
         let sink = self.sink.evaluate_lazy(exec)?;
+        if let Some(variable) = &self.variable {
+            let value = LazyGraphEdge::new(source.clone(), sink.clone());
+            variable.add_lazy(exec, value.into(), false)?;
+        }
         let mut attributes = Attributes::new();
         self.add_debug_attrs(&mut attributes, exec.config)?;
-        let stmt = LazyCreateEdge::new(source, sink, attributes, exec.error_context.clone().into());
+        let stmt = LazyCreateEdge::new(
+            source,
+            sink,
+            self.kind.clone(),
+            attributes,
+            exec.error_context.clone().into(),
+        );
         exec.lazy_graph.push(stmt.into());
         Ok(())
     }
@@ -322,7 +533,7 @@ impl ast::AddEdgeAttribute {
         let mut attributes = Vec::new();
         let mut add_attribute = |a| attributes.push(a);
         for attribute in &self.attributes {
-            attribute.execute_lazy(exec, &mut add_attribute)?;
+            attribute.execute_lazy(exec, &mut add_attribute, false)?;
         }
         let stmt =
             LazyAddEdgeAttribute::new(source, sink, attributes, exec.error_context.clone().into());
@@ -333,8 +544,26 @@ impl ast::AddEdgeAttribute {
 
 impl ast::Scan {
     fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
-        let match_string = self.value.evaluate_eager(exec)?.into_string()?;
+        let value = self.value.evaluate_eager(exec)?;
+        let element = match &self.element {
+            Some(element) => element,
+            None => return self.scan_string_lazy(exec, &value.into_string()?, None),
+        };
+        for value in value.into_list()? {
+            let match_string = ast::Scan::element_text(exec.graph, exec.source, &value)?;
+            self.scan_string_lazy(exec, &match_string, Some((element, &value)))?;
+        }
+        Ok(())
+    }
 
+    /// Runs the arms on the matches of their regular expressions in a string.  When a list is
+    /// scanned, the element that the string belongs to is bound to the element variable.
+    fn scan_string_lazy(
+        &self,
+        exec: &mut ExecutionContext,
+        match_string: &str,
+        element: Option<(&ast::UnscopedVariable, &graph::Value)>,
+    ) -> Result<(), ExecutionError> {
         let mut i = 0;
         let mut matches = Vec::new();
         while i < match_string.len() {
@@ -369,13 +598,12 @@ impl ast::Scan {
 
             let (regex_captures, block_index) = &matches[0];
             let arm = &self.arms[*block_index];
-
-            let mut current_regex_captures = Vec::new();
-            for regex_capture in regex_captures.iter() {
-                current_regex_captures
-                    .push(regex_capture.map(|m| m.as_str()).unwrap_or("").to_string());
+            if let Some(coverage) = exec.config.coverage {
+                coverage.record_arm(arm.location);
             }
 
+            let current_regex_captures = RegexCaptures::new(&arm.regex, regex_captures, i);
+
             let mut arm_locals = VariableMap::nested(exec.locals);
             let mut arm_exec = ExecutionContext {
                 source: exec.source,
@@ -384,6 +612,7 @@ impl ast::Scan {
                 locals: &mut arm_locals,
                 current_regex_captures: &current_regex_captures,
                 mat: exec.mat,
+                call_site: exec.call_site,
                 full_match_file_capture_index: exec.full_match_file_capture_index,
                 store: exec.store,
                 scoped_store: exec.scoped_store,
@@ -393,8 +622,13 @@ impl ast::Scan {
                 error_context: exec.error_context.clone(),
                 inherited_variables: exec.inherited_variables,
                 shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
                 cancellation_flag: exec.cancellation_flag,
             };
+            if let Some((variable, value)) = element {
+                variable.add_lazy(&mut arm_exec, value.clone().into(), false)?;
+            }
 
             for statement in &arm.statements {
                 arm_exec.error_context.statement = format!("{}", statement);
@@ -420,6 +654,9 @@ impl ast::Scan {
 
 impl ast::Print {
     fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        if !output::enabled(exec.config.output, self.level(), self.channel.as_ref()) {
+            return Ok(());
+        }
         let mut arguments = Vec::new();
         for value in &self.values {
             let argument = if let ast::Expression::StringConstant(expr) = value {
@@ -429,7 +666,34 @@ impl ast::Print {
             };
             arguments.push(argument);
         }
-        let stmt = LazyPrint::new(arguments, exec.error_context.clone().into());
+        let stmt = LazyPrint::new(
+            self.level(),
+            self.channel.clone(),
+            arguments,
+            exec.error_context.clone().into(),
+        );
+        exec.lazy_graph.push(stmt.into());
+        Ok(())
+    }
+}
+
+impl ast::Extension {
+    fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        let extension = exec
+            .config
+            .extensions
+            .ok_or_else(|| ExecutionError::UndefinedStatement(self.keyword.to_string()))?
+            .get(&self.keyword)?;
+        let mut arguments = Vec::new();
+        for argument in &self.arguments {
+            arguments.push(argument.evaluate_lazy(exec)?);
+        }
+        let stmt = LazyExtension::new(
+            self.keyword.clone(),
+            extension,
+            arguments,
+            exec.error_context.clone().into(),
+        );
         exec.lazy_graph.push(stmt.into());
         Ok(())
     }
@@ -440,9 +704,15 @@ impl ast::If {
         for arm in &self.arms {
             let mut result = true;
             for condition in &arm.conditions {
-                result &= condition.test_eager(exec)?;
+                if !condition.test_eager(exec)? {
+                    result = false;
+                    break;
+                }
             }
             if result {
+                if let Some(coverage) = exec.config.coverage {
+                    coverage.record_arm(arm.location);
+                }
                 let mut arm_locals = VariableMap::nested(exec.locals);
                 let mut arm_exec = ExecutionContext {
                     source: exec.source,
@@ -451,6 +721,7 @@ impl ast::If {
                     locals: &mut arm_locals,
                     current_regex_captures: exec.current_regex_captures,
                     mat: exec.mat,
+                    call_site: exec.call_site,
                     full_match_file_capture_index: exec.full_match_file_capture_index,
                     store: exec.store,
                     scoped_store: exec.scoped_store,
@@ -460,6 +731,8 @@ impl ast::If {
                     error_context: exec.error_context.clone(),
                     inherited_variables: exec.inherited_variables,
                     shorthands: exec.shorthands,
+                    usage: exec.usage,
+                    sink_filter: exec.sink_filter,
                     cancellation_flag: exec.cancellation_flag,
                 };
                 for stmt in &arm.statements {
@@ -474,13 +747,59 @@ impl ast::If {
     }
 }
 
+impl ast::Match {
+    fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        let match_string = self.value.evaluate_eager(exec)?.into_string()?;
+        let (arm, regex_captures) = match self.select_arm(&match_string) {
+            Some(selected) => selected,
+            None => return Ok(()),
+        };
+        if let Some(coverage) = exec.config.coverage {
+            coverage.record_arm(arm.location);
+        }
+        let mut arm_locals = VariableMap::nested(exec.locals);
+        let mut arm_exec = ExecutionContext {
+            source: exec.source,
+            graph: exec.graph,
+            config: exec.config,
+            locals: &mut arm_locals,
+            current_regex_captures: regex_captures
+                .as_ref()
+                .unwrap_or(exec.current_regex_captures),
+            mat: exec.mat,
+            call_site: exec.call_site,
+            full_match_file_capture_index: exec.full_match_file_capture_index,
+            store: exec.store,
+            scoped_store: exec.scoped_store,
+            lazy_graph: exec.lazy_graph,
+            function_parameters: exec.function_parameters,
+            prev_element_debug_info: exec.prev_element_debug_info,
+            error_context: exec.error_context.clone(),
+            inherited_variables: exec.inherited_variables,
+            shorthands: exec.shorthands,
+            usage: exec.usage,
+            sink_filter: exec.sink_filter,
+            cancellation_flag: exec.cancellation_flag,
+        };
+        for statement in &arm.statements {
+            arm_exec.error_context.statement = format!("{}", statement);
+            arm_exec.error_context.statement_location = statement.location();
+            statement
+                .execute_lazy(&mut arm_exec)
+                .with_context(|| format!("matching {} with arm {}", match_string, arm).into())
+                .with_context(|| arm_exec.error_context.clone().into())?;
+        }
+        Ok(())
+    }
+}
+
 impl ast::Condition {
     // Eagerly evaluate the condition to a boolean. It assumes the argument expressions
     // are local (i.e., `is_local = true` in the checker).
     fn test_eager(&self, exec: &mut ExecutionContext) -> Result<bool, ExecutionError> {
         match self {
-            Self::Some { value, .. } => Ok(!value.evaluate_eager(exec)?.is_null()),
-            Self::None { value, .. } => Ok(value.evaluate_eager(exec)?.is_null()),
+            Self::Some { value, .. } => Ok(!value.evaluate_eager(exec)?.is_null_or_empty()),
+            Self::None { value, .. } => Ok(value.evaluate_eager(exec)?.is_null_or_empty()),
             Self::Bool { value, .. } => Ok(value.evaluate_eager(exec)?.into_boolean()?),
         }
     }
@@ -499,6 +818,7 @@ impl ast::ForIn {
                 locals: &mut loop_locals,
                 current_regex_captures: exec.current_regex_captures,
                 mat: exec.mat,
+                call_site: exec.call_site,
                 full_match_file_capture_index: exec.full_match_file_capture_index,
                 store: exec.store,
                 scoped_store: exec.scoped_store,
@@ -508,6 +828,8 @@ impl ast::ForIn {
                 error_context: exec.error_context.clone(),
                 inherited_variables: exec.inherited_variables,
                 shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
                 cancellation_flag: exec.cancellation_flag,
             };
             self.variable
@@ -522,6 +844,49 @@ impl ast::ForIn {
     }
 }
 
+impl ast::ForInZip {
+    fn execute_lazy(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        let mut lists = Vec::new();
+        for value in &self.values {
+            lists.push(value.evaluate_eager(exec)?.into_list()?);
+        }
+        let mut loop_locals = VariableMap::nested(exec.locals);
+        for values in ast::ForInZip::zip(lists) {
+            loop_locals.clear();
+            let mut loop_exec = ExecutionContext {
+                source: exec.source,
+                graph: exec.graph,
+                config: exec.config,
+                locals: &mut loop_locals,
+                current_regex_captures: exec.current_regex_captures,
+                mat: exec.mat,
+                call_site: exec.call_site,
+                full_match_file_capture_index: exec.full_match_file_capture_index,
+                store: exec.store,
+                scoped_store: exec.scoped_store,
+                lazy_graph: exec.lazy_graph,
+                function_parameters: exec.function_parameters,
+                prev_element_debug_info: exec.prev_element_debug_info,
+                error_context: exec.error_context.clone(),
+                inherited_variables: exec.inherited_variables,
+                shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
+                cancellation_flag: exec.cancellation_flag,
+            };
+            for (variable, value) in self.variables.iter().zip(values) {
+                variable.add_lazy(&mut loop_exec, value.into(), false)?;
+            }
+            for stmt in &self.statements {
+                loop_exec.error_context.statement = format!("{}", stmt);
+                loop_exec.error_context.statement_location = stmt.location();
+                stmt.execute_lazy(&mut loop_exec)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl ast::Expression {
     fn evaluate_lazy(&self, exec: &mut ExecutionContext) -> Result<LazyValue, ExecutionError> {
         match self {
@@ -537,6 +902,7 @@ impl ast::Expression {
             Self::Capture(expr) => expr.evaluate_lazy(exec),
             Self::Variable(expr) => expr.evaluate_lazy(exec),
             Self::Call(expr) => expr.evaluate_lazy(exec),
+            Self::NewNode(expr) => expr.evaluate_lazy(exec),
             Self::RegexCapture(expr) => expr.evaluate_lazy(exec),
         }
     }
@@ -548,11 +914,17 @@ impl ast::Expression {
             source: exec.source,
             graph: exec.graph,
             functions: exec.config.functions,
+            environment: exec.config.environment,
+            statistics: exec.config.statistics,
+            source_map: exec.config.source_map,
+            output: exec.config.output,
             store: exec.store,
             scoped_store: exec.scoped_store,
             inherited_variables: exec.inherited_variables,
             function_parameters: exec.function_parameters,
             prev_element_debug_info: exec.prev_element_debug_info,
+            usage: exec.usage,
+            sink_filter: exec.sink_filter,
             cancellation_flag: exec.cancellation_flag,
         })
     }
@@ -560,7 +932,7 @@ impl ast::Expression {
 
 impl ast::IntegerConstant {
     fn evaluate_lazy(&self, _exec: &mut ExecutionContext) -> Result<LazyValue, ExecutionError> {
-        Ok(self.value.into())
+        Ok(Value::Integer(self.value).into())
     }
 }
 
@@ -594,6 +966,7 @@ impl ast::ListComprehension {
                 locals: &mut loop_locals,
                 current_regex_captures: exec.current_regex_captures,
                 mat: exec.mat,
+                call_site: exec.call_site,
                 full_match_file_capture_index: exec.full_match_file_capture_index,
                 store: exec.store,
                 scoped_store: exec.scoped_store,
@@ -603,10 +976,17 @@ impl ast::ListComprehension {
                 error_context: exec.error_context.clone(),
                 inherited_variables: exec.inherited_variables,
                 shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
                 cancellation_flag: exec.cancellation_flag,
             };
             self.variable
                 .add_lazy(&mut loop_exec, value.into(), false)?;
+            if let Some(condition) = &self.condition {
+                if !condition.evaluate_eager(&mut loop_exec)?.into_boolean()? {
+                    continue;
+                }
+            }
             let element = self.element.evaluate_lazy(&mut loop_exec)?;
             elements.push(element);
         }
@@ -638,6 +1018,7 @@ impl ast::SetComprehension {
                 locals: &mut loop_locals,
                 current_regex_captures: exec.current_regex_captures,
                 mat: exec.mat,
+                call_site: exec.call_site,
                 full_match_file_capture_index: exec.full_match_file_capture_index,
                 store: exec.store,
                 scoped_store: exec.scoped_store,
@@ -647,10 +1028,17 @@ impl ast::SetComprehension {
                 error_context: exec.error_context.clone(),
                 inherited_variables: exec.inherited_variables,
                 shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
                 cancellation_flag: exec.cancellation_flag,
             };
             self.variable
                 .add_lazy(&mut loop_exec, value.into(), false)?;
+            if let Some(condition) = &self.condition {
+                if !condition.evaluate_eager(&mut loop_exec)?.into_boolean()? {
+                    continue;
+                }
+            }
             let element = self.element.evaluate_lazy(&mut loop_exec)?;
             elements.push(element);
         }
@@ -676,13 +1064,23 @@ impl ast::Call {
         for parameter in &self.parameters {
             parameters.push(parameter.evaluate_lazy(exec)?);
         }
-        Ok(LazyCall::new(self.function.clone(), parameters).into())
+        let context = exec.call_site.context(exec.mat, exec.graph);
+        Ok(LazyCall::new(self.function.clone(), parameters, context).into())
+    }
+}
+
+impl ast::NewNode {
+    fn evaluate_lazy(&self, exec: &mut ExecutionContext) -> Result<LazyValue, ExecutionError> {
+        let graph_node = exec.graph.add_graph_node();
+        self.add_debug_attrs(exec.graph, graph_node, exec.config)?;
+        add_match_node_attribute(exec, graph_node, self)?;
+        Ok(graph_node.into())
     }
 }
 
 impl ast::RegexCapture {
     fn evaluate_lazy(&self, exec: &mut ExecutionContext) -> Result<LazyValue, ExecutionError> {
-        let value = exec.current_regex_captures[self.match_index].clone();
+        let value = exec.current_regex_captures.get(self)?;
         Ok(value.into())
     }
 }
@@ -724,7 +1122,11 @@ impl ast::Variable {
 impl ast::ScopedVariable {
     fn evaluate_lazy(&self, exec: &mut ExecutionContext) -> Result<LazyValue, ExecutionError> {
         let scope = self.scope.evaluate_lazy(exec)?;
-        let value = LazyScopedVariable::new(scope, self.name.clone());
+        let default = match &self.default {
+            Some(default) => Some(default.evaluate_lazy(exec)?),
+            None => None,
+        };
+        let value = LazyScopedVariable::new(scope, self.name.clone(), default);
         Ok(value.into())
     }
 
@@ -817,20 +1219,24 @@ impl ast::UnscopedVariable {
 }
 
 impl ast::Attribute {
+    /// Executes this attribute.  The value is appended to the attribute if this attribute uses
+    /// `+=`, or if `append` is set because this attribute is part of a shorthand that does.
     fn execute_lazy<F>(
         &self,
         exec: &mut ExecutionContext,
         add_attribute: &mut F,
+        append: bool,
     ) -> Result<(), ExecutionError>
     where
         F: FnMut(LazyAttribute) -> (),
     {
         exec.cancellation_flag.check("executing attribute")?;
         let value = self.value.evaluate_lazy(exec)?;
+        let append = append || self.append;
         if let Some(shorthand) = exec.shorthands.get(&self.name) {
-            shorthand.execute_lazy(exec, add_attribute, value)
+            shorthand.execute_lazy(exec, add_attribute, value, append)
         } else {
-            add_attribute(LazyAttribute::new(self.name.clone(), value));
+            add_attribute(LazyAttribute::new(self.name.clone(), value, append));
             Ok(())
         }
     }
@@ -842,6 +1248,7 @@ impl ast::AttributeShorthand {
         exec: &mut ExecutionContext,
         add_attribute: &mut F,
         value: LazyValue,
+        append: bool,
     ) -> Result<(), ExecutionError>
     where
         F: FnMut(LazyAttribute) -> (),
@@ -854,6 +1261,7 @@ impl ast::AttributeShorthand {
             locals: &mut shorthand_locals,
             current_regex_captures: exec.current_regex_captures,
             mat: exec.mat,
+            call_site: exec.call_site,
             full_match_file_capture_index: exec.full_match_file_capture_index,
             store: exec.store,
             scoped_store: exec.scoped_store,
@@ -863,11 +1271,13 @@ impl ast::AttributeShorthand {
             error_context: exec.error_context.clone(),
             inherited_variables: exec.inherited_variables,
             shorthands: exec.shorthands,
+            usage: exec.usage,
+            sink_filter: exec.sink_filter,
             cancellation_flag: exec.cancellation_flag,
         };
         self.variable.add_lazy(&mut shorthand_exec, value, false)?;
         for attr in &self.attributes {
-            attr.execute_lazy(&mut shorthand_exec, add_attribute)?;
+            attr.execute_lazy(&mut shorthand_exec, add_attribute, append)?;
         }
         Ok(())
     }
@@ -7,14 +7,21 @@
 
 //! Defines graph statements for lazy DSL evaluation
 
-use log::{debug, trace};
+use tracing::{debug, trace};
 
 use std::convert::From;
 use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
 
+use crate::ast::PrintLevel;
 use crate::execution::error::ExecutionError;
 use crate::execution::error::ResultWithExecutionError;
+use crate::execution::output;
+use crate::extensions::StatementExtension;
 use crate::graph::Attributes;
+use crate::graph::GraphNodeRef;
+use crate::graph::Value;
 use crate::Identifier;
 
 use super::store::DebugInfo;
@@ -27,6 +34,7 @@ use super::GraphElementKey;
 pub(super) struct LazyGraph {
     edge_statements: Vec<LazyStatement>,
     attr_statements: Vec<LazyStatement>,
+    extension_statements: Vec<LazyStatement>,
     print_statements: Vec<LazyStatement>,
 }
 
@@ -35,6 +43,7 @@ impl LazyGraph {
         LazyGraph {
             edge_statements: Vec::new(),
             attr_statements: Vec::new(),
+            extension_statements: Vec::new(),
             print_statements: Vec::new(),
         }
     }
@@ -44,6 +53,7 @@ impl LazyGraph {
             LazyStatement::AddGraphNodeAttribute(_) => self.attr_statements.push(stmt),
             LazyStatement::CreateEdge(_) => self.edge_statements.push(stmt),
             LazyStatement::AddEdgeAttribute(_) => self.attr_statements.push(stmt),
+            LazyStatement::Extension(_) => self.extension_statements.push(stmt),
             LazyStatement::Print(_) => self.print_statements.push(stmt),
         }
     }
@@ -55,6 +65,9 @@ impl LazyGraph {
         for stmt in &self.attr_statements {
             stmt.evaluate(exec)?;
         }
+        for stmt in &self.extension_statements {
+            stmt.evaluate(exec)?;
+        }
         for stmt in &self.print_statements {
             stmt.evaluate(exec)?;
         }
@@ -68,14 +81,20 @@ pub(super) enum LazyStatement {
     AddGraphNodeAttribute(LazyAddGraphNodeAttribute),
     CreateEdge(LazyCreateEdge),
     AddEdgeAttribute(LazyAddEdgeAttribute),
+    Extension(LazyExtension),
     Print(LazyPrint),
 }
 
 impl LazyStatement {
     pub(super) fn evaluate(&self, exec: &mut EvaluationContext) -> Result<(), ExecutionError> {
         exec.cancellation_flag.check("evaluating statement")?;
+        exec.usage
+            .check_timeout()
+            .with_context(|| self.debug_info().clone().into())?;
         debug!("eval {}", self);
         trace!("{{");
+        let start = Instant::now();
+        let node_count = exec.graph.node_count();
         let result = match self {
             Self::AddGraphNodeAttribute(stmt) => stmt
                 .evaluate(exec)
@@ -86,12 +105,37 @@ impl LazyStatement {
             Self::AddEdgeAttribute(stmt) => stmt
                 .evaluate(exec)
                 .with_context(|| stmt.debug_info.clone().into()),
+            Self::Extension(stmt) => stmt
+                .evaluate(exec)
+                .with_context(|| stmt.debug_info.clone().into()),
             Self::Print(stmt) => stmt
                 .evaluate(exec)
                 .with_context(|| stmt.debug_info.clone().into()),
         };
+        if let Some(statistics) = exec.statistics {
+            statistics.record_duration(self.debug_info().stanza_location(), start.elapsed());
+        }
         trace!("}}");
-        result
+        result?;
+        if let Some(source_map) = exec.source_map {
+            source_map.record_nodes(
+                exec.graph.iter_nodes().skip(node_count),
+                self.debug_info().statement_location(),
+            );
+        }
+        exec.usage
+            .check_nodes(exec.graph)
+            .with_context(|| self.debug_info().clone().into())
+    }
+
+    fn debug_info(&self) -> &DebugInfo {
+        match self {
+            Self::AddGraphNodeAttribute(stmt) => &stmt.debug_info,
+            Self::CreateEdge(stmt) => &stmt.debug_info,
+            Self::AddEdgeAttribute(stmt) => &stmt.debug_info,
+            Self::Extension(stmt) => &stmt.debug_info,
+            Self::Print(stmt) => &stmt.debug_info,
+        }
     }
 }
 
@@ -113,6 +157,12 @@ impl From<LazyCreateEdge> for LazyStatement {
     }
 }
 
+impl From<LazyExtension> for LazyStatement {
+    fn from(stmt: LazyExtension) -> Self {
+        Self::Extension(stmt)
+    }
+}
+
 impl From<LazyPrint> for LazyStatement {
     fn from(stmt: LazyPrint) -> Self {
         Self::Print(stmt)
@@ -125,6 +175,7 @@ impl fmt::Display for LazyStatement {
             Self::AddGraphNodeAttribute(stmt) => stmt.fmt(f),
             Self::CreateEdge(stmt) => stmt.fmt(f),
             Self::AddEdgeAttribute(stmt) => stmt.fmt(f),
+            Self::Extension(stmt) => stmt.fmt(f),
             Self::Print(stmt) => stmt.fmt(f),
         }
     }
@@ -154,18 +205,57 @@ impl LazyAddGraphNodeAttribute {
     pub(super) fn evaluate(&self, exec: &mut EvaluationContext) -> Result<(), ExecutionError> {
         let node = self
             .node
-            .evaluate_as_graph_node(exec)
+            .evaluate(exec)
             .with_context(|| "Evaluating target node".to_string().into())?;
+        let node = match node {
+            Value::GraphNode(node) => node,
+            Value::GraphEdge(edge) => {
+                return LazyAddEdgeAttribute::evaluate_attributes(
+                    exec,
+                    edge.source(),
+                    edge.sink(),
+                    &self.attributes,
+                    &self.debug_info,
+                )
+            }
+            _ => return Err(ExecutionError::ExpectedGraphNode(format!("got {}", node))),
+        };
         for attribute in &self.attributes {
-            let value = attribute.value.evaluate(exec)?;
+            let mut value = attribute.value.evaluate(exec)?;
+            if !exec.sink_filter.accept_node_attribute(
+                exec.graph,
+                node,
+                &attribute.name,
+                &mut value,
+            ) {
+                continue;
+            }
+            exec.graph.observe_node_attribute(
+                node,
+                &attribute.name,
+                &value,
+                attribute.append,
+                self.debug_info.statement_context(),
+            )?;
+            if let Some(source_map) = exec.source_map {
+                source_map.record_node_attribute(
+                    node,
+                    attribute.name.clone(),
+                    self.debug_info.statement_location(),
+                );
+            }
             let prev_debug_info = exec.prev_element_debug_info.insert(
                 GraphElementKey::NodeAttribute(node, attribute.name.clone()),
                 self.debug_info.clone(),
             );
-            if let Err(_) = exec.graph[node]
-                .attributes
-                .add(attribute.name.clone(), value)
-            {
+            let result = if attribute.append {
+                exec.graph
+                    .append_node_attribute(node, attribute.name.clone(), value)
+            } else {
+                exec.graph
+                    .add_node_attribute(node, attribute.name.clone(), value)
+            };
+            if let Err(_) = result {
                 return Err(ExecutionError::DuplicateAttribute(format!(
                     "{} on {}",
                     attribute.name, node,
@@ -198,6 +288,7 @@ impl fmt::Display for LazyAddGraphNodeAttribute {
 pub(super) struct LazyCreateEdge {
     source: LazyValue,
     sink: LazyValue,
+    kind: Option<Identifier>,
     attributes: Attributes,
     debug_info: DebugInfo,
 }
@@ -206,12 +297,14 @@ impl LazyCreateEdge {
     pub(super) fn new(
         source: LazyValue,
         sink: LazyValue,
+        kind: Option<Identifier>,
         attributes: Attributes,
         debug_info: DebugInfo,
     ) -> Self {
         Self {
             source,
             sink,
+            kind,
             attributes,
             debug_info,
         }
@@ -226,8 +319,33 @@ impl LazyCreateEdge {
             .sink
             .evaluate_as_graph_node(exec)
             .with_context(|| "Evaluating edge sink".to_string().into())?;
-        let edge = match exec.graph[source].add_edge(sink) {
-            Ok(edge) | Err(edge) => edge,
+        if exec.graph[source].get_edge(sink).is_none()
+            && !exec.sink_filter.accept_edge(exec.graph, source, sink)
+        {
+            return Ok(());
+        }
+        let added = match &self.kind {
+            Some(kind) => exec.graph.add_kinded_edge(source, sink, kind.clone()),
+            None => exec.graph[source].add_edge(sink),
+        };
+        let edge = match added {
+            Ok(edge) => {
+                if let Some(statistics) = exec.statistics {
+                    statistics.record_edge(self.debug_info.stanza_location());
+                }
+                if let Some(source_map) = exec.source_map {
+                    source_map.record_edge(source, sink, self.debug_info.statement_location());
+                }
+                exec.usage.add_edge()?;
+                edge
+            }
+            Err(edge) if edge.kind() != self.kind.as_ref() => {
+                return Err(ExecutionError::DuplicateEdge(format!(
+                    "{} -> {} of another kind in {}",
+                    source, sink, self,
+                )));
+            }
+            Err(edge) => edge,
         };
         edge.attributes = self.attributes.clone();
         Ok(())
@@ -236,11 +354,11 @@ impl LazyCreateEdge {
 
 impl fmt::Display for LazyCreateEdge {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "edge {} -> {} at {}",
-            self.source, self.sink, self.debug_info,
-        )
+        match &self.kind {
+            Some(kind) => write!(f, "edge {} -{:?}-> ", self.source, kind.as_str())?,
+            None => write!(f, "edge {} -> ", self.source)?,
+        }
+        write!(f, "{} at {}", self.sink, self.debug_info)
     }
 }
 
@@ -277,30 +395,73 @@ impl LazyAddEdgeAttribute {
             .sink
             .evaluate_as_graph_node(exec)
             .with_context(|| "Evaluating edge sink".to_string().into())?;
-        for attribute in &self.attributes {
-            let value = attribute.value.evaluate(exec)?;
+        Self::evaluate_attributes(exec, source, sink, &self.attributes, &self.debug_info)
+    }
+
+    /// Adds attributes to the edge between two graph nodes.  The statement is either an edge
+    /// attribute statement, or a node attribute statement whose node is an edge value.
+    fn evaluate_attributes(
+        exec: &mut EvaluationContext,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+        attributes: &[LazyAttribute],
+        debug_info: &DebugInfo,
+    ) -> Result<(), ExecutionError> {
+        if exec.sink_filter.is_rejected_edge(source, sink) {
+            return Ok(());
+        }
+        for attribute in attributes {
+            let mut value = attribute.value.evaluate(exec)?;
+            if exec.graph[source].get_edge(sink).is_some()
+                && !exec.sink_filter.accept_edge_attribute(
+                    exec.graph,
+                    source,
+                    sink,
+                    &attribute.name,
+                    &mut value,
+                )
+            {
+                continue;
+            }
+            exec.graph.observe_edge_attribute(
+                source,
+                sink,
+                &attribute.name,
+                &value,
+                attribute.append,
+                debug_info.statement_context(),
+            )?;
             let edge = match exec.graph[source].get_edge_mut(sink) {
                 Some(edge) => Ok(edge),
                 None => Err(ExecutionError::UndefinedEdge(format!(
                     "({} -> {}) at {}",
-                    source, sink, self.debug_info,
+                    source, sink, debug_info,
                 ))),
             }?;
+            if let Some(source_map) = exec.source_map {
+                source_map.record_edge_attribute(
+                    source,
+                    sink,
+                    attribute.name.clone(),
+                    debug_info.statement_location(),
+                );
+            }
             let prev_debug_info = exec.prev_element_debug_info.insert(
                 GraphElementKey::EdgeAttribute(source, sink, attribute.name.clone()),
-                self.debug_info.clone(),
+                debug_info.clone(),
             );
-            if let Err(_) = edge.attributes.add(attribute.name.clone(), value) {
+            let result = if attribute.append {
+                edge.attributes.append(attribute.name.clone(), value)
+            } else {
+                edge.attributes.add(attribute.name.clone(), value)
+            };
+            if let Err(_) = result {
                 return Err(ExecutionError::DuplicateAttribute(format!(
                     "{} on edge ({} -> {})",
                     attribute.name, source, sink,
                 )))
                 .with_context(|| {
-                    (
-                        prev_debug_info.unwrap().into(),
-                        self.debug_info.clone().into(),
-                    )
-                        .into()
+                    (prev_debug_info.unwrap().into(), debug_info.clone().into()).into()
                 });
             }
         }
@@ -321,6 +482,8 @@ impl fmt::Display for LazyAddEdgeAttribute {
 /// Lazy statement to print values
 #[derive(Debug)]
 pub(super) struct LazyPrint {
+    level: PrintLevel,
+    channel: Option<Identifier>,
     arguments: Vec<LazyPrintArgument>,
     debug_info: DebugInfo,
 }
@@ -332,24 +495,32 @@ pub(super) enum LazyPrintArgument {
 }
 
 impl LazyPrint {
-    pub(super) fn new(arguments: Vec<LazyPrintArgument>, debug_info: DebugInfo) -> Self {
+    pub(super) fn new(
+        level: PrintLevel,
+        channel: Option<Identifier>,
+        arguments: Vec<LazyPrintArgument>,
+        debug_info: DebugInfo,
+    ) -> Self {
         Self {
+            level,
+            channel,
             arguments,
             debug_info,
         }
     }
 
     pub(super) fn evaluate(&self, exec: &mut EvaluationContext) -> Result<(), ExecutionError> {
+        let mut message = String::new();
         for argument in &self.arguments {
             match argument {
-                LazyPrintArgument::Text(string) => eprint!("{}", string),
+                LazyPrintArgument::Text(string) => message += string,
                 LazyPrintArgument::Value(value) => {
                     let value = value.evaluate(exec)?;
-                    eprint!("{:?}", value);
+                    message += &output::format_value(exec.graph, &value);
                 }
             }
         }
-        eprintln!("");
+        output::print(exec.output, self.level, self.channel.as_ref(), &message);
         Ok(())
     }
 }
@@ -357,6 +528,12 @@ impl LazyPrint {
 impl fmt::Display for LazyPrint {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "print")?;
+        if self.level != PrintLevel::Info {
+            write!(f, " {}", self.level)?;
+        }
+        if let Some(channel) = &self.channel {
+            write!(f, " {}:", channel)?;
+        }
         let mut first = true;
         for argument in &self.arguments {
             if first {
@@ -373,21 +550,89 @@ impl fmt::Display for LazyPrint {
     }
 }
 
+/// Lazy extension statement
+pub(super) struct LazyExtension {
+    keyword: Identifier,
+    extension: Arc<dyn StatementExtension + Send + Sync>,
+    arguments: Vec<LazyValue>,
+    debug_info: DebugInfo,
+}
+
+impl LazyExtension {
+    pub(super) fn new(
+        keyword: Identifier,
+        extension: Arc<dyn StatementExtension + Send + Sync>,
+        arguments: Vec<LazyValue>,
+        debug_info: DebugInfo,
+    ) -> Self {
+        Self {
+            keyword,
+            extension,
+            arguments,
+            debug_info,
+        }
+    }
+
+    pub(super) fn evaluate(&self, exec: &mut EvaluationContext) -> Result<(), ExecutionError> {
+        let mut arguments = Vec::with_capacity(self.arguments.len());
+        for argument in &self.arguments {
+            arguments.push(argument.evaluate(exec)?);
+        }
+        self.extension
+            .execute(exec.graph, exec.source, &mut arguments.into_iter())
+    }
+}
+
+impl fmt::Debug for LazyExtension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LazyExtension")
+            .field("keyword", &self.keyword)
+            .field("arguments", &self.arguments)
+            .field("debug_info", &self.debug_info)
+            .finish()
+    }
+}
+
+impl fmt::Display for LazyExtension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.keyword)?;
+        let mut first = true;
+        for argument in &self.arguments {
+            if first {
+                first = false;
+                write!(f, " {}", argument)?;
+            } else {
+                write!(f, ", {}", argument)?;
+            }
+        }
+        write!(f, " at {}", self.debug_info)
+    }
+}
+
 /// Lazy attribute
 #[derive(Debug)]
 pub(super) struct LazyAttribute {
     name: Identifier,
     value: LazyValue,
+    append: bool,
 }
 
 impl LazyAttribute {
-    pub(super) fn new(name: Identifier, value: LazyValue) -> Self {
-        Self { name, value }
+    pub(super) fn new(name: Identifier, value: LazyValue, append: bool) -> Self {
+        Self {
+            name,
+            value,
+            append,
+        }
     }
 }
 
 impl fmt::Display for LazyAttribute {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} = {}", self.name, self.value,)
+        if self.append {
+            write!(f, "{} += {}", self.name, self.value,)
+        } else {
+            write!(f, "{} = {}", self.name, self.value,)
+        }
     }
 }
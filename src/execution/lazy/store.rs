@@ -7,7 +7,7 @@
 
 //! Defines store and thunks for lazy DSL evaluation
 
-use log::trace;
+use tracing::trace;
 
 use std::cell::Cell;
 use std::cell::RefCell;
@@ -23,6 +23,7 @@ use crate::graph;
 use crate::graph::SyntaxNodeID;
 use crate::graph::SyntaxNodeRef;
 use crate::Identifier;
+use crate::Location;
 
 use super::values::*;
 use super::EvaluationContext;
@@ -135,20 +136,16 @@ impl LazyScopedVariables {
         }
     }
 
+    /// Returns the value of a scoped variable, or `None` if it is not defined for the scope.
     pub(super) fn evaluate(
         &self,
         scope: &SyntaxNodeRef,
         name: &Identifier,
         exec: &mut EvaluationContext,
-    ) -> Result<LazyValue, ExecutionError> {
+    ) -> Result<Option<LazyValue>, ExecutionError> {
         let cell = match self.variables.get(name) {
             Some(v) => v,
-            None => {
-                return Err(ExecutionError::UndefinedScopedVariable(format!(
-                    "{}.{}",
-                    scope, name,
-                )));
-            }
+            None => return Ok(None),
         };
         let values = cell.replace(ScopedValues::Forcing);
         let map = self.force(name, values, exec)?;
@@ -173,7 +170,7 @@ impl LazyScopedVariables {
         }
 
         cell.replace(ScopedValues::Forced(map));
-        result.ok_or_else(|| ExecutionError::UndefinedScopedVariable(format!("{}.{}", scope, name)))
+        Ok(result)
     }
 
     pub(super) fn evaluate_all(&self, exec: &mut EvaluationContext) -> Result<(), ExecutionError> {
@@ -296,6 +293,24 @@ impl Thunk {
 #[derive(Debug, Clone)]
 pub(super) struct DebugInfo(StatementContext);
 
+impl DebugInfo {
+    /// The location of the stanza that the value or statement originates from
+    pub(super) fn stanza_location(&self) -> Location {
+        self.0.stanza_location
+    }
+
+    /// The location of the statement that the value or statement originates from
+    pub(super) fn statement_location(&self) -> Location {
+        self.0.statement_location
+    }
+
+    /// The statement that the value or statement originates from, with its stanza and the syntax
+    /// node that the stanza matched
+    pub(super) fn statement_context(&self) -> &StatementContext {
+        &self.0
+    }
+}
+
 impl From<StatementContext> for DebugInfo {
     fn from(value: StatementContext) -> Self {
         Self(value)
@@ -7,13 +7,16 @@
 
 //! Defines values for lazy DSL evaluation
 
-use log::trace;
+use tracing::trace;
 
 use std::convert::From;
 use std::fmt;
+use std::sync::Arc;
 
 use crate::execution::error::ExecutionError;
 use crate::execution::error::ResultWithExecutionError;
+use crate::functions::CallContext;
+use crate::graph::GraphEdgeRef;
 use crate::graph::GraphNodeRef;
 use crate::graph::SyntaxNodeRef;
 use crate::graph::Value;
@@ -31,6 +34,7 @@ pub(super) enum LazyValue {
     Variable(LazyVariable),
     ScopedVariable(LazyScopedVariable),
     Call(LazyCall),
+    GraphEdge(LazyGraphEdge),
 }
 
 impl From<Value> for LazyValue {
@@ -117,17 +121,30 @@ impl From<LazyCall> for LazyValue {
     }
 }
 
+impl From<LazyGraphEdge> for LazyValue {
+    fn from(value: LazyGraphEdge) -> Self {
+        LazyValue::GraphEdge(value)
+    }
+}
+
 impl LazyValue {
     pub(super) fn evaluate(&self, exec: &mut EvaluationContext) -> Result<Value, ExecutionError> {
         exec.cancellation_flag.check("evaluating value")?;
         trace!("eval {} {{", self);
         let ret = match self {
             Self::Value(value) => Ok(value.clone()),
-            Self::List(expr) => expr.evaluate(exec),
-            Self::Set(expr) => expr.evaluate(exec),
+            Self::List(expr) => expr
+                .evaluate(exec)
+                .and_then(|value| exec.usage.check_value(value)),
+            Self::Set(expr) => expr
+                .evaluate(exec)
+                .and_then(|value| exec.usage.check_value(value)),
             Self::Variable(expr) => expr.evaluate(exec),
             Self::ScopedVariable(expr) => expr.evaluate(exec),
-            Self::Call(expr) => expr.evaluate(exec),
+            Self::Call(expr) => expr
+                .evaluate(exec)
+                .and_then(|value| exec.usage.check_value(value)),
+            Self::GraphEdge(expr) => expr.evaluate(exec),
         }?;
         trace!("}} = {}", ret);
         Ok(ret)
@@ -165,6 +182,7 @@ impl fmt::Display for LazyValue {
             Self::Variable(expr) => expr.fmt(f),
             Self::ScopedVariable(expr) => expr.fmt(f),
             Self::Call(expr) => expr.fmt(f),
+            Self::GraphEdge(expr) => expr.fmt(f),
         }
     }
 }
@@ -174,13 +192,15 @@ impl fmt::Display for LazyValue {
 pub(super) struct LazyScopedVariable {
     scope: Box<LazyValue>,
     name: Identifier,
+    default: Option<Box<LazyValue>>,
 }
 
 impl LazyScopedVariable {
-    pub(super) fn new(scope: LazyValue, name: Identifier) -> Self {
+    pub(super) fn new(scope: LazyValue, name: Identifier, default: Option<LazyValue>) -> Self {
         Self {
             scope: scope.into(),
             name,
+            default: default.map(Box::new),
         }
     }
 
@@ -191,7 +211,16 @@ impl LazyScopedVariable {
             .evaluate_as_syntax_node(exec)
             .with_context(|| format!("Evaluating scope of variable _.{}", self.name).into())?;
         let scoped_store = &exec.scoped_store;
-        scoped_store.evaluate(&scope, &self.name, exec)
+        match scoped_store.evaluate(&scope, &self.name, exec)? {
+            Some(value) => Ok(value),
+            None => match &self.default {
+                Some(default) => Ok(default.as_ref().clone()),
+                None => Err(ExecutionError::UndefinedScopedVariable(format!(
+                    "{}.{}",
+                    scope, self.name,
+                ))),
+            },
+        }
     }
 
     pub(super) fn evaluate(&self, exec: &mut EvaluationContext) -> Result<Value, ExecutionError> {
@@ -202,7 +231,45 @@ impl LazyScopedVariable {
 
 impl fmt::Display for LazyScopedVariable {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "(scoped {} '{})", self.scope, self.name,)
+        write!(f, "(scoped {} '{}", self.scope, self.name)?;
+        if let Some(default) = &self.default {
+            write!(f, " {}", default)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Lazy reference to the edge between two graph nodes
+#[derive(Clone, Debug)]
+pub(super) struct LazyGraphEdge {
+    source: Box<LazyValue>,
+    sink: Box<LazyValue>,
+}
+
+impl LazyGraphEdge {
+    pub(super) fn new(source: LazyValue, sink: LazyValue) -> Self {
+        Self {
+            source: source.into(),
+            sink: sink.into(),
+        }
+    }
+
+    pub(super) fn evaluate(&self, exec: &mut EvaluationContext) -> Result<Value, ExecutionError> {
+        let source = self
+            .source
+            .evaluate_as_graph_node(exec)
+            .with_context(|| "Evaluating edge source".to_string().into())?;
+        let sink = self
+            .sink
+            .evaluate_as_graph_node(exec)
+            .with_context(|| "Evaluating edge sink".to_string().into())?;
+        Ok(GraphEdgeRef::new(source, sink).into())
+    }
+}
+
+impl fmt::Display for LazyGraphEdge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(edge {} -> {})", self.source, self.sink)
     }
 }
 
@@ -285,13 +352,19 @@ impl fmt::Display for LazySet {
 pub(super) struct LazyCall {
     function: Identifier,
     arguments: Vec<LazyValue>,
+    context: Arc<CallContext>,
 }
 
 impl LazyCall {
-    pub(super) fn new(function: Identifier, arguments: Vec<LazyValue>) -> Self {
+    pub(super) fn new(
+        function: Identifier,
+        arguments: Vec<LazyValue>,
+        context: Arc<CallContext>,
+    ) -> Self {
         Self {
             function,
             arguments,
+            context,
         }
     }
 
@@ -301,7 +374,9 @@ impl LazyCall {
             exec.function_parameters.push(argument);
         }
 
-        exec.functions.call(
+        exec.functions.call_with_environment(
+            exec.environment,
+            Some(&self.context),
             &self.function,
             exec.graph,
             exec.source,
@@ -0,0 +1,166 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines limits on the resources that an execution can use.
+
+use std::cell::Cell;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::execution::error::ExecutionError;
+use crate::graph::Graph;
+use crate::graph::Value;
+
+/// Limits on the resources that executing a graph DSL file can use.  An execution that exceeds a
+/// limit fails with [`ExecutionError::LimitExceeded`][], in the context of the statement that was
+/// executing.  Use [`ExecutionConfig::limits`][crate::ExecutionConfig::limits] to set them.
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionLimits {
+    /// The maximum number of graph nodes in the graph, including any that it contained before
+    /// execution started
+    pub max_nodes: Option<usize>,
+    /// The maximum number of edges in the graph, including any that it contained before
+    /// execution started
+    pub max_edges: Option<usize>,
+    /// The maximum time that the execution can take
+    pub timeout: Option<Duration>,
+    /// The maximum number of statements that can be executed, counting every statement in the
+    /// body of a loop or conditional each time that it runs
+    pub fuel: Option<u64>,
+    /// The maximum size of each list, set, and function result that is created, counting every
+    /// element of the value and of the lists and sets nested in it, and every byte of its strings
+    pub max_value_size: Option<usize>,
+    /// The maximum depth to which lists and sets can be nested in each list, set, and function
+    /// result that is created.  A list of strings has a depth of 1.
+    pub max_value_depth: Option<usize>,
+}
+
+/// Tracks the resources used by an execution, and reports when they exceed the limits.
+pub(crate) struct ResourceUsage<'a> {
+    limits: &'a ExecutionLimits,
+    edges: Cell<usize>,
+    fuel: Cell<u64>,
+    start: Instant,
+}
+
+impl<'a> ResourceUsage<'a> {
+    pub(crate) fn new(limits: &'a ExecutionLimits, graph: &Graph) -> Self {
+        let edges = match limits.max_edges {
+            Some(_) => graph
+                .iter_nodes()
+                .map(|node| graph[node].edge_count())
+                .sum(),
+            None => 0,
+        };
+        Self {
+            limits,
+            edges: Cell::new(edges),
+            fuel: Cell::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    /// Records the execution of a statement, and checks the fuel and time used so far.
+    pub(crate) fn execute_statement(&self) -> Result<(), ExecutionError> {
+        let fuel = self.fuel.get() + 1;
+        self.fuel.set(fuel);
+        if let Some(max_fuel) = self.limits.fuel {
+            if fuel > max_fuel {
+                return Err(ExecutionError::LimitExceeded(format!(
+                    "fuel of {} statements",
+                    max_fuel
+                )));
+            }
+        }
+        self.check_timeout()
+    }
+
+    /// Checks the time used so far.
+    pub(crate) fn check_timeout(&self) -> Result<(), ExecutionError> {
+        if let Some(timeout) = self.limits.timeout {
+            if self.start.elapsed() > timeout {
+                return Err(ExecutionError::LimitExceeded(format!(
+                    "timeout of {:?}",
+                    timeout
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the number of graph nodes in the graph.
+    pub(crate) fn check_nodes(&self, graph: &Graph) -> Result<(), ExecutionError> {
+        if let Some(max_nodes) = self.limits.max_nodes {
+            if graph.node_count() > max_nodes {
+                return Err(ExecutionError::LimitExceeded(format!(
+                    "maximum of {} graph nodes",
+                    max_nodes
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the size and nesting depth of a value that was created, and returns it if they are
+    /// within the limits.
+    pub(crate) fn check_value(&self, value: Value) -> Result<Value, ExecutionError> {
+        if self.limits.max_value_size.is_some() || self.limits.max_value_depth.is_some() {
+            self.measure_value(&value)?;
+        }
+        Ok(value)
+    }
+
+    fn measure_value(&self, value: &Value) -> Result<(), ExecutionError> {
+        let max_size = self.limits.max_value_size.unwrap_or(usize::MAX);
+        let max_depth = self.limits.max_value_depth.unwrap_or(usize::MAX);
+        let mut size = 0usize;
+        // Values are measured without recursion, since they can be nested arbitrarily deeply when
+        // only their size is limited.  Each value is paired with the depth of its elements.
+        let mut pending = vec![(value, 1)];
+        while let Some((value, depth)) = pending.pop() {
+            size = size.saturating_add(match value {
+                Value::String(value) => value.len().max(1),
+                Value::SourceSlice(value) => value.as_str().len().max(1),
+                _ => 1,
+            });
+            if size > max_size {
+                return Err(ExecutionError::LimitExceeded(format!(
+                    "maximum value size of {}",
+                    max_size
+                )));
+            }
+            let elements = match value {
+                Value::List(values) => values.iter().collect::<Vec<_>>(),
+                Value::Set(values) => values.iter().collect::<Vec<_>>(),
+                _ => continue,
+            };
+            if depth > max_depth {
+                return Err(ExecutionError::LimitExceeded(format!(
+                    "maximum value depth of {}",
+                    max_depth
+                )));
+            }
+            pending.extend(elements.into_iter().map(|element| (element, depth + 1)));
+        }
+        Ok(())
+    }
+
+    /// Records the creation of a new edge.
+    pub(crate) fn add_edge(&self) -> Result<(), ExecutionError> {
+        let edges = self.edges.get() + 1;
+        self.edges.set(edges);
+        if let Some(max_edges) = self.limits.max_edges {
+            if edges > max_edges {
+                return Err(ExecutionError::LimitExceeded(format!(
+                    "maximum of {} edges",
+                    max_edges
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines the outcome of an execution, which collects what the execution reported along with
+//! the graph that it created.
+
+use std::cell::RefCell;
+
+use crate::ast::PrintLevel;
+use crate::execution::output::PrintOutput;
+use crate::execution::source_map::SourceMap;
+use crate::execution::statistics::ExecutionStatistics;
+use crate::graph::Graph;
+
+/// The outcome of [`File::execute_with_outcome`][crate::ast::File::execute_with_outcome]: the
+/// graph that the execution created, together with its warnings, its statistics, and the
+/// provenance of the graph, so that hosts do not need to install a side channel for each of them.
+pub struct ExecutionOutcome<'tree> {
+    /// The graph that the execution created
+    pub graph: Graph<'tree>,
+    /// The messages of the `print` statements at the `warn` and `error` levels, in the order that
+    /// they were printed
+    pub warnings: Vec<ExecutionWarning>,
+    /// Statistics about each stanza of the file
+    pub statistics: ExecutionStatistics,
+    /// The statements that created the nodes, edges, and attributes of the graph
+    pub provenance: SourceMap,
+}
+
+/// The message of a `print` statement at the `warn` or `error` level
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExecutionWarning {
+    pub level: PrintLevel,
+    /// The channel of the statement, if it had one
+    pub channel: Option<String>,
+    pub message: String,
+}
+
+/// A [`PrintOutput`][] that collects warnings.  Messages are passed on to another output, if
+/// there is one.  Otherwise, the messages that are not warnings are written to stderr, as they
+/// would be without an output, and warnings are only collected.
+pub(crate) struct WarningCollector<'a> {
+    output: Option<&'a dyn PrintOutput>,
+    warnings: RefCell<Vec<ExecutionWarning>>,
+}
+
+impl<'a> WarningCollector<'a> {
+    pub(crate) fn new(output: Option<&'a dyn PrintOutput>) -> WarningCollector<'a> {
+        WarningCollector {
+            output,
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn into_warnings(self) -> Vec<ExecutionWarning> {
+        self.warnings.into_inner()
+    }
+}
+
+impl PrintOutput for WarningCollector<'_> {
+    fn enabled(&self, level: PrintLevel, channel: Option<&str>) -> bool {
+        level <= PrintLevel::Warn
+            || match self.output {
+                Some(output) => output.enabled(level, channel),
+                None => true,
+            }
+    }
+
+    fn print(&self, level: PrintLevel, channel: Option<&str>, message: &str) {
+        let is_warning = level <= PrintLevel::Warn;
+        if is_warning {
+            self.warnings.borrow_mut().push(ExecutionWarning {
+                level,
+                channel: channel.map(str::to_string),
+                message: message.to_string(),
+            });
+        }
+        match self.output {
+            Some(output) if output.enabled(level, channel) => output.print(level, channel, message),
+            Some(_) => {}
+            None if !is_warning => eprintln!("{}", message),
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines where the messages of `print` statements go.
+
+use std::collections::HashSet;
+
+use crate::ast::PrintLevel;
+use crate::graph::Graph;
+use crate::graph::Value;
+use crate::Identifier;
+
+/// Receives the messages of `print` statements.  Use
+/// [`ExecutionConfig::output`][crate::ExecutionConfig::output] to install an output.  Without
+/// one, every message is written to stderr.
+///
+/// The values of a `print` statement are only evaluated if the output wants its message, so
+/// disabled prints cost almost nothing.
+pub trait PrintOutput {
+    /// Returns whether the output wants the messages at `level` on `channel`.
+    fn enabled(&self, level: PrintLevel, channel: Option<&str>) -> bool {
+        let _ = (level, channel);
+        true
+    }
+
+    /// Called with the message of a `print` statement, which does not end with a newline.
+    fn print(&self, level: PrintLevel, channel: Option<&str>, message: &str);
+}
+
+/// A [`PrintOutput`][] that writes messages to stderr, like an execution without an output does,
+/// but only those that are at most as detailed as a maximum level, and optionally only those on
+/// some channels.
+#[derive(Clone, Debug)]
+pub struct StderrOutput {
+    max_level: PrintLevel,
+    channels: Option<HashSet<String>>,
+}
+
+impl StderrOutput {
+    /// Writes the messages at `max_level` and at every more important level, on all channels.
+    pub fn new(max_level: PrintLevel) -> StderrOutput {
+        StderrOutput {
+            max_level,
+            channels: None,
+        }
+    }
+
+    /// Writes the messages on `channel`.  Once a channel has been added, messages on the channels
+    /// that have not been added are not written.  Messages without a channel are always written.
+    pub fn channel(self, channel: &str) -> StderrOutput {
+        let mut channels = self.channels.unwrap_or_default();
+        channels.insert(channel.to_string());
+        StderrOutput {
+            max_level: self.max_level,
+            channels: Some(channels),
+        }
+    }
+}
+
+impl PrintOutput for StderrOutput {
+    fn enabled(&self, level: PrintLevel, channel: Option<&str>) -> bool {
+        level <= self.max_level
+            && match (&self.channels, channel) {
+                (Some(channels), Some(channel)) => channels.contains(channel),
+                _ => true,
+            }
+    }
+
+    fn print(&self, _level: PrintLevel, _channel: Option<&str>, message: &str) {
+        eprintln!("{}", message);
+    }
+}
+
+/// Returns whether `output` wants the messages at `level` on `channel`.
+pub(crate) fn enabled(
+    output: Option<&dyn PrintOutput>,
+    level: PrintLevel,
+    channel: Option<&Identifier>,
+) -> bool {
+    match output {
+        Some(output) => output.enabled(level, channel.map(Identifier::as_str)),
+        None => true,
+    }
+}
+
+/// Formats a value of a `print` statement.  Graph nodes are shown with the attributes and edges
+/// that they have in the graph so far, so that rules can be debugged while the graph is built.
+pub(crate) fn format_value(graph: &Graph, value: &Value) -> String {
+    match value {
+        Value::GraphNode(node) => graph.display_node(*node).to_string().trim_end().to_string(),
+        _ => format!("{:?}", value),
+    }
+}
+
+/// Sends the message of a `print` statement to `output`, or to stderr if there is none.
+pub(crate) fn print(
+    output: Option<&dyn PrintOutput>,
+    level: PrintLevel,
+    channel: Option<&Identifier>,
+    message: &str,
+) {
+    match output {
+        Some(output) => output.print(level, channel.map(Identifier::as_str), message),
+        None => eprintln!("{}", message),
+    }
+}
@@ -0,0 +1,119 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines an index of the regions of a syntax tree in which the stanzas of a file can match.
+//!
+//! Rule sets often have many stanzas for language features that most source files do not use.
+//! Before the queries of a file are run, the syntax tree is walked once to find the byte range
+//! that the nodes of each kind span.  The pattern of a stanza that can only match nodes of a few
+//! kinds can only match in the ranges of those kinds, so its query is restricted to them, and is
+//! not run at all if the tree has no nodes of those kinds.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Range;
+
+use tree_sitter::Tree;
+
+use crate::ast::File;
+use crate::explain::pattern_root_kinds;
+
+/// The byte range in which the pattern of each stanza of a file can match a syntax tree, or
+/// nothing if it cannot match anywhere.
+pub(super) struct MatchRegions {
+    regions: Vec<Option<Range<usize>>>,
+}
+
+impl MatchRegions {
+    pub(super) fn new(file: &File, tree: &Tree) -> MatchRegions {
+        let anywhere = 0..usize::MAX;
+        if file.query.is_none() {
+            return MatchRegions {
+                regions: vec![Some(anywhere); file.stanzas.len()],
+            };
+        }
+        // Supertypes and other hidden kinds never appear in the tree, but patterns that mention
+        // them match the nodes of other kinds.
+        let language = &file.language;
+        let hidden = (0..language.node_kind_count() as u16)
+            .filter(|id| !language.node_kind_is_visible(*id))
+            .filter_map(|id| {
+                let kind = language.node_kind_for_id(id)?;
+                Some((kind, language.node_kind_is_named(id)))
+            })
+            .collect::<HashSet<_>>();
+        let kind_ranges = kind_ranges(tree);
+        let regions = (0..file.stanzas.len())
+            .map(|index| {
+                let kinds = match pattern_root_kinds(file.stanza_pattern(index)) {
+                    Some(kinds) => kinds,
+                    None => return Some(anywhere.clone()),
+                };
+                let mut region: Option<Range<usize>> = None;
+                for (kind, named) in &kinds {
+                    if hidden.contains(&(kind.as_str(), *named)) {
+                        return Some(anywhere.clone());
+                    }
+                    if let Some(range) = kind_ranges.get(&(kind.as_str(), *named)) {
+                        region = Some(union(region, range.clone()));
+                    }
+                }
+                // Nodes that are empty at the ends of the region must intersect it as well.
+                region.map(|range| range.start.saturating_sub(1)..range.end + 1)
+            })
+            .collect();
+        MatchRegions { regions }
+    }
+
+    /// Returns the byte range in which the pattern of a stanza can match, if it can match at all.
+    pub(super) fn region(&self, stanza: usize) -> Option<Range<usize>> {
+        self.regions[stanza].clone()
+    }
+
+    /// Returns the byte range in which the pattern of any of the enabled stanzas can match, if
+    /// any of them can match at all.
+    pub(super) fn enabled_region(&self, enabled: &[bool]) -> Option<Range<usize>> {
+        self.regions
+            .iter()
+            .zip(enabled)
+            .filter(|(_, enabled)| **enabled)
+            .filter_map(|(region, _)| region.clone())
+            .reduce(|left, right| union(Some(left), right))
+    }
+}
+
+fn union(left: Option<Range<usize>>, right: Range<usize>) -> Range<usize> {
+    match left {
+        Some(left) => left.start.min(right.start)..left.end.max(right.end),
+        None => right,
+    }
+}
+
+/// Returns the byte range that the nodes of each kind span, keyed by the kind and whether it is
+/// named.
+fn kind_ranges(tree: &Tree) -> HashMap<(&'static str, bool), Range<usize>> {
+    let mut ranges = HashMap::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        let range = node.byte_range();
+        ranges
+            .entry((node.kind(), node.is_named()))
+            .and_modify(|region: &mut Range<usize>| {
+                *region = union(Some(region.clone()), range.clone())
+            })
+            .or_insert(range);
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return ranges;
+            }
+        }
+    }
+}
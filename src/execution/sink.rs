@@ -0,0 +1,181 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines callbacks that downstream consumers receive while a graph is being constructed.
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use crate::graph::Graph;
+use crate::graph::GraphNodeRef;
+use crate::graph::Value;
+use crate::Identifier;
+
+/// Receives callbacks as an execution constructs the graph, and can reject or transform graph
+/// elements before they are added to it.  Use
+/// [`ExecutionConfig::sink`][crate::ExecutionConfig::sink] to install a sink.
+///
+/// A sink is told about every graph node that is created, including those created by functions,
+/// before any edge or attribute that refers to it.  Rejecting a graph node does not remove it
+/// from the graph, since other values might already refer to it, but none of its edges or
+/// attributes are added, and the sink is not told about them.  Similarly, the attributes of a
+/// rejected edge are silently dropped.  The debug attributes that are configured with
+/// [`ExecutionConfig::debug_attributes`][crate::ExecutionConfig::debug_attributes] are not
+/// reported.
+///
+/// Lazy execution creates all graph nodes before it adds any edges or attributes, so the order
+/// of the callbacks differs between strict and lazy execution.  The callbacks take `&self`, so
+/// sinks that collect results need to use interior mutability.
+pub trait GraphSink {
+    /// Called when a graph node has been created.
+    fn node(&self, graph: &Graph, node: GraphNodeRef) -> SinkAction {
+        let _ = (graph, node);
+        SinkAction::Accept
+    }
+
+    /// Called before an edge is added to the graph.  Edges that already exist are not reported
+    /// again.
+    fn edge(&self, graph: &Graph, source: GraphNodeRef, sink: GraphNodeRef) -> SinkAction {
+        let _ = (graph, source, sink);
+        SinkAction::Accept
+    }
+
+    /// Called before an attribute is added to a graph node.  The sink can change the value that
+    /// is added.
+    fn node_attribute(
+        &self,
+        graph: &Graph,
+        node: GraphNodeRef,
+        name: &Identifier,
+        value: &mut Value,
+    ) -> SinkAction {
+        let _ = (graph, node, name, value);
+        SinkAction::Accept
+    }
+
+    /// Called before an attribute is added to an edge.  The sink can change the value that is
+    /// added.
+    fn edge_attribute(
+        &self,
+        graph: &Graph,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+        name: &Identifier,
+        value: &mut Value,
+    ) -> SinkAction {
+        let _ = (graph, source, sink, name, value);
+        SinkAction::Accept
+    }
+}
+
+/// Whether a graph element that was reported to a [`GraphSink`][] is added to the graph.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SinkAction {
+    Accept,
+    Reject,
+}
+
+/// Tracks which graph elements have been reported to the sink of an execution, and which of them
+/// it rejected.
+pub(crate) struct SinkFilter<'a> {
+    sink: Option<&'a dyn GraphSink>,
+    reported_nodes: Cell<usize>,
+    rejected_nodes: RefCell<HashSet<GraphNodeRef>>,
+    rejected_edges: RefCell<HashSet<(GraphNodeRef, GraphNodeRef)>>,
+}
+
+impl<'a> SinkFilter<'a> {
+    /// Creates a filter for an execution into `graph`.  Graph nodes that are already in the graph
+    /// are not reported.
+    pub(crate) fn new(sink: Option<&'a dyn GraphSink>, graph: &Graph) -> Self {
+        Self {
+            sink,
+            reported_nodes: Cell::new(graph.node_count()),
+            rejected_nodes: RefCell::new(HashSet::new()),
+            rejected_edges: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Reports the graph nodes that have been created since the last call.
+    pub(crate) fn report_nodes(&self, graph: &Graph) {
+        let sink = match self.sink {
+            Some(sink) => sink,
+            None => return,
+        };
+        for node in graph.iter_nodes().skip(self.reported_nodes.get()) {
+            if sink.node(graph, node) == SinkAction::Reject {
+                self.rejected_nodes.borrow_mut().insert(node);
+            }
+        }
+        self.reported_nodes.set(graph.node_count());
+    }
+
+    /// Returns whether a new edge should be added to the graph.
+    pub(crate) fn accept_edge(
+        &self,
+        graph: &Graph,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+    ) -> bool {
+        let graph_sink = match self.sink {
+            Some(graph_sink) => graph_sink,
+            None => return true,
+        };
+        self.report_nodes(graph);
+        let accepted = {
+            let rejected_nodes = self.rejected_nodes.borrow();
+            !rejected_nodes.contains(&source)
+                && !rejected_nodes.contains(&sink)
+                && graph_sink.edge(graph, source, sink) == SinkAction::Accept
+        };
+        if !accepted {
+            self.rejected_edges.borrow_mut().insert((source, sink));
+        }
+        accepted
+    }
+
+    /// Returns whether an edge was rejected, so that its attributes should be dropped.
+    pub(crate) fn is_rejected_edge(&self, source: GraphNodeRef, sink: GraphNodeRef) -> bool {
+        self.sink.is_some() && self.rejected_edges.borrow().contains(&(source, sink))
+    }
+
+    /// Returns whether an attribute should be added to a graph node, after letting the sink
+    /// transform its value.
+    pub(crate) fn accept_node_attribute(
+        &self,
+        graph: &Graph,
+        node: GraphNodeRef,
+        name: &Identifier,
+        value: &mut Value,
+    ) -> bool {
+        let sink = match self.sink {
+            Some(sink) => sink,
+            None => return true,
+        };
+        self.report_nodes(graph);
+        !self.rejected_nodes.borrow().contains(&node)
+            && sink.node_attribute(graph, node, name, value) == SinkAction::Accept
+    }
+
+    /// Returns whether an attribute should be added to an edge that was not rejected, after
+    /// letting the sink transform its value.
+    pub(crate) fn accept_edge_attribute(
+        &self,
+        graph: &Graph,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+        name: &Identifier,
+        value: &mut Value,
+    ) -> bool {
+        let graph_sink = match self.sink {
+            Some(graph_sink) => graph_sink,
+            None => return true,
+        };
+        graph_sink.edge_attribute(graph, source, sink, name, value) == SinkAction::Accept
+    }
+}
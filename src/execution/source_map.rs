@@ -0,0 +1,115 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines the map from graph elements back to the statements that created them.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::graph::GraphNodeRef;
+use crate::Identifier;
+use crate::Location;
+
+/// The locations of the statements in a graph DSL file that created the nodes, edges, and
+/// attributes of a graph, collected while executing the file.  Use
+/// [`ExecutionConfig::source_map`][crate::ExecutionConfig::source_map] to collect it.
+///
+/// A graph node maps to the `node` statement that created it, or to the statement that called the
+/// function that created it.  Edges map to their `edge` statements, and attributes to the `attr`
+/// statements that set them.  Debug attributes are not included.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    nodes: RefCell<HashMap<GraphNodeRef, Location>>,
+    edges: RefCell<HashMap<(GraphNodeRef, GraphNodeRef), Location>>,
+    node_attributes: RefCell<HashMap<(GraphNodeRef, Identifier), Location>>,
+    edge_attributes: RefCell<HashMap<(GraphNodeRef, GraphNodeRef, Identifier), Location>>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Returns the location of the statement that created a graph node.
+    pub fn node(&self, node: GraphNodeRef) -> Option<Location> {
+        self.nodes.borrow().get(&node).copied()
+    }
+
+    /// Returns the location of the statement that created an edge.
+    pub fn edge(&self, source: GraphNodeRef, sink: GraphNodeRef) -> Option<Location> {
+        self.edges.borrow().get(&(source, sink)).copied()
+    }
+
+    /// Returns the location of the statement that set an attribute of a graph node.
+    pub fn node_attribute(&self, node: GraphNodeRef, name: &Identifier) -> Option<Location> {
+        self.node_attributes
+            .borrow()
+            .get(&(node, name.clone()))
+            .copied()
+    }
+
+    /// Returns the location of the statement that set an attribute of an edge.
+    pub fn edge_attribute(
+        &self,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+        name: &Identifier,
+    ) -> Option<Location> {
+        self.edge_attributes
+            .borrow()
+            .get(&(source, sink, name.clone()))
+            .copied()
+    }
+
+    /// Records the statement that created some graph nodes, unless an inner statement already
+    /// did.
+    pub(crate) fn record_nodes(
+        &self,
+        nodes: impl Iterator<Item = GraphNodeRef>,
+        statement: Location,
+    ) {
+        let mut map = self.nodes.borrow_mut();
+        for node in nodes {
+            map.entry(node).or_insert(statement);
+        }
+    }
+
+    /// Records the statement that created an edge.
+    pub(crate) fn record_edge(
+        &self,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+        statement: Location,
+    ) {
+        self.edges.borrow_mut().insert((source, sink), statement);
+    }
+
+    /// Records the statement that set an attribute of a graph node.
+    pub(crate) fn record_node_attribute(
+        &self,
+        node: GraphNodeRef,
+        name: Identifier,
+        statement: Location,
+    ) {
+        self.node_attributes
+            .borrow_mut()
+            .insert((node, name), statement);
+    }
+
+    /// Records the statement that set an attribute of an edge.
+    pub(crate) fn record_edge_attribute(
+        &self,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+        name: Identifier,
+        statement: Location,
+    ) {
+        self.edge_attributes
+            .borrow_mut()
+            .insert((source, sink, name), statement);
+    }
+}
@@ -0,0 +1,107 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines the statistics that can be collected while executing a graph DSL file.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::ast::Stanza;
+use crate::Location;
+
+/// Statistics about each stanza of a graph DSL file, collected while executing it.  Use
+/// [`ExecutionConfig::statistics`][crate::ExecutionConfig::statistics] to collect them.  The same
+/// instance can be used for several executions of a file, to aggregate the statistics of many
+/// source files.
+#[derive(Debug, Default)]
+pub struct ExecutionStatistics {
+    stanzas: RefCell<HashMap<Location, StanzaStatistics>>,
+}
+
+/// Statistics about one stanza of a graph DSL file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StanzaStatistics {
+    /// The location of the stanza in the graph DSL file
+    pub location: Location,
+    /// The number of times that the stanza's query matched
+    pub matches: usize,
+    /// The time spent executing the stanza's statements.  With lazy execution, this includes the
+    /// time spent evaluating the stanza's edges and attributes once all stanzas have executed.
+    pub duration: Duration,
+    /// The number of graph nodes that the stanza created
+    pub nodes: usize,
+    /// The number of new edges that the stanza created
+    pub edges: usize,
+}
+
+impl ExecutionStatistics {
+    pub fn new() -> ExecutionStatistics {
+        ExecutionStatistics::default()
+    }
+
+    /// Returns the statistics of every stanza, in the order that they appear in the file.
+    /// Stanzas that never matched are included, with a match count of zero.
+    pub fn stanzas(&self) -> Vec<StanzaStatistics> {
+        let mut stanzas = self.stanzas.borrow().values().cloned().collect::<Vec<_>>();
+        stanzas.sort_by_key(|stanza| stanza.location);
+        stanzas
+    }
+
+    /// Adds the statistics collected by `other` to these.  Both must have been collected from
+    /// the same graph DSL file.
+    pub fn merge(&self, other: &ExecutionStatistics) {
+        let mut stanzas = self.stanzas.borrow_mut();
+        for (location, other) in other.stanzas.borrow().iter() {
+            let stanza = stanzas
+                .entry(*location)
+                .or_insert_with(|| StanzaStatistics {
+                    location: *location,
+                    ..Default::default()
+                });
+            stanza.matches += other.matches;
+            stanza.duration += other.duration;
+            stanza.nodes += other.nodes;
+            stanza.edges += other.edges;
+        }
+    }
+
+    fn update(&self, stanza: Location, update: impl FnOnce(&mut StanzaStatistics)) {
+        let mut stanzas = self.stanzas.borrow_mut();
+        let stanza = stanzas.entry(stanza).or_insert_with(|| StanzaStatistics {
+            location: stanza,
+            ..Default::default()
+        });
+        update(stanza);
+    }
+
+    /// Makes sure that all of `stanzas` are reported, even if they never match.
+    pub(crate) fn add_stanzas<'a>(&self, stanzas: impl IntoIterator<Item = &'a Stanza>) {
+        for stanza in stanzas {
+            self.update(stanza.range.start, |_| {});
+        }
+    }
+
+    /// Records one execution of a stanza, which took `duration` and created `nodes` graph nodes.
+    pub(crate) fn record_match(&self, stanza: Location, duration: Duration, nodes: usize) {
+        self.update(stanza, |stanza| {
+            stanza.matches += 1;
+            stanza.duration += duration;
+            stanza.nodes += nodes;
+        });
+    }
+
+    /// Records time spent on a stanza outside of the execution of its matches.
+    pub(crate) fn record_duration(&self, stanza: Location, duration: Duration) {
+        self.update(stanza, |stanza| stanza.duration += duration);
+    }
+
+    /// Records that a stanza created a new edge.
+    pub(crate) fn record_edge(&self, stanza: Location) {
+        self.update(stanza, |stanza| stanza.edges += 1);
+    }
+}
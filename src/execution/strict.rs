@@ -5,9 +5,16 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::cell::OnceCell;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::time::Instant;
+
+use tracing::debug;
+use tracing::debug_span;
 use tree_sitter::QueryCursor;
 use tree_sitter::QueryMatch;
 use tree_sitter::Tree;
@@ -25,13 +32,18 @@ use crate::ast::CreateEdge;
 use crate::ast::CreateGraphNode;
 use crate::ast::DeclareImmutable;
 use crate::ast::DeclareMutable;
+use crate::ast::DestructureList;
 use crate::ast::Expression;
+use crate::ast::Extension;
 use crate::ast::File;
 use crate::ast::ForIn;
+use crate::ast::ForInZip;
 use crate::ast::If;
 use crate::ast::IntegerConstant;
 use crate::ast::ListComprehension;
 use crate::ast::ListLiteral;
+use crate::ast::Match;
+use crate::ast::NewNode;
 use crate::ast::Print;
 use crate::ast::RegexCapture;
 use crate::ast::Scan;
@@ -43,12 +55,23 @@ use crate::ast::Statement;
 use crate::ast::StringConstant;
 use crate::ast::UnscopedVariable;
 use crate::ast::Variable;
+use crate::execution::debugger::DebugFrame;
+use crate::execution::debugger::ScopedVariableLookup;
 use crate::execution::error::ExecutionError;
 use crate::execution::error::ResultWithExecutionError;
 use crate::execution::error::StatementContext;
+use crate::execution::limits::ExecutionLimits;
+use crate::execution::limits::ResourceUsage;
+use crate::execution::output;
+use crate::execution::regions::MatchRegions;
+use crate::execution::sink::SinkFilter;
 use crate::execution::CancellationFlag;
 use crate::execution::ExecutionConfig;
+use crate::execution::RegexCaptures;
+use crate::functions::CallContext;
 use crate::graph::Graph;
+use crate::graph::GraphEdgeRef;
+use crate::graph::GraphNodeRef;
 use crate::graph::SyntaxNodeID;
 use crate::graph::SyntaxNodeRef;
 use crate::graph::Value;
@@ -82,14 +105,38 @@ impl File {
             location_attr: config.location_attr.clone(),
             variable_name_attr: config.variable_name_attr.clone(),
             match_node_attr: config.match_node_attr.clone(),
+            environment: config.environment,
+            statistics: config.statistics,
+            limits: config.limits,
+            extensions: config.extensions,
+            sink: config.sink,
+            source_map: config.source_map,
+            debugger: config.debugger,
+            trace: config.trace,
+            coverage: config.coverage,
+            seed: config.seed,
+            output: config.output,
+            attribute_storage: config.attribute_storage,
         };
 
         let mut locals = VariableMap::new();
         let mut scoped = ScopedVariables::new();
-        let current_regex_captures = Vec::new();
+        let current_regex_captures = RegexCaptures::default();
         let mut function_parameters = Vec::new();
+        let no_limits = ExecutionLimits::default();
+        let usage = ResourceUsage::new(config.limits.unwrap_or(&no_limits), graph);
+        let sink_filter = SinkFilter::new(config.sink, graph);
 
-        self.try_visit_matches_strict(tree, source, |stanza, mat| {
+        if let Some(statistics) = config.statistics {
+            statistics.add_stanzas(&self.stanzas);
+        }
+        if let Some(coverage) = config.coverage {
+            coverage.add_stanzas(&self.stanzas);
+        }
+        let enabled = self.enabled_stanzas(graph, source, &config)?;
+        self.try_visit_matches_strict(tree, source, &enabled, |stanza, mat| {
+            let start = Instant::now();
+            let node_count = graph.node_count();
             stanza.execute(
                 source,
                 &mat,
@@ -101,8 +148,21 @@ impl File {
                 &mut function_parameters,
                 &self.inherited_variables,
                 &self.shorthands,
+                &usage,
+                &sink_filter,
                 cancellation_flag,
-            )
+            )?;
+            if let Some(statistics) = config.statistics {
+                statistics.record_match(
+                    stanza.range.start,
+                    start.elapsed(),
+                    graph.node_count() - node_count,
+                );
+            }
+            if let Some(coverage) = config.coverage {
+                coverage.record_stanza(stanza.range.start);
+            }
+            Ok::<_, ExecutionError>(())
         })?;
 
         Ok(())
@@ -112,13 +172,31 @@ impl File {
         &self,
         tree: &'tree Tree,
         source: &'tree str,
+        enabled: &[bool],
         mut visit: F,
     ) -> Result<(), E>
     where
         F: FnMut(&Stanza, QueryMatch<'_, 'tree>) -> Result<(), E>,
     {
-        for stanza in &self.stanzas {
-            stanza.try_visit_matches_strict(tree, source, |mat| visit(stanza, mat))?;
+        let regions = MatchRegions::new(self, tree);
+        for (index, (stanza, enabled)) in self.stanzas.iter().zip(enabled).enumerate() {
+            if !enabled {
+                continue;
+            }
+            let region = match regions.region(index) {
+                Some(region) => region,
+                None => {
+                    debug!("stanza at {} cannot match", stanza.range.start);
+                    continue;
+                }
+            };
+            let _span = debug_span!(
+                "stanza",
+                at = %stanza.range.start,
+                name = stanza.name.as_ref().map(Identifier::as_str),
+            )
+            .entered();
+            stanza.try_visit_matches_strict(tree, source, region, |mat| visit(stanza, mat))?;
         }
         Ok(())
     }
@@ -131,16 +209,33 @@ struct ExecutionContext<'a, 'c, 'g, 's, 'tree> {
     config: &'a ExecutionConfig<'c, 'g>,
     locals: &'a mut dyn MutVariables<Value>,
     scoped: &'a mut ScopedVariables<'s>,
-    current_regex_captures: &'a Vec<String>,
+    current_regex_captures: &'a RegexCaptures,
     function_parameters: &'a mut Vec<Value>,
     mat: &'a QueryMatch<'a, 'tree>,
+    stanza: &'a Stanza,
+    /// The context of the function calls that the stanza makes on the match, which is only built
+    /// if it calls any
+    call_context: &'a OnceCell<CallContext>,
+    /// The number of blocks that the current statement is nested in
+    depth: usize,
     full_match_stanza_capture_index: usize,
     error_context: StatementContext,
     inherited_variables: &'a HashSet<Identifier>,
     shorthands: &'a AttributeShorthands,
+    usage: &'a ResourceUsage<'a>,
+    sink_filter: &'a SinkFilter<'a>,
     cancellation_flag: &'a dyn CancellationFlag,
 }
 
+impl ExecutionContext<'_, '_, '_, '_, '_> {
+    /// Records a value that the current statement resolved, if the execution is traced.
+    fn trace_value(&self, label: impl std::fmt::Display, value: &Value) {
+        if let Some(trace) = self.config.trace {
+            trace.record_value(label.to_string(), value);
+        }
+    }
+}
+
 struct ScopedVariables<'a> {
     scopes: HashMap<SyntaxNodeID, VariableMap<'a, Value>>,
 }
@@ -161,6 +256,14 @@ impl<'a> ScopedVariables<'a> {
     }
 }
 
+impl ScopedVariableLookup for ScopedVariables<'_> {
+    fn scoped_variables(&self, node: SyntaxNodeID) -> Vec<(&Identifier, &Value)> {
+        self.try_get(node)
+            .map(|variables| variables.variables())
+            .unwrap_or_default()
+    }
+}
+
 impl Stanza {
     fn execute<'a, 'g, 'l, 's, 'tree>(
         &self,
@@ -170,21 +273,26 @@ impl Stanza {
         config: &ExecutionConfig<'_, 'g>,
         locals: &mut VariableMap<'l, Value>,
         scoped: &mut ScopedVariables<'s>,
-        current_regex_captures: &Vec<String>,
+        current_regex_captures: &RegexCaptures,
         function_parameters: &mut Vec<Value>,
         inherited_variables: &HashSet<Identifier>,
         shorthands: &AttributeShorthands,
+        usage: &ResourceUsage,
+        sink_filter: &SinkFilter,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), ExecutionError> {
         locals.clear();
+        let node = mat
+            .nodes_for_capture_index(self.full_match_stanza_capture_index as u32)
+            .next()
+            .expect("missing full capture");
+        debug!("match {:?} at {}", node, self.range.start);
+        if let Some(trace) = config.trace {
+            trace.record_match(self, mat, node, source);
+        }
+        let call_context = OnceCell::new();
         for statement in &self.statements {
-            let error_context = {
-                let node = mat
-                    .nodes_for_capture_index(self.full_match_stanza_capture_index as u32)
-                    .next()
-                    .expect("missing full capture");
-                StatementContext::new(&statement, &self, &node)
-            };
+            let error_context = StatementContext::new(&statement, &self, &node);
             let mut exec = ExecutionContext {
                 source,
                 graph,
@@ -194,10 +302,15 @@ impl Stanza {
                 current_regex_captures,
                 function_parameters,
                 mat: &mat,
+                stanza: self,
+                call_context: &call_context,
+                depth: 0,
                 full_match_stanza_capture_index: self.full_match_stanza_capture_index,
                 error_context,
                 inherited_variables,
                 shorthands,
+                usage,
+                sink_filter,
                 cancellation_flag,
             };
             statement
@@ -207,46 +320,58 @@ impl Stanza {
         Ok(())
     }
 
+    /// Visits the matches of this stanza's query that are in a byte range of the tree.
     pub(super) fn try_visit_matches_strict<'tree, E, F>(
         &self,
         tree: &'tree Tree,
         source: &'tree str,
+        range: Range<usize>,
         mut visit: F,
     ) -> Result<(), E>
     where
         F: FnMut(QueryMatch<'_, 'tree>) -> Result<(), E>,
     {
         let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(range);
         let matches = cursor.matches(&self.query, tree.root_node(), source.as_bytes());
+        let mut count = 0;
         for mat in matches {
+            count += 1;
             visit(mat)?;
         }
+        debug!("stanza at {} matched {} times", self.range.start, count);
         Ok(())
     }
 }
 
 impl Statement {
-    pub fn location(&self) -> Location {
-        match self {
-            Statement::DeclareImmutable(s) => s.location,
-            Statement::DeclareMutable(s) => s.location,
-            Statement::Assign(s) => s.location,
-            Statement::CreateGraphNode(s) => s.location,
-            Statement::AddGraphNodeAttribute(s) => s.location,
-            Statement::CreateEdge(s) => s.location,
-            Statement::AddEdgeAttribute(s) => s.location,
-            Statement::Scan(s) => s.location,
-            Statement::Print(s) => s.location,
-            Statement::If(s) => s.location,
-            Statement::ForIn(s) => s.location,
-        }
-    }
-
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
         exec.cancellation_flag.check("executing statement")?;
+        exec.usage.execute_statement()?;
+        if let Some(coverage) = exec.config.coverage {
+            coverage.record_statement(self.location());
+        }
+        if let Some(debugger) = exec.config.debugger {
+            debugger.statement(&DebugFrame {
+                statement: self,
+                stanza: exec.stanza,
+                depth: exec.depth,
+                source: exec.source,
+                graph: exec.graph,
+                mat: exec.mat,
+                locals: exec.locals,
+                scoped: exec.scoped,
+            });
+        }
+        let traced = exec
+            .config
+            .trace
+            .map(|trace| trace.record_statement(self, exec.depth));
+        let node_count = exec.graph.node_count();
         match self {
             Statement::DeclareImmutable(statement) => statement.execute(exec),
             Statement::DeclareMutable(statement) => statement.execute(exec),
+            Statement::DestructureList(statement) => statement.execute(exec),
             Statement::Assign(statement) => statement.execute(exec),
             Statement::CreateGraphNode(statement) => statement.execute(exec),
             Statement::AddGraphNodeAttribute(statement) => statement.execute(exec),
@@ -255,21 +380,50 @@ impl Statement {
             Statement::Scan(statement) => statement.execute(exec),
             Statement::Print(statement) => statement.execute(exec),
             Statement::If(statement) => statement.execute(exec),
+            Statement::Match(statement) => statement.execute(exec),
             Statement::ForIn(statement) => statement.execute(exec),
+            Statement::ForInZip(statement) => statement.execute(exec),
+            Statement::Extension(statement) => statement.execute(exec),
+        }?;
+        if let Some(source_map) = exec.config.source_map {
+            source_map.record_nodes(exec.graph.iter_nodes().skip(node_count), self.location());
+        }
+        if let (Some(trace), Some(traced)) = (exec.config.trace, traced) {
+            trace.record_nodes(traced, exec.graph.iter_nodes().skip(node_count));
         }
+        exec.sink_filter.report_nodes(exec.graph);
+        exec.usage.check_nodes(exec.graph)
     }
 }
 
 impl DeclareImmutable {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
         let value = self.value.evaluate(exec)?;
+        exec.trace_value(&self.variable, &value);
         self.variable.add(exec, value, false)
     }
 }
 
+impl DestructureList {
+    fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        let values = self.value.evaluate(exec)?.into_list()?;
+        let (values, rest_value) = self.split(values)?;
+        for (element, value) in self.elements.iter().zip(values) {
+            exec.trace_value(element, &value);
+            element.add(exec, value, false)?;
+        }
+        if let (Some(rest), Some(rest_value)) = (&self.rest, rest_value) {
+            exec.trace_value(rest, &rest_value);
+            rest.add(exec, rest_value, false)?;
+        }
+        Ok(())
+    }
+}
+
 impl DeclareMutable {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
         let value = self.value.evaluate(exec)?;
+        exec.trace_value(&self.variable, &value);
         self.variable.add(exec, value, true)
     }
 }
@@ -277,53 +431,100 @@ impl DeclareMutable {
 impl Assign {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
         let value = self.value.evaluate(exec)?;
+        exec.trace_value(&self.variable, &value);
         self.variable.set(exec, value)
     }
 }
 
 impl CreateGraphNode {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
-        let graph_node = exec.graph.add_graph_node();
+        let graph_node = match &self.label {
+            Some(label) => {
+                let label = label.evaluate(exec)?.into_string()?;
+                exec.graph.add_labeled_graph_node(label.as_str().into())
+            }
+            None => exec.graph.add_graph_node(),
+        };
         self.node
-            .add_debug_attrs(&mut exec.graph[graph_node].attributes, exec.config)?;
-        if let Some(match_node_attr) = &exec.config.match_node_attr {
-            let match_node = exec
-                .mat
-                .nodes_for_capture_index(exec.full_match_stanza_capture_index as u32)
-                .next()
-                .expect("missing capture for full match");
-            let syn_node = exec.graph.add_syntax_node(match_node);
-            exec.graph[graph_node]
-                .attributes
-                .add(match_node_attr.clone(), syn_node)
-                .map_err(|_| {
-                    ExecutionError::DuplicateAttribute(format!(
-                        " {} on graph node ({}) in {}",
-                        match_node_attr, graph_node, self,
-                    ))
-                })?;
-        }
+            .add_debug_attrs(exec.graph, graph_node, exec.config)?;
+        add_match_node_attribute(exec, graph_node, self)?;
         let value = Value::GraphNode(graph_node);
+        exec.trace_value(&self.node, &value);
         self.node.add(exec, value, false)
     }
 }
 
+/// Adds the syntax node that the stanza matched to a new graph node, if the execution is
+/// configured with a match node attribute.  The graph node is created by `statement`, which is
+/// either a `node` statement or a `(new node)` expression.
+fn add_match_node_attribute(
+    exec: &mut ExecutionContext,
+    graph_node: GraphNodeRef,
+    statement: &dyn fmt::Display,
+) -> Result<(), ExecutionError> {
+    if let Some(match_node_attr) = &exec.config.match_node_attr {
+        let match_node = exec
+            .mat
+            .nodes_for_capture_index(exec.full_match_stanza_capture_index as u32)
+            .next()
+            .expect("missing capture for full match");
+        let syn_node = exec.graph.add_syntax_node(match_node);
+        exec.graph
+            .add_node_attribute(graph_node, match_node_attr.clone(), syn_node)
+            .map_err(|_| {
+                ExecutionError::DuplicateAttribute(format!(
+                    " {} on graph node ({}) in {}",
+                    match_node_attr, graph_node, statement,
+                ))
+            })?;
+    }
+    Ok(())
+}
+
 impl AddGraphNodeAttribute {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
-        let node = self.node.evaluate(exec)?.into_graph_node_ref()?;
-        let add_attribute = |exec: &mut ExecutionContext, name: Identifier, value: Value| {
-            exec.graph[node]
-                .attributes
-                .add(name.clone(), value)
-                .map_err(|_| {
+        let node = self.node.evaluate(exec)?;
+        exec.trace_value(&self.node, &node);
+        if let Value::GraphEdge(edge) = node {
+            return add_edge_attributes(
+                exec,
+                edge.source(),
+                edge.sink(),
+                &self.attributes,
+                self,
+                self.location,
+            );
+        }
+        let node = node.into_graph_node_ref()?;
+        let add_attribute =
+            |exec: &mut ExecutionContext, name: Identifier, mut value: Value, append: bool| {
+                exec.trace_value(&name, &value);
+                if !exec
+                    .sink_filter
+                    .accept_node_attribute(exec.graph, node, &name, &mut value)
+                {
+                    return Ok(());
+                }
+                let context = &exec.error_context;
+                exec.graph
+                    .observe_node_attribute(node, &name, &value, append, context)?;
+                if let Some(source_map) = exec.config.source_map {
+                    source_map.record_node_attribute(node, name.clone(), self.location);
+                }
+                let result = if append {
+                    exec.graph.append_node_attribute(node, name.clone(), value)
+                } else {
+                    exec.graph.add_node_attribute(node, name.clone(), value)
+                };
+                result.map_err(|_| {
                     ExecutionError::DuplicateAttribute(format!(
                         " {} on graph node ({}) in {}",
                         name, node, self,
                     ))
                 })
-        };
+            };
         for attribute in &self.attributes {
-            attribute.execute(exec, &add_attribute)?;
+            attribute.execute(exec, &add_attribute, false)?;
         }
         Ok(())
     }
@@ -331,10 +532,44 @@ impl AddGraphNodeAttribute {
 
 impl CreateEdge {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
-        let source = self.source.evaluate(exec)?.into_graph_node_ref()?;
-        let sink = self.sink.evaluate(exec)?.into_graph_node_ref()?;
-        let edge = match exec.graph[source].add_edge(sink) {
-            Ok(edge) | Err(edge) => edge,
+        let source = self.source.evaluate(exec)?;
+        exec.trace_value(&self.source, &source);
+        let source = source.into_graph_node_ref()?;
+        let sink = self.sink.evaluate(exec)?;
+        exec.trace_value(&self.sink, &sink);
+        let sink = sink.into_graph_node_ref()?;
+        if let Some(variable) = &self.variable {
+            let value = GraphEdgeRef::new(source, sink).into();
+            exec.trace_value(variable, &value);
+            variable.add(exec, value, false)?;
+        }
+        if exec.graph[source].get_edge(sink).is_none()
+            && !exec.sink_filter.accept_edge(exec.graph, source, sink)
+        {
+            return Ok(());
+        }
+        let added = match &self.kind {
+            Some(kind) => exec.graph.add_kinded_edge(source, sink, kind.clone()),
+            None => exec.graph[source].add_edge(sink),
+        };
+        let edge = match added {
+            Ok(edge) => {
+                if let Some(statistics) = exec.config.statistics {
+                    statistics.record_edge(exec.error_context.stanza_location);
+                }
+                if let Some(source_map) = exec.config.source_map {
+                    source_map.record_edge(source, sink, self.location);
+                }
+                exec.usage.add_edge()?;
+                edge
+            }
+            Err(edge) if edge.kind() != self.kind.as_ref() => {
+                return Err(ExecutionError::DuplicateEdge(format!(
+                    "{} -> {} of another kind in {}",
+                    source, sink, self,
+                )));
+            }
+            Err(edge) => edge,
         };
         self.add_debug_attrs(&mut edge.attributes, exec.config)?;
         Ok(())
@@ -343,34 +578,98 @@ impl CreateEdge {
 
 impl AddEdgeAttribute {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
-        let source = self.source.evaluate(exec)?.into_graph_node_ref()?;
-        let sink = self.sink.evaluate(exec)?.into_graph_node_ref()?;
-        let add_attribute = |exec: &mut ExecutionContext, name: Identifier, value: Value| {
+        let source = self.source.evaluate(exec)?;
+        exec.trace_value(&self.source, &source);
+        let source = source.into_graph_node_ref()?;
+        let sink = self.sink.evaluate(exec)?;
+        exec.trace_value(&self.sink, &sink);
+        let sink = sink.into_graph_node_ref()?;
+        add_edge_attributes(exec, source, sink, &self.attributes, self, self.location)
+    }
+}
+
+/// Adds the attributes of an `attr` statement to the edge between two graph nodes.  The statement
+/// is either an edge attribute statement, or a node attribute statement whose node is an edge value.
+fn add_edge_attributes(
+    exec: &mut ExecutionContext,
+    source: GraphNodeRef,
+    sink: GraphNodeRef,
+    attributes: &[Attribute],
+    statement: &dyn fmt::Display,
+    location: Location,
+) -> Result<(), ExecutionError> {
+    let add_attribute =
+        |exec: &mut ExecutionContext, name: Identifier, mut value: Value, append: bool| {
+            exec.trace_value(&name, &value);
+            if exec.sink_filter.is_rejected_edge(source, sink) {
+                return Ok(());
+            }
+            if exec.graph[source].get_edge(sink).is_some()
+                && !exec
+                    .sink_filter
+                    .accept_edge_attribute(exec.graph, source, sink, &name, &mut value)
+            {
+                return Ok(());
+            }
+            exec.graph.observe_edge_attribute(
+                source,
+                sink,
+                &name,
+                &value,
+                append,
+                &exec.error_context,
+            )?;
             let edge = match exec.graph[source].get_edge_mut(sink) {
                 Some(edge) => Ok(edge),
                 None => Err(ExecutionError::UndefinedEdge(format!(
                     "({} -> {}) in {}",
-                    source, sink, self,
+                    source, sink, statement,
                 ))),
             }?;
-            edge.attributes.add(name.clone(), value).map_err(|_| {
+            if let Some(source_map) = exec.config.source_map {
+                source_map.record_edge_attribute(source, sink, name.clone(), location);
+            }
+            let result = if append {
+                edge.attributes.append(name.clone(), value)
+            } else {
+                edge.attributes.add(name.clone(), value)
+            };
+            result.map_err(|_| {
                 ExecutionError::DuplicateAttribute(format!(
                     " {} on edge ({} -> {}) in {}",
-                    name, source, sink, self,
+                    name, source, sink, statement,
                 ))
             })
         };
-        for attribute in &self.attributes {
-            attribute.execute(exec, &add_attribute)?;
-        }
-        Ok(())
+    for attribute in attributes {
+        attribute.execute(exec, &add_attribute, false)?;
     }
+    Ok(())
 }
 
 impl Scan {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
-        let match_string = self.value.evaluate(exec)?.into_string()?;
+        let value = self.value.evaluate(exec)?;
+        exec.trace_value(&self.value, &value);
+        let element = match &self.element {
+            Some(element) => element,
+            None => return self.scan_string(exec, &value.into_string()?, None),
+        };
+        for value in value.into_list()? {
+            let match_string = Scan::element_text(exec.graph, exec.source, &value)?;
+            self.scan_string(exec, &match_string, Some((element, &value)))?;
+        }
+        Ok(())
+    }
 
+    /// Runs the arms on the matches of their regular expressions in a string.  When a list is
+    /// scanned, the element that the string belongs to is bound to the element variable.
+    fn scan_string(
+        &self,
+        exec: &mut ExecutionContext,
+        match_string: &str,
+        element: Option<(&UnscopedVariable, &Value)>,
+    ) -> Result<(), ExecutionError> {
         let mut i = 0;
         let mut matches = Vec::new();
         while i < match_string.len() {
@@ -405,13 +704,12 @@ impl Scan {
 
             let (regex_captures, block_index) = &matches[0];
             let arm = &self.arms[*block_index];
-
-            let mut current_regex_captures = Vec::new();
-            for regex_capture in regex_captures.iter() {
-                current_regex_captures
-                    .push(regex_capture.map(|m| m.as_str()).unwrap_or("").to_string());
+            if let Some(coverage) = exec.config.coverage {
+                coverage.record_arm(arm.location);
             }
 
+            let current_regex_captures = RegexCaptures::new(&arm.regex, regex_captures, i);
+
             let mut arm_locals = VariableMap::nested(exec.locals);
             let mut arm_exec = ExecutionContext {
                 source: exec.source,
@@ -422,12 +720,20 @@ impl Scan {
                 current_regex_captures: &current_regex_captures,
                 function_parameters: exec.function_parameters,
                 mat: exec.mat,
+                stanza: exec.stanza,
+                call_context: exec.call_context,
+                depth: exec.depth + 1,
                 full_match_stanza_capture_index: exec.full_match_stanza_capture_index,
                 error_context: exec.error_context.clone(),
                 inherited_variables: exec.inherited_variables,
                 shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
                 cancellation_flag: exec.cancellation_flag,
             };
+            if let Some((variable, value)) = element {
+                variable.add(&mut arm_exec, value.clone(), false)?;
+            }
 
             for statement in &arm.statements {
                 arm_exec.error_context.update_statement(statement);
@@ -452,27 +758,65 @@ impl Scan {
 
 impl Print {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        let output = exec.config.output;
+        if !output::enabled(output, self.level(), self.channel.as_ref()) {
+            return Ok(());
+        }
+        let mut message = String::new();
         for value in &self.values {
             if let Expression::StringConstant(expr) = value {
-                eprint!("{}", expr.value);
+                message += &expr.value;
             } else {
+                let label = value;
                 let value = value.evaluate(exec)?;
-                eprint!("{:?}", value);
+                exec.trace_value(label, &value);
+                message += &output::format_value(exec.graph, &value);
             }
         }
-        eprintln!();
+        output::print(output, self.level(), self.channel.as_ref(), &message);
         Ok(())
     }
 }
 
+impl Extension {
+    fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        let extensions = exec
+            .config
+            .extensions
+            .ok_or_else(|| ExecutionError::UndefinedStatement(self.keyword.to_string()))?;
+        let mut arguments = Vec::with_capacity(self.arguments.len());
+        for argument in &self.arguments {
+            let value = argument.evaluate(exec)?;
+            exec.trace_value(argument, &value);
+            arguments.push(value);
+        }
+        extensions.execute(
+            &self.keyword,
+            exec.graph,
+            exec.source,
+            &mut arguments.into_iter(),
+        )
+    }
+}
+
 impl If {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
         for arm in &self.arms {
             let mut result = true;
             for condition in &arm.conditions {
-                result &= condition.test(exec)?;
+                let test = condition.test(exec)?;
+                exec.trace_value(condition, &Value::Boolean(test));
+                // The remaining conditions are not evaluated once one of them is false, so they
+                // can rely on the earlier ones, as in `some @x, (eq (source-text @x) "a")`.
+                if !test {
+                    result = false;
+                    break;
+                }
             }
             if result {
+                if let Some(coverage) = exec.config.coverage {
+                    coverage.record_arm(arm.location);
+                }
                 let mut arm_locals = VariableMap::nested(exec.locals);
                 let mut arm_exec = ExecutionContext {
                     source: exec.source,
@@ -483,10 +827,15 @@ impl If {
                     current_regex_captures: exec.current_regex_captures,
                     function_parameters: exec.function_parameters,
                     mat: exec.mat,
+                    stanza: exec.stanza,
+                    call_context: exec.call_context,
+                    depth: exec.depth + 1,
                     full_match_stanza_capture_index: exec.full_match_stanza_capture_index,
                     error_context: exec.error_context.clone(),
                     inherited_variables: exec.inherited_variables,
                     shorthands: exec.shorthands,
+                    usage: exec.usage,
+                    sink_filter: exec.sink_filter,
                     cancellation_flag: exec.cancellation_flag,
                 };
                 for stmt in &arm.statements {
@@ -501,11 +850,56 @@ impl If {
     }
 }
 
+impl Match {
+    fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        let match_string = self.value.evaluate(exec)?;
+        exec.trace_value(&self.value, &match_string);
+        let match_string = match_string.into_string()?;
+        let (arm, regex_captures) = match self.select_arm(&match_string) {
+            Some(selected) => selected,
+            None => return Ok(()),
+        };
+        if let Some(coverage) = exec.config.coverage {
+            coverage.record_arm(arm.location);
+        }
+        let mut arm_locals = VariableMap::nested(exec.locals);
+        let mut arm_exec = ExecutionContext {
+            source: exec.source,
+            graph: exec.graph,
+            config: exec.config,
+            locals: &mut arm_locals,
+            scoped: exec.scoped,
+            current_regex_captures: regex_captures
+                .as_ref()
+                .unwrap_or(exec.current_regex_captures),
+            function_parameters: exec.function_parameters,
+            mat: exec.mat,
+            stanza: exec.stanza,
+            call_context: exec.call_context,
+            depth: exec.depth + 1,
+            full_match_stanza_capture_index: exec.full_match_stanza_capture_index,
+            error_context: exec.error_context.clone(),
+            inherited_variables: exec.inherited_variables,
+            shorthands: exec.shorthands,
+            usage: exec.usage,
+            sink_filter: exec.sink_filter,
+            cancellation_flag: exec.cancellation_flag,
+        };
+        for stmt in &arm.statements {
+            arm_exec.error_context.update_statement(stmt);
+            stmt.execute(&mut arm_exec)
+                .with_context(|| format!("matching {} with arm {}", match_string, arm).into())
+                .with_context(|| arm_exec.error_context.clone().into())?;
+        }
+        Ok(())
+    }
+}
+
 impl Condition {
     fn test(&self, exec: &mut ExecutionContext) -> Result<bool, ExecutionError> {
         match self {
-            Condition::Some { value, .. } => Ok(!value.evaluate(exec)?.is_null()),
-            Condition::None { value, .. } => Ok(value.evaluate(exec)?.is_null()),
+            Condition::Some { value, .. } => Ok(!value.evaluate(exec)?.is_null_or_empty()),
+            Condition::None { value, .. } => Ok(value.evaluate(exec)?.is_null_or_empty()),
             Condition::Bool { value, .. } => Ok(value.evaluate(exec)?.into_boolean()?),
         }
     }
@@ -513,7 +907,9 @@ impl Condition {
 
 impl ForIn {
     fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
-        let values = self.value.evaluate(exec)?.into_list()?;
+        let values = self.value.evaluate(exec)?;
+        exec.trace_value(&self.value, &values);
+        let values = values.into_list()?;
         let mut loop_locals = VariableMap::nested(exec.locals);
         for value in values {
             loop_locals.clear();
@@ -526,10 +922,15 @@ impl ForIn {
                 current_regex_captures: exec.current_regex_captures,
                 function_parameters: exec.function_parameters,
                 mat: exec.mat,
+                stanza: exec.stanza,
+                call_context: exec.call_context,
+                depth: exec.depth + 1,
                 full_match_stanza_capture_index: exec.full_match_stanza_capture_index,
                 error_context: exec.error_context.clone(),
                 inherited_variables: exec.inherited_variables,
                 shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
                 cancellation_flag: exec.cancellation_flag,
             };
             self.variable.add(&mut loop_exec, value, false)?;
@@ -543,6 +944,50 @@ impl ForIn {
     }
 }
 
+impl ForInZip {
+    fn execute(&self, exec: &mut ExecutionContext) -> Result<(), ExecutionError> {
+        let mut lists = Vec::new();
+        for value in &self.values {
+            let values = value.evaluate(exec)?;
+            exec.trace_value(value, &values);
+            lists.push(values.into_list()?);
+        }
+        let mut loop_locals = VariableMap::nested(exec.locals);
+        for values in ForInZip::zip(lists) {
+            loop_locals.clear();
+            let mut loop_exec = ExecutionContext {
+                source: exec.source,
+                graph: exec.graph,
+                config: exec.config,
+                locals: &mut loop_locals,
+                scoped: exec.scoped,
+                current_regex_captures: exec.current_regex_captures,
+                function_parameters: exec.function_parameters,
+                mat: exec.mat,
+                stanza: exec.stanza,
+                call_context: exec.call_context,
+                depth: exec.depth + 1,
+                full_match_stanza_capture_index: exec.full_match_stanza_capture_index,
+                error_context: exec.error_context.clone(),
+                inherited_variables: exec.inherited_variables,
+                shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
+                cancellation_flag: exec.cancellation_flag,
+            };
+            for (variable, value) in self.variables.iter().zip(values) {
+                variable.add(&mut loop_exec, value, false)?;
+            }
+            for stmt in &self.statements {
+                loop_exec.error_context.update_statement(stmt);
+                stmt.execute(&mut loop_exec)
+                    .with_context(|| loop_exec.error_context.clone().into())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Expression {
     fn evaluate(&self, exec: &mut ExecutionContext) -> Result<Value, ExecutionError> {
         match self {
@@ -551,13 +996,29 @@ impl Expression {
             Expression::TrueLiteral => Ok(Value::Boolean(true)),
             Expression::IntegerConstant(expr) => expr.evaluate(exec),
             Expression::StringConstant(expr) => expr.evaluate(exec),
-            Expression::ListLiteral(expr) => expr.evaluate(exec),
-            Expression::SetLiteral(expr) => expr.evaluate(exec),
-            Expression::ListComprehension(expr) => expr.evaluate(exec),
-            Expression::SetComprehension(expr) => expr.evaluate(exec),
+            Expression::ListLiteral(expr) => {
+                let value = expr.evaluate(exec)?;
+                exec.usage.check_value(value)
+            }
+            Expression::SetLiteral(expr) => {
+                let value = expr.evaluate(exec)?;
+                exec.usage.check_value(value)
+            }
+            Expression::ListComprehension(expr) => {
+                let value = expr.evaluate(exec)?;
+                exec.usage.check_value(value)
+            }
+            Expression::SetComprehension(expr) => {
+                let value = expr.evaluate(exec)?;
+                exec.usage.check_value(value)
+            }
             Expression::Capture(expr) => expr.evaluate(exec),
             Expression::Variable(expr) => expr.evaluate(exec),
-            Expression::Call(expr) => expr.evaluate(exec),
+            Expression::Call(expr) => {
+                let value = expr.evaluate(exec)?;
+                exec.usage.check_value(value)
+            }
+            Expression::NewNode(expr) => expr.evaluate(exec),
             Expression::RegexCapture(expr) => expr.evaluate(exec),
         }
     }
@@ -602,13 +1063,23 @@ impl ListComprehension {
                 current_regex_captures: exec.current_regex_captures,
                 function_parameters: exec.function_parameters,
                 mat: exec.mat,
+                stanza: exec.stanza,
+                call_context: exec.call_context,
+                depth: exec.depth,
                 full_match_stanza_capture_index: exec.full_match_stanza_capture_index,
                 error_context: exec.error_context.clone(),
                 inherited_variables: exec.inherited_variables,
                 shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
                 cancellation_flag: exec.cancellation_flag,
             };
             self.variable.add(&mut loop_exec, value, false)?;
+            if let Some(condition) = &self.condition {
+                if !condition.evaluate(&mut loop_exec)?.into_boolean()? {
+                    continue;
+                }
+            }
             let element = self.element.evaluate(&mut loop_exec)?;
             elements.push(element);
         }
@@ -643,13 +1114,23 @@ impl SetComprehension {
                 current_regex_captures: exec.current_regex_captures,
                 function_parameters: exec.function_parameters,
                 mat: exec.mat,
+                stanza: exec.stanza,
+                call_context: exec.call_context,
+                depth: exec.depth,
                 full_match_stanza_capture_index: exec.full_match_stanza_capture_index,
                 error_context: exec.error_context.clone(),
                 inherited_variables: exec.inherited_variables,
                 shorthands: exec.shorthands,
+                usage: exec.usage,
+                sink_filter: exec.sink_filter,
                 cancellation_flag: exec.cancellation_flag,
             };
             self.variable.add(&mut loop_exec, value, false)?;
+            if let Some(condition) = &self.condition {
+                if !condition.evaluate(&mut loop_exec)?.into_boolean()? {
+                    continue;
+                }
+            }
             let element = self.element.evaluate(&mut loop_exec)?;
             elements.insert(element);
         }
@@ -675,7 +1156,13 @@ impl Call {
             let parameter = parameter.evaluate(exec)?;
             exec.function_parameters.push(parameter);
         }
-        exec.config.functions.call(
+        let (stanza, mat) = (exec.stanza, exec.mat);
+        let context = exec.call_context.get_or_init(|| {
+            CallContext::new(stanza, stanza.query.capture_names(), mat, exec.graph)
+        });
+        exec.config.functions.call_with_environment(
+            exec.config.environment,
+            Some(context),
             &self.function,
             exec.graph,
             exec.source,
@@ -686,31 +1173,34 @@ impl Call {
     }
 }
 
-impl RegexCapture {
+impl NewNode {
     fn evaluate(&self, exec: &mut ExecutionContext) -> Result<Value, ExecutionError> {
-        let capture = exec
-            .current_regex_captures
-            .get(self.match_index)
-            .ok_or(ExecutionError::UndefinedRegexCapture(format!("{}", self)))?;
-        Ok(Value::String(capture.clone()))
+        let graph_node = exec.graph.add_graph_node();
+        self.add_debug_attrs(exec.graph, graph_node, exec.config)?;
+        add_match_node_attribute(exec, graph_node, self)?;
+        Ok(Value::GraphNode(graph_node))
     }
 }
 
-impl Variable {
+impl RegexCapture {
     fn evaluate(&self, exec: &mut ExecutionContext) -> Result<Value, ExecutionError> {
-        let value = self.get(exec)?;
-        Ok(value.clone())
+        exec.current_regex_captures.get(self)
     }
 }
 
 impl Variable {
-    fn get<'a>(&self, exec: &'a mut ExecutionContext) -> Result<&'a Value, ExecutionError> {
+    fn evaluate(&self, exec: &mut ExecutionContext) -> Result<Value, ExecutionError> {
         match self {
-            Variable::Scoped(variable) => variable.get(exec),
-            Variable::Unscoped(variable) => variable.get(exec),
+            Variable::Scoped(variable) => variable.evaluate(exec),
+            Variable::Unscoped(variable) => {
+                let value = variable.get(exec)?;
+                Ok(value.clone())
+            }
         }
     }
+}
 
+impl Variable {
     fn add(
         &self,
         exec: &mut ExecutionContext,
@@ -732,7 +1222,7 @@ impl Variable {
 }
 
 impl ScopedVariable {
-    fn get<'a>(&self, exec: &'a mut ExecutionContext) -> Result<&'a Value, ExecutionError> {
+    fn evaluate(&self, exec: &mut ExecutionContext) -> Result<Value, ExecutionError> {
         let scope = self.scope.evaluate(exec)?;
         let scope = match scope {
             Value::SyntaxNode(scope) => scope,
@@ -750,7 +1240,7 @@ impl ScopedVariable {
             .try_get(scope.index)
             .and_then(|v| v.get(&self.name))
         {
-            return Ok(value);
+            return Ok(value.clone());
         }
 
         // search parent nodes
@@ -766,12 +1256,16 @@ impl ScopedVariable {
                     .try_get(scope.id() as u32)
                     .and_then(|v| v.get(&self.name))
                 {
-                    return Ok(value);
+                    return Ok(value.clone());
                 }
                 parent = scope.parent();
             }
         }
 
+        if let Some(default) = &self.default {
+            return default.evaluate(exec);
+        }
+
         Err(ExecutionError::UndefinedVariable(format!(
             "{} on node {}",
             self, scope
@@ -863,20 +1357,24 @@ impl UnscopedVariable {
 }
 
 impl Attribute {
+    /// Executes this attribute.  The value is appended to the attribute if this attribute uses
+    /// `+=`, or if `append` is set because this attribute is part of a shorthand that does.
     fn execute<F>(
         &self,
         exec: &mut ExecutionContext,
         add_attribute: &F,
+        append: bool,
     ) -> Result<(), ExecutionError>
     where
-        F: Fn(&mut ExecutionContext, Identifier, Value) -> Result<(), ExecutionError>,
+        F: Fn(&mut ExecutionContext, Identifier, Value, bool) -> Result<(), ExecutionError>,
     {
         exec.cancellation_flag.check("executing attribute")?;
         let value = self.value.evaluate(exec)?;
+        let append = append || self.append;
         if let Some(shorthand) = exec.shorthands.get(&self.name) {
-            shorthand.execute(exec, add_attribute, value)
+            shorthand.execute(exec, add_attribute, value, append)
         } else {
-            add_attribute(exec, self.name.clone(), value)
+            add_attribute(exec, self.name.clone(), value, append)
         }
     }
 }
@@ -887,9 +1385,10 @@ impl AttributeShorthand {
         exec: &mut ExecutionContext,
         add_attribute: &F,
         value: Value,
+        append: bool,
     ) -> Result<(), ExecutionError>
     where
-        F: Fn(&mut ExecutionContext, Identifier, Value) -> Result<(), ExecutionError>,
+        F: Fn(&mut ExecutionContext, Identifier, Value, bool) -> Result<(), ExecutionError>,
     {
         let mut shorthand_locals = VariableMap::new();
         let mut shorthand_exec = ExecutionContext {
@@ -901,15 +1400,20 @@ impl AttributeShorthand {
             current_regex_captures: exec.current_regex_captures,
             function_parameters: exec.function_parameters,
             mat: exec.mat,
+            stanza: exec.stanza,
+            call_context: exec.call_context,
+            depth: exec.depth,
             full_match_stanza_capture_index: exec.full_match_stanza_capture_index,
             error_context: exec.error_context.clone(),
             inherited_variables: exec.inherited_variables,
             shorthands: exec.shorthands,
+            usage: exec.usage,
+            sink_filter: exec.sink_filter,
             cancellation_flag: exec.cancellation_flag,
         };
         self.variable.add(&mut shorthand_exec, value, false)?;
         for attr in &self.attributes {
-            attr.execute(&mut shorthand_exec, add_attribute)?;
+            attr.execute(&mut shorthand_exec, add_attribute, append)?;
         }
         Ok(())
     }
@@ -0,0 +1,228 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines the trace of the statements that an execution runs.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::Serialize;
+use serde::Serializer;
+use tree_sitter::Node;
+use tree_sitter::QueryMatch;
+
+use crate::ast::Stanza;
+use crate::ast::Statement;
+use crate::graph::GraphNodeRef;
+use crate::graph::Value;
+use crate::parser::FULL_MATCH;
+use crate::Location;
+
+/// A log of every statement that an execution runs, grouped by stanza match, with the values that
+/// each statement resolved and the graph nodes that it created.  Use
+/// [`ExecutionConfig::trace`][crate::ExecutionConfig::trace] to record it.  The trace can be
+/// serialized as JSON, or printed with [`pretty_print`][Self::pretty_print].
+///
+/// Statements are recorded before they run, so when an execution fails, the last statement of
+/// the trace is the one that failed.
+///
+/// Only strict execution records a trace, since lazy execution resolves values after all of the
+/// stanzas have run, and not in the order of their statements.
+#[derive(Debug, Default)]
+pub struct ExecutionTrace {
+    matches: RefCell<Vec<TracedMatch>>,
+}
+
+/// One match of a stanza's query, and the statements that ran for it.
+#[derive(Clone, Debug, Serialize)]
+pub struct TracedMatch {
+    /// The location of the stanza in the graph DSL file
+    pub stanza: Location,
+    /// The kind of the syntax node that the stanza's query matched
+    pub kind: String,
+    /// The position of the matched syntax node in the source file
+    pub position: Location,
+    /// The source text of the syntax nodes of each capture
+    pub captures: Vec<TracedCapture>,
+    /// The statements that ran for the match, including those of nested blocks, in the order in
+    /// which they ran
+    pub statements: Vec<TracedStatement>,
+}
+
+/// The syntax nodes of one capture of a stanza's query.
+#[derive(Clone, Debug, Serialize)]
+pub struct TracedCapture {
+    pub name: String,
+    pub text: Vec<String>,
+}
+
+/// One statement that ran.
+#[derive(Clone, Debug, Serialize)]
+pub struct TracedStatement {
+    /// The location of the statement in the graph DSL file
+    pub location: Location,
+    /// The number of blocks that the statement is nested in, which is 0 for the statements of a
+    /// stanza
+    pub depth: usize,
+    /// The statement, as displayed in error messages
+    pub statement: String,
+    /// The values that the statement resolved, in the order in which it resolved them
+    pub values: Vec<TracedValue>,
+    /// The graph nodes that the statement created, not including those of nested blocks
+    #[serde(serialize_with = "serialize_nodes")]
+    pub nodes: Vec<GraphNodeRef>,
+}
+
+/// A value that a statement resolved, labeled with the variable, attribute, or expression that it
+/// is the value of.
+#[derive(Clone, Debug, Serialize)]
+pub struct TracedValue {
+    pub label: String,
+    pub value: Value,
+}
+
+impl ExecutionTrace {
+    pub fn new() -> ExecutionTrace {
+        ExecutionTrace::default()
+    }
+
+    /// Returns the matches that have been recorded, in the order in which they ran.
+    pub fn matches(&self) -> Vec<TracedMatch> {
+        self.matches.borrow().clone()
+    }
+
+    /// Pretty-prints the trace, with one paragraph per match.
+    pub fn pretty_print<'a>(&'a self) -> impl fmt::Display + 'a {
+        struct DisplayTrace<'a>(&'a ExecutionTrace);
+
+        impl<'a> fmt::Display for DisplayTrace<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                for mat in self.0.matches.borrow().iter() {
+                    writeln!(
+                        f,
+                        "match of stanza at {} on ({}) at {}",
+                        mat.stanza, mat.kind, mat.position
+                    )?;
+                    for capture in &mat.captures {
+                        writeln!(f, "  @{} = {:?}", capture.name, capture.text)?;
+                    }
+                    for statement in &mat.statements {
+                        let indent = "  ".repeat(statement.depth + 1);
+                        writeln!(f, "{}{}", indent, statement.statement)?;
+                        for value in &statement.values {
+                            writeln!(f, "{}  {} = {:?}", indent, value.label, value.value)?;
+                        }
+                        if !statement.nodes.is_empty() {
+                            let nodes = statement
+                                .nodes
+                                .iter()
+                                .map(|node| node.to_string())
+                                .collect::<Vec<_>>();
+                            writeln!(f, "{}  created {}", indent, nodes.join(", "))?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        DisplayTrace(self)
+    }
+
+    /// Records a match of a stanza's query.
+    pub(crate) fn record_match(
+        &self,
+        stanza: &Stanza,
+        mat: &QueryMatch<'_, '_>,
+        node: Node,
+        source: &str,
+    ) {
+        let captures = stanza
+            .query
+            .capture_names()
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| **name != FULL_MATCH)
+            .map(|(index, name)| TracedCapture {
+                name: name.to_string(),
+                text: mat
+                    .nodes_for_capture_index(index as u32)
+                    .map(|node| source[node.byte_range()].to_string())
+                    .collect(),
+            })
+            .collect();
+        let position = node.start_position();
+        self.matches.borrow_mut().push(TracedMatch {
+            stanza: stanza.range.start,
+            kind: node.kind().to_string(),
+            position: Location {
+                row: position.row,
+                column: position.column,
+            },
+            captures,
+            statements: Vec::new(),
+        });
+    }
+
+    /// Records a statement of the last match, and returns its index, which identifies it when
+    /// recording the nodes that it created.
+    pub(crate) fn record_statement(&self, statement: &Statement, depth: usize) -> usize {
+        let mut matches = self.matches.borrow_mut();
+        let statements = &mut matches.last_mut().expect("missing match").statements;
+        statements.push(TracedStatement {
+            location: statement.location(),
+            depth,
+            statement: statement.to_string(),
+            values: Vec::new(),
+            nodes: Vec::new(),
+        });
+        statements.len() - 1
+    }
+
+    /// Records a value that the last statement resolved.  Nested blocks only run once their
+    /// statement has resolved all of its values, so the last statement is the one that is
+    /// running.
+    pub(crate) fn record_value(&self, label: String, value: &Value) {
+        if let Some(statement) = self
+            .matches
+            .borrow_mut()
+            .last_mut()
+            .and_then(|mat| mat.statements.last_mut())
+        {
+            statement.values.push(TracedValue {
+                label,
+                value: value.clone(),
+            });
+        }
+    }
+
+    /// Records the graph nodes that a statement created, skipping those that the statements of
+    /// its nested blocks already recorded.
+    pub(crate) fn record_nodes(&self, statement: usize, nodes: impl Iterator<Item = GraphNodeRef>) {
+        let mut matches = self.matches.borrow_mut();
+        let statements = &mut matches.last_mut().expect("missing match").statements;
+        let (statement, nested) = statements[statement..]
+            .split_first_mut()
+            .expect("missing statement");
+        statement
+            .nodes
+            .extend(nodes.filter(|node| !nested.iter().any(|nested| nested.nodes.contains(node))));
+    }
+}
+
+impl Serialize for ExecutionTrace {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.matches.borrow().serialize(serializer)
+    }
+}
+
+fn serialize_nodes<S: Serializer>(
+    nodes: &[GraphNodeRef],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(nodes.iter().map(|node| node.index()))
+}
@@ -0,0 +1,742 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Explains why the stanzas of a graph DSL file do not match a syntax node.
+//!
+//! The query pattern of each stanza is split into its steps, and tree-sitter checks them one at a
+//! time against the syntax node and its descendants: first the kind of a node, then its negated
+//! fields, then its children, one more at a time, and finally the predicates of the pattern.  The
+//! first step that fails is the one that is reported, so the explanation follows tree-sitter's own
+//! matching rules, including supertypes, quantifiers, and anchors.
+
+use std::fmt;
+use std::ops::Range;
+
+use serde::Serialize;
+use tree_sitter::Node;
+use tree_sitter::Query;
+use tree_sitter::QueryCursor;
+
+use crate::ast::File;
+use crate::parser::FULL_MATCH;
+use crate::Identifier;
+use crate::Location;
+
+/// The capture of the queries that check a single step, on the node that the step is checked
+/// against.
+const STEP_CAPTURE: &str = "__tsg__step";
+
+/// Whether one stanza matches a syntax node, and why not if it does not.
+#[derive(Clone, Debug, Serialize)]
+pub struct StanzaExplanation {
+    /// The location of the stanza in the graph DSL file
+    pub stanza: Location,
+    /// The name of the stanza, if it has one
+    pub name: Option<Identifier>,
+    /// Whether the stanza's query matches the syntax node
+    pub matched: bool,
+    /// The step of the stanza's query that fails, if the query does not match the syntax node
+    pub mismatch: Option<Mismatch>,
+}
+
+/// A step of a query pattern that does not match a syntax node.
+#[derive(Clone, Debug, Serialize)]
+pub struct Mismatch {
+    /// The step, as it is written in the graph DSL file
+    pub step: String,
+    /// The location of the step in the graph DSL file
+    pub location: Location,
+    /// The kind of the syntax node that the step does not match, which is the node that was
+    /// explained, or one of its descendants
+    pub kind: String,
+    /// The position of that syntax node in the source file
+    pub position: Location,
+    #[serde(flatten)]
+    pub reason: MismatchReason,
+}
+
+/// Why a step of a query pattern does not match a syntax node.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum MismatchReason {
+    /// The syntax node is not of the kind that the step expects.
+    Kind { expected: String },
+    /// The syntax node does not have a child in a field of the step.
+    MissingField { field: String },
+    /// The syntax node has a child in a field that the step negates.
+    ForbiddenField { field: String },
+    /// None of the children of the syntax node match the step, in the position that the earlier
+    /// steps leave for it.
+    MissingChild,
+    /// The children of the syntax node are not in the positions that an anchor requires.
+    Anchor,
+    /// None of the alternatives of the step match the syntax node.
+    NoAlternative,
+    /// A predicate is false for the captured syntax nodes.
+    Predicate,
+    /// The pattern uses syntax that cannot be explained.
+    Unsupported,
+    /// Every step of the pattern matches, but the pattern as a whole does not.
+    Unexplained,
+}
+
+impl File {
+    /// Explains whether each stanza of this file matches `node`, whose source text is `source`,
+    /// and which step of the stanza's query fails if it does not.  Stanzas are explained in the
+    /// order in which they appear in the file.
+    pub fn explain(&self, node: Node, source: &str) -> Vec<StanzaExplanation> {
+        self.stanzas
+            .iter()
+            .enumerate()
+            .map(|(index, stanza)| {
+                let mut cursor = QueryCursor::new();
+                cursor.set_max_start_depth(Some(0));
+                let full_match_index = stanza.full_match_stanza_capture_index as u32;
+                let matched = cursor
+                    .matches(&stanza.query, node, source.as_bytes())
+                    .any(|mat| {
+                        mat.captures.iter().any(|capture| {
+                            capture.index == full_match_index && capture.node == node
+                        })
+                    });
+                let mismatch = if matched {
+                    None
+                } else {
+                    let explainer = Explainer {
+                        file: self,
+                        pattern: self.stanza_pattern(index),
+                        location: stanza.range.start,
+                        source,
+                        predicates: Vec::new(),
+                    };
+                    Some(explainer.explain(node))
+                };
+                StanzaExplanation {
+                    stanza: stanza.range.start,
+                    name: stanza.name.clone(),
+                    matched,
+                    mismatch,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the query pattern of a stanza, as it is written in the file.
+    pub(crate) fn stanza_pattern(&self, index: usize) -> &str {
+        let query = self.query.as_ref().expect("missing file query");
+        let start = query.start_byte_for_pattern(index);
+        let end = if index + 1 < query.pattern_count() {
+            query.start_byte_for_pattern(index + 1)
+        } else {
+            self.query_source.len()
+        };
+        let pattern = &self.query_source[start..end];
+        let full_match = format!("@{}", FULL_MATCH);
+        match pattern.rfind(&full_match) {
+            Some(end) => pattern[..end].trim_end(),
+            None => pattern.trim_end(),
+        }
+    }
+}
+
+/// Returns the kinds of the syntax nodes that a query pattern can match, as pairs of a kind and
+/// whether it is named.  Returns nothing if the pattern can match nodes of any kind, such as `(_)`
+/// or a supertype like `(expression/identifier)`, or if it uses syntax that is not known here.
+#[cfg(feature = "execution")]
+pub(crate) fn pattern_root_kinds(pattern: &str) -> Option<Vec<(String, bool)>> {
+    let mut parser = PatternParser {
+        pattern,
+        offset: 0,
+        predicates: Vec::new(),
+    };
+    let root = parser.step().ok()?;
+    parser.skip_trivia();
+    if parser.offset != pattern.len() {
+        return None;
+    }
+    let mut kinds = Vec::new();
+    step_kinds(pattern, &root, &mut kinds)?;
+    Some(kinds)
+}
+
+/// Adds the kinds of the syntax nodes that a step can match to `kinds`.  Fails if the step can
+/// match nodes of any kind, or has a quantifier.
+#[cfg(feature = "execution")]
+fn step_kinds(pattern: &str, step: &Step, kinds: &mut Vec<(String, bool)>) -> Option<()> {
+    if pattern[step.body.end..step.range.end].contains(&['?', '*', '+'][..]) {
+        return None;
+    }
+    match &step.kind {
+        StepKind::Node { kind, .. } => {
+            let kind = &pattern[kind.clone()];
+            if kind == "_" || kind.contains('/') {
+                return None;
+            }
+            kinds.push((kind.to_string(), true));
+        }
+        StepKind::Leaf => {
+            let kind = pattern[step.body.clone()]
+                .strip_prefix('"')?
+                .strip_suffix('"')?;
+            if kind.contains('\\') {
+                return None;
+            }
+            kinds.push((kind.to_string(), false));
+        }
+        StepKind::Alternation(steps) => {
+            for step in steps {
+                step_kinds(pattern, step, kinds)?;
+            }
+        }
+        // A group of one step is how predicates are usually attached to a pattern.
+        StepKind::Group(items) => {
+            let mut steps = items.iter().filter_map(|item| match item {
+                Item::Step(step) => Some(step),
+                _ => None,
+            });
+            match (steps.next(), steps.next()) {
+                (Some(step), None) if !items.iter().any(|i| matches!(i, Item::Anchor(_))) => {
+                    step_kinds(pattern, step, kinds)?
+                }
+                _ => return None,
+            }
+        }
+    }
+    Some(())
+}
+
+/// A step of a query pattern, with the byte ranges of its parts in the pattern.
+struct Step {
+    /// The whole step, including its field name, quantifier, and captures
+    range: Range<usize>,
+    /// The step without its field name, quantifier, and captures
+    body: Range<usize>,
+    field: Option<String>,
+    kind: StepKind,
+}
+
+enum StepKind {
+    /// A parenthesized node, such as `(call ...)` or `(_ ...)`, with the range of its kind
+    Node {
+        kind: Range<usize>,
+        items: Vec<Item>,
+    },
+    /// A string, which matches an anonymous node, or the `_` wildcard
+    Leaf,
+    /// Bracketed alternatives
+    Alternation(Vec<Step>),
+    /// A parenthesized sequence of sibling patterns
+    Group(Vec<Item>),
+}
+
+/// Something that appears between the parentheses of a node or a group.
+enum Item {
+    Step(Step),
+    Anchor(Range<usize>),
+    NegatedField(Range<usize>, String),
+    Predicate(Range<usize>),
+}
+
+impl Item {
+    fn range(&self) -> Range<usize> {
+        match self {
+            Item::Step(step) => step.range.clone(),
+            Item::Anchor(range) | Item::NegatedField(range, _) | Item::Predicate(range) => {
+                range.clone()
+            }
+        }
+    }
+}
+
+/// Parses a query pattern into steps.  Fails on syntax that it does not know, which is reported
+/// as [`MismatchReason::Unsupported`][].
+struct PatternParser<'a> {
+    pattern: &'a str,
+    offset: usize,
+    predicates: Vec<Range<usize>>,
+}
+
+fn is_name(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '/'
+}
+
+impl<'a> PatternParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.pattern[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_trivia(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ';' {
+                while !matches!(self.next(), None | Some('\n')) {}
+            } else if c.is_whitespace() {
+                self.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn name(&mut self) -> Range<usize> {
+        let start = self.offset;
+        while self.peek().is_some_and(is_name) {
+            self.next();
+        }
+        start..self.offset
+    }
+
+    fn string(&mut self) -> Result<(), ()> {
+        self.next();
+        loop {
+            match self.next().ok_or(())? {
+                '\\' => {
+                    self.next();
+                }
+                '"' => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Parses a predicate, whose opening parenthesis is next, up to its closing parenthesis.
+    fn predicate(&mut self) -> Result<Range<usize>, ()> {
+        let start = self.offset;
+        self.next();
+        loop {
+            match self.peek().ok_or(())? {
+                ')' => break,
+                '"' => self.string()?,
+                _ => {
+                    self.next();
+                }
+            }
+        }
+        self.next();
+        self.predicates.push(start..self.offset);
+        Ok(start..self.offset)
+    }
+
+    /// Returns whether the next parenthesis starts a predicate.
+    fn at_predicate(&self) -> bool {
+        self.pattern[self.offset + 1..]
+            .trim_start()
+            .starts_with('#')
+    }
+
+    fn step(&mut self) -> Result<Step, ()> {
+        let start = self.offset;
+        let mut field = None;
+        let mut body_start = start;
+        let kind = match self.peek().ok_or(())? {
+            '(' => self.parenthesized()?,
+            '[' => self.alternation()?,
+            '"' => {
+                self.string()?;
+                StepKind::Leaf
+            }
+            c if is_name(c) => {
+                let name = self.name();
+                if &self.pattern[name.clone()] == "_" {
+                    StepKind::Leaf
+                } else if self.peek() == Some(':') {
+                    self.next();
+                    self.skip_trivia();
+                    field = Some(self.pattern[name].to_string());
+                    body_start = self.offset;
+                    match self.peek().ok_or(())? {
+                        '(' => self.parenthesized()?,
+                        '[' => self.alternation()?,
+                        '"' => {
+                            self.string()?;
+                            StepKind::Leaf
+                        }
+                        '_' => {
+                            self.next();
+                            StepKind::Leaf
+                        }
+                        _ => return Err(()),
+                    }
+                } else {
+                    return Err(());
+                }
+            }
+            _ => return Err(()),
+        };
+        let body = body_start..self.offset;
+        // Quantifiers and captures
+        loop {
+            let before = self.offset;
+            self.skip_trivia();
+            match self.peek() {
+                Some('?' | '*' | '+') => {
+                    self.next();
+                }
+                Some('@') => {
+                    self.next();
+                    self.name();
+                }
+                _ => {
+                    self.offset = before;
+                    break;
+                }
+            }
+        }
+        Ok(Step {
+            range: start..self.offset,
+            body,
+            field,
+            kind,
+        })
+    }
+
+    fn parenthesized(&mut self) -> Result<StepKind, ()> {
+        self.next();
+        self.skip_trivia();
+        if self.peek().is_some_and(is_name) {
+            let kind = self.name();
+            if &self.pattern[kind.clone()] == "MISSING" {
+                return Err(());
+            }
+            let items = self.items(')')?;
+            Ok(StepKind::Node { kind, items })
+        } else {
+            Ok(StepKind::Group(self.items(')')?))
+        }
+    }
+
+    fn alternation(&mut self) -> Result<StepKind, ()> {
+        self.next();
+        let mut alternatives = Vec::new();
+        loop {
+            self.skip_trivia();
+            match self.peek().ok_or(())? {
+                ']' => break,
+                '(' if self.at_predicate() => return Err(()),
+                _ => alternatives.push(self.step()?),
+            }
+        }
+        self.next();
+        Ok(StepKind::Alternation(alternatives))
+    }
+
+    fn items(&mut self, close: char) -> Result<Vec<Item>, ()> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia();
+            let start = self.offset;
+            match self.peek().ok_or(())? {
+                c if c == close => break,
+                '.' => {
+                    self.next();
+                    items.push(Item::Anchor(start..self.offset));
+                }
+                '!' => {
+                    self.next();
+                    let name = self.name();
+                    let field = self.pattern[name].to_string();
+                    items.push(Item::NegatedField(start..self.offset, field));
+                }
+                '(' if self.at_predicate() => items.push(Item::Predicate(self.predicate()?)),
+                _ => items.push(Item::Step(self.step()?)),
+            }
+        }
+        self.next();
+        Ok(items)
+    }
+}
+
+/// Checks the steps of a stanza's query pattern against a syntax node.
+struct Explainer<'a> {
+    file: &'a File,
+    pattern: &'a str,
+    /// The location of the pattern in the graph DSL file
+    location: Location,
+    source: &'a str,
+    predicates: Vec<Range<usize>>,
+}
+
+impl<'a> Explainer<'a> {
+    fn explain(mut self, node: Node) -> Mismatch {
+        let mut parser = PatternParser {
+            pattern: self.pattern,
+            offset: 0,
+            predicates: Vec::new(),
+        };
+        let root = parser.step();
+        if parser.offset < self.pattern.len() {
+            parser.skip_trivia();
+        }
+        let root = match root {
+            Ok(root) if parser.offset == self.pattern.len() => root,
+            _ => return self.mismatch(0..self.pattern.len(), node, MismatchReason::Unsupported),
+        };
+        self.predicates = parser.predicates;
+
+        // A group of one step is how predicates are usually attached to a pattern.
+        let step = match &root.kind {
+            StepKind::Group(items) => {
+                let mut steps = items.iter().filter_map(|item| match item {
+                    Item::Step(step) => Some(step),
+                    _ => None,
+                });
+                match (steps.next(), steps.next()) {
+                    (Some(step), None) if !items.iter().any(|i| matches!(i, Item::Anchor(_))) => {
+                        step
+                    }
+                    _ => &root,
+                }
+            }
+            _ => &root,
+        };
+        if let Some(mismatch) = self.explain_step(step, node) {
+            return mismatch;
+        }
+        for (index, predicate) in self.predicates.iter().enumerate() {
+            let text = self.render(root.body.clone(), &self.predicates[index + 1..]);
+            if !self.matches(&text, node) {
+                return self.mismatch(predicate.clone(), node, MismatchReason::Predicate);
+            }
+        }
+        self.mismatch(0..self.pattern.len(), node, MismatchReason::Unexplained)
+    }
+
+    /// Returns why `step` does not match `node`, or nothing if it does.  Predicates are ignored.
+    fn explain_step(&self, step: &Step, node: Node) -> Option<Mismatch> {
+        match &step.kind {
+            StepKind::Node { kind, items } => {
+                if !self.head_matches(step, node) {
+                    let expected = self.pattern[kind.clone()].to_string();
+                    return Some(self.mismatch(
+                        step.range.clone(),
+                        node,
+                        MismatchReason::Kind { expected },
+                    ));
+                }
+                for item in items {
+                    if let Item::NegatedField(range, field) = item {
+                        let text = format!(
+                            "({} {})",
+                            &self.pattern[kind.clone()],
+                            &self.pattern[range.clone()]
+                        );
+                        if !self.matches(&text, node) {
+                            let field = field.clone();
+                            return Some(self.mismatch(
+                                range.clone(),
+                                node,
+                                MismatchReason::ForbiddenField { field },
+                            ));
+                        }
+                    }
+                }
+                for (index, item) in items.iter().enumerate() {
+                    let child = match item {
+                        Item::Step(child) => child,
+                        _ => continue,
+                    };
+                    let later = items[index + 1..]
+                        .iter()
+                        .map(Item::range)
+                        .collect::<Vec<_>>();
+                    if !self.matches(&self.render_structure(step.body.clone(), &later), node) {
+                        return Some(self.explain_child(child, node));
+                    }
+                }
+                if !self.matches(&self.render_structure(step.body.clone(), &[]), node) {
+                    let anchor = items.iter().rev().find_map(|item| match item {
+                        Item::Anchor(range) => Some(range.clone()),
+                        _ => None,
+                    });
+                    let range = anchor.unwrap_or_else(|| step.range.clone());
+                    return Some(self.mismatch(range, node, MismatchReason::Anchor));
+                }
+                None
+            }
+            StepKind::Leaf => {
+                if self.head_matches(step, node) {
+                    return None;
+                }
+                let expected = self.pattern[step.body.clone()].to_string();
+                Some(self.mismatch(step.range.clone(), node, MismatchReason::Kind { expected }))
+            }
+            StepKind::Alternation(alternatives) => {
+                if self.matches(&self.render_structure(step.body.clone(), &[]), node) {
+                    return None;
+                }
+                alternatives
+                    .iter()
+                    .filter(|alternative| self.head_matches(alternative, node))
+                    .find_map(|alternative| self.explain_step(alternative, node))
+                    .or_else(|| {
+                        Some(self.mismatch(step.range.clone(), node, MismatchReason::NoAlternative))
+                    })
+            }
+            StepKind::Group(_) => {
+                Some(self.mismatch(step.range.clone(), node, MismatchReason::Unsupported))
+            }
+        }
+    }
+
+    /// Returns why no child of `parent` matches `step`, which is a step for one of its children.
+    fn explain_child(&self, step: &Step, parent: Node) -> Mismatch {
+        let mut cursor = parent.walk();
+        let candidates = match &step.field {
+            Some(field) => {
+                if parent.child_by_field_name(field).is_none() {
+                    let field = field.clone();
+                    return self.mismatch(
+                        step.range.clone(),
+                        parent,
+                        MismatchReason::MissingField { field },
+                    );
+                }
+                parent
+                    .children_by_field_name(field, &mut cursor)
+                    .collect::<Vec<_>>()
+            }
+            None => parent.children(&mut cursor).collect::<Vec<_>>(),
+        };
+        candidates
+            .into_iter()
+            .filter(|candidate| self.head_matches(step, *candidate))
+            .find_map(|candidate| self.explain_step(step, candidate))
+            .unwrap_or_else(|| {
+                self.mismatch(step.range.clone(), parent, MismatchReason::MissingChild)
+            })
+    }
+
+    /// Returns whether `node` has the kind that `step` expects, ignoring the step's children.
+    fn head_matches(&self, step: &Step, node: Node) -> bool {
+        match &step.kind {
+            StepKind::Node { kind, .. } => {
+                self.matches(&format!("({})", &self.pattern[kind.clone()]), node)
+            }
+            StepKind::Leaf => self.matches(&self.pattern[step.body.clone()], node),
+            StepKind::Alternation(alternatives) => alternatives
+                .iter()
+                .any(|alternative| self.head_matches(alternative, node)),
+            StepKind::Group(_) => false,
+        }
+    }
+
+    /// Returns whether the query pattern `text` matches `node` itself.  Patterns that tree-sitter
+    /// rejects are assumed to match, so that they are not blamed for a mismatch.
+    fn matches(&self, text: &str, node: Node) -> bool {
+        let query = match Query::new(&self.file.language, &format!("{} @{}", text, STEP_CAPTURE)) {
+            Ok(query) => query,
+            Err(_) => return true,
+        };
+        let index = query
+            .capture_index_for_name(STEP_CAPTURE)
+            .expect("missing step capture");
+        let mut cursor = QueryCursor::new();
+        cursor.set_max_start_depth(Some(0));
+        let matched = cursor
+            .matches(&query, node, self.source.as_bytes())
+            .any(|mat| {
+                mat.captures
+                    .iter()
+                    .any(|capture| capture.index == index && capture.node == node)
+            });
+        matched
+    }
+
+    /// Returns the part of the pattern in `range`, without the parts in `excluded`.
+    fn render(&self, range: Range<usize>, excluded: &[Range<usize>]) -> String {
+        let mut excluded = excluded
+            .iter()
+            .filter(|excluded| excluded.start >= range.start && excluded.end <= range.end)
+            .cloned()
+            .collect::<Vec<_>>();
+        excluded.sort_by_key(|excluded| excluded.start);
+        let mut text = String::new();
+        let mut offset = range.start;
+        for excluded in excluded {
+            if excluded.start < offset {
+                continue;
+            }
+            text += &self.pattern[offset..excluded.start];
+            text += " ";
+            offset = excluded.end;
+        }
+        text += &self.pattern[offset..range.end];
+        text
+    }
+
+    /// Returns the part of the pattern in `range`, without its predicates and the parts in
+    /// `excluded`.
+    fn render_structure(&self, range: Range<usize>, excluded: &[Range<usize>]) -> String {
+        let excluded = self
+            .predicates
+            .iter()
+            .chain(excluded)
+            .cloned()
+            .collect::<Vec<_>>();
+        self.render(range, &excluded)
+    }
+
+    fn mismatch(&self, range: Range<usize>, node: Node, reason: MismatchReason) -> Mismatch {
+        let step = self.pattern[range.clone()]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let before = &self.pattern[..range.start];
+        let location = match before.rfind('\n') {
+            Some(newline) => Location {
+                row: self.location.row + before.matches('\n').count(),
+                column: before[newline + 1..].chars().count(),
+            },
+            None => Location {
+                row: self.location.row,
+                column: self.location.column + before.chars().count(),
+            },
+        };
+        let position = node.start_position();
+        Mismatch {
+            step,
+            location,
+            kind: node.kind().to_string(),
+            position: Location {
+                row: position.row,
+                column: position.column,
+            },
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {} does not match ({}) at {}: ",
+            self.step, self.location, self.kind, self.position
+        )?;
+        match &self.reason {
+            MismatchReason::Kind { expected } => write!(f, "expected {}", expected),
+            MismatchReason::MissingField { field } => write!(f, "it has no {} field", field),
+            MismatchReason::ForbiddenField { field } => write!(f, "it has a {} field", field),
+            MismatchReason::MissingChild => write!(f, "none of its children match"),
+            MismatchReason::Anchor => write!(f, "its children are not in the anchored positions"),
+            MismatchReason::NoAlternative => write!(f, "none of the alternatives match"),
+            MismatchReason::Predicate => write!(f, "the predicate is false"),
+            MismatchReason::Unsupported => {
+                write!(f, "the pattern uses syntax that cannot be explained")
+            }
+            MismatchReason::Unexplained => {
+                write!(f, "every step matches, but the pattern as a whole does not")
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Statements that host applications add to the graph DSL.
+//!
+//! An extension statement starts with the keyword that the extension was registered with, which
+//! is followed by a comma-separated list of argument expressions, on the same line as the
+//! keyword:
+//!
+//! ``` tsg
+//! (function_definition name: (identifier) @name) {
+//!   node @name.def
+//!   push_symbol @name.def, (source-text @name)
+//! }
+//! ```
+//!
+//! Extensions are registered in an [`Extensions`][] library, which must be given both when
+//! parsing the file, with [`File::from_str_with_extensions`][from_str_with_extensions], and when
+//! executing it, with [`ExecutionConfig::extensions`][crate::ExecutionConfig::extensions].
+//! The checker checks the arguments like any other expressions, and then calls the
+//! [`check`][StatementExtension::check] method of the extension.
+//!
+//! Lazy execution runs extension statements after all graph nodes, edges, and attributes have
+//! been created, in the order in which they were executed.
+//!
+//! [from_str_with_extensions]: crate::ast::File::from_str_with_extensions
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[cfg(feature = "execution")]
+use tracing::trace;
+
+use crate::ast;
+#[cfg(feature = "execution")]
+use crate::execution::error::ExecutionError;
+#[cfg(feature = "execution")]
+use crate::functions::Parameters;
+#[cfg(feature = "execution")]
+use crate::graph::Graph;
+use crate::Identifier;
+
+/// A statement that a host application adds to the graph DSL.
+pub trait StatementExtension {
+    /// Checks a statement, after its arguments have been checked.  Returns a message that
+    /// describes why the statement is invalid, if it is.
+    fn check(&self, statement: &ast::Extension) -> Result<(), String> {
+        let _ = statement;
+        Ok(())
+    }
+
+    /// Executes a statement, with the values of its arguments.  You have access to the graph, as
+    /// it has been constructed up to the point of the statement, as well as the text content of
+    /// the source file that's being processed.
+    #[cfg(feature = "execution")]
+    fn execute(
+        &self,
+        graph: &mut Graph,
+        source: &str,
+        arguments: &mut dyn Parameters,
+    ) -> Result<(), ExecutionError>;
+}
+
+/// A library of statement extensions, by keyword.
+#[derive(Default)]
+pub struct Extensions {
+    extensions: HashMap<Identifier, Arc<dyn StatementExtension + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Creates a new, empty library of statement extensions.
+    pub fn new() -> Extensions {
+        Extensions::default()
+    }
+
+    /// Adds a new statement extension to this library.  The keyword must not be one of the
+    /// keywords of the graph DSL, which take precedence over it.
+    pub fn add<E>(&mut self, keyword: Identifier, extension: E)
+    where
+        E: StatementExtension + Send + Sync + 'static,
+    {
+        self.extensions.insert(keyword, Arc::new(extension));
+    }
+
+    /// Returns whether there is a statement extension with the given keyword in this library.
+    pub fn contains(&self, keyword: &str) -> bool {
+        self.extensions.contains_key(keyword)
+    }
+
+    /// Returns the keywords of the statement extensions in this library, in no particular order.
+    pub fn keywords(&self) -> impl Iterator<Item = &Identifier> {
+        self.extensions.keys()
+    }
+
+    /// Checks an extension statement, returning an error if there is no extension with its
+    /// keyword.
+    pub(crate) fn check(&self, statement: &ast::Extension) -> Result<(), String> {
+        match self.extensions.get(&statement.keyword) {
+            Some(extension) => extension.check(statement),
+            None => Err("undefined statement".to_string()),
+        }
+    }
+
+    /// Returns the statement extension with the given keyword, returning an error if there is
+    /// none.
+    #[cfg(feature = "execution")]
+    pub(crate) fn get(
+        &self,
+        keyword: &Identifier,
+    ) -> Result<Arc<dyn StatementExtension + Send + Sync>, ExecutionError> {
+        self.extensions
+            .get(keyword)
+            .cloned()
+            .ok_or_else(|| ExecutionError::UndefinedStatement(keyword.to_string()))
+    }
+
+    /// Executes an extension statement, returning an error if there is no extension with its
+    /// keyword.
+    #[cfg(feature = "execution")]
+    pub(crate) fn execute(
+        &self,
+        keyword: &Identifier,
+        graph: &mut Graph,
+        source: &str,
+        arguments: &mut dyn Parameters,
+    ) -> Result<(), ExecutionError> {
+        let extension = self.get(keyword)?;
+        trace!("execute {}", keyword);
+        extension.execute(graph, source, arguments)
+    }
+}
@@ -0,0 +1,154 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines the canonical formatting of graph DSL files.
+
+use crate::ast;
+use crate::Location;
+use crate::ParseError;
+
+/// The number of spaces that each level of nesting is indented by.
+const INDENT: usize = 2;
+
+impl ast::File {
+    /// Formats the source of a graph DSL file in the canonical style.  Every line is indented by
+    /// two spaces for each bracket, parenthesis, or brace that is open at its start.  Runs of
+    /// whitespace within a line are collapsed to a single space, with none just inside brackets
+    /// and parentheses, after the `!` of a false flag attribute, or before commas.  Consecutive
+    /// blank lines are collapsed to one, blank lines at the start and end of a block are removed,
+    /// and the result ends with a single newline.  Comments and the contents of strings are kept
+    /// as is.
+    ///
+    /// Formatting is idempotent, and does not need the file's language, so the query patterns in
+    /// the file are not checked.  It fails if the brackets in the file are unbalanced, or if a
+    /// string is not terminated.
+    pub fn format(source: &str) -> Result<String, ParseError> {
+        let mut formatter = Formatter::default();
+        for (row, line) in source.lines().enumerate() {
+            formatter.format_line(row, line)?;
+        }
+        formatter.finish()
+    }
+}
+
+#[derive(Default)]
+struct Formatter {
+    output: String,
+    /// The brackets that are currently open, and where they were opened.
+    open: Vec<(char, Location)>,
+    /// Where the string that continues onto the next line started, if any.
+    in_string: Option<Location>,
+    blank_line: bool,
+}
+
+impl Formatter {
+    fn format_line(&mut self, row: usize, line: &str) -> Result<(), ParseError> {
+        let location = |column: usize| Location {
+            row,
+            column: line[..column].chars().count(),
+        };
+        let mut chars = line.char_indices();
+        let mut formatted = String::new();
+        if self.in_string.is_some() {
+            // The line continues a multiline string, so its indentation is part of the string.
+            formatted += &self.finish_string(&mut chars);
+        } else {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() {
+                self.blank_line = !self.output.is_empty();
+                return Ok(());
+            }
+            let leading_closers = trimmed
+                .chars()
+                .take_while(|ch| matches!(ch, ')' | ']' | '}'))
+                .count();
+            // Blank lines are dropped at the start and end of a block.
+            let after_opener = self.output.trim_end().ends_with(&['(', '[', '{'][..]);
+            if std::mem::take(&mut self.blank_line) && leading_closers == 0 && !after_opener {
+                self.output.push('\n');
+            }
+            let depth = self.open.len().saturating_sub(leading_closers);
+            self.output += &" ".repeat(depth * INDENT);
+        }
+
+        let mut space = false;
+        while let Some((column, ch)) = chars.next() {
+            if ch.is_whitespace() {
+                space = !formatted.is_empty();
+                continue;
+            }
+            if std::mem::take(&mut space)
+                && !formatted.ends_with(&['(', '[', '!'][..])
+                && !matches!(ch, ')' | ']' | ',')
+            {
+                formatted.push(' ');
+            }
+            match ch {
+                ';' => {
+                    formatted += line[column..].trim_end();
+                    break;
+                }
+                '"' => {
+                    formatted.push(ch);
+                    self.in_string = Some(location(column));
+                    formatted += &self.finish_string(&mut chars);
+                    continue;
+                }
+                '(' | '[' | '{' => self.open.push((ch, location(column))),
+                ')' | ']' | '}' => {
+                    let expected = match self.open.pop() {
+                        Some(('(', _)) => Some(')'),
+                        Some(('[', _)) => Some(']'),
+                        Some(_) => Some('}'),
+                        None => None,
+                    };
+                    if expected != Some(ch) {
+                        return Err(ParseError::UnexpectedCharacter(
+                            ch,
+                            "file",
+                            location(column),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+            formatted.push(ch);
+        }
+        self.output += &formatted;
+        self.output.push('\n');
+        Ok(())
+    }
+
+    /// Copies the rest of a string from `chars`, up to and including its closing quote.  If the
+    /// string does not end on this line, the whole rest of the line is copied.
+    fn finish_string(&mut self, chars: &mut std::str::CharIndices) -> String {
+        let mut result = String::new();
+        let mut escape = false;
+        for (_, ch) in chars.by_ref() {
+            result.push(ch);
+            if escape {
+                escape = false;
+            } else if ch == '\\' {
+                escape = true;
+            } else if ch == '"' {
+                self.in_string = None;
+                break;
+            }
+        }
+        result
+    }
+
+    fn finish(self) -> Result<String, ParseError> {
+        if let Some(location) = self.in_string {
+            return Err(ParseError::UnexpectedEOF(location));
+        }
+        if let Some((_, location)) = self.open.last() {
+            return Err(ParseError::UnexpectedEOF(*location));
+        }
+        Ok(self.output)
+    }
+}
@@ -10,10 +10,20 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use tracing::trace;
+use tree_sitter::Language;
+use tree_sitter::Query;
+use tree_sitter::QueryError;
+use tree_sitter::QueryMatch;
+
+use crate::ast::Stanza;
 use crate::execution::error::ExecutionError;
 use crate::graph::Graph;
+use crate::graph::SyntaxNodeRef;
 use crate::graph::Value;
+use crate::parser::FULL_MATCH;
 use crate::Identifier;
+use crate::Location;
 
 /// The implementation of a function that can be called from the graph DSL.
 ///
@@ -22,7 +32,8 @@ use crate::Identifier;
 ///
 /// Any other data that you need must be passed in as a parameter to the function.  You can use the
 /// [`Parameters`][] trait to consume those parameters and verify that you received the correct
-/// number and type of them.
+/// number and type of them.  Functions that need to know where they are called from can implement
+/// [`call_in_context`][Function::call_in_context] as well.
 pub trait Function {
     fn call(
         &self,
@@ -30,6 +41,28 @@ pub trait Function {
         source: &str,
         parameters: &mut dyn Parameters,
     ) -> Result<Value, ExecutionError>;
+
+    /// Calls this function from the statements of a stanza, with the [context][CallContext] of
+    /// the call: the stanza, and the match that it is executing on.  The default implementation
+    /// ignores the context and calls [`call`][Function::call], which is still used for calls
+    /// that are made outside of stanzas, such as the calls in stanza guards.
+    fn call_in_context(
+        &self,
+        _context: &CallContext,
+        graph: &mut Graph,
+        source: &str,
+        parameters: &mut dyn Parameters,
+    ) -> Result<Value, ExecutionError> {
+        self.call(graph, source, parameters)
+    }
+
+    /// Returns whether this function is pure: its result only depends on its parameters, and it
+    /// does not change the graph.  Calls of pure functions whose parameters are constants are
+    /// evaluated ahead of time by [`File::optimize`][crate::ast::File::optimize].  Functions are
+    /// not pure unless they say so.
+    fn is_pure(&self) -> bool {
+        false
+    }
 }
 
 /// A helper trait for consuming the parameters of a function.  You will typically use it as
@@ -83,10 +116,73 @@ where
     }
 }
 
+/// The context of a function call that a stanza makes: the stanza, and the captures of the match
+/// that it is executing on.  Together with the graph, which contains everything that the
+/// execution created so far, this lets functions inspect their surroundings without any global
+/// state.
+#[derive(Clone, Debug)]
+pub struct CallContext {
+    stanza_name: Option<Identifier>,
+    stanza_location: Location,
+    captures: Vec<(Identifier, SyntaxNodeRef)>,
+}
+
+impl CallContext {
+    /// Creates the context of the calls that a stanza makes on a match of a query with the given
+    /// capture names.
+    pub(crate) fn new<'tree>(
+        stanza: &Stanza,
+        capture_names: &[&str],
+        mat: &QueryMatch<'_, 'tree>,
+        graph: &mut Graph<'tree>,
+    ) -> CallContext {
+        let captures = mat
+            .captures
+            .iter()
+            .filter(|capture| capture_names[capture.index as usize] != FULL_MATCH)
+            .map(|capture| {
+                let name = Identifier::from(capture_names[capture.index as usize]);
+                (name, graph.add_syntax_node(capture.node))
+            })
+            .collect();
+        CallContext {
+            stanza_name: stanza.name.clone(),
+            stanza_location: stanza.range.start,
+            captures,
+        }
+    }
+
+    /// Returns the name of the stanza that made the call, if it was given one.
+    pub fn stanza_name(&self) -> Option<&Identifier> {
+        self.stanza_name.as_ref()
+    }
+
+    /// Returns the location of the stanza that made the call.
+    pub fn stanza_location(&self) -> Location {
+        self.stanza_location
+    }
+
+    /// Returns the captures of the match, in the order of their syntax nodes.  Quantified captures
+    /// appear once for each node that they captured.
+    pub fn captures(&self) -> impl Iterator<Item = (&Identifier, SyntaxNodeRef)> {
+        self.captures.iter().map(|(name, node)| (name, *node))
+    }
+
+    /// Returns the first syntax node that the capture with the given name captured, if any.
+    pub fn capture(&self, name: &str) -> Option<SyntaxNodeRef> {
+        self.captures
+            .iter()
+            .find(|(capture, _)| capture.as_str() == name)
+            .map(|(_, node)| *node)
+    }
+}
+
 /// A library of named functions.
 #[derive(Default)]
 pub struct Functions {
     functions: HashMap<Identifier, Arc<dyn Function + Send + Sync>>,
+    /// The namespace of each function that was added to one
+    namespaces: HashMap<Identifier, String>,
 }
 
 impl Functions {
@@ -100,42 +196,141 @@ impl Functions {
     pub fn stdlib() -> Functions {
         let mut functions = Functions::new();
         // general functions
-        functions.add(Identifier::from("eq"), stdlib::Eq);
-        functions.add(Identifier::from("is-null"), stdlib::IsNull);
+        functions.add_to_namespace("general", Identifier::from("eq"), stdlib::Eq);
+        functions.add_to_namespace("general", Identifier::from("is-null"), stdlib::IsNull);
         // tree functions
-        functions.add(
+        functions.add_to_namespace(
+            "syntax",
             Identifier::from("named-child-index"),
             stdlib::syntax::NamedChildIndex,
         );
-        functions.add(Identifier::from("source-text"), stdlib::syntax::SourceText);
-        functions.add(Identifier::from("start-row"), stdlib::syntax::StartRow);
-        functions.add(
+        functions.add_to_namespace(
+            "syntax",
+            Identifier::from("source-text"),
+            stdlib::syntax::SourceText,
+        );
+        functions.add_to_namespace(
+            "syntax",
+            Identifier::from("start-row"),
+            stdlib::syntax::StartRow,
+        );
+        functions.add_to_namespace(
+            "syntax",
             Identifier::from("start-column"),
             stdlib::syntax::StartColumn,
         );
-        functions.add(Identifier::from("end-row"), stdlib::syntax::EndRow);
-        functions.add(Identifier::from("end-column"), stdlib::syntax::EndColumn);
-        functions.add(Identifier::from("node-type"), stdlib::syntax::NodeType);
-        functions.add(
+        functions.add_to_namespace(
+            "syntax",
+            Identifier::from("end-row"),
+            stdlib::syntax::EndRow,
+        );
+        functions.add_to_namespace(
+            "syntax",
+            Identifier::from("end-column"),
+            stdlib::syntax::EndColumn,
+        );
+        functions.add_to_namespace(
+            "syntax",
+            Identifier::from("node-type"),
+            stdlib::syntax::NodeType,
+        );
+        functions.add_to_namespace(
+            "syntax",
+            Identifier::from("node-kind-matches?"),
+            stdlib::syntax::NodeKindMatches,
+        );
+        functions.add_to_namespace(
+            "syntax",
             Identifier::from("named-child-count"),
             stdlib::syntax::NamedChildCount,
         );
         // graph functions
-        functions.add(Identifier::from("node"), stdlib::graph::Node);
+        functions.add_to_namespace("graph", Identifier::from("node"), stdlib::graph::Node);
+        functions.add_to_namespace(
+            "graph",
+            Identifier::from("external-node"),
+            stdlib::graph::ExternalNode,
+        );
         // boolean functions
-        functions.add(Identifier::from("not"), stdlib::bool::Not);
-        functions.add(Identifier::from("and"), stdlib::bool::And);
-        functions.add(Identifier::from("or"), stdlib::bool::Or);
+        functions.add_to_namespace("bool", Identifier::from("not"), stdlib::bool::Not);
+        functions.add_to_namespace("bool", Identifier::from("and"), stdlib::bool::And);
+        functions.add_to_namespace("bool", Identifier::from("or"), stdlib::bool::Or);
         // math functions
-        functions.add(Identifier::from("plus"), stdlib::math::Plus);
+        functions.add_to_namespace("math", Identifier::from("plus"), stdlib::math::Plus);
+        functions.add_to_namespace("math", Identifier::from("min"), stdlib::math::Min);
+        functions.add_to_namespace("math", Identifier::from("max"), stdlib::math::Max);
+        functions.add_to_namespace("math", Identifier::from("abs"), stdlib::math::Abs);
+        functions.add_to_namespace("math", Identifier::from("clamp"), stdlib::math::Clamp);
+        functions.add_to_namespace("math", Identifier::from("pow"), stdlib::math::Pow);
+        functions.add_to_namespace("math", Identifier::from("random"), stdlib::math::Random);
         // string functions
-        functions.add(Identifier::from("format"), stdlib::string::Format);
-        functions.add(Identifier::from("replace"), stdlib::string::Replace);
+        functions.add_to_namespace("string", Identifier::from("format"), stdlib::string::Format);
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("replace"),
+            stdlib::string::Replace,
+        );
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("base64-encode"),
+            stdlib::string::Base64Encode,
+        );
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("base64-decode"),
+            stdlib::string::Base64Decode,
+        );
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("uri-encode"),
+            stdlib::string::UriEncode,
+        );
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("uri-decode"),
+            stdlib::string::UriDecode,
+        );
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("edit-distance"),
+            stdlib::string::EditDistance,
+        );
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("fuzzy-match?"),
+            stdlib::string::FuzzyMatch,
+        );
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("unicode-nfc"),
+            stdlib::string::UnicodeNfc,
+        );
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("unicode-casefold"),
+            stdlib::string::UnicodeCasefold,
+        );
+        functions.add_to_namespace(
+            "string",
+            Identifier::from("substring"),
+            stdlib::string::Substring,
+        );
+        // JSON functions
+        functions.add_to_namespace(
+            "json",
+            Identifier::from("json-parse"),
+            stdlib::json::JsonParse,
+        );
+        functions.add_to_namespace(
+            "json",
+            Identifier::from("json-stringify"),
+            stdlib::json::JsonStringify,
+        );
         // list functions
-        functions.add(Identifier::from("concat"), stdlib::list::Concat);
-        functions.add(Identifier::from("is-empty"), stdlib::list::IsEmpty);
-        functions.add(Identifier::from("join"), stdlib::list::Join);
-        functions.add(Identifier::from("length"), stdlib::list::Length);
+        functions.add_to_namespace("list", Identifier::from("concat"), stdlib::list::Concat);
+        functions.add_to_namespace("list", Identifier::from("is-empty"), stdlib::list::IsEmpty);
+        functions.add_to_namespace("list", Identifier::from("join"), stdlib::list::Join);
+        functions.add_to_namespace("list", Identifier::from("length"), stdlib::list::Length);
         functions
     }
 
@@ -144,9 +339,69 @@ impl Functions {
     where
         F: Function + Send + Sync + 'static,
     {
+        self.namespaces.remove(&name);
+        self.functions.insert(name, Arc::new(function));
+    }
+
+    /// Adds a new function to this library, in a namespace.  Graph DSL files can declare the
+    /// namespaces that they use with a [`uses`][`crate::reference#declaring-the-functions-a-file-uses`]
+    /// declaration.
+    pub fn add_to_namespace<F>(&mut self, namespace: &str, name: Identifier, function: F)
+    where
+        F: Function + Send + Sync + 'static,
+    {
+        self.namespaces.insert(name.clone(), namespace.to_string());
         self.functions.insert(name, Arc::new(function));
     }
 
+    /// Returns whether this library has any functions in the given namespace.
+    pub fn has_namespace(&self, namespace: &str) -> bool {
+        self.namespaces
+            .values()
+            .any(|existing| existing == namespace)
+    }
+
+    /// Returns the namespace of a function in this library, if it was added to one.
+    pub fn namespace(&self, name: &Identifier) -> Option<&str> {
+        self.namespaces.get(name).map(String::as_str)
+    }
+
+    /// Returns whether this library has a function with the given name.
+    pub fn contains(&self, name: &Identifier) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Adds the [`highlight-of`][`crate::reference::functions#highlight-of`] function to this
+    /// library, which classifies syntax nodes with the captures of a highlights query, such as the
+    /// `highlights.scm` file of a tree-sitter grammar.  Graph DSL files can then reuse the
+    /// language's existing classification instead of repeating its query patterns.
+    pub fn add_highlights(
+        &mut self,
+        language: &Language,
+        highlights_query: &str,
+    ) -> Result<(), QueryError> {
+        let query = Query::new(language, highlights_query)?;
+        self.add_to_namespace(
+            "syntax",
+            Identifier::from("highlight-of"),
+            stdlib::syntax::HighlightOf::new(query),
+        );
+        Ok(())
+    }
+
+    /// Returns the names of the functions in this library, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &Identifier> {
+        self.functions.keys()
+    }
+
+    /// Returns whether this library has a function with the given name, and that function is
+    /// [pure][Function::is_pure].
+    pub fn is_pure(&self, name: &Identifier) -> bool {
+        self.functions
+            .get(name)
+            .is_some_and(|function| function.is_pure())
+    }
+
     /// Calls a named function, returning an error if there is no function with that name.
     pub fn call(
         &self,
@@ -154,12 +409,151 @@ impl Functions {
         graph: &mut Graph,
         source: &str,
         parameters: &mut dyn Parameters,
+    ) -> Result<Value, ExecutionError> {
+        self.call_in(None, name, graph, source, parameters)
+    }
+
+    /// Calls a named function with the context of the call, returning an error if there is no
+    /// function with that name.
+    pub fn call_in_context(
+        &self,
+        context: &CallContext,
+        name: &Identifier,
+        graph: &mut Graph,
+        source: &str,
+        parameters: &mut dyn Parameters,
+    ) -> Result<Value, ExecutionError> {
+        self.call_in(Some(context), name, graph, source, parameters)
+    }
+
+    fn call_in(
+        &self,
+        context: Option<&CallContext>,
+        name: &Identifier,
+        graph: &mut Graph,
+        source: &str,
+        parameters: &mut dyn Parameters,
     ) -> Result<Value, ExecutionError> {
         let function = self
             .functions
             .get(name)
             .ok_or(ExecutionError::UndefinedFunction(format!("{}", name)))?;
-        function.call(graph, source, parameters)
+        trace!("call {}", name);
+        match context {
+            Some(context) => function.call_in_context(context, graph, source, parameters),
+            None => function.call(graph, source, parameters),
+        }
+    }
+
+    /// Calls a named function, with the context of the call if it was made by a stanza.  If there
+    /// is no function with that name in this library, falls back on the environment functions, if
+    /// they have been enabled.
+    pub(crate) fn call_with_environment(
+        &self,
+        environment: Option<&Environment>,
+        context: Option<&CallContext>,
+        name: &Identifier,
+        graph: &mut Graph,
+        source: &str,
+        parameters: &mut dyn Parameters,
+    ) -> Result<Value, ExecutionError> {
+        if self.functions.contains_key(name) {
+            return self.call_in(context, name, graph, source, parameters);
+        }
+        match environment {
+            Some(environment) => environment
+                .functions
+                .call_in(context, name, graph, source, parameters),
+            None if Environment::FUNCTION_NAMES.contains(&name.as_str()) => {
+                Err(ExecutionError::FunctionFailed(
+                    name.to_string(),
+                    "access to the host environment is not enabled".into(),
+                ))
+            }
+            None => self.call_in(context, name, graph, source, parameters),
+        }
+    }
+}
+
+/// Access to the host environment, which is exposed to graph DSL files via the [`env`][],
+/// [`config-get`][], [`file-exists?`][], [`now-iso8601`][], and [`unix-timestamp`][] functions.
+/// These functions are not available unless you enable them via [`ExecutionConfig::environment`][].
+///
+/// The time functions read the system clock by default.  Use [`fixed_time`][Self::fixed_time] to
+/// pin them to a particular time, so that executions are reproducible.
+///
+/// [`env`]: crate::reference::functions#env
+/// [`config-get`]: crate::reference::functions#config-get
+/// [`file-exists?`]: crate::reference::functions#file-exists
+/// [`now-iso8601`]: crate::reference::functions#now-iso8601
+/// [`unix-timestamp`]: crate::reference::functions#unix-timestamp
+/// [`ExecutionConfig::environment`]: crate::ExecutionConfig::environment
+pub struct Environment {
+    functions: Functions,
+}
+
+impl Environment {
+    const FUNCTION_NAMES: [&'static str; 5] = [
+        "env",
+        "config-get",
+        "file-exists?",
+        "now-iso8601",
+        "unix-timestamp",
+    ];
+
+    /// Creates a new environment, whose configuration values can be looked up with the
+    /// [`config-get`][`crate::reference::functions#config-get`] function.
+    pub fn new(config: HashMap<String, Value>) -> Environment {
+        let mut functions = Functions::new();
+        functions.add_to_namespace("env", Identifier::from("env"), stdlib::env::Env);
+        functions.add_to_namespace(
+            "env",
+            Identifier::from("config-get"),
+            stdlib::env::ConfigGet::new(config),
+        );
+        functions.add_to_namespace(
+            "env",
+            Identifier::from("file-exists?"),
+            stdlib::env::FileExists,
+        );
+        functions.add_to_namespace(
+            "env",
+            Identifier::from("now-iso8601"),
+            stdlib::env::NowIso8601::new(None),
+        );
+        functions.add_to_namespace(
+            "env",
+            Identifier::from("unix-timestamp"),
+            stdlib::env::UnixTimestamp::new(None),
+        );
+        Environment { functions }
+    }
+
+    /// Returns the environment functions.
+    pub(crate) fn functions(&self) -> &Functions {
+        &self.functions
+    }
+
+    /// Pins the time functions to the given time, expressed as the number of seconds since the
+    /// Unix epoch.
+    pub fn fixed_time(mut self, unix_timestamp: u64) -> Self {
+        self.functions.add_to_namespace(
+            "env",
+            Identifier::from("now-iso8601"),
+            stdlib::env::NowIso8601::new(Some(unix_timestamp)),
+        );
+        self.functions.add_to_namespace(
+            "env",
+            Identifier::from("unix-timestamp"),
+            stdlib::env::UnixTimestamp::new(Some(unix_timestamp)),
+        );
+        self
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Environment {
+        Environment::new(HashMap::new())
     }
 }
 
@@ -203,9 +597,9 @@ pub mod stdlib {
                     Value::Integer(right) => return Ok((left == right).into()),
                     _ => {}
                 },
-                Value::String(left) => match &right {
+                Value::String(_) | Value::SourceSlice(_) => match &right {
                     Value::Null => return Ok(false.into()),
-                    Value::String(right) => return Ok((left == right).into()),
+                    Value::String(_) | Value::SourceSlice(_) => return Ok((left == right).into()),
                     _ => {}
                 },
                 Value::List(left) => match &right {
@@ -228,6 +622,11 @@ pub mod stdlib {
                     Value::GraphNode(right) => return Ok((left == right).into()),
                     _ => {}
                 },
+                Value::GraphEdge(left) => match &right {
+                    Value::Null => return Ok(false.into()),
+                    Value::GraphEdge(right) => return Ok((left == right).into()),
+                    _ => {}
+                },
             };
             Err(ExecutionError::FunctionFailed(
                 "eq".into(),
@@ -237,6 +636,10 @@ pub mod stdlib {
                 ),
             ))
         }
+
+        fn is_pure(&self) -> bool {
+            true
+        }
     }
 
     /// The implementation of the standard [`is-null`][`crate::reference::functions#is-null`] function.
@@ -258,9 +661,16 @@ pub mod stdlib {
             };
             Ok(result.into())
         }
+
+        fn is_pure(&self) -> bool {
+            true
+        }
     }
 
     pub mod syntax {
+        use tree_sitter::Query;
+        use tree_sitter::QueryCursor;
+
         use super::*;
 
         /// The implementation of the standard [`named-child-index`][`crate::reference::functions#named-child-index`]
@@ -293,7 +703,7 @@ pub mod stdlib {
                         "named-child-index".into(),
                         format!("Called named-child-index on a non-named child"),
                     ))?;
-                Ok(Value::Integer(index as u32))
+                Ok(Value::Integer(index as i64))
             }
         }
 
@@ -310,7 +720,7 @@ pub mod stdlib {
             ) -> Result<Value, ExecutionError> {
                 let node = graph[parameters.param()?.into_syntax_node_ref()?];
                 parameters.finish()?;
-                Ok(Value::String(source[node.byte_range()].to_string()))
+                Ok(graph.source_slice(source, node.byte_range()))
             }
         }
 
@@ -327,7 +737,7 @@ pub mod stdlib {
             ) -> Result<Value, ExecutionError> {
                 let node = graph[parameters.param()?.into_syntax_node_ref()?];
                 parameters.finish()?;
-                Ok(Value::Integer(node.start_position().row as u32))
+                Ok(Value::Integer(node.start_position().row as i64))
             }
         }
 
@@ -345,7 +755,7 @@ pub mod stdlib {
             ) -> Result<Value, ExecutionError> {
                 let node = graph[parameters.param()?.into_syntax_node_ref()?];
                 parameters.finish()?;
-                Ok(Value::Integer(node.start_position().column as u32))
+                Ok(Value::Integer(node.start_position().column as i64))
             }
         }
 
@@ -362,7 +772,7 @@ pub mod stdlib {
             ) -> Result<Value, ExecutionError> {
                 let node = graph[parameters.param()?.into_syntax_node_ref()?];
                 parameters.finish()?;
-                Ok(Value::Integer(node.end_position().row as u32))
+                Ok(Value::Integer(node.end_position().row as i64))
             }
         }
 
@@ -379,7 +789,7 @@ pub mod stdlib {
             ) -> Result<Value, ExecutionError> {
                 let node = graph[parameters.param()?.into_syntax_node_ref()?];
                 parameters.finish()?;
-                Ok(Value::Integer(node.end_position().column as u32))
+                Ok(Value::Integer(node.end_position().column as i64))
             }
         }
 
@@ -400,6 +810,49 @@ pub mod stdlib {
             }
         }
 
+        /// The implementation of the standard [`node-kind-matches?`][`crate::reference::functions#node-kind-matches`]
+        /// function.
+        pub struct NodeKindMatches;
+
+        impl NodeKindMatches {
+            /// Translates a kind pattern into an anchored regular expression.  `|` separates
+            /// alternatives, `*` matches any sequence of characters, and `?` matches any single
+            /// character.
+            fn pattern_to_regex(pattern: &str) -> String {
+                let alternatives = pattern
+                    .split('|')
+                    .map(|alternative| {
+                        alternative
+                            .chars()
+                            .map(|c| match c {
+                                '*' => ".*".to_string(),
+                                '?' => ".".to_string(),
+                                c => regex::escape(&c.to_string()),
+                            })
+                            .collect::<String>()
+                    })
+                    .collect::<Vec<_>>();
+                format!("^(?:{})$", alternatives.join("|"))
+            }
+        }
+
+        impl Function for NodeKindMatches {
+            fn call(
+                &self,
+                graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let node = graph[parameters.param()?.into_syntax_node_ref()?];
+                let pattern = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                let regex = Regex::new(&Self::pattern_to_regex(&pattern)).map_err(|e| {
+                    ExecutionError::FunctionFailed("node-kind-matches?".into(), format!("{}", e))
+                })?;
+                Ok(regex.is_match(node.kind()).into())
+            }
+        }
+
         // The implementation of the standard
         // [`named-child-count`][`crate::reference::functions#named-child-count`] function.
 
@@ -414,7 +867,61 @@ pub mod stdlib {
             ) -> Result<Value, ExecutionError> {
                 let node = graph[parameters.param()?.into_syntax_node_ref()?];
                 parameters.finish()?;
-                Ok(Value::Integer(node.named_child_count() as u32))
+                Ok(Value::Integer(node.named_child_count() as i64))
+            }
+        }
+
+        /// The implementation of the [`highlight-of`][`crate::reference::functions#highlight-of`]
+        /// function, which classifies syntax nodes with a highlights query.
+        pub struct HighlightOf {
+            query: Query,
+        }
+
+        impl HighlightOf {
+            /// Creates a new `highlight-of` function for a highlights query, such as the
+            /// `highlights.scm` file of a tree-sitter grammar.
+            pub fn new(query: Query) -> HighlightOf {
+                HighlightOf { query }
+            }
+        }
+
+        impl Function for HighlightOf {
+            fn call(
+                &self,
+                graph: &mut Graph,
+                source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let node = graph[parameters.param()?.into_syntax_node_ref()?];
+                parameters.finish()?;
+                // Patterns can capture the node in the context of its ancestors, so the query
+                // runs from the root, limited to matches that overlap the node.
+                let mut root = node;
+                while let Some(parent) = root.parent() {
+                    root = parent;
+                }
+                let mut cursor = QueryCursor::new();
+                cursor.set_byte_range(node.byte_range());
+                let capture_names = self.query.capture_names();
+                // As in tree-sitter's highlighter, earlier patterns take precedence.
+                let mut highlight: Option<(usize, &str)> = None;
+                for mat in cursor.matches(&self.query, root, source.as_bytes()) {
+                    if highlight
+                        .is_some_and(|(pattern_index, _)| pattern_index <= mat.pattern_index)
+                    {
+                        continue;
+                    }
+                    let name = mat
+                        .captures
+                        .iter()
+                        .filter(|capture| capture.node == node)
+                        .map(|capture| capture_names[capture.index as usize])
+                        .find(|name| !name.starts_with('_'));
+                    if let Some(name) = name {
+                        highlight = Some((mat.pattern_index, name));
+                    }
+                }
+                Ok(highlight.map_or(Value::Null, |(_, name)| name.into()))
             }
         }
     }
@@ -437,6 +944,25 @@ pub mod stdlib {
                 Ok(Value::GraphNode(node))
             }
         }
+
+        /// The implementation of the standard
+        /// [`external-node`][`crate::reference::functions#external-node`] function.
+        pub struct ExternalNode;
+
+        impl Function for ExternalNode {
+            fn call(
+                &self,
+                graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let key = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                Ok(graph
+                    .external_node(&key)
+                    .map_or(Value::Null, Value::GraphNode))
+            }
+        }
     }
 
     pub mod bool {
@@ -456,6 +982,10 @@ pub mod stdlib {
                 parameters.finish()?;
                 Ok(result.into())
             }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
         }
 
         /// The implementation of the standard [`and`][`crate::reference::functions#and`] function.
@@ -474,6 +1004,10 @@ pub mod stdlib {
                 }
                 Ok(result.into())
             }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
         }
 
         /// The implementation of the standard [`or`][`crate::reference::functions#or`] function.
@@ -492,10 +1026,16 @@ pub mod stdlib {
                 }
                 Ok(result.into())
             }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
         }
     }
 
     pub mod math {
+        use std::convert::TryFrom;
+
         use super::*;
 
         /// The implementation of the standard [`plus`][`crate::reference::functions#plus`] function.
@@ -508,104 +1048,652 @@ pub mod stdlib {
                 _source: &str,
                 parameters: &mut dyn Parameters,
             ) -> Result<Value, ExecutionError> {
-                let mut result = 0;
+                let mut result: i64 = 0;
                 while let Ok(parameter) = parameters.param() {
-                    result += parameter.as_integer()?;
+                    let value = parameter.as_integer()?;
+                    result = result.checked_add(value).ok_or_else(|| {
+                        ExecutionError::FunctionFailed(
+                            "plus".into(),
+                            format!("Adding {} to {} overflows", value, result),
+                        )
+                    })?;
                 }
                 Ok(Value::Integer(result))
             }
-        }
-    }
 
-    pub mod string {
-        use super::*;
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
 
-        /// The implementation of the standard [`format`][`crate::reference::functions#format`] function.
-        pub struct Format;
+        /// The implementation of the standard [`min`][`crate::reference::functions#min`] function.
+        pub struct Min;
 
-        impl Function for Format {
+        impl Function for Min {
             fn call(
                 &self,
                 _graph: &mut Graph,
                 _source: &str,
                 parameters: &mut dyn Parameters,
             ) -> Result<Value, ExecutionError> {
-                let format = parameters.param()?.into_string()?;
-                let mut result = String::new();
-                let mut it = format.chars().enumerate().into_iter();
-                while let Some((_, c)) = it.next() {
-                    match c {
-                        '{' => match it.next() {
-                            Some((_, '{')) => result.push('{'),
-                            Some((_, '}')) => {
-                                let value = parameters.param()?;
-                                result += &value.to_string();
-                            },
-                            Some((i, c)) => return Err(ExecutionError::FunctionFailed("format".into(), format!("Unexpected character `{}` after `{{` at position {} in format string `{}`. Expected `{{` or `}}`.", c, i + 1, format))),
-                            None => return Err(ExecutionError::FunctionFailed("format".into(), format!("Unexpected end of format string `{}` after `{{`. Expected `{{` or `}}`.", format))),
-                        },
-                        '}' => match it.next() {
-                            Some((_, '}')) => result.push('}'),
-                            Some((i, c)) => return Err(ExecutionError::FunctionFailed("format".into(), format!("Unexpected character `{}` after `}}` at position {} in format string `{}`. Expected `}}`.", c, i + 1, format))),
-                            None => return Err(ExecutionError::FunctionFailed("format".into(), format!("Unexpected end of format string `{}` after `{{. Expected `}}`.", format))),
-                        },
-                        c => result.push(c),
-                    }
+                let mut result = parameters.param()?.as_integer()?;
+                while let Ok(parameter) = parameters.param() {
+                    result = result.min(parameter.as_integer()?);
                 }
-                parameters.finish()?;
-                Ok(result.into())
+                Ok(Value::Integer(result))
             }
-        }
 
-        /// The implementation of the standard [`replace`][`crate::reference::functions#replace`] function.
-        pub struct Replace;
-
-        impl Function for Replace {
-            fn call(
-                &self,
-                _graph: &mut Graph,
-                _source: &str,
-                parameters: &mut dyn Parameters,
-            ) -> Result<Value, ExecutionError> {
-                let text = parameters.param()?.into_string()?;
-                let pattern = parameters.param()?.into_string()?;
-                let pattern = Regex::new(&pattern).map_err(|e| {
-                    ExecutionError::FunctionFailed("replace".into(), format!("{}", e))
-                })?;
-                let replacement = parameters.param()?.into_string()?;
-                parameters.finish()?;
-                Ok(Value::String(
-                    pattern.replace_all(&text, replacement.as_str()).to_string(),
-                ))
+            fn is_pure(&self) -> bool {
+                true
             }
         }
-    }
-
-    pub mod list {
-        use super::*;
 
-        /// The implementation of the standard [`concat`][`crate::reference::functions#concat`] function.
-        pub struct Concat;
+        /// The implementation of the standard [`max`][`crate::reference::functions#max`] function.
+        pub struct Max;
 
-        impl Function for Concat {
+        impl Function for Max {
             fn call(
                 &self,
                 _graph: &mut Graph,
                 _source: &str,
                 parameters: &mut dyn Parameters,
             ) -> Result<Value, ExecutionError> {
-                let mut result = Vec::new();
-                while let Ok(list) = parameters.param() {
-                    result.append(&mut list.into_list()?);
+                let mut result = parameters.param()?.as_integer()?;
+                while let Ok(parameter) = parameters.param() {
+                    result = result.max(parameter.as_integer()?);
                 }
-                Ok(result.into())
+                Ok(Value::Integer(result))
+            }
+
+            fn is_pure(&self) -> bool {
+                true
             }
         }
 
-        /// The implementation of the standard [`is-empty`][`crate::reference::functions#is-empty`] function.
-        pub struct IsEmpty;
+        /// The implementation of the standard [`abs`][`crate::reference::functions#abs`] function.
+        pub struct Abs;
 
-        impl Function for IsEmpty {
+        impl Function for Abs {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let value = parameters.param()?.as_integer()?;
+                parameters.finish()?;
+                let result = value.checked_abs().ok_or_else(|| {
+                    ExecutionError::FunctionFailed(
+                        "abs".into(),
+                        format!("The absolute value of {} overflows", value),
+                    )
+                })?;
+                Ok(Value::Integer(result))
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`clamp`][`crate::reference::functions#clamp`] function.
+        pub struct Clamp;
+
+        impl Function for Clamp {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let value = parameters.param()?.as_integer()?;
+                let min = parameters.param()?.as_integer()?;
+                let max = parameters.param()?.as_integer()?;
+                parameters.finish()?;
+                if max < min {
+                    return Err(ExecutionError::FunctionFailed(
+                        "clamp".into(),
+                        format!("Maximum {} is less than minimum {}", max, min),
+                    ));
+                }
+                Ok(Value::Integer(value.clamp(min, max)))
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`pow`][`crate::reference::functions#pow`] function.
+        pub struct Pow;
+
+        impl Function for Pow {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let base = parameters.param()?.as_integer()?;
+                let exponent = parameters.param()?.as_integer()?;
+                parameters.finish()?;
+                if exponent < 0 {
+                    return Err(ExecutionError::FunctionFailed(
+                        "pow".into(),
+                        format!("Exponent {} is negative", exponent),
+                    ));
+                }
+                let result = u32::try_from(exponent)
+                    .ok()
+                    .and_then(|exponent| base.checked_pow(exponent))
+                    .ok_or_else(|| {
+                        ExecutionError::FunctionFailed(
+                            "pow".into(),
+                            format!("{} to the power {} overflows", base, exponent),
+                        )
+                    })?;
+                Ok(Value::Integer(result))
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`random`][`crate::reference::functions#random`]
+        /// function.
+        pub struct Random;
+
+        impl Function for Random {
+            fn call(
+                &self,
+                graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let bound = parameters.param()?.as_integer()?;
+                parameters.finish()?;
+                if bound <= 0 {
+                    return Err(ExecutionError::FunctionFailed(
+                        "random".into(),
+                        format!("Bound {} is not positive", bound),
+                    ));
+                }
+                Ok(Value::Integer((graph.random() % bound as u64) as i64))
+            }
+        }
+    }
+
+    pub mod string {
+        use std::convert::TryFrom;
+
+        use base64::prelude::BASE64_STANDARD;
+        use base64::Engine;
+        use percent_encoding::percent_decode_str;
+        use percent_encoding::utf8_percent_encode;
+        use percent_encoding::AsciiSet;
+        use percent_encoding::NON_ALPHANUMERIC;
+        use unicode_normalization::UnicodeNormalization;
+        use unicode_segmentation::UnicodeSegmentation;
+
+        use super::*;
+
+        /// The implementation of the standard [`format`][`crate::reference::functions#format`] function.
+        pub struct Format;
+
+        impl Function for Format {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let format = parameters.param()?.into_string()?;
+                let mut result = String::new();
+                let mut it = format.chars().enumerate().into_iter();
+                while let Some((_, c)) = it.next() {
+                    match c {
+                        '{' => match it.next() {
+                            Some((_, '{')) => result.push('{'),
+                            Some((_, '}')) => {
+                                let value = parameters.param()?;
+                                result += &value.to_string();
+                            },
+                            Some((i, c)) => return Err(ExecutionError::FunctionFailed("format".into(), format!("Unexpected character `{}` after `{{` at position {} in format string `{}`. Expected `{{` or `}}`.", c, i + 1, format))),
+                            None => return Err(ExecutionError::FunctionFailed("format".into(), format!("Unexpected end of format string `{}` after `{{`. Expected `{{` or `}}`.", format))),
+                        },
+                        '}' => match it.next() {
+                            Some((_, '}')) => result.push('}'),
+                            Some((i, c)) => return Err(ExecutionError::FunctionFailed("format".into(), format!("Unexpected character `{}` after `}}` at position {} in format string `{}`. Expected `}}`.", c, i + 1, format))),
+                            None => return Err(ExecutionError::FunctionFailed("format".into(), format!("Unexpected end of format string `{}` after `{{. Expected `}}`.", format))),
+                        },
+                        c => result.push(c),
+                    }
+                }
+                parameters.finish()?;
+                Ok(result.into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`replace`][`crate::reference::functions#replace`] function.
+        pub struct Replace;
+
+        impl Function for Replace {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let text = parameters.param()?.into_string()?;
+                let pattern = parameters.param()?.into_string()?;
+                let pattern = Regex::new(&pattern).map_err(|e| {
+                    ExecutionError::FunctionFailed("replace".into(), format!("{}", e))
+                })?;
+                let replacement = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                Ok(Value::String(
+                    pattern.replace_all(&text, replacement.as_str()).to_string(),
+                ))
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`base64-encode`][`crate::reference::functions#base64-encode`]
+        /// function.
+        pub struct Base64Encode;
+
+        impl Function for Base64Encode {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let text = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                Ok(BASE64_STANDARD.encode(text).into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`base64-decode`][`crate::reference::functions#base64-decode`]
+        /// function.
+        pub struct Base64Decode;
+
+        impl Function for Base64Decode {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let text = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                let bytes = BASE64_STANDARD.decode(&text).map_err(|e| {
+                    ExecutionError::FunctionFailed("base64-decode".into(), format!("{}", e))
+                })?;
+                let result = String::from_utf8(bytes).map_err(|e| {
+                    ExecutionError::FunctionFailed("base64-decode".into(), format!("{}", e))
+                })?;
+                Ok(result.into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The characters that [`uri-encode`][`crate::reference::functions#uri-encode`] escapes:
+        /// everything except the unreserved characters of RFC 3986.
+        const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+            .remove(b'-')
+            .remove(b'.')
+            .remove(b'_')
+            .remove(b'~');
+
+        /// The implementation of the standard [`uri-encode`][`crate::reference::functions#uri-encode`]
+        /// function.
+        pub struct UriEncode;
+
+        impl Function for UriEncode {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let text = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                Ok(utf8_percent_encode(&text, URI_COMPONENT).to_string().into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`uri-decode`][`crate::reference::functions#uri-decode`]
+        /// function.
+        pub struct UriDecode;
+
+        impl Function for UriDecode {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let text = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                let result = percent_decode_str(&text).decode_utf8().map_err(|e| {
+                    ExecutionError::FunctionFailed("uri-decode".into(), format!("{}", e))
+                })?;
+                Ok(result.into_owned().into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// Computes the Levenshtein distance between two strings, counting Unicode scalar values.
+        fn edit_distance(left: &str, right: &str) -> usize {
+            let right = right.chars().collect::<Vec<_>>();
+            let mut previous = (0..=right.len()).collect::<Vec<_>>();
+            let mut current = vec![0; right.len() + 1];
+            for (i, l) in left.chars().enumerate() {
+                current[0] = i + 1;
+                for (j, r) in right.iter().enumerate() {
+                    let substitution = previous[j] + if l == *r { 0 } else { 1 };
+                    current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+                }
+                std::mem::swap(&mut previous, &mut current);
+            }
+            previous[right.len()]
+        }
+
+        /// The implementation of the standard [`edit-distance`][`crate::reference::functions#edit-distance`]
+        /// function.
+        pub struct EditDistance;
+
+        impl Function for EditDistance {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let left = parameters.param()?.into_string()?;
+                let right = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                Ok((edit_distance(&left, &right) as u32).into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`fuzzy-match?`][`crate::reference::functions#fuzzy-match`]
+        /// function.
+        pub struct FuzzyMatch;
+
+        impl Function for FuzzyMatch {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let left = parameters.param()?.into_string()?;
+                let right = parameters.param()?.into_string()?;
+                let max_distance = match parameters.param() {
+                    Ok(max_distance) => max_distance.as_integer()?,
+                    Err(_) => 1,
+                };
+                parameters.finish()?;
+                let distance = edit_distance(&left.to_lowercase(), &right.to_lowercase());
+                Ok((distance as i64 <= max_distance).into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`unicode-nfc`][`crate::reference::functions#unicode-nfc`]
+        /// function.
+        pub struct UnicodeNfc;
+
+        impl Function for UnicodeNfc {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let text = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                Ok(text.nfc().collect::<String>().into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`unicode-casefold`][`crate::reference::functions#unicode-casefold`]
+        /// function.
+        pub struct UnicodeCasefold;
+
+        impl Function for UnicodeCasefold {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let text = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                Ok(caseless::default_case_fold_str(&text).into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`substring`][`crate::reference::functions#substring`]
+        /// function.
+        pub struct Substring;
+
+        impl Function for Substring {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let text = parameters.param()?.into_string()?;
+                let start = position(parameters.param()?.as_integer()?)?;
+                let end = match parameters.param() {
+                    Ok(end) => Some(position(end.as_integer()?)?),
+                    Err(_) => None,
+                };
+                parameters.finish()?;
+                if let Some(end) = end {
+                    if end < start {
+                        return Err(ExecutionError::FunctionFailed(
+                            "substring".into(),
+                            format!("End {} is before start {}", end, start),
+                        ));
+                    }
+                }
+                let graphemes = text.graphemes(true).skip(start);
+                let result = match end {
+                    Some(end) => graphemes.take(end - start).collect::<String>(),
+                    None => graphemes.collect::<String>(),
+                };
+                Ok(result.into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// Converts a position of the `substring` function into an index, failing if it is
+        /// negative.
+        fn position(value: i64) -> Result<usize, ExecutionError> {
+            usize::try_from(value).map_err(|_| {
+                ExecutionError::FunctionFailed(
+                    "substring".into(),
+                    format!("Position {} is negative", value),
+                )
+            })
+        }
+    }
+
+    pub mod json {
+        use super::*;
+
+        /// The implementation of the standard [`json-parse`][`crate::reference::functions#json-parse`]
+        /// function.
+        pub struct JsonParse;
+
+        impl Function for JsonParse {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let text = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                let json = serde_json::from_str(&text).map_err(|e| {
+                    ExecutionError::FunctionFailed("json-parse".into(), format!("{}", e))
+                })?;
+                from_json(json)
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        fn from_json(json: serde_json::Value) -> Result<Value, ExecutionError> {
+            match json {
+                serde_json::Value::Null => Ok(Value::Null),
+                serde_json::Value::Bool(value) => Ok(value.into()),
+                serde_json::Value::Number(number) => {
+                    number.as_i64().map(Value::Integer).ok_or_else(|| {
+                        ExecutionError::FunctionFailed(
+                            "json-parse".into(),
+                            format!("Unsupported number {}", number),
+                        )
+                    })
+                }
+                serde_json::Value::String(value) => Ok(value.into()),
+                serde_json::Value::Array(values) => values
+                    .into_iter()
+                    .map(from_json)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(Value::from),
+                serde_json::Value::Object(_) => Err(ExecutionError::FunctionFailed(
+                    "json-parse".into(),
+                    "Objects are not supported".into(),
+                )),
+            }
+        }
+
+        /// The implementation of the standard [`json-stringify`][`crate::reference::functions#json-stringify`]
+        /// function.
+        pub struct JsonStringify;
+
+        impl Function for JsonStringify {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let value = parameters.param()?;
+                parameters.finish()?;
+                Ok(to_json(&value)?.to_string().into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        fn to_json(value: &Value) -> Result<serde_json::Value, ExecutionError> {
+            match value {
+                Value::Null => Ok(serde_json::Value::Null),
+                Value::Boolean(value) => Ok((*value).into()),
+                Value::Integer(value) => Ok((*value).into()),
+                Value::String(value) => Ok(value.as_str().into()),
+                Value::SourceSlice(value) => Ok(value.as_str().into()),
+                Value::List(values) => values
+                    .iter()
+                    .map(to_json)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(serde_json::Value::Array),
+                Value::Set(values) => values
+                    .iter()
+                    .map(to_json)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(serde_json::Value::Array),
+                Value::SyntaxNode(_) | Value::GraphNode(_) | Value::GraphEdge(_) => {
+                    Err(ExecutionError::FunctionFailed(
+                        "json-stringify".into(),
+                        format!("Cannot convert {} to JSON", value),
+                    ))
+                }
+            }
+        }
+    }
+
+    pub mod list {
+        use super::*;
+
+        /// The implementation of the standard [`concat`][`crate::reference::functions#concat`] function.
+        pub struct Concat;
+
+        impl Function for Concat {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let mut result = Vec::new();
+                while let Ok(list) = parameters.param() {
+                    result.append(&mut list.into_list()?);
+                }
+                Ok(result.into())
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+
+        /// The implementation of the standard [`is-empty`][`crate::reference::functions#is-empty`] function.
+        pub struct IsEmpty;
+
+        impl Function for IsEmpty {
             fn call(
                 &self,
                 _graph: &mut Graph,
@@ -615,6 +1703,10 @@ pub mod stdlib {
                 let list = parameters.param()?.into_list()?;
                 Ok(list.is_empty().into())
             }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
         }
 
         /// The implementation of the standard [`join`][`crate::reference::functions#join`] function.
@@ -640,6 +1732,10 @@ pub mod stdlib {
                     .join(&sep);
                 Ok(result.into())
             }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
         }
 
         /// The implementation of the standard [`length`][`crate::reference::functions#length`] function.
@@ -655,6 +1751,181 @@ pub mod stdlib {
                 let list = parameters.param()?.into_list()?;
                 Ok((list.len() as u32).into())
             }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+        }
+    }
+
+    pub mod env {
+        use std::collections::HashMap;
+        use std::convert::TryFrom;
+        use std::path::Path;
+        use std::time::SystemTime;
+        use std::time::UNIX_EPOCH;
+
+        use super::*;
+
+        /// The implementation of the [`env`][`crate::reference::functions#env`] function.
+        pub struct Env;
+
+        impl Function for Env {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let name = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                match std::env::var(&name) {
+                    Ok(value) => Ok(value.into()),
+                    Err(std::env::VarError::NotPresent) => Ok(Value::Null),
+                    Err(e) => Err(ExecutionError::FunctionFailed(
+                        "env".into(),
+                        format!("Cannot read environment variable {}: {}", name, e),
+                    )),
+                }
+            }
+        }
+
+        /// The implementation of the [`config-get`][`crate::reference::functions#config-get`]
+        /// function.
+        pub struct ConfigGet {
+            config: HashMap<String, Value>,
+        }
+
+        impl ConfigGet {
+            pub fn new(config: HashMap<String, Value>) -> ConfigGet {
+                ConfigGet { config }
+            }
+        }
+
+        impl Function for ConfigGet {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let key = parameters.param()?.into_string()?;
+                let default = parameters.param().unwrap_or(Value::Null);
+                parameters.finish()?;
+                Ok(self.config.get(&key).cloned().unwrap_or(default))
+            }
+        }
+
+        /// The implementation of the [`file-exists?`][`crate::reference::functions#file-exists`]
+        /// function.
+        pub struct FileExists;
+
+        impl Function for FileExists {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                let path = parameters.param()?.into_string()?;
+                parameters.finish()?;
+                Ok(Path::new(&path).exists().into())
+            }
+        }
+
+        /// Returns the given time, or the current time if none is given, as seconds since the Unix
+        /// epoch.
+        fn unix_timestamp(fixed: Option<u64>, function: &str) -> Result<u64, ExecutionError> {
+            if let Some(fixed) = fixed {
+                return Ok(fixed);
+            }
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .map_err(|e| ExecutionError::FunctionFailed(function.into(), format!("{}", e)))
+        }
+
+        /// Formats a Unix timestamp as an ISO 8601 date and time in UTC.
+        fn format_iso8601(unix_timestamp: u64) -> String {
+            let days = (unix_timestamp / 86400) as i64;
+            let seconds = unix_timestamp % 86400;
+            // Converts days since the epoch into a civil date, using the algorithm from
+            // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+            let z = days + 719468;
+            let era = z.div_euclid(146097);
+            let doe = z.rem_euclid(146097);
+            let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+            let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+            let mp = (5 * doy + 2) / 153;
+            let day = doy - (153 * mp + 2) / 5 + 1;
+            let month = if mp < 10 { mp + 3 } else { mp - 9 };
+            let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+            format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+                year,
+                month,
+                day,
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            )
+        }
+
+        /// The implementation of the [`now-iso8601`][`crate::reference::functions#now-iso8601`]
+        /// function.
+        pub struct NowIso8601 {
+            fixed: Option<u64>,
+        }
+
+        impl NowIso8601 {
+            /// Creates a new instance of the function, which returns the given time if one is
+            /// provided, and the current time otherwise.
+            pub fn new(fixed: Option<u64>) -> NowIso8601 {
+                NowIso8601 { fixed }
+            }
+        }
+
+        impl Function for NowIso8601 {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                parameters.finish()?;
+                let timestamp = unix_timestamp(self.fixed, "now-iso8601")?;
+                Ok(format_iso8601(timestamp).into())
+            }
+        }
+
+        /// The implementation of the [`unix-timestamp`][`crate::reference::functions#unix-timestamp`]
+        /// function.
+        pub struct UnixTimestamp {
+            fixed: Option<u64>,
+        }
+
+        impl UnixTimestamp {
+            /// Creates a new instance of the function, which returns the given time if one is
+            /// provided, and the current time otherwise.
+            pub fn new(fixed: Option<u64>) -> UnixTimestamp {
+                UnixTimestamp { fixed }
+            }
+        }
+
+        impl Function for UnixTimestamp {
+            fn call(
+                &self,
+                _graph: &mut Graph,
+                _source: &str,
+                parameters: &mut dyn Parameters,
+            ) -> Result<Value, ExecutionError> {
+                parameters.finish()?;
+                let timestamp = unix_timestamp(self.fixed, "unix-timestamp")?;
+                let timestamp = i64::try_from(timestamp).map_err(|e| {
+                    ExecutionError::FunctionFailed("unix-timestamp".into(), format!("{}", e))
+                })?;
+                Ok(Value::Integer(timestamp))
+            }
         }
     }
 }
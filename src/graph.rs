@@ -7,8 +7,9 @@
 
 //! Defines data types for the graphs produced by the graph DSL
 
-use std::borrow::Borrow;
-use std::collections::hash_map::Entry;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::fmt;
@@ -16,9 +17,13 @@ use std::fs::File;
 use std::hash::Hash;
 use std::io::prelude::*;
 use std::io::stdout;
+use std::iter::FromIterator;
+use std::ops::Deref;
 use std::ops::Index;
 use std::ops::IndexMut;
+use std::ops::Range;
 use std::path::Path;
+use std::sync::Arc;
 
 use serde::ser::SerializeMap;
 use serde::ser::SerializeSeq;
@@ -27,17 +32,60 @@ use serde::Serializer;
 use serde_json;
 use smallvec::SmallVec;
 use tree_sitter::Node;
+use tree_sitter::Tree;
 
 use crate::execution::error::ExecutionError;
+use crate::execution::error::StatementContext;
 use crate::Identifier;
 use crate::Location;
 
+mod canonical;
+
 /// A graph produced by executing a graph DSL file.  Graphs include a lifetime parameter to ensure
 /// that they don't outlive the tree-sitter syntax tree that they are generated from.
+///
+/// The contents of a graph are shared with its [snapshots][Graph::snapshot], and copied when the
+/// graph is changed after a snapshot was taken.  Graph nodes are stored in chunks, and only the
+/// chunks that change are copied.  The indices of syntax nodes, labels, edge kinds, and external
+/// nodes, and the columns of node attributes, are copied as a whole, the first time that they
+/// change.
+///
+/// The attributes of graph nodes are stored in the [`attributes`][GraphNode::attributes] of each
+/// node, unless the graph [stores them in columns][AttributeStorage::Columns].  The
+/// [`node_attribute`][Graph::node_attribute] and [`node_attributes`][Graph::node_attributes]
+/// methods read them, and [`add_node_attribute`][Graph::add_node_attribute] and
+/// [`append_node_attribute`][Graph::append_node_attribute] change them, in either case.
+///
+/// Graph nodes are indexed by [`GraphNodeRef`][], which gives access to the whole
+/// [`GraphNode`][], so the outgoing edges and label of each node are stored with it, rather than
+/// in separate arrays for all nodes.  Only node attributes can be stored in columns.
 #[derive(Default)]
 pub struct Graph<'tree> {
-    pub(crate) syntax_nodes: HashMap<SyntaxNodeID, Node<'tree>>,
-    graph_nodes: Vec<GraphNode>,
+    pub(crate) syntax_nodes: Arc<HashMap<SyntaxNodeID, Node<'tree>>>,
+    graph_nodes: GraphNodes,
+    /// The graph nodes with each label, in the order in which they were created
+    labels: Arc<HashMap<Identifier, Vec<GraphNodeID>>>,
+    /// The sources and sinks of the edges of each kind, in the order in which they were created
+    edge_kinds: Arc<HashMap<Identifier, Vec<(GraphNodeID, GraphNodeID)>>>,
+    /// The attributes of the graph nodes, by name, if they are stored in columns
+    columns: Option<BTreeMap<Identifier, Arc<Vec<Option<Value>>>>>,
+    /// The state of the random number generator, which the first execution into the graph seeds
+    random: Option<u64>,
+    /// The source of the execution into the graph, if one is running, which the source slices
+    /// that it creates share
+    shared_source: Option<SharedSource>,
+    /// The observer that executions tell about the attributes that they set
+    attribute_observer: Option<Arc<dyn AttributeObserver + Send + Sync>>,
+    /// The graph nodes that were provided from outside of the graph DSL, by key
+    external_nodes: Arc<BTreeMap<String, GraphNodeID>>,
+}
+
+/// The source of an execution, which is identified by its address and length, and copied the
+/// first time that a source slice is created from it.
+struct SharedSource {
+    address: usize,
+    len: usize,
+    text: Option<Arc<str>>,
 }
 
 pub(crate) type SyntaxNodeID = u32;
@@ -60,36 +108,452 @@ impl<'tree> Graph<'tree> {
             kind: node.kind(),
             position: node.start_position(),
         };
-        self.syntax_nodes.entry(index).or_insert(node);
+        if !self.syntax_nodes.contains_key(&index) {
+            Arc::make_mut(&mut self.syntax_nodes).insert(index, node);
+        }
         node_ref
     }
 
     /// Adds a new graph node to the graph, returning a graph DSL reference to it.
     pub fn add_graph_node(&mut self) -> GraphNodeRef {
-        let graph_node = GraphNode::new();
+        let graph_node = GraphNode::new(None);
         let index = self.graph_nodes.len() as GraphNodeID;
         self.graph_nodes.push(graph_node);
         GraphNodeRef(index)
     }
 
+    /// Adds a new graph node with a label to the graph, returning a graph DSL reference to it.
+    /// The label is stored apart from the attributes of the node, and the graph keeps an index of
+    /// the nodes with each label.
+    pub fn add_labeled_graph_node(&mut self, label: Identifier) -> GraphNodeRef {
+        let index = self.graph_nodes.len() as GraphNodeID;
+        Arc::make_mut(&mut self.labels)
+            .entry(label.clone())
+            .or_default()
+            .push(index);
+        self.graph_nodes.push(GraphNode::new(Some(label)));
+        GraphNodeRef(index)
+    }
+
+    /// Returns the nodes with a label, in the order in which they were created.
+    pub fn nodes_with_label(&self, label: &str) -> impl Iterator<Item = GraphNodeRef> + '_ {
+        self.labels
+            .get(label)
+            .into_iter()
+            .flatten()
+            .map(|index| GraphNodeRef(*index))
+    }
+
+    /// Adds a graph node that stands for an entity from outside of the graph DSL, such as a symbol
+    /// from a previous run or from another tool, returning a graph DSL reference to it.  Graph DSL
+    /// files look the node up by its key with the
+    /// [`external-node`][crate::reference::functions#external-node] function, so that they can
+    /// create edges to it, when they are executed [into the graph][crate::ast::File::execute_into].
+    /// If there already is an external node with the key, returns it instead of adding another.
+    pub fn add_external_node(&mut self, key: &str) -> GraphNodeRef {
+        if let Some(node) = self.external_node(key) {
+            return node;
+        }
+        let node = self.add_graph_node();
+        Arc::make_mut(&mut self.external_nodes).insert(key.to_string(), node.0);
+        node
+    }
+
+    /// Returns the external node with a key, if there is one.
+    pub fn external_node(&self, key: &str) -> Option<GraphNodeRef> {
+        self.external_nodes
+            .get(key)
+            .map(|index| GraphNodeRef(*index))
+    }
+
+    /// Returns the keys and graph DSL references of the external nodes, in the order of their keys.
+    pub fn external_nodes(&self) -> impl Iterator<Item = (&str, GraphNodeRef)> + '_ {
+        self.external_nodes
+            .iter()
+            .map(|(key, index)| (key.as_str(), GraphNodeRef(*index)))
+    }
+
+    /// Adds an edge of a particular kind to the graph.  Like [`GraphNode::add_edge`][], there can
+    /// be at most one edge connecting any two graph nodes; the result indicates whether the edge
+    /// is new (`Ok`) or already existed (`Err`), in which case its kind is left unchanged.  The
+    /// kind is stored apart from the attributes of the edge, and the graph keeps an index of the
+    /// edges of each kind.
+    pub fn add_kinded_edge(
+        &mut self,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+        kind: Identifier,
+    ) -> Result<&mut Edge, &mut Edge> {
+        if self[source].get_edge(sink).is_none() {
+            Arc::make_mut(&mut self.edge_kinds)
+                .entry(kind.clone())
+                .or_default()
+                .push((source.0, sink.0));
+        }
+        let edge = self[source].add_edge(sink)?;
+        edge.kind = Some(kind);
+        Ok(edge)
+    }
+
+    /// Returns the sources and sinks of the edges of a kind, in the order in which they were
+    /// created.
+    pub fn edges_with_kind(
+        &self,
+        kind: &str,
+    ) -> impl Iterator<Item = (GraphNodeRef, GraphNodeRef)> + '_ {
+        self.edge_kinds
+            .get(kind)
+            .into_iter()
+            .flatten()
+            .map(|(source, sink)| (GraphNodeRef(*source), GraphNodeRef(*sink)))
+    }
+
+    /// Returns how the attributes of the graph nodes are stored.
+    pub fn attribute_storage(&self) -> AttributeStorage {
+        match self.columns {
+            Some(_) => AttributeStorage::Columns,
+            None => AttributeStorage::Nodes,
+        }
+    }
+
+    /// Changes how the attributes of the graph nodes are stored, moving the attributes that the
+    /// nodes already have.
+    pub fn set_attribute_storage(&mut self, storage: AttributeStorage) {
+        if storage == self.attribute_storage() {
+            return;
+        }
+        match storage {
+            AttributeStorage::Columns => {
+                self.columns = Some(BTreeMap::new());
+                for node in self.iter_nodes() {
+                    let attributes = std::mem::take(&mut self[node].attributes);
+                    for (name, value) in attributes.values {
+                        let _ = self.add_node_attribute(node, name, value);
+                    }
+                }
+            }
+            AttributeStorage::Nodes => {
+                let columns = self.columns.take().unwrap_or_default();
+                for (name, column) in columns {
+                    for (index, value) in column.iter().enumerate() {
+                        let node = GraphNodeRef(index as GraphNodeID);
+                        let attributes = &mut self[node].attributes;
+                        match value {
+                            Some(value) if attributes.get(name.as_str()).is_none() => {
+                                let _ = attributes.add(name.clone(), value.clone());
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the value of an attribute of a graph node, if it exists.  If attributes are stored
+    /// in columns, attributes that were added to the [`attributes`][GraphNode::attributes] of the
+    /// node directly are found as well, and take precedence.
+    pub fn node_attribute(&self, node: GraphNodeRef, name: &str) -> Option<&Value> {
+        let own = self[node].attributes.get(name);
+        match &self.columns {
+            Some(columns) => own.or_else(|| columns.get(name)?.get(node.index())?.as_ref()),
+            None => own,
+        }
+    }
+
+    /// Returns the attributes of a graph node.  They are only copied if they are stored in
+    /// columns, in which case they include the attributes that were added to the
+    /// [`attributes`][GraphNode::attributes] of the node directly, like
+    /// [`node_attribute`][Graph::node_attribute].
+    pub fn node_attributes(&self, node: GraphNodeRef) -> Cow<'_, Attributes> {
+        let own = &self[node].attributes;
+        let columns = match &self.columns {
+            Some(columns) => columns,
+            None => return Cow::Borrowed(own),
+        };
+        let mut attributes = Attributes {
+            values: columns
+                .iter()
+                .filter_map(|(name, column)| {
+                    let value = column.get(node.index())?.as_ref()?;
+                    Some((name.clone(), value.clone()))
+                })
+                .collect(),
+        };
+        for (name, value) in own.iter() {
+            let _ = attributes.add(name.clone(), value.clone());
+        }
+        Cow::Owned(attributes)
+    }
+
+    /// Adds an attribute to a graph node, like [`Attributes::add`][].
+    pub fn add_node_attribute<V: Into<Value>>(
+        &mut self,
+        node: GraphNodeRef,
+        name: Identifier,
+        value: V,
+    ) -> Result<(), Value> {
+        let value = value.into();
+        let slot = match self.column_slot(node, &name) {
+            Some(slot) => slot,
+            None => return self[node].attributes.add(name, value),
+        };
+        match slot {
+            Some(existing) if *existing != value => Err(std::mem::replace(existing, value)),
+            Some(_) => Ok(()),
+            None => {
+                *slot = Some(value);
+                Ok(())
+            }
+        }
+    }
+
+    /// Appends a value to a list-valued attribute of a graph node, like
+    /// [`Attributes::append`][].
+    pub fn append_node_attribute<V: Into<Value>>(
+        &mut self,
+        node: GraphNodeRef,
+        name: Identifier,
+        value: V,
+    ) -> Result<(), Value> {
+        let slot = match self.column_slot(node, &name) {
+            Some(slot) => slot,
+            None => return self[node].attributes.append(name, value),
+        };
+        match slot {
+            Some(Value::List(values)) => {
+                values.push(value.into());
+                Ok(())
+            }
+            Some(_) => Err(value.into()),
+            None => {
+                *slot = Some(Value::List(vec![value.into()]));
+                Ok(())
+            }
+        }
+    }
+
+    /// Installs an observer, which executions into this graph tell about each attribute that they
+    /// are about to set.  Replaces the observer that the graph had, if any.
+    pub fn set_attribute_observer<O>(&mut self, observer: O)
+    where
+        O: AttributeObserver + Send + Sync + 'static,
+    {
+        self.attribute_observer = Some(Arc::new(observer));
+    }
+
+    /// Removes the attribute observer of this graph, if it has one.
+    pub fn remove_attribute_observer(&mut self) {
+        self.attribute_observer = None;
+    }
+
+    /// Tells the attribute observer, if there is one, that an attribute of a graph node is about
+    /// to be set or appended to.  Values that would leave the attribute unchanged, and appends
+    /// that would fail, are not reported.
+    pub(crate) fn observe_node_attribute(
+        &self,
+        node: GraphNodeRef,
+        name: &Identifier,
+        value: &Value,
+        append: bool,
+        provenance: &StatementContext,
+    ) -> Result<(), ExecutionError> {
+        let observer = match &self.attribute_observer {
+            Some(observer) => observer,
+            None => return Ok(()),
+        };
+        let old_value = self.node_attribute(node, name);
+        let new_value = match changed_value(old_value, value, append) {
+            Some(new_value) => new_value,
+            None => return Ok(()),
+        };
+        let change = AttributeChange {
+            name,
+            old_value,
+            new_value: &new_value,
+            provenance,
+        };
+        observer
+            .node_attribute(self, node, &change)
+            .map_err(|reason| {
+                ExecutionError::RejectedAttribute(format!("{} on {}: {}", name, node, reason))
+            })
+    }
+
+    /// Tells the attribute observer, if there is one, that an attribute of an edge is about to be
+    /// set or appended to, like [`observe_node_attribute`][Graph::observe_node_attribute].  Edges
+    /// that do not exist are not reported.
+    pub(crate) fn observe_edge_attribute(
+        &self,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+        name: &Identifier,
+        value: &Value,
+        append: bool,
+        provenance: &StatementContext,
+    ) -> Result<(), ExecutionError> {
+        let observer = match &self.attribute_observer {
+            Some(observer) => observer,
+            None => return Ok(()),
+        };
+        let edge = match self[source].get_edge(sink) {
+            Some(edge) => edge,
+            None => return Ok(()),
+        };
+        let old_value = edge.attributes.get(name);
+        let new_value = match changed_value(old_value, value, append) {
+            Some(new_value) => new_value,
+            None => return Ok(()),
+        };
+        let change = AttributeChange {
+            name,
+            old_value,
+            new_value: &new_value,
+            provenance,
+        };
+        observer
+            .edge_attribute(self, source, sink, &change)
+            .map_err(|reason| {
+                ExecutionError::RejectedAttribute(format!(
+                    "{} on edge ({} -> {}): {}",
+                    name, source, sink, reason
+                ))
+            })
+    }
+
+    /// Returns the entry of a graph node in the column of an attribute, if attributes are stored
+    /// in columns, and the attribute was not added to the [`attributes`][GraphNode::attributes] of
+    /// the node directly.  Columns only grow as far as the last node that has the attribute.
+    fn column_slot(&mut self, node: GraphNodeRef, name: &Identifier) -> Option<&mut Option<Value>> {
+        if self.columns.is_none() || self[node].attributes.get(name.as_str()).is_some() {
+            return None;
+        }
+        let column = Arc::make_mut(self.columns.as_mut()?.entry(name.clone()).or_default());
+        if column.len() <= node.index() {
+            column.resize(node.index() + 1, None);
+        }
+        Some(&mut column[node.index()])
+    }
+
+    /// Returns the next number of the graph's random number generator.  Functions that need
+    /// randomness must use it, rather than any other source, so that executions with the same
+    /// [seed][crate::ExecutionConfig::seed] produce identical graphs.
+    pub fn random(&mut self) -> u64 {
+        // SplitMix64, which is fast, and good enough for identifiers and sampling
+        let state = self.random.get_or_insert(0);
+        *state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Seeds the random number generator, unless an earlier execution into the graph already
+    /// did, so that injected files continue the sequence of their host file.
+    pub(crate) fn seed(&mut self, seed: u64) {
+        self.random.get_or_insert(seed);
+    }
+
     /// Pretty-prints the contents of this graph.
     pub fn pretty_print<'a>(&'a self) -> impl fmt::Display + 'a {
-        struct DisplayGraph<'a, 'tree>(&'a Graph<'tree>);
+        static DEFAULT: PrintOptions = PrintOptions::new();
+        self.pretty_print_with(&DEFAULT)
+    }
+
+    /// Pretty-prints the contents of this graph, in the format of
+    /// [`pretty_print`][Self::pretty_print], but only the nodes and with the details that
+    /// `options` selects.
+    pub fn pretty_print_with<'a>(&'a self, options: &'a PrintOptions) -> impl fmt::Display + 'a {
+        struct DisplayGraph<'a, 'tree>(&'a Graph<'tree>, &'a PrintOptions);
 
         impl<'a, 'tree> fmt::Display for DisplayGraph<'a, 'tree> {
             fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-                let graph = self.0;
-                for (node_index, node) in graph.graph_nodes.iter().enumerate() {
-                    write!(f, "node {}\n{}", node_index, node.attributes)?;
-                    for (sink, edge) in &node.outgoing_edges {
-                        write!(f, "edge {} -> {}\n{}", node_index, *sink, edge.attributes)?;
+                let (graph, options) = (self.0, self.1);
+                let mut nodes = (0..graph.graph_nodes.len())
+                    .filter(|index| match &options.filter {
+                        Some(filter) => filter(&graph.node_attributes_at(*index)),
+                        None => true,
+                    })
+                    .collect::<Vec<_>>();
+                match &options.order {
+                    NodeOrder::Creation => {}
+                    NodeOrder::Span => nodes.sort_by_key(|index| {
+                        let span = graph.node_attributes_at(*index).span();
+                        (span.is_none(), span)
+                    }),
+                    NodeOrder::Attribute(name) => nodes.sort_by(|a, b| {
+                        let a = graph.node_attribute(GraphNodeRef(*a as GraphNodeID), name);
+                        let b = graph.node_attribute(GraphNodeRef(*b as GraphNodeID), name);
+                        (a.is_none(), a).cmp(&(b.is_none(), b))
+                    }),
+                }
+                let mut incoming_edges = vec![Vec::new(); graph.graph_nodes.len()];
+                if options.incoming_edges {
+                    for (node_index, node) in graph.graph_nodes.iter().enumerate() {
+                        for (sink, edge) in &node.outgoing_edges {
+                            incoming_edges[*sink as usize].push((node_index, edge));
+                        }
+                    }
+                }
+                for node_index in nodes {
+                    graph.write_node(f, node_index, options.max_value_length)?;
+                    for (source, edge) in &incoming_edges[node_index] {
+                        match &edge.kind {
+                            Some(kind) => writeln!(
+                                f,
+                                "edge {} <-{:?}- {}",
+                                node_index,
+                                kind.as_str(),
+                                source
+                            )?,
+                            None => writeln!(f, "edge {} <- {}", node_index, source)?,
+                        }
+                        edge.attributes.write(f, options.max_value_length)?;
                     }
                 }
                 Ok(())
             }
         }
 
-        DisplayGraph(self)
+        DisplayGraph(self, options)
+    }
+
+    /// Displays a graph node with its attributes and outgoing edges, in the format of
+    /// [`pretty_print`][Self::pretty_print].
+    pub fn display_node<'a>(&'a self, node: GraphNodeRef) -> impl fmt::Display + 'a {
+        struct DisplayNode<'a, 'tree>(&'a Graph<'tree>, GraphNodeRef);
+
+        impl<'a, 'tree> fmt::Display for DisplayNode<'a, 'tree> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                self.0.write_node(f, self.1.index(), None)
+            }
+        }
+
+        DisplayNode(self, node)
+    }
+
+    /// Writes a graph node with its attributes and outgoing edges.
+    fn write_node(
+        &self,
+        f: &mut fmt::Formatter,
+        node_index: usize,
+        max_value_length: Option<usize>,
+    ) -> fmt::Result {
+        let node = &self.graph_nodes[node_index];
+        match &node.label {
+            Some(label) => writeln!(f, "node {} : {:?}", node_index, label.as_str())?,
+            None => writeln!(f, "node {}", node_index)?,
+        }
+        self.node_attributes_at(node_index)
+            .write(f, max_value_length)?;
+        for (sink, edge) in &node.outgoing_edges {
+            match &edge.kind {
+                Some(kind) => writeln!(f, "edge {} -{:?}-> {}", node_index, kind.as_str(), *sink)?,
+                None => writeln!(f, "edge {} -> {}", node_index, *sink)?,
+            }
+            edge.attributes.write(f, max_value_length)?;
+        }
+        Ok(())
     }
 
     pub fn display_json(&self, path: Option<&Path>) -> std::io::Result<()> {
@@ -99,6 +563,182 @@ impl<'tree> Graph<'tree> {
         })
     }
 
+    /// Displays the contents of this graph in the [DOT][] format used by Graphviz.  Each node and
+    /// edge is labeled with its attributes, after the label of the node or the kind of the edge,
+    /// if it has one.
+    ///
+    /// [DOT]: https://graphviz.org/doc/info/lang.html
+    pub fn display_dot<'a>(&'a self) -> impl fmt::Display + 'a {
+        struct DisplayDot<'a, 'tree>(&'a Graph<'tree>);
+
+        impl<'a, 'tree> fmt::Display for DisplayDot<'a, 'tree> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                let graph = self.0;
+                writeln!(f, "digraph {{")?;
+                for (node_index, node) in graph.graph_nodes.iter().enumerate() {
+                    let mut label = node.display_index(node_index);
+                    for (name, value) in graph.node_attributes_at(node_index).iter() {
+                        label += &format!("\n{}: {}", name, value);
+                    }
+                    writeln!(f, "  n{} [label={:?}];", node_index, label)?;
+                }
+                for (node_index, node) in graph.graph_nodes.iter().enumerate() {
+                    for (sink, edge) in &node.outgoing_edges {
+                        let label = edge.display_lines().join("\n");
+                        writeln!(f, "  n{} -> n{} [label={:?}];", node_index, sink, label)?;
+                    }
+                }
+                writeln!(f, "}}")
+            }
+        }
+
+        DisplayDot(self)
+    }
+
+    /// Displays the contents of this graph in the [GraphML][] format.  All attribute values are
+    /// rendered as strings.  The label of a node, if it has one, is rendered in the `labels`
+    /// attribute of its element, as in `labels=":definition"`, and the kind of an edge in the
+    /// `label` attribute of its element, as in `label="calls"`.
+    ///
+    /// [GraphML]: http://graphml.graphdrawing.org/
+    pub fn display_graphml<'a>(&'a self) -> impl fmt::Display + 'a {
+        struct DisplayGraphml<'a, 'tree>(&'a Graph<'tree>);
+
+        impl<'a, 'tree> fmt::Display for DisplayGraphml<'a, 'tree> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                let graph = self.0;
+                let mut node_keys = BTreeSet::new();
+                let mut edge_keys = BTreeSet::new();
+                if let Some(columns) = &graph.columns {
+                    node_keys.extend(columns.keys());
+                }
+                for node in graph.graph_nodes.iter() {
+                    node_keys.extend(node.attributes.iter().map(|(name, _)| name));
+                }
+                for node in graph.graph_nodes.iter() {
+                    for (_, edge) in &node.outgoing_edges {
+                        edge_keys.extend(edge.attributes.iter().map(|(name, _)| name));
+                    }
+                }
+                writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+                writeln!(
+                    f,
+                    r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+                )?;
+                for key in &node_keys {
+                    let key = escape_xml(key.as_str());
+                    writeln!(
+                        f,
+                        r#"  <key id="node-{}" for="node" attr.name="{}" attr.type="string"/>"#,
+                        key, key
+                    )?;
+                }
+                for key in &edge_keys {
+                    let key = escape_xml(key.as_str());
+                    writeln!(
+                        f,
+                        r#"  <key id="edge-{}" for="edge" attr.name="{}" attr.type="string"/>"#,
+                        key, key
+                    )?;
+                }
+                writeln!(f, r#"  <graph edgedefault="directed">"#)?;
+                for (node_index, node) in graph.graph_nodes.iter().enumerate() {
+                    match &node.label {
+                        Some(label) => writeln!(
+                            f,
+                            r#"    <node id="n{}" labels=":{}">"#,
+                            node_index,
+                            escape_xml(label.as_str())
+                        )?,
+                        None => writeln!(f, r#"    <node id="n{}">"#, node_index)?,
+                    }
+                    for (name, value) in graph.node_attributes_at(node_index).iter() {
+                        writeln!(
+                            f,
+                            r#"      <data key="node-{}">{}</data>"#,
+                            escape_xml(name.as_str()),
+                            escape_xml(&value.to_string())
+                        )?;
+                    }
+                    writeln!(f, "    </node>")?;
+                }
+                for (node_index, node) in graph.graph_nodes.iter().enumerate() {
+                    for (sink, edge) in &node.outgoing_edges {
+                        match &edge.kind {
+                            Some(kind) => writeln!(
+                                f,
+                                r#"    <edge source="n{}" target="n{}" label="{}">"#,
+                                node_index,
+                                sink,
+                                escape_xml(kind.as_str())
+                            )?,
+                            None => writeln!(
+                                f,
+                                r#"    <edge source="n{}" target="n{}">"#,
+                                node_index, sink
+                            )?,
+                        }
+                        for (name, value) in edge.attributes.sorted() {
+                            writeln!(
+                                f,
+                                r#"      <data key="edge-{}">{}</data>"#,
+                                escape_xml(name.as_str()),
+                                escape_xml(&value.to_string())
+                            )?;
+                        }
+                        writeln!(f, "    </edge>")?;
+                    }
+                }
+                writeln!(f, "  </graph>")?;
+                writeln!(f, "</graphml>")
+            }
+        }
+
+        DisplayGraphml(self)
+    }
+
+    /// Displays the contents of this graph as a [Mermaid][] flowchart.  Each node and edge is
+    /// labeled with its attributes, after the label of the node or the kind of the edge, if it
+    /// has one.
+    ///
+    /// [Mermaid]: https://mermaid.js.org/syntax/flowchart.html
+    pub fn display_mermaid<'a>(&'a self) -> impl fmt::Display + 'a {
+        struct DisplayMermaid<'a, 'tree>(&'a Graph<'tree>);
+
+        impl<'a, 'tree> fmt::Display for DisplayMermaid<'a, 'tree> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                let graph = self.0;
+                writeln!(f, "flowchart TD")?;
+                for (node_index, node) in graph.graph_nodes.iter().enumerate() {
+                    let mut label = node.display_index(node_index);
+                    for (name, value) in graph.node_attributes_at(node_index).iter() {
+                        label += &format!("<br/>{}: {}", name, value);
+                    }
+                    writeln!(f, "  n{}[\"{}\"]", node_index, escape_mermaid(&label))?;
+                }
+                for (node_index, node) in graph.graph_nodes.iter().enumerate() {
+                    for (sink, edge) in &node.outgoing_edges {
+                        let label = edge.display_lines().join("<br/>");
+                        if label.is_empty() {
+                            writeln!(f, "  n{} --> n{}", node_index, sink)?;
+                        } else {
+                            writeln!(
+                                f,
+                                "  n{} -->|\"{}\"| n{}",
+                                node_index,
+                                escape_mermaid(&label),
+                                sink
+                            )?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        DisplayMermaid(self)
+    }
+
     // Returns an iterator of references to all of the nodes in the graph.
     pub fn iter_nodes(&self) -> impl Iterator<Item = GraphNodeRef> {
         (0..self.graph_nodes.len() as u32).map(GraphNodeRef)
@@ -108,6 +748,276 @@ impl<'tree> Graph<'tree> {
     pub fn node_count(&self) -> usize {
         self.graph_nodes.len()
     }
+
+    /// Returns an immutable view of this graph as it is now, which shares its contents with this
+    /// graph.  Taking a snapshot is cheap; changing the graph afterwards copies the parts of it
+    /// that change, so that the snapshot keeps showing the graph as it was.  Snapshots can be
+    /// cloned cheaply as well, to hand them to several readers.
+    pub fn snapshot(&self) -> GraphSnapshot<'tree> {
+        GraphSnapshot(Arc::new(Graph {
+            syntax_nodes: self.syntax_nodes.clone(),
+            graph_nodes: self.graph_nodes.clone(),
+            labels: self.labels.clone(),
+            edge_kinds: self.edge_kinds.clone(),
+            columns: self.columns.clone(),
+            random: self.random,
+            shared_source: None,
+            attribute_observer: None,
+            external_nodes: self.external_nodes.clone(),
+        }))
+    }
+
+    /// Returns a copy of this graph that refers to the syntax nodes of another syntax tree, which
+    /// must have the same structure as the tree that this graph refers to, such as a tree that was
+    /// parsed again without changes.  Returns `None` if one of the syntax nodes that this graph
+    /// refers to is not in `tree`.
+    pub fn rebase<'new>(&self, tree: &'new Tree) -> Option<Graph<'new>> {
+        let mut syntax_nodes = HashMap::new();
+        let mut indices = HashMap::new();
+        for (index, node) in self.syntax_nodes.iter() {
+            let new_node = find_syntax_node(tree, node)?;
+            syntax_nodes.insert(new_node.id() as SyntaxNodeID, new_node);
+            indices.insert(*index, new_node.id() as SyntaxNodeID);
+        }
+        let graph_nodes = self
+            .graph_nodes
+            .iter()
+            .map(|node| GraphNode {
+                label: node.label.clone(),
+                outgoing_edges: node
+                    .outgoing_edges
+                    .iter()
+                    .map(|(sink, edge)| {
+                        let edge = Edge {
+                            kind: edge.kind.clone(),
+                            attributes: edge.attributes.rebase(&indices),
+                        };
+                        (*sink, edge)
+                    })
+                    .collect(),
+                attributes: node.attributes.rebase(&indices),
+            })
+            .collect();
+        Some(Graph {
+            syntax_nodes: Arc::new(syntax_nodes),
+            graph_nodes,
+            labels: self.labels.clone(),
+            edge_kinds: self.edge_kinds.clone(),
+            columns: self.columns.as_ref().map(|columns| {
+                columns
+                    .iter()
+                    .map(|(name, column)| {
+                        let column = column
+                            .iter()
+                            .map(|value| value.as_ref().map(|value| value.rebase(&indices)))
+                            .collect();
+                        (name.clone(), Arc::new(column))
+                    })
+                    .collect()
+            }),
+            random: self.random,
+            shared_source: None,
+            attribute_observer: self.attribute_observer.clone(),
+            external_nodes: self.external_nodes.clone(),
+        })
+    }
+
+    /// Returns the attributes of the graph node with an index.
+    fn node_attributes_at(&self, index: usize) -> Cow<'_, Attributes> {
+        self.node_attributes(GraphNodeRef(index as GraphNodeID))
+    }
+
+    /// Makes the source slices that are created from `source`, until
+    /// [`stop_sharing_source`][Graph::stop_sharing_source] is called, share one copy of it.
+    pub(crate) fn share_source(&mut self, source: &str) {
+        self.shared_source = Some(SharedSource {
+            address: source.as_ptr() as usize,
+            len: source.len(),
+            text: None,
+        });
+    }
+
+    pub(crate) fn stop_sharing_source(&mut self) {
+        self.shared_source = None;
+    }
+
+    /// Returns a value for a range of a source.  The value borrows from the shared copy of the
+    /// source, if it is shared, and owns a copy of the range otherwise.
+    pub(crate) fn source_slice(&mut self, source: &str, range: Range<usize>) -> Value {
+        let shared = self.shared_source.as_mut().filter(|shared| {
+            shared.address == source.as_ptr() as usize && shared.len == source.len()
+        });
+        match shared {
+            Some(shared) => SourceSlice {
+                source: shared.text.get_or_insert_with(|| source.into()).clone(),
+                start: range.start,
+                end: range.end,
+            },
+            None => SourceSlice::from(&source[range]),
+        }
+        .into()
+    }
+}
+
+/// How the attributes of the nodes of a graph are stored
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AttributeStorage {
+    /// In the [`attributes`][GraphNode::attributes] of each node, which is the default
+    Nodes,
+    /// In a vector for each attribute name, indexed by node.  This takes much less memory when
+    /// most nodes have the same few attributes, as in graphs that index large corpora.  The
+    /// [`attributes`][GraphNode::attributes] of the nodes are empty, and the attributes are read
+    /// and changed through the methods of the [`Graph`][].  Attributes that are added to the
+    /// [`attributes`][GraphNode::attributes] of a node directly stay there, and the methods of the
+    /// graph read and change them there, so that both ways of accessing them agree.
+    Columns,
+}
+
+/// Observes the attributes that executions set on the nodes and edges of a graph, so that hosts
+/// can enforce policies on them, such as attributes that can only be set once, or mirror them
+/// into another store.  Use [`Graph::set_attribute_observer`][] to install an observer.
+///
+/// The observer is told about each attribute before it is set, including attributes that
+/// already have a value, which is then overwritten.  Setting an attribute to the value that it
+/// already has is not reported.  Returning an error leaves the attribute unchanged, and fails the
+/// execution with [`ExecutionError::RejectedAttribute`][].  The debug attributes that are
+/// configured with [`ExecutionConfig::debug_attributes`][crate::ExecutionConfig::debug_attributes],
+/// and attributes that are set through the methods of the [`Graph`][] rather than by an
+/// execution, are not reported.
+pub trait AttributeObserver {
+    /// Called before an attribute of a graph node is set.
+    fn node_attribute(
+        &self,
+        graph: &Graph,
+        node: GraphNodeRef,
+        change: &AttributeChange,
+    ) -> Result<(), String> {
+        let _ = (graph, node, change);
+        Ok(())
+    }
+
+    /// Called before an attribute of an edge is set.
+    fn edge_attribute(
+        &self,
+        graph: &Graph,
+        source: GraphNodeRef,
+        sink: GraphNodeRef,
+        change: &AttributeChange,
+    ) -> Result<(), String> {
+        let _ = (graph, source, sink, change);
+        Ok(())
+    }
+}
+
+/// An attribute that an execution is about to set, which is reported to an
+/// [`AttributeObserver`][]
+#[derive(Debug)]
+pub struct AttributeChange<'a> {
+    pub name: &'a Identifier,
+    /// The value that the attribute has now, if it has one
+    pub old_value: Option<&'a Value>,
+    /// The value that the attribute will have.  For attributes that are appended to, this is the
+    /// whole list, including the appended value.
+    pub new_value: &'a Value,
+    /// The statement that sets the attribute, with the stanza and the syntax node that it is
+    /// executing on
+    pub provenance: &'a StatementContext,
+}
+
+/// Returns the value that an attribute will have after it is set or appended to, or `None` if it
+/// would not change, or the append would fail.
+fn changed_value(old_value: Option<&Value>, value: &Value, append: bool) -> Option<Value> {
+    match (old_value, append) {
+        (Some(Value::List(values)), true) => {
+            let mut values = values.clone();
+            values.push(value.clone());
+            Some(Value::List(values))
+        }
+        (Some(_), true) => None,
+        (None, true) => Some(Value::List(vec![value.clone()])),
+        (Some(old_value), false) if old_value == value => None,
+        (_, false) => Some(value.clone()),
+    }
+}
+
+/// An immutable view of a graph, as it was when [`Graph::snapshot`][] was called.  A snapshot
+/// dereferences to a [`Graph`][], so that it can be read, printed, and serialized like one.
+#[derive(Clone)]
+pub struct GraphSnapshot<'tree>(Arc<Graph<'tree>>);
+
+impl<'tree> Deref for GraphSnapshot<'tree> {
+    type Target = Graph<'tree>;
+    fn deref(&self) -> &Graph<'tree> {
+        &self.0
+    }
+}
+
+/// The number of graph nodes in each chunk of a graph's storage.
+const CHUNK_SIZE: usize = 1024;
+
+/// The graph nodes of a graph, stored in chunks that are shared with the snapshots of the graph.
+/// A chunk is copied when one of its nodes changes while it is shared.
+#[derive(Clone, Default)]
+struct GraphNodes {
+    chunks: Vec<Arc<Vec<GraphNode>>>,
+    len: usize,
+}
+
+impl GraphNodes {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn push(&mut self, node: GraphNode) {
+        if self.len / CHUNK_SIZE == self.chunks.len() {
+            self.chunks.push(Arc::new(Vec::new()));
+        }
+        let chunk = self.chunks.last_mut().expect("missing chunk");
+        Arc::make_mut(chunk).push(node);
+        self.len += 1;
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &GraphNode> + '_ {
+        self.chunks.iter().flat_map(|chunk| chunk.iter())
+    }
+}
+
+impl Index<usize> for GraphNodes {
+    type Output = GraphNode;
+    fn index(&self, index: usize) -> &GraphNode {
+        &self.chunks[index / CHUNK_SIZE][index % CHUNK_SIZE]
+    }
+}
+
+impl IndexMut<usize> for GraphNodes {
+    fn index_mut(&mut self, index: usize) -> &mut GraphNode {
+        &mut Arc::make_mut(&mut self.chunks[index / CHUNK_SIZE])[index % CHUNK_SIZE]
+    }
+}
+
+impl FromIterator<GraphNode> for GraphNodes {
+    fn from_iter<I: IntoIterator<Item = GraphNode>>(iter: I) -> GraphNodes {
+        let mut nodes = GraphNodes::default();
+        for node in iter {
+            nodes.push(node);
+        }
+        nodes
+    }
+}
+
+/// Returns the syntax node of a tree that has the same kind and range as a syntax node of another
+/// tree.
+fn find_syntax_node<'new>(tree: &'new Tree, node: &Node) -> Option<Node<'new>> {
+    let mut candidate = tree
+        .root_node()
+        .descendant_for_byte_range(node.start_byte(), node.end_byte())?;
+    while candidate.byte_range() == node.byte_range() {
+        if candidate.kind_id() == node.kind_id() {
+            return Some(candidate);
+        }
+        candidate = candidate.parent()?;
+    }
+    None
 }
 
 impl<'tree> Index<SyntaxNodeRef> for Graph<'tree> {
@@ -134,27 +1044,145 @@ impl<'tree> Serialize for Graph<'tree> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let mut seq = serializer.serialize_seq(Some(self.graph_nodes.len()))?;
         for (node_index, node) in self.graph_nodes.iter().enumerate() {
-            seq.serialize_element(&SerializeGraphNode(node_index, node))?;
+            let attributes = self.node_attributes_at(node_index);
+            seq.serialize_element(&SerializeGraphNode(node_index, node, &attributes))?;
         }
         seq.end()
     }
 }
 
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+/// Selects the nodes, and the details of them, that
+/// [`Graph::pretty_print_with`][Graph::pretty_print_with] displays.  By default, it displays
+/// everything, like [`Graph::pretty_print`][Graph::pretty_print].
+#[derive(Default)]
+pub struct PrintOptions {
+    filter: Option<NodeFilter>,
+    order: NodeOrder,
+    max_value_length: Option<usize>,
+    incoming_edges: bool,
+}
+
+type NodeFilter = Box<dyn Fn(&Attributes) -> bool + Send + Sync>;
+
+/// The order in which [`Graph::pretty_print_with`][Graph::pretty_print_with] displays nodes.
+/// Nodes that are equal in the order are displayed in the order in which they were created.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum NodeOrder {
+    /// In the order in which they were created
+    #[default]
+    Creation,
+    /// By the position of the earliest syntax node that their attributes refer to, with nodes
+    /// that refer to none last
+    Span,
+    /// By the value of an attribute, with nodes that do not have it last
+    Attribute(Identifier),
+}
+
+impl PrintOptions {
+    pub const fn new() -> PrintOptions {
+        PrintOptions {
+            filter: None,
+            order: NodeOrder::Creation,
+            max_value_length: None,
+            incoming_edges: false,
+        }
+    }
+
+    /// Only displays the nodes whose attributes satisfy `filter`.  Their edges are displayed even
+    /// if the nodes at the other end are not.
+    pub fn filter<F>(self, filter: F) -> Self
+    where
+        F: Fn(&Attributes) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            filter: Some(Box::new(filter)),
+            order: self.order,
+            max_value_length: self.max_value_length,
+            incoming_edges: self.incoming_edges,
+        }
+    }
+
+    /// Displays the nodes in `order`.
+    pub fn order(self, order: NodeOrder) -> Self {
+        Self {
+            filter: self.filter,
+            order,
+            max_value_length: self.max_value_length,
+            incoming_edges: self.incoming_edges,
+        }
+    }
+
+    /// Elides attribute values that are displayed with more than `max_value_length` characters.
+    pub fn max_value_length(self, max_value_length: usize) -> Self {
+        Self {
+            filter: self.filter,
+            order: self.order,
+            max_value_length: Some(max_value_length),
+            incoming_edges: self.incoming_edges,
+        }
+    }
+
+    /// Also displays the incoming edges of each node, as `edge sink <- source`, after its
+    /// outgoing edges.
+    pub fn incoming_edges(self, incoming_edges: bool) -> Self {
+        Self {
+            filter: self.filter,
+            order: self.order,
+            max_value_length: self.max_value_length,
+            incoming_edges,
+        }
+    }
+}
+
 /// A node in a graph
+///
+/// Graphs of large corpora have millions of nodes, most of which have few edges and attributes,
+/// so nodes are kept small: the first outgoing edge is stored inline, and further edges and the
+/// attributes are stored in vectors that are only allocated when they are needed.
+#[derive(Clone)]
 pub struct GraphNode {
-    outgoing_edges: SmallVec<[(GraphNodeID, Edge); 8]>,
-    /// The set of attributes associated with this graph node
+    label: Option<Identifier>,
+    outgoing_edges: SmallVec<[(GraphNodeID, Edge); 1]>,
+    /// The set of attributes associated with this graph node.  This is empty if the graph
+    /// [stores attributes in columns][AttributeStorage::Columns], unless attributes are added to
+    /// it directly, so code that must work with either storage uses
+    /// [`Graph::node_attributes`][] instead.
     pub attributes: Attributes,
 }
 
 impl GraphNode {
-    fn new() -> GraphNode {
+    fn new(label: Option<Identifier>) -> GraphNode {
         GraphNode {
+            label,
             outgoing_edges: SmallVec::new(),
             attributes: Attributes::new(),
         }
     }
 
+    /// Returns the label of this node, if it was created with one.
+    pub fn label(&self) -> Option<&Identifier> {
+        self.label.as_ref()
+    }
+
+    /// Returns the index of this node, followed by its label if it has one, as in `0: definition`.
+    fn display_index(&self, node_index: usize) -> String {
+        match &self.label {
+            Some(label) => format!("{}: {}", node_index, label),
+            None => node_index.to_string(),
+        }
+    }
+
     /// Adds an edge to this node.  There can be at most one edge connecting any two graph nodes;
     /// the result indicates whether the edge is new (`Ok`) or already existed (`Err`).  In either
     /// case, you also get a mutable reference to the [`Edge`][] instance for the edge.
@@ -166,7 +1194,7 @@ impl GraphNode {
         {
             Ok(index) => Err(&mut self.outgoing_edges[index].1),
             Err(index) => {
-                self.outgoing_edges.insert(index, (sink, Edge::new()));
+                self.outgoing_edges.insert(index, (sink, Edge::new(None)));
                 Ok(&mut self.outgoing_edges[index].1)
             }
         }
@@ -197,13 +1225,22 @@ impl GraphNode {
             .map(|(id, edge)| (GraphNodeRef(*id), edge))
     }
 
+    /// Returns an iterator of the outgoing edges of a kind from this node.
+    pub fn iter_edges_with_kind<'a>(
+        &'a self,
+        kind: &'a str,
+    ) -> impl Iterator<Item = (GraphNodeRef, &'a Edge)> + 'a {
+        self.iter_edges()
+            .filter(move |(_, edge)| matches!(&edge.kind, Some(edge_kind) if edge_kind == kind))
+    }
+
     // Returns the number of outgoing edges from this node.
     pub fn edge_count(&self) -> usize {
         self.outgoing_edges.len()
     }
 }
 
-struct SerializeGraphNode<'a>(usize, &'a GraphNode);
+struct SerializeGraphNode<'a>(usize, &'a GraphNode, &'a Attributes);
 
 impl<'a> Serialize for SerializeGraphNode<'a> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
@@ -212,13 +1249,16 @@ impl<'a> Serialize for SerializeGraphNode<'a> {
         // serializing as a map instead of a struct so we don't have to encode a struct name
         let mut map = serializer.serialize_map(None)?;
         map.serialize_entry("id", &node_index)?;
+        if let Some(label) = &node.label {
+            map.serialize_entry("label", label)?;
+        }
         map.serialize_entry("edges", &SerializeGraphNodeEdges(&node.outgoing_edges))?;
-        map.serialize_entry("attrs", &node.attributes)?;
+        map.serialize_entry("attrs", self.2)?;
         map.end()
     }
 }
 
-struct SerializeGraphNodeEdges<'a>(&'a SmallVec<[(GraphNodeID, Edge); 8]>);
+struct SerializeGraphNodeEdges<'a>(&'a [(GraphNodeID, Edge)]);
 
 impl<'a> Serialize for SerializeGraphNodeEdges<'a> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
@@ -240,88 +1280,189 @@ impl<'a> Serialize for SerializeGraphNodeEdge<'a> {
         let edge = &wrapped.1;
         let mut map = serializer.serialize_map(None)?;
         map.serialize_entry("sink", sink)?;
+        if let Some(kind) = &edge.kind {
+            map.serialize_entry("kind", kind)?;
+        }
         map.serialize_entry("attrs", &edge.attributes)?;
         map.end()
     }
 }
 
 /// An edge between two nodes in a graph
+#[derive(Clone)]
 pub struct Edge {
+    kind: Option<Identifier>,
     /// The set of attributes associated with this edge
     pub attributes: Attributes,
 }
 
 impl Edge {
-    fn new() -> Edge {
+    fn new(kind: Option<Identifier>) -> Edge {
         Edge {
+            kind,
             attributes: Attributes::new(),
         }
     }
+
+    /// Returns the kind of this edge, if it was created with one.
+    pub fn kind(&self) -> Option<&Identifier> {
+        self.kind.as_ref()
+    }
+
+    /// Returns the lines of the label of this edge in the DOT and Mermaid formats, which are its
+    /// kind, if it has one, and its attributes.
+    fn display_lines(&self) -> Vec<String> {
+        self.kind
+            .iter()
+            .map(|kind| kind.to_string())
+            .chain(
+                self.attributes
+                    .sorted()
+                    .into_iter()
+                    .map(|(name, value)| format!("{}: {}", name, value)),
+            )
+            .collect()
+    }
 }
 
 /// A set of attributes associated with a graph node or edge
-#[derive(Clone, Debug)]
+///
+/// Nodes and edges rarely have more than a few attributes, so they are stored as a vector sorted
+/// by name, which is smaller than a hash map and is iterated in a stable order.
+#[derive(Clone, Default)]
 pub struct Attributes {
-    values: HashMap<Identifier, Value>,
+    values: Vec<(Identifier, Value)>,
 }
 
 impl Attributes {
     /// Creates a new, empty set of attributes.
     pub fn new() -> Attributes {
-        Attributes {
-            values: HashMap::new(),
-        }
+        Attributes { values: Vec::new() }
+    }
+
+    /// Returns the position of an attribute in the sorted vector, or where it would be inserted.
+    fn position(&self, name: &str) -> Result<usize, usize> {
+        self.values
+            .binary_search_by(|(existing, _)| existing.as_str().cmp(name))
     }
 
     /// Adds an attribute to this attribute set.  If there was already an attribute with the same
     /// name, replaces its value and returns `Err`.
     pub fn add<V: Into<Value>>(&mut self, name: Identifier, value: V) -> Result<(), Value> {
-        match self.values.entry(name) {
-            Entry::Occupied(mut o) => {
-                let value = value.into();
-                if o.get() != &value {
-                    Err(o.insert(value.into()))
+        let value = value.into();
+        match self.position(&name) {
+            Ok(index) => {
+                let existing = &mut self.values[index].1;
+                if *existing != value {
+                    Err(std::mem::replace(existing, value))
                 } else {
                     Ok(())
                 }
             }
-            Entry::Vacant(v) => {
-                v.insert(value.into());
+            Err(index) => {
+                self.values.insert(index, (name, value));
+                Ok(())
+            }
+        }
+    }
+
+    /// Appends a value to a list-valued attribute of this attribute set, creating the list if
+    /// there is no attribute with the same name.  If there was already an attribute with the same
+    /// name that is not a list, leaves it unchanged and returns `Err`.
+    pub fn append<V: Into<Value>>(&mut self, name: Identifier, value: V) -> Result<(), Value> {
+        match self.position(&name) {
+            Ok(index) => match &mut self.values[index].1 {
+                Value::List(values) => {
+                    values.push(value.into());
+                    Ok(())
+                }
+                _ => Err(value.into()),
+            },
+            Err(index) => {
+                self.values
+                    .insert(index, (name, Value::List(vec![value.into()])));
                 Ok(())
             }
         }
     }
 
     /// Returns the value of a particular attribute, if it exists.
-    pub fn get<Q>(&self, name: &Q) -> Option<&Value>
-    where
-        Q: ?Sized + Eq + Hash,
-        Identifier: Borrow<Q>,
-    {
-        self.values.get(name.borrow())
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.position(name).ok().map(|index| &self.values[index].1)
     }
 
+    /// Returns an iterator of the attributes, sorted by name.
     pub fn iter(&self) -> impl Iterator<Item = (&Identifier, &Value)> {
-        self.values.iter()
+        self.values.iter().map(|(name, value)| (name, value))
+    }
+
+    /// Returns the attributes, sorted by name.
+    pub(crate) fn sorted(&self) -> Vec<(&Identifier, &Value)> {
+        self.iter().collect()
+    }
+
+    /// Writes the attributes, sorted by name, with values longer than `max_value_length`
+    /// characters elided.
+    fn write(&self, f: &mut fmt::Formatter, max_value_length: Option<usize>) -> fmt::Result {
+        for (name, value) in self.iter() {
+            let value = format!("{:?}", value);
+            match max_value_length {
+                Some(max) if value.chars().count() > max => {
+                    let elided = value.chars().take(max).collect::<String>();
+                    let length = value.chars().count();
+                    writeln!(f, "  {}: {}... ({} characters)", name, elided, length)?;
+                }
+                _ => writeln!(f, "  {}: {}", name, value)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the position of the earliest syntax node that the attributes refer to, including
+    /// in lists and sets.
+    fn span(&self) -> Option<Location> {
+        fn span(value: &Value) -> Option<Location> {
+            match value {
+                Value::SyntaxNode(node) => Some(node.location()),
+                Value::List(values) => values.iter().filter_map(span).min(),
+                Value::Set(values) => values.iter().filter_map(span).min(),
+                _ => None,
+            }
+        }
+        self.values
+            .iter()
+            .filter_map(|(_, value)| span(value))
+            .min()
+    }
+
+    /// Returns a copy of these attributes, with the indices of syntax nodes replaced.
+    fn rebase(&self, indices: &HashMap<SyntaxNodeID, SyntaxNodeID>) -> Attributes {
+        Attributes {
+            values: self
+                .values
+                .iter()
+                .map(|(name, value)| (name.clone(), value.rebase(indices)))
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Attributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
     }
 }
 
 impl std::fmt::Display for Attributes {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let mut keys = self.values.keys().collect::<Vec<_>>();
-        keys.sort_by(|a, b| a.cmp(b));
-        for key in &keys {
-            let value = &self.values[*key];
-            write!(f, "  {}: {:?}\n", key, value)?;
-        }
-        Ok(())
+        self.write(f, None)
     }
 }
 
 impl Serialize for Attributes {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let mut map = serializer.serialize_map(None)?;
-        for (key, value) in &self.values {
+        for (key, value) in self.iter() {
             map.serialize_entry(key, value)?;
         }
         map.end()
@@ -329,22 +1470,63 @@ impl Serialize for Attributes {
 }
 
 /// The value of an attribute
-#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+///
+/// Source slices are strings as well, which borrow from a shared copy of the source instead of
+/// owning their text.  They are equal to the strings with the same text, and behave like them in
+/// every other way.
+///
+/// More kinds of values may be added in future versions, so code that matches on values has to
+/// handle the kinds that it does not know about.  Use [`as_str`][Value::as_str] rather than
+/// matching on [`Value::String`][] to get the text of a string or source slice.
+#[derive(Clone)]
+#[non_exhaustive]
 pub enum Value {
     // Scalar
     Null,
     Boolean(bool),
-    Integer(u32),
+    Integer(i64),
     String(String),
+    SourceSlice(SourceSlice),
     // Compound
     List(Vec<Value>),
     Set(BTreeSet<Value>),
     // References
     SyntaxNode(SyntaxNodeRef),
     GraphNode(GraphNodeRef),
+    GraphEdge(GraphEdgeRef),
 }
 
 impl Value {
+    /// Returns the rank of the kind of this value, which orders values of different kinds.
+    /// Strings and source slices have the same rank.
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Boolean(_) => 1,
+            Value::Integer(_) => 2,
+            Value::String(_) | Value::SourceSlice(_) => 3,
+            Value::List(_) => 4,
+            Value::Set(_) => 5,
+            Value::SyntaxNode(_) => 6,
+            Value::GraphNode(_) => 7,
+            Value::GraphEdge(_) => 8,
+        }
+    }
+
+    /// Returns a copy of this value, with the indices of syntax nodes replaced.
+    fn rebase(&self, indices: &HashMap<SyntaxNodeID, SyntaxNodeID>) -> Value {
+        match self {
+            Value::List(values) => Value::List(values.iter().map(|v| v.rebase(indices)).collect()),
+            Value::Set(values) => Value::Set(values.iter().map(|v| v.rebase(indices)).collect()),
+            Value::SyntaxNode(node) => Value::SyntaxNode(SyntaxNodeRef {
+                index: indices[&node.index],
+                kind: node.kind,
+                position: node.position,
+            }),
+            value => value.clone(),
+        }
+    }
+
     /// Check if this value is null
     pub fn is_null(&self) -> bool {
         match self {
@@ -353,6 +1535,15 @@ impl Value {
         }
     }
 
+    /// Check if this value is null or an empty list, which is what `none` conditions test for
+    pub fn is_null_or_empty(&self) -> bool {
+        match self {
+            Value::Null => true,
+            Value::List(values) => values.is_empty(),
+            _ => false,
+        }
+    }
+
     /// Coerces this value into a boolean, returning an error if it's some other type of value.
     pub fn into_boolean(self) -> Result<bool, ExecutionError> {
         match self {
@@ -369,14 +1560,14 @@ impl Value {
     }
 
     /// Coerces this value into an integer, returning an error if it's some other type of value.
-    pub fn into_integer(self) -> Result<u32, ExecutionError> {
+    pub fn into_integer(self) -> Result<i64, ExecutionError> {
         match self {
             Value::Integer(value) => Ok(value),
             _ => Err(ExecutionError::ExpectedInteger(format!("got {}", self))),
         }
     }
 
-    pub fn as_integer(&self) -> Result<u32, ExecutionError> {
+    pub fn as_integer(&self) -> Result<i64, ExecutionError> {
         match self {
             Value::Integer(value) => Ok(*value),
             _ => Err(ExecutionError::ExpectedInteger(format!("got {}", self))),
@@ -387,14 +1578,22 @@ impl Value {
     pub fn into_string(self) -> Result<String, ExecutionError> {
         match self {
             Value::String(value) => Ok(value),
+            Value::SourceSlice(value) => Ok(value.as_str().to_string()),
             _ => Err(ExecutionError::ExpectedString(format!("got {}", self))),
         }
     }
 
     pub fn as_str(&self) -> Result<&str, ExecutionError> {
+        self.string()
+            .ok_or_else(|| ExecutionError::ExpectedString(format!("got {}", self)))
+    }
+
+    /// Returns the text of this value, if it is a string or a source slice.
+    fn string(&self) -> Option<&str> {
         match self {
-            Value::String(value) => Ok(value),
-            _ => Err(ExecutionError::ExpectedString(format!("got {}", self))),
+            Value::String(value) => Some(value),
+            Value::SourceSlice(value) => Some(value.as_str()),
+            _ => None,
         }
     }
 
@@ -464,7 +1663,7 @@ impl From<bool> for Value {
 
 impl From<u32> for Value {
     fn from(value: u32) -> Value {
-        Value::Integer(value)
+        Value::Integer(value.into())
     }
 }
 
@@ -480,6 +1679,12 @@ impl From<String> for Value {
     }
 }
 
+impl From<SourceSlice> for Value {
+    fn from(value: SourceSlice) -> Value {
+        Value::SourceSlice(value)
+    }
+}
+
 impl From<Vec<Value>> for Value {
     fn from(value: Vec<Value>) -> Value {
         Value::List(value)
@@ -505,6 +1710,7 @@ impl std::fmt::Display for Value {
             }
             Value::Integer(value) => write!(f, "{}", value),
             Value::String(value) => write!(f, "{}", value),
+            Value::SourceSlice(value) => write!(f, "{}", value.as_str()),
             Value::List(value) => {
                 write!(f, "[")?;
                 let mut first = true;
@@ -533,6 +1739,7 @@ impl std::fmt::Display for Value {
             }
             Value::SyntaxNode(node) => node.fmt(f),
             Value::GraphNode(node) => node.fmt(f),
+            Value::GraphEdge(edge) => edge.fmt(f),
         }
     }
 }
@@ -550,6 +1757,7 @@ impl std::fmt::Debug for Value {
             }
             Value::Integer(value) => write!(f, "{:?}", value),
             Value::String(value) => write!(f, "{:?}", value),
+            Value::SourceSlice(value) => write!(f, "{:?}", value.as_str()),
             Value::List(value) => {
                 write!(f, "[")?;
                 let mut first = true;
@@ -578,6 +1786,7 @@ impl std::fmt::Debug for Value {
             }
             Value::SyntaxNode(node) => node.fmt(f),
             Value::GraphNode(node) => node.fmt(f),
+            Value::GraphEdge(edge) => edge.fmt(f),
         }
     }
 }
@@ -608,6 +1817,12 @@ impl Serialize for Value {
                 map.serialize_entry("string", str)?;
                 map.end()
             }
+            Value::SourceSlice(slice) => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "string")?;
+                map.serialize_entry("string", slice.as_str())?;
+                map.end()
+            }
             Value::List(list) => {
                 let mut map = serializer.serialize_map(None)?;
                 map.serialize_entry("type", "list")?;
@@ -632,10 +1847,144 @@ impl Serialize for Value {
                 map.serialize_entry("id", &node.0)?;
                 map.end()
             }
+            Value::GraphEdge(edge) => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "graphEdge")?;
+                map.serialize_entry("source", &edge.source.0)?;
+                map.serialize_entry("sink", &edge.sink.0)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Values of different kinds are ordered by kind, and strings by their text, whether they are
+/// source slices or not.
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Boolean(left), Value::Boolean(right)) => left.cmp(right),
+            (Value::Integer(left), Value::Integer(right)) => left.cmp(right),
+            (Value::List(left), Value::List(right)) => left.cmp(right),
+            (Value::Set(left), Value::Set(right)) => left.cmp(right),
+            (Value::SyntaxNode(left), Value::SyntaxNode(right)) => left.cmp(right),
+            (Value::GraphNode(left), Value::GraphNode(right)) => left.cmp(right),
+            (Value::GraphEdge(left), Value::GraphEdge(right)) => left.cmp(right),
+            _ => match (self.string(), other.string()) {
+                (Some(left), Some(right)) => left.cmp(right),
+                _ => self.rank().cmp(&other.rank()),
+            },
+        }
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Value::Null => {}
+            Value::Boolean(value) => value.hash(state),
+            Value::Integer(value) => value.hash(state),
+            Value::String(value) => value.as_str().hash(state),
+            Value::SourceSlice(value) => value.as_str().hash(state),
+            Value::List(values) => values.hash(state),
+            Value::Set(values) => values.hash(state),
+            Value::SyntaxNode(node) => node.hash(state),
+            Value::GraphNode(node) => node.hash(state),
+            Value::GraphEdge(edge) => edge.hash(state),
+        }
+    }
+}
+
+/// A string that is a range of a shared copy of a source.  Many values are the text of syntax
+/// nodes, which [`source-text`][crate::reference::functions#source-text] returns as source slices,
+/// so that executions do not copy the text of each node.
+#[derive(Clone)]
+pub struct SourceSlice {
+    source: Arc<str>,
+    start: usize,
+    end: usize,
+}
+
+impl SourceSlice {
+    /// Creates a source slice for a range of a source.  Returns `None` if the range is not in the
+    /// source, or does not start and end at character boundaries.
+    pub fn new(source: Arc<str>, range: Range<usize>) -> Option<SourceSlice> {
+        source.get(range.clone())?;
+        Some(SourceSlice {
+            source,
+            start: range.start,
+            end: range.end,
+        })
+    }
+
+    /// Returns the text of this slice.
+    pub fn as_str(&self) -> &str {
+        &self.source[self.start..self.end]
+    }
+
+    /// Returns the source that this slice is a range of.
+    pub fn source(&self) -> &Arc<str> {
+        &self.source
+    }
+
+    /// Returns the range of the source that this slice consists of.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+impl From<&str> for SourceSlice {
+    fn from(value: &str) -> SourceSlice {
+        SourceSlice {
+            source: value.into(),
+            start: 0,
+            end: value.len(),
         }
     }
 }
 
+impl fmt::Debug for SourceSlice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl fmt::Display for SourceSlice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl PartialEq for SourceSlice {
+    fn eq(&self, other: &SourceSlice) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SourceSlice {}
+
+impl Hash for SourceSlice {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
 /// A reference to a syntax node in a graph
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct SyntaxNodeRef {
@@ -717,3 +2066,44 @@ impl std::fmt::Debug for GraphNodeRef {
         write!(f, "[graph node {}]", self.0)
     }
 }
+
+/// A reference to the edge between two graph nodes
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GraphEdgeRef {
+    source: GraphNodeRef,
+    sink: GraphNodeRef,
+}
+
+impl GraphEdgeRef {
+    pub(crate) fn new(source: GraphNodeRef, sink: GraphNodeRef) -> GraphEdgeRef {
+        GraphEdgeRef { source, sink }
+    }
+
+    /// Returns the graph node that this edge starts at.
+    pub fn source(self) -> GraphNodeRef {
+        self.source
+    }
+
+    /// Returns the graph node that this edge ends at.
+    pub fn sink(self) -> GraphNodeRef {
+        self.sink
+    }
+}
+
+impl From<GraphEdgeRef> for Value {
+    fn from(value: GraphEdgeRef) -> Value {
+        Value::GraphEdge(value)
+    }
+}
+
+impl std::fmt::Display for GraphEdgeRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[graph edge {} -> {}]", self.source.0, self.sink.0)
+    }
+}
+
+impl std::fmt::Debug for GraphEdgeRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[graph edge {} -> {}]", self.source.0, self.sink.0)
+    }
+}
@@ -0,0 +1,200 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines the canonical text format of graphs, which is meant for golden files that are compared
+//! with `git diff`.
+//!
+//! Every line of the format is a complete fact about the graph, which names the nodes that it is
+//! about, so that a line that changes means the same thing on its own in a diff:
+//!
+//! ``` text
+//! node 3b9f04c1a2e7 : "definition"
+//! attr 3b9f04c1a2e7 name = "x"
+//! edge 3b9f04c1a2e7 -"calls"-> 8c01d2e3f4a5
+//! attr 3b9f04c1a2e7 -> 8c01d2e3f4a5 precedence = 1
+//! ```
+//!
+//! The ID of a node is a hash of its label and attributes, and of the labels and attributes of
+//! the nodes that it is connected to, so that it does not depend on the order in which the nodes
+//! were created, and does not change when unrelated nodes are added or removed.  Nodes whose IDs
+//! are the same get a suffix, as in `3b9f04c1a2e7-2`, in the order in which they are written.
+//! Nodes are written in the order of their contents, each followed by its attributes, in the
+//! order of their names, and its outgoing edges, in the order of the IDs of their sinks.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Write;
+
+use crate::graph::Edge;
+use crate::graph::Graph;
+use crate::graph::Value;
+
+impl<'tree> Graph<'tree> {
+    /// Returns the contents of this graph in a canonical, line-oriented text format, which is meant
+    /// for golden files that are compared with `git diff`.  Unlike
+    /// [`pretty_print`][Self::pretty_print], the text does not depend on the order in which the
+    /// nodes, edges, and attributes of the graph were created, so graphs with the same contents
+    /// have the same text, and small changes to a graph are small changes to its text.  See the
+    /// [module documentation][self] for the details of the format.
+    pub fn to_canonical_text(&self) -> String {
+        let mut text = String::new();
+        self.write_canonical(&mut text)
+            .expect("cannot write to string");
+        text
+    }
+
+    fn write_canonical(&self, f: &mut String) -> fmt::Result {
+        let (ids, order) = self.canonical_ids();
+        for index in order {
+            let id = &ids[index];
+            let node = &self.graph_nodes[index];
+            match &node.label {
+                Some(label) => writeln!(f, "node {} : {:?}", id, label.as_str())?,
+                None => writeln!(f, "node {}", id)?,
+            }
+            for (name, value) in self.node_attributes_at(index).iter() {
+                writeln!(f, "attr {} {} = {}", id, name, canonical_value(value, &ids))?;
+            }
+            let mut edges = node
+                .outgoing_edges
+                .iter()
+                .map(|(sink, edge)| (&ids[*sink as usize], edge))
+                .collect::<Vec<_>>();
+            edges.sort_by_key(|(sink, _)| *sink);
+            for (sink, edge) in edges {
+                match &edge.kind {
+                    Some(kind) => writeln!(f, "edge {} -{:?}-> {}", id, kind.as_str(), sink)?,
+                    None => writeln!(f, "edge {} -> {}", id, sink)?,
+                }
+                for (name, value) in edge.attributes.iter() {
+                    let value = canonical_value(value, &ids);
+                    writeln!(f, "attr {} -> {} {} = {}", id, sink, name, value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the canonical ID of each graph node, and the order in which the nodes are written.
+    fn canonical_ids(&self) -> (Vec<String>, Vec<usize>) {
+        let count = self.graph_nodes.len();
+        // The contents of the nodes, without the nodes that they refer to, identify them in the
+        // contents of the nodes that they are connected to.
+        let placeholders = vec!["_".to_string(); count];
+        let content_ids = (0..count)
+            .map(|index| hash_id(&self.node_contents(index, &placeholders)))
+            .collect::<Vec<_>>();
+        let mut connections = vec![Vec::new(); count];
+        for (source, node) in self.graph_nodes.iter().enumerate() {
+            for (sink, edge) in &node.outgoing_edges {
+                let sink = *sink as usize;
+                let contents = edge_contents(edge, &content_ids);
+                connections[source].push(format!("-> {}{}", content_ids[sink], contents));
+                connections[sink].push(format!("<- {}{}", content_ids[source], contents));
+            }
+        }
+        let keys = connections
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut connections)| {
+                connections.sort();
+                let mut key = self.node_contents(index, &content_ids);
+                for connection in connections {
+                    key.push('\n');
+                    key += &connection;
+                }
+                key
+            })
+            .collect::<Vec<_>>();
+        // The sort is stable, so nodes with the same contents stay in the order of their creation.
+        let mut order = (0..count).collect::<Vec<_>>();
+        order.sort_by(|left, right| keys[*left].cmp(&keys[*right]));
+        let mut ids = vec![String::new(); count];
+        let mut used = HashSet::new();
+        for index in &order {
+            let id = hash_id(&keys[*index]);
+            let mut unique = id.clone();
+            let mut suffix = 2;
+            while !used.insert(unique.clone()) {
+                unique = format!("{}-{}", id, suffix);
+                suffix += 1;
+            }
+            ids[*index] = unique;
+        }
+        (ids, order)
+    }
+
+    /// Returns the label and attributes of a graph node, with the nodes that they refer to
+    /// replaced by `ids`.
+    fn node_contents(&self, index: usize, ids: &[String]) -> String {
+        let mut contents = match &self.graph_nodes[index].label {
+            Some(label) => format!("{:?}", label.as_str()),
+            None => String::new(),
+        };
+        for (name, value) in self.node_attributes_at(index).iter() {
+            contents += &format!("\n{} = {}", name, canonical_value(value, ids));
+        }
+        contents
+    }
+}
+
+/// Returns the kind and attributes of an edge, with the nodes that they refer to replaced by
+/// `ids`.
+fn edge_contents(edge: &Edge, ids: &[String]) -> String {
+    let mut contents = match &edge.kind {
+        Some(kind) => format!(" {:?}", kind.as_str()),
+        None => String::new(),
+    };
+    for (name, value) in edge.attributes.iter() {
+        contents += &format!(" {} = {}", name, canonical_value(value, ids));
+    }
+    contents
+}
+
+/// Returns the text of a value on a single line, with the graph nodes that it refers to replaced
+/// by `ids`.  The elements of sets are in the order of their text.
+fn canonical_value(value: &Value, ids: &[String]) -> String {
+    match value {
+        Value::String(value) => format!("{:?}", value),
+        Value::SourceSlice(value) => format!("{:?}", value.as_str()),
+        Value::List(values) => {
+            let elements = values
+                .iter()
+                .map(|value| canonical_value(value, ids))
+                .collect::<Vec<_>>();
+            format!("[{}]", elements.join(", "))
+        }
+        Value::Set(values) => {
+            let mut elements = values
+                .iter()
+                .map(|value| canonical_value(value, ids))
+                .collect::<Vec<_>>();
+            elements.sort();
+            format!("{{{}}}", elements.join(", "))
+        }
+        Value::GraphNode(node) => format!("[graph node {}]", ids[node.index()]),
+        Value::GraphEdge(edge) => format!(
+            "[graph edge {} -> {}]",
+            ids[edge.source().index()],
+            ids[edge.sink().index()]
+        ),
+        Value::Null | Value::Boolean(_) | Value::Integer(_) | Value::SyntaxNode(_) => {
+            value.to_string()
+        }
+    }
+}
+
+/// Returns an ID for some contents, which is 48 bits of their FNV-1a hash.  The hash does not
+/// depend on the platform or the version of Rust, so IDs are the same everywhere.
+fn hash_id(contents: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in contents.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:012x}", hash >> 16)
+}
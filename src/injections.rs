@@ -0,0 +1,252 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Executes graph DSL files on regions of a source file that are written in another language,
+//! such as JavaScript inside HTML, or SQL inside Python strings.
+//!
+//! Injected regions are found with a tree-sitter query over the host language, in the same
+//! format as the `injections.scm` files of tree-sitter grammars.  Each match captures the region
+//! as `@injection.content`, and names its language either with an `@injection.language` capture,
+//! whose text is the name, or with a `(#set! injection.language "name")` property.  Regions whose
+//! language has not been added to the [`Injections`][] are skipped.
+//!
+//! Every injected region is parsed on its own, and then executed with the graph DSL file of its
+//! language, into the same graph as the host file.  The region's syntax nodes keep their
+//! positions in the host source, so functions like `source-text` work as usual.  To connect the
+//! graphs, each region gets a graph node with a `language` attribute, which the injected file can
+//! refer to as a global variable, `INJECTION` by default, to create cross-language edges:
+//!
+//! ``` tsg
+//! global INJECTION
+//!
+//! (module) @root {
+//!   node @root.node
+//!   edge INJECTION -> @root.node
+//! }
+//! ```
+//!
+//! Since the graph borrows the syntax trees of injected regions, they are parsed before
+//! execution, with [`Injections::parse`][]:
+//!
+//! ``` ignore
+//! let injected = injections.parse(&tree, source)?;
+//! let mut graph = Graph::new();
+//! host_file.execute_into(&mut graph, &tree, source, &config, &NoCancellation)?;
+//! injections.execute_into(&mut graph, source, &injected, &config, &NoCancellation)?;
+//! ```
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+use tracing::debug;
+use tree_sitter::Language;
+use tree_sitter::Parser;
+use tree_sitter::Query;
+use tree_sitter::QueryCursor;
+use tree_sitter::QueryError;
+use tree_sitter::Range;
+use tree_sitter::Tree;
+
+use crate::ast::File;
+use crate::execution::error::ExecutionError;
+use crate::graph::Graph;
+use crate::graph::Value;
+use crate::variables::Globals;
+use crate::CancellationFlag;
+use crate::ExecutionConfig;
+use crate::Identifier;
+
+/// The capture of injection queries that matches an injected region
+const CONTENT_CAPTURE: &str = "injection.content";
+/// The capture or property of injection queries that names the language of an injected region
+const LANGUAGE_CAPTURE: &str = "injection.language";
+
+/// An error that occurs when finding or parsing injected regions
+#[derive(Debug, Error)]
+pub enum InjectionError {
+    #[error("Invalid injection query: {0}")]
+    InvalidQuery(#[from] QueryError),
+    #[error("Injection query has no @{} capture", CONTENT_CAPTURE)]
+    MissingContentCapture,
+    #[error("Cannot parse injected {0} region: {1}")]
+    ParseFailed(String, String),
+}
+
+/// The languages that can be injected into a host language, and the graph DSL files that are
+/// executed on them.
+pub struct Injections<'a> {
+    query: Query,
+    content_index: u32,
+    language_index: Option<u32>,
+    languages: HashMap<String, (Language, &'a File)>,
+    global: Identifier,
+}
+
+impl<'a> Injections<'a> {
+    /// Creates a new set of injections, which finds injected regions with an injection query
+    /// over the host language.
+    pub fn new(host_language: &Language, query_source: &str) -> Result<Self, InjectionError> {
+        let query = Query::new(host_language, query_source)?;
+        let content_index = query
+            .capture_index_for_name(CONTENT_CAPTURE)
+            .ok_or(InjectionError::MissingContentCapture)?;
+        let language_index = query.capture_index_for_name(LANGUAGE_CAPTURE);
+        Ok(Self {
+            query,
+            content_index,
+            language_index,
+            languages: HashMap::new(),
+            global: Identifier::from("INJECTION"),
+        })
+    }
+
+    /// Adds an injected language, whose regions are executed with `file`, which must have been
+    /// parsed for the same language.
+    pub fn add_language(&mut self, name: &str, language: Language, file: &'a File) {
+        self.languages.insert(name.to_string(), (language, file));
+    }
+
+    /// Sets the name of the global variable that holds the graph node of an injected region.
+    pub fn global(mut self, global: Identifier) -> Self {
+        self.global = global;
+        self
+    }
+
+    /// Finds and parses the injected regions of a host source file, in the order in which they
+    /// occur.
+    pub fn parse(&self, tree: &Tree, source: &str) -> Result<Vec<InjectedTree>, InjectionError> {
+        let mut injected = Vec::new();
+        let mut parser = Parser::new();
+        let mut cursor = QueryCursor::new();
+        for mat in cursor.matches(&self.query, tree.root_node(), source.as_bytes()) {
+            let name = match self.language_index {
+                Some(index) => mat
+                    .nodes_for_capture_index(index)
+                    .next()
+                    .and_then(|node| node.utf8_text(source.as_bytes()).ok())
+                    .map(str::to_string),
+                None => None,
+            };
+            let name = name.or_else(|| {
+                self.query
+                    .property_settings(mat.pattern_index)
+                    .iter()
+                    .find(|property| &*property.key == LANGUAGE_CAPTURE)
+                    .and_then(|property| property.value.as_deref())
+                    .map(str::to_string)
+            });
+            let (name, language) = match name.and_then(|name| {
+                self.languages
+                    .get(&name)
+                    .map(|(language, _)| (name, language))
+            }) {
+                Some(language) => language,
+                None => continue,
+            };
+            for node in mat.nodes_for_capture_index(self.content_index) {
+                debug!("injected {} region at {:?}", name, node.range());
+                parser
+                    .set_language(language)
+                    .map_err(|e| InjectionError::ParseFailed(name.clone(), e.to_string()))?;
+                parser
+                    .set_included_ranges(&[node.range()])
+                    .map_err(|e| InjectionError::ParseFailed(name.clone(), e.to_string()))?;
+                let tree = parser.parse(source, None).ok_or_else(|| {
+                    InjectionError::ParseFailed(name.clone(), "parsing failed".to_string())
+                })?;
+                injected.push(InjectedTree {
+                    language: name.clone(),
+                    range: node.range(),
+                    tree,
+                });
+            }
+        }
+        injected.sort_by_key(|injected| injected.range.start_byte);
+        Ok(injected)
+    }
+
+    /// Executes the graph DSL files of the injected languages on their regions, saving the
+    /// results into a graph that usually already contains the results of the host file.
+    pub fn execute_into<'tree>(
+        &self,
+        graph: &mut Graph<'tree>,
+        source: &'tree str,
+        injected: &'tree [InjectedTree],
+        config: &ExecutionConfig,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), ExecutionError> {
+        for injected in injected {
+            let file = match self.languages.get(&injected.language) {
+                Some((_, file)) => file,
+                None => continue,
+            };
+            let node = graph.add_graph_node();
+            graph
+                .add_node_attribute(
+                    node,
+                    Identifier::from("language"),
+                    injected.language.as_str(),
+                )
+                .expect("new graph nodes have no attributes");
+            let mut globals = Globals::nested(config.globals);
+            globals
+                .add(self.global.clone(), Value::GraphNode(node))
+                .map_err(|_| {
+                    ExecutionError::DuplicateVariable(format!(
+                        "global variable {} already defined",
+                        self.global
+                    ))
+                })?;
+            let config = ExecutionConfig {
+                functions: config.functions,
+                globals: &globals,
+                lazy: config.lazy,
+                location_attr: config.location_attr.clone(),
+                variable_name_attr: config.variable_name_attr.clone(),
+                match_node_attr: config.match_node_attr.clone(),
+                environment: config.environment,
+                statistics: config.statistics,
+                limits: config.limits,
+                extensions: config.extensions,
+                sink: config.sink,
+                source_map: config.source_map,
+                debugger: config.debugger,
+                trace: config.trace,
+                coverage: config.coverage,
+                seed: config.seed,
+                output: config.output,
+                attribute_storage: config.attribute_storage,
+            };
+            file.execute_into(graph, &injected.tree, source, &config, cancellation_flag)?;
+        }
+        Ok(())
+    }
+}
+
+/// The syntax tree of an injected region of a host source file
+pub struct InjectedTree {
+    language: String,
+    range: Range,
+    tree: Tree,
+}
+
+impl InjectedTree {
+    /// Returns the name of the language of the region.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Returns the range of the region in the host source file.
+    pub fn range(&self) -> Range {
+        self.range
+    }
+
+    /// Returns the syntax tree of the region.
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+}
@@ -28,24 +28,104 @@
 #[cfg(doc)]
 pub mod reference;
 
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+#[cfg(feature = "artifact")]
+mod artifact;
 pub mod ast;
+#[cfg(feature = "execution")]
+pub mod bench;
 mod checker;
+pub mod diagnostic;
+#[cfg(feature = "execution")]
 mod execution;
+pub mod explain;
+pub mod extensions;
+mod formatter;
+#[cfg(feature = "execution")]
 pub mod functions;
+#[cfg(feature = "execution")]
 pub mod graph;
+#[cfg(feature = "execution")]
+pub mod injections;
+#[cfg(feature = "execution")]
+mod optimizer;
 pub mod parse_error;
 mod parser;
+pub mod rule_set;
+#[cfg(feature = "execution")]
+pub mod testing;
 mod variables;
-
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "artifact")]
+pub use artifact::ArtifactError;
+pub use checker::CheckError;
+pub use diagnostic::Diagnostic;
+#[cfg(feature = "execution")]
+pub use execution::coverage::ArmCoverage;
+#[cfg(feature = "execution")]
+pub use execution::coverage::CoverageCount;
+#[cfg(feature = "execution")]
+pub use execution::coverage::ExecutionCoverage;
+#[cfg(feature = "execution")]
+pub use execution::debugger::DebugFrame;
+#[cfg(feature = "execution")]
+pub use execution::debugger::Debugger;
+#[cfg(feature = "execution")]
 pub use execution::error::ExecutionError;
+#[cfg(feature = "execution")]
+pub use execution::error::StatementContext;
+#[cfg(feature = "execution")]
+pub use execution::limits::ExecutionLimits;
+#[cfg(feature = "execution")]
+pub use execution::outcome::ExecutionOutcome;
+#[cfg(feature = "execution")]
+pub use execution::outcome::ExecutionWarning;
+#[cfg(feature = "execution")]
+pub use execution::output::PrintOutput;
+#[cfg(feature = "execution")]
+pub use execution::output::StderrOutput;
+#[cfg(feature = "execution")]
+pub use execution::sink::GraphSink;
+#[cfg(feature = "execution")]
+pub use execution::sink::SinkAction;
+#[cfg(feature = "execution")]
+pub use execution::source_map::SourceMap;
+#[cfg(feature = "execution")]
+pub use execution::statistics::ExecutionStatistics;
+#[cfg(feature = "execution")]
+pub use execution::statistics::StanzaStatistics;
+#[cfg(feature = "execution")]
+pub use execution::trace::ExecutionTrace;
+#[cfg(feature = "execution")]
+pub use execution::trace::TracedCapture;
+#[cfg(feature = "execution")]
+pub use execution::trace::TracedMatch;
+#[cfg(feature = "execution")]
+pub use execution::trace::TracedStatement;
+#[cfg(feature = "execution")]
+pub use execution::trace::TracedValue;
+#[cfg(feature = "execution")]
 pub use execution::CancellationError;
+#[cfg(feature = "execution")]
 pub use execution::CancellationFlag;
+#[cfg(feature = "execution")]
 pub use execution::ExecutionConfig;
+#[cfg(feature = "execution")]
 pub use execution::Match;
+#[cfg(feature = "execution")]
 pub use execution::NoCancellation;
 pub use parser::Location;
 pub use parser::ParseError;
+pub use parser::ParseLimits;
+pub use parser::Range;
+pub use rule_set::RuleSet;
+pub use rule_set::RuleSetError;
+#[cfg(feature = "execution")]
 pub use variables::Globals as Variables;
+#[cfg(feature = "execution")]
 pub use variables::Iter as VariableIter;
 pub use variables::VariableError;
 
@@ -54,10 +134,15 @@ use std::hash::Hash;
 use std::ops::Deref;
 use std::sync::Arc;
 
+use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
 
 /// An identifier that appears in a graph DSL file or in the graph that is produced as an output.
+///
+/// Identifiers are reference-counted strings that are not interned in any shared context, so
+/// files and graphs that contain them can be used from several threads.
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Identifier(Arc<String>);
 
@@ -120,3 +205,9 @@ impl Serialize for Identifier {
         serializer.serialize_str(self.as_str())
     }
 }
+
+impl<'de> Deserialize<'de> for Identifier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Identifier, D::Error> {
+        String::deserialize(deserializer).map(|name| Identifier(Arc::new(name)))
+    }
+}
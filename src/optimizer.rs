@@ -0,0 +1,160 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::collections::hash_map::Entry::Occupied;
+use std::collections::hash_map::Entry::Vacant;
+use std::collections::HashMap;
+
+use tracing::debug;
+
+use crate::ast;
+use crate::ast::visit_mut;
+use crate::ast::Expression;
+use crate::ast::VisitorMut;
+use crate::functions::Functions;
+use crate::graph::Graph;
+use crate::graph::Value;
+
+impl ast::File {
+    /// Optimizes this file, which must have been checked, for executions with a library of
+    /// functions.  Rule files that are generated often contain many stanzas with the same query
+    /// pattern, and expressions that only consist of constants, which this pass takes care of:
+    ///
+    ///  - Stanzas with identical query patterns are merged into the pattern of the first of them,
+    ///    so that lazy executions find their matches once and execute each of the stanzas on
+    ///    them, in the order of the stanzas.  The other patterns are disabled in the combined
+    ///    query.  The stanzas keep their indices and guards.  Strict executions execute each
+    ///    stanza on all of its matches before the next stanza, and are not affected.
+    ///
+    ///  - Calls of [pure][crate::functions::Function::is_pure] functions whose parameters are
+    ///    constants are replaced by their results.  Calls that fail are kept, so that they fail
+    ///    when the file is executed.
+    ///
+    /// The file must be executed with the same library of functions.  Optimizing a file more than
+    /// once has no further effect.
+    pub fn optimize(&mut self, functions: &Functions) {
+        self.merge_stanzas();
+        ConstantFolder { functions }.visit_file(self);
+    }
+
+    /// Merges the stanzas whose query patterns are the same as the pattern of an earlier stanza.
+    fn merge_stanzas(&mut self) {
+        let patterns = (0..self.pattern_stanzas.len())
+            .map(|index| self.stanza_pattern(index).to_string())
+            .collect::<Vec<_>>();
+        let query = match &mut self.query {
+            Some(query) => query,
+            None => return,
+        };
+        let mut first_patterns = HashMap::new();
+        for (index, pattern) in patterns.into_iter().enumerate() {
+            // Patterns without stanzas are disabled, or were merged already.
+            if self.pattern_stanzas[index].is_empty() {
+                continue;
+            }
+            match first_patterns.entry(pattern) {
+                Vacant(entry) => {
+                    entry.insert(index);
+                }
+                Occupied(entry) => {
+                    let stanzas = std::mem::take(&mut self.pattern_stanzas[index]);
+                    self.pattern_stanzas[*entry.get()].extend(stanzas);
+                    query.disable_pattern(index);
+                    debug!("merged stanza {} into pattern {}", index, entry.get());
+                }
+            }
+        }
+    }
+}
+
+/// Replaces calls of pure functions whose parameters are constants by their results.  The
+/// parameters are folded first, so that nested calls are folded as well.
+struct ConstantFolder<'a> {
+    functions: &'a Functions,
+}
+
+impl ConstantFolder<'_> {
+    fn evaluate(&self, call: &ast::Call) -> Option<Value> {
+        if !self.functions.is_pure(&call.function) {
+            return None;
+        }
+        let parameters = call
+            .parameters
+            .iter()
+            .map(constant_value)
+            .collect::<Option<Vec<_>>>()?;
+        // Pure functions do not use the graph or the source.
+        let mut graph = Graph::new();
+        self.functions
+            .call(&call.function, &mut graph, "", &mut parameters.into_iter())
+            .ok()
+    }
+}
+
+impl VisitorMut for ConstantFolder<'_> {
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        visit_mut::walk_expression(self, expr);
+        if let Expression::Call(call) = expr {
+            if let Some(folded) = self.evaluate(call).and_then(constant_expression) {
+                *expr = folded;
+            }
+        }
+    }
+}
+
+/// Returns the value of an expression that only consists of constants.
+fn constant_value(expr: &Expression) -> Option<Value> {
+    match expr {
+        Expression::FalseLiteral => Some(false.into()),
+        Expression::NullLiteral => Some(Value::Null),
+        Expression::TrueLiteral => Some(true.into()),
+        Expression::IntegerConstant(expr) => Some(Value::Integer(expr.value)),
+        Expression::StringConstant(expr) => Some(Value::String(expr.value.clone())),
+        Expression::ListLiteral(expr) => expr
+            .elements
+            .iter()
+            .map(constant_value)
+            .collect::<Option<Vec<_>>>()
+            .map(Value::List),
+        Expression::SetLiteral(expr) => expr
+            .elements
+            .iter()
+            .map(constant_value)
+            .collect::<Option<_>>()
+            .map(Value::Set),
+        _ => None,
+    }
+}
+
+/// Returns an expression that only consists of constants and evaluates to a value, if there is
+/// one.  References to syntax nodes and the graph cannot be written as constants.
+fn constant_expression(value: Value) -> Option<Expression> {
+    match value {
+        Value::Null => Some(Expression::NullLiteral),
+        Value::Boolean(true) => Some(Expression::TrueLiteral),
+        Value::Boolean(false) => Some(Expression::FalseLiteral),
+        Value::Integer(value) => Some(ast::IntegerConstant { value }.into()),
+        Value::String(value) => Some(ast::StringConstant { value }.into()),
+        Value::SourceSlice(value) => Some(
+            ast::StringConstant {
+                value: value.as_str().to_string(),
+            }
+            .into(),
+        ),
+        Value::List(values) => values
+            .into_iter()
+            .map(constant_expression)
+            .collect::<Option<Vec<_>>>()
+            .map(|elements| ast::ListLiteral { elements }.into()),
+        Value::Set(values) => values
+            .into_iter()
+            .map(constant_expression)
+            .collect::<Option<Vec<_>>>()
+            .map(|elements| ast::SetLiteral { elements }.into()),
+        Value::SyntaxNode(_) | Value::GraphNode(_) | Value::GraphEdge(_) => None,
+    }
+}
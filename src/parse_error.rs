@@ -375,6 +375,8 @@ impl<'a> Excerpt<'a> {
     ) -> Excerpt<'a> {
         let source = source.lines().nth(row);
         columns.end = std::cmp::min(columns.end, source.map(|s| s.len()).unwrap_or_default());
+        // Always underline at least one column, so that the location is visible.
+        columns.end = std::cmp::max(columns.end, columns.start + 1);
         Excerpt {
             path,
             source,
@@ -11,7 +11,11 @@ use std::path::Path;
 use std::str::Chars;
 
 use regex::Regex;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
+use tracing::debug;
+use tracing::debug_span;
 use tree_sitter::CaptureQuantifier;
 use tree_sitter::CaptureQuantifier::One;
 use tree_sitter::CaptureQuantifier::OneOrMore;
@@ -23,6 +27,7 @@ use tree_sitter::Query;
 use tree_sitter::QueryError;
 
 use crate::ast;
+use crate::extensions::Extensions;
 use crate::parse_error::Excerpt;
 use crate::Identifier;
 
@@ -31,13 +36,86 @@ pub const FULL_MATCH: &str = "__tsg__full_match";
 impl ast::File {
     /// Parses a graph DSL file, returning a new `File` instance.
     pub fn from_str(language: Language, source: &str) -> Result<Self, ParseError> {
+        Self::from_str_with_limits(language, source, &ParseLimits::default())
+    }
+
+    /// Parses a graph DSL file that can contain the statements of a library of
+    /// [extensions][crate::extensions], returning a new `File` instance.
+    pub fn from_str_with_extensions(
+        language: Language,
+        source: &str,
+        extensions: &Extensions,
+    ) -> Result<Self, ParseError> {
+        let _span = debug_span!("parse", bytes = source.len()).entered();
+        let mut file = ast::File::new(language);
+        let mut parser = Parser::new(source);
+        parser.extensions = Some(extensions);
+        parser.parse_into_file(&mut file)?;
+        debug!(
+            "parsed {} stanzas and {} globals",
+            file.stanzas.len(),
+            file.globals.len()
+        );
+        file.check_with_extensions(Some(extensions))?;
+        Ok(file)
+    }
+
+    /// Parses a graph DSL file, returning a new `File` instance, and fails with
+    /// [`ParseError::NestingTooDeep`][] if the file is nested more deeply than `limits` allow.
+    /// The other constructors use the [default limits][ParseLimits::default].
+    pub fn from_str_with_limits(
+        language: Language,
+        source: &str,
+        limits: &ParseLimits,
+    ) -> Result<Self, ParseError> {
+        let _span = debug_span!("parse", bytes = source.len()).entered();
         let mut file = ast::File::new(language);
-        #[allow(deprecated)]
-        file.parse(source)?;
+        let mut parser = Parser::new(source);
+        parser.limits = limits.clone();
+        parser.parse_into_file(&mut file)?;
+        debug!(
+            "parsed {} stanzas and {} globals",
+            file.stanzas.len(),
+            file.globals.len()
+        );
         file.check()?;
         Ok(file)
     }
 
+    /// Parses a graph DSL file, recovering from syntax errors at the boundaries of statements and
+    /// stanzas, and returns the globals, stanzas, and statements that did parse, along with
+    /// every syntax error in the file.  The file is not checked, so it is only suitable for
+    /// tools such as editors, and not for execution.
+    pub fn parse_with_recovery(language: Language, source: &str) -> (Self, Vec<ParseError>) {
+        let mut file = ast::File::new(language);
+        let mut parser = Parser::new(source);
+        parser.errors = Some(Vec::new());
+        let result = parser.parse_into_file(&mut file);
+        let mut errors = parser.errors.take().unwrap_or_default();
+        errors.extend(result.err());
+        (file, errors)
+    }
+
+    /// Parses and checks a graph DSL file like [`File::from_str`][], but instead of stopping at
+    /// the first error, returns every syntax error and every check error in the file.  Stanzas
+    /// that contain syntax errors are not checked, since the statements that did not parse would
+    /// cause spurious check errors.  Each stanza reports at most one check error.
+    pub fn check_all(language: Language, source: &str) -> Result<Self, Vec<ParseError>> {
+        let (mut file, mut errors) = Self::parse_with_recovery(language, source);
+        let syntax_errors = errors.iter().map(ParseError::location).collect::<Vec<_>>();
+        let check_errors = file.check_all_with_extensions(None, |stanza| {
+            syntax_errors
+                .iter()
+                .any(|location| stanza.range.start <= *location && *location <= stanza.range.end)
+        });
+        errors.extend(check_errors.into_iter().map(ParseError::from));
+        if !errors.is_empty() {
+            errors.sort_by_key(ParseError::location);
+            return Err(errors);
+        }
+        Ok(file)
+    }
+
     /// Parses a graph DSL file, adding its content to an existing `File` instance.
     #[deprecated(
         note = "Parsing multiple times into the same `File` instance is unsound. Use `File::from_str` instead."
@@ -47,6 +125,33 @@ impl ast::File {
     }
 }
 
+// ----------------------------------------------------------------------------
+// Parse limits
+
+/// The maximum nesting depth of the default [`ParseLimits`][], which leaves room on the stack
+/// of a thread with a small stack, such as a test thread, even in a debug build.
+const DEFAULT_MAX_NESTING_DEPTH: usize = 64;
+
+/// Limits on the graph DSL files that the parser accepts.  The parser is recursive, so files that
+/// nest blocks, expressions, or query patterns very deeply could otherwise exhaust its stack, and
+/// the stacks of the checker and the executor after it.  Long sequences of statements, stanzas,
+/// `elif` arms, or arguments are parsed iteratively, and do not count towards the limits.
+#[derive(Clone, Debug)]
+pub struct ParseLimits {
+    /// The maximum nesting depth of the blocks and expressions of a stanza, and of the
+    /// parentheses of its query.  Each block, each expression that contains another one, and
+    /// each scoped variable access counts as a level.
+    pub max_nesting_depth: Option<usize>,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_nesting_depth: Some(DEFAULT_MAX_NESTING_DEPTH),
+        }
+    }
+}
+
 // ----------------------------------------------------------------------------
 // Parse errors
 
@@ -61,9 +166,11 @@ pub enum ParseError {
     ExpectedVariable(Location),
     #[error("Expected unscoped variable at {0}")]
     ExpectedUnscopedVariable(Location),
+    #[error("Invalid integer constant {0} at {1}")]
+    InvalidInteger(String, Location),
     #[error("Invalid regular expression /{0}/ at {1}")]
     InvalidRegex(String, Location),
-    #[error("Expected integer constant in regex capture at {0}")]
+    #[error("Expected group index or name in regex capture at {0}")]
     InvalidRegexCapture(Location),
     #[error("Invalid query pattern: {}", _0.message)]
     QueryError(#[from] QueryError),
@@ -77,11 +184,37 @@ pub enum ParseError {
     UnexpectedLiteral(String, Location),
     #[error("Query contains multiple patterns at {0}")]
     UnexpectedQueryPatterns(Location),
+    #[error("Exceeded the maximum nesting depth of {0} at {1}")]
+    NestingTooDeep(usize, Location),
     #[error(transparent)]
     Check(#[from] crate::checker::CheckError),
 }
 
 impl ParseError {
+    /// Returns the location in the graph DSL file where this error occurred.
+    pub fn location(&self) -> Location {
+        match self {
+            ParseError::ExpectedQuantifier(location) => *location,
+            ParseError::ExpectedToken(_, location) => *location,
+            ParseError::ExpectedVariable(location) => *location,
+            ParseError::ExpectedUnscopedVariable(location) => *location,
+            ParseError::InvalidInteger(_, location) => *location,
+            ParseError::InvalidRegex(_, location) => *location,
+            ParseError::InvalidRegexCapture(location) => *location,
+            ParseError::QueryError(err) => Location {
+                row: err.row,
+                column: err.column,
+            },
+            ParseError::UnexpectedCharacter(_, _, location) => *location,
+            ParseError::UnexpectedEOF(location) => *location,
+            ParseError::UnexpectedKeyword(_, location) => *location,
+            ParseError::UnexpectedLiteral(_, location) => *location,
+            ParseError::UnexpectedQueryPatterns(location) => *location,
+            ParseError::NestingTooDeep(_, location) => *location,
+            ParseError::Check(err) => err.location(),
+        }
+    }
+
     pub fn display_pretty<'a>(
         &'a self,
         path: &'a Path,
@@ -103,27 +236,10 @@ struct DisplayParseErrorPretty<'a> {
 
 impl std::fmt::Display for DisplayParseErrorPretty<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let location = match self.error {
-            ParseError::ExpectedQuantifier(location) => *location,
-            ParseError::ExpectedToken(_, location) => *location,
-            ParseError::ExpectedVariable(location) => *location,
-            ParseError::ExpectedUnscopedVariable(location) => *location,
-            ParseError::InvalidRegex(_, location) => *location,
-            ParseError::InvalidRegexCapture(location) => *location,
-            ParseError::QueryError(err) => Location {
-                row: err.row,
-                column: err.column,
-            },
-            ParseError::UnexpectedCharacter(_, _, location) => *location,
-            ParseError::UnexpectedEOF(location) => *location,
-            ParseError::UnexpectedKeyword(_, location) => *location,
-            ParseError::UnexpectedLiteral(_, location) => *location,
-            ParseError::UnexpectedQueryPatterns(location) => *location,
-            ParseError::Check(err) => {
-                write!(f, "{}", err.display_pretty(self.path, self.source))?;
-                return Ok(());
-            }
-        };
+        if let ParseError::Check(err) = self.error {
+            return write!(f, "{}", err.display_pretty(self.path, self.source));
+        }
+        let location = self.error.location();
         writeln!(f, "{}", self.error)?;
         write!(
             f,
@@ -144,7 +260,9 @@ impl std::fmt::Display for DisplayParseErrorPretty<'_> {
 // Location
 
 /// The location of a graph DSL entity within its file
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
 pub struct Location {
     pub row: usize,
     pub column: usize,
@@ -175,7 +293,7 @@ impl Display for Location {
 // Range
 
 /// The range of a graph DSL entity within its file
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Range {
     pub start: Location,
     pub end: Location,
@@ -196,6 +314,24 @@ struct Parser<'a> {
     offset: usize,
     location: Location,
     query_source: String,
+    extensions: Option<&'a Extensions>,
+    limits: ParseLimits,
+    /// The nesting depth of the block or expression that is being parsed
+    depth: usize,
+    /// The syntax errors that the parser has recovered from, if it recovers from errors
+    errors: Option<Vec<ParseError>>,
+    /// The comments that have been consumed but not yet attached to an item or statement
+    pending_comments: Vec<ast::Comment>,
+    /// The comments of the empty blocks of the item or statement that is being parsed
+    inner_comments: Vec<ast::Comment>,
+    /// The comments of the items and statements that have been parsed
+    trivia: Vec<(Location, ast::Trivia)>,
+    /// The ranges of the items, statements, and expressions that have been parsed
+    spans: Vec<Range>,
+    /// The end of the last token before the most recent whitespace
+    token_end: Location,
+    /// The offset at which the most recent whitespace ended
+    whitespace_end: usize,
 }
 
 fn is_ident_start(c: char) -> bool {
@@ -216,6 +352,16 @@ impl<'a> Parser<'a> {
             offset: 0,
             location: Location::default(),
             query_source,
+            extensions: None,
+            limits: ParseLimits::default(),
+            depth: 0,
+            errors: None,
+            pending_comments: Vec::new(),
+            inner_comments: Vec::new(),
+            trivia: Vec::new(),
+            spans: Vec::new(),
+            token_end: Location::default(),
+            whitespace_end: 0,
         }
     }
 }
@@ -247,20 +393,71 @@ impl<'a> Parser<'a> {
     }
 
     fn consume_whitespace(&mut self) {
-        let mut in_comment = false;
+        if self.offset != self.whitespace_end {
+            self.token_end = self.location;
+        }
         while let Some(ch) = self.try_peek() {
-            if in_comment {
-                if ch == '\n' {
-                    in_comment = false;
-                }
+            if ch == ';' {
+                let location = self.location;
+                let start = self.offset;
+                self.consume_while(|ch| ch != '\n');
+                let text = self.source[start..self.offset].trim_end().to_string();
+                self.pending_comments.push(ast::Comment { text, location });
+            } else if ch.is_whitespace() {
+                self.skip().unwrap();
             } else {
-                if ch == ';' {
-                    in_comment = true;
-                } else if !ch.is_whitespace() {
-                    return;
-                }
+                break;
             }
-            self.skip().unwrap();
+        }
+        self.whitespace_end = self.offset;
+    }
+
+    /// Records the range of the construct that starts at `start`, which has just been parsed and
+    /// followed by whitespace.
+    fn record_span(&mut self, start: Location) {
+        self.spans.push(Range {
+            start,
+            end: self.token_end,
+        });
+    }
+
+    /// Attaches comments to the item or statement at `location`, which has just been parsed and
+    /// followed by whitespace: the `leading` comments before it, the pending comments up to the
+    /// end of its last line, and the comments of its empty blocks.
+    fn attach_comments(&mut self, location: Location, leading: Vec<ast::Comment>) {
+        let end_row = self.token_end.row;
+        let split = self
+            .pending_comments
+            .iter()
+            .position(|comment| comment.location.row > end_row)
+            .unwrap_or(self.pending_comments.len());
+        let rest = self.pending_comments.split_off(split);
+        let trivia = ast::Trivia {
+            leading,
+            trailing: std::mem::replace(&mut self.pending_comments, rest),
+            inner: std::mem::take(&mut self.inner_comments),
+        };
+        if !trivia.is_empty() {
+            self.trivia.push((location, trivia));
+        }
+    }
+
+    /// Attaches the pending comments to the item or statement at `location` as trailing comments,
+    /// because no other item or statement follows it.
+    fn attach_remaining_comments(&mut self, location: Location) {
+        if self.pending_comments.is_empty() {
+            return;
+        }
+        let comments = std::mem::take(&mut self.pending_comments);
+        match self.trivia.iter().rposition(|(l, _)| *l == location) {
+            Some(index) => self.trivia[index].1.trailing.extend(comments),
+            None => self.trivia.push((
+                location,
+                ast::Trivia {
+                    trailing: comments,
+                    ..Default::default()
+                },
+            )),
         }
     }
 
@@ -288,33 +485,203 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses a nested block or expression with `parse`, failing if that exceeds the maximum
+    /// nesting depth.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        self.check_depth(self.depth + 1)?;
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn check_depth(&self, depth: usize) -> Result<(), ParseError> {
+        match self.limits.max_nesting_depth {
+            Some(max_depth) if depth > max_depth => {
+                Err(ParseError::NestingTooDeep(max_depth, self.location))
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn parse_into_file(&mut self, file: &mut ast::File) -> Result<(), ParseError> {
         self.consume_whitespace();
+        let mut last_item = None;
         while self.try_peek().is_some() {
-            if let Ok(_) = self.consume_token("attribute") {
-                self.consume_whitespace();
-                let shorthand = self.parse_shorthand()?;
-                file.shorthands.add(shorthand);
-            } else if let Ok(_) = self.consume_token("global") {
-                self.consume_whitespace();
-                let global = self.parse_global()?;
-                file.globals.push(global);
-            } else if let Ok(_) = self.consume_token("inherit") {
-                self.consume_whitespace();
-                self.consume_token(".")?;
-                let name = self.parse_identifier("inherit")?;
-                file.inherited_variables.insert(name);
-            } else {
-                let stanza = self.parse_stanza(file.language.clone())?;
-                file.stanzas.push(stanza);
+            let query_source_len = self.query_source.len();
+            let trivia_len = self.trivia.len();
+            let spans_len = self.spans.len();
+            let leading = std::mem::take(&mut self.pending_comments);
+            match self.parse_item(file) {
+                Ok(location) => {
+                    self.consume_whitespace();
+                    self.record_span(location);
+                    self.attach_comments(location, leading);
+                    last_item = Some(location);
+                }
+                Err(err) => {
+                    let eof = matches!(err, ParseError::UnexpectedEOF(_));
+                    self.recover(err)?;
+                    // The query of a stanza that failed must not end up in the file query.
+                    self.query_source.truncate(query_source_len);
+                    if eof {
+                        break;
+                    }
+                    let result = self.skip_item();
+                    // Neither do its comments and spans.
+                    self.trivia.truncate(trivia_len);
+                    self.spans.truncate(spans_len);
+                    self.inner_comments.clear();
+                    if let Err(err) = result {
+                        self.recover(err)?;
+                        break;
+                    }
+                    self.pending_comments.clear();
+                    self.consume_whitespace();
+                }
             }
-            self.consume_whitespace();
+        }
+        let mut dangling = Vec::new();
+        match last_item {
+            Some(location) => self.attach_remaining_comments(location),
+            None => dangling = std::mem::take(&mut self.pending_comments),
         }
         // we can unwrap here because all queries have already been parsed before
         file.query = Some(Query::new(&file.language, &self.query_source).unwrap());
+        file.query_source = self.query_source.clone();
+        file.pattern_stanzas = ast::File::own_pattern_stanzas(file.stanzas.len());
+        file.comments = ast::Comments::new(std::mem::take(&mut self.trivia), dangling);
+        file.spans = ast::Spans::new(std::mem::take(&mut self.spans));
+        Ok(())
+    }
+
+    /// Parses a global declaration, an attribute shorthand, an inherit directive, a uses
+    /// declaration, or a stanza, and returns the location that its comments are attached to.
+    fn parse_item(&mut self, file: &mut ast::File) -> Result<Location, ParseError> {
+        let location = self.location;
+        if let Ok(_) = self.consume_token("attribute") {
+            self.consume_whitespace();
+            let shorthand = self.parse_shorthand()?;
+            let location = shorthand.location;
+            file.shorthands.add(shorthand);
+            Ok(location)
+        } else if let Ok(_) = self.consume_token("global") {
+            self.consume_whitespace();
+            let global = self.parse_global()?;
+            let location = global.location;
+            file.globals.push(global);
+            Ok(location)
+        } else if let Ok(_) = self.consume_token("stanza") {
+            self.consume_whitespace();
+            let name = self.parse_identifier("stanza name")?;
+            self.consume_whitespace();
+            let mut stanza = self.parse_stanza(file.language.clone())?;
+            stanza.name = Some(name);
+            let location = stanza.range.start;
+            file.stanzas.push(stanza);
+            Ok(location)
+        } else if let Ok(_) = self.consume_token("inherit") {
+            self.consume_whitespace();
+            self.consume_token(".")?;
+            let name = self.parse_identifier("inherit")?;
+            file.inherited_variables.insert(name);
+            Ok(location)
+        } else if self.consume_token("uses").is_ok() {
+            self.consume_whitespace();
+            let uses = self.parse_uses(location)?;
+            file.uses.push(uses);
+            Ok(location)
+        } else {
+            let stanza = self.parse_stanza(file.language.clone())?;
+            let location = stanza.range.start;
+            file.stanzas.push(stanza);
+            Ok(location)
+        }
+    }
+
+    /// Records a syntax error and returns, if the parser recovers from errors, or returns the
+    /// error otherwise.
+    fn recover(&mut self, err: ParseError) -> Result<(), ParseError> {
+        match &mut self.errors {
+            Some(errors) => {
+                errors.push(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+
+    /// Skips the rest of a top-level item that failed to parse.  If the item is a stanza whose
+    /// query failed, its block is still parsed, so that the errors in it are found.
+    fn skip_item(&mut self) -> Result<(), ParseError> {
+        self.consume_whitespace();
+        if self.try_peek() == Some('{') {
+            self.parse_statements()?;
+            return Ok(());
+        }
+        self.skip_statement();
+        if self.try_peek() == Some('}') {
+            self.skip().unwrap();
+        }
         Ok(())
     }
 
+    /// Moves back to an earlier position in the source.
+    fn rewind(&mut self, offset: usize, location: Location) {
+        self.chars = self.source[offset..].chars().peekable();
+        self.offset = offset;
+        self.location = location;
+    }
+
+    /// Skips the rest of a statement that failed to parse, up to the end of its line, or up to
+    /// the end of the block that contains it.  Brackets that are opened on the way are skipped
+    /// up to the bracket that closes them, even across lines.
+    fn skip_statement(&mut self) {
+        let mut open = Vec::new();
+        while let Some(ch) = self.try_peek() {
+            match ch {
+                '"' => {
+                    self.skip().unwrap();
+                    while let Some(ch) = self.try_peek() {
+                        if ch == '"' || ch == '\n' {
+                            break;
+                        }
+                        self.skip().unwrap();
+                        if ch == '\\' && self.try_peek().is_some() {
+                            self.skip().unwrap();
+                        }
+                    }
+                    if self.try_peek() != Some('"') {
+                        continue;
+                    }
+                }
+                ';' => {
+                    self.consume_while(|ch| ch != '\n');
+                    continue;
+                }
+                '(' | '[' | '{' => open.push(ch),
+                ')' | ']' | '}' => {
+                    let opening = match ch {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    match open.iter().rposition(|open| *open == opening) {
+                        Some(index) => open.truncate(index),
+                        None if ch == '}' => return,
+                        None => {}
+                    }
+                }
+                '\n' if open.is_empty() => return,
+                _ => {}
+            }
+            self.skip().unwrap();
+        }
+    }
+
     fn parse_global(&mut self) -> Result<ast::Global, ParseError> {
         let location = self.location;
         let name = self.parse_identifier("global variable")?;
@@ -333,6 +700,21 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parses the comma-separated namespaces of a uses declaration, which starts at `location`.
+    fn parse_uses(&mut self, location: Location) -> Result<ast::Uses, ParseError> {
+        let mut namespaces = vec![self.parse_string()?];
+        self.consume_whitespace();
+        while self.consume_token(",").is_ok() {
+            self.consume_whitespace();
+            namespaces.push(self.parse_string()?);
+            self.consume_whitespace();
+        }
+        Ok(ast::Uses {
+            namespaces,
+            location,
+        })
+    }
+
     fn parse_shorthand(&mut self) -> Result<ast::AttributeShorthand, ParseError> {
         let location = self.location;
         let name = self.parse_identifier("shorthand name")?;
@@ -373,11 +755,21 @@ impl<'a> Parser<'a> {
         let start = self.location;
         let (query, full_match_stanza_capture_index) = self.parse_query(language)?;
         self.consume_whitespace();
+        let guard = if self.consume_token("when").is_ok() {
+            self.consume_whitespace();
+            let guard = self.parse_expression()?;
+            self.consume_whitespace();
+            Some(guard)
+        } else {
+            None
+        };
         let statements = self.parse_statements()?;
         let end = self.location;
         let range = Range { start, end };
         Ok(ast::Stanza {
+            name: None,
             query,
+            guard,
             statements,
             full_match_stanza_capture_index,
             full_match_file_capture_index: usize::MAX, // set in checker
@@ -442,13 +834,13 @@ impl<'a> Parser<'a> {
             } else {
                 match ch {
                     '"' => in_string = true,
-                    '(' => paren_depth += 1,
-                    ')' => {
-                        if paren_depth > 0 {
-                            paren_depth -= 1;
-                        }
+                    '(' => {
+                        paren_depth += 1;
+                        self.check_depth(paren_depth)?;
                     }
+                    ')' => paren_depth = paren_depth.saturating_sub(1),
                     '{' => return Ok(()),
+                    'w' if paren_depth == 0 && self.at_guard() => return Ok(()),
                     ';' => in_comment = true,
                     _ => {}
                 }
@@ -457,15 +849,61 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Returns whether the parser is at the `when` keyword that starts the guard of a stanza,
+    /// which ends its query.  The keyword must follow whitespace, so that it is not part of a
+    /// capture name or node type.
+    fn at_guard(&self) -> bool {
+        let rest = &self.source[self.offset..];
+        rest.starts_with("when")
+            && !rest["when".len()..].starts_with(is_ident)
+            && self.source[..self.offset].ends_with(char::is_whitespace)
+    }
+
     fn parse_statements(&mut self) -> Result<Vec<ast::Statement>, ParseError> {
+        self.nested(Self::parse_block)
+    }
+
+    fn parse_block(&mut self) -> Result<Vec<ast::Statement>, ParseError> {
         self.consume_token("{")?;
-        let mut statements = Vec::new();
+        // The comments of the empty blocks of the enclosing statement are set aside while the
+        // statements of this block take the comments of their own empty blocks.
+        let mut inner_comments = std::mem::take(&mut self.inner_comments);
+        let mut statements: Vec<ast::Statement> = Vec::new();
         self.consume_whitespace();
         while self.peek()? != '}' {
-            let statement = self.parse_statement()?;
-            statements.push(statement);
-            self.consume_whitespace();
+            let offset = self.offset;
+            let location = self.location;
+            let trivia_len = self.trivia.len();
+            let spans_len = self.spans.len();
+            let leading = std::mem::take(&mut self.pending_comments);
+            match self.parse_statement() {
+                Ok(statement) => {
+                    let location = statement.location();
+                    statements.push(statement);
+                    self.consume_whitespace();
+                    self.record_span(location);
+                    self.attach_comments(location, leading);
+                }
+                Err(err @ ParseError::UnexpectedEOF(_)) => return Err(err),
+                Err(err) => {
+                    self.recover(err)?;
+                    // The statement is skipped from its start, since the parser may have gone
+                    // past the end of its line before it failed.
+                    self.rewind(offset, location);
+                    self.trivia.truncate(trivia_len);
+                    self.spans.truncate(spans_len);
+                    self.inner_comments.clear();
+                    self.pending_comments = leading;
+                    self.skip_statement();
+                    self.consume_whitespace();
+                }
+            }
         }
+        match statements.last() {
+            Some(statement) => self.attach_remaining_comments(statement.location()),
+            None => inner_comments.append(&mut self.pending_comments),
+        }
+        self.inner_comments = inner_comments;
         self.consume_token("}")?;
         Ok(statements)
     }
@@ -486,6 +924,9 @@ impl<'a> Parser<'a> {
         let keyword = self.parse_name("keyword")?;
         self.consume_whitespace();
         if keyword == "let" {
+            if self.try_peek() == Some('[') {
+                return self.parse_destructure_list(keyword_location);
+            }
             let variable = self.parse_variable()?;
             self.consume_whitespace();
             self.consume_token("=")?;
@@ -523,20 +964,52 @@ impl<'a> Parser<'a> {
             .into())
         } else if keyword == "node" {
             let node = self.parse_variable()?;
+            let label = if self.consume_token(":").is_ok() {
+                self.consume_whitespace();
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
             Ok(ast::CreateGraphNode {
                 node,
+                label,
                 location: keyword_location,
             }
             .into())
         } else if keyword == "edge" {
-            let source = self.parse_expression()?;
+            let variable_location = self.location;
+            let mut source = self.parse_expression()?;
             self.consume_whitespace();
-            self.consume_token("->")?;
+            let variable = if self.consume_token("=").is_ok() {
+                let variable = match source {
+                    ast::Expression::Variable(variable) => variable,
+                    _ => return Err(ParseError::ExpectedVariable(variable_location)),
+                };
+                self.consume_whitespace();
+                source = self.parse_expression()?;
+                self.consume_whitespace();
+                Some(variable)
+            } else {
+                None
+            };
+            let kind = match self.consume_token("->") {
+                Ok(()) => None,
+                Err(err) => {
+                    if self.consume_token("-").is_err() {
+                        return Err(err);
+                    }
+                    let kind = self.parse_string()?;
+                    self.consume_token("->")?;
+                    Some(kind.as_str().into())
+                }
+            };
             self.consume_whitespace();
             let sink = self.parse_expression()?;
             Ok(ast::CreateEdge {
+                variable,
                 source,
                 sink,
+                kind,
                 location: keyword_location,
             }
             .into())
@@ -576,6 +1049,8 @@ impl<'a> Parser<'a> {
                 .into())
             }
         } else if keyword == "print" {
+            let level = self.parse_print_level()?;
+            let channel = self.parse_print_channel()?;
             let mut values = vec![self.parse_expression()?];
             self.consume_whitespace();
             while self.try_peek() == Some(',') {
@@ -586,6 +1061,8 @@ impl<'a> Parser<'a> {
             }
             self.consume_whitespace();
             Ok(ast::Print {
+                level,
+                channel,
                 values,
                 location: keyword_location,
             }
@@ -612,6 +1089,42 @@ impl<'a> Parser<'a> {
             }
             self.consume_token("}")?;
             Ok(ast::Scan {
+                value,
+                arms,
+                element: None, // set in checker
+                location: keyword_location,
+            }
+            .into())
+        } else if keyword == "match" {
+            let value = self.parse_expression()?;
+            self.consume_whitespace();
+            self.consume_token("{")?;
+            self.consume_whitespace();
+            let mut arms = Vec::new();
+            while self.peek()? != '}' {
+                let location = self.location;
+                let patterns = self.parse_match_patterns()?;
+                self.consume_whitespace();
+                self.consume_token("=>")?;
+                self.consume_whitespace();
+                let statements = self.parse_statements()?;
+                self.consume_whitespace();
+                if self.consume_token(",").is_ok() {
+                    self.consume_whitespace();
+                }
+                let is_wildcard = patterns.is_empty();
+                arms.push(ast::MatchArm {
+                    patterns,
+                    statements,
+                    location,
+                });
+                // The `_` arm matches any string, so it must be the last one.
+                if is_wildcard {
+                    break;
+                }
+            }
+            self.consume_token("}")?;
+            Ok(ast::Match {
                 value,
                 arms,
                 location: keyword_location,
@@ -672,10 +1185,31 @@ impl<'a> Parser<'a> {
             .into())
         } else if keyword == "for" {
             self.consume_whitespace();
-            let variable = self.parse_unscoped_variable()?;
+            let mut variables = vec![self.parse_unscoped_variable()?];
             self.consume_whitespace();
+            while self.try_peek() == Some(',') {
+                self.consume_token(",")?;
+                self.consume_whitespace();
+                variables.push(self.parse_unscoped_variable()?);
+                self.consume_whitespace();
+            }
             self.consume_token("in")?;
             self.consume_whitespace();
+            if let Some(values) = self.parse_zip_values()? {
+                self.consume_whitespace();
+                let statements = self.parse_statements()?;
+                return Ok(ast::ForInZip {
+                    variables,
+                    values,
+                    statements,
+                    location: keyword_location,
+                }
+                .into());
+            }
+            if variables.len() > 1 {
+                return Err(ParseError::ExpectedToken("zip", self.location));
+            }
+            let variable = variables.pop().unwrap();
             let value = self.parse_expression()?;
             self.consume_whitespace();
             let statements = self.parse_statements()?;
@@ -686,6 +1220,26 @@ impl<'a> Parser<'a> {
                 location: keyword_location,
             }
             .into())
+        } else if self.extensions.is_some_and(|e| e.contains(keyword)) {
+            // The arguments start on the same line as the keyword, so that a statement without
+            // arguments is not followed by the next statement.
+            let mut arguments = Vec::new();
+            if self.location.row == keyword_location.row && self.try_peek() != Some('}') {
+                arguments.push(self.parse_expression()?);
+                self.consume_whitespace();
+                while self.try_peek() == Some(',') {
+                    self.consume_token(",")?;
+                    self.consume_whitespace();
+                    arguments.push(self.parse_expression()?);
+                    self.consume_whitespace();
+                }
+            }
+            Ok(ast::Extension {
+                keyword: keyword.into(),
+                arguments,
+                location: keyword_location,
+            }
+            .into())
         } else {
             Err(ParseError::UnexpectedKeyword(
                 keyword.into(),
@@ -694,6 +1248,64 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses the rest of a `let` statement that destructures a list, starting at its pattern.
+    fn parse_destructure_list(&mut self, location: Location) -> Result<ast::Statement, ParseError> {
+        self.consume_token("[")?;
+        self.consume_whitespace();
+        let mut elements = Vec::new();
+        let mut rest = None;
+        while self.peek()? != ']' {
+            if self.consume_token("...").is_ok() {
+                rest = Some(self.parse_variable()?);
+                self.consume_whitespace();
+                break;
+            }
+            elements.push(self.parse_variable()?);
+            self.consume_whitespace();
+            if self.consume_token(",").is_err() {
+                break;
+            }
+            self.consume_whitespace();
+        }
+        self.consume_token("]")?;
+        self.consume_whitespace();
+        self.consume_token("=")?;
+        self.consume_whitespace();
+        let value = self.parse_expression()?;
+        Ok(ast::DestructureList {
+            elements,
+            rest,
+            value,
+            location,
+        }
+        .into())
+    }
+
+    /// Parses the lists of a `for` loop that zips lists together, if it has them.  A `zip` that is
+    /// not followed by an opening parenthesis is a variable that is iterated over instead.
+    fn parse_zip_values(&mut self) -> Result<Option<Vec<ast::Expression>>, ParseError> {
+        let rest = &self.source[self.offset..];
+        let name_end = rest.find(|c| !is_ident(c)).unwrap_or(rest.len());
+        if &rest[..name_end] != "zip" || !rest[name_end..].trim_start().starts_with('(') {
+            return Ok(None);
+        }
+        self.consume_token("zip")?;
+        self.consume_whitespace();
+        self.consume_token("(")?;
+        self.consume_whitespace();
+        let mut values = Vec::new();
+        while self.peek()? != ')' {
+            values.push(self.parse_expression()?);
+            self.consume_whitespace();
+            if self.consume_token(",").is_err() {
+                break;
+            }
+            self.consume_whitespace();
+        }
+        self.consume_token(")")?;
+        Ok(Some(values))
+    }
+
     fn parse_conditions(&mut self) -> Result<Vec<ast::Condition>, ParseError> {
         let mut conditions = Vec::new();
         let mut has_next = true;
@@ -721,24 +1333,75 @@ impl<'a> Parser<'a> {
             self.consume_whitespace();
             let value = self.parse_expression()?;
             ast::Condition::None { value, location }
-        } else if let Ok(value) = self.parse_expression() {
-            self.consume_whitespace();
-            ast::Condition::Bool { value, location }
         } else {
-            return Err(ParseError::ExpectedToken(
-                "(some|none)? EXPRESSION",
-                location,
-            ));
+            match self.parse_expression() {
+                Ok(value) => {
+                    self.consume_whitespace();
+                    ast::Condition::Bool { value, location }
+                }
+                Err(err @ ParseError::NestingTooDeep(_, _)) => return Err(err),
+                Err(_) => {
+                    return Err(ParseError::ExpectedToken(
+                        "(some|none)? EXPRESSION",
+                        location,
+                    ))
+                }
+            }
         };
         self.consume_whitespace();
         Ok(condition)
     }
 
+    /// Parses the level at the start of a `print` statement, if there is one.  A level name that
+    /// is followed by a comma, or that ends the statement, is a variable that is printed instead.
+    fn parse_print_level(&mut self) -> Result<Option<ast::PrintLevel>, ParseError> {
+        let rest = &self.source[self.offset..];
+        let name_end = rest.find(|c| !is_ident(c)).unwrap_or(rest.len());
+        let level = match ast::PrintLevel::from_name(&rest[..name_end]) {
+            Some(level) => level,
+            None => return Ok(None),
+        };
+        let after = &rest[name_end..];
+        let next = after.trim_start().chars().next();
+        if !after.starts_with(char::is_whitespace) || matches!(next, None | Some(',' | '}')) {
+            return Ok(None);
+        }
+        self.parse_name("print level")?;
+        self.consume_whitespace();
+        Ok(Some(level))
+    }
+
+    /// Parses the channel at the start of a `print` statement, if there is one.  The channel is a
+    /// name that is immediately followed by a colon.
+    fn parse_print_channel(&mut self) -> Result<Option<Identifier>, ParseError> {
+        let rest = &self.source[self.offset..];
+        let name_end = rest.find(|c| !is_ident(c)).unwrap_or(rest.len());
+        if name_end == 0 || !rest[name_end..].starts_with(':') {
+            return Ok(None);
+        }
+        let channel = self.parse_identifier("print channel")?;
+        self.consume_token(":")?;
+        self.consume_whitespace();
+        Ok(Some(channel))
+    }
+
     fn parse_identifier(&mut self, within: &'static str) -> Result<Identifier, ParseError> {
         let content = self.parse_name(within)?;
         Ok(Identifier::from(content))
     }
 
+    /// Function names are identifiers that can optionally end with a `?`, which by convention
+    /// marks predicates, like `file-exists?`.
+    fn parse_function_name(&mut self) -> Result<Identifier, ParseError> {
+        let start = self.offset;
+        self.parse_name("function name")?;
+        if let Ok('?') = self.peek() {
+            self.skip()?;
+        }
+        let end = self.offset;
+        Ok(Identifier::from(&self.source[start..end]))
+    }
+
     fn parse_string(&mut self) -> Result<String, ParseError> {
         self.consume_token("\"")?;
         let mut escape = false;
@@ -764,7 +1427,60 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses the patterns of a `match` arm, which are strings or regular expressions separated
+    /// by `|`.  The `_` pattern, which matches any string, is returned as no patterns.
+    fn parse_match_patterns(&mut self) -> Result<Vec<ast::MatchPattern>, ParseError> {
+        if self.consume_token("_").is_ok() {
+            return Ok(Vec::new());
+        }
+        let mut patterns = vec![self.parse_match_pattern()?];
+        self.consume_whitespace();
+        while self.consume_token("|").is_ok() {
+            self.consume_whitespace();
+            patterns.push(self.parse_match_pattern()?);
+            self.consume_whitespace();
+        }
+        Ok(patterns)
+    }
+
+    fn parse_match_pattern(&mut self) -> Result<ast::MatchPattern, ParseError> {
+        let location = self.location;
+        if self.try_peek() != Some('/') {
+            return Ok(ast::MatchPattern::String(self.parse_string()?));
+        }
+        let pattern = self.parse_regex_literal()?;
+        let regex =
+            Regex::new(&pattern).map_err(|_| ParseError::InvalidRegex(pattern, location))?;
+        Ok(ast::MatchPattern::Regex(regex))
+    }
+
+    /// Parses a regular expression between slashes.  A slash in the regular expression is
+    /// escaped as `\/`, and the other escapes are kept, since they belong to the regular
+    /// expression.
+    fn parse_regex_literal(&mut self) -> Result<String, ParseError> {
+        self.consume_token("/")?;
+        let mut value = String::new();
+        loop {
+            match self.next()? {
+                '/' => return Ok(value),
+                '\\' => match self.next()? {
+                    '/' => value.push('/'),
+                    ch => {
+                        value.push('\\');
+                        value.push(ch);
+                    }
+                },
+                ch => value.push(ch),
+            }
+        }
+    }
+
     fn parse_expression(&mut self) -> Result<ast::Expression, ParseError> {
+        self.nested(Self::parse_nested_expression)
+    }
+
+    fn parse_nested_expression(&mut self) -> Result<ast::Expression, ParseError> {
+        let start = self.location;
         let mut expression = match self.peek()? {
             '#' => self.parse_literal()?,
             '"' => self.parse_string()?.into(),
@@ -773,7 +1489,7 @@ impl<'a> Parser<'a> {
             '(' => self.parse_call()?,
             '[' => self.parse_list()?,
             '{' => self.parse_set()?,
-            ch if ch.is_ascii_digit() => self.parse_integer_constant()?,
+            ch if ch == '-' || ch.is_ascii_digit() => self.parse_integer_constant()?,
             ch if is_ident_start(ch) => {
                 let location = self.location;
                 let name = self.parse_identifier("variable name")?;
@@ -788,28 +1504,44 @@ impl<'a> Parser<'a> {
             }
         };
         self.consume_whitespace();
+        self.record_span(start);
+        let mut depth = self.depth;
         while self.try_peek() == Some('.') {
             self.skip().unwrap();
             self.consume_whitespace();
             let location = self.location;
+            depth += 1;
+            self.check_depth(depth)?;
             let scope = Box::new(expression);
             let name = self.parse_identifier("scoped variable name")?;
             self.consume_whitespace();
+            self.record_span(location);
             expression = ast::ScopedVariable {
                 scope,
                 name,
+                default: None,
                 location,
             }
             .into();
         }
+        if let ast::Expression::Variable(ast::Variable::Scoped(variable)) = &mut expression {
+            if self.consume_token("??").is_ok() {
+                self.consume_whitespace();
+                variable.default = Some(Box::new(self.parse_expression()?));
+            }
+        }
         Ok(expression)
     }
 
     fn parse_call(&mut self) -> Result<ast::Expression, ParseError> {
+        let location = self.location;
         self.consume_token("(")?;
         self.consume_whitespace();
-        let function = self.parse_identifier("function name")?;
+        let function = self.parse_function_name()?;
         self.consume_whitespace();
+        if function.as_str() == "new" {
+            return self.parse_new_node(location);
+        }
         let mut parameters = Vec::new();
         while self.peek()? != ')' {
             parameters.push(self.parse_expression()?);
@@ -823,6 +1555,17 @@ impl<'a> Parser<'a> {
         .into())
     }
 
+    fn parse_new_node(&mut self, location: Location) -> Result<ast::Expression, ParseError> {
+        let kind_location = self.location;
+        let kind = self.parse_identifier("node")?;
+        if kind.as_str() != "node" {
+            return Err(ParseError::ExpectedToken("node", kind_location));
+        }
+        self.consume_whitespace();
+        self.consume_token(")")?;
+        Ok(ast::NewNode { location }.into())
+    }
+
     fn parse_sequence(&mut self, end_marker: char) -> Result<Vec<ast::Expression>, ParseError> {
         let mut elements = Vec::new();
         while self.peek()? != end_marker {
@@ -864,17 +1607,31 @@ impl<'a> Parser<'a> {
             self.consume_whitespace();
             let value = self.parse_expression()?;
             self.consume_whitespace();
+            let condition = self.parse_comprehension_condition()?;
             self.consume_token("]")?;
             Ok(ast::ListComprehension {
                 element: first_element.into(),
                 variable,
                 value: value.into(),
+                condition,
                 location,
             }
             .into())
         }
     }
 
+    fn parse_comprehension_condition(
+        &mut self,
+    ) -> Result<Option<Box<ast::Expression>>, ParseError> {
+        if self.consume_token("if").is_err() {
+            return Ok(None);
+        }
+        self.consume_whitespace();
+        let condition = self.parse_expression()?;
+        self.consume_whitespace();
+        Ok(Some(condition.into()))
+    }
+
     fn parse_set(&mut self) -> Result<ast::Expression, ParseError> {
         let location = self.location;
         self.consume_token("{")?;
@@ -903,11 +1660,13 @@ impl<'a> Parser<'a> {
             self.consume_whitespace();
             let value = self.parse_expression()?;
             self.consume_whitespace();
+            let condition = self.parse_comprehension_condition()?;
             self.consume_token("}")?;
             Ok(ast::SetComprehension {
                 element: first_element.into(),
                 variable,
                 value: value.into(),
+                condition,
                 location,
             }
             .into())
@@ -939,12 +1698,36 @@ impl<'a> Parser<'a> {
         .into())
     }
 
+    /// Parses an integer constant, which is written in decimal, in hexadecimal after `0x`, or in
+    /// octal after `0o`, and can be negative.  Its digits can be separated by underscores, which
+    /// cannot come before the first digit or after the last one.
     fn parse_integer_constant(&mut self) -> Result<ast::Expression, ParseError> {
-        // We'll have already verified that the next digit is an integer.
+        // We'll have already verified that the next character is a digit or a minus sign.
+        let location = self.location;
         let start = self.offset;
-        self.consume_while(|ch| ch.is_ascii_digit());
-        let end = self.offset;
-        let value = u32::from_str_radix(&self.source[start..end], 10).unwrap();
+        let sign = if self.consume_token("-").is_ok() {
+            "-"
+        } else {
+            ""
+        };
+        let radix = if self.consume_token("0x").is_ok() {
+            16
+        } else if self.consume_token("0o").is_ok() {
+            8
+        } else {
+            10
+        };
+        let digits_start = self.offset;
+        self.consume_while(|ch| ch == '_' || ch.is_ascii_alphanumeric());
+        let digits = &self.source[digits_start..self.offset];
+        let invalid =
+            || ParseError::InvalidInteger(self.source[start..self.offset].to_string(), location);
+        if digits.starts_with('_') || digits.ends_with('_') {
+            return Err(invalid());
+        }
+        let digits = digits.replace('_', "");
+        let value =
+            i64::from_str_radix(&format!("{}{}", sign, digits), radix).map_err(|_| invalid())?;
         Ok(ast::IntegerConstant { value }.into())
     }
 
@@ -970,13 +1753,23 @@ impl<'a> Parser<'a> {
         let regex_capture_location = self.location;
         self.consume_token("$")?;
         let start = self.offset;
-        self.consume_while(|ch| ch.is_ascii_digit());
-        let end = self.offset;
-        if start == end {
-            return Err(ParseError::InvalidRegexCapture(regex_capture_location));
+        if matches!(self.try_peek(), Some(ch) if ch.is_ascii_digit()) {
+            self.consume_while(|ch| ch.is_ascii_digit());
+            let end = self.offset;
+            let index = usize::from_str_radix(&self.source[start..end], 10).unwrap();
+            return Ok(ast::RegexCapture {
+                group: ast::RegexGroup::Index(index),
+            });
         }
-        let match_index = usize::from_str_radix(&self.source[start..end], 10).unwrap();
-        Ok(ast::RegexCapture { match_index }.into())
+        self.consume_while(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+        let end = self.offset;
+        let group = match &self.source[start..end] {
+            "" => return Err(ParseError::InvalidRegexCapture(regex_capture_location)),
+            "start" => ast::RegexGroup::Start,
+            "end" => ast::RegexGroup::End,
+            name => ast::RegexGroup::Name(name.into()),
+        };
+        Ok(ast::RegexCapture { group })
     }
 
     fn parse_attributes(&mut self) -> Result<Vec<ast::Attribute>, ParseError> {
@@ -991,17 +1784,39 @@ impl<'a> Parser<'a> {
         Ok(attributes)
     }
 
+    /// Parses an attribute, which is a name and a value separated by `=` or `+=`, or a flag.  A
+    /// flag is a name on its own, whose value is `#true`, or a name after `!`, whose value is
+    /// `#false`.
     fn parse_attribute(&mut self) -> Result<ast::Attribute, ParseError> {
+        if self.consume_token("!").is_ok() {
+            self.consume_whitespace();
+            let name = self.parse_identifier("attribute name")?;
+            return Ok(ast::Attribute {
+                name,
+                value: ast::Expression::FalseLiteral,
+                append: false,
+                flag: true,
+            });
+        }
         let name = self.parse_identifier("attribute name")?;
         self.consume_whitespace();
-        let value = if self.try_peek() == Some('=') {
+        let append = self.consume_token("+=").is_ok();
+        let (value, flag) = if append {
+            self.consume_whitespace();
+            (self.parse_expression()?, false)
+        } else if self.try_peek() == Some('=') {
             self.consume_token("=")?;
             self.consume_whitespace();
-            self.parse_expression()?
+            (self.parse_expression()?, false)
         } else {
-            ast::Expression::TrueLiteral
+            (ast::Expression::TrueLiteral, true)
         };
-        Ok(ast::Attribute { name, value })
+        Ok(ast::Attribute {
+            name,
+            value,
+            append,
+            flag,
+        })
     }
 
     fn parse_variable(&mut self) -> Result<ast::Variable, ParseError> {
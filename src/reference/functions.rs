@@ -12,8 +12,15 @@
 //! There might be additional functions available, and in rare cases, there might be a completely
 //! different set of functions available!
 //!
+//! Each section below is a namespace of functions, whose name is given at the start of the
+//! section, and which graph DSL files can name in [`uses` declarations][uses].
+//!
+//! [uses]: crate::reference#declaring-the-functions-a-file-uses
+//!
 //! # General functions
 //!
+//! These functions are in the `general` namespace.
+//!
 //! ## `eq`
 //!
 //! Check if values are equal.
@@ -33,6 +40,8 @@
 //!
 //! # Graph manipulation functions
 //!
+//! These functions are in the `graph` namespace.
+//!
 //! ## `node`
 //!
 //! Creates a new graph node.
@@ -40,8 +49,23 @@
 //!   - Input parameters: none
 //!   - Output value: a reference to the new graph node
 //!
+//! ## `external-node`
+//!
+//! Looks up a graph node that the host added to the graph before the execution, to stand for an
+//! entity from outside of the graph DSL file, such as a symbol from a previous run or from another
+//! tool.  Hosts add these nodes with [`Graph::add_external_node`][].
+//!
+//!   - Input parameters:
+//!     - `key`: the key that the host gave the node
+//!   - Output value:
+//!     - a reference to the external node, or null if there is no external node with `key`
+//!
+//! [`Graph::add_external_node`]: crate::graph::Graph::add_external_node
+//!
 //! # Logical functions
 //!
+//! These functions are in the `bool` namespace.
+//!
 //! ## `not`
 //!
 //! Negates a boolean value.
@@ -67,6 +91,8 @@
 //!
 //! # Mathematical functions
 //!
+//! These functions are in the `math` namespace.
+//!
 //! ## `plus`
 //!
 //! Adds integers together.
@@ -74,8 +100,62 @@
 //!   - Input parameters: zero or more integers
 //!   - Output value: the sum of all of the input integers
 //!
+//! ## `min`
+//!
+//! Returns the smallest of one or more integers.
+//!
+//!   - Input parameters: one or more integers
+//!   - Output value: the smallest of the input integers
+//!
+//! ## `max`
+//!
+//! Returns the largest of one or more integers.
+//!
+//!   - Input parameters: one or more integers
+//!   - Output value: the largest of the input integers
+//!
+//! ## `abs`
+//!
+//! Returns the absolute value of an integer.
+//!
+//!   - Input parameters: one integer
+//!   - Output value: the absolute value of the input integer
+//!
+//! ## `clamp`
+//!
+//! Restricts an integer to a range.  It is an error if `max` is less than `min`.
+//!
+//!   - Input parameters:
+//!     - `value`: an integer
+//!     - `min`: the lower bound of the range (inclusive)
+//!     - `max`: the upper bound of the range (inclusive)
+//!   - Output value: `min` if `value` is less than `min`, `max` if `value` is greater than `max`,
+//!     and `value` otherwise
+//!
+//! ## `pow`
+//!
+//! Raises an integer to a power.  It is an error if the exponent is negative, or if the result
+//! does not fit in an integer.
+//!
+//!   - Input parameters:
+//!     - `base`: an integer
+//!     - `exponent`: an integer
+//!   - Output value: `base` raised to the power `exponent`
+//!
+//! ## `random`
+//!
+//! Returns a random integer, from the graph's random number generator.  The numbers only depend on
+//! the [seed][crate::ExecutionConfig::seed] of the execution, so executions with the same seed
+//! return the same numbers.  It is an error if `bound` is not positive.
+//!
+//!   - Input parameters:
+//!     - `bound`: an integer
+//!   - Output value: an integer that is at least 0, and less than `bound`
+//!
 //! # String functions
 //!
+//! These functions are in the `string` namespace.
+//!
 //! ## `format`
 //!
 //! Formats a string according to the given format string and arguments.
@@ -105,8 +185,129 @@
 //! [`Regex::new`]: https://docs.rs/regex/*/regex/struct.Regex.html#method.new
 //! [`Regex::replace_all`]: https://docs.rs/regex/*/regex/struct.Regex.html#method.replace_all
 //!
+//! ## `base64-encode`
+//!
+//! Encodes a string using the standard, padded Base64 alphabet.
+//!
+//!   - Input parameters:
+//!     - `text`: the string to encode
+//!   - Output value:
+//!     - the Base64 encoding of the UTF-8 bytes of `text`
+//!
+//! ## `base64-decode`
+//!
+//! Decodes a string that was encoded using the standard, padded Base64 alphabet.  It is an error
+//! if the input is not valid Base64, or if the decoded bytes are not valid UTF-8.
+//!
+//!   - Input parameters:
+//!     - `text`: the string to decode
+//!   - Output value:
+//!     - the decoded string
+//!
+//! ## `uri-encode`
+//!
+//! Percent-encodes a string so that it can be used as a component of a URI.  All characters
+//! except ASCII letters, digits, and `-`, `.`, `_`, and `~` are encoded.
+//!
+//!   - Input parameters:
+//!     - `text`: the string to encode
+//!   - Output value:
+//!     - the percent-encoded string
+//!
+//! ## `uri-decode`
+//!
+//! Decodes a percent-encoded string.  It is an error if the decoded bytes are not valid UTF-8.
+//!
+//!   - Input parameters:
+//!     - `text`: the string to decode
+//!   - Output value:
+//!     - the decoded string
+//!
+//! ## `edit-distance`
+//!
+//! Computes the Levenshtein distance between two strings: the minimum number of single-character
+//! insertions, deletions, or substitutions needed to turn one into the other.
+//!
+//!   - Input parameters:
+//!     - `left`: a string
+//!     - `right`: a string
+//!   - Output value:
+//!     - the edit distance between `left` and `right`
+//!
+//! ## `fuzzy-match?`
+//!
+//! Checks whether two strings are similar, ignoring case.
+//!
+//!   - Input parameters:
+//!     - `left`: a string
+//!     - `right`: a string
+//!     - `max-distance`: an optional integer, defaulting to 1
+//!   - Output value:
+//!     - a boolean indicating whether the edit distance between the lowercased `left` and
+//!       `right` is at most `max-distance`
+//!
+//! ## `unicode-nfc`
+//!
+//! Normalizes a string to Unicode Normalization Form C (canonical composition), so that strings
+//! that render identically compare as equal.
+//!
+//!   - Input parameters:
+//!     - `text`: a string
+//!   - Output value:
+//!     - the NFC normalization of `text`
+//!
+//! ## `unicode-casefold`
+//!
+//! Applies Unicode default case folding to a string.  Unlike lowercasing, case folding does not
+//! depend on the locale, and is intended for case-insensitive comparisons.
+//!
+//!   - Input parameters:
+//!     - `text`: a string
+//!   - Output value:
+//!     - the case-folded version of `text`
+//!
+//! ## `substring`
+//!
+//! Extracts part of a string.  Positions count extended grapheme clusters (user-perceived
+//! characters), not bytes or code points.  Positions past the end of the string are treated as
+//! the end of the string, and negative positions are an error.
+//!
+//!   - Input parameters:
+//!     - `text`: a string
+//!     - `start`: the zero-based position of the first grapheme to include
+//!     - `end`: an optional zero-based position of the first grapheme to exclude; if missing,
+//!       the substring extends to the end of `text`
+//!   - Output value:
+//!     - the graphemes of `text` from `start` up to `end`
+//!
+//! # JSON functions
+//!
+//! These functions are in the `json` namespace.
+//!
+//! ## `json-parse`
+//!
+//! Parses a JSON string into a value.  JSON arrays become lists.  Only integers that fit in 64
+//! bits are supported as numbers, and objects are not supported at all.
+//!
+//!   - Input parameters:
+//!     - `text`: a string containing a JSON document
+//!   - Output value:
+//!     - the value represented by the JSON document
+//!
+//! ## `json-stringify`
+//!
+//! Converts a value into a JSON string.  Lists and sets become JSON arrays.  Syntax nodes and graph
+//! nodes cannot be converted.
+//!
+//!   - Input parameters:
+//!     - `value`: the value to convert
+//!   - Output value:
+//!     - a string containing the JSON representation of `value`
+//!
 //! # List functions
 //!
+//! These functions are in the `list` namespace.
+//!
 //! ## `concat`
 //!
 //! Concatenate list arguments.
@@ -141,6 +342,8 @@
 //!
 //! # Syntax manipulation functions
 //!
+//! These functions are in the `syntax` namespace.
+//!
 //! ## `named-child-index`
 //!
 //! Returns the index of a "named child" within its parent.
@@ -179,6 +382,20 @@
 //!   - Output value:
 //!     - A string containing the type of `node`
 //!
+//! ## `node-kind-matches?`
+//!
+//! Checks whether a syntax node's type matches a pattern.  The pattern consists of one or more
+//! alternatives separated by `|`.  Within an alternative, `*` matches any sequence of characters
+//! and `?` matches any single character; all other characters match themselves.  The node's type
+//! must match an alternative in its entirety.
+//!
+//!   - Input parameters:
+//!     - `node`: A syntax node
+//!     - `pattern`: A string containing the pattern, such as `"*_definition"` or
+//!       `"call|method_call"`
+//!   - Output value:
+//!     - A boolean indicating whether the type of `node` matches `pattern`
+//!
 //! ## `start-column`
 //!
 //! Returns the zero-based start column of a syntax node.
@@ -214,3 +431,75 @@
 //!     - `node`: A syntax node
 //!   - Output value:
 //!     - The zero-based end row of `node`
+//!
+//! ## `highlight-of`
+//!
+//! Returns the highlight name of a syntax node, according to a highlights query of the language,
+//! such as the `highlights.scm` file of its tree-sitter grammar.  If several patterns capture the
+//! node, the earliest one in the query wins, as in tree-sitter's highlighter, and captures whose
+//! names start with `_` are ignored.  This function is not available by default; the host must
+//! add it, with the highlights query to use, via
+//! [`Functions::add_highlights`][`crate::functions::Functions::add_highlights`].
+//!
+//!   - Input parameters:
+//!     - `node`: A syntax node
+//!   - Output value:
+//!     - A string containing the highlight name of `node`, such as `"function.builtin"`, or null
+//!       if the query does not capture it
+//!
+//! # Environment functions
+//!
+//! These functions are in the `env` namespace.
+//!
+//! These functions give access to the environment of the process that is executing the graph DSL
+//! file.  They are not available by default; the host must enable them via
+//! [`ExecutionConfig::environment`][`crate::ExecutionConfig::environment`].  Calling one of them
+//! when they are not enabled results in an error.
+//!
+//! ## `env`
+//!
+//! Looks up an environment variable.
+//!
+//!   - Input parameters:
+//!     - `name`: the name of the environment variable
+//!   - Output value:
+//!     - the value of the environment variable, or null if it is not set
+//!
+//! ## `config-get`
+//!
+//! Looks up a configuration value provided by the host.
+//!
+//!   - Input parameters:
+//!     - `key`: the name of the configuration value
+//!     - `default`: an optional value to return if `key` is not configured
+//!   - Output value:
+//!     - the configured value, or `default` (null if not given) if there is none
+//!
+//! ## `file-exists?`
+//!
+//! Checks whether a file or directory exists.  Relative paths are resolved against the working
+//! directory of the executing process.
+//!
+//!   - Input parameters:
+//!     - `path`: a string containing the path to check
+//!   - Output value:
+//!     - a boolean indicating whether the path exists
+//!
+//! ## `now-iso8601`
+//!
+//! Returns the current time.  If the host has pinned the time (for instance, to make executions
+//! reproducible), returns that time instead.
+//!
+//!   - Input parameters: none
+//!   - Output value:
+//!     - a string containing the time in ISO 8601 format, in UTC, such as
+//!       `"2024-03-08T12:00:00Z"`
+//!
+//! ## `unix-timestamp`
+//!
+//! Returns the current time as a Unix timestamp.  If the host has pinned the time (for instance,
+//! to make executions reproducible), returns that time instead.
+//!
+//!   - Input parameters: none
+//!   - Output value:
+//!     - an integer containing the number of seconds since 1970-01-01T00:00:00Z
@@ -115,6 +115,34 @@
 //! }
 //! ```
 //!
+//! A stanza can be given a name, by putting the `stanza` keyword and an identifier before its
+//! query pattern.  Names must be unique within a file.  They do not change how the stanza is
+//! executed, but let tools refer to it, for instance to execute only some of the stanzas in a file:
+//!
+//! ``` tsg
+//! stanza identifiers
+//! (identifier) @id
+//! {
+//!   ; ...
+//! }
+//! ```
+//!
+//! A stanza can also be given a **_guard_**, by putting the `when` keyword and an expression
+//! between its query pattern and its block.  The guard can only use literals, [global
+//! variables](#variables), and function calls, and must evaluate to a boolean.  Guards are
+//! evaluated once, before the file is executed, and the stanzas whose guards are false are skipped
+//! entirely, without matching their query patterns.  This lets you turn whole stanzas on or off with global variables, without wrapping
+//! their blocks in conditionals:
+//!
+//! ``` tsg
+//! global INCLUDE_TESTS = "false"
+//!
+//! (function_definition) @function when (eq INCLUDE_TESTS "true")
+//! {
+//!   ; Only executed when the INCLUDE_TESTS global variable is "true"
+//! }
+//! ```
+//!
 //! # Expressions
 //!
 //! The value of an expression in the graph DSL can be any of the following:
@@ -122,9 +150,10 @@
 //!   - null
 //!   - a boolean
 //!   - a string
-//!   - an integer (unsigned, 32 bits)
+//!   - an integer (signed, 64 bits)
 //!   - a reference to a syntax node
 //!   - a reference to a graph node
+//!   - a reference to an edge
 //!   - an ordered list of values
 //!   - a list comprehension
 //!   - an unordered set of values
@@ -141,11 +170,17 @@
 //!   - `"a string with\na newline"`
 //!   - `"a string with\\a backslash"`
 //!
-//! Integer constants are encoded in ASCII decimal:
+//! Integer constants are encoded in ASCII decimal, or in hexadecimal or octal after a `0x` or
+//! `0o` prefix.  They can be negative, and their digits can be separated by underscores, which
+//! cannot come before the first digit or after the last one:
 //!
 //!   - `0`
 //!   - `10`
 //!   - `42`
+//!   - `-1`
+//!   - `0xFF`
+//!   - `0o755`
+//!   - `1_000_000`
 //!
 //! Lists consist of zero or more expressions, separated by commas, enclosed in square brackets.
 //! The elements of a list do not have to have the same type:
@@ -191,6 +226,16 @@
 //! value that is iterated over must be local.  It is therefore not possible to iterator over the value
 //! of a scoped variable. Using scoped variables in the element expression however is no problem.
 //!
+//! A comprehension can end with an `if` condition, in which case only the values for which the
+//! condition is true are included.  The loop variable is visible in the condition, just like in the
+//! element expression:
+//!
+//! ``` tsg
+//! [ (source-text x) for x in @ids if (not (eq (source-text x) "self")) ]
+//! ```
+//!
+//! The condition must be a boolean, and it must be local, so it cannot use scoped variables.
+//!
 //! # Syntax nodes
 //!
 //! Syntax nodes are identified by tree-sitter query captures (`@name`).  For instance, in our
@@ -258,6 +303,19 @@
 //! Variables can be referenced anywhere that you can provide an expression.  It's an error if you
 //! try to reference a variable that hasn't been defined.
 //!
+//! When you read a scoped variable, you can provide a default value with `??`, which is used if
+//! the variable is not defined for that syntax node.  This is useful when only some of the syntax
+//! nodes that a stanza matches have been given a value by other stanzas.  A default value can
+//! only be given when reading a scoped variable, not when defining or changing it.
+//!
+//! ``` tsg
+//! (module (_) @stmt)
+//! {
+//!   node n
+//!   attr (n) kind = @stmt.kind ?? "other"
+//! }
+//! ```
+//!
 //! # Functions
 //!
 //! The process executing a graph DSL file can provide **_functions_** that can be called from
@@ -282,6 +340,22 @@
 //!
 //! [standard library]: functions/index.html
 //!
+//! ## Declaring the functions a file uses
+//!
+//! The functions of a library are grouped into **_namespaces_**, such as the `string` and `math`
+//! namespaces of the [standard library][].  A graph DSL file can declare the namespaces that it
+//! needs with a `uses` declaration, which lists one or more namespaces as strings:
+//!
+//! ``` tsg
+//! uses "string", "math"
+//! ```
+//!
+//! When a file has `uses` declarations, every execution checks, before any stanza is executed,
+//! that the functions of the execution provide each of the declared namespaces, and define every
+//! function that the file calls.  The resulting error lists all of the missing namespaces or
+//! functions, rather than only the first call that would have failed.  A file can have several
+//! `uses` declarations.
+//!
 //! # Graph nodes
 //!
 //! You can use this graph DSL to create any graph structure that you want.  There are no
@@ -320,6 +394,31 @@
 //! anything more complex, such as assigning the graph node reference to a _mutable_ variable, you
 //! can call the [`node`][] function directly.
 //!
+//! A graph node that is only needed in one place, such as an intermediate scope node, can also be
+//! created right where it is used, with a `(new node)` expression.  The expression creates a new
+//! graph node each time it is evaluated, and evaluates to a reference to that node:
+//!
+//! ``` tsg
+//! (identifier) @id
+//! {
+//!   node @id.node
+//!   attr (@id.node) scope = (new node)
+//!   edge @id.node -> (new node)
+//! }
+//! ```
+//!
+//! A `node` statement can give the new graph node a label, which describes what kind of node it
+//! is.  The label is a local string value after a `:`.  It is stored apart from the attributes of
+//! the graph node, and the graph keeps an index of the nodes with each label.  Exporters use it as
+//! the label of the node, such as the `labels` of a GraphML node.
+//!
+//! ``` tsg
+//! (function_definition name: (identifier) @name)
+//! {
+//!   node @name.def : "definition"
+//! }
+//! ```
+//!
 //! By attaching a graph node to a syntax node using a [scoped variable](#variables), you can refer
 //! to them from multiple stanzas:
 //!
@@ -362,6 +461,39 @@
 //! graph.  If multiple stanzas create edges between the same graph nodes, those are "collapsed"
 //! into a single edge.
 //!
+//! An `edge` statement can also assign a reference to the edge to a new immutable variable, which
+//! can be local or scoped.  An `attr` statement whose node is an edge reference adds attributes to
+//! that edge, so that later statements and stanzas do not have to find its graph nodes again.
+//!
+//! ``` tsg
+//! (import_statement name: (_) @name)
+//! {
+//!   node @name.source
+//!   node @name.sink
+//!   edge @name.import = @name.source -> @name.sink
+//! }
+//!
+//! (import_statement name: (dotted_name) @name)
+//! {
+//!   attr (@name.import) dotted
+//! }
+//! ```
+//!
+//! An `edge` statement can also give the new edge a kind, which describes how its graph nodes are
+//! related.  The kind is a string literal between the `-` and `->` of the arrow.  It is stored
+//! apart from the attributes of the edge, and the graph keeps an index of the edges of each kind,
+//! so that they can be found without looking at every edge.  If an edge already exists between the
+//! graph nodes, its kind must be the same.
+//!
+//! ``` tsg
+//! (call function: (identifier) @callee) @call
+//! {
+//!   node @call.node
+//!   node @callee.node
+//!   edge @call.node -"calls"-> @callee.node
+//! }
+//! ```
+//!
 //! # Attributes
 //!
 //! Graph nodes and edges have an associated set of **_attributes_**.  Each attribute has a name
@@ -382,6 +514,35 @@
 //! Note that you have to have already created the graph node or edge, and the graph node or edge
 //! must not already have an attribute with the same name.
 //!
+//! An attribute whose value is `#true` can be written as a flag, which is just its name, and an
+//! attribute whose value is `#false` as its name after `!`:
+//!
+//! ``` tsg
+//! (function_definition name: (_) @name)
+//! {
+//!   node @name.def
+//!   attr (@name.def) is_definition, !is_exported
+//! }
+//! ```
+//!
+//! The exception is an attribute whose value is a list that is built up by several statements,
+//! possibly in different stanzas.  If you use `+=` instead of `=`, the value is appended to the
+//! list.  The first `+=` creates the list, and it is an error if the attribute already has a value
+//! that is not a list.
+//!
+//! ``` tsg
+//! (module (_)* @stmts)
+//! {
+//!   node module
+//!   for stmt in @stmts {
+//!     attr (module) children += stmt
+//!   }
+//! }
+//! ```
+//!
+//! If you use `+=` with an attribute shorthand, the values of all the attributes that the
+//! shorthand expands to are appended.
+//!
 //! (Attributes might seem similar to scoped variables, but they are quite different.  Attributes
 //! are attached to graph nodes and edges, while scoped variables are attached to syntax nodes.
 //! More importantly, scoped variables only exist while executing the graph DSL file.  Once the
@@ -421,7 +582,11 @@
 //! string, or none of the regular expressions match.
 //!
 //! Within each regular expression's block, you can use `$0`, `$1`, etc., to refer to any capture
-//! groups in the regular expression.
+//! groups in the regular expression.  Named capture groups, such as `(?P<stem>[^/]+)`, can also be
+//! referred to by their name, as in `$stem`.  `$start` and `$end` are the byte offsets where the
+//! match starts and ends in the string that is scanned, so capture groups named `start` or `end`
+//! cannot be referred to by their name.  It is an error to refer to a capture group that the
+//! regular expression does not have.
 //!
 //! The value being scanned must be local, which means it cannot be derived from scoped variables.
 //!
@@ -478,13 +643,48 @@
 //! }
 //! ```
 //!
+//! You can also scan a list, such as the value of a capture with the `*` or `+` quantifier.  The
+//! arms are then matched against each element of the list in turn: against the source text of
+//! syntax nodes, and against the value of strings.  Within each arm, the `element` variable refers
+//! to the element whose text was matched, so it cannot be defined again in the arm.
+//!
+//! ``` tsg
+//! (class_definition body: (block (decorated_definition)* @methods))
+//! {
+//!   scan @methods {
+//!     "^@staticmethod"
+//!     {
+//!       node n
+//!       attr (n) static_method = element
+//!     }
+//!   }
+//! }
+//! ```
+//!
 //! # Conditionals
 //!
 //! You can use `if` statements to make blocks of statements conditional on optional values.
 //! Conditions are comma-separated lists of clauses.  The clause `some EXPRESSION` indicates
 //! that the optional value must be present.  The clause `none EXPRESSION` indicates that the
-//! optional value is absent.  A bare expression is evaluated as to boolean.  All values in
-//! conditions must be local, which means they cannot be derived from scoped variables.
+//! optional value is absent.  A bare expression is evaluated as to boolean, and can combine
+//! other booleans with the `and`, `or`, and `not` functions.  All values in conditions must be
+//! local, which means they cannot be derived from scoped variables.
+//!
+//! An arm is taken if all of its clauses hold.  The clauses are evaluated in order, and the
+//! remaining ones are skipped once a clause does not hold, so later clauses can rely on earlier
+//! ones.  It is an error to use a value that can never be a boolean, such as a capture or a string
+//! constant, as a bare expression.
+//!
+//! ``` tsg
+//! (function_definition name:(_) @name return_type:(_)? @type)
+//! {
+//!   if some @type, (eq (source-text @type) "None") {
+//!     ; ...
+//!   } elif (or (eq (source-text @name) "main") (eq (source-text @name) "run")) {
+//!     ; ...
+//!   }
+//! }
+//! ```
 //!
 //! ``` tsg
 //! (lexical_declaration type:(_)? @type value:(_)? @value)
@@ -499,6 +699,59 @@
 //! }
 //! ```
 //!
+//! The `some` and `none` clauses can also be used on lists that can be empty, such as the values
+//! of captures with the `*` quantifier.  In that case, `some` indicates that the list is not
+//! empty, and `none` indicates that it is empty.
+//!
+//! ``` tsg
+//! (class_definition superclasses:(argument_list (_)* @bases))
+//! {
+//!   if none @bases {
+//!     ; ...
+//!   }
+//! }
+//! ```
+//!
+//! # Matching strings
+//!
+//! You can use a `match` statement to select a block of statements based on a string value, such
+//! as the type of a syntax node or a token found by a `scan` statement.  Each arm of the `match`
+//! statement has one or more patterns, separated by `|`, followed by `=>` and a block.  Arms can
+//! be separated by commas.
+//!
+//! A pattern is either a string constant, which matches a string that is equal to it, or a regular
+//! expression between slashes, which matches a string if it matches anywhere in it.  A slash
+//! inside a regular expression is written as `\/`.  The last arm can use the `_` pattern, which
+//! matches any string.
+//!
+//! The arms are tried in order, and the block of the first arm with a matching pattern is
+//! executed.  If no arm matches, nothing is executed.  Within the block of a regular expression
+//! pattern, you can use `$0`, `$1`, etc., to refer to its capture groups, as in a `scan`
+//! statement.  An arm with several regular expression patterns can only refer to the capture
+//! groups that all of them have, and the block of a string pattern or of `_` can refer to the
+//! capture groups of an enclosing `scan` or `match` arm.  The matched value must be local, which means it cannot be derived from scoped
+//! variables.
+//!
+//! ``` tsg
+//! (_) @node
+//! {
+//!   match (node-type @node) {
+//!     "class_definition" => {
+//!       ; ...
+//!     },
+//!     "function_definition" | "lambda" => {
+//!       ; ...
+//!     },
+//!     /^(.*)_statement$/ => {
+//!       ; $1 is the kind of statement
+//!     },
+//!     _ => {
+//!       ; ...
+//!     }
+//!   }
+//! }
+//! ```
+//!
 //! # List iteration
 //!
 //! You can use a `for` statement to execute blocks of statements for every element in list
@@ -513,6 +766,35 @@
 //! }
 //! ```
 //!
+//! To iterate over several lists at once, list one variable for each of them and wrap the lists in
+//! `zip`.  Each list must be local and must come from a list value, such as a capture with a `*` or
+//! `+` quantifier.  The loop stops at the end of the shortest list.
+//!
+//! ```tsg
+//! (dictionary (pair key: (_) @key value: (_) @value)*)
+//! {
+//!   for key, value in zip(@key, @value) {
+//!     print key, value
+//!   }
+//! }
+//! ```
+//!
+//! Loop variables are immutable.  It is an error to assign them with `set`, in the body of the loop
+//! or in any block nested in it, and the error is reported when the file is checked, before it is
+//! executed.
+//!
+//! You can also use a `let` statement to give names to the elements at the start of a list value,
+//! which must be local as well.  The last variable of the pattern can be prefixed with `...`, and
+//! holds the list of the remaining elements.  It is an error if the list has fewer elements than
+//! the pattern has variables, or if it has more and there is no `...` variable.
+//!
+//! ```tsg
+//! (function_definition parameters: (parameters (_)* @params))
+//! {
+//!   let [self, ...others] = @params
+//! }
+//! ```
+//!
 //! # Debugging
 //!
 //! To support members of the Ancient and Harmonious Order of Printf Debuggers, you can use `print`
@@ -526,5 +808,55 @@
 //!    print "Hi! x = ", x
 //! }
 //! ```
+//!
+//! A `print` statement can start with a level, which is one of `error`, `warn`, `info`, `debug`,
+//! or `trace`, and with a channel, which is a name followed by a colon.  Messages without a level
+//! are at the `info` level.  By default, every message is printed, but host applications can
+//! provide a [`PrintOutput`][crate::PrintOutput] that selects the levels and channels that they
+//! want, and decides where the messages go.  The values of messages that are not wanted are not
+//! evaluated, so detailed prints can stay in production rule sets:
+//!
+//! ``` tsg
+//! (identifier) @id
+//! {
+//!    print debug scoping: "identifier ", (source-text @id)
+//!    print warn "found an identifier"
+//! }
+//! ```
+//!
+//! A level name that is followed by a comma, or that is the last thing in a `print` statement,
+//! is printed as a variable instead.
+//!
+//! Graph nodes are printed with their attributes and outgoing edges, in the same format as the
+//! graph output, so that you can see what a node looks like partway through a stanza:
+//!
+//! ``` tsg
+//! (function_definition name: (identifier) @name) @fn
+//! {
+//!    node @fn.def
+//!    attr (@fn.def) name = (source-text @name)
+//!    print debug "definition: ", @fn.def
+//! }
+//! ```
+//!
+//! Lazy executions run `print` statements after the whole graph has been built, so they show the
+//! final attributes and edges of the nodes instead.
+//!
+//! # Extension statements
+//!
+//! Host applications can add their own statements to the language, as described in the
+//! [`extensions`][crate::extensions] module.  An extension statement consists of its keyword,
+//! followed by a comma-separated list of expressions on the same line:
+//!
+//! ``` tsg
+//! (function_definition name: (identifier) @name)
+//! {
+//!   node @name.def
+//!   push_symbol @name.def, (source-text @name)
+//! }
+//! ```
+//!
+//! Extension statements are only available in files that are parsed with the extensions that
+//! define them.
 
 pub mod functions;
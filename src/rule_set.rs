@@ -0,0 +1,233 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines rule sets, which keep the checked graph DSL files of long-running services up to date.
+//!
+//! A [`RuleSet`][] loads one or more graph DSL files, which are combined in order, and checks
+//! them.  When the files change, it loads and checks them again, and swaps the new file in if it
+//! is valid.  Executions hold on to the file that they started with, so a swap never affects an
+//! execution that is in flight.  If the changed files are not valid, the rule set keeps the file
+//! that it had, and reports the error to its error callback.
+//!
+//! ``` ignore
+//! let rules = Arc::new(
+//!     RuleSet::load(language, vec!["rules.tsg"])?
+//!         .on_error(|error| eprintln!("Keeping the previous rules: {}", error)),
+//! );
+//! let watcher = rules.clone().watch(Duration::from_secs(1));
+//! // In each worker thread
+//! let file = rules.file();
+//! file.execute(&tree, source, &config, &NoCancellation)?;
+//! ```
+
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use thiserror::Error;
+use tracing::info;
+use tree_sitter::Language;
+
+use crate::ast::File;
+use crate::ParseError;
+
+type ErrorCallback = Box<dyn Fn(&RuleSetError) + Send + Sync>;
+
+/// An error that occurs when loading the graph DSL files of a rule set
+#[derive(Debug, Error)]
+pub enum RuleSetError {
+    #[error("Cannot read {0}: {1}")]
+    Read(PathBuf, #[source] std::io::Error),
+    #[error("Cannot parse {0}: {1}")]
+    Parse(PathBuf, #[source] ParseError),
+}
+
+/// Graph DSL files that are loaded again when they change.  Rule sets are `Send` and `Sync`, so
+/// one rule set, in an `Arc`, can be shared by every thread of a service.
+pub struct RuleSet {
+    language: Language,
+    paths: Vec<PathBuf>,
+    file: RwLock<Arc<File>>,
+    /// The modification times of the files when they were last loaded, which also serializes
+    /// reloads
+    modified: Mutex<Vec<Option<SystemTime>>>,
+    on_error: Option<ErrorCallback>,
+}
+
+impl RuleSet {
+    /// Loads and checks graph DSL files, which are combined in order, as a rule set.
+    pub fn load<P: Into<PathBuf>>(
+        language: Language,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<RuleSet, RuleSetError> {
+        let paths = paths.into_iter().map(Into::into).collect::<Vec<_>>();
+        let modified = modification_times(&paths);
+        let file = load_file(&language, &paths)?;
+        Ok(RuleSet {
+            language,
+            paths,
+            file: RwLock::new(Arc::new(file)),
+            modified: Mutex::new(modified),
+            on_error: None,
+        })
+    }
+
+    /// Returns this rule set with a callback that is called with the error when the files cannot
+    /// be loaded again after they change.
+    pub fn on_error<F>(self, callback: F) -> RuleSet
+    where
+        F: Fn(&RuleSetError) + Send + Sync + 'static,
+    {
+        RuleSet {
+            language: self.language,
+            paths: self.paths,
+            file: self.file,
+            modified: self.modified,
+            on_error: Some(Box::new(callback)),
+        }
+    }
+
+    /// Returns the paths of the graph DSL files of this rule set.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Returns the current checked file.  Executions should get the file once and use it until
+    /// they finish, so that they are not affected by reloads.
+    pub fn file(&self) -> Arc<File> {
+        self.file.read().unwrap().clone()
+    }
+
+    /// Loads and checks the files again, and swaps the new file in if it is valid.  The current
+    /// file is kept if it is not.
+    pub fn reload(&self) -> Result<(), RuleSetError> {
+        let mut modified = self.modified.lock().unwrap();
+        *modified = modification_times(&self.paths);
+        self.swap()
+    }
+
+    /// Loads and checks the files again if any of them changed since they were last loaded, and
+    /// swaps the new file in if it is valid.  Errors are reported to the error callback, once for
+    /// each change.  Returns whether a new file was swapped in.
+    pub fn reload_if_changed(&self) -> bool {
+        let mut modified = self.modified.lock().unwrap();
+        let current = modification_times(&self.paths);
+        if *modified == current {
+            return false;
+        }
+        *modified = current;
+        match self.swap() {
+            Ok(()) => true,
+            Err(error) => {
+                info!("Cannot reload rule set: {}", error);
+                if let Some(on_error) = &self.on_error {
+                    on_error(&error);
+                }
+                false
+            }
+        }
+    }
+
+    fn swap(&self) -> Result<(), RuleSetError> {
+        let file = load_file(&self.language, &self.paths)?;
+        *self.file.write().unwrap() = Arc::new(file);
+        info!("Reloaded rule set {}", combined_path(&self.paths).display());
+        Ok(())
+    }
+
+    /// Starts a thread that checks whether the files changed at every interval, and reloads them
+    /// if they did, until the returned watcher is dropped.
+    pub fn watch(self: Arc<Self>, interval: Duration) -> RuleSetWatcher {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                self.reload_if_changed();
+            }
+        });
+        RuleSetWatcher {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+/// A thread that reloads a rule set when its files change.  The thread stops when the watcher is
+/// dropped.
+pub struct RuleSetWatcher {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for RuleSetWatcher {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn modification_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// Returns the path that errors in the combination of several files are reported against.
+fn combined_path(paths: &[PathBuf]) -> PathBuf {
+    match paths {
+        [path] => path.clone(),
+        _ => PathBuf::from(
+            paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join("+"),
+        ),
+    }
+}
+
+/// Loads and checks graph DSL files, which are combined in order.  If the combined file has a
+/// syntax error, the files are parsed on their own, so that the error is reported against the
+/// file that contains it, with locations in that file.  Check errors can depend on several files,
+/// and are reported against their combination.
+fn load_file(language: &Language, paths: &[PathBuf]) -> Result<File, RuleSetError> {
+    let mut sources = Vec::new();
+    for path in paths {
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| RuleSetError::Read(path.clone(), error))?;
+        sources.push(source);
+    }
+    let mut combined = String::new();
+    for source in &sources {
+        combined += source;
+        if !combined.ends_with('\n') {
+            combined.push('\n');
+        }
+    }
+    let error = match File::from_str(language.clone(), &combined) {
+        Ok(file) => return Ok(file),
+        Err(error) => error,
+    };
+    if paths.len() > 1 && !matches!(error, ParseError::Check(_)) {
+        for (path, source) in paths.iter().zip(&sources) {
+            match File::from_str(language.clone(), source) {
+                Err(error) if !matches!(error, ParseError::Check(_)) => {
+                    return Err(RuleSetError::Parse(path.clone(), error));
+                }
+                _ => {}
+            }
+        }
+    }
+    Err(RuleSetError::Parse(combined_path(paths), error))
+}
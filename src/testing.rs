@@ -0,0 +1,391 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Helpers for regression tests of graph DSL files, which compare the graphs produced for a set of
+//! fixture source files against checked-in snapshots.
+//!
+//! ``` ignore
+//! use tree_sitter_graph::testing::SnapshotTester;
+//!
+//! #[test]
+//! fn python_rules() {
+//!     SnapshotTester::load(tree_sitter_python::language(), "src/python.tsg")
+//!         .assert_snapshots("test/fixtures");
+//! }
+//! ```
+//!
+//! The snapshot of a fixture is stored next to it, with a `.graph` extension appended.  Snapshots
+//! are [normalized][normalize], so that they do not change when the graph DSL file creates the
+//! same nodes in a different order.  Run the tests with the `UPDATE_SNAPSHOTS` environment
+//! variable set to write the produced graphs to the snapshots, instead of comparing them, and
+//! review the changes before checking them in.
+
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+
+use thiserror::Error;
+use tree_sitter::Language;
+use tree_sitter::Parser;
+
+use crate::ast::File;
+use crate::functions::Functions;
+use crate::graph::Attributes;
+use crate::graph::Graph;
+use crate::graph::Value;
+use crate::ExecutionConfig;
+use crate::Identifier;
+use crate::NoCancellation;
+use crate::Variables;
+
+/// The environment variable that makes snapshot tests write their snapshots.
+pub const UPDATE_SNAPSHOTS: &str = "UPDATE_SNAPSHOTS";
+
+/// An error that occurs when checking a fixture against its snapshot
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("Cannot read {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error("Cannot write {0}: {1}")]
+    Write(PathBuf, std::io::Error),
+    #[error("Cannot parse {0}:\n{1}")]
+    Parse(PathBuf, String),
+    #[error("Cannot execute graph DSL file on {0}:\n{1}")]
+    Execution(PathBuf, String),
+    #[error("Missing snapshot {0}, run with {} set to create it", UPDATE_SNAPSHOTS)]
+    Missing(PathBuf),
+    #[error(
+        "Snapshot {0} does not match, run with {} set to update it:\n{1}",
+        UPDATE_SNAPSHOTS
+    )]
+    Mismatch(PathBuf, String),
+}
+
+/// A graph DSL file that is executed against fixtures, with the functions and global variables to
+/// execute it with.
+pub struct SnapshotTester {
+    language: Language,
+    file: File,
+    tsg_path: PathBuf,
+    tsg: String,
+    functions: Functions,
+    globals: Variables<'static>,
+    lazy: bool,
+}
+
+impl SnapshotTester {
+    /// Loads the graph DSL file at `path`, which is executed with the standard library of
+    /// functions and no global variables.  Panics if the file cannot be read or parsed.
+    pub fn load<P: AsRef<Path>>(language: Language, path: P) -> SnapshotTester {
+        let tsg_path = path.as_ref().to_path_buf();
+        let tsg = std::fs::read_to_string(&tsg_path)
+            .unwrap_or_else(|e| panic!("{}", SnapshotError::Read(tsg_path.clone(), e)));
+        let file = File::from_str(language.clone(), &tsg).unwrap_or_else(|e| {
+            let error = e.display_pretty(&tsg_path, &tsg).to_string();
+            panic!("{}", SnapshotError::Parse(tsg_path.clone(), error))
+        });
+        SnapshotTester {
+            language,
+            file,
+            tsg_path,
+            tsg,
+            functions: Functions::stdlib(),
+            globals: Variables::new(),
+            lazy: false,
+        }
+    }
+
+    /// Executes the file with `functions`, instead of the standard library.
+    pub fn functions(self, functions: Functions) -> SnapshotTester {
+        SnapshotTester { functions, ..self }
+    }
+
+    /// Adds a global variable that the file is executed with.  Panics if it was already added.
+    pub fn global<V: Into<Value>>(mut self, name: &str, value: V) -> SnapshotTester {
+        self.globals
+            .add(Identifier::from(name), value.into())
+            .unwrap_or_else(|_| panic!("Duplicate global variable {}", name));
+        self
+    }
+
+    /// Executes the file lazily.
+    pub fn lazy(self, lazy: bool) -> SnapshotTester {
+        SnapshotTester { lazy, ..self }
+    }
+
+    /// Checks the graph produced for the fixture at `source_path` against its snapshot.  Panics
+    /// if they differ.
+    pub fn assert_snapshot<P: AsRef<Path>>(&self, source_path: P) {
+        if let Err(e) = self.check_snapshot(source_path) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Checks the graphs produced for every fixture in the directory at `path`, and in its
+    /// subdirectories, against their snapshots.  Panics if any of them differ, after checking
+    /// all of them.
+    pub fn assert_snapshots<P: AsRef<Path>>(&self, path: P) {
+        let mut fixtures = Vec::new();
+        find_fixtures(path.as_ref(), &mut fixtures);
+        fixtures.sort();
+        let failures = fixtures
+            .iter()
+            .filter_map(|fixture| self.check_snapshot(fixture).err())
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        if !failures.is_empty() {
+            panic!(
+                "{} of {} snapshots failed\n\n{}",
+                failures.len(),
+                fixtures.len(),
+                failures.join("\n\n")
+            );
+        }
+    }
+
+    /// Checks the graph produced for the fixture at `source_path` against its snapshot, or
+    /// writes the snapshot if `UPDATE_SNAPSHOTS` is set.
+    pub fn check_snapshot<P: AsRef<Path>>(&self, source_path: P) -> Result<(), SnapshotError> {
+        let source_path = source_path.as_ref();
+        let actual = self.execute(source_path)?;
+        let snapshot_path = snapshot_path(source_path);
+        if update_snapshots() {
+            return std::fs::write(&snapshot_path, actual)
+                .map_err(|e| SnapshotError::Write(snapshot_path, e));
+        }
+        let expected = match std::fs::read_to_string(&snapshot_path) {
+            Ok(expected) => expected,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(SnapshotError::Missing(snapshot_path))
+            }
+            Err(e) => return Err(SnapshotError::Read(snapshot_path, e)),
+        };
+        // Snapshots that were checked out on Windows can have CRLF line endings.
+        let expected = expected.replace("\r\n", "\n");
+        if actual != expected {
+            return Err(SnapshotError::Mismatch(
+                snapshot_path,
+                diff(&expected, &actual),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Executes the file against the fixture at `source_path`, and returns the normalized graph.
+    fn execute(&self, source_path: &Path) -> Result<String, SnapshotError> {
+        let source = std::fs::read_to_string(source_path)
+            .map_err(|e| SnapshotError::Read(source_path.to_path_buf(), e))?;
+        let mut parser = Parser::new();
+        parser
+            .set_language(&self.language)
+            .map_err(|e| SnapshotError::Parse(source_path.to_path_buf(), e.to_string()))?;
+        let tree = parser
+            .parse(&source, None)
+            .ok_or_else(|| SnapshotError::Parse(source_path.to_path_buf(), "timeout".into()))?;
+        if let Some(error) = crate::parse_error::ParseError::first(&tree) {
+            let error = error.display(source_path, &source).to_string();
+            return Err(SnapshotError::Parse(source_path.to_path_buf(), error));
+        }
+        let config = ExecutionConfig::new(&self.functions, &self.globals).lazy(self.lazy);
+        let graph = self
+            .file
+            .execute(&tree, &source, &config, &NoCancellation)
+            .map_err(|e| {
+                let error = e
+                    .display_pretty(source_path, &source, &self.tsg_path, &self.tsg)
+                    .to_string();
+                SnapshotError::Execution(source_path.to_path_buf(), error)
+            })?;
+        Ok(normalize(&graph))
+    }
+}
+
+/// Returns the path of the snapshot of the fixture at `source_path`.
+pub fn snapshot_path(source_path: &Path) -> PathBuf {
+    let mut snapshot_path = source_path.to_path_buf().into_os_string();
+    snapshot_path.push(".graph");
+    PathBuf::from(snapshot_path)
+}
+
+/// Returns whether snapshots are written instead of compared.
+fn update_snapshots() -> bool {
+    std::env::var_os(UPDATE_SNAPSHOTS).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+fn find_fixtures(path: &Path, fixtures: &mut Vec<PathBuf>) {
+    let entries = std::fs::read_dir(path)
+        .unwrap_or_else(|e| panic!("{}", SnapshotError::Read(path.to_path_buf(), e)));
+    for entry in entries {
+        let entry = entry.unwrap_or_else(|e| panic!("{}", SnapshotError::Read(path.into(), e)));
+        let path = entry.path();
+        if path.is_dir() {
+            find_fixtures(&path, fixtures);
+        } else if path.extension() != Some(OsStr::new("graph")) {
+            fixtures.push(path);
+        }
+    }
+}
+
+/// Renders a graph in the format of [`Graph::pretty_print`][], but in an order that does not
+/// depend on the order in which the nodes were created.  Nodes are sorted by their labels and
+/// attributes, and numbered in that order, so that references to graph nodes, in edges and in attribute
+/// values, are stable too.  Nodes with the same labels and attributes keep the order in which
+/// they were created.  Edges are sorted by their sink, and the elements of sets by how they are rendered.
+pub fn normalize(graph: &Graph) -> String {
+    let nodes = graph.iter_nodes().collect::<Vec<_>>();
+    let keys = nodes
+        .iter()
+        .map(|node| {
+            let mut key = graph[*node]
+                .label()
+                .map(|label| format!("{}\n", label))
+                .unwrap_or_default();
+            write_attributes(&mut key, &graph.node_attributes(*node), None);
+            key
+        })
+        .collect::<Vec<_>>();
+    let mut order = (0..nodes.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| keys[*a].cmp(&keys[*b]));
+    let mut numbers = vec![0; nodes.len()];
+    for (number, index) in order.iter().enumerate() {
+        numbers[*index] = number;
+    }
+
+    let mut output = String::new();
+    for index in order {
+        let node = &graph[nodes[index]];
+        match node.label() {
+            Some(label) => {
+                let _ = writeln!(output, "node {} : {:?}", numbers[index], label.as_str());
+            }
+            None => {
+                let _ = writeln!(output, "node {}", numbers[index]);
+            }
+        }
+        write_attributes(
+            &mut output,
+            &graph.node_attributes(nodes[index]),
+            Some(&numbers),
+        );
+        let mut edges = node
+            .iter_edges()
+            .map(|(sink, edge)| (numbers[sink.index()], edge))
+            .collect::<Vec<_>>();
+        edges.sort_by_key(|(sink, _)| *sink);
+        for (sink, edge) in edges {
+            match edge.kind() {
+                Some(kind) => {
+                    let _ = writeln!(
+                        output,
+                        "edge {} -{:?}-> {}",
+                        numbers[index],
+                        kind.as_str(),
+                        sink
+                    );
+                }
+                None => {
+                    let _ = writeln!(output, "edge {} -> {}", numbers[index], sink);
+                }
+            }
+            write_attributes(&mut output, &edge.attributes, Some(&numbers));
+        }
+    }
+    output
+}
+
+/// Writes attributes like their `Display` implementation, with graph nodes renumbered, or left
+/// unnumbered if `numbers` is `None`.
+fn write_attributes(output: &mut String, attributes: &Attributes, numbers: Option<&[usize]>) {
+    for (name, value) in attributes.sorted() {
+        let _ = write!(output, "  {}: ", name);
+        write_value(output, value, numbers);
+        output.push('\n');
+    }
+}
+
+fn write_value(output: &mut String, value: &Value, numbers: Option<&[usize]>) {
+    match value {
+        Value::List(elements) => {
+            output.push('[');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    output.push_str(", ");
+                }
+                write_value(output, element, numbers);
+            }
+            output.push(']');
+        }
+        Value::Set(elements) => {
+            let mut elements = elements
+                .iter()
+                .map(|element| {
+                    let mut output = String::new();
+                    write_value(&mut output, element, numbers);
+                    output
+                })
+                .collect::<Vec<_>>();
+            elements.sort();
+            let _ = write!(output, "{{{}}}", elements.join(", "));
+        }
+        Value::GraphNode(node) => match numbers {
+            Some(numbers) => {
+                let _ = write!(output, "[graph node {}]", numbers[node.index()]);
+            }
+            None => output.push_str("[graph node]"),
+        },
+        Value::GraphEdge(edge) => match numbers {
+            Some(numbers) => {
+                let _ = write!(
+                    output,
+                    "[graph edge {} -> {}]",
+                    numbers[edge.source().index()],
+                    numbers[edge.sink().index()],
+                );
+            }
+            None => output.push_str("[graph edge]"),
+        },
+        value => {
+            let _ = write!(output, "{:?}", value);
+        }
+    }
+}
+
+/// Returns the lines that differ between two snapshots, prefixed with `-` if they are only in
+/// `expected`, and with `+` if they are only in `actual`.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    // The length of the longest common subsequence of every pair of suffixes
+    let mut lengths = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            i += 1;
+            j += 1;
+        } else if j == actual.len()
+            || (i < expected.len() && lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            let _ = writeln!(output, "{:>5} - {}", i + 1, expected[i]);
+            i += 1;
+        } else {
+            let _ = writeln!(output, "{:>5} + {}", j + 1, actual[j]);
+            j += 1;
+        }
+    }
+    output
+}
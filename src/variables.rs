@@ -10,6 +10,7 @@ use std::collections::hash_map::Entry::Vacant;
 use std::collections::HashMap;
 use thiserror::Error;
 
+#[cfg(feature = "execution")]
 use crate::graph::Value;
 use crate::Identifier;
 
@@ -27,6 +28,10 @@ pub enum VariableError {
 pub(crate) trait Variables<V> {
     /// Returns the value of a variable, if it exists in this environment.
     fn get(&self, name: &Identifier) -> Option<&V>;
+
+    /// Returns the variables in this environment, including the ones it inherits, in no
+    /// particular order.
+    fn variables(&self) -> Vec<(&Identifier, &V)>;
 }
 
 pub(crate) trait MutVariables<V>: Variables<V> {
@@ -68,6 +73,7 @@ impl<'a, V> VariableMap<'a, V> {
     }
 
     /// Clears this enviroment.
+    #[cfg(feature = "execution")]
     pub(crate) fn clear(&mut self) {
         self.values.clear();
     }
@@ -80,6 +86,22 @@ impl<V> Variables<V> for VariableMap<'_, V> {
             .map(|v| &v.value)
             .or_else(|| self.context.as_ref().map(|p| p.get(name)).flatten())
     }
+
+    fn variables(&self) -> Vec<(&Identifier, &V)> {
+        let mut variables = self
+            .values
+            .iter()
+            .map(|(name, v)| (name, &v.value))
+            .collect::<Vec<_>>();
+        if let Some(context) = &self.context {
+            for (name, value) in context.variables() {
+                if !self.values.contains_key(name) {
+                    variables.push((name, value));
+                }
+            }
+        }
+        variables
+    }
 }
 
 impl<V> MutVariables<V> for VariableMap<'_, V> {
@@ -119,11 +141,13 @@ impl<V> MutVariables<V> for VariableMap<'_, V> {
 }
 
 /// Environment of immutable variables
+#[cfg(feature = "execution")]
 pub struct Globals<'a> {
-    context: Option<&'a dyn Variables<Value>>,
+    context: Option<&'a Globals<'a>>,
     values: HashMap<Identifier, Value>,
 }
 
+#[cfg(feature = "execution")]
 impl<'a> Globals<'a> {
     /// Creates a new, empty variable environment.
     pub fn new() -> Self {
@@ -180,8 +204,10 @@ impl<'a> Globals<'a> {
     }
 }
 
+#[cfg(feature = "execution")]
 pub struct Iter<'a>(std::collections::hash_map::Iter<'a, Identifier, Value>);
 
+#[cfg(feature = "execution")]
 impl<'a> std::iter::Iterator for Iter<'a> {
     type Item = (&'a Identifier, &'a Value);
 
@@ -190,8 +216,21 @@ impl<'a> std::iter::Iterator for Iter<'a> {
     }
 }
 
+#[cfg(feature = "execution")]
 impl Variables<Value> for Globals<'_> {
     fn get(&self, name: &Identifier) -> Option<&Value> {
         self.get(name)
     }
+
+    fn variables(&self) -> Vec<(&Identifier, &Value)> {
+        let mut variables = self.iter().collect::<Vec<_>>();
+        if let Some(context) = self.context {
+            for (name, value) in Variables::variables(context) {
+                if !self.values.contains_key(name) {
+                    variables.push((name, value));
+                }
+            }
+        }
+        variables
+    }
 }
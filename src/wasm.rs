@@ -0,0 +1,124 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! JavaScript bindings, for building a rule playground that runs in the browser.  Enabled with the
+//! `wasm` feature.
+//!
+//! Syntax trees cannot be shared with [web-tree-sitter][], so source code is parsed again in the
+//! WebAssembly module, with a grammar that is compiled into it.  Grammars cannot be passed in from
+//! JavaScript either, so the crate that is compiled to WebAssembly exports a function that creates
+//! a [`Playground`][] for each language that it embeds:
+//!
+//! ``` ignore
+//! use tree_sitter_graph::wasm::Playground;
+//! use wasm_bindgen::prelude::*;
+//!
+//! #[wasm_bindgen]
+//! pub fn python() -> Playground {
+//!     Playground::new(tree_sitter_python::language())
+//! }
+//! ```
+//!
+//! [web-tree-sitter]: https://github.com/tree-sitter/tree-sitter/tree/master/lib/binding_web
+
+use std::path::Path;
+
+use tree_sitter::Language;
+use tree_sitter::Parser;
+use tree_sitter::Tree;
+use wasm_bindgen::prelude::*;
+
+use crate::ast::File;
+use crate::functions::Functions;
+use crate::parse_error::ParseError;
+use crate::ExecutionConfig;
+use crate::NoCancellation;
+use crate::Variables;
+
+/// The path that errors in TSG files are reported at.
+const TSG_PATH: &str = "<tsg>";
+/// The path that errors in source files are reported at.
+const SOURCE_PATH: &str = "<source>";
+
+/// Parses, checks, and executes TSG files for one language.  Errors are thrown with messages that
+/// include excerpts of the TSG file or source code involved.
+#[wasm_bindgen]
+pub struct Playground {
+    language: Language,
+}
+
+impl Playground {
+    /// Creates a playground for a language that is compiled into the WebAssembly module.
+    pub fn new(language: Language) -> Playground {
+        Playground { language }
+    }
+
+    fn parse_tsg(&self, tsg: &str) -> Result<File, JsError> {
+        File::from_str(self.language.clone(), tsg)
+            .map_err(|err| JsError::new(&err.display_pretty(Path::new(TSG_PATH), tsg).to_string()))
+    }
+
+    fn parse_source(&self, source: &str) -> Result<Tree, JsError> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&self.language)
+            .map_err(|err| JsError::new(&err.to_string()))?;
+        parser
+            .parse(source, None)
+            .ok_or_else(|| JsError::new("Cannot parse source code"))
+    }
+}
+
+#[wasm_bindgen]
+impl Playground {
+    /// Parses source code, returning its syntax tree as an S-expression.  The syntax tree is
+    /// returned even if the source code contains syntax errors.
+    pub fn parse(&self, source: &str) -> Result<String, JsError> {
+        let tree = self.parse_source(source)?;
+        Ok(tree.root_node().to_sexp())
+    }
+
+    /// Parses and checks a TSG file.
+    pub fn check(&self, tsg: &str) -> Result<(), JsError> {
+        self.parse_tsg(tsg)?;
+        Ok(())
+    }
+
+    /// Executes a TSG file against source code, returning the graph in the same JSON format as
+    /// `--format json` on the command line.  Source code with syntax errors is rejected unless
+    /// `allow_parse_errors` is set.
+    pub fn execute(
+        &self,
+        tsg: &str,
+        source: &str,
+        lazy: bool,
+        allow_parse_errors: bool,
+    ) -> Result<String, JsError> {
+        let file = self.parse_tsg(tsg)?;
+        let tree = self.parse_source(source)?;
+        if !allow_parse_errors {
+            if let Some(err) = ParseError::first(&tree) {
+                return Err(JsError::new(
+                    &err.display_pretty(Path::new(SOURCE_PATH), source)
+                        .to_string(),
+                ));
+            }
+        }
+        let functions = Functions::stdlib();
+        let globals = Variables::new();
+        let config = ExecutionConfig::new(&functions, &globals).lazy(lazy);
+        let graph = file
+            .execute(&tree, source, &config, &NoCancellation)
+            .map_err(|err| {
+                JsError::new(
+                    &err.display_pretty(Path::new(SOURCE_PATH), source, Path::new(TSG_PATH), tsg)
+                        .to_string(),
+                )
+            })?;
+        serde_json::to_string_pretty(&graph).map_err(|err| JsError::new(&err.to_string()))
+    }
+}
@@ -0,0 +1,57 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use proptest::prelude::*;
+use tree_sitter::Parser;
+use tree_sitter_graph::arbitrary::source_text;
+use tree_sitter_graph::arbitrary::tsg_source;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::Variables;
+
+fn samples() -> Vec<String> {
+    vec![
+        "pass\n".to_string(),
+        "def f(x, y):\n  return x + 42\n".to_string(),
+        "class C:\n  a = [1, 'two', None]\n  b = {c: d for c, d in e}\n".to_string(),
+    ]
+}
+
+fn execute(python_source: &str, dsl_source: &str, lazy: bool) -> String {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::language())
+        .unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = match File::from_str(tree_sitter_python::language(), dsl_source) {
+        Ok(file) => file,
+        Err(e) => panic!("Cannot parse generated file: {}\n{}", e, dsl_source),
+    };
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let mut config = ExecutionConfig::new(&functions, &globals).lazy(lazy);
+    match file.execute(&tree, python_source, &mut config, &NoCancellation) {
+        Ok(graph) => graph.pretty_print().to_string(),
+        Err(e) => panic!("Cannot execute generated file: {}\n{}", e, dsl_source),
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn generated_files_execute_deterministically(
+        dsl_source in tsg_source(&tree_sitter_python::language()),
+        python_source in source_text(samples()),
+    ) {
+        let graph = execute(&python_source, &dsl_source, false);
+        prop_assert_eq!(&graph, &execute(&python_source, &dsl_source, false));
+        execute(&python_source, &dsl_source, true);
+    }
+}
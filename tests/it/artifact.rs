@@ -0,0 +1,46 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::ArtifactError;
+
+#[test]
+fn can_compile_and_load_files() {
+    let source = r#"
+        global filename
+        stanza functions
+        (function_definition name: (identifier) @name) {
+          node @name.def
+          attr (@name.def) file = filename, name = (source-text @name)
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("parse to succeed");
+    let artifact = file.compile();
+    let loaded = File::load(tree_sitter_python::language(), &artifact).expect("load to succeed");
+    assert_eq!(loaded.globals, file.globals);
+    assert_eq!(loaded.query_source, file.query_source);
+    assert_eq!(loaded.stanza_index("functions"), Some(0));
+    assert_eq!(loaded.stanzas[0].statements, file.stanzas[0].statements);
+
+    let err =
+        File::load(tree_sitter_python::language(), source.as_bytes()).expect_err("load to fail");
+    assert!(matches!(err, ArtifactError::NotAnArtifact));
+    let truncated = &artifact[..artifact.len() / 2];
+    let err = File::load(tree_sitter_python::language(), truncated).expect_err("load to fail");
+    assert!(matches!(err, ArtifactError::Invalid(_)));
+}
+
+#[test]
+fn artifact_keys_depend_on_source() {
+    let language = tree_sitter_python::language();
+    let source = "(module) @_m {}\n";
+    let key = File::artifact_key(&language, source);
+    assert_eq!(key.len(), 16);
+    assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(key, File::artifact_key(&language, source));
+    assert_ne!(key, File::artifact_key(&language, "(module) @_module {}\n"));
+}
@@ -0,0 +1,65 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use indoc::indoc;
+use tree_sitter::Parser;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::bench::bench;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::Location;
+use tree_sitter_graph::Variables;
+
+#[test]
+fn can_bench_stanzas() {
+    let python_sources = ["a = b\n", "c = 1\nd = 2\n"];
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::language())
+        .unwrap();
+    let trees = python_sources
+        .iter()
+        .map(|source| parser.parse(source, None).unwrap())
+        .collect::<Vec<_>>();
+    let sources = trees
+        .iter()
+        .zip(python_sources.iter())
+        .map(|(tree, source)| (tree, *source))
+        .collect::<Vec<_>>();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (identifier) @id
+          {
+            node n
+            attr (n) name = (source-text @id)
+          }
+
+          (class_definition) @_class
+          {
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals);
+    let report = bench(&file, &sources, config, 3).expect("Cannot execute file");
+
+    assert_eq!(report.total.samples().len(), 3);
+    assert_eq!(report.stanzas.len(), 2);
+    assert_eq!(report.stanzas[0].location, Location { row: 0, column: 0 });
+    assert_eq!(report.stanzas[0].matches, 4);
+    assert_eq!(report.stanzas[1].matches, 0);
+    for stanza in &report.stanzas {
+        let durations = &stanza.durations;
+        assert_eq!(durations.samples().len(), 3);
+        assert!(durations.min() <= durations.median());
+        assert!(durations.median() <= durations.max());
+        assert!(durations.sum() <= report.total.sum());
+    }
+}
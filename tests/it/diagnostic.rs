@@ -0,0 +1,102 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use indoc::indoc;
+use tree_sitter::Parser;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::diagnostic::DiagnosticFile;
+use tree_sitter_graph::diagnostic::Severity;
+use tree_sitter_graph::diagnostic::Span;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::Diagnostic;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::ExecutionError;
+use tree_sitter_graph::Location;
+use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::ParseError;
+use tree_sitter_graph::Variables;
+
+#[test]
+fn parse_and_check_errors_are_diagnostics() {
+    let err = File::from_str(tree_sitter_python::language(), "(module) @_m {\n  let\n}\n")
+        .expect_err("Parse succeeded unexpectedly");
+    let diagnostic = Diagnostic::from(err);
+    assert_eq!(diagnostic.code, "tsg-parse");
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(
+        diagnostic.span.map(|span| span.file),
+        Some(DiagnosticFile::Tsg)
+    );
+
+    let err = File::from_str(tree_sitter_python::language(), "(module) @m {}\n")
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(err, ParseError::Check(_)));
+    let diagnostic = Diagnostic::from(&err);
+    assert_eq!(diagnostic.code, "tsg-check");
+    assert_eq!(
+        diagnostic.span,
+        Some(Span::tsg(Location { row: 0, column: 0 }))
+    );
+    assert!(diagnostic.help.is_some());
+}
+
+#[test]
+fn execution_errors_are_diagnostics() {
+    let python_source = "pass\n";
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::language())
+        .unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module) @_m
+          {
+            let x = (undefined-function)
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals);
+    let err = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .map(|_| ())
+        .expect_err("Execution succeeded unexpectedly");
+    let diagnostic = Diagnostic::from(&err);
+    assert_eq!(diagnostic.code, "execution");
+    assert_eq!(diagnostic.message, err.root_cause().to_string());
+    assert_eq!(
+        diagnostic.span,
+        Some(Span::tsg(Location { row: 2, column: 2 }))
+    );
+    assert_eq!(diagnostic.secondary[0].message, "in stanza");
+    assert_eq!(
+        diagnostic.secondary[0].span,
+        Span::tsg(Location { row: 0, column: 0 })
+    );
+    assert_eq!(diagnostic.secondary[1].message, "matching (module) node");
+    assert_eq!(diagnostic.secondary[1].span.file, DiagnosticFile::Source);
+    assert!(diagnostic.help.is_some());
+
+    let diagnostic = diagnostic.with_spans(&file.spans);
+    assert_eq!(
+        diagnostic.span.and_then(|span| span.end),
+        Some(Location { row: 2, column: 30 })
+    );
+    assert_eq!(
+        diagnostic.secondary[0].span.end,
+        Some(Location { row: 3, column: 1 })
+    );
+
+    let diagnostic = Diagnostic::from(ExecutionError::LimitExceeded("fuel limit".to_string()));
+    assert_eq!(diagnostic.code, "limit-exceeded");
+    assert_eq!(diagnostic.span, None);
+    assert!(diagnostic.secondary.is_empty());
+}
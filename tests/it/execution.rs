@@ -5,14 +5,41 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::sync::Mutex;
+
 use indoc::indoc;
+use tree_sitter::InputEdit;
 use tree_sitter::Parser;
+use tree_sitter::Point;
 use tree_sitter_graph::ast::File;
+use tree_sitter_graph::ast::PrintLevel;
+use tree_sitter_graph::functions::CallContext;
+use tree_sitter_graph::functions::Function;
 use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::functions::Parameters;
+use tree_sitter_graph::graph::AttributeChange;
+use tree_sitter_graph::graph::AttributeObserver;
+use tree_sitter_graph::graph::AttributeStorage;
+use tree_sitter_graph::graph::Graph;
+use tree_sitter_graph::graph::GraphNodeRef;
+use tree_sitter_graph::graph::Value;
+use tree_sitter_graph::DebugFrame;
+use tree_sitter_graph::Debugger;
 use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::ExecutionCoverage;
 use tree_sitter_graph::ExecutionError;
+use tree_sitter_graph::ExecutionLimits;
+use tree_sitter_graph::ExecutionStatistics;
+use tree_sitter_graph::ExecutionTrace;
+use tree_sitter_graph::ExecutionWarning;
 use tree_sitter_graph::Identifier;
+use tree_sitter_graph::Location;
 use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::PrintOutput;
+use tree_sitter_graph::SourceMap;
+use tree_sitter_graph::StderrOutput;
 use tree_sitter_graph::Variables;
 
 fn init_log() {
@@ -87,6 +114,118 @@ fn can_build_simple_graph() {
     );
 }
 
+#[test]
+fn can_create_labeled_graph_nodes() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            let kind = "definition"
+            node n : kind
+            node m
+            attr (n) name = "n"
+          }
+        "#},
+        indoc! {r#"
+          node 0 : "definition"
+            name: "n"
+          node 1
+        "#},
+    );
+}
+
+#[test]
+fn can_create_kinded_edges() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            node m
+            edge n -"calls"-> m
+            edge m -> n
+            attr (n -> m) weight = 1
+          }
+        "#},
+        indoc! {r#"
+          node 0
+          edge 0 -"calls"-> 1
+            weight: 1
+          node 1
+          edge 1 -> 0
+        "#},
+    );
+}
+
+#[test]
+fn cannot_create_edges_of_different_kinds() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            node m
+            edge n -"calls"-> m
+            edge n -"returns"-> m
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_create_graph_nodes_in_expressions() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) scope = (new node), scopes = [(new node), (new node)]
+            edge n -> (new node)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            scope: [graph node 1]
+            scopes: [[graph node 2], [graph node 3]]
+          edge 0 -> 4
+          node 1
+          node 2
+          node 3
+          node 4
+        "#},
+    );
+}
+
+#[test]
+fn can_add_flag_attributes() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node a
+            node b
+            attr (a) is_exported, !is_test
+            edge a -> b
+            attr (a -> b) !precedence, is_call
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            is_exported: #true
+            is_test: #false
+          edge 0 -> 1
+            is_call: #true
+            precedence: #false
+          node 1
+        "#},
+    );
+}
+
 #[test]
 fn can_scan_strings() {
     check_execution(
@@ -133,6 +272,42 @@ fn can_scan_strings() {
     );
 }
 
+#[test]
+fn can_use_named_groups_and_offsets_in_scan_arms() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            scan "alpha/beta.py" {
+               "(?P<dir>[^/]+)/"
+               {
+                 node n
+                 attr (n) name = $dir, start = $start, end = $end
+               }
+
+               "(?P<stem>[^/]+)\\.(?P<ext>py)$"
+               {
+                 node n
+                 attr (n) name = $stem, ext = $ext, start = $start, end = $end
+               }
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            end: 6
+            name: "alpha"
+            start: 0
+          node 1
+            end: 13
+            ext: "py"
+            name: "beta"
+            start: 6
+        "#},
+    );
+}
+
 #[test]
 fn variables_in_scan_arms_are_local() {
     check_execution(
@@ -178,6 +353,63 @@ fn variables_in_scan_arms_are_local() {
     );
 }
 
+#[test]
+fn can_match_strings() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            for name in ["alpha", "beta", "test_gamma", "delta"] {
+              match name {
+                "alpha" => {
+                  attr (n) alpha = name
+                },
+                "beta" | "gamma" => {
+                  attr (n) beta = name
+                },
+                /^test_(.*)$/ => {
+                  attr (n) test = $1
+                },
+                _ => {
+                  attr (n) other = name
+                }
+              }
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            alpha: "alpha"
+            beta: "beta"
+            other: "delta"
+            test: "gamma"
+        "#},
+    );
+}
+
+#[test]
+fn can_match_strings_without_matching_arm() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module) @m
+          {
+            node n
+            match (node-type @m) {
+              "class_definition" => {
+                attr (n) class = #true
+              }
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+        "#},
+    );
+}
+
 #[test]
 fn scoped_variables_carry_across_stanzas() {
     check_execution(
@@ -216,6 +448,34 @@ fn scoped_variables_carry_across_stanzas() {
     );
 }
 
+#[test]
+fn can_read_scoped_variables_with_default() {
+    check_execution(
+        indoc! {r#"
+          import a
+          pass
+        "#},
+        indoc! {r#"
+          (import_statement) @stmt
+          {
+            let @stmt.kind = "import"
+          }
+
+          (module (_) @stmt)
+          {
+            node n
+            attr (n) kind = @stmt.kind ?? "other"
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            kind: "import"
+          node 1
+            kind: "other"
+        "#},
+    );
+}
+
 #[test]
 fn can_match_stanza_multiple_times() {
     check_execution(
@@ -305,6 +565,52 @@ fn cannot_omit_global_variable() {
     );
 }
 
+#[test]
+fn can_guard_stanzas_with_global_variables() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          global mode = "tests"
+          global filename
+
+          (module) when (eq mode "tests") {
+            node n
+            attr (n) kind = "tests"
+          }
+
+          (module) when (eq mode "main") {
+            node n
+            attr (n) kind = "main"
+          }
+
+          (module) when (eq filename "test.py") {
+            node n
+            attr (n) kind = "file"
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            kind: "tests"
+          node 1
+            kind: "file"
+    "#},
+    );
+}
+
+#[test]
+fn cannot_guard_stanza_with_non_boolean() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          global mode = "tests"
+
+          (module) when mode {
+            node n
+          }
+        "#},
+    );
+}
+
 #[test]
 fn cannot_pass_string_to_global_list_variable() {
     fail_execution(
@@ -315,6 +621,236 @@ fn cannot_pass_string_to_global_list_variable() {
     );
 }
 
+#[test]
+fn can_validate_globals_before_execution() {
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          global root
+          global pkgname = ""
+          global paths*
+        "#},
+    )
+    .expect("Cannot parse file");
+    let mut globals = Variables::new();
+    assert!(matches!(
+        file.validate_globals(&globals),
+        Err(ExecutionError::MissingGlobalVariable(name)) if name == "root"
+    ));
+    globals.add(Identifier::from("root"), "/".into()).unwrap();
+    globals
+        .add(Identifier::from("paths"), "a.py".into())
+        .unwrap();
+    assert!(matches!(
+        file.validate_globals(&globals),
+        Err(ExecutionError::ExpectedList(name)) if name == "paths"
+    ));
+    globals.remove(&Identifier::from("paths"));
+    globals
+        .add(Identifier::from("paths"), Value::from(vec!["a.py".into()]))
+        .unwrap();
+    assert!(file.validate_globals(&globals).is_ok());
+}
+
+#[test]
+fn can_collect_execution_statistics() {
+    let python_source = "pass\npass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module)
+          {
+            node root
+          }
+
+          (pass_statement)
+          {
+            node a
+            node b
+            edge a -> b
+          }
+
+          (function_definition)
+          {
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    for lazy in [false, true] {
+        let statistics = ExecutionStatistics::new();
+        let mut config = ExecutionConfig::new(&functions, &globals)
+            .lazy(lazy)
+            .statistics(&statistics);
+        file.execute(&tree, python_source, &mut config, &NoCancellation)
+            .expect("Cannot execute file");
+        let stanzas = statistics.stanzas();
+        let counts = stanzas
+            .iter()
+            .map(|stanza| {
+                (
+                    stanza.location.row,
+                    stanza.matches,
+                    stanza.nodes,
+                    stanza.edges,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![(0, 1, 1, 0), (5, 2, 4, 2), (12, 0, 0, 0)]);
+    }
+}
+
+#[test]
+fn can_limit_execution_resources() {
+    let python_source = "pass\npass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (pass_statement)
+          {
+            node a
+            node b
+            edge a -> b
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let execute = |limits: &ExecutionLimits, lazy: bool| {
+        let config = ExecutionConfig::new(&functions, &globals)
+            .lazy(lazy)
+            .limits(limits);
+        file.execute(&tree, python_source, &config, &NoCancellation)
+            .map(|_| ())
+    };
+    for lazy in [false, true] {
+        let mut limits = ExecutionLimits::default();
+        limits.max_nodes = Some(4);
+        limits.max_edges = Some(2);
+        limits.fuel = Some(6);
+        assert!(execute(&limits, lazy).is_ok());
+        for limits in [
+            ExecutionLimits {
+                max_nodes: Some(3),
+                ..Default::default()
+            },
+            ExecutionLimits {
+                max_edges: Some(1),
+                ..Default::default()
+            },
+            ExecutionLimits {
+                fuel: Some(5),
+                ..Default::default()
+            },
+        ] {
+            let err = execute(&limits, lazy).expect_err("Execution succeeded unexpectedly");
+            assert!(matches!(err.root_cause(), ExecutionError::LimitExceeded(_)));
+            assert!(!err.statement_contexts().is_empty());
+        }
+    }
+}
+
+#[test]
+fn can_limit_value_sizes() {
+    let python_source = "pass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (pass_statement)
+          {
+            node n
+            let strings = ["ab", "cd"]
+            let nested = [strings, [strings]]
+            attr (n) value = nested
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let execute = |limits: &ExecutionLimits, lazy: bool| {
+        let config = ExecutionConfig::new(&functions, &globals)
+            .lazy(lazy)
+            .limits(limits);
+        file.execute(&tree, python_source, &config, &NoCancellation)
+            .map(|_| ())
+    };
+    for lazy in [false, true] {
+        let mut limits = ExecutionLimits::default();
+        limits.max_value_size = Some(12);
+        limits.max_value_depth = Some(3);
+        assert!(execute(&limits, lazy).is_ok());
+        for limits in [
+            ExecutionLimits {
+                max_value_size: Some(11),
+                ..Default::default()
+            },
+            ExecutionLimits {
+                max_value_depth: Some(2),
+                ..Default::default()
+            },
+        ] {
+            let err = execute(&limits, lazy).expect_err("Execution succeeded unexpectedly");
+            assert!(matches!(err.root_cause(), ExecutionError::LimitExceeded(_)));
+            assert!(!err.statement_contexts().is_empty());
+        }
+    }
+}
+
+#[test]
+fn can_disable_stanzas() {
+    let python_source = "pass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    for lazy in [false, true] {
+        let mut file = File::from_str(
+            tree_sitter_python::language(),
+            indoc! {r#"
+              stanza module
+              (module)
+              {
+                node n
+                attr (n) name = "module"
+              }
+
+              (pass_statement)
+              {
+                node n
+                attr (n) name = "pass"
+              }
+            "#},
+        )
+        .expect("Cannot parse file");
+        let index = file.stanza_index("module").expect("missing stanza");
+        file.disable_stanza(index);
+        let mut config = ExecutionConfig::new(&functions, &globals).lazy(lazy);
+        let graph = file
+            .execute(&tree, python_source, &mut config, &NoCancellation)
+            .expect("Cannot execute file");
+        assert_eq!(
+            graph.pretty_print().to_string(),
+            indoc! {r#"
+              node 0
+                name: "pass"
+            "#}
+        );
+    }
+}
+
 #[test]
 fn can_use_variable_multiple_times() {
     check_execution(
@@ -578,11 +1114,33 @@ fn can_execute_if_literal() {
 }
 
 #[test]
-fn skip_if_without_true_conditions() {
+fn can_short_circuit_if_conditions() {
     check_execution(
         "pass",
         indoc! {r#"
-          (module (import_statement)? @x (import_statement)? @y)
+          (module (import_statement)? @x (pass_statement)? @y)
+          {
+            node node0
+            if some @x, (eq (source-text @x) "import a") {
+              attr (node0) val = 0
+            } elif some @y, (eq (source-text @y) "pass") {
+              attr (node0) val = 1
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            val: 1
+        "#},
+    );
+}
+
+#[test]
+fn skip_if_without_true_conditions() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module (import_statement)? @x (import_statement)? @y)
           {
             node node0
             if some @x {
@@ -670,6 +1228,32 @@ fn variables_are_inherited_in_if_body() {
     );
 }
 
+#[test]
+fn can_test_list_captures_for_emptiness() {
+    check_execution(
+        r#"
+          pass
+        "#,
+        indoc! {r#"
+          (module (pass_statement)* @xs (import_statement)* @ys)
+          {
+            node node0
+            if some @xs {
+              attr (node0) xs = "some"
+            }
+            if none @ys {
+              attr (node0) ys = "none"
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            xs: "some"
+            ys: "none"
+        "#},
+    );
+}
+
 #[test]
 fn can_execute_for_in_nonempty_list_capture() {
     check_execution(
@@ -744,6 +1328,85 @@ fn can_execute_for_in_list_literal() {
     );
 }
 
+#[test]
+fn can_destructure_lists() {
+    check_execution(
+        indoc! {r#"
+          pass
+          pass
+          pass
+        "#},
+        indoc! {r#"
+          (module (pass_statement)* @xs)
+          {
+            let [first, ...rest] = @xs
+            let [a, b] = [1, 2]
+            let [...all] = []
+            node node0
+            attr (node0) a = a, b = b, first = first, rest = rest, all = all
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            a: 1
+            all: []
+            b: 2
+            first: [syntax node pass_statement (1, 1)]
+            rest: [[syntax node pass_statement (2, 1)], [syntax node pass_statement (3, 1)]]
+        "#},
+    );
+}
+
+#[test]
+fn cannot_destructure_lists_of_the_wrong_length() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module (pass_statement)* @xs)
+          {
+            let [first, second] = @xs
+          }
+        "#},
+    );
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            let [first] = [1, 2]
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_iterate_zipped_lists() {
+    check_execution(
+        indoc! {r#"
+          pass
+          pass
+          pass
+        "#},
+        indoc! {r#"
+          (module (pass_statement)* @xs)
+          {
+            for x, i in zip(@xs, [1, 2]) {
+              node n
+              attr (n) pass = x, index = i
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            index: 1
+            pass: [syntax node pass_statement (1, 1)]
+          node 1
+            index: 2
+            pass: [syntax node pass_statement (2, 1)]
+        "#},
+    );
+}
+
 #[test]
 fn variables_are_local_in_for_in_body() {
     check_execution(
@@ -862,6 +1525,32 @@ fn can_execute_set_comprehension() {
     );
 }
 
+#[test]
+fn can_execute_comprehensions_with_conditions() {
+    check_execution(
+        r#"
+          import a
+          pass
+          import b
+        "#,
+        indoc! {r#"
+          (module (_)* @xs)
+          {
+            node node0
+            attr (node0) imports = [
+              (named-child-index x) for x in @xs if (not (eq (node-type x) "pass_statement"))
+            ]
+            attr (node0) kinds = { (node-type x) for x in @xs if (eq (named-child-index x) 1) }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            imports: [0, 2]
+            kinds: {"pass_statement"}
+        "#},
+    );
+}
+
 #[test]
 fn can_execute_scan_of_local_call_expression() {
     check_execution(
@@ -915,6 +1604,43 @@ fn can_execute_scan_of_local_variable() {
     );
 }
 
+#[test]
+fn can_execute_scan_of_list() {
+    check_execution(
+        r#"
+          import a
+          import b_c
+        "#,
+        indoc! {r#"
+          (module (import_statement)* @imports)
+          {
+            scan @imports {
+              "import (.*)" {
+                node n
+                attr (n) name = $1, row = (start-row element)
+              }
+            }
+            scan ["x", "y"] {
+              "y" {
+                node n
+                attr (n) name = element
+              }
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            name: "a"
+            row: 1
+          node 1
+            name: "b_c"
+            row: 2
+          node 2
+            name: "y"
+        "#},
+    );
+}
+
 #[test]
 fn can_execute_shorthand() {
     check_execution(
@@ -1072,3 +1798,988 @@ fn cannot_change_attribute_value() {
         "#},
     );
 }
+
+#[test]
+fn can_append_to_attribute_values() {
+    check_execution(
+        indoc! { r#"
+            pass
+            pass
+        "#},
+        indoc! {r#"
+            (module) @m {
+              node @m.node
+              attr (@m.node) refs += 1
+              node n
+              attr (n) a += 1, a += 2
+              attr (n) a += 3
+            }
+            (module (pass_statement) @_p) @m {
+              attr (@m.node) refs += 1
+            }
+        "#},
+        indoc! {r#"
+          node 0
+            refs: [1, 1, 1]
+          node 1
+            a: [1, 2, 3]
+        "#},
+    );
+}
+
+#[test]
+fn cannot_append_to_non_list_attribute_value() {
+    fail_execution(
+        indoc! { r#"
+            pass
+        "#},
+        indoc! {r#"
+            (module) {
+              node n
+              attr (n) a = 1
+              attr (n) a += 2
+            }
+        "#},
+    );
+}
+
+#[test]
+fn can_add_attributes_to_bound_edges() {
+    check_execution(
+        indoc! { r#"
+            pass
+        "#},
+        indoc! {r#"
+            (module) @m {
+              node n1
+              node n2
+              node n3
+              edge @m.edge = n1 -> n2
+              edge e = n2 -> n3
+              attr (e) local = #true
+            }
+            (module) @m {
+              attr (@m.edge) scoped = #true
+            }
+        "#},
+        indoc! {r#"
+          node 0
+          edge 0 -> 1
+            scoped: #true
+          node 1
+          edge 1 -> 2
+            local: #true
+          node 2
+        "#},
+    );
+}
+
+#[test]
+fn can_execute_file_from_several_threads() {
+    init_log();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (function_definition name: (identifier) @name) {
+            node @name.def
+            attr (@name.def) name = (source-text @name)
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    std::thread::scope(|scope| {
+        let threads = (0..4)
+            .map(|i| {
+                let (file, functions, globals) = (&file, &functions, &globals);
+                scope.spawn(move || {
+                    let python_source = format!("def f{}(): pass", i);
+                    let mut parser = Parser::new();
+                    parser.set_language(&tree_sitter_python::language()).unwrap();
+                    let tree = parser.parse(&python_source, None).unwrap();
+                    let config = ExecutionConfig::new(functions, globals);
+                    let graph = file
+                        .execute(&tree, &python_source, &config, &NoCancellation)
+                        .expect("Cannot execute file");
+                    let result = graph.pretty_print().to_string();
+                    (i, result)
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            let (i, graph) = thread.join().unwrap();
+            assert_eq!(graph, format!("node 0\n  name: \"f{}\"\n", i));
+        }
+    });
+}
+
+#[test]
+fn can_share_file_with_worker_threads() {
+    init_log();
+    let file = Arc::new(
+        File::from_str(
+            tree_sitter_python::language(),
+            indoc! {r#"
+              (function_definition name: (identifier) @name) {
+                node @name.def
+                attr (@name.def) name = (source-text @name)
+              }
+            "#},
+        )
+        .expect("Cannot parse file"),
+    );
+    let functions = Arc::new(Functions::stdlib());
+    let globals = Arc::new(Variables::new());
+    let workers = (0..4)
+        .map(|i| {
+            let (file, functions, globals) = (file.clone(), functions.clone(), globals.clone());
+            std::thread::spawn(move || {
+                let python_source = format!("def f{}(): pass", i);
+                let mut parser = Parser::new();
+                parser.set_language(&tree_sitter_python::language()).unwrap();
+                let tree = parser.parse(&python_source, None).unwrap();
+                let config = ExecutionConfig::new(&functions, &globals);
+                let graph = file
+                    .execute(&tree, &python_source, &config, &NoCancellation)
+                    .expect("Cannot execute file");
+                let result = graph.pretty_print().to_string();
+                result
+            })
+        })
+        .collect::<Vec<_>>();
+    for (i, worker) in workers.into_iter().enumerate() {
+        assert_eq!(
+            worker.join().unwrap(),
+            format!("node 0\n  name: \"f{}\"\n", i)
+        );
+    }
+}
+
+#[test]
+fn can_reuse_graphs_of_unedited_trees() {
+    init_log();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (function_definition name: (identifier) @name) {
+            node @name.def
+            attr (@name.def) name = (source-text @name), syntax = @name
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals);
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let old_source = "def f(): pass";
+    let old_tree = parser.parse(old_source, None).unwrap();
+    let previous = file
+        .execute(&old_tree, old_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+
+    // Without edits, the previous graph is reused for the new tree.
+    let same_tree = parser.parse(old_source, Some(&old_tree)).unwrap();
+    let graph = file
+        .execute_or_reuse(
+            &same_tree,
+            old_source,
+            &[],
+            Some(&previous),
+            &config,
+            &NoCancellation,
+        )
+        .expect("Cannot execute file");
+    assert_eq!(
+        graph.pretty_print().to_string(),
+        previous.pretty_print().to_string()
+    );
+    let node = graph.iter_nodes().next().unwrap();
+    let syntax = graph[node].attributes.get("syntax").unwrap();
+    let syntax = syntax.as_syntax_node_ref().unwrap();
+    let name = same_tree
+        .root_node()
+        .descendant_for_byte_range(4, 5)
+        .unwrap();
+    assert_eq!(graph[syntax].id(), name.id());
+
+    // With edits, the whole file is executed against the new tree.
+    let edit = InputEdit {
+        start_byte: 4,
+        old_end_byte: 5,
+        new_end_byte: 6,
+        start_position: Point::new(0, 4),
+        old_end_position: Point::new(0, 5),
+        new_end_position: Point::new(0, 6),
+    };
+    let mut edited_tree = old_tree.clone();
+    edited_tree.edit(&edit);
+    let new_source = "def gh(): pass";
+    let new_tree = parser.parse(new_source, Some(&edited_tree)).unwrap();
+    let graph = file
+        .execute_or_reuse(
+            &new_tree,
+            new_source,
+            &[edit],
+            Some(&previous),
+            &config,
+            &NoCancellation,
+        )
+        .expect("Cannot execute file");
+    assert_eq!(
+        graph.pretty_print().to_string(),
+        "node 0\n  name: \"gh\"\n  syntax: [syntax node identifier (1, 5)]\n"
+    );
+}
+
+#[test]
+fn can_map_graph_elements_to_statements() {
+    init_log();
+    let python_source = "pass";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module)
+          {
+            node n0
+            node n1
+            edge n0 -> n1
+            attr (n0 -> n1) precedence = 1
+            attr (n0) name = "n0"
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    for lazy in [false, true] {
+        let source_map = SourceMap::new();
+        let config = ExecutionConfig::new(&functions, &globals)
+            .lazy(lazy)
+            .source_map(&source_map);
+        let graph = file
+            .execute(&tree, python_source, &config, &NoCancellation)
+            .expect("Cannot execute file");
+        let nodes = graph.iter_nodes().collect::<Vec<_>>();
+        let (n0, n1) = (nodes[0], nodes[1]);
+        let location = |row, column| Some(Location { row, column });
+        assert_eq!(source_map.node(n0), location(2, 2));
+        assert_eq!(source_map.node(n1), location(3, 2));
+        assert_eq!(source_map.edge(n0, n1), location(4, 2));
+        assert_eq!(source_map.edge(n1, n0), None);
+        assert_eq!(
+            source_map.edge_attribute(n0, n1, &Identifier::from("precedence")),
+            location(5, 2)
+        );
+        assert_eq!(
+            source_map.node_attribute(n0, &Identifier::from("name")),
+            location(6, 2)
+        );
+    }
+}
+
+#[test]
+fn can_route_prints_to_output() {
+    init_log();
+    struct Messages(RefCell<Vec<String>>);
+    impl PrintOutput for Messages {
+        fn enabled(&self, level: PrintLevel, channel: Option<&str>) -> bool {
+            level <= PrintLevel::Debug && channel != Some("noisy")
+        }
+        fn print(&self, level: PrintLevel, channel: Option<&str>, message: &str) {
+            let entry = format!("{} {:?} {}", level, channel, message);
+            self.0.borrow_mut().push(entry);
+        }
+    }
+    let python_source = "pass";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module)
+          {
+            print "x = ", 1
+            print debug scoping: "y = ", 2
+            print trace "z = ", (plus "z" 1)
+            print noisy: "w = ", (plus "w" 1)
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    for lazy in [false, true] {
+        let messages = Messages(RefCell::new(Vec::new()));
+        let config = ExecutionConfig::new(&functions, &globals)
+            .lazy(lazy)
+            .output(&messages);
+        file.execute(&tree, python_source, &config, &NoCancellation)
+            .expect("Cannot execute file");
+        assert_eq!(
+            messages.0.into_inner(),
+            vec![
+                "info None x = 1".to_string(),
+                "debug Some(\"scoping\") y = 2".to_string(),
+            ]
+        );
+    }
+}
+
+#[test]
+fn can_print_graph_nodes() {
+    init_log();
+    struct Messages(RefCell<Vec<String>>);
+    impl PrintOutput for Messages {
+        fn print(&self, _level: PrintLevel, _channel: Option<&str>, message: &str) {
+            self.0.borrow_mut().push(message.to_string());
+        }
+    }
+    let python_source = "pass";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module)
+          {
+            node a
+            node b
+            attr (a) name = "a"
+            print "before: ", a
+            edge a -> b
+            attr (a -> b) precedence = 1
+            print "after: ", a
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let after = indoc! {r#"
+      after: node 0
+        name: "a"
+      edge 0 -> 1
+        precedence: 1"#};
+    // Lazy executions print after the whole graph is built.
+    let lazy_before = after.replace("after", "before");
+    for (lazy, before) in [
+        (false, "before: node 0\n  name: \"a\""),
+        (true, lazy_before.as_str()),
+    ] {
+        let messages = Messages(RefCell::new(Vec::new()));
+        let config = ExecutionConfig::new(&functions, &globals)
+            .lazy(lazy)
+            .output(&messages);
+        file.execute(&tree, python_source, &config, &NoCancellation)
+            .expect("Cannot execute file");
+        assert_eq!(messages.0.into_inner(), vec![before, after]);
+    }
+}
+
+#[test]
+fn can_observe_attribute_changes() {
+    init_log();
+    type Change = (String, String, Option<Value>, Value, usize);
+    struct Observer(Arc<Mutex<Vec<Change>>>);
+    impl Observer {
+        fn record(&self, target: String, change: &AttributeChange) -> Result<(), String> {
+            if change.name == "secret" {
+                return Err("secrets cannot be stored".to_string());
+            }
+            self.0.lock().unwrap().push((
+                target,
+                change.name.to_string(),
+                change.old_value.cloned(),
+                change.new_value.clone(),
+                change.provenance.statement_location.row,
+            ));
+            Ok(())
+        }
+    }
+    impl AttributeObserver for Observer {
+        fn node_attribute(
+            &self,
+            _graph: &Graph,
+            node: GraphNodeRef,
+            change: &AttributeChange,
+        ) -> Result<(), String> {
+            self.record(node.to_string(), change)
+        }
+
+        fn edge_attribute(
+            &self,
+            _graph: &Graph,
+            source: GraphNodeRef,
+            sink: GraphNodeRef,
+            change: &AttributeChange,
+        ) -> Result<(), String> {
+            self.record(format!("{} -> {}", source, sink), change)
+        }
+    }
+    let python_source = "pass";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module)
+          {
+            node a
+            node b
+            attr (a) name = "a"
+            attr (a) tags += "x"
+            attr (a) tags += "y"
+            attr (a) name = "a"
+            edge a -> b
+            attr (a -> b) precedence = 1
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let secret_file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module)
+          {
+            node a
+            attr (a) secret = "s"
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let list = |values: &[&str]| Value::List(values.iter().map(|value| (*value).into()).collect());
+    let mut expected = vec![
+        (
+            "[graph node 0]".to_string(),
+            "name".to_string(),
+            None,
+            Value::from("a"),
+            4,
+        ),
+        (
+            "[graph node 0]".to_string(),
+            "tags".to_string(),
+            None,
+            list(&["x"]),
+            5,
+        ),
+        (
+            "[graph node 0]".to_string(),
+            "tags".to_string(),
+            Some(list(&["x"])),
+            list(&["x", "y"]),
+            6,
+        ),
+        (
+            "[graph node 0] -> [graph node 1]".to_string(),
+            "precedence".to_string(),
+            None,
+            Value::Integer(1),
+            9,
+        ),
+    ];
+    expected.sort();
+    for lazy in [false, true] {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let config = ExecutionConfig::new(&functions, &globals).lazy(lazy);
+        let mut graph = Graph::new();
+        graph.set_attribute_observer(Observer(changes.clone()));
+        file.execute_into(&mut graph, &tree, python_source, &config, &NoCancellation)
+            .expect("Cannot execute file");
+        let mut changes = std::mem::take(&mut *changes.lock().unwrap());
+        changes.sort();
+        assert_eq!(changes, expected);
+
+        let mut graph = Graph::new();
+        graph.set_attribute_observer(Observer(Arc::new(Mutex::new(Vec::new()))));
+        let error = secret_file
+            .execute_into(&mut graph, &tree, python_source, &config, &NoCancellation)
+            .expect_err("Execution succeeded unexpectedly");
+        assert!(matches!(
+            error.root_cause(),
+            ExecutionError::RejectedAttribute(_)
+        ));
+        let node = graph.iter_nodes().next().unwrap();
+        assert!(graph.node_attribute(node, "secret").is_none());
+    }
+}
+
+#[test]
+fn can_execute_with_outcome() {
+    init_log();
+    let python_source = "pass\npass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (pass_statement)
+          {
+            node n
+            print "created ", n
+            print warn checks: "found a pass statement"
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let output = StderrOutput::new(PrintLevel::Error);
+    for lazy in [false, true] {
+        let config = ExecutionConfig::new(&functions, &globals)
+            .lazy(lazy)
+            .output(&output);
+        let outcome = file
+            .execute_with_outcome(&tree, python_source, &config, &NoCancellation)
+            .expect("Cannot execute file");
+        assert_eq!(outcome.graph.node_count(), 2);
+        let warning = ExecutionWarning {
+            level: PrintLevel::Warn,
+            channel: Some("checks".to_string()),
+            message: "found a pass statement".to_string(),
+        };
+        assert_eq!(outcome.warnings, vec![warning.clone(), warning]);
+        let matches = outcome
+            .statistics
+            .stanzas()
+            .iter()
+            .map(|stanza| stanza.matches)
+            .collect::<Vec<_>>();
+        assert_eq!(matches, vec![2]);
+        let node = outcome.graph.iter_nodes().next().unwrap();
+        assert_eq!(
+            outcome.provenance.node(node),
+            Some(Location { row: 2, column: 2 })
+        );
+    }
+    let statistics = ExecutionStatistics::new();
+    let config = ExecutionConfig::new(&functions, &globals).statistics(&statistics);
+    let result = file.execute_with_outcome(&tree, python_source, &config, &NoCancellation);
+    assert!(matches!(result, Err(ExecutionError::ConflictingConfig(_))));
+}
+
+#[test]
+fn can_debug_statements() {
+    init_log();
+    struct Trace(RefCell<Vec<(Location, usize, Vec<String>)>>);
+    impl Debugger for Trace {
+        fn statement(&self, frame: &DebugFrame) {
+            let mut locals = frame
+                .locals()
+                .into_iter()
+                .map(|(name, value)| format!("{} = {}", name, value))
+                .collect::<Vec<_>>();
+            locals.sort();
+            let entry = (frame.location(), frame.depth(), locals);
+            self.0.borrow_mut().push(entry);
+        }
+    }
+    let python_source = "pass";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module)
+          {
+            let x = 1
+            if #true {
+              node n
+            }
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let trace = Trace(RefCell::new(Vec::new()));
+    let config = ExecutionConfig::new(&functions, &globals)
+        .lazy(false)
+        .debugger(&trace);
+    file.execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+    let location = |row, column| Location { row, column };
+    assert_eq!(
+        trace.0.into_inner(),
+        vec![
+            (location(2, 2), 0, vec![]),
+            (location(3, 2), 0, vec!["x = 1".to_string()]),
+            (location(4, 4), 1, vec!["x = 1".to_string()]),
+        ]
+    );
+}
+
+#[test]
+fn can_trace_statements() {
+    init_log();
+    let python_source = "x = 1";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (assignment left: (_) @left)
+          {
+            let name = (source-text @left)
+            if #true {
+              node n
+              attr (n) name = name
+            }
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let trace = ExecutionTrace::new();
+    let config = ExecutionConfig::new(&functions, &globals)
+        .lazy(false)
+        .trace(&trace);
+    file.execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+    let matches = trace.matches();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].kind, "assignment");
+    assert_eq!(matches[0].captures[0].text, vec!["x".to_string()]);
+    let statements = matches[0]
+        .statements
+        .iter()
+        .map(|statement| {
+            let values = statement
+                .values
+                .iter()
+                .map(|value| format!("{} = {}", value.label, value.value))
+                .collect::<Vec<_>>();
+            (
+                statement.location,
+                statement.depth,
+                values,
+                statement.nodes.len(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let location = |row, column| Location { row, column };
+    assert_eq!(
+        statements,
+        vec![
+            (location(2, 2), 0, vec!["name = x".to_string()], 0),
+            (location(3, 2), 0, vec!["true = #true".to_string()], 0),
+            (location(4, 4), 1, vec!["n = [graph node 0]".to_string()], 1),
+            (
+                location(5, 4),
+                1,
+                vec!["n = [graph node 0]".to_string(), "name = x".to_string()],
+                0
+            ),
+        ]
+    );
+}
+
+#[test]
+fn can_collect_coverage() {
+    init_log();
+    let python_source = "pass\npass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (pass_statement)
+          {
+            node n
+            if #true {
+              attr (n) taken = #true
+            } else {
+              attr (n) taken = #false
+            }
+          }
+          (function_definition)
+          {
+            node n
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    for lazy in [false, true] {
+        let coverage = ExecutionCoverage::new();
+        let config = ExecutionConfig::new(&functions, &globals)
+            .lazy(lazy)
+            .coverage(&coverage);
+        file.execute(&tree, python_source, &config, &NoCancellation)
+            .expect("Cannot execute file");
+        let counts = |counts: Vec<tree_sitter_graph::CoverageCount>| {
+            counts
+                .iter()
+                .map(|count| (count.location.row, count.count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(counts(coverage.stanzas()), vec![(0, 2), (9, 0)]);
+        assert_eq!(
+            counts(coverage.statements()),
+            vec![(2, 2), (3, 2), (4, 2), (6, 0), (11, 0)]
+        );
+        let arms = coverage
+            .arms()
+            .iter()
+            .map(|arm| (arm.statement.row, arm.index, arm.count))
+            .collect::<Vec<_>>();
+        assert_eq!(arms, vec![(3, 0, 2), (3, 1, 0)]);
+    }
+}
+
+#[test]
+fn random_numbers_depend_only_on_seed() {
+    init_log();
+    let python_source = "pass\npass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (pass_statement)
+          {
+            node n
+            attr (n) id = (random 1000000007)
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let execute = |seed: Option<u64>, lazy: bool| {
+        let mut config = ExecutionConfig::new(&functions, &globals).lazy(lazy);
+        if let Some(seed) = seed {
+            config = config.seed(seed);
+        }
+        file.execute(&tree, python_source, &config, &NoCancellation)
+            .expect("Cannot execute file")
+            .pretty_print()
+            .to_string()
+    };
+    for lazy in [false, true] {
+        let graph = execute(Some(1), lazy);
+        assert_eq!(graph, execute(Some(1), lazy));
+        assert_ne!(graph, execute(Some(2), lazy));
+        assert_eq!(execute(None, lazy), execute(Some(0), lazy));
+    }
+}
+
+#[test]
+fn functions_can_inspect_the_context_of_their_calls() {
+    struct Context;
+
+    impl Function for Context {
+        fn call(
+            &self,
+            _graph: &mut Graph,
+            _source: &str,
+            _parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            Err(ExecutionError::FunctionFailed(
+                "context".into(),
+                "called without context".into(),
+            ))
+        }
+
+        fn call_in_context(
+            &self,
+            context: &CallContext,
+            graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            parameters.finish()?;
+            let mut result = vec![context.stanza_name().unwrap().to_string()];
+            for (name, node) in context.captures() {
+                result.push(format!("{}:{}", name, node.location().row));
+            }
+            let second = context.captures().last().map(|(_, node)| node);
+            assert_eq!(context.capture("_second"), second);
+            result.push(graph.node_count().to_string());
+            Ok(result.join(" ").into())
+        }
+    }
+
+    init_log();
+    let python_source = "pass\npass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          stanza passes
+          (module (pass_statement) @_first (pass_statement) @_second)
+          {
+            node n
+            attr (n) context = (context)
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let mut functions = Functions::stdlib();
+    functions.add(Identifier::from("context"), Context);
+    let globals = Variables::new();
+    for lazy in [false, true] {
+        let config = ExecutionConfig::new(&functions, &globals).lazy(lazy);
+        let graph = file
+            .execute(&tree, python_source, &config, &NoCancellation)
+            .expect("Cannot execute file");
+        assert_eq!(
+            graph.pretty_print().to_string(),
+            indoc! {r#"
+              node 0
+                context: "passes _first:0 _second:1 1"
+            "#}
+        );
+    }
+}
+
+#[test]
+fn can_store_attributes_in_columns() {
+    init_log();
+    let python_source = "pass\npass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (pass_statement) @pass
+          {
+            node @pass.node
+            attr (@pass.node) kind = "pass", refs += 1
+            attr (@pass.node) refs += 2
+          }
+          (module (_) @first (_) @second)
+          {
+            attr (@first.node) next = @second.node
+            edge @first.node -> @second.node
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals);
+    let graph = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+    let config = config.attribute_storage(AttributeStorage::Columns);
+    let columns = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+    assert_eq!(columns.attribute_storage(), AttributeStorage::Columns);
+    assert_eq!(
+        columns.pretty_print().to_string(),
+        graph.pretty_print().to_string()
+    );
+    for node in columns.iter_nodes() {
+        assert!(columns[node].attributes.iter().next().is_none());
+        assert_eq!(
+            columns.node_attribute(node, "kind"),
+            Some(&Value::from("pass"))
+        );
+    }
+}
+
+#[test]
+fn source_text_values_share_the_source() {
+    init_log();
+    let python_source = "x = 1\ny = 2\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (identifier) @id
+          {
+            node @id.node
+            attr (@id.node) name = (source-text @id)
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals);
+    let graph = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+    let slices = graph
+        .iter_nodes()
+        .map(|node| match graph.node_attribute(node, "name") {
+            Some(Value::SourceSlice(slice)) => slice.clone(),
+            value => panic!("Expected a source slice, got {:?}", value),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(slices.len(), 2);
+    assert_eq!(slices[0].as_str(), "x");
+    assert_eq!(slices[1].as_str(), "y");
+    assert!(std::sync::Arc::ptr_eq(
+        slices[0].source(),
+        slices[1].source()
+    ));
+    assert_eq!(
+        graph.pretty_print().to_string(),
+        indoc! {r#"
+          node 0
+            name: "x"
+          node 1
+            name: "y"
+        "#}
+    );
+}
+
+#[test]
+fn can_skip_stanzas_whose_kinds_do_not_occur() {
+    check_execution(
+        indoc! {r#"
+          x = 1
+          pass
+          y = 2
+        "#},
+        indoc! {r#"
+          (class_definition) @c
+          {
+            node @c.node
+            attr (@c.node) kind = "class"
+          }
+          [(while_statement) (pass_statement)] @s
+          {
+            node @s.node
+            attr (@s.node) kind = "statement"
+          }
+          ((identifier) @id (#eq? @id "y"))
+          {
+            node n
+            attr (n) name = (source-text @id)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            kind: "statement"
+          node 1
+            name: "y"
+        "#},
+    );
+}
@@ -0,0 +1,74 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use indoc::indoc;
+use tree_sitter::Parser;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::explain::MismatchReason;
+use tree_sitter_graph::Location;
+
+#[test]
+fn can_explain_non_matching_stanzas() {
+    let python_source = "def f(x):\n    return x\n";
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::language())
+        .unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (function_definition name: (identifier) @_name) {}
+          (class_definition) @_class {}
+          (function_definition return_type: (_) @_type) {}
+          (function_definition body: (block (pass_statement))) @_function {}
+          ((function_definition name: (identifier) @_name) (#eq? @_name "g")) {}
+        "#},
+    )
+    .expect("Cannot parse file");
+    let node = tree.root_node().named_child(0).unwrap();
+    assert_eq!(node.kind(), "function_definition");
+
+    let explanations = file.explain(node, python_source);
+    assert_eq!(explanations.len(), 5);
+    assert!(explanations[0].matched);
+    assert!(explanations[0].mismatch.is_none());
+    let mismatches = explanations[1..]
+        .iter()
+        .map(|explanation| {
+            assert!(!explanation.matched);
+            explanation.mismatch.clone().expect("missing mismatch")
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(mismatches[0].step, "(class_definition) @_class");
+    assert_eq!(mismatches[0].location, Location { row: 1, column: 0 });
+    assert_eq!(mismatches[0].kind, "function_definition");
+    assert_eq!(
+        mismatches[0].reason,
+        MismatchReason::Kind {
+            expected: "class_definition".to_string()
+        }
+    );
+
+    assert_eq!(mismatches[1].step, "return_type: (_) @_type");
+    assert_eq!(mismatches[1].location, Location { row: 2, column: 21 });
+    assert_eq!(
+        mismatches[1].reason,
+        MismatchReason::MissingField {
+            field: "return_type".to_string()
+        }
+    );
+
+    assert_eq!(mismatches[2].step, "(pass_statement)");
+    assert_eq!(mismatches[2].kind, "block");
+    assert_eq!(mismatches[2].position, Location { row: 1, column: 4 });
+    assert_eq!(mismatches[2].reason, MismatchReason::MissingChild);
+
+    assert_eq!(mismatches[3].step, "(#eq? @_name \"g\")");
+    assert_eq!(mismatches[3].reason, MismatchReason::Predicate);
+}
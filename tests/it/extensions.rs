@@ -0,0 +1,121 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use indoc::indoc;
+use tree_sitter::Parser;
+use tree_sitter_graph::ast;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::extensions::Extensions;
+use tree_sitter_graph::extensions::StatementExtension;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::functions::Parameters;
+use tree_sitter_graph::graph::Graph;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::ExecutionError;
+use tree_sitter_graph::Identifier;
+use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::ParseError;
+use tree_sitter_graph::Variables;
+
+/// Sets the `symbol` attribute of a graph node.
+struct PushSymbol;
+
+impl StatementExtension for PushSymbol {
+    fn check(&self, statement: &ast::Extension) -> Result<(), String> {
+        if statement.arguments.len() != 2 {
+            return Err("expected a node and a symbol".to_string());
+        }
+        Ok(())
+    }
+
+    fn execute(
+        &self,
+        graph: &mut Graph,
+        _source: &str,
+        arguments: &mut dyn Parameters,
+    ) -> Result<(), ExecutionError> {
+        let node = arguments.param()?.into_graph_node_ref()?;
+        let symbol = arguments.param()?;
+        arguments.finish()?;
+        graph[node]
+            .attributes
+            .add(Identifier::from("symbol"), symbol)
+            .map_err(|_| ExecutionError::DuplicateAttribute("symbol".to_string()))
+    }
+}
+
+fn extensions() -> Extensions {
+    let mut extensions = Extensions::new();
+    extensions.add(Identifier::from("push_symbol"), PushSymbol);
+    extensions
+}
+
+fn execute(python_source: &str, dsl_source: &str, lazy: bool) -> Result<String, ExecutionError> {
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let extensions = extensions();
+    let file =
+        File::from_str_with_extensions(tree_sitter_python::language(), dsl_source, &extensions)
+            .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals)
+        .lazy(lazy)
+        .extensions(&extensions);
+    let graph = file.execute(&tree, python_source, &config, &NoCancellation)?;
+    let result = graph.pretty_print().to_string();
+    Ok(result)
+}
+
+#[test]
+fn can_execute_extension_statements() {
+    let dsl_source = indoc! {r#"
+      (function_definition name: (identifier) @name) {
+        node @name.def
+        push_symbol @name.def, (source-text @name)
+      }
+    "#};
+    let expected = indoc! {r#"
+      node 0
+        symbol: "f"
+    "#};
+    for lazy in [false, true] {
+        let graph = execute("def f(): pass", dsl_source, lazy).expect("Cannot execute file");
+        assert_eq!(graph, expected);
+    }
+}
+
+#[test]
+fn cannot_parse_invalid_extension_statements() {
+    let dsl_source = indoc! {r#"
+      (function_definition name: (identifier) @name) {
+        node @name.def
+        push_symbol @name.def
+      }
+    "#};
+    let err =
+        File::from_str_with_extensions(tree_sitter_python::language(), dsl_source, &extensions())
+            .expect_err("Parse succeeded unexpectedly");
+    assert_eq!(
+        err.to_string(),
+        "Invalid push_symbol statement: expected a node and a symbol at (3, 3)"
+    );
+}
+
+#[test]
+fn cannot_parse_extension_statements_without_extensions() {
+    let dsl_source = indoc! {r#"
+      (function_definition name: (identifier) @name) {
+        node @name.def
+        push_symbol @name.def, (source-text @name)
+      }
+    "#};
+    let err = File::from_str(tree_sitter_python::language(), dsl_source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(err, ParseError::UnexpectedKeyword(keyword, _) if keyword == "push_symbol"));
+}
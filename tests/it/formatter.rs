@@ -0,0 +1,115 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use indoc::indoc;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::Location;
+use tree_sitter_graph::ParseError;
+
+fn check_format(source: &str, expected: &str) {
+    let formatted = File::format(source).expect("Cannot format file");
+    assert_eq!(formatted, expected);
+    let reformatted = File::format(&formatted).expect("Cannot format file");
+    assert_eq!(reformatted, expected, "Formatting is not idempotent");
+}
+
+#[test]
+fn can_format_indentation() {
+    check_format(
+        indoc! {r#"
+          (function_definition
+                name: (identifier) @name) @def
+          {
+          node n
+              if some @name {
+          attr (n) name = (source-text @name)
+                  }
+          }
+        "#},
+        indoc! {r#"
+          (function_definition
+            name: (identifier) @name) @def
+          {
+            node n
+            if some @name {
+              attr (n) name = (source-text @name)
+            }
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_format_whitespace() {
+    check_format(
+        "\n\nglobal   filename\n\n\n\n( module ) @m {\n\n  node   n  \n  attr (n)  a = [ 1 , 2 ]\n\n}\n\n",
+        indoc! {r#"
+          global filename
+
+          (module) @m {
+            node n
+            attr (n) a = [1, 2]
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_format_flag_attributes() {
+    check_format(
+        indoc! {r#"
+          (module) @m {
+            node n
+            attr (n) is_module ,  ! is_test,  kind = "module"
+          }
+        "#},
+        indoc! {r#"
+          (module) @m {
+            node n
+            attr (n) is_module, !is_test, kind = "module"
+          }
+        "#},
+    );
+}
+
+#[test]
+fn formatting_preserves_comments_and_strings() {
+    check_format(
+        indoc! {r#"
+          ; a   comment   (with parens
+          (module)   {  ;  trailing   comment
+              let s =   "a  ( b"
+              let t = "multi
+                line   string"
+          }
+        "#},
+        indoc! {r#"
+          ; a   comment   (with parens
+          (module) { ;  trailing   comment
+            let s = "a  ( b"
+            let t = "multi
+                line   string"
+          }
+        "#},
+    );
+}
+
+#[test]
+fn cannot_format_unbalanced_brackets() {
+    assert!(matches!(
+        File::format("(module) {\n  node n)\n}\n"),
+        Err(ParseError::UnexpectedCharacter(
+            ')',
+            _,
+            Location { row: 1, column: 8 }
+        ))
+    ));
+    assert!(matches!(
+        File::format("(module) {\n  node n\n"),
+        Err(ParseError::UnexpectedEOF(Location { row: 0, column: 9 }))
+    ));
+}
@@ -5,10 +5,14 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::collections::HashMap;
+
 use indoc::indoc;
 use tree_sitter::Parser;
 use tree_sitter_graph::ast::File;
+use tree_sitter_graph::functions::Environment;
 use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph::Graph;
 use tree_sitter_graph::ExecutionConfig;
 use tree_sitter_graph::ExecutionError;
 use tree_sitter_graph::Identifier;
@@ -25,6 +29,14 @@ fn init_log() {
 }
 
 fn execute(python_source: &str, dsl_source: &str) -> Result<String, ExecutionError> {
+    execute_in_environment(python_source, dsl_source, None)
+}
+
+fn execute_in_environment(
+    python_source: &str,
+    dsl_source: &str,
+    environment: Option<&Environment>,
+) -> Result<String, ExecutionError> {
     init_log();
     let mut parser = Parser::new();
     parser.set_language(&tree_sitter_python::language()).unwrap();
@@ -37,6 +49,9 @@ fn execute(python_source: &str, dsl_source: &str) -> Result<String, ExecutionErr
         .add(Identifier::from("filename"), "test.py".into())
         .map_err(|_| ExecutionError::DuplicateVariable("filename".into()))?;
     let mut config = ExecutionConfig::new(&functions, &globals);
+    if let Some(environment) = environment {
+        config = config.environment(environment);
+    }
     let graph = file.execute(&tree, python_source, &mut config, &NoCancellation)?;
     let result = graph.pretty_print().to_string();
     Ok(result)
@@ -232,3 +247,659 @@ fn can_join_list_without_separator() {
         "#},
     );
 }
+
+#[test]
+fn can_encode_and_decode_base64() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) encoded = (base64-encode "def f(): pass")
+            attr (n) decoded = (base64-decode "ZGVmIGYoKTogcGFzcw==")
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            decoded: "def f(): pass"
+            encoded: "ZGVmIGYoKTogcGFzcw=="
+        "#},
+    );
+}
+
+#[test]
+fn cannot_decode_invalid_base64() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) decoded = (base64-decode "not base64!")
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_encode_and_decode_uri_components() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) encoded = (uri-encode "src/a b.py#L1")
+            attr (n) decoded = (uri-decode "src%2Fa%20b.py%23L1")
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            decoded: "src/a b.py#L1"
+            encoded: "src%2Fa%20b.py%23L1"
+        "#},
+    );
+}
+
+#[test]
+fn can_parse_json() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) value = (json-parse "[1, \"two\", [true, null]]")
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            value: [1, "two", [#true, #null]]
+        "#},
+    );
+}
+
+#[test]
+fn cannot_parse_json_objects() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) value = (json-parse "{\"a\": 1}")
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_stringify_json() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) value = (json-stringify [1, "two", [#true, #null]])
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            value: "[1,\"two\",[true,null]]"
+        "#},
+    );
+}
+
+#[test]
+fn can_compute_edit_distance() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) same = (edit-distance "field" "field")
+            attr (n) different = (edit-distance "kitten" "sitting")
+            attr (n) empty = (edit-distance "" "abc")
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            different: 3
+            empty: 3
+            same: 0
+        "#},
+    );
+}
+
+#[test]
+fn can_fuzzy_match_strings() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) case = (fuzzy-match? "Name" "name")
+            attr (n) close = (fuzzy-match? "color" "colour")
+            attr (n) far = (fuzzy-match? "getName" "name")
+            attr (n) far_with_distance = (fuzzy-match? "getName" "name" 3)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            case: #true
+            close: #true
+            far: #false
+            far_with_distance: #true
+        "#},
+    );
+}
+
+#[test]
+fn can_normalize_unicode() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            ; the first string ends with "e" followed by a combining acute accent
+            attr (n) composed = (eq (unicode-nfc "café") "café")
+            attr (n) folded = (unicode-casefold "Straße")
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            composed: #true
+            folded: "strasse"
+        "#},
+    );
+}
+
+#[test]
+fn can_take_substrings_of_graphemes() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) prefix = (substring "naïve" 0 3)
+            attr (n) suffix = (substring "naïve" 3)
+            attr (n) past_end = (substring "naïve" 3 10)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            past_end: "ve"
+            prefix: "naï"
+            suffix: "ve"
+        "#},
+    );
+}
+
+#[test]
+fn can_match_node_kinds() {
+    check_execution(
+        indoc! {r#"
+          def f():
+            pass
+        "#},
+        indoc! {r#"
+          (function_definition) @def
+          {
+            node n
+            attr (n) glob = (node-kind-matches? @def "*_definition")
+            attr (n) alternation = (node-kind-matches? @def "class_definition|function_definition")
+            attr (n) single = (node-kind-matches? @def "function_definitio?")
+            attr (n) partial = (node-kind-matches? @def "function")
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            alternation: #true
+            glob: #true
+            partial: #false
+            single: #true
+        "#},
+    );
+}
+
+#[test]
+fn can_compute_min_and_max() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) min = (min 3 1 2)
+            attr (n) max = (max 3 1 2)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            max: 3
+            min: 1
+        "#},
+    );
+}
+
+#[test]
+fn can_clamp_integers() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) low = (clamp 0 1 5)
+            attr (n) mid = (clamp 3 1 5)
+            attr (n) high = (clamp 9 1 5)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            high: 5
+            low: 1
+            mid: 3
+        "#},
+    );
+}
+
+#[test]
+fn can_compute_powers() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) pow = (pow 2 10)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            pow: 1024
+        "#},
+    );
+}
+
+#[test]
+fn can_compute_with_negative_integers() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) abs = (abs -0x10)
+            attr (n) sum = (plus 1 -3)
+            attr (n) min = (min 0 -1_000)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            abs: 16
+            min: -1000
+            sum: -2
+        "#},
+    );
+}
+
+#[test]
+fn cannot_compute_negative_powers() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) pow = (pow 2 -1)
+          }
+        "#},
+    );
+}
+
+#[test]
+fn cannot_compute_overflowing_powers() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) pow = (pow 2 63)
+          }
+        "#},
+    );
+}
+
+#[test]
+fn cannot_compute_overflowing_sums() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) sum = (plus 9223372036854775807 1)
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_compute_random_numbers() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) die = (random 1)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            die: 0
+        "#},
+    );
+}
+
+#[test]
+fn cannot_compute_random_numbers_without_positive_bound() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) die = (random 0)
+          }
+        "#},
+    );
+}
+
+fn test_environment() -> Environment {
+    let mut config = HashMap::new();
+    config.insert("module-root".to_string(), "src".into());
+    Environment::new(config)
+}
+
+#[test]
+fn can_get_config_values() {
+    let environment = test_environment();
+    let actual_graph = execute_in_environment(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) root = (config-get "module-root")
+            attr (n) missing = (config-get "missing")
+            attr (n) default = (config-get "missing" "lib")
+          }
+        "#},
+        Some(&environment),
+    )
+    .expect("Could not execute file");
+    assert_eq!(
+        actual_graph,
+        indoc! {r#"
+          node 0
+            default: "lib"
+            missing: #null
+            root: "src"
+        "#}
+    );
+}
+
+#[test]
+fn can_check_file_existence() {
+    let environment = test_environment();
+    let actual_graph = execute_in_environment(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) exists = (file-exists? "Cargo.toml")
+            attr (n) missing = (file-exists? "Cargo.missing")
+          }
+        "#},
+        Some(&environment),
+    )
+    .expect("Could not execute file");
+    assert_eq!(
+        actual_graph,
+        indoc! {r#"
+          node 0
+            exists: #true
+            missing: #false
+        "#}
+    );
+}
+
+#[test]
+fn missing_environment_variable_is_null() {
+    let environment = test_environment();
+    let actual_graph = execute_in_environment(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) var = (env "TREE_SITTER_GRAPH_UNSET_VARIABLE")
+          }
+        "#},
+        Some(&environment),
+    )
+    .expect("Could not execute file");
+    assert_eq!(
+        actual_graph,
+        indoc! {r#"
+          node 0
+            var: #null
+        "#}
+    );
+}
+
+#[test]
+fn cannot_access_environment_unless_enabled() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) exists = (file-exists? "Cargo.toml")
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_pin_time() {
+    let environment = test_environment().fixed_time(1709899200);
+    let actual_graph = execute_in_environment(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) iso = (now-iso8601)
+            attr (n) timestamp = (unix-timestamp)
+          }
+        "#},
+        Some(&environment),
+    )
+    .expect("Could not execute file");
+    assert_eq!(
+        actual_graph,
+        indoc! {r#"
+          node 0
+            iso: "2024-03-08T12:00:00Z"
+            timestamp: 1709899200
+        "#}
+    );
+}
+
+#[test]
+fn can_list_function_names() {
+    let functions = Functions::stdlib();
+    let names = functions
+        .names()
+        .map(Identifier::as_str)
+        .collect::<Vec<_>>();
+    assert!(names.contains(&"node"));
+    assert!(names.contains(&"source-text"));
+    assert!(!names.contains(&"env"));
+}
+
+#[test]
+fn can_classify_nodes_with_highlights_query() {
+    init_log();
+    let python_source = "print(x)";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (identifier) @id
+          {
+            node n
+            attr (n) name = (source-text @id), highlight = (highlight-of @id)
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let mut functions = Functions::stdlib();
+    functions
+        .add_highlights(
+            &tree_sitter_python::language(),
+            indoc! {r#"
+              (call function: (identifier) @function.call)
+              (identifier) @variable
+            "#},
+        )
+        .expect("Cannot parse highlights query");
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals);
+    let graph = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Could not execute file");
+    assert_eq!(
+        graph.pretty_print().to_string(),
+        indoc! {r#"
+          node 0
+            highlight: "function.call"
+            name: "print"
+          node 1
+            highlight: "variable"
+            name: "x"
+        "#}
+    );
+}
+
+#[test]
+fn can_create_edges_to_external_nodes() {
+    init_log();
+    let python_source = "print(x)\nlen(x)";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (call function: (identifier) @function)
+          {
+            node n
+            attr (n) name = (source-text @function)
+            let builtin = (external-node (format "builtins.{}" (source-text @function)))
+            if (not (is-null builtin)) {
+              edge n -> builtin
+            }
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let mut graph = Graph::new();
+    let print = graph.add_external_node("builtins.print");
+    graph
+        .add_node_attribute(print, Identifier::from("builtin"), "print")
+        .unwrap();
+    assert_eq!(graph.add_external_node("builtins.print"), print);
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals);
+    file.execute_into(&mut graph, &tree, python_source, &config, &NoCancellation)
+        .expect("Could not execute file");
+    assert_eq!(graph.external_node("builtins.print"), Some(print));
+    assert_eq!(graph.external_node("builtins.len"), None);
+    assert_eq!(
+        graph.pretty_print().to_string(),
+        indoc! {r#"
+          node 0
+            builtin: "print"
+          node 1
+            name: "print"
+          edge 1 -> 0
+          node 2
+            name: "len"
+        "#}
+    );
+}
+
+#[test]
+fn can_validate_the_functions_a_file_uses() {
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          uses "string", "env"
+          (module)
+          {
+            node n
+            attr (n) home = (replace (env "HOME") "/" ":")
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    assert_eq!(file.uses.len(), 1);
+    let functions = Functions::stdlib();
+    match file.validate_functions(&functions, None) {
+        Err(ExecutionError::MissingFunctionNamespaces(namespaces)) => {
+            assert_eq!(namespaces, "env")
+        }
+        result => panic!("Unexpected result {:?}", result),
+    }
+    let environment = test_environment();
+    file.validate_functions(&functions, Some(&environment))
+        .expect("Cannot validate functions");
+}
+
+#[test]
+fn cannot_execute_file_that_uses_missing_functions() {
+    let result = execute(
+        "pass",
+        indoc! {r#"
+          uses "string"
+          (module)
+          {
+            node n
+            attr (n) a = (frobnicate (format "{}" (twiddle)))
+            attr (n) b = (twiddle)
+          }
+        "#},
+    );
+    match result {
+        Err(ExecutionError::MissingFunctions(functions)) => {
+            assert_eq!(functions, "frobnicate, twiddle")
+        }
+        result => panic!("Unexpected result {:?}", result),
+    }
+}
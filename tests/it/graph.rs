@@ -5,9 +5,16 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::collections::HashSet;
+use std::sync::Arc;
+
 use indoc::indoc;
 use tree_sitter::Parser;
+use tree_sitter_graph::graph::AttributeStorage;
 use tree_sitter_graph::graph::Graph;
+use tree_sitter_graph::graph::NodeOrder;
+use tree_sitter_graph::graph::PrintOptions;
+use tree_sitter_graph::graph::SourceSlice;
 use tree_sitter_graph::graph::Value;
 use tree_sitter_graph::Identifier;
 
@@ -22,6 +29,37 @@ fn can_overwrite_attributes() {
     assert_eq!(*attrs.get(&name).unwrap(), Value::from("overwritten"));
 }
 
+#[test]
+fn can_iterate_attributes_in_name_order() {
+    let mut graph = Graph::new();
+    let node = graph.add_graph_node();
+    let attrs = &mut graph[node].attributes;
+    attrs
+        .add(Identifier::from("zeta"), Value::Integer(1))
+        .unwrap();
+    attrs
+        .add(Identifier::from("alpha"), Value::Integer(2))
+        .unwrap();
+    attrs
+        .append(Identifier::from("mu"), Value::Integer(3))
+        .unwrap();
+    attrs
+        .append(Identifier::from("mu"), Value::Integer(4))
+        .unwrap();
+    attrs
+        .append(Identifier::from("alpha"), Value::Integer(5))
+        .unwrap_err();
+    let names = attrs
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["alpha", "mu", "zeta"]);
+    assert_eq!(
+        *attrs.get("mu").unwrap(),
+        Value::from(vec![Value::Integer(3), Value::Integer(4)])
+    );
+}
+
 #[test]
 fn can_iterate_graph_nodes() {
     let mut graph = Graph::new();
@@ -102,3 +140,433 @@ fn can_display_graph() {
         "#}
     );
 }
+
+#[test]
+fn can_display_graph_with_options() {
+    let mut graph = Graph::new();
+    for (name, rank) in [("first", 2), ("second", 1), ("third", 3)] {
+        let node = graph.add_graph_node();
+        graph[node]
+            .attributes
+            .add(Identifier::from("name"), name)
+            .unwrap();
+        graph[node]
+            .attributes
+            .add(Identifier::from("rank"), rank)
+            .unwrap();
+    }
+    let node0 = graph.iter_nodes().next().unwrap();
+    let node3 = graph.add_graph_node();
+    let edge = graph[node3]
+        .add_edge(node0)
+        .unwrap_or_else(|_| unreachable!());
+    edge.attributes
+        .add(Identifier::from("precedence"), 14)
+        .unwrap();
+    let options = PrintOptions::new()
+        .filter(|attributes| attributes.get("rank").is_some())
+        .order(NodeOrder::Attribute(Identifier::from("rank")))
+        .max_value_length(7)
+        .incoming_edges(true);
+    assert_eq!(
+        graph.pretty_print_with(&options).to_string(),
+        indoc! {r#"
+          node 1
+            name: "second... (8 characters)
+            rank: 1
+          node 0
+            name: "first"
+            rank: 2
+          edge 0 <- 3
+            precedence: 14
+          node 2
+            name: "third"
+            rank: 3
+        "#}
+    );
+}
+
+fn small_graph() -> Graph<'static> {
+    let mut graph = Graph::new();
+    let node0 = graph.add_graph_node();
+    graph[node0]
+        .attributes
+        .add(Identifier::from("name"), "a \"quoted\" <name>")
+        .unwrap();
+    let node1 = graph.add_graph_node();
+    let edge01 = graph[node0]
+        .add_edge(node1)
+        .unwrap_or_else(|_| unreachable!());
+    edge01
+        .attributes
+        .add(Identifier::from("precedence"), 14)
+        .unwrap();
+    graph
+}
+
+#[test]
+fn can_display_graph_as_dot() {
+    let graph = small_graph();
+    assert_eq!(
+        graph.display_dot().to_string(),
+        indoc! {r#"
+          digraph {
+            n0 [label="0\nname: a \"quoted\" <name>"];
+            n1 [label="1"];
+            n0 -> n1 [label="precedence: 14"];
+          }
+        "#}
+    );
+}
+
+#[test]
+fn can_display_graph_as_graphml() {
+    let graph = small_graph();
+    assert_eq!(
+        graph.display_graphml().to_string(),
+        indoc! {r#"
+          <?xml version="1.0" encoding="UTF-8"?>
+          <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+            <key id="node-name" for="node" attr.name="name" attr.type="string"/>
+            <key id="edge-precedence" for="edge" attr.name="precedence" attr.type="string"/>
+            <graph edgedefault="directed">
+              <node id="n0">
+                <data key="node-name">a &quot;quoted&quot; &lt;name&gt;</data>
+              </node>
+              <node id="n1">
+              </node>
+              <edge source="n0" target="n1">
+                <data key="edge-precedence">14</data>
+              </edge>
+            </graph>
+          </graphml>
+        "#}
+    );
+}
+
+#[test]
+fn can_display_graph_as_mermaid() {
+    let graph = small_graph();
+    assert_eq!(
+        graph.display_mermaid().to_string(),
+        indoc! {r#"
+          flowchart TD
+            n0["0<br/>name: a #quot;quoted#quot; <name>"]
+            n1["1"]
+            n0 -->|"precedence: 14"| n1
+        "#}
+    );
+}
+
+#[test]
+fn can_display_graph_as_canonical_text() {
+    let graph = small_graph();
+    assert_eq!(
+        graph.to_canonical_text(),
+        indoc! {r#"
+          node 144b1787e53f
+          node d7b5905a6114
+          attr d7b5905a6114 name = "a \"quoted\" <name>"
+          edge d7b5905a6114 -> 144b1787e53f
+          attr d7b5905a6114 -> 144b1787e53f precedence = 14
+        "#}
+    );
+}
+
+#[test]
+fn canonical_text_does_not_depend_on_creation_order() {
+    let build = |reference_first: bool| {
+        let mut graph = Graph::new();
+        let (definition, reference) = if reference_first {
+            let reference = graph.add_graph_node();
+            (graph.add_labeled_graph_node("definition".into()), reference)
+        } else {
+            let definition = graph.add_labeled_graph_node("definition".into());
+            (definition, graph.add_graph_node())
+        };
+        graph[definition]
+            .attributes
+            .add(Identifier::from("name"), "x")
+            .unwrap();
+        graph[reference]
+            .attributes
+            .add(Identifier::from("target"), definition)
+            .unwrap();
+        let _ = graph.add_kinded_edge(reference, definition, "refers".into());
+        graph.to_canonical_text()
+    };
+    let expected = indoc! {r#"
+      node 2154eb24c744
+      attr 2154eb24c744 target = [graph node 8ae6b5e5085f]
+      edge 2154eb24c744 -"refers"-> 8ae6b5e5085f
+      node 8ae6b5e5085f : "definition"
+      attr 8ae6b5e5085f name = "x"
+    "#};
+    assert_eq!(build(false), expected);
+    assert_eq!(build(true), expected);
+
+    // Nodes with the same contents are told apart by a suffix.
+    let mut graph = Graph::new();
+    graph.add_graph_node();
+    graph.add_graph_node();
+    assert_eq!(
+        graph.to_canonical_text(),
+        "node cbf29ce48422\nnode cbf29ce48422-2\n"
+    );
+}
+
+#[test]
+fn can_label_graph_nodes() {
+    let mut graph = Graph::new();
+    let node0 = graph.add_labeled_graph_node("definition".into());
+    let node1 = graph.add_graph_node();
+    let node2 = graph.add_labeled_graph_node("definition".into());
+    let _ = graph[node0].add_edge(node1);
+    assert_eq!(graph[node0].label(), Some(&Identifier::from("definition")));
+    assert_eq!(graph[node1].label(), None);
+    assert!(graph[node0].attributes.iter().next().is_none());
+    assert_eq!(
+        graph.nodes_with_label("definition").collect::<Vec<_>>(),
+        vec![node0, node2]
+    );
+    assert_eq!(graph.nodes_with_label("reference").count(), 0);
+
+    assert_eq!(
+        graph.pretty_print().to_string(),
+        indoc! {r#"
+          node 0 : "definition"
+          edge 0 -> 1
+          node 1
+          node 2 : "definition"
+        "#}
+    );
+    assert_eq!(
+        graph.display_dot().to_string(),
+        indoc! {r#"
+          digraph {
+            n0 [label="0: definition"];
+            n1 [label="1"];
+            n2 [label="2: definition"];
+            n0 -> n1 [label=""];
+          }
+        "#}
+    );
+    assert!(graph
+        .display_graphml()
+        .to_string()
+        .contains(r#"<node id="n0" labels=":definition">"#));
+    assert!(graph
+        .display_mermaid()
+        .to_string()
+        .contains(r#"n0["0: definition"]"#));
+    let json = serde_json::to_value(&graph).unwrap();
+    assert_eq!(json[0]["label"], "definition");
+    assert!(json[1].get("label").is_none());
+}
+
+#[test]
+fn can_index_edge_kinds() {
+    let mut graph = Graph::new();
+    let node0 = graph.add_graph_node();
+    let node1 = graph.add_graph_node();
+    let node2 = graph.add_graph_node();
+    let _ = graph.add_kinded_edge(node0, node1, "calls".into());
+    let _ = graph.add_kinded_edge(node2, node1, "calls".into());
+    let _ = graph.add_kinded_edge(node0, node2, "returns".into());
+    let _ = graph[node1].add_edge(node0);
+    assert!(graph.add_kinded_edge(node0, node1, "calls".into()).is_err());
+    assert_eq!(
+        graph[node0].get_edge(node1).unwrap().kind(),
+        Some(&Identifier::from("calls"))
+    );
+    assert_eq!(graph[node1].get_edge(node0).unwrap().kind(), None);
+    assert_eq!(
+        graph.edges_with_kind("calls").collect::<Vec<_>>(),
+        vec![(node0, node1), (node2, node1)]
+    );
+    assert_eq!(graph.edges_with_kind("defines").count(), 0);
+    assert_eq!(
+        graph[node0]
+            .iter_edges_with_kind("returns")
+            .map(|(sink, _)| sink)
+            .collect::<Vec<_>>(),
+        vec![node2]
+    );
+
+    assert_eq!(
+        graph.pretty_print().to_string(),
+        indoc! {r#"
+          node 0
+          edge 0 -"calls"-> 1
+          edge 0 -"returns"-> 2
+          node 1
+          edge 1 -> 0
+          node 2
+          edge 2 -"calls"-> 1
+        "#}
+    );
+    assert!(graph
+        .display_dot()
+        .to_string()
+        .contains(r#"n0 -> n1 [label="calls"];"#));
+    assert!(graph
+        .display_graphml()
+        .to_string()
+        .contains(r#"<edge source="n0" target="n1" label="calls">"#));
+    let json = serde_json::to_value(&graph).unwrap();
+    assert_eq!(json[0]["edges"][0]["kind"], "calls");
+    assert!(json[1]["edges"][0].get("kind").is_none());
+}
+
+#[test]
+fn external_nodes_are_listed_in_order_of_their_keys() {
+    let mut graph = Graph::new();
+    let keys = ["c", "a", "d", "b"];
+    let nodes = keys
+        .iter()
+        .map(|key| graph.add_external_node(key))
+        .collect::<Vec<_>>();
+    let listed = graph.external_nodes().collect::<Vec<_>>();
+    assert_eq!(
+        listed,
+        vec![
+            ("a", nodes[1]),
+            ("b", nodes[3]),
+            ("c", nodes[0]),
+            ("d", nodes[2])
+        ]
+    );
+}
+
+#[test]
+fn snapshots_are_not_changed_by_later_changes() {
+    let mut graph = Graph::new();
+    let node0 = graph.add_graph_node();
+    let node1 = graph.add_graph_node();
+    let name = Identifier::from("name");
+    graph[node0].attributes.add(name.clone(), "before").unwrap();
+    let snapshot = graph.snapshot();
+    graph[node0]
+        .attributes
+        .add(name.clone(), "after")
+        .unwrap_err();
+    let _ = graph.add_kinded_edge(node0, node1, Identifier::from("calls"));
+    graph.add_labeled_graph_node(Identifier::from("definition"));
+    assert_eq!(
+        *snapshot[node0].attributes.get(&name).unwrap(),
+        Value::from("before")
+    );
+    assert_eq!(snapshot[node0].edge_count(), 0);
+    assert_eq!(snapshot.node_count(), 2);
+    assert_eq!(snapshot.nodes_with_label("definition").count(), 0);
+    assert_eq!(
+        *graph[node0].attributes.get(&name).unwrap(),
+        Value::from("after")
+    );
+    assert_eq!(graph[node0].edge_count(), 1);
+    assert_eq!(graph.node_count(), 3);
+    let copy = snapshot.clone();
+    assert_eq!(
+        copy.pretty_print().to_string(),
+        snapshot.pretty_print().to_string()
+    );
+}
+
+#[test]
+fn can_move_attributes_between_storages() {
+    let mut graph = Graph::new();
+    let node0 = graph.add_graph_node();
+    let node1 = graph.add_graph_node();
+    let name = Identifier::from("name");
+    graph[node0].attributes.add(name.clone(), "node0").unwrap();
+    let expected = graph.pretty_print().to_string();
+
+    graph.set_attribute_storage(AttributeStorage::Columns);
+    assert!(graph[node0].attributes.get(&name).is_none());
+    assert_eq!(
+        graph.node_attribute(node0, "name"),
+        Some(&Value::from("node0"))
+    );
+    assert_eq!(graph.node_attribute(node1, "name"), None);
+    assert_eq!(graph.pretty_print().to_string(), expected);
+    graph
+        .add_node_attribute(node1, name.clone(), "node1")
+        .unwrap();
+    graph
+        .add_node_attribute(node1, name.clone(), "overwritten")
+        .unwrap_err();
+
+    graph.set_attribute_storage(AttributeStorage::Nodes);
+    assert_eq!(
+        *graph[node1].attributes.get(&name).unwrap(),
+        Value::from("overwritten")
+    );
+    assert_eq!(
+        graph.node_attributes(node0).get(&name),
+        Some(&Value::from("node0"))
+    );
+}
+
+#[test]
+fn column_storage_reads_attributes_added_to_nodes() {
+    let mut graph = Graph::new();
+    graph.set_attribute_storage(AttributeStorage::Columns);
+    let node = graph.add_graph_node();
+    let name = Identifier::from("name");
+    let kind = Identifier::from("kind");
+    graph[node].attributes.add(name.clone(), "node0").unwrap();
+    graph.add_node_attribute(node, kind.clone(), "def").unwrap();
+    assert_eq!(
+        graph.node_attribute(node, "name"),
+        Some(&Value::from("node0"))
+    );
+    let attributes = graph.node_attributes(node);
+    assert_eq!(attributes.get(&name), Some(&Value::from("node0")));
+    assert_eq!(attributes.get(&kind), Some(&Value::from("def")));
+
+    graph
+        .add_node_attribute(node, name.clone(), "overwritten")
+        .unwrap_err();
+    assert_eq!(
+        *graph[node].attributes.get(&name).unwrap(),
+        Value::from("overwritten")
+    );
+    assert_eq!(
+        graph.pretty_print().to_string(),
+        indoc! {r#"
+          node 0
+            kind: "def"
+            name: "overwritten"
+        "#}
+    );
+
+    graph.set_attribute_storage(AttributeStorage::Nodes);
+    assert_eq!(
+        graph.node_attributes(node).get(&name),
+        Some(&Value::from("overwritten"))
+    );
+    assert_eq!(
+        graph.node_attributes(node).get(&kind),
+        Some(&Value::from("def"))
+    );
+}
+
+#[test]
+fn source_slices_behave_like_strings() {
+    let source: Arc<str> = "def f(): pass".into();
+    let slice = Value::from(SourceSlice::new(source.clone(), 4..5).unwrap());
+    let string = Value::from("f");
+    assert_eq!(slice, string);
+    assert_eq!(slice.as_str().unwrap(), "f");
+    assert_eq!(slice.to_string(), string.to_string());
+    assert_eq!(format!("{:?}", slice), format!("{:?}", string));
+    assert_eq!(
+        serde_json::to_string(&slice).unwrap(),
+        serde_json::to_string(&string).unwrap()
+    );
+    assert!(slice < Value::from("g"));
+    assert!(slice > Value::Integer(1));
+    let set = vec![slice, string].into_iter().collect::<HashSet<_>>();
+    assert_eq!(set.len(), 1);
+    assert!(SourceSlice::new(source, 4..20).is_none());
+}
@@ -0,0 +1,101 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use indoc::indoc;
+use tree_sitter::Parser;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph::Graph;
+use tree_sitter_graph::injections::InjectionError;
+use tree_sitter_graph::injections::Injections;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::Variables;
+
+const INJECTION_QUERY: &str = r#"
+  ((string (string_content) @injection.content)
+   (#set! injection.language "python"))
+"#;
+
+#[test]
+fn can_execute_injected_regions() {
+    let python_source = r#"x = "y = 1""#;
+    let host_file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module) @root {
+            node @root.node
+            attr (@root.node) kind = "host"
+          }
+        "#},
+    )
+    .expect("Cannot parse host file");
+    let injected_file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          global INJECTION
+
+          (assignment left: (identifier) @name) {
+            node @name.def
+            attr (@name.def) name = (source-text @name)
+            edge INJECTION -> @name.def
+          }
+        "#},
+    )
+    .expect("Cannot parse injected file");
+
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let mut injections = Injections::new(&tree_sitter_python::language(), INJECTION_QUERY)
+        .expect("Cannot create injections");
+    injections.add_language("python", tree_sitter_python::language(), &injected_file);
+    let injected = injections
+        .parse(&tree, python_source)
+        .expect("Cannot parse injected regions");
+    assert_eq!(injected.len(), 1);
+    assert_eq!(injected[0].language(), "python");
+    assert_eq!(
+        &python_source[injected[0].range().start_byte..injected[0].range().end_byte],
+        "y = 1"
+    );
+
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals);
+    let mut graph = Graph::new();
+    host_file
+        .execute_into(&mut graph, &tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute host file");
+    injections
+        .execute_into(
+            &mut graph,
+            python_source,
+            &injected,
+            &config,
+            &NoCancellation,
+        )
+        .expect("Cannot execute injected file");
+    let expected = indoc! {r#"
+      node 0
+        kind: "host"
+      node 1
+        language: "python"
+      edge 1 -> 2
+      node 2
+        name: "y"
+    "#};
+    assert_eq!(graph.pretty_print().to_string(), expected);
+}
+
+#[test]
+fn cannot_create_injections_without_content_capture() {
+    let err = Injections::new(&tree_sitter_python::language(), "(string) @string")
+        .err()
+        .expect("Creating injections succeeded unexpectedly");
+    assert!(matches!(err, InjectionError::MissingContentCapture));
+}
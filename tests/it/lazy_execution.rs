@@ -9,6 +9,8 @@ use indoc::indoc;
 use tree_sitter::Parser;
 use tree_sitter_graph::ast::File;
 use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph::AttributeStorage;
+use tree_sitter_graph::graph::Value;
 use tree_sitter_graph::ExecutionConfig;
 use tree_sitter_graph::ExecutionError;
 use tree_sitter_graph::NoCancellation;
@@ -86,6 +88,118 @@ fn can_build_simple_graph() {
     );
 }
 
+#[test]
+fn can_create_labeled_graph_nodes() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            let kind = "definition"
+            node n : kind
+            node m
+            attr (n) name = "n"
+          }
+        "#},
+        indoc! {r#"
+          node 0 : "definition"
+            name: "n"
+          node 1
+        "#},
+    );
+}
+
+#[test]
+fn can_create_kinded_edges() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            node m
+            edge n -"calls"-> m
+            edge m -> n
+            attr (n -> m) weight = 1
+          }
+        "#},
+        indoc! {r#"
+          node 0
+          edge 0 -"calls"-> 1
+            weight: 1
+          node 1
+          edge 1 -> 0
+        "#},
+    );
+}
+
+#[test]
+fn cannot_create_edges_of_different_kinds() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            node m
+            edge n -"calls"-> m
+            edge n -"returns"-> m
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_create_graph_nodes_in_expressions() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            attr (n) scope = (new node), scopes = [(new node), (new node)]
+            edge n -> (new node)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            scope: [graph node 1]
+            scopes: [[graph node 2], [graph node 3]]
+          edge 0 -> 4
+          node 1
+          node 2
+          node 3
+          node 4
+        "#},
+    );
+}
+
+#[test]
+fn can_add_flag_attributes() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node a
+            node b
+            attr (a) is_exported, !is_test
+            edge a -> b
+            attr (a -> b) !precedence, is_call
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            is_exported: #true
+            is_test: #false
+          edge 0 -> 1
+            is_call: #true
+            precedence: #false
+          node 1
+        "#},
+    );
+}
+
 #[test]
 fn can_scan_strings() {
     check_execution(
@@ -132,6 +246,42 @@ fn can_scan_strings() {
     );
 }
 
+#[test]
+fn can_use_named_groups_and_offsets_in_scan_arms() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            scan "alpha/beta.py" {
+               "(?P<dir>[^/]+)/"
+               {
+                 node n
+                 attr (n) name = $dir, start = $start, end = $end
+               }
+
+               "(?P<stem>[^/]+)\\.(?P<ext>py)$"
+               {
+                 node n
+                 attr (n) name = $stem, ext = $ext, start = $start, end = $end
+               }
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            end: 6
+            name: "alpha"
+            start: 0
+          node 1
+            end: 13
+            ext: "py"
+            name: "beta"
+            start: 6
+        "#},
+    );
+}
+
 #[test]
 fn variables_in_scan_arms_are_local() {
     check_execution(
@@ -179,6 +329,63 @@ fn variables_in_scan_arms_are_local() {
     );
 }
 
+#[test]
+fn can_match_strings() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            node n
+            for name in ["alpha", "beta", "test_gamma", "delta"] {
+              match name {
+                "alpha" => {
+                  attr (n) alpha = name
+                },
+                "beta" | "gamma" => {
+                  attr (n) beta = name
+                },
+                /^test_(.*)$/ => {
+                  attr (n) test = $1
+                },
+                _ => {
+                  attr (n) other = name
+                }
+              }
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            alpha: "alpha"
+            beta: "beta"
+            other: "delta"
+            test: "gamma"
+        "#},
+    );
+}
+
+#[test]
+fn can_match_strings_without_matching_arm() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module) @m
+          {
+            node n
+            match (node-type @m) {
+              "class_definition" => {
+                attr (n) class = #true
+              }
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+        "#},
+    );
+}
+
 #[test]
 fn scoped_variables_carry_across_stanzas() {
     check_execution(
@@ -217,6 +424,34 @@ fn scoped_variables_carry_across_stanzas() {
     );
 }
 
+#[test]
+fn can_read_scoped_variables_with_default() {
+    check_execution(
+        indoc! {r#"
+          import a
+          pass
+        "#},
+        indoc! {r#"
+          (import_statement) @stmt
+          {
+            let @stmt.kind = "import"
+          }
+
+          (module (_) @stmt)
+          {
+            node n
+            attr (n) kind = @stmt.kind ?? "other"
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            kind: "import"
+          node 1
+            kind: "other"
+        "#},
+    );
+}
+
 #[test]
 fn can_match_stanza_multiple_times() {
     check_execution(
@@ -306,6 +541,52 @@ fn cannot_omit_global_variable() {
     );
 }
 
+#[test]
+fn can_guard_stanzas_with_global_variables() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          global mode = "tests"
+          global filename
+
+          (module) when (eq mode "tests") {
+            node n
+            attr (n) kind = "tests"
+          }
+
+          (module) when (eq mode "main") {
+            node n
+            attr (n) kind = "main"
+          }
+
+          (module) when (eq filename "test.py") {
+            node n
+            attr (n) kind = "file"
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            kind: "tests"
+          node 1
+            kind: "file"
+    "#},
+    );
+}
+
+#[test]
+fn cannot_guard_stanza_with_non_boolean() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          global mode = "tests"
+
+          (module) when mode {
+            node n
+          }
+        "#},
+    );
+}
+
 #[test]
 fn can_use_variable_multiple_times() {
     check_execution(
@@ -573,6 +854,28 @@ fn can_execute_if_literal() {
     );
 }
 
+#[test]
+fn can_short_circuit_if_conditions() {
+    check_execution(
+        "pass",
+        indoc! {r#"
+          (module (import_statement)? @x (pass_statement)? @y)
+          {
+            node node0
+            if some @x, (eq (source-text @x) "import a") {
+              attr (node0) val = 0
+            } elif some @y, (eq (source-text @y) "pass") {
+              attr (node0) val = 1
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            val: 1
+        "#},
+    );
+}
+
 #[test]
 fn skip_if_without_true_conditions() {
     check_execution(
@@ -666,6 +969,32 @@ fn variables_are_inherited_in_if_body() {
     );
 }
 
+#[test]
+fn can_test_list_captures_for_emptiness() {
+    check_execution(
+        r#"
+          pass
+        "#,
+        indoc! {r#"
+          (module (pass_statement)* @xs (import_statement)* @ys)
+          {
+            node node0
+            if some @xs {
+              attr (node0) xs = "some"
+            }
+            if none @ys {
+              attr (node0) ys = "none"
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            xs: "some"
+            ys: "none"
+        "#},
+    );
+}
+
 #[test]
 fn can_execute_for_in_nonempty_list_capture() {
     check_execution(
@@ -740,6 +1069,85 @@ fn can_execute_for_in_list_literal() {
     );
 }
 
+#[test]
+fn can_destructure_lists() {
+    check_execution(
+        indoc! {r#"
+          pass
+          pass
+          pass
+        "#},
+        indoc! {r#"
+          (module (pass_statement)* @xs)
+          {
+            let [first, ...rest] = @xs
+            let [a, b] = [1, 2]
+            let [...all] = []
+            node node0
+            attr (node0) a = a, b = b, first = first, rest = rest, all = all
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            a: 1
+            all: []
+            b: 2
+            first: [syntax node pass_statement (1, 1)]
+            rest: [[syntax node pass_statement (2, 1)], [syntax node pass_statement (3, 1)]]
+        "#},
+    );
+}
+
+#[test]
+fn cannot_destructure_lists_of_the_wrong_length() {
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module (pass_statement)* @xs)
+          {
+            let [first, second] = @xs
+          }
+        "#},
+    );
+    fail_execution(
+        "pass",
+        indoc! {r#"
+          (module)
+          {
+            let [first] = [1, 2]
+          }
+        "#},
+    );
+}
+
+#[test]
+fn can_iterate_zipped_lists() {
+    check_execution(
+        indoc! {r#"
+          pass
+          pass
+          pass
+        "#},
+        indoc! {r#"
+          (module (pass_statement)* @xs)
+          {
+            for x, i in zip(@xs, [1, 2]) {
+              node n
+              attr (n) pass = x, index = i
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            index: 1
+            pass: [syntax node pass_statement (1, 1)]
+          node 1
+            index: 2
+            pass: [syntax node pass_statement (2, 1)]
+        "#},
+    );
+}
+
 #[test]
 fn variables_are_local_in_for_in_body() {
     check_execution(
@@ -858,6 +1266,32 @@ fn can_execute_set_comprehension() {
     );
 }
 
+#[test]
+fn can_execute_comprehensions_with_conditions() {
+    check_execution(
+        r#"
+          import a
+          pass
+          import b
+        "#,
+        indoc! {r#"
+          (module (_)* @xs)
+          {
+            node node0
+            attr (node0) imports = [
+              (named-child-index x) for x in @xs if (not (eq (node-type x) "pass_statement"))
+            ]
+            attr (node0) kinds = { (node-type x) for x in @xs if (eq (named-child-index x) 1) }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            imports: [0, 2]
+            kinds: {"pass_statement"}
+        "#},
+    );
+}
+
 #[test]
 fn can_execute_scan_of_local_call_expression() {
     check_execution(
@@ -911,6 +1345,43 @@ fn can_execute_scan_of_local_variable() {
     );
 }
 
+#[test]
+fn can_execute_scan_of_list() {
+    check_execution(
+        r#"
+          import a
+          import b_c
+        "#,
+        indoc! {r#"
+          (module (import_statement)* @imports)
+          {
+            scan @imports {
+              "import (.*)" {
+                node n
+                attr (n) name = $1, row = (start-row element)
+              }
+            }
+            scan ["x", "y"] {
+              "y" {
+                node n
+                attr (n) name = element
+              }
+            }
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            name: "a"
+            row: 1
+          node 1
+            name: "b_c"
+            row: 2
+          node 2
+            name: "y"
+        "#},
+    );
+}
+
 #[test]
 fn can_build_node() {
     check_execution(
@@ -1591,3 +2062,160 @@ fn cannot_change_attribute_value() {
         "#},
     );
 }
+
+#[test]
+fn can_append_to_attribute_values() {
+    check_execution(
+        indoc! { r#"
+            pass
+            pass
+        "#},
+        indoc! {r#"
+            (module) @m {
+              node @m.node
+              attr (@m.node) refs += 1
+              node n
+              attr (n) a += 1, a += 2
+              attr (n) a += 3
+            }
+            (module (pass_statement) @_p) @m {
+              attr (@m.node) refs += 1
+            }
+        "#},
+        indoc! {r#"
+          node 0
+            refs: [1, 1, 1]
+          node 1
+            a: [1, 2, 3]
+        "#},
+    );
+}
+
+#[test]
+fn cannot_append_to_non_list_attribute_value() {
+    fail_execution(
+        indoc! { r#"
+            pass
+        "#},
+        indoc! {r#"
+            (module) {
+              node n
+              attr (n) a = 1
+              attr (n) a += 2
+            }
+        "#},
+    );
+}
+
+#[test]
+fn can_add_attributes_to_bound_edges() {
+    check_execution(
+        indoc! { r#"
+            pass
+        "#},
+        indoc! {r#"
+            (module) @m {
+              node n1
+              node n2
+              node n3
+              edge @m.edge = n1 -> n2
+              edge e = n2 -> n3
+              attr (e) local = #true
+            }
+            (module) @m {
+              attr (@m.edge) scoped = #true
+            }
+        "#},
+        indoc! {r#"
+          node 0
+          edge 0 -> 1
+            scoped: #true
+          node 1
+          edge 1 -> 2
+            local: #true
+          node 2
+        "#},
+    );
+}
+
+#[test]
+fn can_store_attributes_in_columns() {
+    init_log();
+    let python_source = "pass\npass\n";
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (pass_statement) @pass
+          {
+            node @pass.node
+            attr (@pass.node) kind = "pass", refs += 1
+            attr (@pass.node) refs += 2
+          }
+          (module (_) @first (_) @second)
+          {
+            attr (@first.node) next = @second.node
+            edge @first.node -> @second.node
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals).lazy(true);
+    let graph = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+    let config = config.attribute_storage(AttributeStorage::Columns);
+    let columns = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+    assert_eq!(columns.attribute_storage(), AttributeStorage::Columns);
+    assert_eq!(
+        columns.pretty_print().to_string(),
+        graph.pretty_print().to_string()
+    );
+    for node in columns.iter_nodes() {
+        assert!(columns[node].attributes.iter().next().is_none());
+        assert_eq!(
+            columns.node_attribute(node, "kind"),
+            Some(&Value::from("pass"))
+        );
+    }
+}
+
+#[test]
+fn can_skip_stanzas_whose_kinds_do_not_occur() {
+    check_execution(
+        indoc! {r#"
+          x = 1
+          pass
+          y = 2
+        "#},
+        indoc! {r#"
+          (class_definition) @c
+          {
+            node @c.node
+            attr (@c.node) kind = "class"
+          }
+          [(while_statement) (pass_statement)] @s
+          {
+            node @s.node
+            attr (@s.node) kind = "statement"
+          }
+          ((identifier) @id (#eq? @id "y"))
+          {
+            node n
+            attr (n) name = (source-text @id)
+          }
+        "#},
+        indoc! {r#"
+          node 0
+            kind: "statement"
+          node 1
+            name: "y"
+        "#},
+    );
+}
@@ -5,10 +5,25 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+#[cfg(feature = "proptest")]
+mod arbitrary;
+#[cfg(feature = "artifact")]
+mod artifact;
+mod bench;
+mod diagnostic;
 mod execution;
+mod explain;
+mod extensions;
+mod formatter;
 mod functions;
 mod graph;
+mod injections;
 mod lazy_execution;
+mod optimizer;
 mod parse_errors;
 mod parser;
+mod rule_set;
+mod sink;
+mod testing;
 mod variables;
+mod visit;
@@ -0,0 +1,140 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use indoc::indoc;
+use tree_sitter::Parser;
+use tree_sitter_graph::ast::Expression;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::ast::IntegerConstant;
+use tree_sitter_graph::ast::ListLiteral;
+use tree_sitter_graph::ast::Statement;
+use tree_sitter_graph::ast::StringConstant;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::Variables;
+
+fn execute_lazily(file: &File, python_source: &str) -> String {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::language())
+        .unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals).lazy(true);
+    let graph = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Could not execute file");
+    let result = graph.pretty_print().to_string();
+    result
+}
+
+fn declared_values(file: &File) -> Vec<&Expression> {
+    file.stanzas[0]
+        .statements
+        .iter()
+        .map(|statement| match statement {
+            Statement::DeclareImmutable(statement) => &statement.value,
+            _ => panic!("Unexpected statement {}", statement),
+        })
+        .collect()
+}
+
+#[test]
+fn can_fold_calls_of_pure_functions() {
+    let mut file = File::from_str(
+        tree_sitter_python::language(),
+        indoc! {r#"
+          (module) @root
+          {
+            let sum = (plus 1 (plus 2 3))
+            let name = (format "{}-{}" "a" (join ["b", "c"] ","))
+            let list = (concat [1] [2])
+            let power = (pow 2 -1)
+            let text = (source-text @root)
+            let graph_node = (node)
+          }
+        "#},
+    )
+    .expect("Cannot parse file");
+    file.optimize(&Functions::stdlib());
+
+    let values = declared_values(&file);
+    assert_eq!(*values[0], IntegerConstant { value: 6 }.into());
+    assert_eq!(
+        *values[1],
+        StringConstant {
+            value: "a-b,c".into()
+        }
+        .into()
+    );
+    assert_eq!(
+        *values[2],
+        ListLiteral {
+            elements: vec![
+                IntegerConstant { value: 1 }.into(),
+                IntegerConstant { value: 2 }.into()
+            ]
+        }
+        .into()
+    );
+    // Calls that fail, that depend on the syntax tree, or that are not pure are kept.
+    assert!(matches!(values[3], Expression::Call(_)));
+    assert!(matches!(values[4], Expression::Call(_)));
+    assert!(matches!(values[5], Expression::Call(_)));
+}
+
+#[test]
+fn can_merge_stanzas_with_identical_patterns() {
+    let source = indoc! {r#"
+      (module)
+      {
+        node n
+        attr (n) name = "first"
+      }
+
+      (class_definition)
+      {
+        node n
+        attr (n) name = "class"
+      }
+
+      (module)
+      {
+        node n
+        attr (n) name = "second"
+      }
+    "#};
+    let expected_graph = indoc! {r#"
+      node 0
+        name: "first"
+      node 1
+        name: "second"
+    "#};
+
+    let mut file = File::from_str(tree_sitter_python::language(), source).unwrap();
+    assert_eq!(file.pattern_stanzas, vec![vec![0], vec![1], vec![2]]);
+    assert_eq!(execute_lazily(&file, "pass"), expected_graph);
+
+    file.optimize(&Functions::stdlib());
+    assert_eq!(file.pattern_stanzas, vec![vec![0, 2], vec![1], vec![]]);
+    assert_eq!(execute_lazily(&file, "pass"), expected_graph);
+
+    file.optimize(&Functions::stdlib());
+    assert_eq!(file.pattern_stanzas, vec![vec![0, 2], vec![1], vec![]]);
+
+    file.disable_stanza(0);
+    assert_eq!(file.pattern_stanzas, vec![vec![2], vec![1], vec![]]);
+    assert_eq!(
+        execute_lazily(&file, "pass"),
+        indoc! {r#"
+          node 0
+            name: "second"
+        "#}
+    );
+}
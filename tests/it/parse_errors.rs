@@ -5,10 +5,13 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::path::Path;
+
 use indoc::indoc;
 use tree_sitter::Parser;
 use tree_sitter::Point;
 use tree_sitter::Tree;
+use tree_sitter_graph::parse_error::Excerpt;
 use tree_sitter_graph::parse_error::ParseError;
 
 fn init_log() {
@@ -92,3 +95,31 @@ fn can_move_tree_with_errors() {
     assert_eq!(positions, vec![Point::new(1, 4), Point::new(3, 4)]);
     let _recovered_tree = moved_parse_errors.into_tree();
 }
+
+#[test]
+fn excerpts_underline_column_range() {
+    let source = "first line\nlet value = 42\n";
+    let excerpt = Excerpt::from_source(Path::new("test.py"), source, 1, 4..9, 0);
+    assert_eq!(
+        excerpt.to_string(),
+        indoc! {r#"
+          test.py:2:5:
+          2 | let value = 42
+            |     ^^^^^
+        "#}
+    );
+}
+
+#[test]
+fn excerpts_underline_at_least_one_column() {
+    let source = "first line\nlet value = 42\n";
+    let excerpt = Excerpt::from_source(Path::new("test.py"), source, 1, 14..usize::MAX, 0);
+    assert_eq!(
+        excerpt.to_string(),
+        indoc! {r#"
+          test.py:2:15:
+          2 | let value = 42
+            |               ^
+        "#}
+    );
+}
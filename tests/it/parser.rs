@@ -5,12 +5,16 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use indoc::indoc;
 use tree_sitter::CaptureQuantifier::*;
 
 use tree_sitter_graph::ast::*;
+use tree_sitter_graph::CheckError;
 use tree_sitter_graph::Identifier;
 use tree_sitter_graph::Location;
 use tree_sitter_graph::ParseError;
+use tree_sitter_graph::ParseLimits;
+use tree_sitter_graph::Range;
 
 #[test]
 fn can_parse_blocks() {
@@ -51,6 +55,7 @@ fn can_parse_blocks() {
                     location: Location { row: 4, column: 15 }
                 }
                 .into(),
+                label: None,
                 location: Location { row: 4, column: 10 }
             }
             .into(),
@@ -67,13 +72,16 @@ fn can_parse_blocks() {
                         .into()
                     ),
                     name: prop1.clone(),
+                    default: None,
                     location: Location { row: 5, column: 21 }
                 }
                 .into(),
+                label: None,
                 location: Location { row: 5, column: 10 },
             }
             .into(),
             CreateEdge {
+                variable: None,
                 source: ScopedVariable {
                     scope: Box::new(
                         Capture {
@@ -86,6 +94,7 @@ fn can_parse_blocks() {
                         .into()
                     ),
                     name: prop1.clone(),
+                    default: None,
                     location: Location { row: 6, column: 21 }
                 }
                 .into(),
@@ -94,6 +103,7 @@ fn can_parse_blocks() {
                     location: Location { row: 6, column: 30 },
                 }
                 .into(),
+                kind: None,
                 location: Location { row: 6, column: 10 },
             }
             .into(),
@@ -110,6 +120,7 @@ fn can_parse_blocks() {
                         .into()
                     ),
                     name: prop1.clone(),
+                    default: None,
                     location: Location { row: 7, column: 22 },
                 }
                 .into(),
@@ -120,7 +131,9 @@ fn can_parse_blocks() {
                 .into(),
                 attributes: vec![Attribute {
                     name: precedence,
-                    value: Expression::TrueLiteral
+                    value: Expression::TrueLiteral,
+                    append: false,
+                    flag: true,
                 }],
                 location: Location { row: 7, column: 10 },
             }
@@ -138,6 +151,7 @@ fn can_parse_blocks() {
                         .into()
                     ),
                     name: prop1.clone(),
+                    default: None,
                     location: Location { row: 8, column: 22 },
                 }
                 .into(),
@@ -145,10 +159,14 @@ fn can_parse_blocks() {
                     Attribute {
                         name: push.clone(),
                         value: String::from("str2").into(),
+                        append: false,
+                        flag: false,
                     },
                     Attribute {
                         name: pop.clone(),
                         value: Expression::TrueLiteral,
+                        append: false,
+                        flag: true,
                     },
                 ],
                 location: Location { row: 8, column: 10 },
@@ -167,6 +185,7 @@ fn can_parse_blocks() {
                         .into()
                     ),
                     name: var1.clone(),
+                    default: None,
                     location: Location { row: 9, column: 20 },
                 }
                 .into(),
@@ -194,6 +213,7 @@ fn can_parse_blocks() {
                         .into()
                     ),
                     name: var1.clone(),
+                    default: None,
                     location: Location {
                         row: 10,
                         column: 20
@@ -276,6 +296,143 @@ fn can_parse_literals() {
     );
 }
 
+#[test]
+fn can_parse_flag_attributes() {
+    let source = r#"
+        (identifier)
+        {
+          node n
+          attr (n) is_exported, !is_test, ! is_module, kind = "x"
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let attributes = match &file.stanzas[0].statements[1] {
+        Statement::AddGraphNodeAttribute(statement) => &statement.attributes,
+        statement => panic!("Expected attr statement, got {}", statement),
+    };
+    assert_eq!(
+        attributes,
+        &vec![
+            Attribute {
+                name: "is_exported".into(),
+                value: Expression::TrueLiteral,
+                append: false,
+                flag: true,
+            },
+            Attribute {
+                name: "is_test".into(),
+                value: Expression::FalseLiteral,
+                append: false,
+                flag: true,
+            },
+            Attribute {
+                name: "is_module".into(),
+                value: Expression::FalseLiteral,
+                append: false,
+                flag: true,
+            },
+            Attribute {
+                name: "kind".into(),
+                value: String::from("x").into(),
+                append: false,
+                flag: false,
+            },
+        ]
+    );
+    assert_eq!(
+        attributes
+            .iter()
+            .map(|attribute| attribute.to_string())
+            .collect::<Vec<_>>(),
+        vec!["is_exported", "!is_test", "!is_module", "kind = \"x\""]
+    );
+}
+
+#[test]
+fn can_parse_labeled_graph_nodes() {
+    let source = r#"
+        (identifier)
+        {
+          node n : "definition"
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let statements = file
+        .stanzas
+        .into_iter()
+        .map(|s| s.statements)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statements,
+        vec![vec![CreateGraphNode {
+            node: UnscopedVariable {
+                name: "n".into(),
+                location: Location { row: 3, column: 15 },
+            }
+            .into(),
+            label: Some(String::from("definition").into()),
+            location: Location { row: 3, column: 10 },
+        }
+        .into()]]
+    );
+}
+
+#[test]
+fn cannot_parse_graph_node_with_scoped_label() {
+    let source = r#"
+        (identifier) @id
+        {
+          node n : @id.kind
+        }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::Check(CheckError::ExpectedLocalValue(_))
+    ));
+}
+
+#[test]
+fn can_parse_edge_kinds() {
+    let source = r#"
+        (identifier)
+        {
+          node a
+          node b
+          edge a -"calls"-> b
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let statements = file
+        .stanzas
+        .into_iter()
+        .map(|s| s.statements)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statements[0][2],
+        CreateEdge {
+            variable: None,
+            source: UnscopedVariable {
+                name: "a".into(),
+                location: Location { row: 5, column: 15 },
+            }
+            .into(),
+            sink: UnscopedVariable {
+                name: "b".into(),
+                location: Location { row: 5, column: 28 },
+            }
+            .into(),
+            kind: Some("calls".into()),
+            location: Location { row: 5, column: 10 },
+        }
+        .into()
+    );
+}
+
 #[test]
 fn can_parse_strings() {
     let source = r#"
@@ -308,6 +465,64 @@ fn can_parse_strings() {
     );
 }
 
+#[test]
+fn can_parse_integer_constants() {
+    let source = r#"
+        (identifier)
+        {
+          let ints = [42, -1, 0xFF, 0o755, 1_000_000, -0x8000_0000]
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let value = match &file.stanzas[0].statements[0] {
+        Statement::DeclareImmutable(statement) => &statement.value,
+        statement => panic!("Expected let statement, got {}", statement),
+    };
+    assert_eq!(
+        value,
+        &ListLiteral {
+            elements: vec![
+                IntegerConstant { value: 42 }.into(),
+                IntegerConstant { value: -1 }.into(),
+                IntegerConstant { value: 255 }.into(),
+                IntegerConstant { value: 493 }.into(),
+                IntegerConstant { value: 1_000_000 }.into(),
+                IntegerConstant {
+                    value: -0x8000_0000
+                }
+                .into(),
+            ],
+        }
+        .into()
+    );
+}
+
+#[test]
+fn cannot_parse_invalid_integer_constants() {
+    for constant in &[
+        "0x",
+        "0o8",
+        "12ab",
+        "-",
+        "0x1_0000_0000_0000_0000",
+        "-_5",
+        "1__",
+        "0x_ff",
+    ] {
+        let source = format!("(identifier) {{ let x = {} }}", constant);
+        let err = File::from_str(tree_sitter_python::language(), &source)
+            .expect_err("Parse succeeded unexpectedly");
+        match err {
+            ParseError::InvalidInteger(text, location) => {
+                assert_eq!(&text, constant);
+                assert_eq!(location, Location { row: 0, column: 23 });
+            }
+            err => panic!("Unexpected error {}", err),
+        }
+    }
+}
+
 #[test]
 fn can_parse_lists() {
     let source = r#"
@@ -480,6 +695,8 @@ fn can_parse_print() {
     assert_eq!(
         statements,
         vec![vec![Print {
+            level: None,
+            channel: None,
             values: vec![
                 StringConstant {
                     value: String::from("x =")
@@ -493,6 +710,47 @@ fn can_parse_print() {
     );
 }
 
+#[test]
+fn can_parse_print_levels_and_channels() {
+    let source = r#"
+        (identifier)
+        {
+          let debug = 1
+          print debug scoping: "x =", 5
+          print warn 6
+          print scoping: 7
+          print debug, 8
+          print debug
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let prints = file.stanzas[0]
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Print(print) => {
+                Some((print.level, print.channel.clone(), print.values.len()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        prints,
+        vec![
+            (
+                Some(PrintLevel::Debug),
+                Some(Identifier::from("scoping")),
+                2
+            ),
+            (Some(PrintLevel::Warn), None, 1),
+            (None, Some(Identifier::from("scoping")), 1),
+            (None, None, 2),
+            (None, None, 1),
+        ]
+    );
+}
+
 #[test]
 fn cannot_parse_nullable_regex() {
     let source = r#"
@@ -530,6 +788,8 @@ fn can_parse_star_capture() {
     assert_eq!(
         statements,
         vec![vec![Print {
+            level: None,
+            channel: None,
             values: vec![Capture {
                 quantifier: ZeroOrMore,
                 name: stmts,
@@ -567,6 +827,8 @@ fn can_parse_star_multiple_capture() {
         statements,
         vec![vec![
             Print {
+                level: None,
+                channel: None,
                 values: vec![Capture {
                     quantifier: ZeroOrMore,
                     name: stmt,
@@ -579,6 +841,8 @@ fn can_parse_star_multiple_capture() {
             }
             .into(),
             Print {
+                level: None,
+                channel: None,
                 values: vec![Capture {
                     quantifier: ZeroOrMore,
                     name: stmts,
@@ -614,6 +878,8 @@ fn can_parse_plus_capture() {
     assert_eq!(
         statements,
         vec![vec![Print {
+            level: None,
+            channel: None,
             values: vec![Capture {
                 quantifier: OneOrMore,
                 name: stmts,
@@ -648,6 +914,8 @@ fn can_parse_optional_capture() {
     assert_eq!(
         statements,
         vec![vec![Print {
+            level: None,
+            channel: None,
             values: vec![Capture {
                 quantifier: ZeroOrOne,
                 name: stmt,
@@ -682,6 +950,8 @@ fn can_parse_parent_optional_capture() {
     assert_eq!(
         statements,
         vec![vec![Print {
+            level: None,
+            channel: None,
             values: vec![Capture {
                 quantifier: ZeroOrOne,
                 name: stmt,
@@ -716,6 +986,8 @@ fn can_parse_alternative_capture() {
     assert_eq!(
         statements,
         vec![vec![Print {
+            level: None,
+            channel: None,
             values: vec![Capture {
                 quantifier: ZeroOrOne,
                 name: stmt,
@@ -750,6 +1022,8 @@ fn can_parse_nested_plus_and_optional_capture() {
     assert_eq!(
         statements,
         vec![vec![Print {
+            level: None,
+            channel: None,
             values: vec![Capture {
                 quantifier: ZeroOrMore,
                 name: stmt,
@@ -799,6 +1073,8 @@ fn can_parse_if() {
                     location: Location { row: 3, column: 13 },
                 }],
                 statements: vec![Print {
+                    level: None,
+                    channel: None,
                     values: vec![StringConstant {
                         value: "x is not null".into()
                     }
@@ -853,6 +1129,8 @@ fn can_parse_if_elif() {
                     }
                     .into(),],
                     statements: vec![Print {
+                        level: None,
+                        channel: None,
                         values: vec![StringConstant {
                             value: "x is null".into()
                         }
@@ -875,6 +1153,8 @@ fn can_parse_if_elif() {
                         location: Location { row: 5, column: 17 },
                     }],
                     statements: vec![Print {
+                        level: None,
+                        channel: None,
                         values: vec![StringConstant {
                             value: "x is not null".into()
                         }
@@ -929,6 +1209,8 @@ fn can_parse_if_else() {
                         location: Location { row: 3, column: 13 },
                     }],
                     statements: vec![Print {
+                        level: None,
+                        channel: None,
                         values: vec![StringConstant {
                             value: "x is null".into()
                         }
@@ -941,6 +1223,8 @@ fn can_parse_if_else() {
                 IfArm {
                     conditions: vec![],
                     statements: vec![Print {
+                        level: None,
+                        channel: None,
                         values: vec![StringConstant {
                             value: "x is not null".into()
                         }
@@ -972,6 +1256,42 @@ fn cannot_parse_if_some_list_capture() {
     }
 }
 
+#[test]
+fn cannot_parse_if_non_boolean_condition() {
+    let source = r#"
+        (module (_)? @x) @root
+        {
+          if some @x, @root {
+            node n
+          }
+        }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::Check(CheckError::ExpectedBooleanValue(Location {
+            row: 3,
+            column: 22
+        }))
+    ));
+}
+
+#[test]
+fn can_parse_if_some_empty_list_capture() {
+    let source = r#"
+        (module (_)* @xs)
+        {
+          if some @xs {
+            node n
+          }
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+    let statement = &file.stanzas[0].statements[0];
+    assert_eq!(statement.to_string(), "if some @xs { ... } at (4, 11)");
+}
+
 #[test]
 fn can_parse_for_in() {
     let source = r#"
@@ -1008,6 +1328,8 @@ fn can_parse_for_in() {
             }
             .into(),
             statements: vec![Print {
+                level: None,
+                channel: None,
                 values: vec![UnscopedVariable {
                     name: x.clone(),
                     location: Location { row: 4, column: 18 },
@@ -1038,50 +1360,67 @@ fn cannot_parse_for_in_optional_capture() {
 }
 
 #[test]
-fn cannot_parse_scan_of_nonlocal_call_expression() {
+fn cannot_parse_assignment_to_loop_variable() {
     let source = r#"
-      (function_definition
-      name: (identifier) @name)
-      {
-        node n
-        scan (source-text @name.val) {
-          "get_.*" {
-            attr (n) is_getter = #true
+        (module (_)* @xs (_)* @ys)
+        {
+          for x in @xs {
+            if #true {
+              for y in @ys {
+                set x = y
+              }
+            }
           }
         }
-      }
     "#;
-    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
-        panic!("Parse succeeded unexpectedly");
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    match err {
+        ParseError::Check(CheckError::CannotAssignLoopVariable(name, location)) => {
+            assert_eq!(name, "x");
+            assert_eq!(location, Location { row: 6, column: 20 });
+        }
+        err => panic!("Unexpected error {}", err),
     }
+
+    let source = r#"
+        (module (_)* @xs (_)* @ys)
+        {
+          for x, y in zip(@xs, @ys) {
+            set y = x
+          }
+        }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::Check(CheckError::CannotAssignLoopVariable(_, _))
+    ));
 }
 
 #[test]
-fn cannot_parse_scan_of_nonlocal_variable() {
+fn can_parse_assignment_to_variable_hiding_loop_variable() {
     let source = r#"
-      (function_definition
-      name: (identifier) @name)
-      {
-        node n
-        let val = (source-text @name.val)
-        scan val {
-          "get_.*" {
-            attr (n) is_getter = #true
+        (module (_)* @xs)
+        {
+          for x in @xs {
+            if #true {
+              var x = 0
+              set x = 1
+            }
           }
         }
-      }
     "#;
-    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
-        panic!("Parse succeeded unexpectedly");
-    }
+    File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
 }
 
 #[test]
-fn can_parse_list_comprehension() {
+fn can_parse_destructure_list() {
     let source = r#"
         (module (_)* @xs)
         {
-          print [ (named-child-index x) for x in @xs ]
+          let [first, ...rest] = @xs
         }
     "#;
     let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
@@ -1093,36 +1432,27 @@ fn can_parse_list_comprehension() {
         .collect::<Vec<_>>();
     assert_eq!(
         statements,
-        vec![vec![Print {
-            values: vec![ListComprehension {
-                element: Box::new(
-                    Call {
-                        function: "named-child-index".into(),
-                        parameters: vec![UnscopedVariable {
-                            name: "x".into(),
-                            location: Location { row: 3, column: 37 }
-                        }
-                        .into()]
-                    }
-                    .into()
-                ),
-                variable: UnscopedVariable {
-                    name: "x".into(),
-                    location: Location { row: 3, column: 44 }
-                },
-                value: Box::new(
-                    Capture {
-                        name: "xs".into(),
-                        quantifier: ZeroOrMore,
-                        file_capture_index: 0,
-                        stanza_capture_index: 0,
-                        location: Location { row: 3, column: 49 }
-                    }
-                    .into()
-                ),
-                location: Location { row: 3, column: 16 }
+        vec![vec![DestructureList {
+            elements: vec![UnscopedVariable {
+                name: Identifier::from("first"),
+                location: Location { row: 3, column: 15 }
             }
             .into()],
+            rest: Some(
+                UnscopedVariable {
+                    name: Identifier::from("rest"),
+                    location: Location { row: 3, column: 25 }
+                }
+                .into()
+            ),
+            value: Capture {
+                quantifier: ZeroOrMore,
+                name: Identifier::from("xs"),
+                file_capture_index: 0,
+                stanza_capture_index: 0,
+                location: Location { row: 3, column: 33 },
+            }
+            .into(),
             location: Location { row: 3, column: 10 }
         }
         .into()]]
@@ -1130,39 +1460,486 @@ fn can_parse_list_comprehension() {
 }
 
 #[test]
-fn can_parse_set_comprehension() {
+fn cannot_parse_destructure_list_of_optional_capture() {
+    let source = r#"
+        (module (_)? @xs)
+        {
+          let [x] = @xs
+        }
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+}
+
+#[test]
+fn cannot_parse_destructure_list_with_misplaced_rest() {
     let source = r#"
         (module (_)* @xs)
         {
-          print { (named-child-index x) for x in @xs }
+          let [...rest, last] = @xs
+        }
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+}
+
+#[test]
+fn can_parse_edge_with_variable() {
+    let source = r#"
+        (module)
+        {
+          node n
+          edge e = n -> n
         }
     "#;
     let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
 
-    let statements = file
-        .stanzas
-        .into_iter()
-        .map(|s| s.statements)
-        .collect::<Vec<_>>();
     assert_eq!(
-        statements,
-        vec![vec![Print {
-            values: vec![SetComprehension {
-                element: Box::new(
-                    Call {
-                        function: "named-child-index".into(),
-                        parameters: vec![UnscopedVariable {
-                            name: "x".into(),
-                            location: Location { row: 3, column: 37 }
-                        }
-                        .into()]
-                    }
-                    .into()
-                ),
-                variable: UnscopedVariable {
-                    name: "x".into(),
-                    location: Location { row: 3, column: 44 }
-                },
+        file.stanzas[0].statements[1],
+        CreateEdge {
+            variable: Some(
+                UnscopedVariable {
+                    name: Identifier::from("e"),
+                    location: Location { row: 4, column: 15 }
+                }
+                .into()
+            ),
+            source: UnscopedVariable {
+                name: Identifier::from("n"),
+                location: Location { row: 4, column: 19 }
+            }
+            .into(),
+            sink: UnscopedVariable {
+                name: Identifier::from("n"),
+                location: Location { row: 4, column: 24 }
+            }
+            .into(),
+            kind: None,
+            location: Location { row: 4, column: 10 }
+        }
+        .into()
+    );
+}
+
+#[test]
+fn cannot_parse_edge_with_non_variable() {
+    let source = r#"
+        (module)
+        {
+          node n
+          edge "e" = n -> n
+        }
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+}
+
+#[test]
+fn can_parse_new_node() {
+    let source = r#"
+        (module)
+        {
+          let n = (new node)
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    assert_eq!(
+        file.stanzas[0].statements[0],
+        DeclareImmutable {
+            variable: UnscopedVariable {
+                name: Identifier::from("n"),
+                location: Location { row: 3, column: 14 }
+            }
+            .into(),
+            value: NewNode {
+                location: Location { row: 3, column: 18 }
+            }
+            .into(),
+            location: Location { row: 3, column: 10 }
+        }
+        .into()
+    );
+}
+
+#[test]
+fn cannot_parse_new_edge() {
+    let source = r#"
+        (module)
+        {
+          let e = (new edge)
+        }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::ExpectedToken("node", Location { row: 3, column: 23 })
+    ));
+}
+
+#[test]
+fn can_parse_for_in_zip() {
+    let source = r#"
+        (module (_)* @xs (_)+ @ys)
+        {
+          for x, y in zip(@xs, @ys) {}
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let statements = file
+        .stanzas
+        .into_iter()
+        .map(|s| s.statements)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statements,
+        vec![vec![ForInZip {
+            variables: vec![
+                UnscopedVariable {
+                    name: Identifier::from("x"),
+                    location: Location { row: 3, column: 14 }
+                },
+                UnscopedVariable {
+                    name: Identifier::from("y"),
+                    location: Location { row: 3, column: 17 }
+                },
+            ],
+            values: vec![
+                Capture {
+                    quantifier: ZeroOrMore,
+                    name: Identifier::from("xs"),
+                    file_capture_index: 0,
+                    stanza_capture_index: 0,
+                    location: Location { row: 3, column: 26 },
+                }
+                .into(),
+                Capture {
+                    quantifier: OneOrMore,
+                    name: Identifier::from("ys"),
+                    file_capture_index: 1,
+                    stanza_capture_index: 1,
+                    location: Location { row: 3, column: 31 },
+                }
+                .into(),
+            ],
+            statements: vec![],
+            location: Location { row: 3, column: 10 }
+        }
+        .into()]]
+    );
+}
+
+#[test]
+fn cannot_parse_for_in_zip_of_optional_capture() {
+    let source = r#"
+        (module (_)* @xs (_)? @y)
+        {
+          for x, y in zip(@xs, @y) {}
+        }
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+}
+
+#[test]
+fn cannot_parse_for_in_zip_with_mismatched_variables() {
+    let source = r#"
+        (module (_)* @xs (_)* @ys)
+        {
+          for x in zip(@xs, @ys) {}
+        }
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+    let source = r#"
+        (module (_)* @xs)
+        {
+          for x, y in @xs {}
+        }
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+}
+
+#[test]
+fn can_parse_match() {
+    let source = r#"
+        (module) @root
+        {
+          match (node-type @root) {
+            "module" | /^mod\/(.*)/ => {}
+            _ => {}
+          }
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let statements = file
+        .stanzas
+        .into_iter()
+        .map(|s| s.statements)
+        .collect::<Vec<_>>();
+    let arms = match &statements[0][0] {
+        Statement::Match(statement) => &statement.arms,
+        statement => panic!("Expected match statement, got {}", statement),
+    };
+    assert_eq!(arms.len(), 2);
+    assert_eq!(arms[0].patterns.len(), 2);
+    assert_eq!(
+        arms[0].patterns[0],
+        MatchPattern::String("module".to_string())
+    );
+    assert_eq!(arms[0].patterns[1].to_string(), r#"/^mod\/(.*)/"#);
+    assert_eq!(arms[0].location, Location { row: 4, column: 12 });
+    assert!(arms[1].patterns.is_empty());
+}
+
+#[test]
+fn cannot_parse_match_arm_after_wildcard() {
+    let source = r#"
+        (module) @root
+        {
+          match (node-type @root) {
+            _ => {}
+            "module" => {}
+          }
+        }
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+}
+
+#[test]
+fn cannot_parse_scan_of_nonlocal_call_expression() {
+    let source = r#"
+      (function_definition
+      name: (identifier) @name)
+      {
+        node n
+        scan (source-text @name.val) {
+          "get_.*" {
+            attr (n) is_getter = #true
+          }
+        }
+      }
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+}
+
+#[test]
+fn cannot_parse_scan_of_nonlocal_variable() {
+    let source = r#"
+      (function_definition
+      name: (identifier) @name)
+      {
+        node n
+        let val = (source-text @name.val)
+        scan val {
+          "get_.*" {
+            attr (n) is_getter = #true
+          }
+        }
+      }
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+}
+
+#[test]
+fn cannot_parse_scan_of_list_with_element_variable() {
+    let source = r#"
+      (module (_)* @xs)
+      {
+        scan @xs {
+          "pass" {
+            let element = "pass"
+          }
+        }
+      }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::Check(CheckError::Variable(_, _, _))
+    ));
+}
+
+#[test]
+fn can_parse_regex_captures() {
+    let source = r#"
+      (module) @root
+      {
+        scan (source-text @root) {
+          "(?P<word>\\w+)" {
+            print $0, $word, $start, $end
+          }
+        }
+      }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let arms = match &file.stanzas[0].statements[0] {
+        Statement::Scan(statement) => &statement.arms,
+        statement => panic!("Expected scan statement, got {}", statement),
+    };
+    let values = match &arms[0].statements[0] {
+        Statement::Print(statement) => &statement.values,
+        statement => panic!("Expected print statement, got {}", statement),
+    };
+    assert_eq!(
+        values,
+        &vec![
+            RegexCapture {
+                group: RegexGroup::Index(0)
+            }
+            .into(),
+            RegexCapture {
+                group: RegexGroup::Name("word".into())
+            }
+            .into(),
+            RegexCapture {
+                group: RegexGroup::Start
+            }
+            .into(),
+            RegexCapture {
+                group: RegexGroup::End
+            }
+            .into(),
+        ]
+    );
+}
+
+#[test]
+fn cannot_parse_undefined_regex_captures() {
+    let source = r#"
+      (module) @root
+      {
+        scan (source-text @root) {
+          "(a)" {
+            print $2
+          }
+        }
+      }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    match err {
+        ParseError::Check(CheckError::UndefinedRegexCapture(capture, location)) => {
+            assert_eq!(capture, "$2");
+            assert_eq!(location, Location { row: 3, column: 8 });
+        }
+        err => panic!("Unexpected error {}", err),
+    }
+
+    let source = r#"
+      (module) @root
+      {
+        scan (source-text @root) {
+          "(?P<word>\\w+)" {
+            print $name
+          }
+        }
+      }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::Check(CheckError::UndefinedRegexCapture(_, _))
+    ));
+}
+
+#[test]
+fn can_parse_regex_captures_of_match_arms() {
+    let source = r#"
+      (module) @root
+      {
+        scan (source-text @root) {
+          "(?P<word>\\w+)" {
+            match (source-text @root) {
+              /(a)(b)/ | /(c)(d)/ => {
+                print $2
+              }
+              "x" | "y" => {
+                print $word
+              }
+              _ => {
+                print $word
+              }
+            }
+          }
+        }
+      }
+    "#;
+    File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let source = r#"
+      (module) @root
+      {
+        match (source-text @root) {
+          /(a)(b)/ | /(c)/ => {
+            print $2
+          }
+        }
+      }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::Check(CheckError::UndefinedRegexCapture(_, _))
+    ));
+}
+
+#[test]
+fn can_parse_list_comprehension() {
+    let source = r#"
+        (module (_)* @xs)
+        {
+          print [ (named-child-index x) for x in @xs ]
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let statements = file
+        .stanzas
+        .into_iter()
+        .map(|s| s.statements)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statements,
+        vec![vec![Print {
+            level: None,
+            channel: None,
+            values: vec![ListComprehension {
+                element: Box::new(
+                    Call {
+                        function: "named-child-index".into(),
+                        parameters: vec![UnscopedVariable {
+                            name: "x".into(),
+                            location: Location { row: 3, column: 37 }
+                        }
+                        .into()]
+                    }
+                    .into()
+                ),
+                variable: UnscopedVariable {
+                    name: "x".into(),
+                    location: Location { row: 3, column: 44 }
+                },
                 value: Box::new(
                     Capture {
                         name: "xs".into(),
@@ -1173,6 +1950,7 @@ fn can_parse_set_comprehension() {
                     }
                     .into()
                 ),
+                condition: None,
                 location: Location { row: 3, column: 16 }
             }
             .into()],
@@ -1182,6 +1960,97 @@ fn can_parse_set_comprehension() {
     );
 }
 
+#[test]
+fn can_parse_set_comprehension() {
+    let source = r#"
+        (module (_)* @xs)
+        {
+          print { (named-child-index x) for x in @xs }
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+
+    let statements = file
+        .stanzas
+        .into_iter()
+        .map(|s| s.statements)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statements,
+        vec![vec![Print {
+            level: None,
+            channel: None,
+            values: vec![SetComprehension {
+                element: Box::new(
+                    Call {
+                        function: "named-child-index".into(),
+                        parameters: vec![UnscopedVariable {
+                            name: "x".into(),
+                            location: Location { row: 3, column: 37 }
+                        }
+                        .into()]
+                    }
+                    .into()
+                ),
+                variable: UnscopedVariable {
+                    name: "x".into(),
+                    location: Location { row: 3, column: 44 }
+                },
+                value: Box::new(
+                    Capture {
+                        name: "xs".into(),
+                        quantifier: ZeroOrMore,
+                        file_capture_index: 0,
+                        stanza_capture_index: 0,
+                        location: Location { row: 3, column: 49 }
+                    }
+                    .into()
+                ),
+                condition: None,
+                location: Location { row: 3, column: 16 }
+            }
+            .into()],
+            location: Location { row: 3, column: 10 }
+        }
+        .into()]]
+    );
+}
+
+#[test]
+fn can_parse_comprehension_with_condition() {
+    let source = r#"
+        (module (_)* @xs)
+        {
+          print [ (named-child-index x) for x in @xs if (not (eq (node-type x) "pass_statement")) ]
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+    let statement = &file.stanzas[0].statements[0];
+    assert_eq!(
+        statement.to_string(),
+        "print [ (named-child-index x) for x in @xs if (not (eq (node-type x) \"pass_statement\")) ], at (4, 11)"
+    );
+}
+
+#[test]
+fn cannot_parse_comprehension_with_non_boolean_condition() {
+    let source = r#"
+        (module (_)* @xs)
+        {
+          print { (node-type x) for x in @xs if "pass_statement" }
+        }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::Check(CheckError::ExpectedBooleanValue(Location {
+            row: 3,
+            column: 16
+        }))
+    ));
+}
+
 #[test]
 fn can_parse_global() {
     let source = r#"
@@ -1218,10 +2087,12 @@ fn can_parse_global() {
                     location: Location { row: 4, column: 15 },
                 }
                 .into(),
+                label: None,
                 location: Location { row: 4, column: 10 },
             }
             .into(),
             CreateEdge {
+                variable: None,
                 source: UnscopedVariable {
                     name: "n".into(),
                     location: Location { row: 5, column: 15 },
@@ -1232,6 +2103,7 @@ fn can_parse_global() {
                     location: Location { row: 5, column: 20 },
                 }
                 .into(),
+                kind: None,
                 location: Location { row: 5, column: 10 },
             }
             .into(),
@@ -1268,6 +2140,8 @@ fn can_parse_global_with_default() {
     assert_eq!(
         statements,
         vec![vec![Print {
+            level: None,
+            channel: None,
             values: vec![UnscopedVariable {
                 name: "PKG_NAME".into(),
                 location: Location { row: 4, column: 16 }
@@ -1341,10 +2215,12 @@ fn can_parse_list_global() {
                         location: Location { row: 5, column: 17 },
                     }
                     .into(),
+                    label: None,
                     location: Location { row: 5, column: 12 },
                 }
                 .into(),
                 CreateEdge {
+                    variable: None,
                     source: UnscopedVariable {
                         name: "n".into(),
                         location: Location { row: 6, column: 17 },
@@ -1355,6 +2231,7 @@ fn can_parse_list_global() {
                         location: Location { row: 6, column: 22 },
                     }
                     .into(),
+                    kind: None,
                     location: Location { row: 6, column: 12 },
                 }
                 .into(),
@@ -1413,10 +2290,12 @@ fn can_parse_optional_global() {
                             location: Location { row: 5, column: 17 },
                         }
                         .into(),
+                        label: None,
                         location: Location { row: 5, column: 12 },
                     }
                     .into(),
                     CreateEdge {
+                        variable: None,
                         source: UnscopedVariable {
                             name: "n".into(),
                             location: Location { row: 6, column: 17 },
@@ -1427,6 +2306,7 @@ fn can_parse_optional_global() {
                             location: Location { row: 6, column: 22 },
                         }
                         .into(),
+                        kind: None,
                         location: Location { row: 6, column: 12 },
                     }
                     .into(),
@@ -1481,6 +2361,39 @@ fn cannot_parse_set_global() {
     }
 }
 
+#[test]
+fn can_parse_scoped_variable_with_default() {
+    let source = r#"
+        (module) @root
+        {
+          node n
+          attr (n) kind = @root.kind ?? "module"
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+    let statement = &file.stanzas[0].statements[1];
+    assert_eq!(
+        statement.to_string(),
+        "attr (n) kind = @root.kind ?? \"module\" at (5, 11)"
+    );
+}
+
+#[test]
+fn cannot_parse_set_scoped_variable_with_default() {
+    let source = r#"
+        (module) @root
+        {
+          let @root.kind ?? "module" = "module"
+        }
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::Check(CheckError::CannotAssignVariableWithDefault(_, _))
+    ));
+}
+
 #[test]
 fn can_parse_shorthand() {
     let source = r#"
@@ -1508,7 +2421,9 @@ fn can_parse_shorthand() {
                         name: "x".into(),
                         location: Location { row: 1, column: 43 }
                     }
-                    .into()
+                    .into(),
+                    append: false,
+                    flag: false,
                 },
                 Attribute {
                     name: "symbol".into(),
@@ -1521,6 +2436,8 @@ fn can_parse_shorthand() {
                         .into()]
                     }
                     .into(),
+                    append: false,
+                    flag: false,
                 }
             ],
             location: Location { row: 1, column: 18 }
@@ -1580,6 +2497,180 @@ fn multiline_query_parse_errors_have_file_location() {
     assert_eq!(err.offset, 112, "expected offset 112, got {}", err.offset);
 }
 
+#[test]
+fn parse_errors_report_their_location() {
+    let source = r#"
+        ; skip the first line
+        (module (non_existing_node))
+        {}
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert_eq!(err.location(), Location { row: 2, column: 17 });
+}
+
+#[test]
+fn cannot_parse_deeply_nested_files() {
+    let source = format!(
+        "(module) @_m {{\n  let x = {}y{}\n}}\n",
+        "(f ".repeat(10000),
+        ")".repeat(10000)
+    );
+    let err = File::from_str(tree_sitter_python::language(), &source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(err, ParseError::NestingTooDeep(64, _)));
+
+    let source = r#"
+        (module) @_m {
+          let x = (f (g 1))
+        }
+    "#;
+    let limits = ParseLimits {
+        max_nesting_depth: Some(3),
+    };
+    let err = File::from_str_with_limits(tree_sitter_python::language(), source, &limits)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(err, ParseError::NestingTooDeep(3, _)));
+    assert_eq!(err.location(), Location { row: 2, column: 24 });
+    let limits = ParseLimits {
+        max_nesting_depth: Some(4),
+    };
+    File::from_str_with_limits(tree_sitter_python::language(), source, &limits)
+        .expect("parse to succeed");
+}
+
+#[test]
+fn can_recover_from_syntax_errors() {
+    let source = r#"
+        global root = 1
+        (module) @_m {
+          node n
+          let = 3
+          attr (n) name = "m"
+        }
+        (pass_statement @_p {
+          nope
+        }
+        (identifier) @id {
+        }
+    "#;
+    let (file, errors) = File::parse_with_recovery(tree_sitter_python::language(), source);
+    let rows = errors
+        .iter()
+        .map(|err| err.location().row)
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![1, 4, 7, 8]);
+    assert!(matches!(errors[0], ParseError::ExpectedToken("\"", _)));
+    assert!(matches!(errors[2], ParseError::QueryError(_)));
+    assert!(matches!(errors[3], ParseError::UnexpectedKeyword(_, _)));
+    assert_eq!(file.stanzas.len(), 2);
+    assert_eq!(file.stanzas[0].statements.len(), 2);
+    assert_eq!(file.query.as_ref().unwrap().pattern_count(), 2);
+
+    let errors = File::check_all(tree_sitter_python::language(), source)
+        .expect_err("Check succeeded unexpectedly");
+    let rows = errors
+        .iter()
+        .map(|err| err.location().row)
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![1, 4, 7, 8, 10]);
+    assert!(matches!(
+        errors[4],
+        ParseError::Check(CheckError::UnusedCaptures(_, _))
+    ));
+
+    let source = r#"
+        (module) @_m {
+          node n
+        }
+    "#;
+    File::check_all(tree_sitter_python::language(), source).expect("check to succeed");
+}
+
+#[test]
+fn can_attach_comments() {
+    let source = indoc! {r#"
+        ; the global
+        global root ; trailing
+        (module) @_m {
+          ; leading
+          node n ; trailing
+          if #true {
+            ; inner
+          }
+          ; last
+        }
+    "#};
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+    let texts = |comments: &[Comment]| {
+        comments
+            .iter()
+            .map(|comment| comment.text.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let global = Location { row: 1, column: 7 };
+    assert_eq!(texts(file.comments.leading(global)), vec!["; the global"]);
+    assert_eq!(texts(file.comments.trailing(global)), vec!["; trailing"]);
+
+    let statements = &file.stanzas[0].statements;
+    let node = statements[0].location();
+    assert_eq!(texts(file.comments.leading(node)), vec!["; leading"]);
+    assert_eq!(texts(file.comments.trailing(node)), vec!["; trailing"]);
+    let trivia = file.comments.get(statements[1].location()).unwrap();
+    assert!(trivia.leading.is_empty());
+    assert_eq!(texts(&trivia.inner), vec!["; inner"]);
+    assert_eq!(texts(&trivia.trailing), vec!["; last"]);
+
+    assert!(file.comments.get(file.stanzas[0].range.start).is_none());
+    assert!(file.comments.dangling().is_empty());
+}
+
+#[test]
+fn can_record_spans() {
+    let source = indoc! {r#"
+        (module) @m {
+          let x = (plus 1 @m.value)
+          if #true {
+            node n
+          }
+        }
+    "#};
+    let file = File::from_str(tree_sitter_python::language(), source).expect("Cannot parse file");
+    let text = |range: Range| {
+        let lines = source.lines().collect::<Vec<_>>();
+        if range.start.row == range.end.row {
+            lines[range.start.row][range.start.column..range.end.column].to_string()
+        } else {
+            let mut text = lines[range.start.row][range.start.column..].to_string();
+            for line in &lines[range.start.row + 1..range.end.row] {
+                text += &format!("\n{}", line);
+            }
+            text + "\n" + &lines[range.end.row][..range.end.column]
+        }
+    };
+    let span = |row, column| {
+        text(
+            file.spans
+                .get(Location { row, column })
+                .expect("missing span"),
+        )
+    };
+
+    assert_eq!(span(0, 0), source.trim_end());
+    assert_eq!(span(1, 2), "let x = (plus 1 @m.value)");
+    assert_eq!(span(1, 10), "(plus 1 @m.value)");
+    assert_eq!(span(1, 18), "@m");
+    assert_eq!(span(1, 21), "value");
+    assert_eq!(span(2, 2), "if #true {\n    node n\n  }");
+    assert_eq!(file.spans.get(Location { row: 1, column: 3 }), None);
+
+    let innermost = file.spans.innermost(Location { row: 1, column: 16 });
+    assert_eq!(innermost.map(text), Some("1".to_string()));
+    let innermost = file.spans.innermost(Location { row: 1, column: 11 });
+    assert_eq!(innermost.map(text), Some("(plus 1 @m.value)".to_string()));
+}
+
 #[test]
 fn cannot_parse_unused_capture() {
     let source = r#"
@@ -1608,3 +2699,173 @@ fn can_parse_inherit_directives() {
     let file = File::from_str(tree_sitter_python::language(), source).expect("parse to succeed");
     assert!(file.inherited_variables.contains("scope".into()));
 }
+
+#[test]
+fn can_parse_uses_declarations() {
+    let source = r#"
+        uses "string", "math"
+        uses "json"
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("parse to succeed");
+    let namespaces = file
+        .uses
+        .iter()
+        .map(|uses| uses.namespaces.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        namespaces,
+        vec![
+            vec!["string".to_string(), "math".to_string()],
+            vec!["json".to_string()],
+        ]
+    );
+}
+
+#[test]
+fn cannot_parse_empty_uses_declaration() {
+    let source = r#"
+        uses
+        (module) {}
+    "#;
+    if let Ok(_) = File::from_str(tree_sitter_python::language(), source) {
+        panic!("Parse succeeded unexpectedly");
+    }
+}
+
+#[test]
+fn can_parse_named_stanzas() {
+    let source = r#"
+        stanza modules
+        (module) {}
+
+        (pass_statement) {}
+
+        stanza functions (function_definition) {}
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("parse to succeed");
+    let names = file
+        .stanzas
+        .iter()
+        .map(|stanza| stanza.name.as_ref().map(|name| name.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            Some("modules".to_string()),
+            None,
+            Some("functions".to_string())
+        ]
+    );
+    assert_eq!(file.stanzas[0].range.start, Location { row: 2, column: 8 });
+    assert_eq!(file.stanza_index("functions"), Some(2));
+    assert_eq!(file.stanza_index("classes"), None);
+}
+
+#[test]
+fn cannot_parse_duplicate_stanza_names() {
+    let source = r#"
+        stanza modules
+        (module) {}
+
+        stanza modules
+        (module) {}
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert_eq!(err.location(), Location { row: 5, column: 8 });
+}
+
+#[test]
+fn can_parse_stanza_guards() {
+    let source = r#"
+        global INCLUDE_TESTS
+
+        stanza tests
+        (module) when INCLUDE_TESTS {}
+
+        (pass_statement) @_when when (not INCLUDE_TESTS) {}
+
+        (function_definition) {}
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("parse to succeed");
+    let guards = file
+        .stanzas
+        .iter()
+        .map(|stanza| stanza.guard.as_ref().map(|guard| guard.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        guards,
+        vec![
+            Some("INCLUDE_TESTS".to_string()),
+            Some("(not INCLUDE_TESTS)".to_string()),
+            None
+        ]
+    );
+    assert_eq!(file.stanza_index("tests"), Some(0));
+}
+
+#[test]
+fn cannot_parse_stanza_guard_with_capture() {
+    let source = r#"
+        (module) @m when (eq (source-text @m) "") {}
+    "#;
+    let err = File::from_str(tree_sitter_python::language(), source)
+        .expect_err("Parse succeeded unexpectedly");
+    assert!(matches!(
+        err,
+        ParseError::Check(CheckError::InvalidStanzaGuard(_, _))
+    ));
+    assert_eq!(err.location(), Location { row: 1, column: 8 });
+}
+
+#[test]
+fn can_get_combined_query_source() {
+    let source = r#"
+        (module) {}
+        stanza passes
+        (pass_statement) {}
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("parse to succeed");
+    assert_eq!(
+        file.query_source,
+        "(module) @__tsg__full_match\n(pass_statement) @__tsg__full_match\n"
+    );
+}
+
+#[test]
+fn can_serialize_and_deserialize_files() {
+    let source = r#"
+        global filename
+        uses "string"
+        attribute def = x => type = "def", name = x
+
+        stanza modules
+        (module) @mod {
+          node @mod.node
+          attr (@mod.node) def = filename
+        }
+
+        (function_definition name: (identifier) @name) {
+          scan (source-text @name) {
+            "^_" { print "private" }
+          }
+        }
+    "#;
+    let file = File::from_str(tree_sitter_python::language(), source).expect("parse to succeed");
+    let json = serde_json::to_string(&file).expect("serialize to succeed");
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    let deserialized = File::deserialize_with(tree_sitter_python::language(), &mut deserializer)
+        .expect("deserialize to succeed");
+
+    assert_eq!(deserialized.globals, file.globals);
+    assert_eq!(deserialized.inherited_variables, file.inherited_variables);
+    assert_eq!(deserialized.uses, file.uses);
+    assert_eq!(deserialized.shorthands, file.shorthands);
+    assert_eq!(deserialized.query_source, file.query_source);
+    assert_eq!(deserialized.stanza_index("modules"), Some(0));
+    for (stanza, expected) in deserialized.stanzas.iter().zip(&file.stanzas) {
+        assert_eq!(stanza.statements, expected.statements);
+        assert_eq!(stanza.range, expected.range);
+        assert_eq!(stanza.query.capture_names(), expected.query.capture_names());
+    }
+}
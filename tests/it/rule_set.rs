@@ -0,0 +1,88 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use tree_sitter_graph::ParseError;
+use tree_sitter_graph::RuleSet;
+use tree_sitter_graph::RuleSetError;
+
+/// Returns an empty directory for the files of a test.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tsg-rule-set-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes a file with a modification time that is later than any earlier write, even on file
+/// systems with coarse timestamps.
+fn write(path: &Path, content: &str, generation: u64) {
+    std::fs::write(path, content).unwrap();
+    let file = std::fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(generation))
+        .unwrap();
+}
+
+#[test]
+fn can_reload_rule_sets_when_files_change() {
+    let dir = test_dir("reload");
+    let path = dir.join("rules.tsg");
+    write(&path, "(module) @_m {}\n", 0);
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let reported = errors.clone();
+    let rules = RuleSet::load(tree_sitter_python::language(), vec![&path])
+        .expect("Cannot load rule set")
+        .on_error(move |error| reported.lock().unwrap().push(error.to_string()));
+    let first = rules.file();
+    assert_eq!(first.stanzas.len(), 1);
+    assert!(!rules.reload_if_changed());
+
+    write(&path, "(module) @_m {\n  let\n}\n", 1);
+    assert!(!rules.reload_if_changed());
+    assert_eq!(errors.lock().unwrap().len(), 1);
+    assert_eq!(rules.file().stanzas.len(), 1);
+    assert!(!rules.reload_if_changed());
+    assert_eq!(errors.lock().unwrap().len(), 1);
+
+    write(&path, "(module) @_m {}\n(pass_statement) @_p {}\n", 2);
+    assert!(rules.reload_if_changed());
+    assert_eq!(rules.file().stanzas.len(), 2);
+    assert_eq!(first.stanzas.len(), 1);
+}
+
+#[test]
+fn rule_set_errors_are_reported_against_the_file_that_contains_them() {
+    let dir = test_dir("errors");
+    let globals = dir.join("globals.tsg");
+    let stanzas = dir.join("stanzas.tsg");
+    write(&globals, "global FILENAME\n", 0);
+    write(&stanzas, "(module) @_m {\n  let\n}\n", 0);
+    let error = RuleSet::load(tree_sitter_python::language(), vec![&globals, &stanzas])
+        .map(|_| ())
+        .expect_err("Load succeeded unexpectedly");
+    assert!(matches!(error, RuleSetError::Parse(ref path, _) if *path == stanzas));
+
+    write(&stanzas, "global FILENAME\n(module) @_m {}\n", 1);
+    let error = RuleSet::load(tree_sitter_python::language(), vec![&globals, &stanzas])
+        .map(|_| ())
+        .expect_err("Load succeeded unexpectedly");
+    assert!(
+        matches!(error, RuleSetError::Parse(ref path, ParseError::Check(_)) if *path != stanzas)
+    );
+
+    std::fs::remove_file(&globals).unwrap();
+    let error = RuleSet::load(tree_sitter_python::language(), vec![&globals, &stanzas])
+        .map(|_| ())
+        .expect_err("Load succeeded unexpectedly");
+    assert!(matches!(error, RuleSetError::Read(ref path, _) if *path == globals));
+}
@@ -0,0 +1,112 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::cell::Cell;
+
+use indoc::indoc;
+use tree_sitter::Parser;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph::Graph;
+use tree_sitter_graph::graph::GraphNodeRef;
+use tree_sitter_graph::graph::Value;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::GraphSink;
+use tree_sitter_graph::Identifier;
+use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::SinkAction;
+use tree_sitter_graph::Variables;
+
+/// Rejects the second graph node and `source` attributes, and upper-cases `name` attributes.
+#[derive(Default)]
+struct TestSink {
+    nodes: Cell<usize>,
+    edges: Cell<usize>,
+}
+
+impl GraphSink for TestSink {
+    fn node(&self, _graph: &Graph, node: GraphNodeRef) -> SinkAction {
+        self.nodes.set(self.nodes.get() + 1);
+        if node.index() == 1 {
+            SinkAction::Reject
+        } else {
+            SinkAction::Accept
+        }
+    }
+
+    fn edge(&self, _graph: &Graph, _source: GraphNodeRef, _sink: GraphNodeRef) -> SinkAction {
+        self.edges.set(self.edges.get() + 1);
+        SinkAction::Accept
+    }
+
+    fn node_attribute(
+        &self,
+        _graph: &Graph,
+        _node: GraphNodeRef,
+        name: &Identifier,
+        value: &mut Value,
+    ) -> SinkAction {
+        if name.as_str() == "source" {
+            return SinkAction::Reject;
+        }
+        if name.as_str() == "name" {
+            *value = value.as_str().unwrap().to_uppercase().into();
+        }
+        SinkAction::Accept
+    }
+}
+
+fn execute(python_source: &str, dsl_source: &str, lazy: bool, sink: &TestSink) -> String {
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_python::language()).unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file =
+        File::from_str(tree_sitter_python::language(), dsl_source).expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals)
+        .lazy(lazy)
+        .sink(sink);
+    let graph = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+    format!("{}", graph.pretty_print())
+}
+
+#[test]
+fn can_reject_and_transform_graph_elements() {
+    let dsl_source = indoc! {r#"
+      (module) @root
+      {
+        node node0
+        attr (node0) name = "node0", source = @root
+        node node1
+        attr (node1) name = "node1"
+        edge node0 -> node1
+        attr (node0 -> node1) precedence = 14
+        node node2
+        attr (node2) name = "node2", parent = node1
+        edge node2 -> node0
+      }
+    "#};
+    let expected = indoc! {r#"
+      node 0
+        name: "NODE0"
+      node 1
+      node 2
+        name: "NODE2"
+        parent: [graph node 1]
+      edge 2 -> 0
+    "#};
+    for lazy in [false, true] {
+        let sink = TestSink::default();
+        let graph = execute("pass", dsl_source, lazy, &sink);
+        assert_eq!(graph, expected);
+        assert_eq!(sink.nodes.get(), 3);
+        assert_eq!(sink.edges.get(), 1);
+    }
+}
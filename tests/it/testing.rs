@@ -0,0 +1,141 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::path::PathBuf;
+
+use indoc::indoc;
+use tree_sitter::Parser;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::testing::normalize;
+use tree_sitter_graph::testing::snapshot_path;
+use tree_sitter_graph::testing::SnapshotError;
+use tree_sitter_graph::testing::SnapshotTester;
+use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::NoCancellation;
+use tree_sitter_graph::Variables;
+
+fn normalized(python_source: &str, dsl_source: &str) -> String {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_python::language())
+        .unwrap();
+    let tree = parser.parse(python_source, None).unwrap();
+    let file =
+        File::from_str(tree_sitter_python::language(), dsl_source).expect("Cannot parse file");
+    let functions = Functions::stdlib();
+    let globals = Variables::new();
+    let config = ExecutionConfig::new(&functions, &globals);
+    let graph = file
+        .execute(&tree, python_source, &config, &NoCancellation)
+        .expect("Cannot execute file");
+    normalize(&graph)
+}
+
+/// Returns an empty directory for the files of a test.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tsg-testing-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn normalized_graphs_do_not_depend_on_creation_order() {
+    let python_source = "a = b\n";
+    let expected = indoc! {r#"
+      node 0
+        name: "left"
+      edge 0 -> 1
+        precedence: 1
+      node 1
+        name: "right"
+        other: [graph node 0]
+    "#};
+    assert_eq!(
+        normalized(
+            python_source,
+            indoc! {r#"
+              (module) @_m
+              {
+                node left
+                node right
+                attr (left) name = "left"
+                attr (right) name = "right", other = left
+                edge left -> right
+                attr (left -> right) precedence = 1
+              }
+            "#},
+        ),
+        expected
+    );
+    assert_eq!(
+        normalized(
+            python_source,
+            indoc! {r#"
+              (module) @_m
+              {
+                node right
+                node left
+                attr (left) name = "left"
+                attr (right) name = "right", other = left
+                edge left -> right
+                attr (left -> right) precedence = 1
+              }
+            "#},
+        ),
+        expected
+    );
+}
+
+#[test]
+fn can_check_snapshots() {
+    let dir = test_dir("check");
+    let tsg_path = dir.join("rules.tsg");
+    std::fs::write(
+        &tsg_path,
+        indoc! {r#"
+          (identifier) @id
+          {
+            node n
+            attr (n) name = (source-text @id)
+          }
+        "#},
+    )
+    .unwrap();
+    let fixtures = dir.join("fixtures");
+    std::fs::create_dir(&fixtures).unwrap();
+    let fixture = fixtures.join("fixture.py");
+    std::fs::write(&fixture, "a = b\n").unwrap();
+    let tester = SnapshotTester::load(tree_sitter_python::language(), &tsg_path);
+
+    match tester.check_snapshot(&fixture) {
+        Err(SnapshotError::Missing(path)) => assert_eq!(path, snapshot_path(&fixture)),
+        result => panic!("Expected a missing snapshot, got {:?}", result),
+    }
+
+    std::fs::write(
+        snapshot_path(&fixture),
+        "node 0\n  name: \"a\"\nnode 1\n  name: \"c\"\n",
+    )
+    .unwrap();
+    match tester.check_snapshot(&fixture) {
+        Err(SnapshotError::Mismatch(_, diff)) => {
+            assert_eq!(diff, "    4 -   name: \"c\"\n    4 +   name: \"b\"\n")
+        }
+        result => panic!("Expected a mismatched snapshot, got {:?}", result),
+    }
+
+    std::fs::write(
+        snapshot_path(&fixture),
+        "node 0\n  name: \"a\"\nnode 1\n  name: \"b\"\n",
+    )
+    .unwrap();
+    tester.assert_snapshot(&fixture);
+    tester.assert_snapshots(&fixtures);
+    let _ = std::fs::remove_dir_all(&dir);
+}
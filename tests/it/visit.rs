@@ -0,0 +1,98 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use tree_sitter_graph::ast::visit;
+use tree_sitter_graph::ast::visit_mut;
+use tree_sitter_graph::ast::Call;
+use tree_sitter_graph::ast::Capture;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::ast::UnscopedVariable;
+use tree_sitter_graph::ast::Visitor;
+use tree_sitter_graph::ast::VisitorMut;
+use tree_sitter_graph::Identifier;
+
+const SOURCE: &str = r#"
+    (function_definition name: (identifier) @name parameters: (parameters (_)* @params)) {
+      node @name.def
+      let names = [(source-text p) for p in @params]
+      if (eq (source-text @name) "main") {
+        attr (@name.def) main = #true
+      }
+      scan (source-text @name) {
+        "^_" { print names }
+      }
+    }
+"#;
+
+#[derive(Default)]
+struct Collector<'ast> {
+    captures: Vec<&'ast str>,
+    calls: Vec<&'ast str>,
+    variables: Vec<&'ast str>,
+}
+
+impl<'ast> Visitor<'ast> for Collector<'ast> {
+    fn visit_capture(&mut self, capture: &'ast Capture) {
+        self.captures.push(capture.name.as_str());
+    }
+
+    fn visit_call(&mut self, call: &'ast Call) {
+        self.calls.push(call.function.as_str());
+        visit::walk_call(self, call);
+    }
+
+    fn visit_unscoped_variable(&mut self, variable: &'ast UnscopedVariable) {
+        self.variables.push(variable.name.as_str());
+    }
+}
+
+#[test]
+fn can_visit_files() {
+    let file = File::from_str(tree_sitter_python::language(), SOURCE).expect("parse to succeed");
+    let mut collector = Collector::default();
+    collector.visit_file(&file);
+    assert_eq!(
+        collector.captures,
+        vec!["name", "params", "name", "name", "name"]
+    );
+    assert_eq!(
+        collector.calls,
+        vec!["source-text", "eq", "source-text", "source-text"]
+    );
+    assert_eq!(collector.variables, vec!["names", "p", "p", "names"]);
+}
+
+struct Rename {
+    from: Identifier,
+    to: Identifier,
+}
+
+impl VisitorMut for Rename {
+    fn visit_call(&mut self, call: &mut Call) {
+        if call.function == self.from {
+            call.function = self.to.clone();
+        }
+        visit_mut::walk_call(self, call);
+    }
+}
+
+#[test]
+fn can_rewrite_files() {
+    let mut file =
+        File::from_str(tree_sitter_python::language(), SOURCE).expect("parse to succeed");
+    let mut rename = Rename {
+        from: Identifier::from("source-text"),
+        to: Identifier::from("node-text"),
+    };
+    rename.visit_file(&mut file);
+    let mut collector = Collector::default();
+    collector.visit_file(&file);
+    assert_eq!(
+        collector.calls,
+        vec!["node-text", "eq", "node-text", "node-text"]
+    );
+}
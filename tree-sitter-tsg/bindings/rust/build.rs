@@ -0,0 +1,18 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+fn main() {
+    // The parser is generated from grammar.js by `tree-sitter generate`.
+    let src_dir = std::path::Path::new("src");
+    let parser_path = src_dir.join("parser.c");
+    cc::Build::new()
+        .include(src_dir)
+        .file(&parser_path)
+        .warnings(false)
+        .compile("tree-sitter-tsg");
+    println!("cargo:rerun-if-changed={}", parser_path.to_str().unwrap());
+}
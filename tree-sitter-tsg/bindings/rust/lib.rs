@@ -0,0 +1,60 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! This crate provides a [tree-sitter][] grammar for the graph DSL of [tree-sitter-graph][], for
+//! editors and tools that work with `.tsg` files.
+//!
+//! ```
+//! let code = r#"
+//!     (module) @root {
+//!       node @root.node
+//!     }
+//! "#;
+//! let mut parser = tree_sitter::Parser::new();
+//! parser.set_language(&tree_sitter_tsg::language()).expect("Error loading TSG grammar");
+//! let tree = parser.parse(code, None).unwrap();
+//! assert!(!tree.root_node().has_error());
+//! ```
+//!
+//! [tree-sitter]: https://tree-sitter.github.io/
+//! [tree-sitter-graph]: https://docs.rs/tree-sitter-graph/
+
+use tree_sitter::Language;
+
+extern "C" {
+    fn tree_sitter_tsg() -> Language;
+}
+
+/// Returns the tree-sitter [`Language`][] for the graph DSL.
+pub fn language() -> Language {
+    unsafe { tree_sitter_tsg() }
+}
+
+/// The content of the [`node-types.json`][] file for this grammar.
+///
+/// [`node-types.json`]: https://tree-sitter.github.io/tree-sitter/using-parsers#static-node-types
+pub const NODE_TYPES: &str = include_str!("../../src/node-types.json");
+
+/// The syntax highlighting query for this grammar.
+pub const HIGHLIGHTS_QUERY: &str = include_str!("../../queries/highlights.scm");
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn can_load_grammar() {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&super::language())
+            .expect("Error loading TSG grammar");
+    }
+
+    #[test]
+    fn can_compile_highlights_query() {
+        tree_sitter::Query::new(&super::language(), super::HIGHLIGHTS_QUERY)
+            .expect("Error compiling highlights query");
+    }
+}
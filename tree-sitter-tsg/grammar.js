@@ -0,0 +1,448 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, tree-sitter authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+// A tree-sitter grammar for graph DSL files.  It follows the parser in src/parser.rs, but accepts
+// some files that the library rejects, such as stanzas with more than one query pattern, and it
+// does not know which keywords the host has registered as extension statements.
+
+const IDENTIFIER = /[\p{L}_][\p{L}\p{N}_-]*/;
+
+const commaSep1 = (rule) => seq(rule, repeat(seq(',', rule)));
+
+module.exports = grammar({
+  name: 'tsg',
+
+  extras: $ => [/\s/, $.comment],
+
+  word: $ => $.identifier,
+
+  // A list after `let` is either a pattern that destructures the value, or the scope of a scoped
+  // variable, which is only known at the `=` or `.` after it.
+  conflicts: $ => [
+    [$._expression, $._variable],
+    [$.list, $.list_pattern],
+  ],
+
+  rules: {
+    source_file: $ => repeat(choice(
+      $.attribute_declaration,
+      $.global_declaration,
+      $.inherit_declaration,
+      $.uses_declaration,
+      $.stanza,
+    )),
+
+    comment: _ => token(seq(';', /.*/)),
+
+    identifier: _ => IDENTIFIER,
+
+    // ---------------------------------------------------------------------------------------------
+    // Declarations
+
+    attribute_declaration: $ => seq(
+      'attribute',
+      field('name', $.identifier),
+      '=',
+      field('variable', $.identifier),
+      '=>',
+      commaSep1($.attribute),
+    ),
+
+    global_declaration: $ => seq(
+      'global',
+      field('name', $.identifier),
+      optional(field('quantifier', alias($._immediate_quantifier, $.quantifier))),
+      optional(seq('=', field('default', $.string))),
+    ),
+
+    inherit_declaration: $ => seq(
+      'inherit',
+      '.',
+      field('name', $.identifier),
+    ),
+
+    uses_declaration: $ => seq(
+      'uses',
+      commaSep1(field('namespace', $.string)),
+    ),
+
+    stanza: $ => seq(
+      optional(seq('stanza', field('name', $.identifier))),
+      field('query', $._pattern),
+      optional(seq('when', field('guard', $._expression))),
+      field('body', $.block),
+    ),
+
+    // ---------------------------------------------------------------------------------------------
+    // Query patterns, in the syntax of tree-sitter queries
+
+    _pattern: $ => choice(
+      $.named_node,
+      $.anonymous_node,
+      $.grouping,
+      $.alternation,
+    ),
+
+    _child: $ => choice(
+      $._pattern,
+      $.field_definition,
+      $.negated_field,
+      $.predicate,
+      $.anchor,
+    ),
+
+    named_node: $ => seq(
+      '(',
+      field('kind', choice($.identifier, alias('_', $.wildcard))),
+      optional(seq('/', field('subtype', $.identifier))),
+      repeat($._child),
+      ')',
+      optional(field('quantifier', $.quantifier)),
+      repeat(field('capture', $.query_capture)),
+    ),
+
+    anonymous_node: $ => prec.right(seq(
+      field('kind', choice($.string, alias('_', $.wildcard))),
+      optional(field('quantifier', $.quantifier)),
+      repeat(field('capture', $.query_capture)),
+    )),
+
+    // A grouping cannot start with a wildcard, since `(_ ...)` is a named node.
+    grouping: $ => seq(
+      '(',
+      choice($.named_node, alias($._string_node, $.anonymous_node), $.grouping, $.alternation),
+      repeat($._child),
+      ')',
+      optional(field('quantifier', $.quantifier)),
+      repeat(field('capture', $.query_capture)),
+    ),
+
+    alternation: $ => seq(
+      '[',
+      repeat1(choice($._pattern, $.field_definition, $.predicate)),
+      ']',
+      optional(field('quantifier', $.quantifier)),
+      repeat(field('capture', $.query_capture)),
+    ),
+
+    _string_node: $ => prec.right(seq(
+      field('kind', $.string),
+      optional(field('quantifier', $.quantifier)),
+      repeat(field('capture', $.query_capture)),
+    )),
+
+    field_definition: $ => seq(
+      field('name', $.identifier),
+      ':',
+      field('value', $._pattern),
+    ),
+
+    negated_field: $ => seq('!', field('name', $.identifier)),
+
+    predicate: $ => seq(
+      '(',
+      field('name', $.predicate_name),
+      repeat(field('argument', choice($.query_capture, $.string, $.identifier))),
+      ')',
+    ),
+
+    predicate_name: _ => token(seq('#', IDENTIFIER, optional(choice('?', '!')))),
+
+    anchor: _ => '.',
+
+    quantifier: _ => choice('*', '+', '?'),
+
+    _immediate_quantifier: _ => token.immediate(choice('*', '+', '?')),
+
+    query_capture: _ => token(seq('@', /[\p{L}_][\p{L}\p{N}_.-]*/)),
+
+    // ---------------------------------------------------------------------------------------------
+    // Statements
+
+    block: $ => seq('{', repeat($._statement), '}'),
+
+    _statement: $ => choice(
+      $.let_statement,
+      $.var_statement,
+      $.set_statement,
+      $.node_statement,
+      $.edge_statement,
+      $.attr_statement,
+      $.print_statement,
+      $.scan_statement,
+      $.match_statement,
+      $.if_statement,
+      $.for_statement,
+      $.extension_statement,
+    ),
+
+    let_statement: $ => seq(
+      'let',
+      field('variable', choice($._variable, $.list_pattern)),
+      '=',
+      field('value', $._expression),
+    ),
+
+    list_pattern: $ => seq(
+      '[',
+      optional(choice(
+        seq(
+          commaSep1(field('element', $._variable)),
+          optional(seq(',', optional(field('rest', $.rest_pattern)))),
+        ),
+        field('rest', $.rest_pattern),
+      )),
+      ']',
+    ),
+
+    rest_pattern: $ => seq('...', field('variable', $._variable)),
+
+    var_statement: $ => seq(
+      'var',
+      field('variable', $._variable),
+      '=',
+      field('value', $._expression),
+    ),
+
+    set_statement: $ => seq(
+      'set',
+      field('variable', $._variable),
+      '=',
+      field('value', $._expression),
+    ),
+
+    node_statement: $ => seq(
+      'node',
+      field('node', $._variable),
+      optional(seq(':', field('label', $._expression))),
+    ),
+
+    edge_statement: $ => seq(
+      'edge',
+      optional(seq(field('variable', $._variable), '=')),
+      field('source', $._expression),
+      choice('->', seq('-', field('kind', $.string), '->')),
+      field('sink', $._expression),
+    ),
+
+    attr_statement: $ => seq(
+      'attr',
+      '(',
+      choice(
+        field('node', $._expression),
+        seq(field('source', $._expression), '->', field('sink', $._expression)),
+      ),
+      ')',
+      commaSep1(choice($.attribute, $.negated_attribute)),
+    ),
+
+    attribute: $ => seq(
+      field('name', $.identifier),
+      optional(seq(choice('=', '+='), field('value', $._expression))),
+    ),
+
+    // A flag attribute whose value is `#false`
+    negated_attribute: $ => seq('!', field('name', $.identifier)),
+
+    print_statement: $ => seq(
+      'print',
+      optional(field('level', $.print_level)),
+      optional(seq(field('channel', $.identifier), ':')),
+      commaSep1(field('value', $._expression)),
+    ),
+
+    print_level: _ => choice('trace', 'debug', 'info', 'warn', 'error'),
+
+    scan_statement: $ => seq(
+      'scan',
+      field('value', $._expression),
+      '{',
+      repeat($.scan_arm),
+      '}',
+    ),
+
+    scan_arm: $ => seq(field('pattern', $.string), field('body', $.block)),
+
+    match_statement: $ => seq(
+      'match',
+      field('value', $._expression),
+      '{',
+      repeat($.match_arm),
+      '}',
+    ),
+
+    match_arm: $ => seq(
+      choice(
+        field('pattern', alias('_', $.wildcard)),
+        seq(
+          field('pattern', choice($.string, $.regex)),
+          repeat(seq('|', field('pattern', choice($.string, $.regex)))),
+        ),
+      ),
+      '=>',
+      field('body', $.block),
+      optional(','),
+    ),
+
+    regex: _ => token(seq('/', repeat(choice(/[^\/\\\n]/, /\\./)), '/')),
+
+    if_statement: $ => seq(
+      'if',
+      commaSep1(field('condition', $.condition)),
+      field('body', $.block),
+      repeat($.elif_clause),
+      optional($.else_clause),
+    ),
+
+    elif_clause: $ => seq(
+      'elif',
+      commaSep1(field('condition', $.condition)),
+      field('body', $.block),
+    ),
+
+    else_clause: $ => seq('else', field('body', $.block)),
+
+    condition: $ => choice(
+      seq('some', field('value', $._expression)),
+      seq('none', field('value', $._expression)),
+      field('value', $._expression),
+    ),
+
+    for_statement: $ => seq(
+      'for',
+      commaSep1(field('variable', $.identifier)),
+      'in',
+      field('value', choice($._expression, $.zip)),
+      field('body', $.block),
+    ),
+
+    zip: $ => seq(
+      'zip',
+      '(',
+      optional(seq(commaSep1(field('value', $._expression)), optional(','))),
+      ')',
+    ),
+
+    // The library only takes arguments from the line of the keyword, which a context-free grammar
+    // cannot express, so arguments are taken greedily instead.
+    extension_statement: $ => prec.right(seq(
+      field('keyword', $.identifier),
+      optional(commaSep1(field('argument', $._expression))),
+    )),
+
+    // ---------------------------------------------------------------------------------------------
+    // Expressions
+
+    _variable: $ => choice($.identifier, $.scoped_variable),
+
+    _expression: $ => choice(
+      $.identifier,
+      $.scoped_variable,
+      $.capture,
+      $.regex_capture,
+      $.string,
+      $.integer,
+      $.boolean,
+      $.null,
+      $.call,
+      $.new_node,
+      $.list,
+      $.list_comprehension,
+      $.set,
+      $.set_comprehension,
+    ),
+
+    // The default of a scoped variable extends as far as possible, so that in `@a.x ?? @b.y` it
+    // is `@b.y`.
+    scoped_variable: $ => choice(
+      prec.left(seq(
+        field('scope', $._expression),
+        '.',
+        field('name', $.identifier),
+      )),
+      prec.right(seq(
+        field('scope', $._expression),
+        '.',
+        field('name', $.identifier),
+        '??',
+        field('default', $._expression),
+      )),
+    ),
+
+    capture: _ => token(seq('@', IDENTIFIER)),
+
+    // A group of the match by index or by name, or the `$start` and `$end` offsets of the match
+    regex_capture: _ => /\$[A-Za-z0-9_]+/,
+
+    string: $ => seq(
+      '"',
+      repeat(choice(token.immediate(prec(1, /[^"\\]+/)), $.escape_sequence)),
+      token.immediate('"'),
+    ),
+
+    escape_sequence: _ => token.immediate(/\\./),
+
+    integer: _ => token(seq(
+      optional('-'),
+      choice(
+        /0x[0-9A-Fa-f]+(_+[0-9A-Fa-f]+)*/,
+        /0o[0-7]+(_+[0-7]+)*/,
+        /[0-9]+(_+[0-9]+)*/,
+      ),
+    )),
+
+    boolean: _ => choice('#true', '#false'),
+
+    null: _ => '#null',
+
+    new_node: _ => seq('(', 'new', 'node', ')'),
+
+    call: $ => seq(
+      '(',
+      field('function', $.function_name),
+      repeat(field('argument', $._expression)),
+      ')',
+    ),
+
+    // Function names that end with a `?` are predicates by convention.
+    function_name: _ => token(seq(IDENTIFIER, optional('?'))),
+
+    list: $ => seq(
+      '[',
+      optional(seq(commaSep1(field('element', $._expression)), optional(','))),
+      ']',
+    ),
+
+    list_comprehension: $ => seq(
+      '[',
+      field('element', $._expression),
+      'for',
+      field('variable', $.identifier),
+      'in',
+      field('value', $._expression),
+      optional(seq('if', field('condition', $._expression))),
+      ']',
+    ),
+
+    set: $ => seq(
+      '{',
+      optional(seq(commaSep1(field('element', $._expression)), optional(','))),
+      '}',
+    ),
+
+    set_comprehension: $ => seq(
+      '{',
+      field('element', $._expression),
+      'for',
+      field('variable', $.identifier),
+      'in',
+      field('value', $._expression),
+      optional(seq('if', field('condition', $._expression))),
+      '}',
+    ),
+  },
+});
@@ -0,0 +1,98 @@
+(comment) @comment
+
+[
+  "attribute"
+  "global"
+  "inherit"
+  "uses"
+  "stanza"
+  "let"
+  "var"
+  "set"
+  "node"
+  "edge"
+  "attr"
+  "print"
+  "scan"
+  "match"
+  "if"
+  "elif"
+  "else"
+  "some"
+  "none"
+  "for"
+  "in"
+  "zip"
+  "when"
+  "new"
+] @keyword
+
+(print_level) @keyword
+
+(extension_statement keyword: (identifier) @keyword)
+
+[
+  "="
+  "=>"
+  "->"
+  "-"
+  "+="
+  "??"
+  "|"
+  "..."
+  "!"
+  (quantifier)
+] @operator
+
+[
+  "("
+  ")"
+  "["
+  "]"
+  "{"
+  "}"
+] @punctuation.bracket
+
+[
+  ","
+  ":"
+  "."
+  "/"
+] @punctuation.delimiter
+
+(string) @string
+(escape_sequence) @string.escape
+(scan_arm pattern: (string) @string.regexp)
+(regex) @string.regexp
+
+(integer) @number
+[(boolean) (null)] @constant.builtin
+
+; Query patterns
+
+(named_node kind: (identifier) @type)
+(named_node subtype: (identifier) @type)
+(named_node kind: (wildcard) @type)
+(anonymous_node kind: (wildcard) @type)
+(match_arm pattern: (wildcard) @keyword)
+(anonymous_node kind: (string) @string.special)
+(field_definition name: (identifier) @property)
+(negated_field name: (identifier) @property)
+(predicate name: (predicate_name) @function.builtin)
+(query_capture) @label
+
+; Statements
+
+(call function: (function_name) @function)
+(capture) @label
+(regex_capture) @label
+(attribute name: (identifier) @property)
+(negated_attribute name: (identifier) @property)
+(edge_statement kind: (string) @type)
+(print_statement channel: (identifier) @label)
+(scoped_variable name: (identifier) @property)
+(attribute_declaration name: (identifier) @property)
+(global_declaration name: (identifier) @variable.parameter)
+(inherit_declaration name: (identifier) @property)
+(stanza name: (identifier) @label)
+(identifier) @variable